@@ -0,0 +1,14 @@
+//! Records read from the BPF perf and ring buffers.
+#![no_main]
+
+use cognitod::runtime::stream_listener::parse_event;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(event) = parse_event(data) {
+        // Derived fields must cope with any values the kernel side sends.
+        let _ = event.cpu_percent();
+        let _ = event.mem_percent();
+        let _ = event.exit_time();
+    }
+});
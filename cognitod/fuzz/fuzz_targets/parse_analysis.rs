@@ -0,0 +1,13 @@
+//! LLM responses are free text that should contain one JSON object.
+//!
+//! Stands in for a `schema::parse_and_validate` target: no such function
+//! exists in `cognitod/src/schema.rs`, and `parse_analysis` is where LLM
+//! output gets parsed.
+#![no_main]
+
+use cognitod::IncidentAnalyzer;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let _ = IncidentAnalyzer::parse_analysis(text);
+});
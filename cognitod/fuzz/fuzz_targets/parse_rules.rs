@@ -0,0 +1,20 @@
+//! Rules files are operator-written YAML or TOML.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // First byte picks the extension hint, the rest is the file.
+    let Some((&hint, text)) = data.split_first() else {
+        return;
+    };
+    let Ok(text) = std::str::from_utf8(text) else {
+        return;
+    };
+    let hint = match hint % 3 {
+        0 => Some("yaml"),
+        1 => Some("toml"),
+        _ => None,
+    };
+    let _ = cognitod::alerts::parse_rules(text, hint);
+});
@@ -1,20 +1,40 @@
 #[cfg(test)]
 use crate::ProcessEventWire;
+use crate::baselines::{self, Baselines};
+use crate::connection::{Connection, Direction};
+use crate::crashloop::{self, CrashLoopTracker, DaemonKey, ExitStatus};
+use crate::creds::CredChange;
+use crate::exec_compress::argv_hash;
+use crate::file_access::FileAccess;
+use crate::fingerprint::{Novelty, WorkloadFingerprint};
 use crate::handler::Handler;
-use crate::metrics::Metrics;
+use crate::k8s::K8sContext;
+use crate::kernel_counts::KernelCounts;
+use crate::kernel_load::KernelLoad;
+use crate::metrics::{Metrics, RuleCounters};
+use crate::rule_scope::{RawRuleMatch, RuleMatch, RuleScope, ScopeResolver, ScopeTarget};
+use crate::runtime::lineage::LineageCache;
+use crate::sessions::SessionResolver;
+use crate::signals::SignalSent;
+use crate::silences::Silences;
+use crate::subtree_cpu::SubtreeCpu;
 use crate::{ProcessEvent, types::SystemSnapshot};
-use anyhow::{Context, anyhow};
+use anyhow::{Context, anyhow, bail};
 use async_trait::async_trait;
+use linnix_ai_ebpf_common::{BlockOp, FileAccessOp, KernelLoadKind, fork_ancestors};
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::de::Deserializer;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use sysinfo::System;
-use tokio::sync::{Mutex, broadcast};
+use tokio::sync::{Mutex, MutexGuard, broadcast};
 use tokio::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Serialize, PartialEq, PartialOrd)]
@@ -35,6 +55,15 @@ impl Severity {
         }
     }
 
+    /// One level up; `High` stays `High`.
+    fn escalated(&self) -> Self {
+        match self {
+            Severity::Info => Severity::Low,
+            Severity::Low => Severity::Medium,
+            Severity::Medium | Severity::High => Severity::High,
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Severity::Info => "info",
@@ -55,15 +84,46 @@ impl<'de> Deserialize<'de> for Severity {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Alert {
     pub rule: String,
     pub severity: Severity,
     pub message: String,
     pub host: String,
+    /// Set on the event closing an earlier alert of the same rule (and
+    /// scope key) once its condition has cleared: seconds from that alert
+    /// to the condition's last match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_after_secs: Option<u64>,
+    /// Machine-readable context: the detector and its threshold, what it
+    /// measured, and the triggering process (`pid`, `comm`, `pod`, ...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Line of the alerts file; the timestamp lets retention prune by age.
+#[derive(Serialize)]
+struct AlertLine<'a> {
+    timestamp: u64,
+    #[serde(flatten)]
+    alert: &'a Alert,
 }
 
 impl Alert {
+    /// Whether this closes an earlier alert rather than raising one.
+    pub fn is_resolution(&self) -> bool {
+        self.resolved_after_secs.is_some()
+    }
+
+    /// Labels as `key=value` pairs for plain-text notifiers.
+    pub fn labels_line(&self) -> String {
+        self.labels
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     pub fn incident_context_line(&self) -> String {
         let mut message = self.message.replace(['\n', '\r'], " ");
         if message.len() > 256 {
@@ -89,13 +149,11 @@ pub enum Detector {
         threshold: u64,
         window_seconds: u64,
     },
-    #[allow(dead_code)]
+    /// Counts only execs whose comm or argv matches `regex` when one is
+    /// set.
     ExecRate {
-        #[allow(dead_code)]
-        regex: String,
-        #[allow(dead_code)]
+        regex: Option<Regex>,
         rate_per_min: u64,
-        #[allow(dead_code)]
         median_lifetime: u64,
     },
     ShortJobFlood {
@@ -140,6 +198,139 @@ pub enum Detector {
         threshold_pct: f32,
         duration: u64,
     },
+    /// Alert when a cgroup with a CPU quota is throttled in more than
+    /// `threshold_pct` of its CFS periods, sustained for `duration` seconds.
+    CfsThrottling {
+        threshold_pct: f32,
+        duration: u64,
+    },
+    /// Alert when CPUs are thermally throttled (or run below `max_freq_pct`
+    /// of base frequency) while host CPU is above `min_cpu_pct`, sustained
+    /// for `duration` seconds.
+    ThermalThrottling {
+        min_cpu_pct: f32,
+        max_freq_pct: f32,
+        duration: u64,
+    },
+    /// Alert when a process's GPU memory has grown without shrinking for
+    /// `duration` seconds at `min_growth_mib_per_min` or faster, once it
+    /// holds at least `min_used_mib`.
+    GpuMemoryLeak {
+        min_growth_mib_per_min: f32,
+        min_used_mib: u64,
+        duration: u64,
+    },
+    /// Alert when the same binary under the same parent exits within
+    /// `max_lifetime_secs` of exec at least `threshold` times in
+    /// `window_seconds`. `parent` filters on the parent's comm (`*` = any).
+    DaemonCrashLoop {
+        threshold: u64,
+        window_seconds: u64,
+        max_lifetime_secs: u64,
+        parent: String,
+    },
+    /// Alert on the first exec of a binary (or, with `match_argv`, of a
+    /// command line) not seen during the first `learning_secs`.
+    NewBinaryExecuted {
+        learning_secs: u64,
+        match_argv: bool,
+    },
+    /// Alert when a known binary is started by a parent it never had during
+    /// the first `learning_secs`.
+    UnusualParent {
+        learning_secs: u64,
+    },
+    /// Alert when the kernel OOM killer kills a process holding at least
+    /// `min_rss_mb` of RSS.
+    OomKill {
+        min_rss_mb: u64,
+    },
+    /// Alert when a process waits at least `threshold_ms` on a runqueue
+    /// before it runs.
+    SchedDelay {
+        threshold_ms: u64,
+    },
+    /// Alert when a process spends at least `threshold_ms` stalled in
+    /// direct reclaim within `window_seconds`.
+    DirectReclaim {
+        threshold_ms: u64,
+        window_seconds: u64,
+    },
+    /// Alert when one process sends signals to at least `threshold`
+    /// distinct processes within `window_seconds`.
+    KillSweep {
+        threshold: u64,
+        window_seconds: u64,
+    },
+    /// Alert when block I/O queued on one device (with `per_process`, by
+    /// one process) averages `threshold_mb_per_sec` over `window_seconds`.
+    BlockIoRate {
+        threshold_mb_per_sec: f32,
+        window_seconds: u64,
+        per_process: bool,
+    },
+    /// Alert when the latest baselines sample of `metric` is at least
+    /// `sigma` standard deviations above normal for the hour, and at least
+    /// `min_value`.
+    Baseline {
+        metric: baselines::Metric,
+        sigma: f32,
+        min_value: f64,
+    },
+    /// Alert when a process whose comm matches `comm` and that descends
+    /// from a process named `ancestor` (`*` = any) becomes root, joins the
+    /// root group or gains capabilities, unless its comm is in `ignore`.
+    PrivilegeEscalation {
+        comm: String,
+        ancestor: String,
+        ignore: Vec<String>,
+    },
+    /// Alert when a process whose comm isn't in `ignore` performs one of
+    /// `ops` on a path equal to or below one of `paths`.
+    SensitiveFileAccess {
+        paths: Vec<String>,
+        ops: Vec<FileAccessOp>,
+        ignore: Vec<String>,
+    },
+    /// Alert when a process whose comm isn't in `ignore` loads a kernel
+    /// module or BPF program of `kind` (`None` = both) whose name isn't in
+    /// `ignore_names`.
+    KernelLoad {
+        kind: Option<KernelLoadKind>,
+        ignore: Vec<String>,
+        ignore_names: Vec<String>,
+    },
+    /// Alert on each TCP connection in `direction` (`None` = both) by a
+    /// process whose comm and parent's comm match `comm` and `parent`
+    /// (`*` = any), to or on `port` (0 = any).
+    Connection {
+        direction: Option<Direction>,
+        comm: String,
+        parent: String,
+        port: u16,
+    },
+    /// Rhai script evaluated on each event of `event_types`, with counters
+    /// over the last `window_seconds`.
+    Script {
+        script: Arc<crate::script::Script>,
+        window_seconds: u64,
+        event_types: Vec<u32>,
+    },
+    /// Rhai expression evaluated on each event of `event_types`, with
+    /// counters over the last `window_seconds`; fires when it holds.
+    Expr {
+        expr: Arc<crate::expr::Expr>,
+        window_seconds: u64,
+        event_types: Vec<u32>,
+    },
+    /// Fires when all `conditions` (with `any`, one of them) have matched
+    /// within `within_seconds` of each other. Each condition is evaluated
+    /// as a rule of its own that records a match instead of alerting.
+    Composite {
+        any: bool,
+        within_seconds: u64,
+        conditions: Vec<Detector>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -147,83 +338,1153 @@ pub struct RuleConfig {
     pub name: String,
     pub severity: Severity,
     pub cooldown: u64,
+    /// Seconds without a match before a fired alert is resolved; 0 never
+    /// resolves.
+    pub resolve_after: u64,
     pub detector: Detector,
+    pub scope: Option<RuleScope>,
+    /// Process selectors an event must meet to count for the rule.
+    pub matcher: Option<RuleMatch>,
+    pub escalate: Option<Escalation>,
+    pub mode: RuleMode,
+    /// Notification channels the rule's alerts go to; `None` is all.
+    pub notify: Option<Vec<String>>,
+    /// Trigger fields that tell the rule's alerts apart; each combination
+    /// has its own cooldown.
+    pub fingerprint: Vec<FingerprintField>,
+}
+
+/// Field of the triggering process in a rule's `fingerprint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FingerprintField {
+    Pid,
+    Ppid,
+    Uid,
+    Comm,
+    Container,
+    /// `namespace/pod`.
+    Pod,
+}
+
+/// Whether a rule alerts (`enforce`) or only counts what it would have
+/// sent (`shadow`), for trialling a rule or threshold.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMode {
+    #[default]
+    Enforce,
+    Shadow,
+}
+
+/// `escalate:` block of a rule: raise the severity of a rule that keeps
+/// firing.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Escalation {
+    /// Alerts (per scope key) tolerated within `within_minutes`; the next
+    /// ones are sent one severity level up.
+    pub fires: u32,
+    pub within_minutes: u64,
+}
+
+impl RuleConfig {
+    /// Whether the rule counts only its own events rather than reading
+    /// the shared host-wide queues.
+    fn keeps_own_counters(&self) -> bool {
+        self.scope.is_some() || self.matcher.is_some()
+    }
+
+    /// The key the rule counts `target`'s events under, `""` unless it
+    /// keeps counters per scope key; `None` when the rule doesn't select
+    /// the process.
+    fn scope_key(&self, target: &ScopeTarget<'_>) -> Option<String> {
+        if let Some(matcher) = &self.matcher
+            && !matcher.matches(target)
+        {
+            return None;
+        }
+        match &self.scope {
+            Some(scope) => scope.key(target),
+            None => Some(String::new()),
+        }
+    }
+}
+
+impl Detector {
+    /// The `detector:` value used in rule files.
+    fn kind(&self) -> &'static str {
+        match self {
+            Detector::ForksPerSec { .. } => "forks_per_sec",
+            Detector::ForkBurst { .. } => "fork_burst",
+            Detector::ExecRate { .. } => "exec_rate",
+            Detector::ShortJobFlood { .. } => "short_job_flood",
+            Detector::RunawayTree { .. } => "runaway_tree",
+            Detector::SubtreeCpuPct { .. } => "subtree_cpu_pct",
+            Detector::SubtreeRssMb { .. } => "subtree_rss_mb",
+            Detector::ZombieCount { .. } => "zombie_count",
+            Detector::SystemPsiCpu { .. } => "system_psi_cpu",
+            Detector::SystemPsiMemory { .. } => "system_psi_memory",
+            Detector::SystemPsiIo { .. } => "system_psi_io",
+            Detector::CfsThrottling { .. } => "cfs_throttling",
+            Detector::ThermalThrottling { .. } => "thermal_throttling",
+            Detector::GpuMemoryLeak { .. } => "gpu_memory_leak",
+            Detector::DaemonCrashLoop { .. } => "daemon_crash_loop",
+            Detector::NewBinaryExecuted { .. } => "new_binary_executed",
+            Detector::UnusualParent { .. } => "unusual_parent",
+            Detector::OomKill { .. } => "oom_kill",
+            Detector::SchedDelay { .. } => "sched_delay",
+            Detector::DirectReclaim { .. } => "direct_reclaim",
+            Detector::KillSweep { .. } => "kill_sweep",
+            Detector::BlockIoRate { .. } => "block_io_rate",
+            Detector::Baseline { .. } => "baseline",
+            Detector::PrivilegeEscalation { .. } => "privilege_escalation",
+            Detector::SensitiveFileAccess { .. } => "sensitive_file_access",
+            Detector::KernelLoad { .. } => "kernel_load",
+            Detector::Connection { .. } => "connection",
+            Detector::Script { .. } => "script",
+            Detector::Expr { .. } => "expr",
+            Detector::Composite { .. } => "composite",
+        }
+    }
+
+    /// Whether the detector keeps counters a scope's `per` can split.
+    fn counts_per_scope(&self) -> bool {
+        matches!(
+            self,
+            Detector::ForksPerSec { .. }
+                | Detector::ForkBurst { .. }
+                | Detector::ExecRate { .. }
+                | Detector::SubtreeCpuPct { .. }
+                | Detector::SubtreeRssMb { .. }
+        )
+    }
+
+    /// Detectors judging host-wide state rather than the process behind
+    /// an event, which a scope can't select.
+    fn is_host_wide(&self) -> bool {
+        matches!(
+            self,
+            Detector::ShortJobFlood { .. }
+                | Detector::ZombieCount { .. }
+                | Detector::SystemPsiCpu { .. }
+                | Detector::SystemPsiMemory { .. }
+                | Detector::SystemPsiIo { .. }
+                | Detector::CfsThrottling { .. }
+                | Detector::ThermalThrottling { .. }
+                | Detector::GpuMemoryLeak { .. }
+                | Detector::Baseline { .. }
+        )
+    }
+
+    /// Whether events are evaluated against the detector. The rest fire
+    /// from snapshots, or through a composite rule's conditions.
+    fn runs_on_events(&self) -> bool {
+        !matches!(
+            self,
+            Detector::SystemPsiCpu { .. }
+                | Detector::SystemPsiMemory { .. }
+                | Detector::SystemPsiIo { .. }
+                | Detector::CfsThrottling { .. }
+                | Detector::ThermalThrottling { .. }
+                | Detector::GpuMemoryLeak { .. }
+                | Detector::Baseline { .. }
+                | Detector::Composite { .. }
+        )
+    }
+
+    /// How long the detector can go without matching while its condition
+    /// still holds: its window or duration. `None` for detectors reporting
+    /// one-off events, which have no condition to clear.
+    fn hold_secs(&self) -> Option<u64> {
+        match self {
+            Detector::ForksPerSec { duration, .. }
+            | Detector::SubtreeCpuPct { duration, .. }
+            | Detector::SubtreeRssMb { duration, .. }
+            | Detector::ZombieCount { duration, .. }
+            | Detector::SystemPsiCpu { duration, .. }
+            | Detector::SystemPsiMemory { duration, .. }
+            | Detector::SystemPsiIo { duration, .. }
+            | Detector::CfsThrottling { duration, .. }
+            | Detector::ThermalThrottling { duration, .. }
+            | Detector::GpuMemoryLeak { duration, .. } => Some(*duration),
+            Detector::ForkBurst { window_seconds, .. }
+            | Detector::ShortJobFlood { window_seconds, .. }
+            | Detector::RunawayTree { window_seconds, .. }
+            | Detector::DaemonCrashLoop { window_seconds, .. }
+            | Detector::DirectReclaim { window_seconds, .. }
+            | Detector::KillSweep { window_seconds, .. }
+            | Detector::BlockIoRate { window_seconds, .. }
+            | Detector::Script { window_seconds, .. }
+            | Detector::Expr { window_seconds, .. } => Some(*window_seconds),
+            // A per-minute rate.
+            Detector::ExecRate { .. } => Some(60),
+            Detector::Composite { within_seconds, .. } => Some(*within_seconds),
+            // Judged one baselines sample at a time.
+            Detector::Baseline { .. } => Some(0),
+            Detector::NewBinaryExecuted { .. }
+            | Detector::UnusualParent { .. }
+            | Detector::OomKill { .. }
+            | Detector::SchedDelay { .. }
+            | Detector::PrivilegeEscalation { .. }
+            | Detector::SensitiveFileAccess { .. }
+            | Detector::KernelLoad { .. }
+            | Detector::Connection { .. } => None,
+        }
+    }
+
+    /// Rule-file field holding this detector's threshold.
+    fn threshold_field(&self) -> &'static str {
+        match self {
+            Detector::ExecRate { .. } => "rate_per_min",
+            Detector::SystemPsiCpu { .. }
+            | Detector::SystemPsiMemory { .. }
+            | Detector::SystemPsiIo { .. }
+            | Detector::CfsThrottling { .. } => "threshold_pct",
+            Detector::ThermalThrottling { .. } => "min_cpu_pct",
+            Detector::GpuMemoryLeak { .. } => "min_growth_mib_per_min",
+            Detector::OomKill { .. } => "min_rss_mb",
+            Detector::SchedDelay { .. } | Detector::DirectReclaim { .. } => "threshold_ms",
+            Detector::BlockIoRate { .. } => "threshold_mb_per_sec",
+            Detector::Baseline { .. } => "sigma",
+            _ => "threshold",
+        }
+    }
+
+    /// `None` for detectors without a tunable threshold (scripts and the
+    /// workload fingerprint).
+    fn threshold(&self) -> Option<serde_json::Number> {
+        let value = match self {
+            Detector::ForksPerSec { threshold, .. }
+            | Detector::ForkBurst { threshold, .. }
+            | Detector::ShortJobFlood { threshold, .. }
+            | Detector::RunawayTree { threshold, .. }
+            | Detector::SubtreeRssMb { threshold, .. }
+            | Detector::ZombieCount { threshold, .. }
+            | Detector::DaemonCrashLoop { threshold, .. }
+            | Detector::KillSweep { threshold, .. }
+            | Detector::OomKill {
+                min_rss_mb: threshold,
+            }
+            | Detector::SchedDelay {
+                threshold_ms: threshold,
+            }
+            | Detector::DirectReclaim {
+                threshold_ms: threshold,
+                ..
+            }
+            | Detector::ExecRate {
+                rate_per_min: threshold,
+                ..
+            } => serde_json::Number::from(*threshold),
+            Detector::SubtreeCpuPct { threshold, .. }
+            | Detector::SystemPsiCpu {
+                threshold_pct: threshold,
+                ..
+            }
+            | Detector::SystemPsiMemory {
+                threshold_pct: threshold,
+                ..
+            }
+            | Detector::SystemPsiIo {
+                threshold_pct: threshold,
+                ..
+            }
+            | Detector::CfsThrottling {
+                threshold_pct: threshold,
+                ..
+            }
+            | Detector::ThermalThrottling {
+                min_cpu_pct: threshold,
+                ..
+            }
+            | Detector::GpuMemoryLeak {
+                min_growth_mib_per_min: threshold,
+                ..
+            }
+            | Detector::BlockIoRate {
+                threshold_mb_per_sec: threshold,
+                ..
+            }
+            | Detector::Baseline {
+                sigma: threshold, ..
+            } => {
+                // Go through the shortest decimal form so 0.1f32 reads back
+                // as 0.1 rather than 0.10000000149.
+                let value: f64 = threshold.to_string().parse().unwrap_or(0.0);
+                serde_json::Number::from_f64(value).unwrap_or_else(|| 0.into())
+            }
+            Detector::NewBinaryExecuted { .. }
+            | Detector::UnusualParent { .. }
+            | Detector::PrivilegeEscalation { .. }
+            | Detector::SensitiveFileAccess { .. }
+            | Detector::KernelLoad { .. }
+            | Detector::Connection { .. }
+            | Detector::Script { .. }
+            | Detector::Expr { .. }
+            | Detector::Composite { .. } => return None,
+        };
+        Some(value)
+    }
+
+    fn with_threshold(&self, value: f64) -> Result<Detector, String> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(format!(
+                "threshold must be a non-negative number, got {value}"
+            ));
+        }
+        let mut detector = self.clone();
+        match &mut detector {
+            Detector::ForksPerSec { threshold, .. }
+            | Detector::ForkBurst { threshold, .. }
+            | Detector::ShortJobFlood { threshold, .. }
+            | Detector::RunawayTree { threshold, .. }
+            | Detector::SubtreeRssMb { threshold, .. }
+            | Detector::ZombieCount { threshold, .. }
+            | Detector::DaemonCrashLoop { threshold, .. }
+            | Detector::KillSweep { threshold, .. }
+            | Detector::OomKill {
+                min_rss_mb: threshold,
+            }
+            | Detector::SchedDelay {
+                threshold_ms: threshold,
+            }
+            | Detector::DirectReclaim {
+                threshold_ms: threshold,
+                ..
+            }
+            | Detector::ExecRate {
+                rate_per_min: threshold,
+                ..
+            } => {
+                if value.fract() != 0.0 || value > u64::MAX as f64 {
+                    return Err(format!(
+                        "{} threshold must be a whole number, got {value}",
+                        self.kind()
+                    ));
+                }
+                *threshold = value as u64;
+            }
+            Detector::SubtreeCpuPct { threshold, .. }
+            | Detector::GpuMemoryLeak {
+                min_growth_mib_per_min: threshold,
+                ..
+            }
+            | Detector::BlockIoRate {
+                threshold_mb_per_sec: threshold,
+                ..
+            }
+            | Detector::Baseline {
+                sigma: threshold, ..
+            } => *threshold = value as f32,
+            Detector::SystemPsiCpu { threshold_pct, .. }
+            | Detector::SystemPsiMemory { threshold_pct, .. }
+            | Detector::SystemPsiIo { threshold_pct, .. }
+            | Detector::CfsThrottling { threshold_pct, .. }
+            | Detector::ThermalThrottling {
+                min_cpu_pct: threshold_pct,
+                ..
+            } => {
+                if value > 100.0 {
+                    return Err(format!(
+                        "{} {} must be at most 100, got {value}",
+                        self.kind(),
+                        self.threshold_field()
+                    ));
+                }
+                *threshold_pct = value as f32;
+            }
+            Detector::NewBinaryExecuted { .. }
+            | Detector::UnusualParent { .. }
+            | Detector::PrivilegeEscalation { .. }
+            | Detector::SensitiveFileAccess { .. }
+            | Detector::KernelLoad { .. }
+            | Detector::Connection { .. }
+            | Detector::Script { .. }
+            | Detector::Expr { .. }
+            | Detector::Composite { .. } => {
+                return Err(format!("{} detectors have no threshold", self.kind()));
+            }
+        }
+        Ok(detector)
+    }
+}
+
+/// Runtime change to a loaded rule (`PATCH /rules/{name}`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulePatch {
+    /// New value for the detector's threshold field (`threshold`,
+    /// `threshold_pct` or `rate_per_min`).
+    #[serde(default)]
+    pub threshold: Option<f64>,
+    #[serde(default)]
+    pub cooldown: Option<u64>,
+    /// Move the rule in or out of shadow mode.
+    #[serde(default)]
+    pub mode: Option<RuleMode>,
+    /// Also write the change back to the rules file, which must be TOML.
+    #[serde(default)]
+    pub persist: bool,
+}
+
+/// Tunable settings of a loaded rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleView {
+    pub name: String,
+    pub detector: &'static str,
+    pub severity: &'static str,
+    pub threshold_field: &'static str,
+    /// `null` for script detectors.
+    pub threshold: Option<serde_json::Number>,
+    pub cooldown: u64,
+    pub mode: RuleMode,
+}
+
+impl From<&RuleConfig> for RuleView {
+    fn from(cfg: &RuleConfig) -> Self {
+        Self {
+            name: cfg.name.clone(),
+            detector: cfg.detector.kind(),
+            severity: cfg.severity.as_str(),
+            threshold_field: cfg.detector.threshold_field(),
+            threshold: cfg.detector.threshold(),
+            cooldown: cfg.cooldown,
+            mode: cfg.mode,
+        }
+    }
+}
+
+/// What [`RuleEngine::reload`] changed, by rule name.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RulesReload {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl std::fmt::Display for RulesReload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = |names: &[String]| match names {
+            [] => "none".to_string(),
+            names => names.join(", "),
+        };
+        write!(
+            f,
+            "added: {}; changed: {}; removed: {}; {} unchanged",
+            names(&self.added),
+            names(&self.changed),
+            names(&self.removed),
+            self.unchanged
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum RulePatchError {
+    NotFound,
+    Invalid(String),
+    Persist(anyhow::Error),
+}
+
+impl std::fmt::Display for RulePatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RulePatchError::NotFound => write!(f, "no such rule"),
+            RulePatchError::Invalid(msg) => write!(f, "{msg}"),
+            RulePatchError::Persist(err) => write!(f, "failed to update rules file: {err:#}"),
+        }
+    }
+}
+
+/// What the detector windows hold right now (`GET /stats/windows`), for
+/// seeing how close each rule is to firing while tuning thresholds.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowStats {
+    /// Events held in the shared windows.
+    pub forks: usize,
+    pub execs: usize,
+    pub completions: usize,
+    /// Parents with forks in the runaway-tree window.
+    pub tracked_parents: usize,
+    /// Rules with a windowed or sustained condition; script, expr and
+    /// zombie rules are not listed.
+    pub rules: Vec<RuleWindow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleWindow {
+    pub rule: String,
+    pub detector: &'static str,
+    /// What `current` counts: `forks`, `execs`, `parent_forks`,
+    /// `short_jobs`, `quick_exits`, `stall_ms`, `signalled`,
+    /// `breach_seconds` or `conditions`.
+    pub metric: &'static str,
+    pub current: f64,
+    /// Value of `current` at which the rule fires.
+    pub threshold: f64,
+    /// `current / threshold`; at 1.0 the next evaluation fires.
+    pub ratio: f64,
+    pub window_seconds: u64,
+    /// Parent PID, binary, cgroup or scope key closest to firing, for
+    /// detectors that count per key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Seconds until the rule can fire again.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown_remaining_secs: Option<u64>,
+}
+
+impl RuleWindow {
+    fn new(rule: &RuleConfig, metric: &'static str, current: f64, threshold: f64) -> Self {
+        Self {
+            rule: rule.name.clone(),
+            detector: rule.detector.kind(),
+            metric,
+            current,
+            threshold,
+            ratio: if threshold > 0.0 {
+                current / threshold
+            } else {
+                1.0
+            },
+            window_seconds: 0,
+            key: None,
+            cooldown_remaining_secs: None,
+        }
+    }
+
+    fn window(mut self, seconds: u64) -> Self {
+        self.window_seconds = seconds;
+        self
+    }
+
+    fn key(mut self, key: Option<String>) -> Self {
+        self.key = key;
+        self
+    }
 }
 
+#[derive(Clone)]
 struct Rule {
     cfg: RuleConfig,
+    stats: Arc<RuleCounters>,
+    /// For a condition of a composite rule: that rule's name and the
+    /// condition's index.
+    condition_of: Option<(String, usize)>,
+    /// Shared by the copies of the rule in later snapshots, so tuning it
+    /// keeps its counters and cooldowns.
+    state: Arc<Mutex<RuleShard>>,
+}
+
+impl Rule {
+    /// The rule's counters and cooldowns. Never held across an await.
+    async fn state(&self) -> MutexGuard<'_, RuleShard> {
+        self.state.lock().await
+    }
+}
+
+/// How long the shared event windows are kept, and which optional
+/// trackers run, for a set of rules.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct WindowSpans {
+    fork_secs: u64,
+    exec_secs: u64,
+    completion_secs: u64,
+    runaway_secs: u64,
+    crash_loop_secs: u64,
+    fingerprint: bool,
+    subtree_cpu: bool,
+}
+
+impl WindowSpans {
+    fn for_rules(rules: &[Rule]) -> Self {
+        let mut fork_window_secs = 0u64;
+        let mut exec_window_secs = 60u64;
+        let mut completion_window_secs = 60u64;
+        let mut runaway_window_secs = 0u64;
+        let mut crash_loop_window_secs = 0u64;
+        let mut fingerprint = false;
+        let mut subtree_cpu = false;
+
+        for rule in rules {
+            match &rule.cfg.detector {
+                Detector::DaemonCrashLoop { window_seconds, .. } => {
+                    crash_loop_window_secs = crash_loop_window_secs.max(*window_seconds);
+                }
+                Detector::NewBinaryExecuted { .. } | Detector::UnusualParent { .. } => {
+                    fingerprint = true;
+                }
+                Detector::SubtreeCpuPct { .. } => {
+                    subtree_cpu = true;
+                }
+                Detector::ForksPerSec { duration, .. } => {
+                    fork_window_secs = fork_window_secs.max(*duration);
+                }
+                Detector::ForkBurst { window_seconds, .. } => {
+                    fork_window_secs = fork_window_secs.max(*window_seconds);
+                }
+                Detector::RunawayTree { window_seconds, .. } => {
+                    fork_window_secs = fork_window_secs.max(*window_seconds);
+                    runaway_window_secs = runaway_window_secs.max(*window_seconds);
+                }
+                Detector::ShortJobFlood { window_seconds, .. } => {
+                    completion_window_secs = completion_window_secs.max(*window_seconds);
+                }
+                Detector::ExecRate { .. } => {
+                    completion_window_secs = completion_window_secs.max(60);
+                }
+                Detector::Script { window_seconds, .. } | Detector::Expr { window_seconds, .. } => {
+                    fork_window_secs = fork_window_secs.max(*window_seconds);
+                    runaway_window_secs = runaway_window_secs.max(*window_seconds);
+                    exec_window_secs = exec_window_secs.max(*window_seconds);
+                    completion_window_secs = completion_window_secs.max(*window_seconds);
+                }
+                _ => {}
+            }
+        }
+
+        if fork_window_secs == 0 {
+            fork_window_secs = 5;
+        }
+        if runaway_window_secs == 0 {
+            runaway_window_secs = fork_window_secs;
+        }
+        if completion_window_secs == 0 {
+            completion_window_secs = 60;
+        }
+
+        Self {
+            fork_secs: fork_window_secs,
+            exec_secs: exec_window_secs,
+            completion_secs: completion_window_secs,
+            runaway_secs: runaway_window_secs,
+            crash_loop_secs: crash_loop_window_secs,
+            fingerprint,
+            subtree_cpu,
+        }
+    }
+}
+
+/// Rules ready to evaluate: each rule, followed by its conditions if it is
+/// composite. Conditions are named `rule[index]` and count towards the
+/// composite rule's stats.
+fn rules_from(cfgs: Vec<RuleConfig>, metrics: &Metrics) -> Vec<Rule> {
+    let mut rules = Vec::with_capacity(cfgs.len());
+    for cfg in cfgs {
+        let stats = metrics.register_rule(&cfg.name);
+        let conditions = match &cfg.detector {
+            Detector::Composite { conditions, .. } => conditions.clone(),
+            _ => Vec::new(),
+        };
+        let (name, severity) = (cfg.name.clone(), cfg.severity.clone());
+        rules.push(Rule {
+            cfg,
+            stats: Arc::clone(&stats),
+            condition_of: None,
+            state: Arc::default(),
+        });
+        for (index, detector) in conditions.into_iter().enumerate() {
+            rules.push(Rule {
+                cfg: RuleConfig {
+                    name: format!("{name}[{index}]"),
+                    severity: severity.clone(),
+                    cooldown: 0,
+                    resolve_after: 0,
+                    detector,
+                    scope: None,
+                    matcher: None,
+                    escalate: None,
+                    notify: None,
+                    fingerprint: Vec::new(),
+                    mode: RuleMode::Enforce,
+                },
+                stats: Arc::clone(&stats),
+                condition_of: Some((name.clone(), index)),
+                state: Arc::default(),
+            });
+        }
+    }
+    rules
 }
 
 const DEFAULT_COOLDOWN_SECS: u64 = 60;
+const DEFAULT_RESOLVE_AFTER_SECS: u64 = 60;
 const DEFAULT_SHORT_JOB_DURATION_MS: u64 = 1000;
+const DEFAULT_SCRIPT_WINDOW_SECS: u64 = 60;
 
-#[derive(Debug, Deserialize)]
+/// One entry of the rules file.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(title = "Rule")]
 struct RawRule {
+    /// Unique rule name; used as the alert's `rule` field.
     name: String,
+    /// Alert severity. Unknown values are treated as `info`.
     #[serde(default)]
+    #[schemars(extend("enum" = ["info", "low", "medium", "high"], "default" = "info"))]
     severity: Option<String>,
+    /// Seconds before the same rule can fire again on this host.
     #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_COOLDOWN_SECS))]
     cooldown: Option<u64>,
+    /// Seconds without a match (at least twice the detector's window or
+    /// duration) before a fired alert is resolved; 0 never resolves.
+    #[serde(default)]
+    #[schemars(extend("default" = DEFAULT_RESOLVE_AFTER_SECS))]
+    resolve_after: Option<u64>,
+    /// Only evaluate the rule on matching processes, optionally with
+    /// separate counters per cgroup, pod, namespace or UID.
+    #[serde(default)]
+    scope: Option<RuleScope>,
+    /// Only count events of processes with a matching name, user or
+    /// parent.
+    #[serde(default, rename = "match")]
+    matcher: Option<RawRuleMatch>,
+    /// Raise the severity one level when the rule fires more than `fires`
+    /// times within `within_minutes`.
+    #[serde(default)]
+    escalate: Option<Escalation>,
+    /// `shadow` evaluates the rule and counts its matches in
+    /// `/rules/stats` without sending alerts.
+    #[serde(default)]
+    mode: RuleMode,
+    /// Notification channels to send the rule's alerts to (`slack`,
+    /// `apprise`); all of them when omitted, none when empty.
+    #[serde(default)]
+    notify: Option<Vec<String>>,
+    /// Fields of the triggering process (`pid`, `ppid`, `uid`, `comm`,
+    /// `container`, `pod`) that tell alerts apart; each combination has its
+    /// own cooldown. Alerts deduplicate by rule only when omitted.
+    #[serde(default)]
+    fingerprint: Vec<FingerprintField>,
     #[serde(flatten)]
     detector: RawDetector,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 #[serde(tag = "detector", rename_all = "snake_case")]
 enum RawDetector {
-    ForkBurst {
-        threshold: u64,
-        window_seconds: u64,
-    },
+    /// At least `threshold` forks system-wide within `window_seconds`.
+    ForkBurst { threshold: u64, window_seconds: u64 },
+    /// At least `threshold` processes that exited within
+    /// `max_exec_duration_ms` of exec, over `window_seconds`.
     ShortJobFlood {
         threshold: u64,
         window_seconds: u64,
         #[serde(default = "default_short_job_duration_ms")]
         max_exec_duration_ms: u64,
     },
-    RunawayTree {
-        threshold: u64,
-        window_seconds: u64,
-    },
-    ForksPerSec {
-        threshold: u64,
-        duration: u64,
-    },
+    /// A single parent forking at least `threshold` children within
+    /// `window_seconds`.
+    RunawayTree { threshold: u64, window_seconds: u64 },
+    /// Fork rate above `threshold` per second sustained for `duration` seconds.
+    ForksPerSec { threshold: u64, duration: u64 },
+    /// More than `rate_per_min` execs per minute with a median lifetime of at
+    /// most `median_lifetime` seconds.
     ExecRate {
+        /// Only count execs whose comm, or argv joined with spaces, matches;
+        /// empty counts every exec.
+        #[serde(default)]
         regex: String,
         rate_per_min: u64,
         median_lifetime: u64,
     },
-    SubtreeCpuPct {
-        threshold: f32,
+    /// A process above `threshold` percent CPU for `duration` seconds.
+    SubtreeCpuPct { threshold: f32, duration: u64 },
+    /// A process above `threshold` MB RSS for `duration` seconds.
+    SubtreeRssMb { threshold: u64, duration: u64 },
+    /// Accepted but not evaluated yet.
+    ZombieCount { threshold: u64, duration: u64 },
+    /// System CPU PSI (some avg10) above `threshold_pct` for `duration` seconds.
+    SystemPsiCpu { threshold_pct: f32, duration: u64 },
+    /// System memory PSI (full avg10) above `threshold_pct` for `duration`
+    /// seconds.
+    SystemPsiMemory { threshold_pct: f32, duration: u64 },
+    /// System IO PSI (full avg10) above `threshold_pct` for `duration` seconds.
+    SystemPsiIo { threshold_pct: f32, duration: u64 },
+    /// A cgroup with a CPU quota throttled in more than `threshold_pct`
+    /// percent of its CFS periods for `duration` seconds.
+    CfsThrottling { threshold_pct: f32, duration: u64 },
+    /// CPU thermal throttle events, or frequency below `max_freq_pct` of
+    /// base, while host CPU is above `min_cpu_pct`, for `duration` seconds.
+    ThermalThrottling {
+        #[serde(default = "default_thermal_min_cpu_pct")]
+        min_cpu_pct: f32,
+        #[serde(default = "default_thermal_max_freq_pct")]
+        max_freq_pct: f32,
         duration: u64,
     },
-    SubtreeRssMb {
-        threshold: u64,
+    /// A process's GPU memory growing without shrinking for `duration`
+    /// seconds at `min_growth_mib_per_min` or faster, once it holds at least
+    /// `min_used_mib`: a leaking training or inference job.
+    GpuMemoryLeak {
+        min_growth_mib_per_min: f32,
+        #[serde(default = "default_gpu_leak_min_used_mib")]
+        min_used_mib: u64,
         duration: u64,
     },
-    ZombieCount {
+    /// The same binary under the same parent exiting within
+    /// `max_lifetime_secs` of exec at least `threshold` times in
+    /// `window_seconds`: a host daemon crash-looping.
+    DaemonCrashLoop {
         threshold: u64,
-        duration: u64,
+        window_seconds: u64,
+        #[serde(default = "default_crash_loop_max_lifetime_secs")]
+        max_lifetime_secs: u64,
+        /// Parent comm to watch; `*` matches any parent.
+        #[serde(default = "default_crash_loop_parent")]
+        parent: String,
     },
-    SystemPsiCpu {
-        threshold_pct: f32,
-        duration: u64,
+    /// An exec of a binary never seen during the first `learning_secs` of
+    /// recording execs.
+    NewBinaryExecuted {
+        #[serde(default = "default_fingerprint_learning_secs")]
+        learning_secs: u64,
+        /// Also alert on new command lines of known binaries.
+        #[serde(default)]
+        match_argv: bool,
     },
-    SystemPsiMemory {
-        threshold_pct: f32,
-        duration: u64,
+    /// A known binary started by a parent (by comm) it was never started by
+    /// during the first `learning_secs`, e.g. `sh` under `nginx`.
+    UnusualParent {
+        #[serde(default = "default_fingerprint_learning_secs")]
+        learning_secs: u64,
     },
-    SystemPsiIo {
-        threshold_pct: f32,
-        duration: u64,
+    /// The kernel OOM killer killing a process that held at least
+    /// `min_rss_mb` of RSS; 0 alerts on every kill.
+    OomKill {
+        #[serde(default)]
+        min_rss_mb: u64,
     },
-}
-
-fn default_short_job_duration_ms() -> u64 {
-    DEFAULT_SHORT_JOB_DURATION_MS
-}
-
+    /// A SchedLatency event of at least `threshold_ms`: a process kept
+    /// off the CPU that long after it became runnable. The probe only
+    /// reports delays above `probes.sched_latency_threshold_us`.
+    SchedDelay {
+        #[serde(default = "default_sched_delay_threshold_ms")]
+        threshold_ms: u64,
+    },
+    /// DirectReclaim events of one process adding up to `threshold_ms`
+    /// within `window_seconds`: allocations stalling on reclaim, the
+    /// early sign of thrashing. The probe only reports stalls above
+    /// `probes.direct_reclaim_min_us`.
+    DirectReclaim {
+        #[serde(default = "default_direct_reclaim_threshold_ms")]
+        threshold_ms: u64,
+        #[serde(default = "default_direct_reclaim_window_seconds")]
+        window_seconds: u64,
+    },
+    /// Signal events from one process reaching `threshold` distinct
+    /// targets within `window_seconds`, e.g. a `pkill` or a runaway
+    /// supervisor killing everything in sight. Only the signals in
+    /// `probes.signals` are seen; signals raised by the kernel and sent to
+    /// the sender itself don't count.
+    KillSweep {
+        #[serde(default = "default_kill_sweep_threshold")]
+        threshold: u64,
+        #[serde(default = "default_kill_sweep_window_seconds")]
+        window_seconds: u64,
+    },
+    /// BlockIo bytes queued on one device, or by one process, averaging
+    /// `threshold_mb_per_sec` (MiB/s) over `window_seconds`: a disk being
+    /// saturated, and who is doing it. Bytes count once, when queued,
+    /// not again when issued and completed.
+    BlockIoRate {
+        threshold_mb_per_sec: f32,
+        #[serde(default = "default_block_io_rate_window_seconds")]
+        window_seconds: u64,
+        /// `device` or `process`.
+        #[serde(default = "default_block_io_rate_per")]
+        per: String,
+    },
+    /// The latest `[baselines]` sample of `metric` at least `sigma`
+    /// standard deviations above its mean for the hour of day, and at
+    /// least `min_value`: a host busier than it usually is at this time,
+    /// whatever its usual load. Quiet until the hour has
+    /// `baselines.min_samples` samples.
+    Baseline {
+        metric: baselines::Metric,
+        #[serde(default = "default_baseline_sigma")]
+        sigma: f32,
+        #[serde(default)]
+        min_value: f64,
+    },
+    /// A Creds event that makes a process root, puts it in the root group
+    /// or gives it capabilities, e.g. a shell under `nginx` exec'ing a
+    /// setuid binary.
+    PrivilegeEscalation {
+        /// Comm of the process; `*` matches any.
+        #[serde(default = "default_any")]
+        comm: String,
+        /// Comm of any of its ancestors; `*` matches any.
+        #[serde(default = "default_any")]
+        ancestor: String,
+        /// Comms expected to escalate (setuid helpers); replaces the
+        /// default list when given.
+        #[serde(default = "default_privilege_escalation_ignore")]
+        ignore: Vec<String>,
+    },
+    /// A FileAccess event on a sensitive path, e.g. a web server's child
+    /// writing to `/root/.ssh/authorized_keys`. Only paths covered by
+    /// `probes.watched_paths` produce events at all.
+    SensitiveFileAccess {
+        /// Files or directories; a directory matches everything below it.
+        #[serde(default = "default_sensitive_file_access_paths")]
+        paths: Vec<String>,
+        /// `open`, `open_write` and/or `unlink`.
+        #[serde(default = "default_sensitive_file_access_ops")]
+        ops: Vec<String>,
+        /// Comms expected to touch these files (account and package
+        /// tools); replaces the default list when given.
+        #[serde(default = "default_sensitive_file_access_ignore")]
+        ignore: Vec<String>,
+    },
+    /// A kernel module or BPF program load, e.g. `insmod` of an
+    /// out-of-tree module or an unexpected tracing agent.
+    KernelLoad {
+        /// `module`, `bpf` or `any`.
+        #[serde(default = "default_kernel_load_kind")]
+        kind: String,
+        /// Comms expected to load programs (systemd attaches cgroup
+        /// programs to units); replaces the default list when given.
+        #[serde(default = "default_kernel_load_ignore")]
+        ignore: Vec<String>,
+        /// Module and program names expected on the host.
+        #[serde(default)]
+        ignore_names: Vec<String>,
+    },
+    /// A TCP connection, e.g. any outbound connection from a child of
+    /// `cron`.
+    Connection {
+        /// `outbound`, `inbound` or `any`.
+        #[serde(default = "default_connection_direction")]
+        direction: String,
+        /// Comm of the connecting or accepting process; `*` matches any.
+        #[serde(default = "default_any")]
+        comm: String,
+        /// Comm of its parent; `*` matches any.
+        #[serde(default = "default_any")]
+        parent: String,
+        /// Remote port of outbound and local port of inbound connections;
+        /// 0 matches any.
+        #[serde(default)]
+        port: u16,
+    },
+    /// Any detector conditions, e.g. `subtree_cpu_pct` and
+    /// `forks_per_sec`, alerting once all of them (`op: and`) or any (`op:
+    /// or`) have matched within `within_seconds`, with this rule's severity
+    /// and cooldown.
+    Composite {
+        /// `and` or `or`.
+        #[serde(default = "default_composite_op")]
+        op: String,
+        #[serde(default = "default_composite_within_seconds")]
+        within_seconds: u64,
+        /// Detectors with their fields, without `name`, `severity`,
+        /// `cooldown` or `scope`.
+        conditions: Vec<RawDetector>,
+    },
+    /// Rhai script run per event; returning a non-empty string fires the
+    /// rule with that message. See the rules guide for the `event` and
+    /// `window` variables.
+    Script {
+        script: String,
+        /// Span of the `window` counters.
+        #[serde(default = "default_script_window_seconds")]
+        window_seconds: u64,
+        /// Event types to run on (`exec`, `fork`, `exit`, `net`, ...);
+        /// defaults to exec, fork and exit.
+        #[serde(default)]
+        event_types: Option<Vec<String>>,
+    },
+    /// Rhai expression over the event's fields and window counters (e.g.
+    /// `forks_per_sec(ppid) > 20 && comm =~ "make"`), evaluated per event;
+    /// fires when it holds. See the rules guide for what it can use.
+    Expr {
+        expr: String,
+        /// Span of the counters.
+        #[serde(default = "default_script_window_seconds")]
+        window_seconds: u64,
+        /// Event types to evaluate on; defaults to exec, fork and exit.
+        #[serde(default)]
+        event_types: Option<Vec<String>>,
+    },
+}
+
+/// Top level of a rules file: a bare list (YAML) or a `[[rules]]` table
+/// array (TOML). Both shapes are accepted in either format.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum RulesDocument {
+    Wrapper { rules: Vec<RawRule> },
+    List(Vec<RawRule>),
+}
+
+impl RulesDocument {
+    fn into_rules(self) -> Vec<RawRule> {
+        match self {
+            RulesDocument::Wrapper { rules } => rules,
+            RulesDocument::List(rules) => rules,
+        }
+    }
+}
+
+/// JSON Schema (draft-07) for the rules file, generated from the same types
+/// the loader deserializes, so it can't drift from what cognitod accepts.
+pub fn rules_schema() -> serde_json::Value {
+    let generator = schemars::generate::SchemaSettings::draft07()
+        .for_deserialize()
+        .into_generator();
+    let mut schema = generator.into_root_schema_for::<RulesDocument>();
+    schema.insert("title".into(), "Linnix rules file".into());
+    schema.to_value()
+}
+
+fn default_short_job_duration_ms() -> u64 {
+    DEFAULT_SHORT_JOB_DURATION_MS
+}
+
+fn default_thermal_min_cpu_pct() -> f32 {
+    50.0
+}
+
+fn default_thermal_max_freq_pct() -> f32 {
+    60.0
+}
+
+fn default_gpu_leak_min_used_mib() -> u64 {
+    1024
+}
+
+fn default_crash_loop_max_lifetime_secs() -> u64 {
+    30
+}
+
+fn default_crash_loop_parent() -> String {
+    "systemd".to_string()
+}
+
+fn default_fingerprint_learning_secs() -> u64 {
+    86_400
+}
+
+fn default_sched_delay_threshold_ms() -> u64 {
+    100
+}
+
+fn default_direct_reclaim_threshold_ms() -> u64 {
+    500
+}
+
+fn default_direct_reclaim_window_seconds() -> u64 {
+    10
+}
+
+fn default_kill_sweep_threshold() -> u64 {
+    10
+}
+
+fn default_kill_sweep_window_seconds() -> u64 {
+    10
+}
+
+fn default_block_io_rate_window_seconds() -> u64 {
+    10
+}
+
+fn default_block_io_rate_per() -> String {
+    "device".into()
+}
+
+fn default_baseline_sigma() -> f32 {
+    3.0
+}
+
+fn default_privilege_escalation_ignore() -> Vec<String> {
+    [
+        "sudo",
+        "su",
+        "passwd",
+        "pkexec",
+        "unix_chkpwd",
+        "newgrp",
+        "ping",
+        "mount",
+        "umount",
+        "fusermount",
+        "fusermount3",
+        "newuidmap",
+        "newgidmap",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_sensitive_file_access_paths() -> Vec<String> {
+    [
+        "/etc/shadow",
+        "/etc/sudoers",
+        "/etc/sudoers.d",
+        "/etc/ssh",
+        "/root/.ssh",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_sensitive_file_access_ops() -> Vec<String> {
+    ["open_write", "unlink"].map(String::from).to_vec()
+}
+
+fn default_sensitive_file_access_ignore() -> Vec<String> {
+    [
+        "passwd",
+        "chpasswd",
+        "chage",
+        "useradd",
+        "usermod",
+        "userdel",
+        "groupadd",
+        "groupmod",
+        "groupdel",
+        "vipw",
+        "visudo",
+        "ssh-keygen",
+        "dpkg",
+        "rpm",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+fn default_kernel_load_kind() -> String {
+    "any".to_string()
+}
+
+fn default_kernel_load_ignore() -> Vec<String> {
+    ["systemd", "systemd-udevd"].map(String::from).to_vec()
+}
+
+fn default_connection_direction() -> String {
+    "outbound".to_string()
+}
+
+fn default_any() -> String {
+    "*".to_string()
+}
+
+fn default_script_window_seconds() -> u64 {
+    DEFAULT_SCRIPT_WINDOW_SECS
+}
+
+fn default_composite_op() -> String {
+    "and".to_string()
+}
+
+fn default_composite_within_seconds() -> u64 {
+    60
+}
+
 impl TryFrom<RawRule> for RuleConfig {
     type Error = anyhow::Error;
 
@@ -234,8 +1495,88 @@ impl TryFrom<RawRule> for RuleConfig {
             .map(Severity::from_str)
             .unwrap_or(Severity::Info);
         let cooldown = value.cooldown.unwrap_or(DEFAULT_COOLDOWN_SECS);
+        let resolve_after = value.resolve_after.unwrap_or(DEFAULT_RESOLVE_AFTER_SECS);
+
+        let detector = value.detector.into_detector(&value.name)?;
+
+        if let Some(scope) = &value.scope {
+            if matches!(detector, Detector::Composite { .. }) {
+                bail!("rule '{}': composite rules can't have a scope", value.name);
+            }
+            if detector.is_host_wide() {
+                bail!(
+                    "rule '{}': {} rules watch the whole host and can't have a scope",
+                    value.name,
+                    detector.kind()
+                );
+            }
+            if scope.per.is_some() && !detector.counts_per_scope() {
+                bail!(
+                    "rule '{}': {} rules keep no counters for scope.per to split",
+                    value.name,
+                    detector.kind()
+                );
+            }
+        }
+
+        let matcher = match value.matcher {
+            Some(_) if matches!(detector, Detector::Composite { .. }) => {
+                bail!("rule '{}': composite rules can't have a match", value.name);
+            }
+            Some(_) if detector.is_host_wide() => bail!(
+                "rule '{}': {} rules watch the whole host and can't have a match",
+                value.name,
+                detector.kind()
+            ),
+            Some(raw) => Some(
+                RuleMatch::try_from(raw)
+                    .with_context(|| format!("rule '{}': invalid match.comm", value.name))?,
+            ),
+            None => None,
+        };
+
+        if let Some(escalate) = &value.escalate
+            && escalate.within_minutes == 0
+        {
+            bail!(
+                "rule '{}': escalate.within_minutes must be at least 1",
+                value.name
+            );
+        }
+
+        if let Some(unknown) = value
+            .notify
+            .iter()
+            .flatten()
+            .find(|c| !crate::notifications::CHANNELS.contains(&c.as_str()))
+        {
+            bail!(
+                "rule '{}': unknown notify channel '{unknown}' (expected one of {})",
+                value.name,
+                crate::notifications::CHANNELS.join(", ")
+            );
+        }
+
+        Ok(RuleConfig {
+            name: value.name,
+            severity,
+            cooldown,
+            resolve_after,
+            detector,
+            scope: value.scope,
+            matcher,
+            escalate: value.escalate,
+            mode: value.mode,
+            notify: value.notify,
+            fingerprint: value.fingerprint,
+        })
+    }
+}
 
-        let detector = match value.detector {
+impl RawDetector {
+    /// The detector of rule `rule`, validated.
+    fn into_detector(self, rule: &str) -> anyhow::Result<Detector> {
+        Ok(match self {
             RawDetector::ForkBurst {
                 threshold,
                 window_seconds,
@@ -271,7 +1612,13 @@ impl TryFrom<RawRule> for RuleConfig {
                 rate_per_min,
                 median_lifetime,
             } => Detector::ExecRate {
-                regex,
+                regex: match regex.as_str() {
+                    "" => None,
+                    pattern => Some(
+                        Regex::new(pattern)
+                            .with_context(|| format!("rule '{rule}': invalid regex"))?,
+                    ),
+                },
                 rate_per_min,
                 median_lifetime,
             },
@@ -317,44 +1664,520 @@ impl TryFrom<RawRule> for RuleConfig {
                 threshold_pct,
                 duration,
             },
-        };
-
-        Ok(RuleConfig {
-            name: value.name,
-            severity,
-            cooldown,
-            detector,
+            RawDetector::CfsThrottling {
+                threshold_pct,
+                duration,
+            } => Detector::CfsThrottling {
+                threshold_pct,
+                duration,
+            },
+            RawDetector::ThermalThrottling {
+                min_cpu_pct,
+                max_freq_pct,
+                duration,
+            } => Detector::ThermalThrottling {
+                min_cpu_pct,
+                max_freq_pct,
+                duration,
+            },
+            RawDetector::GpuMemoryLeak {
+                min_growth_mib_per_min,
+                min_used_mib,
+                duration,
+            } => Detector::GpuMemoryLeak {
+                min_growth_mib_per_min,
+                min_used_mib,
+                duration,
+            },
+            RawDetector::DaemonCrashLoop {
+                threshold,
+                window_seconds,
+                max_lifetime_secs,
+                parent,
+            } => Detector::DaemonCrashLoop {
+                threshold,
+                window_seconds,
+                max_lifetime_secs,
+                parent,
+            },
+            RawDetector::NewBinaryExecuted {
+                learning_secs,
+                match_argv,
+            } => Detector::NewBinaryExecuted {
+                learning_secs,
+                match_argv,
+            },
+            RawDetector::UnusualParent { learning_secs } => {
+                Detector::UnusualParent { learning_secs }
+            }
+            RawDetector::OomKill { min_rss_mb } => Detector::OomKill { min_rss_mb },
+            RawDetector::SchedDelay { threshold_ms } => Detector::SchedDelay { threshold_ms },
+            RawDetector::DirectReclaim {
+                threshold_ms,
+                window_seconds,
+            } => Detector::DirectReclaim {
+                threshold_ms,
+                window_seconds: window_seconds.max(1),
+            },
+            RawDetector::KillSweep {
+                threshold,
+                window_seconds,
+            } => Detector::KillSweep {
+                threshold,
+                window_seconds: window_seconds.max(1),
+            },
+            RawDetector::BlockIoRate {
+                threshold_mb_per_sec,
+                window_seconds,
+                per,
+            } => {
+                if !threshold_mb_per_sec.is_finite() || threshold_mb_per_sec <= 0.0 {
+                    bail!("rule '{rule}': threshold_mb_per_sec must be positive");
+                }
+                Detector::BlockIoRate {
+                    threshold_mb_per_sec,
+                    window_seconds: window_seconds.max(1),
+                    per_process: match per.as_str() {
+                        "device" => false,
+                        "process" => true,
+                        other => {
+                            bail!("rule '{rule}': per must be device or process, got '{other}'")
+                        }
+                    },
+                }
+            }
+            RawDetector::Baseline {
+                metric,
+                sigma,
+                min_value,
+            } => {
+                if !sigma.is_finite() || sigma <= 0.0 {
+                    bail!("rule '{rule}': sigma must be positive");
+                }
+                Detector::Baseline {
+                    metric,
+                    sigma,
+                    min_value,
+                }
+            }
+            RawDetector::PrivilegeEscalation {
+                comm,
+                ancestor,
+                ignore,
+            } => Detector::PrivilegeEscalation {
+                comm,
+                ancestor,
+                ignore,
+            },
+            RawDetector::SensitiveFileAccess { paths, ops, ignore } => {
+                let ops = ops
+                    .iter()
+                    .map(|op| match op.as_str() {
+                        "open" => Ok(FileAccessOp::Open),
+                        "open_write" => Ok(FileAccessOp::OpenWrite),
+                        "unlink" => Ok(FileAccessOp::Unlink),
+                        other => Err(anyhow!(
+                            "rule '{rule}': ops must be open, open_write or unlink, got '{other}'"
+                        )),
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Detector::SensitiveFileAccess {
+                    paths: paths
+                        .into_iter()
+                        .map(|p| p.trim_end_matches('/').to_string())
+                        .collect(),
+                    ops,
+                    ignore,
+                }
+            }
+            RawDetector::KernelLoad {
+                kind,
+                ignore,
+                ignore_names,
+            } => Detector::KernelLoad {
+                kind: match kind.as_str() {
+                    "module" => Some(KernelLoadKind::Module),
+                    "bpf" => Some(KernelLoadKind::BpfProgram),
+                    "any" => None,
+                    other => {
+                        return Err(anyhow!(
+                            "rule '{rule}': kind must be module, bpf or any, got '{other}'"
+                        ));
+                    }
+                },
+                ignore,
+                ignore_names,
+            },
+            RawDetector::Connection {
+                direction,
+                comm,
+                parent,
+                port,
+            } => Detector::Connection {
+                direction: match direction.as_str() {
+                    "outbound" => Some(Direction::Outbound),
+                    "inbound" => Some(Direction::Inbound),
+                    "any" => None,
+                    other => {
+                        return Err(anyhow!(
+                            "rule '{rule}': direction must be outbound, inbound or any, got '{other}'"
+                        ));
+                    }
+                },
+                comm,
+                parent,
+                port,
+            },
+            RawDetector::Composite {
+                op,
+                within_seconds,
+                conditions,
+            } => {
+                let any = match op.as_str() {
+                    "and" => false,
+                    "or" => true,
+                    other => bail!("rule '{rule}': op must be and or or, got '{other}'"),
+                };
+                if conditions.is_empty() {
+                    bail!("rule '{rule}': composite rules need at least one condition");
+                }
+                let conditions = conditions
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, raw)| {
+                        if matches!(raw, RawDetector::Composite { .. }) {
+                            bail!("rule '{rule}': conditions can't be composite");
+                        }
+                        raw.into_detector(&format!("{rule}[{index}]"))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Detector::Composite {
+                    any,
+                    within_seconds,
+                    conditions,
+                }
+            }
+            RawDetector::Script {
+                script,
+                window_seconds,
+                event_types,
+            } => {
+                let script = crate::script::Script::compile(&script)
+                    .with_context(|| format!("rule '{rule}'"))?;
+                Detector::Script {
+                    script: Arc::new(script),
+                    window_seconds,
+                    event_types: event_types_named(rule, event_types)?,
+                }
+            }
+            RawDetector::Expr {
+                expr,
+                window_seconds,
+                event_types,
+            } => {
+                let expr =
+                    crate::expr::Expr::compile(&expr).with_context(|| format!("rule '{rule}'"))?;
+                Detector::Expr {
+                    expr: Arc::new(expr),
+                    window_seconds,
+                    event_types: event_types_named(rule, event_types)?,
+                }
+            }
         })
     }
 }
 
-struct RuleState {
+/// Event types a script or expr rule runs on, by name.
+fn event_types_named(rule: &str, names: Option<Vec<String>>) -> anyhow::Result<Vec<u32>> {
+    Ok(match names {
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                crate::script::event_type_from_name(name)
+                    .ok_or_else(|| anyhow!("rule '{rule}': unknown event type '{name}'"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        None => crate::script::DEFAULT_EVENT_TYPES
+            .iter()
+            .filter_map(|name| crate::script::event_type_from_name(name))
+            .collect(),
+    })
+}
+
+/// Upper bound on distinct parent PIDs tracked for runaway-tree detection.
+const MAX_TRACKED_PPIDS: usize = 8192;
+/// Ancestors checked by `privilege_escalation` rules with an `ancestor`,
+/// and credited with the CPU of `subtree_cpu_pct` rules' descendants.
+const MAX_ANCESTOR_DEPTH: usize = 32;
+/// Signals remembered per sender for `kill_sweep` rules.
+const MAX_SIGNALS_PER_SENDER: usize = 4096;
+/// BlockIo events remembered per device or process for `block_io_rate`
+/// rules.
+const MAX_BLOCK_IO_PER_KEY: usize = 4096;
+/// Upper bound on exec start times awaiting a matching exit.
+const MAX_TRACKED_EXECS: usize = 32768;
+/// Upper bound on entries in a per-key sliding-window queue. The shared
+/// fork, exec and completion windows are only bounded by their span, so
+/// their counts stay exact under load.
+const MAX_WINDOW_EVENTS: usize = 100_000;
+/// Upper bound on scope keys (pods, cgroups, ...) counted per rule.
+const MAX_SCOPE_KEYS: usize = 1024;
+/// Cooldowns a rule keeps before expired ones are dropped; scoped rules
+/// keep one per key that fired.
+const MAX_ACTIVE_COOLDOWNS: usize = 4096;
+/// Upper bound on binaries, parents and command lines in the workload
+/// fingerprint.
+const MAX_FINGERPRINT_PATTERNS: usize = 65_536;
+/// How long a process's CPU sample counts towards its ancestors'
+/// subtrees without a newer one.
+const SUBTREE_CPU_KEEP: Duration = Duration::from_secs(30);
+/// A rule using more than this share of rule evaluation time is logged.
+const SLOW_RULE_SHARE: f64 = 0.5;
+/// Intervals with less total evaluation time than this are not judged.
+const SLOW_RULE_MIN_NS: u64 = 50_000_000;
+/// How often evaluation time shares are checked (and at most warned about).
+const SLOW_RULE_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Evaluation time per rule at the previous slow-rule check.
+#[derive(Default)]
+struct SlowRuleWatch {
+    last_ns: Vec<u64>,
+    last_check: Option<Instant>,
+}
+
+/// Index and share of the rule that dominated evaluation time since the
+/// previous check, if any rule exceeded [`SLOW_RULE_SHARE`].
+fn dominant_rule(deltas: &[u64]) -> Option<(usize, f64)> {
+    if deltas.len() < 2 {
+        return None;
+    }
+    let total: u64 = deltas.iter().sum();
+    if total < SLOW_RULE_MIN_NS {
+        return None;
+    }
+    let (idx, max) = deltas.iter().enumerate().max_by_key(|(_, ns)| **ns)?;
+    let share = *max as f64 / total as f64;
+    (share > SLOW_RULE_SHARE).then_some((idx, share))
+}
+
+/// Execs counted by one `exec_rate` rule: when they started, those still
+/// running, and the lifetimes of those that exited.
+#[derive(Default)]
+struct MatchedExecs {
+    execs: VecDeque<Instant>,
+    running: HashMap<u32, Instant>,
+    lifetimes: VecDeque<(Instant, Duration)>,
+    /// When the rule last fired; kernel exec counts from before it are
+    /// ignored.
+    reset: Option<Instant>,
+}
+
+/// What a detector found: the alert's message, and labels with the values
+/// behind it.
+struct Detection {
+    message: String,
+    labels: BTreeMap<String, String>,
+}
+
+impl Detection {
+    fn label(mut self, key: &str, value: impl ToString) -> Self {
+        self.labels.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+impl From<String> for Detection {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+/// A fired alert whose condition hasn't cleared yet.
+struct Firing {
+    rule: String,
+    severity: Severity,
+    /// Message of the alert, without the trigger's session.
+    message: String,
+    labels: BTreeMap<String, String>,
+    scope_key: String,
+    since: Instant,
+    last_match: Instant,
+    /// Time without a match after which the alert is resolved.
+    quiet: Duration,
+}
+
+/// Counters a rule keeps for itself rather than sharing the engine's
+/// host-wide ones: one set per scope key, or a single set under `""`.
+#[derive(Default)]
+struct ScopedCounters {
+    /// Forks, for fork rules with a scope.
+    forks: VecDeque<Instant>,
+    execs: MatchedExecs,
+    /// When the CPU of each subtree, by root PID, went over the rule's
+    /// threshold, and when it was last seen over it.
+    cpu_exceed: HashMap<u32, (Instant, Instant)>,
+    /// When RSS went over the rule's threshold.
+    rss_exceed: Option<Instant>,
+    last_seen: Option<Instant>,
+}
+
+/// Event windows shared by all rules, filled once per event.
+struct EventWindows {
     fork_events: VecDeque<Instant>,
     exec_events: VecDeque<Instant>,
     exec_start: HashMap<u32, Instant>,
-    exec_completions: VecDeque<(Instant, Duration)>,
+    exec_completions: VecDeque<(Instant, Duration, ExitStatus)>,
     forks_by_ppid: HashMap<u32, VecDeque<Instant>>,
-    cpu_exceed: HashMap<String, Instant>,
-    rss_exceed: HashMap<String, Instant>,
+    /// Exec/exit cycles per (parent, binary); only kept when a
+    /// `daemon_crash_loop` rule is loaded.
+    crash_loops: Option<CrashLoopTracker>,
+    /// Execs seen since startup; only kept when a `new_binary_executed` or
+    /// `unusual_parent` rule is loaded.
+    fingerprint: Option<WorkloadFingerprint>,
+    /// CPU samples credited to each process's ancestors; only kept when a
+    /// `subtree_cpu_pct` rule is loaded.
+    subtree_cpu: Option<SubtreeCpu>,
+    /// Window spans of the loaded rules.
+    spans: WindowSpans,
+    slow_rules: SlowRuleWatch,
+}
+
+impl EventWindows {
+    fn with_spans(mut self, spans: WindowSpans) -> Self {
+        self.apply_spans(spans);
+        self
+    }
+
+    /// Keep windows for `spans`, starting or stopping the crash-loop
+    /// tracker, workload fingerprint and subtree CPU as the rules need them. A changed
+    /// crash-loop window starts tracking over.
+    fn apply_spans(&mut self, spans: WindowSpans) {
+        if spans.crash_loop_secs == 0 {
+            self.crash_loops = None;
+        } else if self.crash_loops.is_none() || spans.crash_loop_secs != self.spans.crash_loop_secs
+        {
+            self.crash_loops = Some(CrashLoopTracker::new(
+                Duration::from_secs(spans.crash_loop_secs),
+                MAX_TRACKED_EXECS,
+                MAX_TRACKED_PPIDS,
+            ));
+        }
+        if !spans.fingerprint {
+            self.fingerprint = None;
+        } else if self.fingerprint.is_none() {
+            self.fingerprint = Some(WorkloadFingerprint::new(
+                Instant::now(),
+                MAX_FINGERPRINT_PATTERNS,
+            ));
+        }
+        if !spans.subtree_cpu {
+            self.subtree_cpu = None;
+        } else if self.subtree_cpu.is_none() {
+            self.subtree_cpu = Some(SubtreeCpu::new(SUBTREE_CPU_KEEP, MAX_TRACKED_EXECS));
+        }
+        self.spans = spans;
+    }
+}
+
+/// Counters and cooldowns of one rule. Each rule locks its own, so rules
+/// don't wait on each other, and nothing is locked while an alert is sent.
+#[derive(Default)]
+struct RuleShard {
+    /// When a sustained condition (PSI, thermal throttling) started.
+    breach_start: Option<Instant>,
+    /// `cfs_throttling`: when each cgroup started breaching.
+    throttle_breach: HashMap<String, Instant>,
+    /// `gpu_memory_leak`: the process growing longest at the configured
+    /// rate, and for how many seconds.
+    gpu_growth: Option<(String, u64)>,
+    /// `direct_reclaim`: recent reclaim stalls (ns) per PID.
+    reclaim_stalls: HashMap<u32, VecDeque<(Instant, u64)>>,
+    /// `kill_sweep`: recent signal targets per sending PID.
+    kill_targets: HashMap<u32, VecDeque<(Instant, u32)>>,
+    /// `block_io_rate`: recent bytes queued per device, or per PID.
+    block_io: HashMap<u32, VecDeque<(Instant, u64)>>,
+    /// Counters by scope key; see [`ScopedCounters`].
+    scoped: HashMap<String, ScopedCounters>,
+    /// Composite rules: when each condition last matched, and its message.
+    condition_hits: Vec<Option<(Instant, String)>>,
+    /// Cooldown ends, by scope key (`""` for unscoped rules).
     active: HashMap<String, Instant>,
-    /// Tracks when a PSI threshold was first breached per rule name.
-    /// Used by SystemPsiCpu/Memory/Io detectors for sustained-pressure windows.
-    psi_breach: HashMap<String, Instant>,
+    /// Fired alerts awaiting resolution, by scope key.
+    firing: HashMap<String, Firing>,
+    /// Recent alerts of rules with `escalate`, by scope key.
+    fired_at: HashMap<String, VecDeque<Instant>>,
+    /// Matches dropped by the cooldown since the last alert or
+    /// resolution, by cooldown key.
+    deduplicated: HashMap<String, u64>,
+}
+
+impl RuleShard {
+    /// Remove the alerts that haven't matched for their quiet period, with
+    /// the matches their cooldown dropped.
+    fn take_cleared(&mut self, now: Instant) -> Vec<(Firing, u64)> {
+        let cleared: Vec<String> = self
+            .firing
+            .iter()
+            .filter(|(_, f)| now.duration_since(f.last_match) >= f.quiet)
+            .map(|(key, _)| key.clone())
+            .collect();
+        cleared
+            .iter()
+            .filter_map(|key| {
+                let firing = self.firing.remove(key)?;
+                Some((firing, self.deduplicated.remove(key).unwrap_or(0)))
+            })
+            .collect()
+    }
+}
+
+/// What [`RuleEngine::on_event`] works out about an event once, for
+/// every rule to evaluate it against.
+struct EventFacts {
+    now: Instant,
+    /// Pids from the event's process up through its ancestors.
+    subtree: Vec<u32>,
+    /// The daemon whose crash loop the event's exit extends.
+    crash_key: Option<DaemonKey>,
+    /// For an exec once fingerprinting is on: comm, parent comm, what was
+    /// new about it, and how long the fingerprint has been learning.
+    exec_novelty: Option<(String, String, Novelty, Duration)>,
 }
 
 pub struct RuleEngine {
-    rules: Vec<Rule>,
-    state: Mutex<RuleState>,
+    /// Swapped wholesale by [`RuleEngine::update_rule`]; evaluation works on
+    /// a snapshot so no lock is held across awaits.
+    rules: RwLock<Arc<Vec<Rule>>>,
+    /// File the rules were loaded from, for persisting runtime changes.
+    source: Option<PathBuf>,
+    /// Held from reading or rewriting the rules file until the rules are
+    /// swapped, so a reload and a persisted update can't interleave.
+    source_lock: Mutex<()>,
+    /// Only held while an event is recorded or a window counted; rules
+    /// keep everything else in their own [`RuleShard`].
+    windows: Mutex<EventWindows>,
     tx: broadcast::Sender<Alert>,
     alerts_file: String,
     journald: bool,
     host: String,
-    fork_window_secs: u64,
-    exec_window_secs: u64,
-    completion_window_secs: u64,
-    runaway_window_secs: u64,
     metrics: Arc<Metrics>,
     total_memory_bytes: Option<u64>,
+    max_tracked_ppids: usize,
+    max_tracked_execs: usize,
+    /// Appends the login session of the triggering process to event alerts.
+    sessions: Option<Arc<SessionResolver>>,
+    /// Fork and exec counts from the kernel, which stay exact when events
+    /// are dropped.
+    kernel_counts: Option<Arc<KernelCounts>>,
+    /// Parents of forked processes, recorded for `subtree_cpu_pct` rules.
+    lineage: LineageCache,
+    /// Cgroups and pods of events, for rules with a `scope`.
+    scopes: ScopeResolver,
+    silences: Arc<Silences>,
+    baselines: Option<Arc<Baselines>>,
 }
 
 impl RuleEngine {
@@ -366,46 +2189,10 @@ impl RuleEngine {
     ) -> anyhow::Result<Self> {
         let text = std::fs::read_to_string(path)?;
         let hint = Path::new(path).extension().and_then(|ext| ext.to_str());
-        let cfgs = parse_rules(&text, hint)?;
-
-        let mut fork_window_secs = 0u64;
-        let exec_window_secs = 60u64;
-        let mut completion_window_secs = 60u64;
-        let mut runaway_window_secs = 0u64;
-
-        for cfg in &cfgs {
-            match &cfg.detector {
-                Detector::ForksPerSec { duration, .. } => {
-                    fork_window_secs = fork_window_secs.max(*duration);
-                }
-                Detector::ForkBurst { window_seconds, .. } => {
-                    fork_window_secs = fork_window_secs.max(*window_seconds);
-                }
-                Detector::RunawayTree { window_seconds, .. } => {
-                    fork_window_secs = fork_window_secs.max(*window_seconds);
-                    runaway_window_secs = runaway_window_secs.max(*window_seconds);
-                }
-                Detector::ShortJobFlood { window_seconds, .. } => {
-                    completion_window_secs = completion_window_secs.max(*window_seconds);
-                }
-                Detector::ExecRate { .. } => {
-                    completion_window_secs = completion_window_secs.max(60);
-                }
-                _ => {}
-            }
-        }
+        let rules = rules_from(parse_rules(&text, hint)?, &metrics);
 
-        if fork_window_secs == 0 {
-            fork_window_secs = 5;
-        }
-        if runaway_window_secs == 0 {
-            runaway_window_secs = fork_window_secs;
-        }
-        if completion_window_secs == 0 {
-            completion_window_secs = 60;
-        }
+        let spans = WindowSpans::for_rules(&rules);
 
-        let rules = cfgs.into_iter().map(|cfg| Rule { cfg }).collect();
         let (tx, _rx) = broadcast::channel(128);
         let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".into());
         let mut sys = System::new_all();
@@ -415,758 +2202,5204 @@ impl RuleEngine {
             kb => Some(kb.saturating_mul(1024)),
         };
         Ok(Self {
-            rules,
-            state: Mutex::new(RuleState {
-                fork_events: VecDeque::new(),
-                exec_events: VecDeque::new(),
-                exec_start: HashMap::new(),
-                exec_completions: VecDeque::new(),
-                forks_by_ppid: HashMap::new(),
-                cpu_exceed: HashMap::new(),
-                rss_exceed: HashMap::new(),
-                active: HashMap::new(),
-                psi_breach: HashMap::new(),
-            }),
+            rules: RwLock::new(Arc::new(rules)),
+            source: Some(PathBuf::from(path)),
+            source_lock: Mutex::new(()),
+            windows: Mutex::new(
+                EventWindows {
+                    fork_events: VecDeque::new(),
+                    exec_events: VecDeque::new(),
+                    exec_start: HashMap::new(),
+                    exec_completions: VecDeque::new(),
+                    forks_by_ppid: HashMap::new(),
+                    crash_loops: None,
+                    fingerprint: None,
+                    subtree_cpu: None,
+                    spans: WindowSpans::default(),
+                    slow_rules: SlowRuleWatch::default(),
+                }
+                .with_spans(spans),
+            ),
             tx,
             alerts_file,
             journald,
             host,
-            fork_window_secs,
-            exec_window_secs,
-            completion_window_secs,
-            runaway_window_secs,
             metrics,
             total_memory_bytes,
+            max_tracked_ppids: MAX_TRACKED_PPIDS,
+            max_tracked_execs: MAX_TRACKED_EXECS,
+            sessions: None,
+            kernel_counts: None,
+            lineage: LineageCache::default(),
+            scopes: ScopeResolver::default(),
+            silences: Arc::default(),
+            baselines: None,
         })
     }
 
-    pub fn broadcaster(&self) -> broadcast::Sender<Alert> {
-        self.tx.clone()
+    /// Attribute alerts raised by a process event to its login session, e.g.
+    /// "(triggered by alice's SSH session from 10.0.0.5)".
+    pub fn with_sessions(mut self, sessions: Arc<SessionResolver>) -> Self {
+        self.sessions = Some(sessions);
+        self
     }
 
-    pub fn rule_count(&self) -> usize {
-        self.rules.len()
+    /// Count forks and execs with the probes' counters where they exceed
+    /// the events received.
+    pub fn with_kernel_counts(mut self, counts: Arc<KernelCounts>) -> Self {
+        self.kernel_counts = Some(counts);
+        self
     }
 
-    async fn emit_alert(&self, rule: &RuleConfig, message: String) {
-        let key = format!("{}:{}", self.host, rule.name);
-        let mut state = self.state.lock().await;
-        let now = Instant::now();
-        if let Some(until) = state.active.get(&key)
-            && now <= *until
-        {
-            return;
-        }
-        let cooldown = if rule.cooldown == 0 {
-            Duration::from_millis(100)
-        } else {
-            Duration::from_secs(rule.cooldown)
-        };
-        state.active.insert(key.clone(), now + cooldown);
-        drop(state);
-
-        let alert = Alert {
-            rule: rule.name.clone(),
-            severity: rule.severity.clone(),
-            message,
-            host: self.host.clone(),
-        };
-
-        log::info!(
-            "[rules] emitting alert rule={} severity={} message={}",
-            alert.rule,
-            alert.severity.as_str(),
-            alert.message
-        );
-
-        if self.journald {
-            let _ = std::process::Command::new("logger")
-                .arg(format!("linnix: {} - {}", alert.rule, alert.message))
-                .status();
-        }
+    /// Resolve the `namespace`, `pod` and `per: pod` of rule scopes from
+    /// the pod metadata of `ctx`; without it scopes see no pods.
+    pub fn with_k8s(mut self, ctx: Arc<K8sContext>) -> Self {
+        self.scopes = ScopeResolver::new(Some(ctx));
+        self
+    }
 
-        if let Ok(line) = serde_json::to_string(&alert) {
-            if let Some(dir) = std::path::Path::new(&self.alerts_file).parent() {
-                let _ = std::fs::create_dir_all(dir);
-            }
-            if let Ok(mut f) = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&self.alerts_file)
-            {
-                let _ = writeln!(f, "{line}");
-            }
-        }
+    /// Drop the alerts of rules muted by `silences`.
+    pub fn with_silences(mut self, silences: Arc<Silences>) -> Self {
+        self.silences = silences;
+        self
+    }
 
-        let _ = self.tx.send(alert);
-        self.metrics.inc_alerts_emitted();
+    pub fn silences(&self) -> &Arc<Silences> {
+        &self.silences
     }
-}
 
-enum RuleFormat {
-    Toml,
-    Yaml,
-}
+    /// Compare `baseline` rules with the host's per-hour baselines; without
+    /// them those rules never fire.
+    pub fn with_baselines(mut self, baselines: Arc<Baselines>) -> Self {
+        self.baselines = Some(baselines);
+        self
+    }
 
-impl RuleFormat {
-    fn as_str(&self) -> &'static str {
-        match self {
-            RuleFormat::Toml => "toml",
-            RuleFormat::Yaml => "yaml",
-        }
+    async fn windows(&self) -> MutexGuard<'_, EventWindows> {
+        self.windows.lock().await
     }
-}
 
-fn parse_rules(text: &str, hint: Option<&str>) -> anyhow::Result<Vec<RuleConfig>> {
-    if text.trim().is_empty() {
-        return Ok(Vec::new());
+    /// Forks within `window` of `now`: the Fork events received, or the
+    /// kernel's count when events were lost.
+    fn recent_forks(&self, windows: &EventWindows, window: Duration, now: Instant) -> u64 {
+        let seen = count_recent(&windows.fork_events, window, now) as u64;
+        self.kernel_counts
+            .as_ref()
+            .map_or(seen, |counts| seen.max(counts.forks_within(window, now)))
     }
 
-    let preferred = match hint.map(|h| h.to_ascii_lowercase()) {
-        Some(ref h) if matches!(h.as_str(), "yaml" | "yml") => {
-            vec![RuleFormat::Yaml, RuleFormat::Toml]
+    /// Execs an `exec_rate` rule counted within `window` of `now`. A rule
+    /// counting every exec on the host also takes the kernel's count since
+    /// it last fired, in case events were lost.
+    fn rule_execs(
+        &self,
+        cfg: &RuleConfig,
+        execs: &MatchedExecs,
+        window: Duration,
+        now: Instant,
+    ) -> u64 {
+        let seen = count_recent(&execs.execs, window, now) as u64;
+        let every_exec = matches!(cfg.detector, Detector::ExecRate { regex: None, .. })
+            && !cfg.keeps_own_counters();
+        match &self.kernel_counts {
+            Some(counts) if every_exec => {
+                let since = execs.reset.map_or(window, |reset| {
+                    window.min(now.saturating_duration_since(reset))
+                });
+                seen.max(counts.execs_within(since, now))
+            }
+            _ => seen,
         }
-        Some(ref h) if h == "toml" => vec![RuleFormat::Toml, RuleFormat::Yaml],
-        _ => vec![RuleFormat::Toml, RuleFormat::Yaml],
-    };
-
-    let mut errors: Vec<(RuleFormat, anyhow::Error)> = Vec::new();
+    }
 
-    for format in preferred {
-        match parse_rules_with_format(text, &format) {
-            Ok(cfgs) => return Ok(cfgs),
-            Err(err) => errors.push((format, err)),
+    /// `event`'s PID followed by its ancestors, parent first, as far as the
+    /// event's parent and the lineage of recorded forks go. Init and
+    /// kthreadd are left out: everything descends from them.
+    async fn lineage_of(&self, event: &ProcessEvent) -> Vec<u32> {
+        let mut chain = vec![event.pid];
+        let mut parent = Some(event.ppid).filter(|&ppid| ppid != 0);
+        while let Some(pid) = parent {
+            if pid <= 2 || chain.contains(&pid) || chain.len() > MAX_ANCESTOR_DEPTH {
+                break;
+            }
+            chain.push(pid);
+            parent = self.lineage.lookup(pid).await;
         }
+        chain
     }
 
-    let joined = errors
-        .into_iter()
-        .map(|(fmt, err)| format!("{}: {}", fmt.as_str(), err))
-        .collect::<Vec<_>>()
-        .join("; ");
-    Err(anyhow!("failed to parse rules: {joined}"))
-}
-
-fn parse_rules_with_format(text: &str, format: &RuleFormat) -> anyhow::Result<Vec<RuleConfig>> {
-    let raw = match format {
-        RuleFormat::Toml => {
-            parse_rules_from_toml(text).with_context(|| "failed to parse rules file as TOML")?
-        }
-        RuleFormat::Yaml => {
-            parse_rules_from_yaml(text).with_context(|| "failed to parse rules file as YAML")?
+    /// The counters of `key` for `rule`, created on first use. Keys idle
+    /// for longer than `keep_for` make room once a rule has `MAX_SCOPE_KEYS`.
+    fn scoped_counters<'a>(
+        &self,
+        rule: &Rule,
+        state: &'a mut RuleShard,
+        key: &str,
+        keep_for: Duration,
+        now: Instant,
+    ) -> &'a mut ScopedCounters {
+        let keys = &mut state.scoped;
+        if let Some(counters) = keys.get_mut(key) {
+            counters.last_seen = Some(now);
+        } else {
+            keys.insert(
+                key.to_string(),
+                ScopedCounters {
+                    last_seen: Some(now),
+                    ..Default::default()
+                },
+            );
+            let evicted = enforce_cap(keys, MAX_SCOPE_KEYS, Some(keep_for), now, |c| c.last_seen);
+            if evicted > 0 {
+                self.metrics.add_detector_state_evictions(evicted);
+            }
+            rule.stats.set_scoped_keys(keys.len());
         }
-    };
-    raw.into_iter().map(RuleConfig::try_from).collect()
-}
+        keys.entry(key.to_string()).or_default()
+    }
 
-fn parse_rules_from_yaml(text: &str) -> Result<Vec<RawRule>, serde_yaml::Error> {
-    serde_yaml::from_str(text)
-}
+    /// Forks within `window` for one scope key of a rule, this one included.
+    fn scoped_forks(
+        &self,
+        rule: &Rule,
+        state: &mut RuleShard,
+        key: &str,
+        window: Duration,
+        now: Instant,
+    ) -> u64 {
+        let counters = self.scoped_counters(rule, state, key, window, now);
+        counters.forks.push_back(now);
+        trim_instant_queue(&mut counters.forks, window, now);
+        let evicted = cap_queue(&mut counters.forks);
+        if evicted > 0 {
+            self.metrics.add_detector_state_evictions(evicted);
+        }
+        counters.forks.len() as u64
+    }
 
-fn parse_rules_from_toml(text: &str) -> Result<Vec<RawRule>, toml::de::Error> {
-    #[derive(Deserialize)]
-    #[serde(untagged)]
-    enum RuleDoc {
-        Wrapper { rules: Vec<RawRule> },
-        Array(Vec<RawRule>),
+    pub fn broadcaster(&self) -> broadcast::Sender<Alert> {
+        self.tx.clone()
     }
 
-    let doc: RuleDoc = toml::from_str(text)?;
-    Ok(match doc {
-        RuleDoc::Wrapper { rules } => rules,
-        RuleDoc::Array(rules) => rules,
-    })
-}
+    /// Run `events` (e.g. a trace from [`crate::trace::load`]) through the
+    /// rules and return the alerts they raise. The gaps between the events'
+    /// `ts_ns` pass on a paused clock, so windows and durations see the
+    /// trace's timing however fast it replays. The replay has its own
+    /// runtime and clock: use a fresh engine, without an alerts file.
+    /// Needs the `trace-replay` feature, for tokio's paused clock.
+    #[cfg(any(test, feature = "trace-replay"))]
+    pub fn evaluate_trace(&self, events: &[ProcessEvent]) -> Vec<Alert> {
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .start_paused(true)
+                        .build()
+                        .expect("failed to build the trace replay runtime")
+                        .block_on(self.replay(events))
+                })
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
 
-fn trim_instant_queue(queue: &mut VecDeque<Instant>, keep_for: Duration, now: Instant) {
-    while let Some(&front) = queue.front() {
-        if now.duration_since(front) > keep_for {
-            queue.pop_front();
-        } else {
-            break;
+    #[cfg(any(test, feature = "trace-replay"))]
+    async fn replay(&self, events: &[ProcessEvent]) -> Vec<Alert> {
+        let mut rx = self.tx.subscribe();
+        let mut alerts = Vec::new();
+        let mut clock: Option<u64> = None;
+        for event in events {
+            // Events out of order run at the latest time seen.
+            if let Some(last) = clock
+                && event.ts_ns > last
+            {
+                tokio::time::advance(Duration::from_nanos(event.ts_ns - last)).await;
+            }
+            clock = Some(clock.map_or(event.ts_ns, |last| last.max(event.ts_ns)));
+            if let Err(e) = self.on_event(event).await {
+                log::warn!("[rules] trace event of pid {} failed: {e:#}", event.pid);
+            }
+            alerts.extend(std::iter::from_fn(|| rx.try_recv().ok()));
         }
+        alerts
     }
-}
 
-fn trim_completion_queue(
-    queue: &mut VecDeque<(Instant, Duration)>,
-    keep_for: Duration,
-    now: Instant,
-) {
-    while let Some(&(ts, _)) = queue.front() {
-        if now.duration_since(ts) > keep_for {
-            queue.pop_front();
-        } else {
-            break;
-        }
+    pub fn rule_count(&self) -> usize {
+        self.snapshot()
+            .iter()
+            .filter(|r| r.condition_of.is_none())
+            .count()
     }
-}
 
-fn count_recent(queue: &VecDeque<Instant>, window: Duration, now: Instant) -> usize {
-    queue
-        .iter()
-        .rev()
-        .take_while(|&&ts| now.duration_since(ts) <= window)
-        .count()
-}
+    fn snapshot(&self) -> Arc<Vec<Rule>> {
+        Arc::clone(&self.rules.read().unwrap_or_else(|e| e.into_inner()))
+    }
 
-#[async_trait]
-impl Handler for RuleEngine {
-    fn name(&self) -> &'static str {
-        "rules"
+    /// Current tunable settings of the rule called `name`.
+    pub fn rule(&self, name: &str) -> Option<RuleView> {
+        self.snapshot()
+            .iter()
+            .find(|r| r.condition_of.is_none() && r.cfg.name == name)
+            .map(|r| RuleView::from(&r.cfg))
     }
 
-    async fn on_event(&self, event: &ProcessEvent) {
-        use linnix_ai_ebpf_common::EventType;
-        let now = Instant::now();
-        let fork_keep = Duration::from_secs(self.fork_window_secs.max(1));
-        let exec_keep = Duration::from_secs(self.exec_window_secs.max(1));
-        let completion_keep = Duration::from_secs(self.completion_window_secs.max(1));
-        let runaway_keep = Duration::from_secs(self.runaway_window_secs.max(1));
+    /// Notification channels the rule called `name` routes to; `None` for
+    /// rules without `notify` and for alerts not raised by a rule.
+    pub fn notify_channels(&self, name: &str) -> Option<Vec<String>> {
+        self.snapshot()
+            .iter()
+            .find(|r| r.condition_of.is_none() && r.cfg.name == name)
+            .and_then(|r| r.cfg.notify.clone())
+    }
 
-        let mut state = self.state.lock().await;
+    /// Current tunable settings of every loaded rule, in file order.
+    pub fn rule_views(&self) -> Vec<RuleView> {
+        self.snapshot()
+            .iter()
+            .filter(|r| r.condition_of.is_none())
+            .map(|r| RuleView::from(&r.cfg))
+            .collect()
+    }
 
-        match event.event_type {
-            x if x == EventType::Fork as u32 => {
-                state.fork_events.push_back(now);
-                trim_instant_queue(&mut state.fork_events, fork_keep, now);
+    /// Path of the rules file, when the rules were loaded from one.
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
 
-                if self.runaway_window_secs > 0 {
-                    let mut remove_entry = false;
-                    {
-                        let queue = state
-                            .forks_by_ppid
-                            .entry(event.ppid)
-                            .or_insert_with(VecDeque::new);
-                        queue.push_back(now);
-                        trim_instant_queue(queue, runaway_keep, now);
-                        if queue.is_empty() {
-                            remove_entry = true;
-                        }
-                    }
-                    if remove_entry {
-                        state.forks_by_ppid.remove(&event.ppid);
-                    }
+    /// Re-read the rules file and swap in its rules. Rules defined exactly
+    /// as before keep their counters and cooldowns; changed and removed
+    /// rules start over. If the file doesn't load, the running rules stay.
+    pub async fn reload(&self) -> anyhow::Result<RulesReload> {
+        let path = self
+            .source
+            .as_deref()
+            .ok_or_else(|| anyhow!("rules were not loaded from a file"))?;
+        let _source = self.source_lock.lock().await;
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let hint = path.extension().and_then(|ext| ext.to_str());
+        let mut rules = rules_from(parse_rules(&text, hint)?, &self.metrics);
+
+        // Swap under the windows lock so no event sees the new rules with
+        // the old windows.
+        let mut windows = self.windows().await;
+        let old = self.snapshot();
+        // Detectors hold compiled regexes and scripts, which can't be
+        // compared; their Debug form shows the source.
+        let definitions = |rules: &[Rule]| -> HashMap<String, String> {
+            rules
+                .iter()
+                .filter(|r| r.condition_of.is_none())
+                .map(|r| (r.cfg.name.clone(), format!("{:?}", r.cfg)))
+                .collect()
+        };
+        let (before, after) = (definitions(&old), definitions(&rules));
+        let mut summary = RulesReload::default();
+        for rule in rules.iter().filter(|r| r.condition_of.is_none()) {
+            let name = &rule.cfg.name;
+            match before.get(name) {
+                None => summary.added.push(name.clone()),
+                Some(definition) if *definition != after[name] => {
+                    summary.changed.push(name.clone())
                 }
+                Some(_) => summary.unchanged += 1,
             }
-            x if x == EventType::Exec as u32 => {
-                state.exec_events.push_back(now);
-                trim_instant_queue(&mut state.exec_events, exec_keep, now);
-                state.exec_start.insert(event.pid, now);
+        }
+        for rule in old.iter().filter(|r| r.condition_of.is_none()) {
+            if !after.contains_key(&rule.cfg.name) {
+                summary.removed.push(rule.cfg.name.clone());
             }
-            x if x == EventType::Exit as u32 => {
-                if let Some(start) = state.exec_start.remove(&event.pid) {
-                    let lifetime = now.saturating_duration_since(start);
-                    state.exec_completions.push_back((now, lifetime));
-                    trim_completion_queue(&mut state.exec_completions, completion_keep, now);
-                }
+        }
+        // Unchanged rules, and their conditions, carry their state over;
+        // the rest start with fresh shards.
+        for rule in rules.iter_mut() {
+            let top = rule
+                .condition_of
+                .as_ref()
+                .map_or(&rule.cfg.name, |(name, _)| name);
+            if before
+                .get(top)
+                .is_some_and(|definition| *definition == after[top])
+                && let Some(previous) = old.iter().find(|r| r.cfg.name == rule.cfg.name)
+            {
+                rule.state = Arc::clone(&previous.state);
             }
-            _ => {}
         }
+        for name in &summary.removed {
+            self.metrics.unregister_rule(name);
+        }
+        windows.apply_spans(WindowSpans::for_rules(&rules));
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(rules);
+        drop(windows);
+        self.metrics.sub_active_rules(before.len());
+        self.metrics.add_active_rules(after.len());
+        Ok(summary)
+    }
 
-        let is_fork_event = event.event_type == EventType::Fork as u32;
-        let is_exec_event = event.event_type == EventType::Exec as u32;
-        let is_exit_event = event.event_type == EventType::Exit as u32;
+    /// Change a loaded rule's threshold and/or cooldown. With
+    /// `patch.persist` the rules file is rewritten first, so a failed write
+    /// leaves the running rule untouched.
+    pub async fn update_rule(
+        &self,
+        name: &str,
+        patch: &RulePatch,
+    ) -> Result<RuleView, RulePatchError> {
+        // A reload between the snapshot and the swap would be undone by it.
+        let _source = self.source_lock.lock().await;
+        let mut cfg = self
+            .snapshot()
+            .iter()
+            .find(|r| r.condition_of.is_none() && r.cfg.name == name)
+            .map(|r| r.cfg.clone())
+            .ok_or(RulePatchError::NotFound)?;
+        if let Some(threshold) = patch.threshold {
+            cfg.detector = cfg
+                .detector
+                .with_threshold(threshold)
+                .map_err(RulePatchError::Invalid)?;
+        }
+        if let Some(cooldown) = patch.cooldown {
+            cfg.cooldown = cooldown;
+        }
+        if let Some(mode) = patch.mode {
+            cfg.mode = mode;
+        }
+        if patch.persist {
+            let path = self.source.as_deref().ok_or_else(|| {
+                RulePatchError::Invalid("rules were not loaded from a file".into())
+            })?;
+            // Re-emitting YAML would drop its comments, so only TOML files
+            // are edited in place.
+            if is_yaml_path(path) {
+                return Err(RulePatchError::Invalid(format!(
+                    "{} is YAML, which can't be rewritten without losing its comments; \
+                     apply the change without persist, or move the rules to a TOML file",
+                    path.display()
+                )));
+            }
+            persist_rule(path, &cfg).map_err(RulePatchError::Persist)?;
+        }
 
-        for rule in &self.rules {
-            match &rule.cfg.detector {
+        let mut rules = self.rules.write().unwrap_or_else(|e| e.into_inner());
+        let mut updated = Vec::clone(&rules);
+        let rule = updated
+            .iter_mut()
+            .find(|r| r.condition_of.is_none() && r.cfg.name == name)
+            .ok_or(RulePatchError::NotFound)?;
+        rule.cfg = cfg;
+        let view = RuleView::from(&rule.cfg);
+        *rules = Arc::new(updated);
+        log::info!(
+            "[rules] rule '{}' updated: {}={} cooldown={}s mode={:?}{}",
+            view.name,
+            view.threshold_field,
+            view.threshold
+                .as_ref()
+                .map_or_else(|| "-".to_string(), |t| t.to_string()),
+            view.cooldown,
+            view.mode,
+            if patch.persist { " (persisted)" } else { "" }
+        );
+        Ok(view)
+    }
+
+    /// Current window counts against each rule's threshold. Counts are taken
+    /// at the time of the call, so entries that have aged out since the last
+    /// event are not included.
+    pub async fn window_stats(&self) -> WindowStats {
+        let now = Instant::now();
+        let windows = self.windows().await;
+        let secs = |start: Option<&Instant>| {
+            start.map_or(0.0, |s| now.saturating_duration_since(*s).as_secs_f64())
+        };
+        // Largest value over a rule's scope keys, and its key unless that
+        // is the single unnamed one.
+        let busiest = |state: &RuleShard, value: &dyn Fn(&ScopedCounters) -> f64| {
+            state
+                .scoped
+                .iter()
+                .map(|(key, counters)| (value(counters), key))
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map_or((0.0, None), |(value, key)| {
+                    (value, (!key.is_empty()).then(|| key.clone()))
+                })
+        };
+        let quick_completions = |window: Duration, max_lifetime: Duration| {
+            windows
+                .exec_completions
+                .iter()
+                .rev()
+                .take_while(|(ts, _, _)| now.duration_since(*ts) <= window)
+                .filter(|(_, lifetime, _)| *lifetime <= max_lifetime)
+                .count()
+        };
+
+        let mut rules = Vec::new();
+        for rule in self.snapshot().iter() {
+            let cfg = &rule.cfg;
+            let state = rule.state().await;
+            let window = match &cfg.detector {
                 Detector::ForksPerSec {
                     threshold,
                     duration,
                 } => {
-                    if is_fork_event {
-                        let duration_secs = *duration;
-                        let window = Duration::from_secs(duration_secs);
-                        let count = count_recent(&state.fork_events, window, now) as u64;
-                        let target = threshold.saturating_mul(duration_secs);
-                        if log::log_enabled!(log::Level::Debug) && count > 0 {
-                            let rate = if duration_secs > 0 {
-                                count as f32 / duration_secs as f32
-                            } else {
-                                0.0
-                            };
-                            log::debug!(
-                                "[rules] detector=forks_per_sec rule={} count={} target={} window={}s rate_per_sec={:.2} pid={} ppid={}",
-                                rule.cfg.name,
-                                count,
-                                target.max(*threshold),
-                                duration_secs,
-                                rate,
-                                event.pid,
-                                event.ppid
-                            );
-                        }
-                        if count >= target.max(*threshold) {
-                            drop(state);
-                            self.emit_alert(
-                                &rule.cfg,
-                                format!("fork rate exceeded {} per second", threshold),
-                            )
-                            .await;
-                            state = self.state.lock().await;
-                        }
-                    }
+                    let window = Duration::from_secs(*duration);
+                    let (count, key) = if cfg.keeps_own_counters() {
+                        busiest(&state, &|c| count_recent(&c.forks, window, now) as f64)
+                    } else {
+                        (self.recent_forks(&windows, window, now) as f64, None)
+                    };
+                    let target = threshold.saturating_mul(*duration).max(*threshold);
+                    RuleWindow::new(cfg, "forks", count, target as f64)
+                        .window(*duration)
+                        .key(key)
                 }
                 Detector::ForkBurst {
                     threshold,
                     window_seconds,
                 } => {
-                    if is_fork_event {
-                        let window_secs = *window_seconds;
-                        let window = Duration::from_secs(window_secs);
-                        let count = count_recent(&state.fork_events, window, now) as u64;
-                        if log::log_enabled!(log::Level::Debug) && count > 0 {
-                            log::debug!(
-                                "[rules] detector=fork_burst rule={} count={} threshold={} window={}s pid={} ppid={}",
-                                rule.cfg.name,
-                                count,
-                                threshold,
-                                window_secs,
-                                event.pid,
-                                event.ppid
-                            );
-                        }
-                        if count >= *threshold {
-                            drop(state);
-                            self.emit_alert(
-                                &rule.cfg,
-                                format!("fork burst: {} forks in {}s", count, window_seconds),
-                            )
-                            .await;
-                            state = self.state.lock().await;
-                        }
-                    }
+                    let window = Duration::from_secs(*window_seconds);
+                    let (count, key) = if cfg.keeps_own_counters() {
+                        busiest(&state, &|c| count_recent(&c.forks, window, now) as f64)
+                    } else {
+                        (self.recent_forks(&windows, window, now) as f64, None)
+                    };
+                    RuleWindow::new(cfg, "forks", count, *threshold as f64)
+                        .window(*window_seconds)
+                        .key(key)
                 }
-                Detector::ExecRate {
-                    rate_per_min,
-                    median_lifetime,
-                    ..
-                } => {
-                    if is_exec_event && state.exec_events.len() as u64 >= *rate_per_min {
-                        let mut durations: Vec<u64> = state
-                            .exec_completions
-                            .iter()
-                            .rev()
-                            .take_while(|(ts, _)| {
-                                now.duration_since(*ts) <= Duration::from_secs(60)
-                            })
-                            .map(|(_, lifetime)| lifetime.as_secs())
-                            .collect();
-                        if !durations.is_empty() {
-                            durations.sort_unstable();
-                            let median = durations[durations.len() / 2];
-                            if median <= *median_lifetime {
-                                drop(state);
-                                self.emit_alert(
-                                    &rule.cfg,
-                                    format!("exec rate exceeded {rate_per_min}/min"),
-                                )
-                                .await;
-                                state = self.state.lock().await;
-                                state.exec_events.clear();
-                                state.exec_completions.clear();
-                            }
-                        }
-                    }
+                Detector::ExecRate { rate_per_min, .. } => {
+                    let window = Duration::from_secs(60);
+                    let (count, key) = busiest(&state, &|c| {
+                        self.rule_execs(cfg, &c.execs, window, now) as f64
+                    });
+                    RuleWindow::new(cfg, "execs", count, *rate_per_min as f64)
+                        .window(60)
+                        .key(key)
                 }
                 Detector::ShortJobFlood {
                     threshold,
                     window_seconds,
                     max_exec_duration_ms,
                 } => {
-                    if is_exit_event {
-                        let window_secs = *window_seconds;
-                        let window = Duration::from_secs(window_secs);
-                        let max_duration = Duration::from_millis(*max_exec_duration_ms);
-                        let mut count = 0u64;
-                        for (ts, lifetime) in state.exec_completions.iter().rev() {
-                            if now.duration_since(*ts) > window {
-                                break;
-                            }
-                            if *lifetime <= max_duration {
-                                count += 1;
-                                if count >= *threshold {
-                                    drop(state);
-                                    self.emit_alert(
-                                        &rule.cfg,
-                                        format!(
-                                            "{} short-lived execs (<= {}ms) in {}s",
-                                            threshold, max_exec_duration_ms, window_seconds
-                                        ),
-                                    )
-                                    .await;
-                                    state = self.state.lock().await;
-                                    break;
-                                }
-                            }
-                        }
-                        if log::log_enabled!(log::Level::Debug) && count > 0 {
-                            log::debug!(
-                                "[rules] detector=short_job_flood rule={} count={} threshold={} window={}s max_exec_ms={} pid={}",
-                                rule.cfg.name,
-                                count,
-                                threshold,
-                                window_secs,
-                                max_exec_duration_ms,
-                                event.pid
-                            );
-                        }
-                    }
+                    let count = quick_completions(
+                        Duration::from_secs(*window_seconds),
+                        Duration::from_millis(*max_exec_duration_ms),
+                    );
+                    RuleWindow::new(cfg, "short_jobs", count as f64, *threshold as f64)
+                        .window(*window_seconds)
                 }
                 Detector::RunawayTree {
                     threshold,
                     window_seconds,
                 } => {
-                    if is_fork_event && let Some(queue) = state.forks_by_ppid.get(&event.ppid) {
-                        let window_secs = *window_seconds;
-                        let window = Duration::from_secs(window_secs);
-                        let count = queue
-                            .iter()
-                            .rev()
-                            .take_while(|ts| now.duration_since(**ts) <= window)
-                            .count() as u64;
-                        if log::log_enabled!(log::Level::Debug) && count > 0 {
-                            log::debug!(
-                                "[rules] detector=runaway_tree rule={} ppid={} count={} threshold={} window={}s",
-                                rule.cfg.name,
-                                event.ppid,
-                                count,
-                                threshold,
-                                window_secs
-                            );
-                        }
-                        if count >= *threshold {
-                            drop(state);
-                            self.emit_alert(
-                                &rule.cfg,
-                                format!(
-                                    "ppid {} spawned {} forks in {}s",
-                                    event.ppid, count, window_seconds
-                                ),
-                            )
-                            .await;
-                            state = self.state.lock().await;
-                        }
-                    }
+                    let window = Duration::from_secs(*window_seconds);
+                    let busiest = windows
+                        .forks_by_ppid
+                        .iter()
+                        .map(|(ppid, queue)| (count_recent(queue, window, now), *ppid))
+                        .filter(|(count, _)| *count > 0)
+                        .max();
+                    let count = busiest.map_or(0, |(count, _)| count);
+                    RuleWindow::new(cfg, "parent_forks", count as f64, *threshold as f64)
+                        .window(*window_seconds)
+                        .key(busiest.map(|(_, ppid)| format!("ppid {ppid}")))
                 }
-                Detector::SubtreeCpuPct {
+                Detector::DaemonCrashLoop {
                     threshold,
-                    duration,
+                    window_seconds,
+                    max_lifetime_secs,
+                    parent,
                 } => {
-                    if let Some(cpu) = event.cpu_percent() {
-                        if log::log_enabled!(log::Level::Debug) {
-                            log::debug!(
-                                "[rules] detector=subtree_cpu rule={} cpu={:.2}% threshold={} duration={}s pid={}",
-                                rule.cfg.name,
-                                cpu,
-                                threshold,
-                                duration,
-                                event.pid
-                            );
-                        }
-                        if cpu > *threshold {
-                            let entry =
-                                state.cpu_exceed.entry(rule.cfg.name.clone()).or_insert(now);
-                            if now.duration_since(*entry) > Duration::from_secs(*duration) {
-                                state.cpu_exceed.remove(&rule.cfg.name);
-                                drop(state);
-                                self.emit_alert(
-                                    &rule.cfg,
-                                    format!("cpu pct {threshold} over {duration}s"),
-                                )
-                                .await;
-                                state = self.state.lock().await;
-                            }
-                        } else {
-                            state.cpu_exceed.remove(&rule.cfg.name);
-                        }
-                    }
+                    let window = Duration::from_secs(*window_seconds);
+                    let max_lifetime = Duration::from_secs(*max_lifetime_secs);
+                    let worst = windows
+                        .crash_loops
+                        .iter()
+                        .flat_map(CrashLoopTracker::histories)
+                        .filter(|(_, h)| parent == "*" || h.parent == *parent)
+                        .map(|(key, h)| (h.quick_exits(window, max_lifetime, now).count(), key))
+                        .filter(|(count, _)| *count > 0)
+                        .max_by_key(|(count, _)| *count);
+                    let count = worst.map_or(0, |(count, _)| count);
+                    RuleWindow::new(cfg, "quick_exits", count as f64, *threshold as f64)
+                        .window(*window_seconds)
+                        .key(worst.map(|(_, key)| key.binary.clone()))
+                }
+                Detector::SubtreeCpuPct { duration, .. } => {
+                    let (breaching, key) = busiest(&state, &|c| {
+                        secs(c.cpu_exceed.values().map(|(start, _)| start).min())
+                    });
+                    RuleWindow::new(cfg, "breach_seconds", breaching, *duration as f64)
+                        .window(*duration)
+                        .key(key)
+                }
+                Detector::SubtreeRssMb { duration, .. } => {
+                    let (breaching, key) = busiest(&state, &|c| secs(c.rss_exceed.as_ref()));
+                    RuleWindow::new(cfg, "breach_seconds", breaching, *duration as f64)
+                        .window(*duration)
+                        .key(key)
+                }
+                Detector::SystemPsiCpu { duration, .. }
+                | Detector::SystemPsiMemory { duration, .. }
+                | Detector::SystemPsiIo { duration, .. }
+                | Detector::ThermalThrottling { duration, .. } => {
+                    let breaching = secs(state.breach_start.as_ref());
+                    RuleWindow::new(cfg, "breach_seconds", breaching, *duration as f64)
+                        .window(*duration)
+                }
+                Detector::CfsThrottling { duration, .. } => {
+                    let longest = state
+                        .throttle_breach
+                        .iter()
+                        .min_by_key(|(_, start)| **start);
+                    let breaching = secs(longest.map(|(_, start)| start));
+                    RuleWindow::new(cfg, "breach_seconds", breaching, *duration as f64)
+                        .window(*duration)
+                        .key(longest.map(|(cgroup, _)| cgroup.clone()))
+                }
+                Detector::Baseline { metric, sigma, .. } => {
+                    let deviation = self.baselines.as_ref().and_then(|b| b.deviation(*metric));
+                    let current = deviation.map_or(0.0, |d| d.sigmas().max(0.0));
+                    RuleWindow::new(cfg, "sigma", current, *sigma as f64)
+                }
+                Detector::GpuMemoryLeak { duration, .. } => {
+                    let growth = state.gpu_growth.as_ref();
+                    let growing = growth.map_or(0, |(_, secs)| *secs);
+                    RuleWindow::new(cfg, "breach_seconds", growing as f64, *duration as f64)
+                        .window(*duration)
+                        .key(growth.map(|(label, _)| label.clone()))
+                }
+                Detector::NewBinaryExecuted { learning_secs, .. }
+                | Detector::UnusualParent { learning_secs } => {
+                    let learned = windows
+                        .fingerprint
+                        .as_ref()
+                        .map_or(0, |f| f.age(now).as_secs().min(*learning_secs));
+                    RuleWindow::new(
+                        cfg,
+                        "learning_seconds",
+                        learned as f64,
+                        *learning_secs as f64,
+                    )
+                    .window(*learning_secs)
+                }
+                Detector::DirectReclaim {
+                    threshold_ms,
+                    window_seconds,
+                } => {
+                    let window = Duration::from_secs(*window_seconds);
+                    let worst = state
+                        .reclaim_stalls
+                        .iter()
+                        .map(|(pid, stalls)| (recent_total(stalls, window, now), *pid))
+                        .filter(|(ns, _)| *ns > 0)
+                        .max();
+                    let stalled_ms = worst.map_or(0, |(ns, _)| ns / 1_000_000);
+                    RuleWindow::new(cfg, "stall_ms", stalled_ms as f64, *threshold_ms as f64)
+                        .window(*window_seconds)
+                        .key(worst.map(|(_, pid)| format!("pid {pid}")))
                 }
-                Detector::SubtreeRssMb {
+                Detector::KillSweep {
                     threshold,
-                    duration,
+                    window_seconds,
                 } => {
-                    if let Some(mem_pct) = event.mem_percent() {
-                        let used_mb = if let Some(total_bytes) = self.total_memory_bytes {
-                            let used_bytes = (mem_pct as f64 / 100.0) * total_bytes as f64;
-                            let mb = used_bytes / (1024.0 * 1024.0);
-                            mb.clamp(0.0, u64::MAX as f64).round() as u64
-                        } else {
-                            mem_pct.round() as u64
-                        };
-                        if log::log_enabled!(log::Level::Debug) {
-                            log::debug!(
-                                "[rules] detector=subtree_rss rule={} mem_pct={:.2}% approx_mb={} threshold={} duration={}s pid={}",
-                                rule.cfg.name,
-                                mem_pct,
-                                used_mb,
-                                threshold,
-                                duration,
-                                event.pid
-                            );
-                        }
-                        if used_mb > *threshold {
-                            let entry =
-                                state.rss_exceed.entry(rule.cfg.name.clone()).or_insert(now);
-                            if now.duration_since(*entry) > Duration::from_secs(*duration) {
-                                state.rss_exceed.remove(&rule.cfg.name);
-                                drop(state);
-                                self.emit_alert(
-                                    &rule.cfg,
-                                    format!("rss mb {threshold} over {duration}s"),
-                                )
-                                .await;
-                                state = self.state.lock().await;
-                            }
-                        } else {
-                            state.rss_exceed.remove(&rule.cfg.name);
-                        }
-                    }
+                    let window = Duration::from_secs(*window_seconds);
+                    let busiest = state
+                        .kill_targets
+                        .iter()
+                        .map(|(pid, sent)| (distinct_targets(sent, window, now), *pid))
+                        .filter(|(count, _)| *count > 0)
+                        .max();
+                    let count = busiest.map_or(0, |(count, _)| count);
+                    RuleWindow::new(cfg, "signalled", count as f64, *threshold as f64)
+                        .window(*window_seconds)
+                        .key(busiest.map(|(_, pid)| format!("pid {pid}")))
                 }
-                Detector::ZombieCount { .. } => {}
-                // PSI detectors fire from on_snapshot, not on individual events.
-                Detector::SystemPsiCpu { .. }
-                | Detector::SystemPsiMemory { .. }
-                | Detector::SystemPsiIo { .. } => {}
+                Detector::BlockIoRate {
+                    threshold_mb_per_sec,
+                    window_seconds,
+                    per_process,
+                } => {
+                    let window = Duration::from_secs(*window_seconds);
+                    let busiest = state
+                        .block_io
+                        .iter()
+                        .map(|(key, queued)| (recent_total(queued, window, now), *key))
+                        .filter(|(bytes, _)| *bytes > 0)
+                        .max();
+                    let rate = busiest.map_or(0.0, |(bytes, _)| mib_per_sec(bytes, window));
+                    RuleWindow::new(cfg, "mb_per_sec", rate, *threshold_mb_per_sec as f64)
+                        .window(*window_seconds)
+                        .key(busiest.map(|(_, key)| block_io_key(key, *per_process)))
+                }
+                Detector::Composite {
+                    any,
+                    within_seconds,
+                    conditions,
+                } => {
+                    let within = Duration::from_secs(*within_seconds);
+                    let met = state
+                        .condition_hits
+                        .iter()
+                        .flatten()
+                        .filter(|(at, _)| now.duration_since(*at) <= within)
+                        .count();
+                    let needed = if *any { 1 } else { conditions.len() };
+                    RuleWindow::new(cfg, "conditions", met as f64, needed as f64)
+                        .window(*within_seconds)
+                }
+                Detector::ZombieCount { .. }
+                | Detector::PrivilegeEscalation { .. }
+                | Detector::SensitiveFileAccess { .. }
+                | Detector::KernelLoad { .. }
+                | Detector::OomKill { .. }
+                | Detector::SchedDelay { .. }
+                | Detector::Connection { .. }
+                | Detector::Script { .. }
+                | Detector::Expr { .. } => continue,
+            };
+            let cooldown = state
+                .active
+                .get("")
+                .map(|until| until.saturating_duration_since(now))
+                .filter(|left| !left.is_zero());
+            rules.push(RuleWindow {
+                cooldown_remaining_secs: cooldown.map(|left| left.as_secs_f64().ceil() as u64),
+                ..window
+            });
+        }
+
+        WindowStats {
+            forks: windows.fork_events.len(),
+            execs: windows.exec_events.len(),
+            completions: windows.exec_completions.len(),
+            tracked_parents: windows.forks_by_ppid.len(),
+            rules,
+        }
+    }
+
+    fn check_slow_rules(&self, watch: &mut SlowRuleWatch, now: Instant) {
+        if watch
+            .last_check
+            .is_some_and(|at| now.duration_since(at) < SLOW_RULE_CHECK_INTERVAL)
+        {
+            return;
+        }
+        watch.last_check = Some(now);
+        let rules = self.snapshot();
+        let current: Vec<u64> = rules.iter().map(|r| r.stats.eval_ns()).collect();
+        let deltas: Vec<u64> = current
+            .iter()
+            .enumerate()
+            .map(|(i, ns)| ns.saturating_sub(watch.last_ns.get(i).copied().unwrap_or(0)))
+            .collect();
+        watch.last_ns = current;
+        if let Some((idx, share)) = dominant_rule(&deltas) {
+            log::warn!(
+                "[rules] rule '{}' used {:.0}% of rule evaluation time over the last {}s ({} ms); see /rules/stats",
+                rules[idx].cfg.name,
+                share * 100.0,
+                SLOW_RULE_CHECK_INTERVAL.as_secs(),
+                deltas[idx] / 1_000_000
+            );
+        }
+    }
+
+    async fn emit_alert(&self, rule: &Rule, detection: impl Into<Detection>) {
+        self.emit(rule, detection.into(), None, "").await
+    }
+
+    /// Alert raised by `event`; names its login session when known.
+    async fn emit_event_alert(
+        &self,
+        rule: &Rule,
+        event: &ProcessEvent,
+        detection: impl Into<Detection>,
+    ) {
+        self.emit(rule, detection.into(), Some(event), "").await
+    }
+
+    /// Alert raised by `event` for one scope key of a rule. Each key has
+    /// its own cooldown, so one busy pod can't mute the rule for others.
+    async fn emit_scoped_alert(
+        &self,
+        rule: &Rule,
+        event: &ProcessEvent,
+        scope_key: &str,
+        detection: impl Into<Detection>,
+    ) {
+        self.emit(rule, detection.into(), Some(event), scope_key)
+            .await
+    }
+
+    async fn emit(
+        &self,
+        rule: &Rule,
+        detection: Detection,
+        trigger: Option<&ProcessEvent>,
+        scope_key: &str,
+    ) {
+        match &rule.condition_of {
+            Some((composite, index)) => {
+                self.condition_matched(composite, *index, detection.message, trigger)
+                    .await
+            }
+            None => self.fire(rule, detection, trigger, scope_key).await,
+        }
+    }
+
+    /// Record a match of condition `index` of the composite rule `name`,
+    /// and alert once the rule's conditions hold together.
+    async fn condition_matched(
+        &self,
+        name: &str,
+        index: usize,
+        message: String,
+        trigger: Option<&ProcessEvent>,
+    ) {
+        let rules = self.snapshot();
+        let Some(rule) = rules
+            .iter()
+            .find(|r| r.condition_of.is_none() && r.cfg.name == name)
+        else {
+            return;
+        };
+        let Detector::Composite {
+            any,
+            within_seconds,
+            conditions,
+        } = &rule.cfg.detector
+        else {
+            return;
+        };
+        let now = Instant::now();
+        let within = Duration::from_secs(*within_seconds);
+        let messages: Vec<String> = {
+            let mut state = rule.state().await;
+            let hits = &mut state.condition_hits;
+            if hits.len() != conditions.len() {
+                *hits = vec![None; conditions.len()];
+            }
+            let Some(hit) = hits.get_mut(index) else {
+                return;
+            };
+            *hit = Some((now, message));
+            let met = |hit: &Option<(Instant, String)>| {
+                hit.as_ref()
+                    .is_some_and(|(at, _)| now.duration_since(*at) <= within)
+            };
+            if !*any && !hits.iter().all(met) {
+                return;
+            }
+            hits.iter_mut()
+                .filter_map(|hit| {
+                    hit.take()
+                        .filter(|(at, _)| now.duration_since(*at) <= within)
+                })
+                .map(|(_, message)| message)
+                .collect()
+        };
+        self.fire(rule, messages.join(" and ").into(), trigger, "")
+            .await
+    }
+
+    async fn fire(
+        &self,
+        rule: &Rule,
+        detection: Detection,
+        trigger: Option<&ProcessEvent>,
+        scope_key: &str,
+    ) {
+        let Rule {
+            cfg: rule,
+            stats,
+            state,
+            ..
+        } = rule;
+        let Detection {
+            message,
+            mut labels,
+        } = detection;
+        let fingerprint = trigger
+            .filter(|_| !rule.fingerprint.is_empty())
+            .map(|event| self.fingerprint(&rule.fingerprint, event));
+        let key = match &fingerprint {
+            Some(fingerprint) => format!("{scope_key}|{fingerprint}"),
+            None => scope_key.to_string(),
+        };
+        let mut state = state.lock().await;
+        let now = Instant::now();
+        if let Some(firing) = state.firing.get_mut(&key) {
+            firing.last_match = now;
+        }
+        if let Some(until) = state.active.get(&key)
+            && now <= *until
+        {
+            if state.deduplicated.contains_key(&key)
+                || state.deduplicated.len() < MAX_ACTIVE_COOLDOWNS
+            {
+                *state.deduplicated.entry(key).or_default() += 1;
+            }
+            return;
+        }
+        if state.active.len() >= MAX_ACTIVE_COOLDOWNS {
+            state.active.retain(|_, until| now <= *until);
+        }
+        let cooldown = if rule.cooldown == 0 {
+            Duration::from_millis(100)
+        } else {
+            Duration::from_secs(rule.cooldown)
+        };
+        state.active.insert(key.clone(), now + cooldown);
+        let deduplicated = state.deduplicated.remove(&key).unwrap_or(0);
+        // Shadow matches go through the cooldown too, so their count reads
+        // like the alerts the rule would have sent.
+        if rule.mode == RuleMode::Shadow {
+            drop(state);
+            stats.inc_shadow_match();
+            log::info!("[rules] shadow rule={} would alert: {message}", rule.name);
+            return;
+        }
+        // A silenced alert still starts the cooldown, so it is counted
+        // once per cooldown rather than on every match.
+        if let Some(silence) = self.silences.silencing(&rule.name, chrono::Local::now()) {
+            drop(state);
+            self.metrics.inc_alerts_silenced();
+            log::info!(
+                "[rules] alert rule={} silenced by {silence}: {message}",
+                rule.name
+            );
+            return;
+        }
+        let mut severity = rule.severity.clone();
+        let mut escalated = None;
+        if let Some(escalate) = &rule.escalate {
+            let window = Duration::from_secs(escalate.within_minutes.saturating_mul(60));
+            if !state.fired_at.contains_key(&key) && state.fired_at.len() >= MAX_ACTIVE_COOLDOWNS {
+                state.fired_at.retain(|_, fired| {
+                    fired
+                        .back()
+                        .is_some_and(|t| now.duration_since(*t) <= window)
+                });
+            }
+            if state.fired_at.contains_key(&key) || state.fired_at.len() < MAX_ACTIVE_COOLDOWNS {
+                let fired = state.fired_at.entry(key.clone()).or_default();
+                while fired.front().is_some_and(|t| {
+                    now.duration_since(*t) > window || fired.len() > escalate.fires as usize
+                }) {
+                    fired.pop_front();
+                }
+                fired.push_back(now);
+                if fired.len() > escalate.fires as usize {
+                    severity = severity.escalated();
+                    escalated = Some(format!(
+                        "escalated: fired more than {} times in {}m",
+                        escalate.fires, escalate.within_minutes
+                    ));
+                }
+            }
+        }
+        labels.insert("detector".into(), rule.detector.kind().into());
+        if let Some(threshold) = rule.detector.threshold() {
+            labels
+                .entry("threshold".into())
+                .or_insert_with(|| threshold.to_string());
+        }
+        if !scope_key.is_empty() {
+            labels.insert("scope".into(), scope_key.to_string());
+        }
+        if let Some(fingerprint) = fingerprint {
+            labels.insert("fingerprint".into(), fingerprint);
+        }
+        if let Some(event) = trigger {
+            self.process_labels(event, &mut labels);
+        }
+        if let Some(hold) = rule.detector.hold_secs()
+            && rule.resolve_after > 0
+            && (state.firing.contains_key(&key) || state.firing.len() < MAX_ACTIVE_COOLDOWNS)
+        {
+            let firing = state.firing.entry(key).or_insert_with(|| Firing {
+                rule: rule.name.clone(),
+                severity: rule.severity.clone(),
+                message: String::new(),
+                labels: BTreeMap::new(),
+                scope_key: scope_key.to_string(),
+                since: now,
+                last_match: now,
+                quiet: Duration::from_secs(rule.resolve_after.max(hold.saturating_mul(2))),
+            });
+            firing.message = message.clone();
+            firing.labels = labels.clone();
+            firing.severity = severity.clone();
+        }
+        drop(state);
+        stats.inc_fired();
+
+        let message = match (trigger, &self.sessions) {
+            (Some(event), Some(sessions)) => match sessions.session_for(event.pid, event.ppid) {
+                Some(session) => format!("{message} (triggered by {})", session.describe()),
+                None => message,
+            },
+            _ => message,
+        };
+        let message = match scope_key {
+            "" => message,
+            scope => format!("{message} [{scope}]"),
+        };
+        let message = match escalated {
+            Some(note) => format!("{message} ({note})"),
+            None => message,
+        };
+        let message = match deduplicated {
+            0 => message,
+            n => {
+                labels.insert("deduplicated".into(), n.to_string());
+                format!("{message} ({n} duplicates suppressed since the last alert)")
+            }
+        };
+        let alert = Alert {
+            rule: rule.name.clone(),
+            severity,
+            message,
+            host: self.host.clone(),
+            resolved_after_secs: None,
+            labels,
+        };
+
+        log::info!(
+            "[rules] emitting alert rule={} severity={} message={}",
+            alert.rule,
+            alert.severity.as_str(),
+            alert.message
+        );
+
+        write_alert(&self.alerts_file, self.journald, &alert);
+
+        let _ = self.tx.send(alert);
+        self.metrics.inc_alerts_emitted();
+    }
+
+    /// The `fields` of the process behind `event`, e.g. `ppid=7 comm=bash`.
+    fn fingerprint(&self, fields: &[FingerprintField], event: &ProcessEvent) -> String {
+        let target = self.scopes.target(event);
+        fields
+            .iter()
+            .map(|field| match field {
+                FingerprintField::Pid => format!("pid={}", event.pid),
+                FingerprintField::Ppid => format!("ppid={}", event.ppid),
+                FingerprintField::Uid => format!("uid={}", event.uid),
+                FingerprintField::Comm => format!("comm={}", comm_of(event)),
+                FingerprintField::Container => {
+                    format!("container={}", target.container().unwrap_or("-"))
+                }
+                FingerprintField::Pod => match target.pod() {
+                    Some((namespace, pod)) => format!("pod={namespace}/{pod}"),
+                    None => "pod=-".to_string(),
+                },
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Labels naming the process behind `event`, and its pod when known.
+    fn process_labels(&self, event: &ProcessEvent, labels: &mut BTreeMap<String, String>) {
+        labels.insert("pid".into(), event.pid.to_string());
+        labels.insert("ppid".into(), event.ppid.to_string());
+        labels.insert("uid".into(), event.uid.to_string());
+        labels.insert("comm".into(), comm_of(event));
+        let target = self.scopes.target(event);
+        if let Some(container) = target.container() {
+            labels.insert("container".into(), container.to_string());
+        }
+        if let Some((namespace, pod)) = target.pod() {
+            labels.insert("namespace".into(), namespace.to_string());
+            labels.insert("pod".into(), pod.to_string());
+        }
+    }
+
+    /// Send a resolution for each fired alert whose condition has cleared.
+    async fn resolve_cleared(&self, now: Instant) {
+        let mut cleared = Vec::new();
+        for rule in self.snapshot().iter() {
+            cleared.extend(rule.state().await.take_cleared(now));
+        }
+        for (mut firing, deduplicated) in cleared {
+            let after = firing.last_match.duration_since(firing.since).as_secs();
+            if deduplicated > 0 {
+                firing
+                    .labels
+                    .insert("deduplicated".into(), deduplicated.to_string());
             }
+            let message = match firing.scope_key.as_str() {
+                "" => format!("resolved after {}: {}", format_span(after), firing.message),
+                scope => format!(
+                    "resolved after {}: {} [{scope}]",
+                    format_span(after),
+                    firing.message
+                ),
+            };
+            let alert = Alert {
+                rule: firing.rule,
+                severity: firing.severity,
+                message,
+                host: self.host.clone(),
+                resolved_after_secs: Some(after),
+                labels: firing.labels,
+            };
+            log::info!(
+                "[rules] resolving alert rule={} message={}",
+                alert.rule,
+                alert.message
+            );
+            write_alert(&self.alerts_file, self.journald, &alert);
+            let _ = self.tx.send(alert);
         }
     }
 
-    async fn on_snapshot(&self, snapshot: &SystemSnapshot) {
-        let now = Instant::now();
-        let mut state = self.state.lock().await;
+    /// Evaluate one rule against `event`, counting it under `scope_key`.
+    /// Returns as soon as the rule can't match, so the caller records the
+    /// evaluation however it ends.
+    async fn evaluate_event(
+        &self,
+        rule: &Rule,
+        event: &ProcessEvent,
+        scope_key: &str,
+        facts: &EventFacts,
+    ) {
+        use linnix_ai_ebpf_common::EventType;
+        let now = facts.now;
+        let is_fork_event = event.event_type == EventType::Fork as u32;
+        let is_exec_event = event.event_type == EventType::Exec as u32;
+        let is_exit_event = event.event_type == EventType::Exit as u32;
+
+        match &rule.cfg.detector {
+            Detector::ForksPerSec {
+                threshold,
+                duration,
+            } => {
+                if is_fork_event {
+                    let duration_secs = *duration;
+                    let window = Duration::from_secs(duration_secs);
+                    let count = if rule.cfg.keeps_own_counters() {
+                        self.scoped_forks(rule, &mut *rule.state().await, scope_key, window, now)
+                    } else {
+                        self.recent_forks(&*self.windows().await, window, now)
+                    };
+                    let target = threshold.saturating_mul(duration_secs);
+                    if log::log_enabled!(log::Level::Debug) && count > 0 {
+                        let rate = if duration_secs > 0 {
+                            count as f32 / duration_secs as f32
+                        } else {
+                            0.0
+                        };
+                        log::debug!(
+                            "[rules] detector=forks_per_sec rule={} count={} target={} window={}s rate_per_sec={:.2} pid={} ppid={}",
+                            rule.cfg.name,
+                            count,
+                            target.max(*threshold),
+                            duration_secs,
+                            rate,
+                            event.pid,
+                            event.ppid
+                        );
+                    }
+                    if count >= target.max(*threshold) {
+                        self.emit_scoped_alert(
+                            rule,
+                            event,
+                            scope_key,
+                            Detection::from(format!("fork rate exceeded {} per second", threshold))
+                                .label("value", count),
+                        )
+                        .await;
+                    }
+                }
+            }
+            Detector::ForkBurst {
+                threshold,
+                window_seconds,
+            } => {
+                if is_fork_event {
+                    let window_secs = *window_seconds;
+                    let window = Duration::from_secs(window_secs);
+                    let count = if rule.cfg.keeps_own_counters() {
+                        self.scoped_forks(rule, &mut *rule.state().await, scope_key, window, now)
+                    } else {
+                        self.recent_forks(&*self.windows().await, window, now)
+                    };
+                    if log::log_enabled!(log::Level::Debug) && count > 0 {
+                        log::debug!(
+                            "[rules] detector=fork_burst rule={} count={} threshold={} window={}s pid={} ppid={}",
+                            rule.cfg.name,
+                            count,
+                            threshold,
+                            window_secs,
+                            event.pid,
+                            event.ppid
+                        );
+                    }
+                    if count >= *threshold {
+                        self.emit_scoped_alert(
+                            rule,
+                            event,
+                            scope_key,
+                            Detection::from(format!(
+                                "fork burst: {} forks in {}s",
+                                count, window_seconds
+                            ))
+                            .label("value", count)
+                            .label("window_seconds", window_seconds),
+                        )
+                        .await;
+                    }
+                }
+            }
+            Detector::ExecRate {
+                regex,
+                rate_per_min,
+                median_lifetime,
+            } => {
+                let mut state = rule.state().await;
+                let window = Duration::from_secs(60);
+                if is_exit_event {
+                    if let Some(matched) = state
+                        .scoped
+                        .get_mut(scope_key)
+                        .map(|counters| &mut counters.execs)
+                        && let Some(start) = matched.running.remove(&event.pid)
+                    {
+                        matched
+                            .lifetimes
+                            .push_back((now, now.saturating_duration_since(start)));
+                        trim_lifetime_queue(&mut matched.lifetimes, window, now);
+                        cap_queue(&mut matched.lifetimes);
+                    }
+                    return;
+                }
+                if !is_exec_event || regex.as_ref().is_some_and(|re| !exec_matches(re, event)) {
+                    return;
+                }
+                let matched = &mut self
+                    .scoped_counters(rule, &mut state, scope_key, window, now)
+                    .execs;
+                matched.execs.push_back(now);
+                trim_instant_queue(&mut matched.execs, window, now);
+                let mut evicted = cap_queue(&mut matched.execs);
+                matched.running.insert(event.pid, now);
+                evicted += enforce_cap(
+                    &mut matched.running,
+                    self.max_tracked_execs,
+                    None,
+                    now,
+                    |start| Some(*start),
+                );
+                if evicted > 0 {
+                    self.metrics.add_detector_state_evictions(evicted);
+                }
+                let count = self.rule_execs(&rule.cfg, matched, window, now);
+                if count < *rate_per_min {
+                    return;
+                }
+                trim_lifetime_queue(&mut matched.lifetimes, window, now);
+                let mut lifetimes: Vec<u64> =
+                    matched.lifetimes.iter().map(|(_, l)| l.as_secs()).collect();
+                if lifetimes.is_empty() {
+                    return;
+                }
+                lifetimes.sort_unstable();
+                if lifetimes[lifetimes.len() / 2] > *median_lifetime {
+                    return;
+                }
+                matched.execs.clear();
+                matched.lifetimes.clear();
+                matched.reset = Some(now);
+                drop(state);
+                let execs = match regex {
+                    Some(regex) => format!("execs matching /{regex}/"),
+                    None => "execs".to_string(),
+                };
+                self.emit_scoped_alert(
+                    rule,
+                    event,
+                    scope_key,
+                    Detection::from(format!(
+                        "exec rate exceeded {rate_per_min}/min: {count} {execs} in 60s, last {}",
+                        comm_of(event)
+                    ))
+                    .label("value", count),
+                )
+                .await;
+            }
+            Detector::ShortJobFlood {
+                threshold,
+                window_seconds,
+                max_exec_duration_ms,
+            } => {
+                if is_exit_event {
+                    let windows = self.windows().await;
+                    let window_secs = *window_seconds;
+                    let window = Duration::from_secs(window_secs);
+                    let max_duration = Duration::from_millis(*max_exec_duration_ms);
+                    let mut count = 0u64;
+                    let mut statuses = Vec::new();
+                    for (ts, lifetime, status) in windows.exec_completions.iter().rev() {
+                        if now.duration_since(*ts) > window {
+                            break;
+                        }
+                        if *lifetime <= max_duration {
+                            count += 1;
+                            statuses.push(*status);
+                            if count >= *threshold {
+                                drop(windows);
+                                let mut message = format!(
+                                    "{} short-lived execs (<= {}ms) in {}s",
+                                    threshold, max_exec_duration_ms, window_seconds
+                                );
+                                // Crashing jobs and jobs that finish
+                                // cleanly but too often call for
+                                // different fixes.
+                                if statuses.iter().any(|s| *s != ExitStatus::Unknown) {
+                                    let failed = statuses.iter().filter(|s| s.is_failure()).count();
+                                    message.push_str(&format!(
+                                        "; {failed} failed, exits: {}",
+                                        crashloop::describe_statuses(statuses)
+                                    ));
+                                }
+                                let detection = Detection::from(message)
+                                    .label("value", count)
+                                    .label("window_seconds", window_seconds);
+                                self.emit_event_alert(rule, event, detection).await;
+                                break;
+                            }
+                        }
+                    }
+                    if log::log_enabled!(log::Level::Debug) && count > 0 {
+                        log::debug!(
+                            "[rules] detector=short_job_flood rule={} count={} threshold={} window={}s max_exec_ms={} pid={}",
+                            rule.cfg.name,
+                            count,
+                            threshold,
+                            window_secs,
+                            max_exec_duration_ms,
+                            event.pid
+                        );
+                    }
+                }
+            }
+            Detector::RunawayTree {
+                threshold,
+                window_seconds,
+            } => {
+                if !is_fork_event {
+                    return;
+                }
+                let windows = self.windows().await;
+                if let Some(queue) = windows.forks_by_ppid.get(&event.ppid) {
+                    let window_secs = *window_seconds;
+                    let window = Duration::from_secs(window_secs);
+                    let count = queue
+                        .iter()
+                        .rev()
+                        .take_while(|ts| now.duration_since(**ts) <= window)
+                        .count() as u64;
+                    if log::log_enabled!(log::Level::Debug) && count > 0 {
+                        log::debug!(
+                            "[rules] detector=runaway_tree rule={} ppid={} count={} threshold={} window={}s",
+                            rule.cfg.name,
+                            event.ppid,
+                            count,
+                            threshold,
+                            window_secs
+                        );
+                    }
+                    if count >= *threshold {
+                        drop(windows);
+                        self.emit_event_alert(
+                            rule,
+                            event,
+                            Detection::from(format!(
+                                "ppid {} spawned {} forks in {}s",
+                                event.ppid, count, window_seconds
+                            ))
+                            .label("value", count)
+                            .label("window_seconds", window_seconds),
+                        )
+                        .await;
+                    }
+                }
+            }
+            Detector::SubtreeCpuPct {
+                threshold,
+                duration,
+            } => {
+                if let Some(cpu) = event.cpu_percent() {
+                    // CPU of the process's subtree and each ancestor's,
+                    // the process's own first.
+                    let totals: Vec<(u32, f32)> = {
+                        let windows = self.windows().await;
+                        match windows.subtree_cpu.as_ref() {
+                            Some(tracker) => facts
+                                .subtree
+                                .iter()
+                                .map(|&pid| (pid, tracker.total(pid)))
+                                .collect(),
+                            None => vec![(event.pid, cpu)],
+                        }
+                    };
+                    if log::log_enabled!(log::Level::Debug) {
+                        log::debug!(
+                            "[rules] detector=subtree_cpu rule={} cpu={:.2}% subtree={:?} threshold={} duration={}s pid={}",
+                            rule.cfg.name,
+                            cpu,
+                            totals,
+                            threshold,
+                            duration,
+                            event.pid
+                        );
+                    }
+                    let breach = Duration::from_secs(*duration);
+                    let mut state = rule.state().await;
+                    let counters = self.scoped_counters(rule, &mut state, scope_key, breach, now);
+                    counters
+                        .cpu_exceed
+                        .retain(|_, (_, last)| now.duration_since(*last) <= SUBTREE_CPU_KEEP);
+                    for &(root, total) in &totals {
+                        if total > *threshold {
+                            counters.cpu_exceed.entry(root).or_insert((now, now)).1 = now;
+                        } else {
+                            counters.cpu_exceed.remove(&root);
+                        }
+                    }
+                    // The smallest subtree over the threshold for long
+                    // enough; its ancestors are over it for the same load.
+                    let breached = totals.iter().position(|(root, _)| {
+                        counters
+                            .cpu_exceed
+                            .get(root)
+                            .is_some_and(|(start, _)| now.duration_since(*start) > breach)
+                    });
+                    if let Some(at) = breached {
+                        for (root, _) in &totals[at..] {
+                            counters.cpu_exceed.remove(root);
+                        }
+                        drop(state);
+                        let (root, total) = totals[at];
+                        let comm = if root == event.pid {
+                            Some(comm_of(event))
+                        } else {
+                            crashloop::read_comm(root)
+                        };
+                        let root_name = match comm {
+                            Some(comm) if !comm.is_empty() => format!("{comm} (pid {root})"),
+                            _ => format!("pid {root}"),
+                        };
+                        self.emit_scoped_alert(
+                            rule,
+                            event,
+                            scope_key,
+                            Detection::from(format!(
+                                "subtree of {root_name} at {total:.1}% cpu, over {threshold}% for {duration}s"
+                            ))
+                            .label("value", format!("{total:.1}"))
+                            .label("subtree_pid", root),
+                        )
+                        .await;
+                    }
+                }
+            }
+            Detector::SubtreeRssMb {
+                threshold,
+                duration,
+            } => {
+                let mut state = rule.state().await;
+                // MemGrowth events carry the RSS itself, sampled during
+                // an allocation ramp rather than at the next exec or exit.
+                let growth = event.event_type == EventType::MemGrowth as u32 && event.data2 > 0;
+                let used_mb = if growth {
+                    Some(event.data2 / (1024 * 1024))
+                } else {
+                    event.mem_percent().map(|mem_pct| {
+                        if let Some(total_bytes) = self.total_memory_bytes {
+                            let used_bytes = (mem_pct as f64 / 100.0) * total_bytes as f64;
+                            let mb = used_bytes / (1024.0 * 1024.0);
+                            mb.clamp(0.0, u64::MAX as f64).round() as u64
+                        } else {
+                            mem_pct.round() as u64
+                        }
+                    })
+                };
+                if let Some(used_mb) = used_mb {
+                    if log::log_enabled!(log::Level::Debug) {
+                        log::debug!(
+                            "[rules] detector=subtree_rss rule={} approx_mb={} threshold={} duration={}s pid={}",
+                            rule.cfg.name,
+                            used_mb,
+                            threshold,
+                            duration,
+                            event.pid
+                        );
+                    }
+                    let breach = Duration::from_secs(*duration);
+                    let counters = self.scoped_counters(rule, &mut state, scope_key, breach, now);
+                    if breach_elapsed(&mut counters.rss_exceed, used_mb > *threshold, breach, now) {
+                        let mut message = format!("rss mb {threshold} over {duration}s");
+                        if growth {
+                            message.push_str(&format!(
+                                "; {} (pid {}) grew {} MiB in {} ms to {used_mb} MiB",
+                                comm_of(event),
+                                event.pid,
+                                event.data / (1024 * 1024),
+                                event.aux
+                            ));
+                        }
+                        drop(state);
+                        let detection = Detection::from(message).label("value", used_mb);
+                        self.emit_scoped_alert(rule, event, scope_key, detection)
+                            .await;
+                    }
+                }
+            }
+            Detector::Script {
+                script,
+                window_seconds,
+                event_types,
+            } => {
+                if !event_types.contains(&event.event_type) {
+                    return;
+                }
+                let span = Duration::from_secs(*window_seconds);
+                let short_job = Duration::from_millis(DEFAULT_SHORT_JOB_DURATION_MS);
+                let windows = self.windows().await;
+                let mut window = crate::script::Window {
+                    seconds: *window_seconds,
+                    forks: count_recent(&windows.fork_events, span, now) as u64,
+                    execs: count_recent(&windows.exec_events, span, now) as u64,
+                    parent_forks: windows
+                        .forks_by_ppid
+                        .get(&event.ppid)
+                        .map_or(0, |queue| count_recent(queue, span, now) as u64),
+                    ..Default::default()
+                };
+                for (ts, lifetime, _) in windows.exec_completions.iter().rev() {
+                    if now.duration_since(*ts) > span {
+                        break;
+                    }
+                    window.exits += 1;
+                    if *lifetime <= short_job {
+                        window.short_jobs += 1;
+                    }
+                }
+                drop(windows);
+                if let Some(message) = script.eval(&rule.cfg.name, event, &window) {
+                    self.emit_event_alert(rule, event, message).await;
+                }
+            }
+            Detector::Expr {
+                expr,
+                window_seconds,
+                event_types,
+            } => {
+                use crate::expr::{Aggregate, Counter, Key};
+                if !event_types.contains(&event.event_type) {
+                    return;
+                }
+                let span = Duration::from_secs(*window_seconds);
+                let short_job = Duration::from_millis(DEFAULT_SHORT_JOB_DURATION_MS);
+                let windows = self.windows().await;
+                let count = |aggregate: Aggregate| {
+                    let forks_of = |pid| {
+                        windows
+                            .forks_by_ppid
+                            .get(&pid)
+                            .map_or(0, |queue| count_recent(queue, span, now))
+                    };
+                    let n = match (aggregate.counter, aggregate.key) {
+                        (Counter::Forks, Key::Host) => {
+                            count_recent(&windows.fork_events, span, now)
+                        }
+                        (Counter::Forks, Key::Pid) => forks_of(event.pid),
+                        (Counter::Forks, Key::Ppid) => forks_of(event.ppid),
+                        (Counter::Execs, _) => count_recent(&windows.exec_events, span, now),
+                        (Counter::Exits | Counter::ShortJobs, _) => windows
+                            .exec_completions
+                            .iter()
+                            .rev()
+                            .take_while(|(ts, _, _)| now.duration_since(*ts) <= span)
+                            .filter(|(_, lifetime, _)| {
+                                aggregate.counter == Counter::Exits || *lifetime <= short_job
+                            })
+                            .count(),
+                    };
+                    n as u64
+                };
+                let values = expr.eval(&rule.cfg.name, event, *window_seconds, count);
+                drop(windows);
+                let Some(values) = values else {
+                    return;
+                };
+                let mut message = format!(
+                    "{} (pid {}, parent {}) matched {}",
+                    comm_of(event),
+                    event.pid,
+                    event.ppid,
+                    expr.source()
+                );
+                let mut labels = Vec::new();
+                for (i, (aggregate, value)) in values.iter().enumerate() {
+                    message.push_str(if i == 0 { "; " } else { ", " });
+                    message.push_str(&format!("{aggregate} = {value}"));
+                    labels.push((aggregate.to_string(), value.to_string()));
+                }
+                let detection = labels
+                    .into_iter()
+                    .fold(Detection::from(message), |d, (k, v)| d.label(&k, v));
+                self.emit_event_alert(rule, event, detection).await;
+            }
+            Detector::DaemonCrashLoop {
+                threshold,
+                window_seconds,
+                max_lifetime_secs,
+                parent,
+            } => {
+                let Some(key) = &facts.crash_key else {
+                    return;
+                };
+                let windows = self.windows().await;
+                let Some(history) = windows.crash_loops.as_ref().and_then(|t| t.history(key))
+                else {
+                    return;
+                };
+                if parent != "*" && history.parent != *parent {
+                    return;
+                }
+                let exits: Vec<_> = history
+                    .quick_exits(
+                        Duration::from_secs(*window_seconds),
+                        Duration::from_secs(*max_lifetime_secs),
+                        now,
+                    )
+                    .copied()
+                    .collect();
+                if exits.len() as u64 >= *threshold {
+                    let message = format!(
+                        "daemon crash loop: {} under {} (pid {}) exited {} times within {}s of start in the last {}s; exits: {}",
+                        key.binary,
+                        if history.parent.is_empty() {
+                            "unknown parent"
+                        } else {
+                            history.parent.as_str()
+                        },
+                        key.ppid,
+                        exits.len(),
+                        max_lifetime_secs,
+                        window_seconds,
+                        crashloop::describe_exits(&exits)
+                    );
+                    let detection = Detection::from(message)
+                        .label("value", exits.len())
+                        .label("binary", &key.binary);
+                    drop(windows);
+                    self.emit_event_alert(rule, event, detection).await;
+                }
+            }
+            Detector::NewBinaryExecuted {
+                learning_secs,
+                match_argv,
+            } => {
+                let Some((comm, parent, novelty, learned)) = &facts.exec_novelty else {
+                    return;
+                };
+                if learned.as_secs() < *learning_secs {
+                    return;
+                }
+                let what = if novelty.new_binary {
+                    "new binary"
+                } else if *match_argv && novelty.new_argv {
+                    "new command line for"
+                } else {
+                    return;
+                };
+                let message = format!(
+                    "{what} {comm} executed (pid {}) under {}; not seen in the first {}s of observation",
+                    event.pid,
+                    parent_label(parent, event.ppid),
+                    learning_secs
+                );
+                self.emit_event_alert(rule, event, message).await;
+            }
+            Detector::UnusualParent { learning_secs } => {
+                let Some((comm, parent, novelty, learned)) = &facts.exec_novelty else {
+                    return;
+                };
+                if !novelty.unusual_parent || learned.as_secs() < *learning_secs {
+                    return;
+                }
+                let windows = self.windows().await;
+                let usual: Vec<&str> = windows
+                    .fingerprint
+                    .as_ref()
+                    .map(|f| f.parents_of(comm))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|p| p != parent)
+                    .collect();
+                let message = format!(
+                    "unusual parent: {comm} (pid {}) started by {}; usually started by {}",
+                    event.pid,
+                    parent_label(parent, event.ppid),
+                    usual.join(", ")
+                );
+                drop(windows);
+                self.emit_event_alert(rule, event, message).await;
+            }
+            Detector::OomKill { min_rss_mb } => {
+                if event.event_type != EventType::OomKill as u32 {
+                    return;
+                }
+                let rss_mb = event.data / (1024 * 1024);
+                if rss_mb < *min_rss_mb {
+                    return;
+                }
+                let message = format!(
+                    "OOM killer killed {} (pid {}, parent {}) holding {} MiB RSS; {}, triggered by pid {}",
+                    comm_of(event),
+                    event.pid,
+                    event.ppid,
+                    rss_mb,
+                    if event.aux == 1 {
+                        "cgroup memory limit"
+                    } else {
+                        "system-wide memory shortage"
+                    },
+                    event.data2
+                );
+                let detection = Detection::from(message).label("value", rss_mb);
+                self.emit_event_alert(rule, event, detection).await;
+            }
+            Detector::SchedDelay { threshold_ms } => {
+                if event.event_type != EventType::SchedLatency as u32 {
+                    return;
+                }
+                let delay_ms = event.data / 1_000_000;
+                if delay_ms < *threshold_ms {
+                    return;
+                }
+                let message = format!(
+                    "{} (pid {}, thread {}) waited {} ms on the runqueue of CPU {} before running",
+                    comm_of(event),
+                    event.pid,
+                    event.data2,
+                    delay_ms,
+                    event.aux
+                );
+                let detection = Detection::from(message)
+                    .label("value", delay_ms)
+                    .label("cpu", event.aux);
+                self.emit_event_alert(rule, event, detection).await;
+            }
+            Detector::DirectReclaim {
+                threshold_ms,
+                window_seconds,
+            } => {
+                if event.event_type != EventType::DirectReclaim as u32 {
+                    return;
+                }
+                let window = Duration::from_secs(*window_seconds);
+                let max_pids = self.max_tracked_ppids;
+                let mut state = rule.state().await;
+                let by_pid = &mut state.reclaim_stalls;
+                if !by_pid.contains_key(&event.pid) && by_pid.len() >= max_pids {
+                    by_pid.retain(|_, stalls| recent_total(stalls, window, now) > 0);
+                    if by_pid.len() >= max_pids {
+                        return;
+                    }
+                }
+                let stalls = by_pid.entry(event.pid).or_default();
+                while stalls
+                    .front()
+                    .is_some_and(|(t, _)| now.duration_since(*t) > window)
+                {
+                    stalls.pop_front();
+                }
+                stalls.push_back((now, event.data));
+                let stalled_ms = recent_total(stalls, window, now) / 1_000_000;
+                if stalled_ms < *threshold_ms {
+                    return;
+                }
+                let count = stalls.len();
+                stalls.clear();
+                let message = format!(
+                    "{} (pid {}) stalled {} ms in direct reclaim over the last {}s ({} stalls, latest reclaimed {} pages at order {})",
+                    comm_of(event),
+                    event.pid,
+                    stalled_ms,
+                    window_seconds,
+                    count,
+                    event.data2,
+                    event.aux
+                );
+                let detection = Detection::from(message)
+                    .label("value", stalled_ms)
+                    .label("window_seconds", window_seconds);
+                drop(state);
+                self.emit_event_alert(rule, event, detection).await;
+            }
+            Detector::KillSweep {
+                threshold,
+                window_seconds,
+            } => {
+                let Some(sent) = SignalSent::from_event(event) else {
+                    return;
+                };
+                if sent.from_kernel || sent.target == event.pid {
+                    return;
+                }
+                let window = Duration::from_secs(*window_seconds);
+                let max_pids = self.max_tracked_ppids;
+                let mut state = rule.state().await;
+                let by_sender = &mut state.kill_targets;
+                if !by_sender.contains_key(&event.pid) && by_sender.len() >= max_pids {
+                    by_sender.retain(|_, sent| distinct_targets(sent, window, now) > 0);
+                    if by_sender.len() >= max_pids {
+                        return;
+                    }
+                }
+                let targets = by_sender.entry(event.pid).or_default();
+                while targets.front().is_some_and(|(t, _)| {
+                    now.duration_since(*t) > window || targets.len() >= MAX_SIGNALS_PER_SENDER
+                }) {
+                    targets.pop_front();
+                }
+                targets.push_back((now, sent.target));
+                let count = distinct_targets(targets, window, now);
+                if (count as u64) < *threshold {
+                    return;
+                }
+                targets.clear();
+                let message = format!(
+                    "{} (pid {}) signalled {} processes in {}s, latest {}",
+                    comm_of(event),
+                    event.pid,
+                    count,
+                    window_seconds,
+                    sent
+                );
+                let detection = Detection::from(message)
+                    .label("value", count)
+                    .label("window_seconds", window_seconds);
+                drop(state);
+                self.emit_event_alert(rule, event, detection).await;
+            }
+            Detector::BlockIoRate {
+                threshold_mb_per_sec,
+                window_seconds,
+                per_process,
+            } => {
+                if event.event_type != EventType::BlockIo as u32
+                    || event.aux != BlockOp::Queue as u32
+                {
+                    return;
+                }
+                let window = Duration::from_secs(*window_seconds);
+                let key = if *per_process { event.pid } else { event.aux2 };
+                let max_keys = self.max_tracked_ppids;
+                let mut state = rule.state().await;
+                let by_key = &mut state.block_io;
+                if !by_key.contains_key(&key) && by_key.len() >= max_keys {
+                    by_key.retain(|_, queued| recent_total(queued, window, now) > 0);
+                    if by_key.len() >= max_keys {
+                        return;
+                    }
+                }
+                let queued = by_key.entry(key).or_default();
+                while queued.front().is_some_and(|(t, _)| {
+                    now.duration_since(*t) > window || queued.len() >= MAX_BLOCK_IO_PER_KEY
+                }) {
+                    queued.pop_front();
+                }
+                queued.push_back((now, event.data));
+                let bytes = recent_total(queued, window, now);
+                let rate = mib_per_sec(bytes, window);
+                if rate < *threshold_mb_per_sec as f64 {
+                    return;
+                }
+                queued.clear();
+                let (major, minor) = decode_block_dev(event.aux2);
+                let message = if *per_process {
+                    format!(
+                        "{} (pid {}) queued {:.1} MB/s of block I/O over {}s, latest on dev {major}:{minor}",
+                        comm_of(event),
+                        event.pid,
+                        rate,
+                        window_seconds
+                    )
+                } else {
+                    format!(
+                        "block I/O on dev {major}:{minor} at {:.1} MB/s over {}s, latest from {} (pid {})",
+                        rate,
+                        window_seconds,
+                        comm_of(event),
+                        event.pid
+                    )
+                };
+                let detection = Detection::from(message)
+                    .label("value", format!("{rate:.1}"))
+                    .label("device", format!("{major}:{minor}"))
+                    .label("window_seconds", window_seconds);
+                drop(state);
+                self.emit_event_alert(rule, event, detection).await;
+            }
+            Detector::PrivilegeEscalation {
+                comm,
+                ancestor,
+                ignore,
+            } => {
+                let Some(change) = CredChange::from_event(event) else {
+                    return;
+                };
+                if !change.is_escalation() {
+                    return;
+                }
+                let name = comm_of(event);
+                if (comm != "*" && name != *comm) || ignore.contains(&name) {
+                    return;
+                }
+                if ancestor != "*" && !descends_from(event.ppid, ancestor) {
+                    return;
+                }
+                let parent_comm = crashloop::read_comm(event.ppid).unwrap_or_default();
+                let message = format!(
+                    "privilege escalation: {name} (pid {}, parent {}) {change}",
+                    event.pid,
+                    parent_label(&parent_comm, event.ppid),
+                );
+                self.emit_event_alert(rule, event, message).await;
+            }
+            Detector::SensitiveFileAccess { paths, ops, ignore } => {
+                let Some(access) = FileAccess::from_event(event) else {
+                    return;
+                };
+                if !ops.contains(&access.op) {
+                    return;
+                }
+                // Without the path (evicted before it was read) there's
+                // nothing to match on.
+                let Some(path) = access.path.as_deref() else {
+                    return;
+                };
+                if !paths.iter().any(|p| is_under(path, p)) {
+                    return;
+                }
+                let name = comm_of(event);
+                if ignore.contains(&name) {
+                    return;
+                }
+                let parent_comm = crashloop::read_comm(event.ppid).unwrap_or_default();
+                let message = format!(
+                    "sensitive file access: {name} (pid {}, parent {}) {access}",
+                    event.pid,
+                    parent_label(&parent_comm, event.ppid),
+                );
+                self.emit_event_alert(rule, event, message).await;
+            }
+            Detector::KernelLoad {
+                kind,
+                ignore,
+                ignore_names,
+            } => {
+                let Some(load) = KernelLoad::from_event(event) else {
+                    return;
+                };
+                if kind.is_some_and(|k| k != load.kind) || ignore_names.contains(&load.name) {
+                    return;
+                }
+                let name = comm_of(event);
+                if ignore.contains(&name) {
+                    return;
+                }
+                let parent_comm = crashloop::read_comm(event.ppid).unwrap_or_default();
+                let message = format!(
+                    "kernel load: {name} (pid {}, parent {}) {load}",
+                    event.pid,
+                    parent_label(&parent_comm, event.ppid),
+                );
+                self.emit_event_alert(rule, event, message).await;
+            }
+            Detector::Connection {
+                direction,
+                comm,
+                parent,
+                port,
+            } => {
+                let Some(conn) = Connection::from_event(event) else {
+                    return;
+                };
+                if direction.is_some_and(|d| d != conn.direction) {
+                    return;
+                }
+                let conn_port = conn.local_port.unwrap_or(conn.remote.port());
+                if *port != 0 && conn_port != *port {
+                    return;
+                }
+                let name = comm_of(event);
+                if comm != "*" && name != *comm {
+                    return;
+                }
+                let parent_comm = crashloop::read_comm(event.ppid).unwrap_or_default();
+                if parent != "*" && parent_comm != *parent {
+                    return;
+                }
+                let message = format!(
+                    "connection: {name} (pid {}, parent {}) {conn}",
+                    event.pid,
+                    parent_label(&parent_comm, event.ppid),
+                );
+                self.emit_event_alert(rule, event, message).await;
+            }
+            Detector::ZombieCount { .. } => {}
+            // Filtered out by `runs_on_events`.
+            Detector::SystemPsiCpu { .. }
+            | Detector::SystemPsiMemory { .. }
+            | Detector::SystemPsiIo { .. }
+            | Detector::CfsThrottling { .. }
+            | Detector::ThermalThrottling { .. }
+            | Detector::GpuMemoryLeak { .. }
+            | Detector::Baseline { .. }
+            | Detector::Composite { .. } => {}
+        }
+    }
+}
+
+/// `secs` as e.g. `45s`, `4m`, `4m10s` or `2h5m`.
+fn format_span(secs: u64) -> String {
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, 0) => format!("{m}m"),
+        (0, m, s) => format!("{m}m{s}s"),
+        (h, 0, _) => format!("{h}h"),
+        (h, m, _) => format!("{h}h{m}m"),
+    }
+}
+
+/// Append `alert` to the alerts file (and the journal); shared by every
+/// alert source so the log stays complete.
+pub(crate) fn write_alert(alerts_file: &str, journald: bool, alert: &Alert) {
+    if journald && let Err(e) = crate::journald::send(Path::new(crate::journald::SOCKET), alert) {
+        log::debug!("[rules] failed to write alert to journald: {e}");
+    }
+
+    let line = AlertLine {
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        alert,
+    };
+    if let Ok(line) = serde_json::to_string(&line) {
+        if let Some(dir) = std::path::Path::new(alerts_file).parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Ok(mut f) = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(alerts_file)
+        {
+            let _ = writeln!(f, "{line}");
+        }
+    }
+}
+
+enum RuleFormat {
+    Toml,
+    Yaml,
+}
+
+impl RuleFormat {
+    fn as_str(&self) -> &'static str {
+        match self {
+            RuleFormat::Toml => "toml",
+            RuleFormat::Yaml => "yaml",
+        }
+    }
+}
+
+/// Parse a rules file. `hint` is the file extension and decides whether YAML
+/// or TOML is tried first.
+pub fn parse_rules(text: &str, hint: Option<&str>) -> anyhow::Result<Vec<RuleConfig>> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let preferred = match hint.map(|h| h.to_ascii_lowercase()) {
+        Some(ref h) if matches!(h.as_str(), "yaml" | "yml") => {
+            vec![RuleFormat::Yaml, RuleFormat::Toml]
+        }
+        Some(ref h) if h == "toml" => vec![RuleFormat::Toml, RuleFormat::Yaml],
+        _ => vec![RuleFormat::Toml, RuleFormat::Yaml],
+    };
+
+    let mut errors: Vec<(RuleFormat, anyhow::Error)> = Vec::new();
+
+    for format in preferred {
+        match parse_rules_with_format(text, &format) {
+            Ok(cfgs) => return Ok(cfgs),
+            Err(err) => errors.push((format, err)),
+        }
+    }
+
+    let joined = errors
+        .into_iter()
+        .map(|(fmt, err)| format!("{}: {}", fmt.as_str(), err))
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(anyhow!("failed to parse rules: {joined}"))
+}
+
+fn parse_rules_with_format(text: &str, format: &RuleFormat) -> anyhow::Result<Vec<RuleConfig>> {
+    let raw = match format {
+        RuleFormat::Toml => {
+            parse_rules_from_toml(text).with_context(|| "failed to parse rules file as TOML")?
+        }
+        RuleFormat::Yaml => {
+            parse_rules_from_yaml(text).with_context(|| "failed to parse rules file as YAML")?
+        }
+    };
+    raw.into_iter().map(RuleConfig::try_from).collect()
+}
+
+fn parse_rules_from_yaml(text: &str) -> Result<Vec<RawRule>, serde_yaml::Error> {
+    // Try the usual bare list first so its (field-level) error is the one
+    // reported when neither shape matches.
+    serde_yaml::from_str::<Vec<RawRule>>(text).or_else(|err| {
+        serde_yaml::from_str::<RulesDocument>(text)
+            .map(RulesDocument::into_rules)
+            .map_err(|_| err)
+    })
+}
+
+fn parse_rules_from_toml(text: &str) -> Result<Vec<RawRule>, toml::de::Error> {
+    toml::from_str::<RulesDocument>(text).map(RulesDocument::into_rules)
+}
+
+/// Rewrite one rule's threshold, cooldown and mode in a TOML rules file.
+/// Only that entry changes; comments and the rest of the file are kept.
+fn persist_rule(path: &Path, cfg: &RuleConfig) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut doc: toml_edit::DocumentMut = text
+        .parse()
+        .with_context(|| format!("{} is not a TOML rules file", path.display()))?;
+    let entry = doc
+        .get_mut("rules")
+        .and_then(|v| v.as_array_of_tables_mut())
+        .ok_or_else(|| anyhow!("rules file has no [[rules]] array"))?
+        .iter_mut()
+        .find(|r| r.get("name").and_then(|n| n.as_str()) == Some(cfg.name.as_str()))
+        .ok_or_else(|| anyhow!("rule '{}' not found in {}", cfg.name, path.display()))?;
+    if let Some(threshold) = cfg.detector.threshold() {
+        let value = match threshold.as_i64() {
+            Some(n) => toml_edit::Value::from(n),
+            None => toml_edit::Value::from(threshold.as_f64().unwrap_or_default()),
+        };
+        set_toml_value(entry, cfg.detector.threshold_field(), value);
+    }
+    set_toml_value(entry, "cooldown", (cfg.cooldown as i64).into());
+    match cfg.mode {
+        RuleMode::Shadow => set_toml_value(entry, "mode", "shadow".into()),
+        RuleMode::Enforce => drop(entry.remove("mode")),
+    }
+    let updated = doc.to_string();
+
+    // Write beside the original and rename so a crash can't truncate it.
+    // The name is unique to this file and call: rules.yaml and rules.toml
+    // (or two concurrent patches) must not share a temp file.
+    static PERSISTS: AtomicU64 = AtomicU64::new(0);
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file", path.display()))?;
+    let tmp = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        PERSISTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, updated).with_context(|| format!("failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Replace `key`'s value in `entry`, keeping the comments around it.
+fn set_toml_value(entry: &mut toml_edit::Table, key: &str, value: toml_edit::Value) {
+    match entry.get_mut(key).and_then(|item| item.as_value_mut()) {
+        Some(old) => {
+            let decor = old.decor().clone();
+            *old = value;
+            *old.decor_mut() = decor;
+        }
+        None => {
+            entry.insert(key, toml_edit::Item::Value(value));
+        }
+    }
+}
+
+fn is_yaml_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "yaml" | "yml"))
+}
+
+fn trim_instant_queue(queue: &mut VecDeque<Instant>, keep_for: Duration, now: Instant) {
+    while let Some(&front) = queue.front() {
+        if now.duration_since(front) > keep_for {
+            queue.pop_front();
+        } else {
+            break;
+        }
+    }
+}
+
+/// Drop the oldest entries once a per-key window queue exceeds
+/// `MAX_WINDOW_EVENTS`.
+fn cap_queue<T>(queue: &mut VecDeque<T>) -> u64 {
+    let excess = queue.len().saturating_sub(MAX_WINDOW_EVENTS);
+    queue.drain(..excess);
+    excess as u64
+}
+
+/// Shrink a per-key map to at most `cap` entries.
+///
+/// Entries idle for longer than `keep_for` are dropped first (plain garbage
+/// collection, not counted). If the map is still over the cap, the least
+/// recently seen entries are evicted down to 7/8 of `cap` so the scan is
+/// amortised across many inserts. Returns the number of evictions.
+fn enforce_cap<K: Clone + Eq + std::hash::Hash, V>(
+    map: &mut HashMap<K, V>,
+    cap: usize,
+    keep_for: Option<Duration>,
+    now: Instant,
+    last_seen: impl Fn(&V) -> Option<Instant>,
+) -> u64 {
+    if map.len() <= cap {
+        return 0;
+    }
+    if let Some(keep_for) = keep_for {
+        map.retain(|_, v| last_seen(v).is_some_and(|ts| now.duration_since(ts) <= keep_for));
+        if map.len() <= cap {
+            return 0;
+        }
+    }
+    let target = cap - cap / 8;
+    let mut by_age: Vec<(Instant, K)> = map
+        .iter()
+        .map(|(k, v)| (last_seen(v).unwrap_or(now), k.clone()))
+        .collect();
+    by_age.sort_unstable_by_key(|(ts, _)| *ts);
+    let excess = map.len() - target;
+    for (_, key) in by_age.into_iter().take(excess) {
+        map.remove(&key);
+    }
+    excess as u64
+}
+
+/// Track a breach of a threshold that started at `start`: true, and the
+/// breach forgotten, once it has lasted longer than `duration`.
+fn breach_elapsed(
+    start: &mut Option<Instant>,
+    breaching: bool,
+    duration: Duration,
+    now: Instant,
+) -> bool {
+    if !breaching {
+        *start = None;
+        return false;
+    }
+    let since = *start.get_or_insert(now);
+    if now.duration_since(since) > duration {
+        *start = None;
+        return true;
+    }
+    false
+}
+
+fn trim_lifetime_queue(
+    queue: &mut VecDeque<(Instant, Duration)>,
+    keep_for: Duration,
+    now: Instant,
+) {
+    while queue
+        .front()
+        .is_some_and(|&(ts, _)| now.duration_since(ts) > keep_for)
+    {
+        queue.pop_front();
+    }
+}
+
+fn trim_completion_queue(
+    queue: &mut VecDeque<(Instant, Duration, ExitStatus)>,
+    keep_for: Duration,
+    now: Instant,
+) {
+    while let Some(&(ts, _, _)) = queue.front() {
+        if now.duration_since(ts) > keep_for {
+            queue.pop_front();
+        } else {
+            break;
+        }
+    }
+}
+
+/// `bash (pid 42)`, or just the pid when the parent's comm is unknown.
+fn parent_label(comm: &str, ppid: u32) -> String {
+    if comm.is_empty() {
+        format!("pid {ppid}")
+    } else {
+        format!("{comm} (pid {ppid})")
+    }
+}
+
+/// Whether `pid` or one of its ancestors is named `comm`, walking
+/// `/proc` up to init.
+fn descends_from(mut pid: u32, comm: &str) -> bool {
+    for _ in 0..MAX_ANCESTOR_DEPTH {
+        if pid <= 1 {
+            break;
+        }
+        if crashloop::read_comm(pid).is_some_and(|c| c == comm) {
+            return true;
+        }
+        match crashloop::read_ppid(pid) {
+            Some(ppid) => pid = ppid,
+            None => break,
+        }
+    }
+    false
+}
+
+fn comm_of(event: &ProcessEvent) -> String {
+    String::from_utf8_lossy(&event.comm)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// Whether an exec's comm, or its argv joined with spaces, matches `regex`.
+fn exec_matches(regex: &Regex, event: &ProcessEvent) -> bool {
+    regex.is_match(&comm_of(event))
+        || event
+            .argv
+            .as_ref()
+            .is_some_and(|argv| regex.is_match(&argv.join(" ")))
+}
+
+/// Whether `path` is `prefix` or lies below it.
+fn is_under(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn count_recent(queue: &VecDeque<Instant>, window: Duration, now: Instant) -> usize {
+    queue
+        .iter()
+        .rev()
+        .take_while(|&&ts| now.duration_since(ts) <= window)
+        .count()
+}
+
+/// Distinct PIDs signalled within `window` of `now`.
+fn distinct_targets(sent: &VecDeque<(Instant, u32)>, window: Duration, now: Instant) -> usize {
+    sent.iter()
+        .rev()
+        .take_while(|(ts, _)| now.duration_since(*ts) <= window)
+        .map(|(_, target)| *target)
+        .collect::<std::collections::HashSet<_>>()
+        .len()
+}
+
+/// Block I/O device number as the kernel's `MINORBITS` split packs it.
+fn decode_block_dev(dev: u32) -> (u32, u32) {
+    (dev >> 20, dev & ((1 << 20) - 1))
+}
+
+/// Key of a `block_io_rate` rule's counters, for `/rules/windows`.
+fn block_io_key(key: u32, per_process: bool) -> String {
+    if per_process {
+        format!("pid {key}")
+    } else {
+        let (major, minor) = decode_block_dev(key);
+        format!("dev {major}:{minor}")
+    }
+}
+
+/// Average MiB/s of `bytes` spread over `window`.
+fn mib_per_sec(bytes: u64, window: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / window.as_secs_f64().max(1.0)
+}
+
+/// Values (stall nanoseconds, bytes) recorded within `window` of `now`.
+fn recent_total(stalls: &VecDeque<(Instant, u64)>, window: Duration, now: Instant) -> u64 {
+    stalls
+        .iter()
+        .rev()
+        .take_while(|(ts, _)| now.duration_since(*ts) <= window)
+        .map(|(_, ns)| ns)
+        .sum()
+}
+
+#[async_trait]
+impl Handler for RuleEngine {
+    fn name(&self) -> &'static str {
+        "rules"
+    }
+
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
+        use linnix_ai_ebpf_common::EventType;
+        let now = Instant::now();
+
+        let rules = self.snapshot();
+        let scoped_keys: usize = rules
+            .iter()
+            .filter(|rule| rule.cfg.keeps_own_counters())
+            .map(|rule| rule.stats.scoped_keys())
+            .sum();
+        // The event's process and its ancestors, for subtree CPU.
+        let mut subtree = Vec::new();
+        if rules
+            .iter()
+            .any(|rule| matches!(rule.cfg.detector, Detector::SubtreeCpuPct { .. }))
+        {
+            if event.event_type == EventType::Fork as u32 {
+                let ancestors = fork_ancestors(event.data, event.data2);
+                self.lineage
+                    .record_fork(event.pid, event.ppid, &ancestors)
+                    .await;
+            }
+            if event.cpu_percent().is_some() {
+                subtree = self.lineage_of(event).await;
+            }
+        }
+        let mut windows = self.windows().await;
+        let spans = windows.spans;
+        let fork_keep = Duration::from_secs(spans.fork_secs.max(1));
+        let exec_keep = Duration::from_secs(spans.exec_secs.max(1));
+        let completion_keep = Duration::from_secs(spans.completion_secs.max(1));
+        let runaway_keep = Duration::from_secs(spans.runaway_secs.max(1));
+        let mut evicted = 0u64;
+        let mut crash_key = None;
+        let mut exec_novelty = None;
+
+        match event.event_type {
+            x if x == EventType::Fork as u32 => {
+                windows.fork_events.push_back(now);
+                trim_instant_queue(&mut windows.fork_events, fork_keep, now);
+
+                if spans.runaway_secs > 0 {
+                    let mut remove_entry = false;
+                    {
+                        let queue = windows
+                            .forks_by_ppid
+                            .entry(event.ppid)
+                            .or_insert_with(VecDeque::new);
+                        queue.push_back(now);
+                        trim_instant_queue(queue, runaway_keep, now);
+                        evicted += cap_queue(queue);
+                        if queue.is_empty() {
+                            remove_entry = true;
+                        }
+                    }
+                    if remove_entry {
+                        windows.forks_by_ppid.remove(&event.ppid);
+                    }
+                    evicted += enforce_cap(
+                        &mut windows.forks_by_ppid,
+                        self.max_tracked_ppids,
+                        Some(runaway_keep),
+                        now,
+                        |queue| queue.back().copied(),
+                    );
+                }
+            }
+            x if x == EventType::Exec as u32 => {
+                windows.exec_events.push_back(now);
+                trim_instant_queue(&mut windows.exec_events, exec_keep, now);
+                windows.exec_start.insert(event.pid, now);
+                // Long-lived processes legitimately sit here for days, so
+                // there is no idle expiry; only the size cap applies.
+                evicted += enforce_cap(
+                    &mut windows.exec_start,
+                    self.max_tracked_execs,
+                    None,
+                    now,
+                    |start| Some(*start),
+                );
+                let parent = (windows.crash_loops.is_some() || windows.fingerprint.is_some())
+                    .then(|| crashloop::read_comm(event.ppid).unwrap_or_default());
+                if let Some(fingerprint) = windows.fingerprint.as_mut() {
+                    let comm = comm_of(event);
+                    let parent = parent.clone().unwrap_or_default();
+                    let novelty = fingerprint.observe(&comm, argv_hash(event), &parent);
+                    exec_novelty = Some((comm, parent, novelty, fingerprint.age(now)));
+                }
+                if let Some(tracker) = windows.crash_loops.as_mut() {
+                    let key = DaemonKey {
+                        ppid: event.ppid,
+                        binary: crashloop::read_exe(event.pid).unwrap_or_else(|| comm_of(event)),
+                    };
+                    tracker.on_exec(event.pid, key, parent.unwrap_or_default(), now);
+                }
+            }
+            x if x == EventType::Exit as u32 => {
+                let status = ExitStatus::from_event(event);
+                if let Some(tracker) = windows.crash_loops.as_mut() {
+                    crash_key = tracker.on_exit(
+                        event.pid,
+                        || status.unwrap_or_else(|| ExitStatus::read_zombie(event.pid)),
+                        now,
+                    );
+                }
+                if let Some(start) = windows.exec_start.remove(&event.pid) {
+                    let lifetime = now.saturating_duration_since(start);
+                    windows.exec_completions.push_back((
+                        now,
+                        lifetime,
+                        status.unwrap_or(ExitStatus::Unknown),
+                    ));
+                    trim_completion_queue(&mut windows.exec_completions, completion_keep, now);
+                }
+            }
+            _ => {}
+        }
+        if let Some(tracker) = windows.subtree_cpu.as_mut() {
+            if event.event_type == EventType::Exit as u32 {
+                tracker.remove(event.pid);
+            } else if let (Some(cpu), [pid, ancestors @ ..]) = (event.cpu_percent(), &subtree[..]) {
+                evicted += tracker.observe(*pid, ancestors, cpu, now);
+            }
+        }
+
+        if evicted > 0 {
+            self.metrics.add_detector_state_evictions(evicted);
+        }
+        self.metrics.set_detector_state_keys(
+            windows.forks_by_ppid.len()
+                + windows.exec_start.len()
+                + windows
+                    .crash_loops
+                    .as_ref()
+                    .map_or(0, CrashLoopTracker::len)
+                + windows
+                    .fingerprint
+                    .as_ref()
+                    .map_or(0, WorkloadFingerprint::len)
+                + windows.subtree_cpu.as_ref().map_or(0, SubtreeCpu::len)
+                + scoped_keys,
+        );
+        drop(windows);
+
+        if rules.iter().any(|rule| rule.cfg.scope.is_some()) {
+            self.scopes.observe(event);
+        }
+        let target = self.scopes.target(event);
+        let facts = EventFacts {
+            now,
+            subtree,
+            crash_key,
+            exec_novelty,
+        };
+        for rule in rules
+            .iter()
+            .filter(|rule| rule.cfg.detector.runs_on_events())
+        {
+            let started = Instant::now();
+            let scope_key = rule.cfg.scope_key(&target);
+            if let Some(scope_key) = scope_key {
+                self.evaluate_event(rule, event, &scope_key, &facts).await;
+            }
+            rule.stats.record_eval(started.elapsed());
+        }
+        Ok(())
+    }
+
+    async fn on_snapshot(&self, snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+        let now = Instant::now();
+        self.resolve_cleared(now).await;
+
+        for rule in self.snapshot().iter() {
+            let started = Instant::now();
+            match &rule.cfg.detector {
+                Detector::SystemPsiCpu {
+                    threshold_pct,
+                    duration,
+                } => {
+                    let mut state = rule.state().await;
+                    let current = snapshot.psi_cpu_some_avg10;
+                    if current > *threshold_pct {
+                        let breach_start = *state.breach_start.get_or_insert(now);
+                        let elapsed = now.duration_since(breach_start).as_secs();
+                        if elapsed >= *duration {
+                            state.breach_start = None;
+                            drop(state);
+                            self.emit_alert(
+                                rule,
+                                Detection::from(format!(
+                                    "CPU PSI {:.1}% > {:.1}% sustained {}s",
+                                    current, threshold_pct, duration
+                                ))
+                                .label("value", format!("{current:.1}")),
+                            )
+                            .await;
+                        }
+                    } else {
+                        state.breach_start = None;
+                    }
+                }
+                Detector::SystemPsiMemory {
+                    threshold_pct,
+                    duration,
+                } => {
+                    let mut state = rule.state().await;
+                    let current = snapshot.psi_memory_full_avg10;
+                    if current > *threshold_pct {
+                        let breach_start = *state.breach_start.get_or_insert(now);
+                        let elapsed = now.duration_since(breach_start).as_secs();
+                        if elapsed >= *duration {
+                            state.breach_start = None;
+                            drop(state);
+                            self.emit_alert(
+                                rule,
+                                Detection::from(format!(
+                                    "memory PSI (full) {:.1}% > {:.1}% sustained {}s",
+                                    current, threshold_pct, duration
+                                ))
+                                .label("value", format!("{current:.1}")),
+                            )
+                            .await;
+                        }
+                    } else {
+                        state.breach_start = None;
+                    }
+                }
+                Detector::SystemPsiIo {
+                    threshold_pct,
+                    duration,
+                } => {
+                    let mut state = rule.state().await;
+                    let current = snapshot.psi_io_full_avg10;
+                    if current > *threshold_pct {
+                        let breach_start = *state.breach_start.get_or_insert(now);
+                        let elapsed = now.duration_since(breach_start).as_secs();
+                        if elapsed >= *duration {
+                            state.breach_start = None;
+                            drop(state);
+                            self.emit_alert(
+                                rule,
+                                Detection::from(format!(
+                                    "IO PSI (full) {:.1}% > {:.1}% sustained {}s",
+                                    current, threshold_pct, duration
+                                ))
+                                .label("value", format!("{current:.1}")),
+                            )
+                            .await;
+                        }
+                    } else {
+                        state.breach_start = None;
+                    }
+                }
+                Detector::CfsThrottling {
+                    threshold_pct,
+                    duration,
+                } => {
+                    let mut state = rule.state().await;
+                    let breaching: Vec<_> = snapshot
+                        .cgroup_throttling
+                        .iter()
+                        .filter(|t| t.throttled_pct > *threshold_pct)
+                        .collect();
+                    let timers = &mut state.throttle_breach;
+                    timers.retain(|cgroup, _| breaching.iter().any(|t| &t.cgroup == cgroup));
+                    for t in &breaching {
+                        timers.entry(t.cgroup.clone()).or_insert(now);
+                    }
+                    // Snapshots are sorted, so the first sustained one is the worst.
+                    let sustained = breaching.into_iter().find(|t| {
+                        timers
+                            .get(&t.cgroup)
+                            .is_some_and(|start| now.duration_since(*start).as_secs() >= *duration)
+                    });
+                    if let Some(worst) = sustained {
+                        timers.remove(&worst.cgroup);
+                        let message = format!(
+                            "CFS throttling in {}: {:.1}% of periods throttled ({}ms) > {:.1}% sustained {}s",
+                            worst.label(),
+                            worst.throttled_pct,
+                            worst.throttled_usec / 1000,
+                            threshold_pct,
+                            duration
+                        );
+                        let detection = Detection::from(message)
+                            .label("value", format!("{:.1}", worst.throttled_pct))
+                            .label("cgroup", worst.label());
+                        drop(state);
+                        self.emit_alert(rule, detection).await;
+                    }
+                }
+                Detector::ThermalThrottling {
+                    min_cpu_pct,
+                    max_freq_pct,
+                    duration,
+                } => {
+                    let mut state = rule.state().await;
+                    let throttled = snapshot.cpu_thermal.as_ref().filter(|t| {
+                        t.throttle_events > 0 || t.freq_pct.is_some_and(|f| f < *max_freq_pct)
+                    });
+                    match throttled {
+                        Some(thermal) if snapshot.cpu_percent >= *min_cpu_pct => {
+                            let breach_start = *state.breach_start.get_or_insert(now);
+                            if now.duration_since(breach_start).as_secs() >= *duration {
+                                state.breach_start = None;
+                                let freq = thermal
+                                    .freq_pct
+                                    .map(|f| format!(", frequency {f:.0}% of base"))
+                                    .unwrap_or_default();
+                                let message = format!(
+                                    "CPU throttling under load: {} thermal throttle events on {} CPUs{freq}, CPU {:.1}% sustained {}s (host cooling/power, not workload)",
+                                    thermal.throttle_events,
+                                    thermal.throttled_cpus,
+                                    snapshot.cpu_percent,
+                                    duration
+                                );
+                                let mut detection = Detection::from(message)
+                                    .label("value", format!("{:.1}", snapshot.cpu_percent))
+                                    .label("throttle_events", thermal.throttle_events);
+                                if let Some(freq) = thermal.freq_pct {
+                                    detection = detection.label("freq_pct", format!("{freq:.0}"));
+                                }
+                                drop(state);
+                                self.emit_alert(rule, detection).await;
+                            }
+                        }
+                        _ => {
+                            state.breach_start = None;
+                        }
+                    }
+                }
+                Detector::GpuMemoryLeak {
+                    min_growth_mib_per_min,
+                    min_used_mib,
+                    duration,
+                } => {
+                    let mut state = rule.state().await;
+                    let leaking: Vec<_> = snapshot
+                        .gpu_memory
+                        .iter()
+                        .filter(|t| {
+                            t.rate_mib_per_min >= *min_growth_mib_per_min
+                                && t.used_mib >= *min_used_mib
+                        })
+                        .collect();
+                    match leaking.iter().max_by_key(|t| t.growing_secs) {
+                        Some(t) => {
+                            state.gpu_growth = Some((t.label(), t.growing_secs));
+                        }
+                        None => {
+                            state.gpu_growth = None;
+                        }
+                    }
+                    // Trends are sorted fastest first.
+                    if let Some(worst) = leaking.iter().find(|t| t.growing_secs >= *duration) {
+                        let message = format!(
+                            "GPU memory leak: {} grew {} MiB to {} MiB over {}s ({:.0} MiB/min) without releasing any",
+                            worst.label(),
+                            worst.growth_mib,
+                            worst.used_mib,
+                            worst.growing_secs,
+                            worst.rate_mib_per_min
+                        );
+                        let detection = Detection::from(message)
+                            .label("value", format!("{:.0}", worst.rate_mib_per_min))
+                            .label("used_mib", worst.used_mib)
+                            .label("gpu", worst.label());
+                        drop(state);
+                        self.emit_alert(rule, detection).await;
+                    }
+                }
+                Detector::Baseline {
+                    metric,
+                    sigma,
+                    min_value,
+                } => {
+                    let Some(deviation) =
+                        self.baselines.as_ref().and_then(|b| b.deviation(*metric))
+                    else {
+                        continue;
+                    };
+                    if deviation.value >= *min_value && deviation.sigmas() >= *sigma as f64 {
+                        let detection = Detection::from(format!("above baseline: {deviation}"))
+                            .label("value", format!("{:.2}", deviation.value))
+                            .label("mean", format!("{:.2}", deviation.mean))
+                            .label("sigmas", format!("{:.1}", deviation.sigmas()));
+                        self.emit_alert(rule, detection).await;
+                    }
+                }
+                _ => continue,
+            }
+            rule.stats.record_eval(started.elapsed());
+        }
+
+        self.check_slow_rules(&mut self.windows().await.slow_rules, now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PERCENT_MILLI_UNKNOWN;
+    use tokio::time::{self, Duration};
+
+    fn test_engine(cooldown: u64) -> RuleEngine {
+        let cfg = RuleConfig {
+            name: "test".into(),
+            severity: Severity::Low,
+            cooldown,
+            resolve_after: 0,
+            detector: Detector::ForksPerSec {
+                threshold: 1,
+                duration: 1,
+            },
+            scope: None,
+            matcher: None,
+            escalate: None,
+            notify: None,
+            fingerprint: Vec::new(),
+            mode: RuleMode::Enforce,
+        };
+        let (tx, _rx) = broadcast::channel(16);
+        let metrics = Arc::new(Metrics::new());
+        RuleEngine {
+            rules: RwLock::new(Arc::new(rules_from(vec![cfg], &metrics))),
+            source: None,
+            source_lock: Mutex::new(()),
+            windows: Mutex::new(EventWindows {
+                fork_events: VecDeque::new(),
+                exec_events: VecDeque::new(),
+                exec_start: HashMap::new(),
+                exec_completions: VecDeque::new(),
+                forks_by_ppid: HashMap::new(),
+                crash_loops: None,
+                fingerprint: None,
+                subtree_cpu: None,
+                spans: WindowSpans {
+                    fork_secs: 1,
+                    exec_secs: 60,
+                    completion_secs: 60,
+                    runaway_secs: 1,
+                    crash_loop_secs: 0,
+                    fingerprint: false,
+                    subtree_cpu: false,
+                },
+                slow_rules: SlowRuleWatch::default(),
+            }),
+            tx,
+            alerts_file: "/dev/null".into(),
+            journald: false,
+            host: "test-host".into(),
+            metrics,
+            total_memory_bytes: Some(16 * 1024 * 1024 * 1024),
+            max_tracked_ppids: MAX_TRACKED_PPIDS,
+            max_tracked_execs: MAX_TRACKED_EXECS,
+            sessions: None,
+            kernel_counts: None,
+            lineage: LineageCache::default(),
+            scopes: ScopeResolver::default(),
+            silences: Arc::default(),
+            baselines: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn cooldown_suppresses_alerts() {
+        time::pause();
+        let engine = test_engine(60);
+        let mut rx = engine.tx.subscribe();
+        let base = ProcessEventWire {
+            pid: 0,
+            ppid: 0,
+            uid: 0,
+            gid: 0,
+            event_type: linnix_ai_ebpf_common::EventType::Fork as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        let event = ProcessEvent::new(base);
+        engine.on_event(&event).await.unwrap();
+        engine.on_event(&event).await.unwrap();
+        let _first = rx.recv().await.unwrap();
+        assert!(
+            rx.try_recv().is_err(),
+            "second alert suppressed by cooldown"
+        );
+        time::advance(Duration::from_secs(61)).await;
+        engine.on_event(&event).await.unwrap();
+        assert!(rx.recv().await.is_ok(), "alert after cooldown");
+    }
+
+    #[tokio::test]
+    async fn tuned_rules_keep_their_cooldowns() {
+        let engine = test_engine(3600);
+        let mut rx = engine.tx.subscribe();
+        let fork = wire_event(10, 7, linnix_ai_ebpf_common::EventType::Fork);
+        engine.on_event(&fork).await.unwrap();
+        assert!(rx.try_recv().is_ok());
+
+        let patch = RulePatch {
+            threshold: Some(2.0),
+            ..Default::default()
+        };
+        engine.update_rule("test", &patch).await.unwrap();
+        for _ in 0..3 {
+            engine.on_event(&fork).await.unwrap();
+        }
+        assert!(rx.try_recv().is_err(), "still cooling down after tuning");
+    }
+
+    #[tokio::test]
+    async fn fingerprints_split_cooldowns_and_count_duplicates() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        let engine = test_engine(60);
+        let mut cfg = engine.snapshot()[0].cfg.clone();
+        cfg.fingerprint = vec![FingerprintField::Ppid];
+        *engine.rules.write().unwrap() = Arc::new(rules_from(vec![cfg], &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        for ppid in [7, 8, 7, 7] {
+            engine
+                .on_event(&wire_event(100, ppid, EventType::Fork))
+                .await
+                .unwrap();
+        }
+        let fingerprints: Vec<String> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|a| a.labels["fingerprint"].clone())
+            .collect();
+        assert_eq!(fingerprints, ["ppid=7", "ppid=8"]);
+
+        time::advance(Duration::from_secs(61)).await;
+        engine
+            .on_event(&wire_event(100, 7, EventType::Fork))
+            .await
+            .unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.labels["deduplicated"], "2");
+        assert!(
+            alert
+                .message
+                .ends_with("(2 duplicates suppressed since the last alert)"),
+            "{}",
+            alert.message
+        );
+    }
+
+    #[tokio::test]
+    async fn silenced_rules_do_not_alert() {
+        use crate::silences::NewSilence;
+        use linnix_ai_ebpf_common::EventType;
+        let engine = test_engine(0);
+        let mut rx = engine.tx.subscribe();
+        let silence = engine
+            .silences()
+            .add(
+                NewSilence {
+                    rules: vec!["te*".into()],
+                    duration_minutes: Some(10),
+                    ..Default::default()
+                },
+                chrono::Local::now(),
+            )
+            .unwrap();
+        let fork = wire_event(10, 7, EventType::Fork);
+        engine.on_event(&fork).await.unwrap();
+        assert!(rx.try_recv().is_err());
+        assert_eq!(engine.metrics.alerts_silenced(), 1);
+
+        engine.silences().remove(&silence.id).unwrap();
+        time::pause();
+        time::advance(Duration::from_millis(200)).await;
+        engine.on_event(&fork).await.unwrap();
+        assert_eq!(rx.try_recv().unwrap().rule, "test");
+    }
+
+    #[tokio::test]
+    async fn repeated_alerts_escalate_severity() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        assert!(
+            parse_rules(
+                "- name: f\n  detector: fork_burst\n  threshold: 1\n  window_seconds: 1\n  escalate: {fires: 2, within_minutes: 0}\n",
+                Some("yaml")
+            )
+            .is_err()
+        );
+        let cfgs = parse_rules(
+            "- name: forks\n  detector: fork_burst\n  threshold: 1\n  window_seconds: 1\n  severity: medium\n  cooldown: 0\n  escalate: {fires: 2, within_minutes: 10}\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let mut fire = async || {
+            time::advance(Duration::from_secs(2)).await;
+            engine
+                .on_event(&wire_event(10, 7, EventType::Fork))
+                .await
+                .unwrap();
+            rx.try_recv().unwrap()
+        };
+
+        for _ in 0..2 {
+            let alert = fire().await;
+            assert_eq!(alert.severity, Severity::Medium);
+            assert_eq!(alert.message, "fork burst: 1 forks in 1s");
+        }
+        let alert = fire().await;
+        assert_eq!(alert.severity, Severity::High);
+        assert_eq!(
+            alert.message,
+            "fork burst: 1 forks in 1s (escalated: fired more than 2 times in 10m)"
+        );
+
+        // Once the earlier alerts leave the window, severity drops back.
+        time::advance(Duration::from_secs(600)).await;
+        assert_eq!(fire().await.severity, Severity::Medium);
+    }
+
+    #[tokio::test]
+    async fn shadow_rules_count_matches_without_alerting() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            "[[rules]]\nname = \"forks\"\ndetector = \"fork_burst\"\nthreshold = 1\nwindow_seconds = 1\ncooldown = 0\nmode = \"shadow\"\n",
+        )
+        .unwrap();
+        let engine = RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+        assert_eq!(engine.rule("forks").unwrap().mode, RuleMode::Shadow);
+        let mut rx = engine.tx.subscribe();
+        for pid in 10..12 {
+            time::advance(Duration::from_secs(2)).await;
+            engine
+                .on_event(&wire_event(pid, 7, EventType::Fork))
+                .await
+                .unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+        let stats = &engine.metrics.rule_stats()[0];
+        assert_eq!((stats.fires, stats.shadow_matches), (0, 2));
+
+        let patch = RulePatch {
+            mode: Some(RuleMode::Enforce),
+            persist: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            engine.update_rule("forks", &patch).await.unwrap().mode,
+            RuleMode::Enforce
+        );
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("mode"));
+        time::advance(Duration::from_secs(2)).await;
+        engine
+            .on_event(&wire_event(12, 7, EventType::Fork))
+            .await
+            .unwrap();
+        assert_eq!(rx.try_recv().unwrap().rule, "forks");
+        assert_eq!(engine.metrics.rule_stats()[0].fires, 1);
+    }
+
+    #[tokio::test]
+    async fn alerts_carry_labels_of_the_trigger() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: forks\n  detector: fork_burst\n  threshold: 1\n  window_seconds: 5\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let mut event = wire_event(10, 7, EventType::Fork);
+        event.base.comm[..5].copy_from_slice(b"nginx");
+        engine.on_event(&event).await.unwrap();
+
+        let alert = rx.try_recv().unwrap();
+        let labels: Vec<(&str, &str)> = alert
+            .labels
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                ("comm", "nginx"),
+                ("detector", "fork_burst"),
+                ("pid", "10"),
+                ("ppid", "7"),
+                ("threshold", "1"),
+                ("uid", "0"),
+                ("value", "1"),
+                ("window_seconds", "5"),
+            ]
+        );
+        let line = serde_json::to_value(&alert).unwrap();
+        assert_eq!(line["labels"]["comm"], "nginx");
+        assert_eq!(alert.labels_line().split(' ').count(), 8);
+    }
+
+    #[tokio::test]
+    async fn fork_burst_counts_forks_whose_events_were_dropped() {
+        time::pause();
+        let counts = Arc::new(KernelCounts::default());
+        let engine = test_engine(0).with_kernel_counts(Arc::clone(&counts));
+        let cfgs = parse_rules(
+            "rules:\n  - name: storm\n    detector: fork_burst\n    threshold: 500\n    window_seconds: 5\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let fork = ProcessEvent::new(ProcessEventWire {
+            pid: 10,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: linnix_ai_ebpf_common::EventType::Fork as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        });
+
+        engine.on_event(&fork).await.unwrap();
+        assert!(rx.try_recv().is_err());
+        assert_eq!(engine.window_stats().await.rules[0].current, 1.0);
+
+        // Only one event got through, but the probes counted 600 forks.
+        counts.record(Instant::now(), 600, 0);
+        engine.on_event(&fork).await.unwrap();
+        let alert = rx.recv().await.unwrap();
+        assert_eq!(alert.message, "fork burst: 600 forks in 5s");
+    }
+
+    #[tokio::test]
+    async fn event_alerts_name_the_login_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let proc_dir = dir.path().join("proc/4242");
+        std::fs::create_dir_all(&proc_dir).unwrap();
+        std::fs::write(proc_dir.join("loginuid"), "1000\n").unwrap();
+        std::fs::write(proc_dir.join("sessionid"), "7\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("sessions")).unwrap();
+        std::fs::write(
+            dir.path().join("sessions/7"),
+            "USER=alice\nSERVICE=sshd\nREMOTE_HOST=10.0.0.5\n",
+        )
+        .unwrap();
+        let sessions = SessionResolver::with_roots(
+            dir.path().join("proc"),
+            dir.path().join("sessions"),
+            dir.path().join("passwd"),
+        );
+        let engine = test_engine(0).with_sessions(Arc::new(sessions));
+        let mut rx = engine.tx.subscribe();
+
+        let event = ProcessEvent::new(ProcessEventWire {
+            pid: 4242,
+            ppid: 4241,
+            uid: 1000,
+            gid: 1000,
+            event_type: linnix_ai_ebpf_common::EventType::Fork as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        });
+        engine.on_event(&event).await.unwrap();
+        let alert = rx.recv().await.unwrap();
+        assert_eq!(
+            alert.message,
+            "fork rate exceeded 1 per second (triggered by alice's SSH session from 10.0.0.5)"
+        );
+    }
+
+    #[tokio::test]
+    async fn dedupe_prevents_duplicates() {
+        let engine = test_engine(0);
+        let mut rx = engine.tx.subscribe();
+        let base = ProcessEventWire {
+            pid: 0,
+            ppid: 0,
+            uid: 0,
+            gid: 0,
+            event_type: linnix_ai_ebpf_common::EventType::Fork as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        let event = ProcessEvent::new(base);
+        let f1 = engine.on_event(&event);
+        let f2 = engine.on_event(&event);
+        let (r1, r2) = futures_util::join!(f1, f2);
+        r1.unwrap();
+        r2.unwrap();
+        let _first = rx.recv().await.unwrap();
+        assert!(rx.try_recv().is_err(), "duplicate alert suppressed");
+    }
+
+    fn wire_event(
+        pid: u32,
+        ppid: u32,
+        event_type: linnix_ai_ebpf_common::EventType,
+    ) -> ProcessEvent {
+        ProcessEvent::new(ProcessEventWire {
+            pid,
+            ppid,
+            uid: 0,
+            gid: 0,
+            event_type: event_type as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn forks_by_ppid_is_capped_under_pid_churn() {
+        let mut engine = test_engine(60);
+        engine.max_tracked_ppids = 64;
+        for ppid in 0..1000u32 {
+            let event = wire_event(ppid + 1, ppid, linnix_ai_ebpf_common::EventType::Fork);
+            engine.on_event(&event).await.unwrap();
+        }
+        let windows = engine.windows.lock().await;
+        assert!(windows.forks_by_ppid.len() <= 64);
+        assert!(engine.metrics.detector_state_evictions() > 0);
+        // the most recent parent is always retained
+        assert!(windows.forks_by_ppid.contains_key(&999));
+    }
+
+    #[tokio::test]
+    async fn exec_start_is_capped_when_exits_are_missed() {
+        let mut engine = test_engine(60);
+        engine.max_tracked_execs = 128;
+        for pid in 0..2000u32 {
+            let event = wire_event(pid, 1, linnix_ai_ebpf_common::EventType::Exec);
+            engine.on_event(&event).await.unwrap();
+        }
+        let windows = engine.windows.lock().await;
+        assert!(windows.exec_start.len() <= 128);
+        assert!(windows.exec_start.contains_key(&1999));
+        assert_eq!(
+            engine.metrics.detector_state_keys(),
+            windows.forks_by_ppid.len() + windows.exec_start.len()
+        );
+    }
+
+    #[test]
+    fn parses_rules_from_yaml_and_toml() {
+        let yaml = r#"- name: fork_storm
+  detector: forks_per_sec
+  threshold: 5
+  duration: 1
+  severity: high
+  cooldown: 10
+- name: cpu_spin
+  detector: subtree_cpu_pct
+  threshold: 90.0
+  duration: 15
+  severity: medium
+"#;
+        let toml = r#"
+[[rules]]
+name = "fork_storm"
+detector = "forks_per_sec"
+threshold = 5
+duration = 1
+severity = "high"
+cooldown = 10
+
+[[rules]]
+name = "cpu_spin"
+detector = "subtree_cpu_pct"
+threshold = 90.0
+duration = 15
+severity = "medium"
+"#;
+
+        let yaml_rules = parse_rules(yaml, Some("yaml")).expect("yaml parses");
+        let toml_rules = parse_rules(toml, Some("toml")).expect("toml parses");
+        assert_eq!(yaml_rules.len(), 2, "yaml rule count");
+        assert_eq!(toml_rules.len(), 2, "toml rule count");
+        assert_eq!(yaml_rules[0].name, "fork_storm");
+        assert_eq!(toml_rules[0].name, "fork_storm");
+        assert_eq!(yaml_rules[1].name, "cpu_spin");
+        assert_eq!(toml_rules[1].name, "cpu_spin");
+    }
+
+    #[tokio::test]
+    async fn rule_stats_count_evaluations_and_fires() {
+        let engine = test_engine(60);
+        let event = wire_event(10, 1, linnix_ai_ebpf_common::EventType::Fork);
+        for _ in 0..3 {
+            engine.on_event(&event).await.unwrap();
+        }
+        let stats = engine.metrics.rule_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].rule, "test");
+        assert_eq!(stats[0].evaluations, 3);
+        // Cooldown keeps the later matches from firing.
+        assert_eq!(stats[0].fires, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn window_stats_report_counts_against_thresholds() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: burst\n  detector: fork_burst\n  threshold: 10\n  window_seconds: 1\n\
+             - name: runaway\n  detector: runaway_tree\n  threshold: 4\n  window_seconds: 1\n\
+             - name: short_jobs\n  detector: short_job_flood\n  threshold: 5\n  window_seconds: 10\n  max_exec_duration_ms: 500\n\
+             - name: custom\n  detector: script\n  script: \"false\"\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+
+        for parent in [7, 7, 7, 8] {
+            engine
+                .on_event(&wire_event(100, parent, EventType::Fork))
+                .await
+                .unwrap();
+        }
+        engine
+            .on_event(&wire_event(200, 1, EventType::Exec))
+            .await
+            .unwrap();
+        engine
+            .on_event(&wire_event(200, 1, EventType::Exit))
+            .await
+            .unwrap();
+
+        let stats = engine.window_stats().await;
+        assert_eq!((stats.forks, stats.execs, stats.completions), (4, 1, 1));
+        assert_eq!(stats.tracked_parents, 2);
+        let names: Vec<_> = stats.rules.iter().map(|r| r.rule.as_str()).collect();
+        assert_eq!(names, ["burst", "runaway", "short_jobs"], "scripts skipped");
+        let burst = &stats.rules[0];
+        assert_eq!(
+            (burst.metric, burst.current, burst.threshold),
+            ("forks", 4.0, 10.0)
+        );
+        assert!((burst.ratio - 0.4).abs() < 1e-9);
+        let runaway = &stats.rules[1];
+        assert_eq!(runaway.current, 3.0);
+        assert_eq!(runaway.key.as_deref(), Some("ppid 7"));
+        assert_eq!(runaway.cooldown_remaining_secs, None);
+        assert_eq!(stats.rules[2].current, 1.0);
+
+        // Firing starts the cooldown; aged-out forks no longer count.
+        engine
+            .on_event(&wire_event(100, 7, EventType::Fork))
+            .await
+            .unwrap();
+        let stats = engine.window_stats().await;
+        assert_eq!(stats.rules[1].ratio, 1.0);
+        assert_eq!(stats.rules[1].cooldown_remaining_secs, Some(60));
+        time::advance(Duration::from_secs(2)).await;
+        let stats = engine.window_stats().await;
+        assert_eq!(stats.rules[0].current, 0.0);
+        assert_eq!(stats.rules[1].key, None);
+        assert_eq!(stats.rules[1].cooldown_remaining_secs, Some(58));
+    }
+
+    #[test]
+    fn dominant_rule_needs_majority_share_and_enough_time() {
+        assert_eq!(dominant_rule(&[90_000_000]), None);
+        assert_eq!(dominant_rule(&[40_000, 10_000]), None);
+        assert_eq!(dominant_rule(&[50_000_000, 50_000_000]), None);
+        let (idx, share) = dominant_rule(&[10_000_000, 90_000_000]).unwrap();
+        assert_eq!(idx, 1);
+        assert!((share - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rules_schema_accepts_shipped_rules_and_rejects_incomplete_ones() {
+        let schema = jsonschema::JSONSchema::compile(&rules_schema()).expect("schema compiles");
+
+        let shipped = include_str!("../../configs/rules.yaml");
+        let doc: serde_json::Value = serde_yaml::from_str(shipped).unwrap();
+        assert!(schema.is_valid(&doc), "configs/rules.yaml fails schema");
+
+        let wrapped = serde_json::json!({
+            "rules": [{ "name": "x", "detector": "short_job_flood", "threshold": 5, "window_seconds": 10 }]
+        });
+        assert!(schema.is_valid(&wrapped));
+
+        let missing =
+            serde_json::json!([{ "name": "x", "detector": "fork_burst", "threshold": 5 }]);
+        assert!(!schema.is_valid(&missing));
+        let unknown = serde_json::json!([{ "name": "x", "detector": "nope" }]);
+        assert!(!schema.is_valid(&unknown));
+    }
+
+    #[test]
+    fn yaml_rules_accept_rules_wrapper() {
+        let yaml = "rules:\n  - name: burst\n    detector: fork_burst\n    threshold: 5\n    window_seconds: 10\n";
+        let rules = parse_rules(yaml, Some("yaml")).expect("wrapped yaml parses");
+        assert_eq!(rules[0].name, "burst");
+    }
+
+    #[tokio::test]
+    async fn update_rule_persists_to_toml_and_rounds_float_thresholds() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            "[[rules]]\nname = \"cpu_psi\"\ndetector = \"system_psi_cpu\"\nthreshold_pct = 40.0\nduration = 30\n",
+        )
+        .unwrap();
+        let metrics = Arc::new(Metrics::new());
+        let engine = RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::clone(&metrics),
+        )
+        .unwrap();
+
+        let patch = RulePatch {
+            threshold: Some(0.1),
+            persist: true,
+            ..Default::default()
+        };
+        let view = engine.update_rule("cpu_psi", &patch).await.unwrap();
+        assert_eq!(view.threshold_field, "threshold_pct");
+        assert_eq!(view.threshold.unwrap().as_f64(), Some(0.1));
+        assert_eq!(view.cooldown, DEFAULT_COOLDOWN_SECS);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("threshold_pct = 0.1"), "{text}");
+        let cfgs = parse_rules(&text, Some("toml")).unwrap();
+        assert!(matches!(
+            cfgs[0].detector,
+            Detector::SystemPsiCpu { threshold_pct, duration: 30 } if threshold_pct == 0.1
+        ));
+
+        let too_high = RulePatch {
+            threshold: Some(150.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            engine.update_rule("cpu_psi", &too_high).await,
+            Err(RulePatchError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn persist_rule_edits_only_the_rule_and_keeps_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            "# tuned for prod\n[[rules]]\nname = \"storm\"\ndetector = \"forks_per_sec\"\nthreshold = 10 # per second\nduration = 2\n\n[[rules]]\nname = \"burst\"\ndetector = \"fork_burst\"\nthreshold = 3 # forks\nwindow_seconds = 5\nmode = \"shadow\"\n",
+        )
+        .unwrap();
+
+        let mut cfg = parse_rules(&std::fs::read_to_string(&path).unwrap(), None)
+            .unwrap()
+            .into_iter()
+            .find(|cfg| cfg.name == "burst")
+            .unwrap();
+        cfg.detector = cfg.detector.with_threshold(7.0).unwrap();
+        cfg.cooldown = 90;
+        cfg.mode = RuleMode::Enforce;
+        persist_rule(&path, &cfg).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# tuned for prod\n[[rules]]\nname = \"storm\"\ndetector = \"forks_per_sec\"\nthreshold = 10 # per second\nduration = 2\n\n[[rules]]\nname = \"burst\"\ndetector = \"fork_burst\"\nthreshold = 7 # forks\nwindow_seconds = 5\ncooldown = 90\n"
+        );
+        // The file was replaced through a temp file.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn persisting_to_yaml_rules_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        let text = "# tuned for prod\n- name: burst\n  detector: fork_burst\n  threshold: 3  # forks\n  window_seconds: 5\n";
+        std::fs::write(&path, text).unwrap();
+        let engine = RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+
+        let patch = RulePatch {
+            threshold: Some(7.0),
+            persist: true,
+            ..Default::default()
+        };
+        let err = engine.update_rule("burst", &patch).await.unwrap_err();
+        assert!(
+            matches!(&err, RulePatchError::Invalid(msg) if msg.contains("is YAML")),
+            "{err}"
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+        let view = engine.rule("burst").unwrap();
+        assert_eq!(view.threshold.unwrap().as_u64(), Some(3));
+
+        // Without persist the running rule still changes.
+        let patch = RulePatch {
+            persist: false,
+            ..patch
+        };
+        let view = engine.update_rule("burst", &patch).await.unwrap();
+        assert_eq!(view.threshold.unwrap().as_u64(), Some(7));
+    }
+
+    #[test]
+    fn traces_replay_with_their_own_timing() {
+        use linnix_ai_ebpf_common::EventType;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        std::fs::write(
+            &path,
+            "- name: burst\n  detector: fork_burst\n  threshold: 3\n  window_seconds: 10\n",
+        )
+        .unwrap();
+        let trace = |gap_secs: u64| {
+            (0..3)
+                .map(|i| {
+                    let mut fork = wire_event(100 + i as u32, 7, EventType::Fork);
+                    fork.base.ts_ns = 1_000_000_000 + i * gap_secs * 1_000_000_000;
+                    fork
+                })
+                .collect::<Vec<_>>()
+        };
+        let engine = || {
+            RuleEngine::from_path(
+                path.to_str().unwrap(),
+                "/dev/null".into(),
+                false,
+                Arc::new(Metrics::new()),
+            )
+            .unwrap()
+        };
+
+        let alerts = engine().evaluate_trace(&trace(2));
+        assert_eq!(
+            alerts.iter().map(|a| a.rule.as_str()).collect::<Vec<_>>(),
+            ["burst"]
+        );
+        // The same forks spread over 40s never fill the window.
+        assert!(engine().evaluate_trace(&trace(20)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn script_rules_fire_on_window_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        std::fs::write(
+            &path,
+            "- name: fork_storm\n  detector: script\n  window_seconds: 10\n  event_types: [fork]\n  cooldown: 0\n  script: |\n    if window.parent_forks >= 3 { `ppid ${event.ppid} forked ${window.parent_forks} times` }\n",
+        )
+        .unwrap();
+        let engine = RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+        let view = engine.rule("fork_storm").unwrap();
+        assert_eq!((view.detector, view.threshold), ("script", None));
+        assert!(matches!(
+            engine
+                .update_rule(
+                    "fork_storm",
+                    &RulePatch {
+                        threshold: Some(1.0),
+                        ..Default::default()
+                    }
+                )
+                .await,
+            Err(RulePatchError::Invalid(_))
+        ));
+
+        let mut rx = engine.tx.subscribe();
+        use linnix_ai_ebpf_common::EventType;
+        // Exec events are filtered out by event_types.
+        engine
+            .on_event(&wire_event(9, 7, EventType::Exec))
+            .await
+            .unwrap();
+        for pid in 10..13 {
+            engine
+                .on_event(&wire_event(pid, 7, EventType::Fork))
+                .await
+                .unwrap();
+        }
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "fork_storm");
+        assert_eq!(alert.message, "ppid 7 forked 3 times");
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn reload_keeps_cooldowns_of_unchanged_rules() {
+        use linnix_ai_ebpf_common::EventType;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        let rule = |name: &str, script: &str| {
+            format!(
+                "- name: {name}\n  detector: script\n  cooldown: 3600\n  script: '\"{script}\"'\n"
+            )
+        };
+        std::fs::write(&path, [rule("kept", "kept"), rule("tuned", "old")].concat()).unwrap();
+        let engine = RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+        let mut rx = engine.tx.subscribe();
+        let fired = |rx: &mut broadcast::Receiver<Alert>| {
+            let mut messages = std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|a| a.message)
+                .collect::<Vec<_>>();
+            messages.sort();
+            messages
+        };
+        let fork = wire_event(10, 7, EventType::Fork);
+        engine.on_event(&fork).await.unwrap();
+        assert_eq!(fired(&mut rx), ["kept", "old"]);
+
+        std::fs::write(
+            &path,
+            [
+                rule("kept", "kept"),
+                rule("tuned", "new"),
+                rule("added", "added"),
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let summary = engine.reload().await.unwrap();
+        assert_eq!(
+            summary.to_string(),
+            "added: added; changed: tuned; removed: none; 1 unchanged"
+        );
+        // `kept` is still cooling down; the others start fresh.
+        engine.on_event(&fork).await.unwrap();
+        assert_eq!(fired(&mut rx), ["added", "new"]);
+
+        std::fs::write(&path, "- name: broken\n  detector: nope\n").unwrap();
+        assert!(engine.reload().await.is_err());
+        assert_eq!(engine.rule_count(), 3);
+    }
+
+    #[tokio::test]
+    async fn expr_rules_fire_on_fields_and_counters() {
+        use linnix_ai_ebpf_common::EventType;
+        assert!(
+            parse_rules(
+                "- name: e\n  detector: expr\n  expr: forks_per_sec(comm) > 1\n",
+                Some("yaml")
+            )
+            .is_err()
+        );
+        let cfgs = parse_rules(
+            "- name: make_storm\n  detector: expr\n  window_seconds: 10\n  event_types: [fork]\n  cooldown: 0\n  expr: 'forks(ppid) >= 3 && comm =~ \"^make\"'\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let fork = |pid, comm: &[u8]| {
+            let mut event = wire_event(pid, 7, EventType::Fork);
+            event.base.comm[..comm.len()].copy_from_slice(comm);
+            event
+        };
+        for pid in 10..13 {
+            engine.on_event(&fork(pid, b"cc")).await.unwrap();
+        }
+        assert!(rx.try_recv().is_err(), "comm doesn't match");
+        engine.on_event(&fork(13, b"make")).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            r#"make (pid 13, parent 7) matched forks(ppid) >= 3 && comm =~ "^make"; forks(ppid) = 4"#
+        );
+        assert_eq!(alert.labels["forks(ppid)"], "4");
+        assert_eq!(alert.labels["detector"], "expr");
+    }
+
+    #[test]
+    fn script_rules_reject_bad_scripts_and_event_types() {
+        let bad_syntax = "- name: s\n  detector: script\n  script: \"if {\"\n";
+        assert!(parse_rules(bad_syntax, Some("yaml")).is_err());
+        let bad_type = "- name: s\n  detector: script\n  script: \"1\"\n  event_types: [bogus]\n";
+        let err = parse_rules(bad_type, Some("yaml")).unwrap_err();
+        assert!(format!("{err:#}").contains("bogus"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn cfs_throttling_fires_for_the_worst_sustained_cgroup() {
+        use crate::collectors::throttle::CgroupThrottle;
+        let cfgs = parse_rules(
+            "- name: throttled\n  detector: cfs_throttling\n  threshold_pct: 25\n  duration: 0\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let throttle = |cgroup: &str, pod: Option<&str>, pct: f32| CgroupThrottle {
+            cgroup: cgroup.into(),
+            pod: pod.map(Into::into),
+            periods: 100,
+            throttled_periods: pct as u64,
+            throttled_pct: pct,
+            throttled_usec: 250_000,
+        };
+        let mut snapshot = SystemSnapshot {
+            timestamp: 0,
+            cpu_percent: 10.0,
+            mem_percent: 10.0,
+            load_avg: [0.0; 3],
+            disk_read_bytes: 0,
+            disk_write_bytes: 0,
+            net_rx_bytes: 0,
+            net_tx_bytes: 0,
+            psi_cpu_some_avg10: 0.0,
+            psi_memory_some_avg10: 0.0,
+            psi_memory_full_avg10: 0.0,
+            psi_io_some_avg10: 0.0,
+            psi_io_full_avg10: 0.0,
+            cgroup_throttling: vec![throttle("/batch.slice", None, 10.0)],
+            cpu_thermal: None,
+            gpu_memory: Vec::new(),
+        };
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err(), "below threshold");
+
+        snapshot.cgroup_throttling = vec![
+            throttle("/kubepods.slice/api.scope", Some("prod/api"), 80.0),
+            throttle("/kubepods.slice/worker.scope", None, 40.0),
+        ];
+        engine.on_snapshot(&snapshot).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "throttled");
+        assert!(
+            alert
+                .message
+                .starts_with("CFS throttling in prod/api: 80.0%"),
+            "{}",
+            alert.message
+        );
+    }
+
+    #[tokio::test]
+    async fn baseline_rules_alert_far_above_normal() {
+        use crate::baselines::Observed;
+        let dir = tempfile::tempdir().unwrap();
+        let baselines = Arc::new(Baselines::new(crate::config::BaselineConfig {
+            path: dir.path().join("baselines.json").display().to_string(),
+            min_samples: 3,
+            ..Default::default()
+        }));
+        let cfgs = parse_rules(
+            "- name: busy\n  detector: baseline\n  metric: cpu_pct\n  sigma: 4\n  min_value: 20\n\
+             - name: floor\n  detector: baseline\n  metric: cpu_pct\n  min_value: 50\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0).with_baselines(Arc::clone(&baselines));
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let snapshot = SystemSnapshot {
+            timestamp: 0,
+            cpu_percent: 40.0,
+            mem_percent: 10.0,
+            load_avg: [0.0; 3],
+            disk_read_bytes: 0,
+            disk_write_bytes: 0,
+            net_rx_bytes: 0,
+            net_tx_bytes: 0,
+            psi_cpu_some_avg10: 0.0,
+            psi_memory_some_avg10: 0.0,
+            psi_memory_full_avg10: 0.0,
+            psi_io_some_avg10: 0.0,
+            psi_io_full_avg10: 0.0,
+            cgroup_throttling: Vec::new(),
+            cpu_thermal: None,
+            gpu_memory: Vec::new(),
+        };
+        let cpu = |value: f64| Observed {
+            cpu_pct: Some(value),
+            ..Observed::default()
+        };
+
+        // Still learning the hour: nothing to compare with.
+        for value in [10.0, 12.0, 14.0] {
+            baselines.record(9, cpu(value));
+            engine.on_snapshot(&snapshot).await.unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+
+        baselines.record(9, cpu(40.0));
+        engine.on_snapshot(&snapshot).await.unwrap();
+        let fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(fired.len(), 1, "40% is below the floor rule's min_value");
+        assert_eq!(fired[0].rule, "busy");
+        assert_eq!(
+            fired[0].message,
+            "above baseline: CPU 40.0% vs 12.0% normal at 09:00 (17.1 sigma)"
+        );
+        let windows = engine.window_stats().await;
+        let busy = windows.rules.iter().find(|w| w.rule == "busy").unwrap();
+        assert_eq!((busy.metric, busy.threshold), ("sigma", 4.0));
+    }
+
+    #[tokio::test]
+    async fn alerts_resolve_once_the_condition_stops_matching() {
+        time::pause();
+        let cfgs = parse_rules(
+            "- name: cpu_psi\n  detector: system_psi_cpu\n  threshold_pct: 50\n  duration: 0\n  cooldown: 600\n  resolve_after: 30\n- name: oom\n  detector: oom_kill\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        assert_eq!(cfgs[1].resolve_after, DEFAULT_RESOLVE_AFTER_SECS);
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let mut snapshot = SystemSnapshot {
+            timestamp: 0,
+            cpu_percent: 10.0,
+            mem_percent: 10.0,
+            load_avg: [0.0; 3],
+            disk_read_bytes: 0,
+            disk_write_bytes: 0,
+            net_rx_bytes: 0,
+            net_tx_bytes: 0,
+            psi_cpu_some_avg10: 80.0,
+            psi_memory_some_avg10: 0.0,
+            psi_memory_full_avg10: 0.0,
+            psi_io_some_avg10: 0.0,
+            psi_io_full_avg10: 0.0,
+            cgroup_throttling: Vec::new(),
+            cpu_thermal: None,
+            gpu_memory: Vec::new(),
+        };
+        engine.on_snapshot(&snapshot).await.unwrap();
+        let fired = rx.try_recv().unwrap();
+        assert!(!fired.is_resolution());
+        assert_eq!(fired.labels["detector"], "system_psi_cpu");
+        assert_eq!(fired.labels["threshold"], "50.0");
+        assert_eq!(fired.labels["value"], "80.0");
+
+        // Still matching, within the cooldown: no new alert, but the
+        // condition is held for another quiet period.
+        time::advance(Duration::from_secs(10)).await;
+        engine.on_snapshot(&snapshot).await.unwrap();
+        snapshot.psi_cpu_some_avg10 = 5.0;
+        time::advance(Duration::from_secs(29)).await;
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        time::advance(Duration::from_secs(1)).await;
+        engine.on_snapshot(&snapshot).await.unwrap();
+        let resolved = rx.try_recv().unwrap();
+        assert_eq!(resolved.rule, "cpu_psi");
+        assert_eq!(resolved.resolved_after_secs, Some(10));
+        assert_eq!(
+            resolved.message,
+            format!("resolved after 10s: {}", fired.message)
+        );
+        let line = serde_json::to_value(&resolved).unwrap();
+        assert_eq!(line["resolved_after_secs"], 10);
+        // Resolutions keep the labels, and count the match the cooldown
+        // dropped.
+        let mut labels = fired.labels.clone();
+        labels.insert("deduplicated".into(), "1".into());
+        assert_eq!(resolved.labels, labels);
+        assert!(serde_json::to_value(&fired).unwrap()["resolved_after_secs"].is_null());
+
+        time::advance(Duration::from_secs(60)).await;
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err(), "resolved only once");
+        assert_eq!(format_span(250), "4m10s");
+        assert_eq!(format_span(7500), "2h5m");
+    }
+
+    #[tokio::test]
+    async fn thermal_throttling_needs_load_and_throttling() {
+        use crate::collectors::thermal::CpuThermal;
+        let cfgs = parse_rules(
+            "- name: hot_host\n  detector: thermal_throttling\n  duration: 0\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        assert!(matches!(
+            cfgs[0].detector,
+            Detector::ThermalThrottling { min_cpu_pct, max_freq_pct, .. }
+                if min_cpu_pct == 50.0 && max_freq_pct == 60.0
+        ));
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let mut snapshot = SystemSnapshot {
+            timestamp: 0,
+            cpu_percent: 20.0,
+            mem_percent: 10.0,
+            load_avg: [0.0; 3],
+            disk_read_bytes: 0,
+            disk_write_bytes: 0,
+            net_rx_bytes: 0,
+            net_tx_bytes: 0,
+            psi_cpu_some_avg10: 0.0,
+            psi_memory_some_avg10: 0.0,
+            psi_memory_full_avg10: 0.0,
+            psi_io_some_avg10: 0.0,
+            psi_io_full_avg10: 0.0,
+            cgroup_throttling: Vec::new(),
+            cpu_thermal: Some(CpuThermal {
+                throttle_events: 12,
+                throttled_cpus: 4,
+                freq_pct: Some(45.0),
+            }),
+            gpu_memory: Vec::new(),
+        };
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err(), "idle host");
+
+        snapshot.cpu_percent = 90.0;
+        snapshot.cpu_thermal = Some(CpuThermal {
+            freq_pct: Some(95.0),
+            ..Default::default()
+        });
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err(), "busy but not throttled");
+
+        snapshot.cpu_thermal = Some(CpuThermal {
+            throttle_events: 12,
+            throttled_cpus: 4,
+            freq_pct: Some(45.0),
+        });
+        engine.on_snapshot(&snapshot).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert!(
+            alert
+                .message
+                .starts_with("CPU throttling under load: 12 thermal throttle events on 4 CPUs, frequency 45% of base"),
+            "{}",
+            alert.message
+        );
+    }
+
+    #[tokio::test]
+    async fn gpu_memory_leak_needs_sustained_growth_and_size() {
+        use crate::collectors::gpu::GpuMemoryTrend;
+        let cfgs = parse_rules(
+            "- name: gpu_leak\n  detector: gpu_memory_leak\n  min_growth_mib_per_min: 50\n  duration: 600\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        assert!(matches!(
+            cfgs[0].detector,
+            Detector::GpuMemoryLeak { min_used_mib, .. } if min_used_mib == 1024
+        ));
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let trend = |growing_secs: u64, used_mib: u64, rate_mib_per_min: f32| GpuMemoryTrend {
+            pid: 4242,
+            comm: "python".into(),
+            pod: Some("ml/trainer-0".into()),
+            used_mib,
+            growth_mib: (rate_mib_per_min * growing_secs as f32 / 60.0) as u64,
+            growing_secs,
+            rate_mib_per_min,
+        };
+        let mut snapshot = SystemSnapshot {
+            timestamp: 0,
+            cpu_percent: 20.0,
+            mem_percent: 10.0,
+            load_avg: [0.0; 3],
+            disk_read_bytes: 0,
+            disk_write_bytes: 0,
+            net_rx_bytes: 0,
+            net_tx_bytes: 0,
+            psi_cpu_some_avg10: 0.0,
+            psi_memory_some_avg10: 0.0,
+            psi_memory_full_avg10: 0.0,
+            psi_io_some_avg10: 0.0,
+            psi_io_full_avg10: 0.0,
+            cgroup_throttling: Vec::new(),
+            cpu_thermal: None,
+            gpu_memory: vec![trend(300, 20_000, 100.0)],
+        };
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err(), "not sustained yet");
+        let windows = engine.window_stats().await;
+        assert_eq!(windows.rules[0].current, 300.0);
+        assert_eq!(
+            windows.rules[0].key.as_deref(),
+            Some("python (pid 4242) in ml/trainer-0")
+        );
+
+        snapshot.gpu_memory = vec![trend(900, 20_000, 10.0)];
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err(), "growing too slowly");
+
+        snapshot.gpu_memory = vec![trend(900, 800, 100.0)];
+        engine.on_snapshot(&snapshot).await.unwrap();
+        assert!(rx.try_recv().is_err(), "still small");
+
+        snapshot.gpu_memory = vec![trend(900, 20_000, 100.0)];
+        engine.on_snapshot(&snapshot).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "GPU memory leak: python (pid 4242) in ml/trainer-0 grew 1500 MiB to 20000 MiB over 900s (100 MiB/min) without releasing any"
+        );
+    }
+
+    #[tokio::test]
+    async fn daemon_crash_loop_counts_quick_restarts_per_binary() {
+        time::pause();
+        let cfgs = parse_rules(
+            "- name: crashloop\n  detector: daemon_crash_loop\n  threshold: 3\n  window_seconds: 60\n  max_lifetime_secs: 5\n  parent: \"*\"\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        engine.windows.lock().await.crash_loops = Some(CrashLoopTracker::new(
+            Duration::from_secs(60),
+            MAX_TRACKED_EXECS,
+            MAX_TRACKED_PPIDS,
+        ));
+        let mut rx = engine.tx.subscribe();
+
+        // PIDs above pid_max so /proc lookups fall back to the comm.
+        async fn run(engine: &RuleEngine, pid: u32, comm: &[u8], lifetime: u64) {
+            use linnix_ai_ebpf_common::EventType;
+            let mut name = [0u8; 16];
+            name[..comm.len()].copy_from_slice(comm);
+            let mut wire = ProcessEventWire {
+                pid,
+                ppid: 5_000_000,
+                uid: 0,
+                gid: 0,
+                event_type: EventType::Exec as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm: name,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 0,
+                data2: 0,
+                aux: 0,
+                aux2: 0,
+                cgroup_id: 0,
+            };
+            engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+            time::advance(Duration::from_secs(lifetime)).await;
+            wire.event_type = EventType::Exit as u32;
+            engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        }
+
+        run(&engine, 5_000_001, b"flaky", 1).await;
+        run(&engine, 5_000_002, b"flaky", 30).await; // ran long enough: not a crash
+        run(&engine, 5_000_003, b"other", 1).await;
+        run(&engine, 5_000_004, b"flaky", 1).await;
+        assert!(rx.try_recv().is_err(), "two quick exits of flaky so far");
+
+        run(&engine, 5_000_005, b"flaky", 2).await;
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "daemon crash loop: flaky under unknown parent (pid 5000000) exited 3 times within 5s of start in the last 60s; exits: 3x unknown"
+        );
+    }
+
+    #[tokio::test]
+    async fn short_job_flood_reports_how_jobs_exited() {
+        use linnix_ai_ebpf_common::{EXIT_STATUS_KNOWN, EventType};
+        time::pause();
+        let cfgs = parse_rules(
+            "- name: short_jobs\n  detector: short_job_flood\n  threshold: 3\n  window_seconds: 60\n  max_exec_duration_ms: 1000\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        for (pid, wait_status) in [(5_000_001, 11), (5_000_002, 0), (5_000_003, 11)] {
+            let mut wire = ProcessEventWire {
+                pid,
+                ppid: 5_000_000,
+                uid: 0,
+                gid: 0,
+                event_type: EventType::Exec as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm: [0; 16],
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 0,
+                data2: 0,
+                aux: 0,
+                aux2: 0,
+                cgroup_id: 0,
+            };
+            engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+            time::advance(Duration::from_millis(100)).await;
+            wire.event_type = EventType::Exit as u32;
+            wire.aux = wait_status;
+            wire.aux2 = EXIT_STATUS_KNOWN;
+            engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        }
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "3 short-lived execs (<= 1000ms) in 60s; 2 failed, exits: 2x SIGSEGV, 1x exit 0"
+        );
+    }
+
+    #[tokio::test]
+    async fn composite_rules_fire_once_all_conditions_match() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        for (rejected, error) in [
+            (
+                "op: xor\n  conditions:\n    - {detector: oom_kill}\n",
+                "op must be",
+            ),
+            ("conditions: []\n", "at least one condition"),
+            (
+                "conditions:\n    - {detector: composite, conditions: [{detector: oom_kill}]}\n",
+                "can't be composite",
+            ),
+            (
+                "conditions:\n    - {detector: oom_kill}\n    - {detector: kernel_load, kind: driver}\n",
+                "rule 'c[1]': kind must be",
+            ),
+        ] {
+            let text = format!("- name: c\n  detector: composite\n  {rejected}");
+            let err = parse_rules(&text, Some("yaml")).unwrap_err();
+            assert!(format!("{err:#}").contains(error), "{err:#}");
+        }
+
+        let cfgs = parse_rules(
+            "- name: busy\n  detector: composite\n  within_seconds: 30\n  cooldown: 0\n  conditions:\n\
+             \x20   - detector: subtree_cpu_pct\n      threshold: 50\n      duration: 1\n\
+             \x20   - detector: fork_burst\n      threshold: 3\n      window_seconds: 10\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        assert_eq!(engine.rule_count(), 1);
+        assert_eq!(engine.rule_views()[0].detector, "composite");
+        let mut rx = engine.tx.subscribe();
+        let hot = || {
+            let mut event = wire_event(10, 1, EventType::Exec);
+            event.set_cpu_percent(Some(60.0));
+            event
+        };
+
+        // CPU stays hot past its duration: one condition alone stays quiet.
+        engine.on_event(&hot()).await.unwrap();
+        time::advance(Duration::from_secs(2)).await;
+        engine.on_event(&hot()).await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // A fork burst within the window completes the rule.
+        for pid in 20..23 {
+            engine
+                .on_event(&wire_event(pid, 1, EventType::Fork))
+                .await
+                .unwrap();
+        }
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "busy");
+        assert_eq!(
+            alert.message,
+            "subtree of pid 10 at 60.0% cpu, over 50% for 1s and fork burst: 3 forks in 10s"
+        );
+        assert!(rx.try_recv().is_err());
+
+        // Firing used up both matches; more forks alone don't fire again.
+        time::advance(Duration::from_secs(1)).await;
+        engine
+            .on_event(&wire_event(23, 1, EventType::Fork))
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+        let windows = engine.window_stats().await;
+        let busy = windows.rules.iter().find(|w| w.rule == "busy").unwrap();
+        assert_eq!((busy.current, busy.threshold), (1.0, 2.0));
+    }
+
+    #[tokio::test]
+    async fn scoped_rules_keep_counters_per_key() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        for rejected in [
+            "- name: z\n  detector: zombie_count\n  threshold: 5\n  duration: 10\n  scope: {uid: 0}\n",
+            "- name: r\n  detector: runaway_tree\n  threshold: 5\n  window_seconds: 10\n  scope: {per: pod}\n",
+        ] {
+            assert!(parse_rules(rejected, Some("yaml")).is_err(), "{rejected}");
+        }
+        let cfgs = parse_rules(
+            "- name: host\n  detector: fork_burst\n  threshold: 3\n  window_seconds: 10\n  cooldown: 0\n\
+             - name: per_user\n  detector: fork_burst\n  threshold: 3\n  window_seconds: 10\n  cooldown: 0\n  scope: {per: uid}\n\
+             - name: root_only\n  detector: fork_burst\n  threshold: 1\n  window_seconds: 10\n  cooldown: 0\n  scope: {uid: 0}\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let fork = |pid: u32, uid: u32| {
+            let mut event = wire_event(pid, 1, EventType::Fork);
+            event.uid = uid;
+            event
+        };
+
+        // Two users forking twice each: three forks on the host, but
+        // neither user reaches three, and root doesn't fork at all.
+        for (pid, uid) in [(100, 1000), (101, 1001), (102, 1000), (103, 1001)] {
+            engine.on_event(&fork(pid, uid)).await.unwrap();
+        }
+        let fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].rule, "host");
+
+        // A third fork of one user fires for that user alone.
+        engine.on_event(&fork(104, 1001)).await.unwrap();
+        let fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|alert| alert.rule == "per_user")
+            .collect();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].message, "fork burst: 3 forks in 10s [1001]");
+        let windows = engine.window_stats().await;
+        let per_user = windows.rules.iter().find(|w| w.rule == "per_user").unwrap();
+        assert_eq!(per_user.key.as_deref(), Some("1001"));
+        let keys: Vec<_> = engine
+            .snapshot()
+            .iter()
+            .map(|rule| (rule.cfg.name.clone(), rule.stats.scoped_keys()))
+            .collect();
+        assert_eq!(keys[1..], [("per_user".into(), 2), ("root_only".into(), 0)]);
+        // Events outside a scope are still evaluated against the rule.
+        let stats = engine.metrics.rule_stats();
+        let root_only = stats.iter().find(|s| s.rule == "root_only").unwrap();
+        assert_eq!(root_only.evaluations, 5);
+    }
+
+    #[tokio::test]
+    async fn matched_rules_count_only_selected_processes() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        for rejected in [
+            "- name: z\n  detector: zombie_count\n  threshold: 5\n  duration: 10\n  match: {uid: [0]}\n",
+            "- name: f\n  detector: fork_burst\n  threshold: 5\n  window_seconds: 10\n  match: {comm: '(build'}\n",
+        ] {
+            assert!(parse_rules(rejected, Some("yaml")).is_err(), "{rejected}");
+        }
+        let cfgs = parse_rules(
+            "- name: host\n  detector: fork_burst\n  threshold: 3\n  window_seconds: 10\n  cooldown: 0\n\
+             - name: builds\n  detector: fork_burst\n  threshold: 3\n  window_seconds: 10\n  cooldown: 0\n  match: {comm: ^build, uid: [1000]}\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+        let fork = |pid: u32, comm: &[u8], uid: u32| {
+            let mut event = wire_event(pid, 1, EventType::Fork);
+            event.comm[..comm.len()].copy_from_slice(comm);
+            event.uid = uid;
+            event
+        };
+
+        // Unrelated forks fill the host queue but not the build rule's.
+        for (pid, comm, uid) in [
+            (100, &b"build"[..], 1000),
+            (101, b"nginx", 1000),
+            (102, b"build", 0),
+            (103, b"build-agent", 1000),
+        ] {
+            engine.on_event(&fork(pid, comm, uid)).await.unwrap();
+        }
+        let fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|alert| alert.rule)
+            .collect();
+        assert_eq!(fired, ["host"]);
+        let windows = engine.window_stats().await;
+        let builds = windows.rules.iter().find(|w| w.rule == "builds").unwrap();
+        assert_eq!((builds.current, builds.key.as_deref()), (2.0, None));
+
+        engine.on_event(&fork(104, b"build", 1000)).await.unwrap();
+        let fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|alert| alert.rule == "builds")
+            .collect();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].message, "fork burst: 3 forks in 10s");
+    }
+
+    #[tokio::test]
+    async fn events_a_matcher_rejects_still_count_as_evaluations() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: builds\n  detector: fork_burst\n  threshold: 3\n  window_seconds: 10\n  match: {comm: ^build}\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut event = wire_event(100, 1, EventType::Fork);
+        event.comm[..5].copy_from_slice(b"nginx");
+        engine.on_event(&event).await.unwrap();
+        engine.on_event(&event).await.unwrap();
+
+        let stats = engine.metrics.rule_stats();
+        assert_eq!((stats[0].evaluations, stats[0].fires), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn exec_rate_rules_keep_their_own_counts() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        let cfgs = parse_rules(
+            "- name: fast\n  detector: exec_rate\n  rate_per_min: 3\n  median_lifetime: 5\n  cooldown: 0\n\
+             - name: slow\n  detector: exec_rate\n  rate_per_min: 5\n  median_lifetime: 5\n  cooldown: 0\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        // `fast` firing starts its count over, but not `slow`'s.
+        for pid in 5_000_001..5_000_006 {
+            let mut exec = wire_event(pid, 5_000_000, EventType::Exec);
+            exec.comm[..4].copy_from_slice(b"bash");
+            let mut exit = exec.clone();
+            exit.event_type = EventType::Exit as u32;
+            engine.on_event(&exec).await.unwrap();
+            time::advance(Duration::from_millis(100)).await;
+            engine.on_event(&exit).await.unwrap();
+        }
+        let fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(fired.len(), 2);
+        assert_eq!(fired[0].rule, "fast");
+        assert_eq!(fired[1].rule, "slow");
+        assert_eq!(
+            fired[1].message,
+            "exec rate exceeded 5/min: 5 execs in 60s, last bash"
+        );
+    }
+
+    #[tokio::test]
+    async fn exec_rate_counts_only_matching_execs() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        assert!(
+            parse_rules(
+                "- name: e\n  detector: exec_rate\n  regex: '(curl'\n  rate_per_min: 3\n  median_lifetime: 5\n",
+                Some("yaml")
+            )
+            .is_err()
+        );
+        let cfgs = parse_rules(
+            "- name: downloads\n  detector: exec_rate\n  regex: '^curl$|wget'\n  rate_per_min: 3\n  median_lifetime: 5\n  cooldown: 0\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let run = |pid: u32, comm: &[u8], argv: &[&str]| {
+            let mut name = [0u8; 16];
+            name[..comm.len()].copy_from_slice(comm);
+            let mut event = ProcessEvent::new(ProcessEventWire {
+                pid,
+                ppid: 5_000_000,
+                uid: 0,
+                gid: 0,
+                event_type: EventType::Exec as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm: name,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 0,
+                data2: 0,
+                aux: 0,
+                aux2: 0,
+                cgroup_id: 0,
+            });
+            event.argv = (!argv.is_empty()).then(|| argv.iter().map(|a| a.to_string()).collect());
+            let mut exit = event.clone();
+            exit.event_type = EventType::Exit as u32;
+            (event, exit)
+        };
+
+        // Plenty of execs, none of them downloads.
+        for pid in 5_000_001..5_000_011 {
+            let (exec, exit) = run(pid, b"bash", &[]);
+            engine.on_event(&exec).await.unwrap();
+            engine.on_event(&exit).await.unwrap();
+        }
+        assert!(rx.try_recv().is_err());
+
+        for (pid, comm, argv) in [
+            (5_000_011, &b"curl"[..], &[][..]),
+            (
+                5_000_012,
+                b"sh",
+                &["sh", "-c", "wget -qO- http://203.0.113.9/x"],
+            ),
+            (5_000_013, b"curl", &[]),
+        ] {
+            let (exec, exit) = run(pid, comm, argv);
+            engine.on_event(&exec).await.unwrap();
+            time::advance(Duration::from_millis(100)).await;
+            engine.on_event(&exit).await.unwrap();
+        }
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "exec rate exceeded 3/min: 3 execs matching /^curl$|wget/ in 60s, last curl"
+        );
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn oom_kill_alerts_on_victims_above_min_rss() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: oom\n  detector: oom_kill\n  min_rss_mb: 100\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let mut name = [0u8; 16];
+        name[..4].copy_from_slice(b"java");
+        let mut wire = ProcessEventWire {
+            pid: 5_000_001,
+            ppid: 5_000_000,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::OomKill as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: name,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 50 * 1024 * 1024,
+            data2: 5_000_002,
+            aux: 1,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "victim below min_rss_mb");
+
+        wire.data = 2048 * 1024 * 1024;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "OOM killer killed java (pid 5000001, parent 5000000) holding 2048 MiB RSS; cgroup memory limit, triggered by pid 5000002"
+        );
+
+        wire.event_type = EventType::Exit as u32;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "only OOM kill events count");
+        // Every event is an evaluation, whichever check turned it away.
+        assert_eq!(engine.metrics.rule_stats()[0].evaluations, 3);
+    }
+
+    #[tokio::test]
+    async fn sched_delay_alerts_on_long_runqueue_waits() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules("- name: sched\n  detector: sched_delay\n", Some("yaml")).unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let mut name = [0u8; 16];
+        name[..5].copy_from_slice(b"nginx");
+        let mut wire = ProcessEventWire {
+            pid: 5_000_001,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::SchedLatency as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: name,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 40_000_000,
+            data2: 5_000_007,
+            aux: 3,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "below the default 100 ms");
+
+        wire.data = 250_000_000;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "nginx (pid 5000001, thread 5000007) waited 250 ms on the runqueue of CPU 3 before running"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn subtree_cpu_adds_up_fanned_out_children() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: cpu\n  detector: subtree_cpu_pct\n  threshold: 100\n  duration: 1\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let spans = WindowSpans::for_rules(&engine.snapshot());
+        engine.windows.lock().await.apply_spans(spans);
+        let mut rx = engine.tx.subscribe();
+
+        // make (5000100, under sh 5000050) runs four jobs at 30% each.
+        let (make, sh) = (5_000_100, 5_000_050);
+        for pid in 5_000_201..5_000_205 {
+            let mut fork = wire_event(pid, make, EventType::Fork);
+            fork.data = sh as u64;
+            engine.on_event(&fork).await.unwrap();
+        }
+        let job = |pid| {
+            let mut event = wire_event(pid, make, EventType::Exec);
+            event.set_cpu_percent(Some(30.0));
+            event
+        };
+        for pid in 5_000_201..5_000_205 {
+            engine.on_event(&job(pid)).await.unwrap();
+        }
+        time::advance(Duration::from_secs(2)).await;
+        engine.on_event(&job(5_000_201)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "subtree of pid 5000100 at 120.0% cpu, over 100% for 1s"
+        );
+        assert_eq!(alert.labels["subtree_pid"], "5000100");
+        // sh's subtree was over for the same load; it doesn't alert again.
+        time::advance(Duration::from_secs(2)).await;
+        engine.on_event(&job(5_000_202)).await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        // Exited jobs stop counting.
+        for pid in 5_000_202..5_000_205 {
+            engine
+                .on_event(&wire_event(pid, make, EventType::Exit))
+                .await
+                .unwrap();
+        }
+        let windows = engine.windows.lock().await;
+        let tracker = windows.subtree_cpu.as_ref().unwrap();
+        assert_eq!(tracker.total(make), 30.0);
+        assert_eq!(tracker.total(sh), 30.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn subtree_rss_uses_rss_of_mem_growth_events() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: rss\n  detector: subtree_rss_mb\n  threshold: 1024\n  duration: 1\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let mut name = [0u8; 16];
+        name[..6].copy_from_slice(b"python");
+        let mut wire = ProcessEventWire {
+            pid: 5_000_040,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::MemGrowth as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: name,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 256 << 20,
+            data2: 1_500 << 20,
+            aux: 800,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        time::advance(Duration::from_secs(2)).await;
+        wire.data2 = 1_800 << 20;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(
+            alert.message,
+            "rss mb 1024 over 1s; python (pid 5000040) grew 256 MiB in 800 ms to 1800 MiB"
+        );
+    }
+
+    #[tokio::test]
+    async fn direct_reclaim_alerts_on_stalls_adding_up_in_window() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: reclaim\n  detector: direct_reclaim\n  threshold_ms: 300\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let mut name = [0u8; 16];
+        name[..4].copy_from_slice(b"java");
+        let mut wire = ProcessEventWire {
+            pid: 5_000_002,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::DirectReclaim as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: name,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 200_000_000,
+            data2: 32,
+            aux: 0,
+            aux2: 5_000_002,
+            cgroup_id: 0,
+        };
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "200 ms is below the threshold");
+        assert_eq!(engine.metrics.rule_stats()[0].evaluations, 1);
+        let stats = engine.window_stats().await;
+        assert_eq!(stats.rules[0].metric, "stall_ms");
+        assert_eq!(stats.rules[0].current, 200.0);
+        assert_eq!(stats.rules[0].key.as_deref(), Some("pid 5000002"));
+
+        wire.pid = 5_000_003;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "stalls are summed per process");
 
-        for rule in &self.rules {
-            match &rule.cfg.detector {
-                Detector::SystemPsiCpu {
-                    threshold_pct,
-                    duration,
-                } => {
-                    let current = snapshot.psi_cpu_some_avg10;
-                    let key = rule.cfg.name.clone();
-                    if current > *threshold_pct {
-                        let breach_start = state.psi_breach.entry(key.clone()).or_insert(now);
-                        let elapsed = now.duration_since(*breach_start).as_secs();
-                        if elapsed >= *duration {
-                            state.psi_breach.remove(&key);
-                            drop(state);
-                            self.emit_alert(
-                                &rule.cfg,
-                                format!(
-                                    "CPU PSI {:.1}% > {:.1}% sustained {}s",
-                                    current, threshold_pct, duration
-                                ),
-                            )
-                            .await;
-                            state = self.state.lock().await;
-                        }
-                    } else {
-                        state.psi_breach.remove(&key);
-                    }
-                }
-                Detector::SystemPsiMemory {
-                    threshold_pct,
-                    duration,
-                } => {
-                    let current = snapshot.psi_memory_full_avg10;
-                    let key = rule.cfg.name.clone();
-                    if current > *threshold_pct {
-                        let breach_start = state.psi_breach.entry(key.clone()).or_insert(now);
-                        let elapsed = now.duration_since(*breach_start).as_secs();
-                        if elapsed >= *duration {
-                            state.psi_breach.remove(&key);
-                            drop(state);
-                            self.emit_alert(
-                                &rule.cfg,
-                                format!(
-                                    "memory PSI (full) {:.1}% > {:.1}% sustained {}s",
-                                    current, threshold_pct, duration
-                                ),
-                            )
-                            .await;
-                            state = self.state.lock().await;
-                        }
-                    } else {
-                        state.psi_breach.remove(&key);
-                    }
-                }
-                Detector::SystemPsiIo {
-                    threshold_pct,
-                    duration,
-                } => {
-                    let current = snapshot.psi_io_full_avg10;
-                    let key = rule.cfg.name.clone();
-                    if current > *threshold_pct {
-                        let breach_start = state.psi_breach.entry(key.clone()).or_insert(now);
-                        let elapsed = now.duration_since(*breach_start).as_secs();
-                        if elapsed >= *duration {
-                            state.psi_breach.remove(&key);
-                            drop(state);
-                            self.emit_alert(
-                                &rule.cfg,
-                                format!(
-                                    "IO PSI (full) {:.1}% > {:.1}% sustained {}s",
-                                    current, threshold_pct, duration
-                                ),
-                            )
-                            .await;
-                            state = self.state.lock().await;
-                        }
-                    } else {
-                        state.psi_breach.remove(&key);
-                    }
-                }
-                _ => {}
-            }
-        }
+        wire.data = 150_000_000;
+        wire.data2 = 64;
+        wire.aux = 2;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "reclaim");
+        assert_eq!(
+            alert.message,
+            "java (pid 5000003) stalled 350 ms in direct reclaim over the last 10s (2 stalls, latest reclaimed 64 pages at order 2)"
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::PERCENT_MILLI_UNKNOWN;
-    use tokio::time::{self, Duration};
+    #[tokio::test]
+    async fn block_io_rate_sums_queued_bytes_per_device_or_process() {
+        use linnix_ai_ebpf_common::{BlockOp, EventType};
+        assert!(
+            parse_rules(
+                "- name: io\n  detector: block_io_rate\n  threshold_mb_per_sec: 10\n  per: cgroup\n",
+                Some("yaml")
+            )
+            .is_err()
+        );
+        let cfgs = parse_rules(
+            "- name: disk\n  detector: block_io_rate\n  threshold_mb_per_sec: 10\n\
+             - name: writer\n  detector: block_io_rate\n  threshold_mb_per_sec: 10\n  per: process\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
 
-    fn test_engine(cooldown: u64) -> RuleEngine {
-        let cfg = RuleConfig {
-            name: "test".into(),
-            severity: Severity::Low,
-            cooldown,
-            detector: Detector::ForksPerSec {
-                threshold: 1,
-                duration: 1,
-            },
+        let mut name = [0u8; 16];
+        name[..2].copy_from_slice(b"dd");
+        let mut wire = ProcessEventWire {
+            pid: 5_000_010,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::BlockIo as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: name,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 60 << 20,
+            data2: 2048,
+            aux: BlockOp::Queue as u32,
+            aux2: 8 << 20,
+            cgroup_id: 0,
         };
-        let (tx, _rx) = broadcast::channel(16);
-        RuleEngine {
-            rules: vec![Rule { cfg }],
-            state: Mutex::new(RuleState {
-                fork_events: VecDeque::new(),
-                exec_events: VecDeque::new(),
-                exec_start: HashMap::new(),
-                exec_completions: VecDeque::new(),
-                forks_by_ppid: HashMap::new(),
-                cpu_exceed: HashMap::new(),
-                rss_exceed: HashMap::new(),
-                active: HashMap::new(),
-                psi_breach: HashMap::new(),
-            }),
-            tx,
-            alerts_file: "/dev/null".into(),
-            journald: false,
-            host: "test-host".into(),
-            fork_window_secs: 1,
-            exec_window_secs: 60,
-            completion_window_secs: 60,
-            runaway_window_secs: 1,
-            metrics: Arc::new(Metrics::new()),
-            total_memory_bytes: Some(16 * 1024 * 1024 * 1024),
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        // The same bytes issued and completed don't count again.
+        for op in [BlockOp::Issue, BlockOp::Complete] {
+            wire.aux = op as u32;
+            engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
         }
+        assert!(rx.try_recv().is_err(), "6 MB/s is below the threshold");
+        let stats = engine.window_stats().await;
+        let disk = stats.rules.iter().find(|w| w.rule == "disk").unwrap();
+        assert_eq!(disk.metric, "mb_per_sec");
+        assert_eq!(disk.current, 6.0);
+        assert_eq!(disk.key.as_deref(), Some("dev 8:0"));
+
+        wire.pid = 5_000_011;
+        wire.aux = BlockOp::Queue as u32;
+        wire.data = 50 << 20;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(fired.len(), 1, "neither process reaches 10 MB/s alone");
+        assert_eq!(fired[0].rule, "disk");
+        assert_eq!(
+            fired[0].message,
+            "block I/O on dev 8:0 at 11.0 MB/s over 10s, latest from dd (pid 5000011)"
+        );
     }
 
     #[tokio::test]
-    async fn cooldown_suppresses_alerts() {
-        time::pause();
-        let engine = test_engine(60);
+    async fn kill_sweep_alerts_on_many_distinct_targets() {
+        use linnix_ai_ebpf_common::EventType;
+        let cfgs = parse_rules(
+            "- name: sweep\n  detector: kill_sweep\n  threshold: 3\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
         let mut rx = engine.tx.subscribe();
-        let base = ProcessEventWire {
-            pid: 0,
-            ppid: 0,
+
+        let mut name = [0u8; 16];
+        name[..5].copy_from_slice(b"pkill");
+        let mut wire = ProcessEventWire {
+            pid: 5_000_010,
+            ppid: 1,
             uid: 0,
             gid: 0,
-            event_type: linnix_ai_ebpf_common::EventType::Fork as u32,
+            event_type: EventType::Signal as u32,
             ts_ns: 0,
             seq: 0,
-            comm: [0; 16],
+            comm: name,
             exit_time_ns: 0,
             cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
             mem_pct_milli: PERCENT_MILLI_UNKNOWN,
-            data: 0,
+            data: 5_000_020,
             data2: 0,
-            aux: 0,
+            aux: 15,
             aux2: 0,
+            cgroup_id: 0,
         };
-        let event = ProcessEvent::new(base);
-        engine.on_event(&event).await;
-        engine.on_event(&event).await;
-        let _first = rx.recv().await.unwrap();
+        // The same target twice, itself and a kernel-raised signal don't add up.
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        wire.data = 5_000_010;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        wire.data = 5_000_021;
+        wire.data2 = 0x80;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err());
+
+        wire.data2 = 0;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "two distinct targets so far");
+        assert_eq!(engine.metrics.rule_stats()[0].evaluations, 5);
+        let stats = engine.window_stats().await;
+        assert_eq!(stats.rules[0].current, 2.0);
+        assert_eq!(stats.rules[0].key.as_deref(), Some("pid 5000010"));
+
+        wire.data = 5_000_022;
+        wire.aux = 9;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "sweep");
+        assert_eq!(
+            alert.message,
+            "pkill (pid 5000010) signalled 3 processes in 10s, latest SIGKILL to pid 5000022"
+        );
+    }
+
+    #[tokio::test]
+    async fn privilege_escalation_alerts_outside_ignored_helpers() {
+        use linnix_ai_ebpf_common::EventType;
+        let me = crashloop::read_comm(std::process::id()).unwrap();
+        let cfgs = parse_rules(
+            &format!(
+                "- name: any\n  detector: privilege_escalation\n  cooldown: 0\n\
+                 - name: under_me\n  detector: privilege_escalation\n  ancestor: {me}\n  cooldown: 0\n\
+                 - name: under_nginx\n  detector: privilege_escalation\n  ancestor: nginx\n  cooldown: 0\n"
+            ),
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let mut name = [0u8; 16];
+        name[..4].copy_from_slice(b"sudo");
+        let mut wire = ProcessEventWire {
+            pid: 5_000_030,
+            ppid: std::process::id(),
+            uid: 1000,
+            gid: 1000,
+            event_type: EventType::Creds as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: name,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 1 << 21,
+            data2: 0,
+            aux: 0,
+            aux2: 1000,
+            cgroup_id: 0,
+        };
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "sudo is expected to escalate");
+
+        name = [0u8; 16];
+        name[..4].copy_from_slice(b"bash");
+        wire.comm = name;
+        (wire.uid, wire.aux, wire.data) = (0, 1000, 0);
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "dropping root is not an escalation");
+
+        (wire.uid, wire.aux, wire.data) = (1000, 0, 1 << 21);
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let mut fired: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|a| a.rule)
+            .collect();
+        fired.sort();
+        assert_eq!(fired, ["any", "under_me"]);
+    }
+
+    #[tokio::test]
+    async fn sensitive_file_access_matches_paths_and_ops() {
+        use linnix_ai_ebpf_common::EventType;
         assert!(
-            rx.try_recv().is_err(),
-            "second alert suppressed by cooldown"
+            parse_rules(
+                "- name: f\n  detector: sensitive_file_access\n  ops: [read]\n",
+                Some("yaml")
+            )
+            .is_err()
         );
-        time::advance(Duration::from_secs(61)).await;
-        engine.on_event(&event).await;
-        assert!(rx.recv().await.is_ok(), "alert after cooldown");
+        let cfgs = parse_rules(
+            "- name: keys\n  detector: sensitive_file_access\n  cooldown: 0\n\
+             - name: reads\n  detector: sensitive_file_access\n  paths: [/etc/shadow]\n  ops: [open]\n  cooldown: 0\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let access = |comm: &[u8], op: u32, path: &str| {
+            let mut name = [0u8; 16];
+            name[..comm.len()].copy_from_slice(comm);
+            let mut event = ProcessEvent::new(ProcessEventWire {
+                pid: 5_000_040,
+                ppid: std::process::id(),
+                uid: 33,
+                gid: 33,
+                event_type: EventType::FileAccess as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm: name,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 4242,
+                data2: 1,
+                aux: op,
+                aux2: 0,
+                cgroup_id: 0,
+            });
+            event.path = Some(path.to_string());
+            event
+        };
+        let fired = |rx: &mut broadcast::Receiver<Alert>| -> Vec<String> {
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|a| a.rule)
+                .collect()
+        };
+
+        engine
+            .on_event(&access(b"sh", 1, "/root/.ssh/authorized_keys"))
+            .await
+            .unwrap();
+        let alerts: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].rule, "keys");
+        assert!(
+            alerts[0]
+                .message
+                .ends_with("opened for writing /root/.ssh/authorized_keys"),
+            "{}",
+            alerts[0].message
+        );
+
+        engine
+            .on_event(&access(b"ssh-keygen", 1, "/etc/ssh/ssh_host_ed25519_key"))
+            .await
+            .unwrap();
+        assert!(fired(&mut rx).is_empty(), "ssh-keygen is expected to write");
+
+        engine
+            .on_event(&access(b"sh", 1, "/etc/shadowed"))
+            .await
+            .unwrap();
+        assert!(fired(&mut rx).is_empty(), "not below /etc/shadow");
+
+        engine
+            .on_event(&access(b"cat", 0, "/etc/shadow"))
+            .await
+            .unwrap();
+        assert_eq!(fired(&mut rx), ["reads"]);
     }
 
     #[tokio::test]
-    async fn dedupe_prevents_duplicates() {
+    async fn kernel_load_filters_kind_comm_and_name() {
+        use linnix_ai_ebpf_common::{EventType, KERNEL_LOAD_NAME_LEN, kernel_load_name_fields};
+        time::pause();
+        assert!(
+            parse_rules(
+                "- name: k\n  detector: kernel_load\n  kind: driver\n",
+                Some("yaml")
+            )
+            .is_err()
+        );
+        let cfgs = parse_rules(
+            "- name: any\n  detector: kernel_load\n  ignore_names: [nf_tables]\n  cooldown: 0\n\
+             - name: modules\n  detector: kernel_load\n  kind: module\n  ignore: []\n  cooldown: 0\n",
+            Some("yaml"),
+        )
+        .unwrap();
         let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
         let mut rx = engine.tx.subscribe();
-        let base = ProcessEventWire {
-            pid: 0,
-            ppid: 0,
+
+        let load = |comm: &[u8], kind: u32, name: &str| {
+            let mut comm_bytes = [0u8; 16];
+            comm_bytes[..comm.len()].copy_from_slice(comm);
+            let mut name_bytes = [0u8; KERNEL_LOAD_NAME_LEN];
+            name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+            let (data, data2) = kernel_load_name_fields(name_bytes);
+            ProcessEvent::new(ProcessEventWire {
+                pid: 5_000_050,
+                ppid: std::process::id(),
+                uid: 0,
+                gid: 0,
+                event_type: EventType::KernelLoad as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm: comm_bytes,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data,
+                data2,
+                aux: kind,
+                aux2: if kind == 0 { 1 << 12 } else { 2 },
+                cgroup_id: 0,
+            })
+        };
+        let fired = |rx: &mut broadcast::Receiver<Alert>| -> Vec<String> {
+            let mut rules: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|a| a.rule)
+                .collect();
+            rules.sort();
+            rules
+        };
+
+        engine
+            .on_event(&load(b"insmod", 0, "rootkit"))
+            .await
+            .unwrap();
+        let alerts: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(alerts.len(), 2);
+        assert!(
+            alerts[0]
+                .message
+                .starts_with("kernel load: insmod (pid 5000050")
+                && alerts[0].message.ends_with("loaded module rootkit [O]"),
+            "{}",
+            alerts[0].message
+        );
+
+        // Past the 100ms floor of a zero cooldown.
+        time::advance(Duration::from_secs(1)).await;
+        engine
+            .on_event(&load(b"systemd-udevd", 0, "nf_tables"))
+            .await
+            .unwrap();
+        assert_eq!(fired(&mut rx), ["modules"]);
+
+        engine
+            .on_event(&load(b"systemd", 1, "sd_devices"))
+            .await
+            .unwrap();
+        assert!(
+            fired(&mut rx).is_empty(),
+            "systemd is expected to load programs"
+        );
+
+        engine
+            .on_event(&load(b"agent", 1, "trace_exec"))
+            .await
+            .unwrap();
+        assert_eq!(fired(&mut rx), ["any"]);
+    }
+
+    #[tokio::test]
+    async fn connection_alerts_on_matching_connections() {
+        use linnix_ai_ebpf_common::{EventType, conn_direction, connection_addr_fields};
+        assert!(
+            parse_rules(
+                "- name: c\n  detector: connection\n  direction: sideways\n",
+                Some("yaml")
+            )
+            .is_err()
+        );
+        let cfgs = parse_rules(
+            "- name: curl_https\n  detector: connection\n  comm: curl\n  port: 443\n  cooldown: 0\n\
+             - name: from_cron\n  detector: connection\n  parent: cron\n  cooldown: 0\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        let mut rx = engine.tx.subscribe();
+
+        let mut name = [0u8; 16];
+        name[..4].copy_from_slice(b"curl");
+        let remote: std::net::Ipv4Addr = "203.0.113.9".parse().unwrap();
+        let (data, data2) = connection_addr_fields(remote.to_ipv6_mapped().octets());
+        let mut wire = ProcessEventWire {
+            pid: 5_000_001,
+            ppid: 5_000_000,
             uid: 0,
             gid: 0,
-            event_type: linnix_ai_ebpf_common::EventType::Fork as u32,
+            event_type: EventType::Connection as u32,
             ts_ns: 0,
             seq: 0,
-            comm: [0; 16],
+            comm: name,
             exit_time_ns: 0,
             cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
             mem_pct_milli: PERCENT_MILLI_UNKNOWN,
-            data: 0,
-            data2: 0,
-            aux: 0,
-            aux2: 0,
+            data,
+            data2,
+            aux: 80,
+            aux2: conn_direction::OUTBOUND,
+            cgroup_id: 0,
         };
-        let event = ProcessEvent::new(base);
-        let f1 = engine.on_event(&event);
-        let f2 = engine.on_event(&event);
-        futures_util::join!(f1, f2);
-        let _first = rx.recv().await.unwrap();
-        assert!(rx.try_recv().is_err(), "duplicate alert suppressed");
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "port 80, and the parent isn't cron");
+
+        wire.aux2 = conn_direction::INBOUND | (443 << 16);
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(rx.try_recv().is_err(), "only outbound by default");
+
+        wire.aux = 443;
+        wire.aux2 = conn_direction::OUTBOUND;
+        engine.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "curl_https");
+        assert_eq!(
+            alert.message,
+            "connection: curl (pid 5000001, parent pid 5000000) outbound to 203.0.113.9:443"
+        );
+        assert!(rx.try_recv().is_err());
     }
 
-    #[test]
-    fn parses_rules_from_yaml_and_toml() {
-        let yaml = r#"- name: fork_storm
-  detector: forks_per_sec
-  threshold: 5
-  duration: 1
-  severity: high
-  cooldown: 10
-- name: cpu_spin
-  detector: subtree_cpu_pct
-  threshold: 90.0
-  duration: 15
-  severity: medium
-"#;
-        let toml = r#"
-[[rules]]
-name = "fork_storm"
-detector = "forks_per_sec"
-threshold = 5
-duration = 1
-severity = "high"
-cooldown = 10
+    #[tokio::test]
+    async fn fingerprint_flags_new_binaries_and_parents_after_learning() {
+        use linnix_ai_ebpf_common::EventType;
+        time::pause();
+        let cfgs = parse_rules(
+            "- name: new_binary\n  detector: new_binary_executed\n  learning_secs: 60\n- name: parent\n  detector: unusual_parent\n  learning_secs: 60\n",
+            Some("yaml"),
+        )
+        .unwrap();
+        let engine = test_engine(0);
+        *engine.rules.write().unwrap() = Arc::new(rules_from(cfgs, &engine.metrics));
+        engine.windows.lock().await.fingerprint = Some(WorkloadFingerprint::new(
+            Instant::now(),
+            MAX_FINGERPRINT_PATTERNS,
+        ));
+        let mut rx = engine.tx.subscribe();
 
-[[rules]]
-name = "cpu_spin"
-detector = "subtree_cpu_pct"
-threshold = 90.0
-duration = 15
-severity = "medium"
-"#;
+        // Parents are real processes (init and this test) so their comm can
+        // be read; the execs themselves are not.
+        let exec = |pid: u32, ppid: u32, comm: &[u8]| {
+            let mut name = [0u8; 16];
+            name[..comm.len()].copy_from_slice(comm);
+            ProcessEvent::new(ProcessEventWire {
+                pid,
+                ppid,
+                uid: 0,
+                gid: 0,
+                event_type: EventType::Exec as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm: name,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 0,
+                data2: 0,
+                aux: 0,
+                aux2: 0,
+                cgroup_id: 0,
+            })
+        };
+        let me = std::process::id();
+        engine
+            .on_event(&exec(5_000_001, me, b"nginx"))
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err(), "still learning");
+        assert_eq!(engine.window_stats().await.rules[0].current, 0.0);
 
-        let yaml_rules = parse_rules(yaml, Some("yaml")).expect("yaml parses");
-        let toml_rules = parse_rules(toml, Some("toml")).expect("toml parses");
-        assert_eq!(yaml_rules.len(), 2, "yaml rule count");
-        assert_eq!(toml_rules.len(), 2, "toml rule count");
-        assert_eq!(yaml_rules[0].name, "fork_storm");
-        assert_eq!(toml_rules[0].name, "fork_storm");
-        assert_eq!(yaml_rules[1].name, "cpu_spin");
-        assert_eq!(toml_rules[1].name, "cpu_spin");
+        time::advance(Duration::from_secs(61)).await;
+        engine
+            .on_event(&exec(5_000_002, me, b"nginx"))
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err(), "known pattern");
+        assert_eq!(engine.window_stats().await.rules[0].current, 60.0);
+
+        engine
+            .on_event(&exec(5_000_003, me, b"xmrig"))
+            .await
+            .unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "new_binary");
+        assert!(
+            alert
+                .message
+                .starts_with("new binary xmrig executed (pid 5000003) under ")
+        );
+        engine
+            .on_event(&exec(5_000_004, me, b"xmrig"))
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err(), "reported once");
+
+        let me_comm = crashloop::read_comm(me).unwrap();
+        if crashloop::read_comm(1).is_some_and(|init| init != me_comm) {
+            engine
+                .on_event(&exec(5_000_005, 1, b"nginx"))
+                .await
+                .unwrap();
+            let alert = rx.try_recv().unwrap();
+            assert_eq!(alert.rule, "parent");
+            assert!(
+                alert
+                    .message
+                    .starts_with("unusual parent: nginx (pid 5000005) started by ")
+            );
+            assert!(
+                alert
+                    .message
+                    .ends_with(&format!("usually started by {me_comm}"))
+            );
+        }
     }
 }
@@ -0,0 +1,53 @@
+//! `POST /changes` and `GET /changes`: deploys and config changes reported
+//! by CI/CD, mentioned with the insights and incidents that follow them.
+
+use super::AppState;
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use cognitod::changes::{ChangeEvent, ChangeLog};
+use cognitod::redaction::Destination;
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ChangesQuery {
+    /// Epoch seconds; defaults to everything kept.
+    since: Option<u64>,
+    service: Option<String>,
+    limit: Option<usize>,
+}
+
+fn change_log(app: &AppState) -> Result<&Arc<ChangeLog>, (StatusCode, String)> {
+    app.changes.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Change log not available".to_string(),
+        )
+    })
+}
+
+pub async fn post_change(
+    State(app): State<Arc<AppState>>,
+    Json(change): Json<ChangeEvent>,
+) -> Result<(StatusCode, Json<ChangeEvent>), (StatusCode, String)> {
+    let log = change_log(&app)?;
+    if change.service.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "service is required".to_string()));
+    }
+    let change = log.record(change);
+    log::info!("[changes] recorded {change}");
+    Ok((StatusCode::CREATED, Json(change)))
+}
+
+pub async fn list_changes(
+    State(app): State<Arc<AppState>>,
+    Query(query): Query<ChangesQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let log = change_log(&app)?;
+    let mut changes = log.list(query.since.unwrap_or(0), query.service.as_deref());
+    changes.truncate(query.limit.unwrap_or(100));
+    Ok(Json(app.redaction.redact_json(Destination::Api, &changes)))
+}
@@ -0,0 +1,302 @@
+//! Grafana JSON datasource endpoints.
+//!
+//! Speaks the protocol of the `simpod-json-datasource` plugin under
+//! `/grafana`, so panels can chart event rates, alert counts and the top
+//! processes straight from cognitod without an intermediate TSDB. The same
+//! `POST /grafana/query` body also works from the Infinity plugin.
+//!
+//! Series are built from the in-memory context and alert history, so the
+//! queryable range is bounded by their retention (a few minutes of events,
+//! the last alerts kept in the ring buffer).
+
+use super::{AppState, EventKind};
+use axum::{Json, extract::State, http::StatusCode};
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const EVENT_TARGETS: &[&str] = &[
+    "events.total",
+    "events.exec",
+    "events.fork",
+    "events.exit",
+    "events.net",
+    "events.file_io",
+    "events.syscall",
+    "events.block_io",
+    "events.page_fault",
+    "events.oom_kill",
+    "events.sched_latency",
+    "events.off_cpu",
+    "events.connection",
+    "events.direct_reclaim",
+    "events.signal",
+    "events.creds",
+    "events.mem_growth",
+    "events.file_access",
+    "events.kernel_load",
+];
+
+const ALERT_TARGETS: &[&str] = &[
+    "alerts.total",
+    "alerts.info",
+    "alerts.low",
+    "alerts.medium",
+    "alerts.high",
+];
+
+const TABLE_TARGETS: &[&str] = &["top_cpu", "top_rss"];
+
+/// Default bucket width when the panel does not send `intervalMs`.
+const DEFAULT_INTERVAL_MS: u64 = 10_000;
+/// Upper bound on buckets per series so a tiny interval over a wide range
+/// cannot allocate unboundedly.
+const MAX_BUCKETS: u64 = 2_000;
+const TOP_LIMIT: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct QueryRequest {
+    #[serde(default)]
+    range: Option<QueryRange>,
+    #[serde(default, rename = "intervalMs")]
+    interval_ms: Option<u64>,
+    #[serde(default)]
+    targets: Vec<QueryTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryRange {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryTarget {
+    #[serde(default)]
+    target: String,
+    #[serde(default, rename = "refId")]
+    ref_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct SearchRequest {
+    #[serde(default)]
+    target: String,
+}
+
+fn all_targets() -> impl Iterator<Item = &'static str> {
+    EVENT_TARGETS
+        .iter()
+        .chain(ALERT_TARGETS)
+        .chain(TABLE_TARGETS)
+        .copied()
+}
+
+/// GET /grafana - datasource "Test connection" probe.
+pub(super) async fn health() -> StatusCode {
+    StatusCode::OK
+}
+
+/// POST /grafana/search - plain list of target names, filtered by prefix.
+pub(super) async fn search(body: Option<Json<SearchRequest>>) -> Json<Vec<&'static str>> {
+    let filter = body.map(|Json(b)| b.target).unwrap_or_default();
+    Json(all_targets().filter(|t| t.starts_with(&filter)).collect())
+}
+
+/// POST /grafana/metrics - target list in the newer `{label, value}` form.
+pub(super) async fn metrics() -> Json<Vec<Value>> {
+    Json(
+        all_targets()
+            .map(|t| json!({ "label": t, "value": t }))
+            .collect(),
+    )
+}
+
+/// POST /grafana/query - time series and tables for the requested targets.
+pub(super) async fn query(
+    State(app_state): State<Arc<AppState>>,
+    Json(req): Json<QueryRequest>,
+) -> Result<Json<Vec<Value>>, (StatusCode, String)> {
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let (from_ms, to_ms) = match &req.range {
+        Some(range) => (parse_time_ms(&range.from)?, parse_time_ms(&range.to)?),
+        None => (now_ms.saturating_sub(5 * 60 * 1000), now_ms),
+    };
+    if from_ms > to_ms {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "range.from must not be after range.to".to_string(),
+        ));
+    }
+    let buckets = Buckets::new(from_ms, to_ms, req.interval_ms);
+
+    // Only fetch what the targets actually need.
+    let wants_events = req.targets.iter().any(|t| t.target.starts_with("events."));
+    let wants_alerts = req.targets.iter().any(|t| t.target.starts_with("alerts."));
+    let events = if wants_events {
+        app_state
+            .context
+            .event_times(from_ms * 1_000_000, to_ms * 1_000_000 + 999_999)
+    } else {
+        Vec::new()
+    };
+    let alerts = if wants_alerts {
+        app_state.alert_history.get_all().await
+    } else {
+        Vec::new()
+    };
+
+    let mut out = Vec::with_capacity(req.targets.len());
+    for t in &req.targets {
+        let target = t.target.as_str();
+        let value = if let Some(kind) = target.strip_prefix("events.") {
+            let points = events
+                .iter()
+                .filter(|(_, ty)| kind == "total" || event_kind_name(*ty) == kind)
+                .map(|(ts_ns, _)| ts_ns / 1_000_000);
+            series(target, buckets.count(points))
+        } else if let Some(sev) = target.strip_prefix("alerts.") {
+            let points = alerts
+                .iter()
+                .filter(|a| sev == "total" || a.severity.eq_ignore_ascii_case(sev))
+                .map(|a| a.timestamp * 1000);
+            series(target, buckets.count(points))
+        } else if target == "top_cpu" {
+            let rows = app_state.context.top_cpu_processes(TOP_LIMIT);
+            process_table(t, "cpu_pct", rows)
+        } else if target == "top_rss" {
+            let rows = app_state.context.top_rss_processes(TOP_LIMIT);
+            process_table(t, "mem_pct", rows)
+        } else {
+            return Err((StatusCode::BAD_REQUEST, format!("unknown target: {target}")));
+        };
+        out.push(value);
+    }
+
+    Ok(Json(out))
+}
+
+/// Accepts RFC 3339 (what Grafana sends) or raw epoch milliseconds.
+fn parse_time_ms(s: &str) -> Result<u64, (StatusCode, String)> {
+    if let Ok(ms) = s.parse::<u64>() {
+        return Ok(ms);
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.timestamp_millis().max(0) as u64)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid time {s:?}: {e}")))
+}
+
+fn event_kind_name(event_type: u32) -> String {
+    serde_json::to_value(EventKind::from(event_type))
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Fixed-width buckets aligned to `interval` covering `[from, to]` in ms.
+struct Buckets {
+    start: u64,
+    width: u64,
+    len: usize,
+}
+
+impl Buckets {
+    fn new(from_ms: u64, to_ms: u64, interval_ms: Option<u64>) -> Self {
+        let span = to_ms - from_ms;
+        let mut width = interval_ms.unwrap_or(DEFAULT_INTERVAL_MS).max(1);
+        if span / width >= MAX_BUCKETS {
+            width = span / MAX_BUCKETS + 1;
+        }
+        let start = from_ms - from_ms % width;
+        let len = ((to_ms - start) / width + 1) as usize;
+        Self { start, width, len }
+    }
+
+    /// Grafana datapoints `[count, bucket_start_ms]` for the given timestamps.
+    fn count(&self, timestamps_ms: impl Iterator<Item = u64>) -> Vec<[u64; 2]> {
+        let mut counts = vec![0u64; self.len];
+        for ts in timestamps_ms {
+            if ts < self.start {
+                continue;
+            }
+            let idx = ((ts - self.start) / self.width) as usize;
+            if let Some(c) = counts.get_mut(idx) {
+                *c += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| [c, self.start + i as u64 * self.width])
+            .collect()
+    }
+}
+
+fn series(target: &str, datapoints: Vec<[u64; 2]>) -> Value {
+    json!({ "target": target, "datapoints": datapoints })
+}
+
+fn process_table(
+    target: &QueryTarget,
+    value_column: &str,
+    rows: Vec<crate::context::ProcessMemorySummary>,
+) -> Value {
+    json!({
+        "type": "table",
+        "refId": target.ref_id,
+        "columns": [
+            { "text": "pid", "type": "number" },
+            { "text": "comm", "type": "string" },
+            { "text": value_column, "type": "number" },
+        ],
+        "rows": rows
+            .into_iter()
+            .map(|p| json!([p.pid, p.comm, p.mem_percent]))
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_align_to_interval_and_count() {
+        let b = Buckets::new(10_500, 40_000, Some(10_000));
+        assert_eq!(b.start, 10_000);
+        let points = b.count([10_600, 19_999, 20_000, 39_000, 5_000].into_iter());
+        assert_eq!(
+            points,
+            vec![[2, 10_000], [1, 20_000], [1, 30_000], [0, 40_000]]
+        );
+    }
+
+    #[test]
+    fn tiny_interval_is_widened_to_bucket_cap() {
+        let b = Buckets::new(0, 3_600_000, Some(1));
+        assert!(b.len as u64 <= MAX_BUCKETS + 1);
+    }
+
+    #[test]
+    fn parses_rfc3339_and_epoch_ms() {
+        assert_eq!(
+            parse_time_ms("2024-01-01T00:00:10.500Z").unwrap(),
+            1_704_067_210_500
+        );
+        assert_eq!(parse_time_ms("1704067210500").unwrap(), 1_704_067_210_500);
+        assert!(parse_time_ms("yesterday").is_err());
+    }
+
+    #[test]
+    fn event_kind_names_match_targets() {
+        for ty in 0..8u32 {
+            let name = format!("events.{}", event_kind_name(ty));
+            assert!(EVENT_TARGETS.contains(&name.as_str()), "{name}");
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! `GET /events/history`: filtered lookups over the recent event history.
+//!
+//! Answers "what execed between 02:00 and 02:05" from the context store
+//! instead of a live `/events` subscription. The history is in memory, so
+//! only the last few minutes are queryable; `oldest` in the response says
+//! how far back it currently reaches and `partial` flags windows starting
+//! before that.
+
+use super::{AppState, ProcessEventSse};
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone, Utc};
+use cognitod::redaction::Destination;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const DEFAULT_LIMIT: usize = 1000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    /// Start of the window (default: oldest retained event).
+    since: Option<String>,
+    /// End of the window (default: now).
+    until: Option<String>,
+    /// Comma-separated event types, e.g. `exec,exit`.
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    /// Substring of the process name.
+    comm: Option<String>,
+    pid: Option<u32>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct HistoryEvent {
+    /// When cognitod received the event (RFC 3339).
+    time: String,
+    #[serde(flatten)]
+    event: ProcessEventSse,
+}
+
+#[derive(Serialize)]
+struct HistoryResponse {
+    oldest: Option<String>,
+    partial: bool,
+    truncated: bool,
+    events: Vec<HistoryEvent>,
+}
+
+pub async fn events_history(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let now = Local::now();
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, e);
+    let from_ns = match &query.since {
+        Some(s) => parse_time_ns(s, now).map_err(bad_request)?,
+        None => 0,
+    };
+    let to_ns = match &query.until {
+        Some(s) => parse_time_ns(s, now).map_err(bad_request)?,
+        None => u64::MAX,
+    };
+    if from_ns > to_ns {
+        return Err(bad_request("since is after until".to_string()));
+    }
+    let types: Option<Vec<String>> = query.event_type.as_deref().map(|t| {
+        t.split(',')
+            .map(type_key)
+            .filter(|t| !t.is_empty())
+            .collect()
+    });
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).max(1);
+
+    let mut events: Vec<HistoryEvent> = app_state
+        .context
+        .events_between(from_ns, to_ns)
+        .into_iter()
+        .map(|(t, e)| HistoryEvent {
+            time: format_ns(t),
+            event: ProcessEventSse::from(e),
+        })
+        .filter(|h| {
+            let e = &h.event;
+            query.pid.is_none_or(|pid| e.pid == pid)
+                && query.comm.as_deref().is_none_or(|c| e.comm.contains(c))
+                && types.as_ref().is_none_or(|types| {
+                    types.iter().any(|t| {
+                        *t == type_key(&e.event_type_name) || *t == e.event_type.to_string()
+                    })
+                })
+        })
+        .collect();
+    let truncated = events.len() > limit;
+    events.truncate(limit);
+
+    let oldest = app_state.context.oldest_event_ns();
+    let response = HistoryResponse {
+        oldest: oldest.map(format_ns),
+        partial: query.since.is_some() && oldest.is_some_and(|o| from_ns < o),
+        truncated,
+        events,
+    };
+    Ok(Json(
+        app_state.redaction.redact_json(Destination::Api, &response),
+    ))
+}
+
+/// `exec`, `file_io` and `FileIo` all compare equal to `fileio`.
+fn type_key(name: &str) -> String {
+    name.trim().replace('_', "").to_ascii_lowercase()
+}
+
+fn format_ns(ns: u64) -> String {
+    Utc.timestamp_nanos(ns as i64)
+        .with_timezone(&Local)
+        .to_rfc3339()
+}
+
+/// Nanoseconds since the epoch for a relative age (`90s`, `15m`, `2h`,
+/// `1d`, meaning that long before `now`), a local wall-clock time
+/// (`02:00`, `02:00:30`; the most recent such moment), RFC 3339 or epoch
+/// seconds.
+fn parse_time_ns(s: &str, now: DateTime<Local>) -> Result<u64, String> {
+    let s = s.trim();
+    let invalid = || format!("invalid time {s:?} (use e.g. 15m, 02:00, RFC 3339 or epoch seconds)");
+    let to_ns = |dt: DateTime<Local>| dt.timestamp_nanos_opt().unwrap_or(0).max(0) as u64;
+
+    if s == "now" {
+        return Ok(to_ns(now));
+    }
+    if let Ok(secs) = s.parse::<u64>() {
+        return secs.checked_mul(1_000_000_000).ok_or_else(invalid);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(to_ns(dt.with_timezone(&Local)));
+    }
+    if let Some(unit) = s.chars().last().filter(char::is_ascii_alphabetic)
+        && let Ok(n) = s[..s.len() - 1].parse::<i64>()
+    {
+        let age = match unit {
+            's' => Duration::seconds(n),
+            'm' => Duration::minutes(n),
+            'h' => Duration::hours(n),
+            'd' => Duration::days(n),
+            _ => return Err(invalid()),
+        };
+        return Ok(to_ns(now - age));
+    }
+    let time = NaiveTime::parse_from_str(s, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M"))
+        .map_err(|_| invalid())?;
+    let mut day = now.date_naive();
+    if time > now.time() {
+        day = day.pred_opt().ok_or_else(invalid)?;
+    }
+    let dt = Local
+        .from_local_datetime(&day.and_time(time))
+        .earliest()
+        .ok_or_else(invalid)?;
+    Ok(to_ns(dt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_relative_clock_and_absolute_times() {
+        let now = Local.with_ymd_and_hms(2025, 6, 1, 2, 10, 0).unwrap();
+        let ns = |dt: DateTime<Local>| dt.timestamp_nanos_opt().unwrap() as u64;
+
+        assert_eq!(parse_time_ns("now", now), Ok(ns(now)));
+        assert_eq!(
+            parse_time_ns("15m", now),
+            Ok(ns(now - Duration::minutes(15)))
+        );
+        assert_eq!(
+            parse_time_ns("02:05", now),
+            Ok(ns(Local.with_ymd_and_hms(2025, 6, 1, 2, 5, 0).unwrap()))
+        );
+        // A clock time later than now means yesterday.
+        assert_eq!(
+            parse_time_ns("23:59:30", now),
+            Ok(ns(Local.with_ymd_and_hms(2025, 5, 31, 23, 59, 30).unwrap()))
+        );
+        assert_eq!(
+            parse_time_ns("2025-06-01T00:00:00Z", now),
+            Ok(1_748_736_000_000_000_000)
+        );
+        assert_eq!(
+            parse_time_ns("1748736000", now),
+            Ok(1_748_736_000_000_000_000)
+        );
+        assert!(parse_time_ns("5w", now).is_err());
+        assert!(parse_time_ns("yesterday", now).is_err());
+
+        assert_eq!(type_key("file_io"), type_key("fileio"));
+        assert_eq!(type_key(" Exec"), "exec");
+    }
+}
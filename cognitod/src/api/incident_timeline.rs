@@ -0,0 +1,416 @@
+//! `GET /incidents/{id}/timeline`: what happened around an incident, in order.
+//!
+//! Merges the stored incident (thresholds crossed, circuit breaker action,
+//! LLM analysis, recovery) with the reported deploys and config changes,
+//! alerts, insights and process events of the target (including signals it
+//! sent or received) from the surrounding window. Alerts, insights and events come
+//! from in-memory histories, so for older incidents only the stored
+//! milestones remain; `complete` says whether the window is still covered.
+
+use super::{AlertRecord, AppState, ProcessEventSse};
+use crate::insights::InsightRecord;
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::{Local, TimeZone};
+use cognitod::Incident;
+use cognitod::changes::ChangeEvent;
+use cognitod::crashloop::ExitStatus;
+use cognitod::redaction::Destination;
+use linnix_ai_ebpf_common::EventType;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Seconds before the breach and after the action covered by default.
+const DEFAULT_WINDOW_SECS: i64 = 600;
+/// Process events of the target kept in a timeline.
+const MAX_EVENTS: usize = 50;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TimelineQuery {
+    /// Seconds before the incident (or its breach start) to include.
+    before: Option<i64>,
+    /// Seconds after the incident to include.
+    after: Option<i64>,
+}
+
+/// Entry kinds, in the order they are listed when timestamps tie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EntryKind {
+    Change,
+    ThresholdCrossed,
+    Alert,
+    Event,
+    Action,
+    Insight,
+    Analysis,
+    Recovery,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct TimelineEntry {
+    timestamp_ms: i64,
+    /// Local time (RFC 3339).
+    time: String,
+    kind: EntryKind,
+    summary: String,
+}
+
+#[derive(Serialize)]
+struct TimelineResponse {
+    incident_id: i64,
+    from: String,
+    to: String,
+    /// False when the in-memory event history no longer reaches back to the
+    /// start of the window.
+    complete: bool,
+    entries: Vec<TimelineEntry>,
+}
+
+pub async fn incident_timeline(
+    Path(id): Path<i64>,
+    Query(query): Query<TimelineQuery>,
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let store = app.incident_store.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Incident store not available".to_string(),
+        )
+    })?;
+    let incident = store
+        .get(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Incident not found".to_string()))?;
+
+    let start = incident.breach_started_at.unwrap_or(incident.timestamp);
+    let from = start - query.before.unwrap_or(DEFAULT_WINDOW_SECS).max(0);
+    let to = incident.timestamp + query.after.unwrap_or(DEFAULT_WINDOW_SECS).max(0);
+    let to_ns = |secs: i64| (secs.max(0) as u64).saturating_mul(1_000_000_000);
+
+    let alerts = app.alert_history.get_all().await;
+    let insights = app.insights.recent(usize::MAX);
+    let changes = app.changes.as_ref().map_or_else(Vec::new, |c| {
+        c.between(from.max(0) as u64, to.max(0) as u64)
+    });
+    let events: Vec<_> = app
+        .context
+        .events_between(to_ns(from), to_ns(to))
+        .into_iter()
+        .map(|(t, e)| (t, ProcessEventSse::from(e)))
+        .collect();
+    let complete = app
+        .context
+        .oldest_event_ns()
+        .is_some_and(|oldest| oldest <= to_ns(from));
+
+    let response = TimelineResponse {
+        incident_id: id,
+        from: format_ms(from * 1000),
+        to: format_ms(to * 1000),
+        complete,
+        entries: build(&incident, &changes, &alerts, &insights, &events, from, to),
+    };
+    Ok(Json(app.redaction.redact_json(Destination::Api, &response)))
+}
+
+/// Timeline of `incident` from the sources, limited to `from..=to` (epoch
+/// seconds) and sorted by time.
+pub(crate) fn build(
+    incident: &Incident,
+    changes: &[ChangeEvent],
+    alerts: &[AlertRecord],
+    insights: &[InsightRecord],
+    events: &[(u64, ProcessEventSse)],
+    from: i64,
+    to: i64,
+) -> Vec<TimelineEntry> {
+    let mut entries = Vec::new();
+    let mut push = |timestamp_ms: i64, kind: EntryKind, summary: String| {
+        entries.push(TimelineEntry {
+            timestamp_ms,
+            time: format_ms(timestamp_ms),
+            kind,
+            summary,
+        })
+    };
+    let in_window = |secs: i64| (from..=to).contains(&secs);
+    let target = match (&incident.target_name, incident.target_pid) {
+        (Some(name), Some(pid)) => format!("{name} (pid {pid})"),
+        (None, Some(pid)) => format!("pid {pid}"),
+        _ => "no target".to_string(),
+    };
+
+    for change in changes.iter().filter(|c| in_window(c.timestamp as i64)) {
+        push(
+            change.timestamp as i64 * 1000,
+            EntryKind::Change,
+            change.to_string(),
+        );
+    }
+    if let Some(started) = incident.breach_started_at {
+        push(
+            started * 1000,
+            EntryKind::ThresholdCrossed,
+            format!(
+                "{} conditions first met; sustained {}s before action",
+                incident.event_type,
+                incident.timestamp - started
+            ),
+        );
+    }
+    for alert in alerts.iter().filter(|a| in_window(a.timestamp as i64)) {
+        push(
+            alert.timestamp as i64 * 1000,
+            EntryKind::Alert,
+            format!("[{}] {}: {}", alert.severity, alert.rule, alert.message),
+        );
+    }
+    if let Some(pid) = incident.target_pid.map(|pid| pid as u32) {
+        let key_events = events.iter().filter(|(_, e)| {
+            let lifecycle = (e.pid == pid || e.ppid == pid)
+                && [EventType::Exec, EventType::Fork, EventType::Exit]
+                    .iter()
+                    .any(|t| e.event_type == *t as u32);
+            let signalled = e.event_type == EventType::Signal as u32
+                && (e.pid == pid || e.target_pid == Some(pid));
+            lifecycle || signalled
+        });
+        for (ns, e) in key_events.take(MAX_EVENTS) {
+            if let (Some(signal), Some(target)) = (&e.signal, e.target_pid) {
+                push(
+                    (*ns / 1_000_000) as i64,
+                    EntryKind::Event,
+                    format!("{} (pid {}) sent {signal} to pid {target}", e.comm, e.pid),
+                );
+                continue;
+            }
+            let status = match (e.exit_code, e.exit_signal) {
+                (_, Some(sig)) => format!(": {}", ExitStatus::Signal(sig)),
+                (Some(code), _) => format!(": {}", ExitStatus::Code(code)),
+                _ => String::new(),
+            };
+            push(
+                (*ns / 1_000_000) as i64,
+                EntryKind::Event,
+                format!(
+                    "{} {} (pid {}, parent {}){status}",
+                    e.event_type_name.to_lowercase(),
+                    e.comm,
+                    e.pid,
+                    e.ppid
+                ),
+            );
+        }
+    }
+    push(
+        incident.timestamp * 1000,
+        EntryKind::Action,
+        format!(
+            "{} on {target}: CPU {:.1}%, CPU PSI {:.1}%, memory PSI {:.1}%, load {}",
+            incident.action,
+            incident.cpu_percent,
+            incident.psi_cpu,
+            incident.psi_memory,
+            incident.load_avg
+        ),
+    );
+    for record in insights.iter().filter(|r| in_window(r.timestamp as i64)) {
+        push(
+            record.timestamp as i64 * 1000,
+            EntryKind::Insight,
+            format!(
+                "{} ({:.0}% confidence): {}",
+                record.insight.reason_code.as_str(),
+                record.insight.confidence * 100.0,
+                record.insight.summary
+            ),
+        );
+    }
+    if let (Some(at), Some(analysis)) = (incident.llm_analyzed_at, &incident.llm_analysis) {
+        let first_line = analysis
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or("");
+        push(
+            at * 1000,
+            EntryKind::Analysis,
+            format!("LLM analysis: {first_line}"),
+        );
+    }
+    if let Some(ms) = incident.recovery_time_ms {
+        let psi = incident
+            .psi_after
+            .map_or(String::new(), |psi| format!(", CPU PSI {psi:.1}%"));
+        push(
+            incident.timestamp * 1000 + ms,
+            EntryKind::Recovery,
+            format!("back below thresholds after {}s{psi}", ms / 1000),
+        );
+    }
+
+    entries.sort_by_key(|e| (e.timestamp_ms, e.kind));
+    entries
+}
+
+fn format_ms(ms: i64) -> String {
+    Local
+        .timestamp_millis_opt(ms)
+        .single()
+        .map_or_else(|| ms.to_string(), |t| t.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEvent, ProcessEventWire};
+
+    #[test]
+    fn merges_sources_in_time_order() {
+        let incident = Incident {
+            id: Some(7),
+            timestamp: 1_000,
+            event_type: "circuit_breaker_cpu".into(),
+            psi_cpu: 62.0,
+            psi_memory: 0.0,
+            cpu_percent: 97.5,
+            load_avg: "8.00,6.00,4.00".into(),
+            action: "auto_kill".into(),
+            target_pid: Some(4242),
+            target_name: Some("stress".into()),
+            system_snapshot: None,
+            llm_analysis: Some("\nRoot cause: runaway stress test\nmore".into()),
+            llm_analyzed_at: Some(1_005),
+            recovery_time_ms: Some(20_000),
+            psi_after: Some(3.0),
+            profile: None,
+            breach_started_at: Some(970),
+        };
+        let alert = |timestamp: u64, rule: &str| AlertRecord {
+            id: format!("alert-{timestamp}"),
+            timestamp,
+            severity: "high".into(),
+            rule: rule.into(),
+            message: "cpu high".into(),
+            host: "node-1".into(),
+        };
+        let event = |ns: u64, pid: u32, ppid: u32, event_type: EventType| {
+            let mut comm = [0u8; 16];
+            comm[..6].copy_from_slice(b"stress");
+            let wire = ProcessEventWire {
+                pid,
+                ppid,
+                uid: 0,
+                gid: 0,
+                event_type: event_type as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 0,
+                data2: 0,
+                // The target was killed: SIGKILL.
+                aux: 9,
+                aux2: linnix_ai_ebpf_common::EXIT_STATUS_KNOWN,
+                cgroup_id: 0,
+            };
+            (ns, ProcessEventSse::from(ProcessEvent::new(wire)))
+        };
+        let sigterm = {
+            let mut comm = [0u8; 16];
+            comm[..7].copy_from_slice(b"kubelet");
+            let wire = ProcessEventWire {
+                pid: 900,
+                ppid: 1,
+                uid: 0,
+                gid: 0,
+                event_type: EventType::Signal as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 4242,
+                data2: 0,
+                aux: 15,
+                aux2: 0,
+                cgroup_id: 0,
+            };
+            (
+                999_000_000_000,
+                ProcessEventSse::from(ProcessEvent::new(wire)),
+            )
+        };
+
+        let deploy: ChangeEvent =
+            serde_json::from_str(r#"{"service": "stress", "timestamp": 900, "version": "v2"}"#)
+                .unwrap();
+        let entries = build(
+            &incident,
+            &[deploy],
+            &[alert(100, "too_early"), alert(980, "cpu_spin")],
+            &[],
+            &[
+                event(960_000_000_000, 4242, 1, EventType::Exec),
+                event(990_000_000_000, 4243, 4242, EventType::Fork),
+                event(995_000_000_000, 5000, 1, EventType::Exec),
+                sigterm,
+                event(1_001_000_000_000, 4242, 1, EventType::Exit),
+            ],
+            400,
+            1_600,
+        );
+        let kinds: Vec<EntryKind> = entries.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                EntryKind::Change,
+                EntryKind::Event,
+                EntryKind::ThresholdCrossed,
+                EntryKind::Alert,
+                EntryKind::Event,
+                EntryKind::Event,
+                EntryKind::Action,
+                EntryKind::Event,
+                EntryKind::Analysis,
+                EntryKind::Recovery,
+            ]
+        );
+        assert_eq!(entries[0].summary, "deploy of stress v2");
+        assert_eq!(
+            entries[2].summary,
+            "circuit_breaker_cpu conditions first met; sustained 30s before action"
+        );
+        assert_eq!(entries[3].summary, "[high] cpu_spin: cpu high");
+        assert_eq!(entries[4].summary, "fork stress (pid 4243, parent 4242)");
+        assert_eq!(
+            entries[5].summary,
+            "kubelet (pid 900) sent SIGTERM to pid 4242"
+        );
+        assert_eq!(
+            entries[6].summary,
+            "auto_kill on stress (pid 4242): CPU 97.5%, CPU PSI 62.0%, memory PSI 0.0%, load 8.00,6.00,4.00"
+        );
+        assert_eq!(
+            entries[7].summary,
+            "exit stress (pid 4242, parent 1): SIGKILL"
+        );
+        assert_eq!(
+            entries[8].summary,
+            "LLM analysis: Root cause: runaway stress test"
+        );
+        assert_eq!(
+            entries[9].summary,
+            "back below thresholds after 20s, CPU PSI 3.0%"
+        );
+        assert_eq!(entries[9].timestamp_ms, 1_020_000);
+    }
+}
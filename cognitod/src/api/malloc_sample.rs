@@ -0,0 +1,84 @@
+//! `POST /processes/{pid}/malloc-sample`: attach the malloc/free uprobes to
+//! one process for a while and report what it allocated and didn't free.
+
+use super::AppState;
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use log::info;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// One sampling session at a time; each attaches its own probes.
+static SAMPLING: AtomicBool = AtomicBool::new(false);
+
+/// Clears [`SAMPLING`] however the request ends.
+struct SessionGuard;
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        SAMPLING.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MallocSampleQuery {
+    /// Sampling window; `malloc_sampler.duration_secs` when absent.
+    seconds: Option<u64>,
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// Returns folded stacks weighted by outstanding bytes by default, or the
+/// full report with `?format=json`.
+pub async fn malloc_sample(
+    Path(pid): Path<u32>,
+    Query(params): Query<MallocSampleQuery>,
+    State(app): State<Arc<AppState>>,
+) -> Result<Response, (StatusCode, String)> {
+    let cfg = app.malloc_sampler.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Malloc sampling not enabled".to_string(),
+        )
+    })?;
+    let seconds = params.seconds.unwrap_or(cfg.duration_secs);
+    if seconds == 0 || seconds > cfg.max_duration_secs {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("seconds must be between 1 and {}", cfg.max_duration_secs),
+        ));
+    }
+    let (bytes, path) = crate::read_malloc_trace_bytes()
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, e.to_string()))?;
+
+    if SAMPLING.swap(true, Ordering::SeqCst) {
+        return Err((
+            StatusCode::CONFLICT,
+            "A malloc sample is already running".to_string(),
+        ));
+    }
+    let _session = SessionGuard;
+    info!("[malloc_sampler] sampling pid {pid} for {seconds}s with {path}");
+    let sample = cognitod::malloc_sampler::sample(pid, seconds, bytes, cfg)
+        .await
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, format!("{e:#}")))?;
+    info!(
+        "[malloc_sampler] pid {pid} ({}): {}",
+        sample.comm,
+        sample.summary()
+    );
+
+    if params.format.as_deref() == Some("json") {
+        return Ok(Json(sample).into_response());
+    }
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        sample.folded(),
+    )
+        .into_response())
+}
@@ -1,10 +1,22 @@
 mod auth;
+mod changes;
+mod grafana;
+mod history;
+mod incident_timeline;
+mod malloc_sample;
+mod observe_report;
+mod projection;
+mod quotas;
+mod silences;
+mod slack;
 
 use crate::runtime::probes::ProbeState;
+use crate::runtime::throttle::{ThrottleControl, ThrottleIntervals, ThrottlePatch};
 use axum::{
     Router,
-    extract::{Form, Path, Query, State},
-    http::{StatusCode, header},
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
     response::{
         IntoResponse, Json, Response,
         sse::{Event, Sse},
@@ -32,13 +44,20 @@ use crate::ProcessEventWire;
 use crate::config::{OfflineGuard, ReasonerConfig};
 use crate::context::ContextStore;
 use cognitod::alerts::Alert;
+use cognitod::connection::Connection;
+use cognitod::crashloop::ExitStatus;
+use cognitod::creds::{CredChange, capability_names};
+use cognitod::file_access::{FileAccess, op_name};
+use cognitod::kernel_load::{KernelLoad, kind_name, prog_type_name, taint_letters};
+use cognitod::redaction::Destination;
+use cognitod::signals::SignalSent;
 // use crate::handler::local_ilm::schema::insight_json_schema; // Removed (YAGNI cleanup)
 use crate::insights::{InsightRecord, InsightStore as InsightsStore};
 use crate::metrics::Metrics;
 use crate::types::ProcessAlert;
 use crate::types::SystemSnapshot;
 use cognitod::{Incident, IncidentStats, IncidentStore};
-use linnix_ai_ebpf_common::EventType;
+use linnix_ai_ebpf_common::{EventType, KernelLoadKind, fork_ancestors};
 use sysinfo::{Pid, System};
 use tokio::sync::broadcast;
 
@@ -53,6 +72,16 @@ enum EventKind {
     Syscall,
     BlockIo,
     PageFault,
+    OomKill,
+    SchedLatency,
+    OffCpu,
+    Connection,
+    DirectReclaim,
+    Signal,
+    Creds,
+    MemGrowth,
+    FileAccess,
+    KernelLoad,
     Unknown,
 }
 
@@ -67,6 +96,16 @@ impl From<u32> for EventKind {
             x if x == EventType::Syscall as u32 => EventKind::Syscall,
             x if x == EventType::BlockIo as u32 => EventKind::BlockIo,
             x if x == EventType::PageFault as u32 => EventKind::PageFault,
+            x if x == EventType::OomKill as u32 => EventKind::OomKill,
+            x if x == EventType::SchedLatency as u32 => EventKind::SchedLatency,
+            x if x == EventType::OffCpu as u32 => EventKind::OffCpu,
+            x if x == EventType::Connection as u32 => EventKind::Connection,
+            x if x == EventType::DirectReclaim as u32 => EventKind::DirectReclaim,
+            x if x == EventType::Signal as u32 => EventKind::Signal,
+            x if x == EventType::Creds as u32 => EventKind::Creds,
+            x if x == EventType::MemGrowth as u32 => EventKind::MemGrowth,
+            x if x == EventType::FileAccess as u32 => EventKind::FileAccess,
+            x if x == EventType::KernelLoad as u32 => EventKind::KernelLoad,
             _ => EventKind::Unknown,
         }
     }
@@ -91,6 +130,9 @@ struct ProcessInfo {
     k8s: Option<cognitod::k8s::K8sMetadata>,
     #[serde(skip_serializing_if = "Option::is_none")]
     priority: Option<cognitod::k8s::Priority>,
+    /// Login session the process belongs to (SSH user, TTY).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session: Option<cognitod::sessions::LoginSession>,
 }
 
 impl ProcessInfo {
@@ -98,7 +140,7 @@ impl ProcessInfo {
         let k8s = app_state
             .k8s
             .as_ref()
-            .and_then(|k| k.get_metadata_for_pid(e.pid));
+            .and_then(|k| k.get_metadata_for_event(e));
         Self {
             pid: e.pid,
             ppid: e.ppid,
@@ -114,6 +156,11 @@ impl ProcessInfo {
             state: Some(process_state_str(e.event_type, e.exit_time_ns)),
             k8s: k8s.clone(),
             priority: k8s.map(|m| m.priority),
+            session: app_state
+                .sessions
+                .as_ref()
+                .and_then(|s| s.session_for_pid(e.pid))
+                .map(|s| (*s).clone()),
         }
     }
 }
@@ -158,6 +205,151 @@ struct ProcessEventSse {
     data2: u64,
     aux: u32,
     aux2: u32,
+    /// cgroup v2 ID of the process, 0 if unknown.
+    cgroup_id: u64,
+    /// Exit events: the exit code, or the signal that killed the process.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_signal: Option<i32>,
+    /// Connection events: `outbound` or `inbound`, the remote `ip:port`
+    /// and, for inbound ones, the local port.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direction: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_addr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_port: Option<u16>,
+    /// Fork events: the forking process's parent and older ancestors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ancestors: Option<Vec<u32>>,
+    /// Signal events: the signal's name and the PID it was sent to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_pid: Option<u32>,
+    /// Creds events: the effective IDs after the change (`uid`/`gid` hold
+    /// the ones before) and the capabilities gained and dropped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_uid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_gid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caps_gained: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caps_dropped: Option<Vec<String>>,
+    /// FileAccess events: `open`, `open_write` or `unlink`, and the path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_op: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// KernelLoad events: `module` or `bpf`, the module or program name,
+    /// the program type and the module's taint flags.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    load_kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    load_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prog_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    taints: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    argv: Option<Vec<String>>,
+}
+
+impl From<ProcessEvent> for ProcessEventSse {
+    fn from(event: ProcessEvent) -> Self {
+        let event_type_name = match event.event_type {
+            0 => "exec",
+            1 => "fork",
+            2 => "exit",
+            3 => "net",
+            4 => "fileio",
+            5 => "syscall",
+            6 => "blockio",
+            7 => "pagefault",
+            10 => "oomkill",
+            11 => "schedlatency",
+            12 => "offcpu",
+            13 => "connection",
+            14 => "directreclaim",
+            15 => "signal",
+            16 => "creds",
+            17 => "memgrowth",
+            18 => "fileaccess",
+            19 => "kernelload",
+            _ => "unknown",
+        }
+        .to_string();
+        let (exit_code, exit_signal) = match ExitStatus::from_event(&event) {
+            Some(ExitStatus::Code(code)) => (Some(code), None),
+            Some(ExitStatus::Signal(sig)) => (None, Some(sig)),
+            _ => (None, None),
+        };
+        let connection = Connection::from_event(&event);
+        let sent = SignalSent::from_event(&event);
+        let change = CredChange::from_event(&event);
+        let access = FileAccess::from_event(&event);
+        let load = KernelLoad::from_event(&event);
+        let caps = |caps: u64| Some(capability_names(caps)).filter(|names| !names.is_empty());
+        let ancestors = (event.event_type == EventType::Fork as u32)
+            .then(|| {
+                fork_ancestors(event.data, event.data2)
+                    .into_iter()
+                    .take_while(|&pid| pid != 0)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|chain| !chain.is_empty());
+
+        ProcessEventSse {
+            pid: event.pid,
+            ppid: event.ppid,
+            uid: event.uid,
+            gid: event.gid,
+            comm: String::from_utf8_lossy(&event.comm)
+                .trim_end_matches('\0')
+                .to_string(),
+            event_type: event.event_type,
+            event_type_name,
+            ts_ns: event.ts_ns,
+            seq: event.seq,
+            exit_time_ns: event.exit_time_ns,
+            cpu_pct_milli: event.cpu_pct_milli,
+            mem_pct_milli: event.mem_pct_milli,
+            cpu_percent: event.cpu_percent(),
+            mem_percent: event.mem_percent(),
+            data: event.data,
+            data2: event.data2,
+            aux: event.aux,
+            aux2: event.aux2,
+            cgroup_id: event.cgroup_id,
+            exit_code,
+            exit_signal,
+            direction: connection.map(|c| c.direction.as_str()),
+            remote_addr: connection.map(|c| c.remote.to_string()),
+            local_port: connection.and_then(|c| c.local_port),
+            ancestors,
+            signal: sent.map(|s| s.name()),
+            target_pid: sent.map(|s| s.target),
+            new_uid: change.map(|c| c.new_uid),
+            new_gid: change.map(|c| c.new_gid),
+            caps_gained: change.and_then(|c| caps(c.caps_gained)),
+            caps_dropped: change.and_then(|c| caps(c.caps_lost)),
+            file_op: access.as_ref().map(|a| op_name(a.op)),
+            path: event.path,
+            load_kind: load.as_ref().map(|l| kind_name(l.kind)),
+            prog_type: load
+                .as_ref()
+                .filter(|l| l.kind == KernelLoadKind::BpfProgram)
+                .and_then(|l| prog_type_name(l.prog_type)),
+            taints: load
+                .as_ref()
+                .map(|l| taint_letters(l.taints))
+                .filter(|t| !t.is_empty()),
+            load_name: load.map(|l| l.name),
+            argv: event.argv,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -236,6 +428,14 @@ impl AlertHistory {
         records.push_back(record);
     }
 
+    /// Drop alerts recorded before `cutoff` (epoch seconds).
+    pub async fn prune_before(&self, cutoff: u64) -> usize {
+        let mut records = self.records.write().await;
+        let before = records.len();
+        records.retain(|r| r.timestamp >= cutoff);
+        before - records.len()
+    }
+
     pub async fn get_all(&self) -> Vec<AlertRecord> {
         self.records.read().await.iter().cloned().collect()
     }
@@ -246,10 +446,13 @@ struct StatusResponse {
     version: &'static str,
     uptime_s: u64,
     offline: bool,
+    /// `live` or `observe`.
+    mode: &'static str,
     cpu_pct: f64,
     rss_mb: u64,
     events_per_sec: u64,
     rb_overflows: u64,
+    kernel_dropped_events: u64,
     rate_limited: u64,
     kernel_version: String,
     aya_version: String,
@@ -264,6 +467,17 @@ struct StatusResponse {
     slack_stats: SlackStats,
     perf_poll_errors: u64,
     dropped_events_total: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    leader: Option<LeaderStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update: Option<cognitod::update::UpdateStatus>,
+}
+
+#[derive(Serialize)]
+struct LeaderStatus {
+    identity: String,
+    backend: &'static str,
+    is_leader: bool,
 }
 
 #[derive(Serialize)]
@@ -277,6 +491,7 @@ struct SlackStats {
 #[derive(Serialize)]
 struct StatusProbeState {
     rss_probe: String,
+    block_probe: &'static str,
     btf: bool,
 }
 
@@ -377,10 +592,12 @@ async fn status_handler(State(app_state): State<Arc<AppState>>) -> Json<StatusRe
         version: env!("CARGO_PKG_VERSION"),
         uptime_s: uptime,
         offline: app_state.offline.is_offline(),
+        mode: app_state.observe.as_ref().map_or("live", |o| o.mode()),
         cpu_pct,
         rss_mb,
         events_per_sec: metrics.events_per_sec(),
         rb_overflows: metrics.rb_overflows(),
+        kernel_dropped_events: metrics.kernel_dropped_events(),
         rate_limited: metrics.rate_limited_events(),
         kernel_version: kernel_version_string(),
         aya_version: aya_version_string(),
@@ -390,6 +607,7 @@ async fn status_handler(State(app_state): State<Arc<AppState>>) -> Json<StatusRe
         top_cpu,
         probes: StatusProbeState {
             rss_probe: app_state.probe_state.rss_probe.as_str().to_string(),
+            block_probe: app_state.probe_state.block_probe.as_str(),
             btf: app_state.probe_state.btf_available,
         },
         reasoner,
@@ -400,6 +618,12 @@ async fn status_handler(State(app_state): State<Arc<AppState>>) -> Json<StatusRe
         dropped_events_total: metrics
             .dropped_events_total
             .load(std::sync::atomic::Ordering::Relaxed),
+        leader: app_state.leadership.as_ref().map(|l| LeaderStatus {
+            identity: l.identity().to_string(),
+            backend: l.backend(),
+            is_leader: l.is_leader(),
+        }),
+        update: app_state.updates.as_ref().and_then(|u| u.status()),
     };
     Json(resp)
 }
@@ -699,7 +923,10 @@ async fn get_graph(
 
 pub async fn stream_events(
     State(app_state): State<Arc<AppState>>,
-) -> Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>> {
+    Query(query): Query<projection::EventQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, std::convert::Infallible>>>, (StatusCode, String)>
+{
+    let view = projection::EventView::parse(&query).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
     let ctx = &app_state.context;
     let rx = ctx.broadcaster().subscribe();
     let metrics = Arc::clone(&app_state.metrics);
@@ -710,45 +937,7 @@ pub async fn stream_events(
         let metrics = metrics_clone.clone();
         async move {
             match msg {
-                Ok(event) => {
-                    let event_type_name = match event.event_type {
-                        0 => "exec",
-                        1 => "fork",
-                        2 => "exit",
-                        3 => "net",
-                        4 => "fileio",
-                        5 => "syscall",
-                        6 => "blockio",
-                        7 => "pagefault",
-                        _ => "unknown",
-                    }
-                    .to_string();
-
-                    let sse_event = ProcessEventSse {
-                        pid: event.pid,
-                        ppid: event.ppid,
-                        uid: event.uid,
-                        gid: event.gid,
-                        comm: String::from_utf8_lossy(&event.comm)
-                            .trim_end_matches('\0')
-                            .to_string(),
-                        event_type: event.event_type,
-                        event_type_name,
-                        ts_ns: event.ts_ns,
-                        seq: event.seq,
-                        exit_time_ns: event.exit_time_ns,
-                        cpu_pct_milli: event.cpu_pct_milli,
-                        mem_pct_milli: event.mem_pct_milli,
-                        cpu_percent: event.cpu_percent(),
-                        mem_percent: event.mem_percent(),
-                        data: event.data,
-                        data2: event.data2,
-                        aux: event.aux,
-                        aux2: event.aux2,
-                    };
-                    let json = to_string(&sse_event).unwrap();
-                    Some(Ok(Event::default().data(json)))
-                }
+                Ok(event) => Some(ProcessEventSse::from(event)),
                 Err(BroadcastStreamRecvError::Lagged(n)) => {
                     log::warn!("dropped {n} events (broadcast lag)");
                     metrics.dropped_events_total.fetch_add(n, Ordering::Relaxed);
@@ -758,6 +947,47 @@ pub async fn stream_events(
         }
     });
 
+    let event_stream: BoxStream<'static, Result<Event, std::convert::Infallible>> = match view {
+        projection::EventView::Events(None) => event_stream
+            .map(|event| Ok(Event::default().data(to_string(&event).unwrap())))
+            .boxed(),
+        projection::EventView::Events(Some(fields)) => event_stream
+            .map(move |event| {
+                let projected = match serde_json::to_value(&event) {
+                    Ok(serde_json::Value::Object(map)) => projection::project(&map, &fields),
+                    _ => serde_json::Value::Null,
+                };
+                Ok(Event::default().data(projected.to_string()))
+            })
+            .boxed(),
+        projection::EventView::Aggregate(aggregator) => {
+            let every = Duration::from_secs(aggregator.interval_secs);
+            let ticks = IntervalStream::new(tokio::time::interval_at(
+                tokio::time::Instant::now() + every,
+                every,
+            ))
+            .map(|_| None);
+            futures_util::stream::select(event_stream.map(Some), ticks)
+                .scan(aggregator, |aggregator, item| {
+                    let out = match item {
+                        Some(event) => {
+                            if let Ok(serde_json::Value::Object(map)) = serde_json::to_value(&event)
+                            {
+                                aggregator.add(&map);
+                            }
+                            None
+                        }
+                        None => Some(Ok(Event::default()
+                            .event("aggregate")
+                            .data(aggregator.flush().to_string()))),
+                    };
+                    futures_util::future::ready(Some(out))
+                })
+                .filter_map(futures_util::future::ready)
+                .boxed()
+        }
+    };
+
     let keepalive = IntervalStream::new(tokio::time::interval(Duration::from_secs(10)))
         .map(|_| Ok(Event::default().comment("keep-alive")));
 
@@ -779,11 +1009,11 @@ pub async fn stream_events(
         let _ = &guard;
     });
 
-    Sse::new(stream).keep_alive(
+    Ok(Sse::new(stream).keep_alive(
         axum::response::sse::KeepAlive::new()
             .interval(Duration::from_secs(10))
             .text("keep-alive"),
-    )
+    ))
 }
 
 pub async fn stream_alerts(
@@ -870,7 +1100,7 @@ struct TimelineQuery {
 async fn get_timeline(
     State(app_state): State<Arc<AppState>>,
     Query(query): Query<TimelineQuery>,
-) -> Json<Vec<AlertRecord>> {
+) -> Json<serde_json::Value> {
     let mut alerts = app_state.alert_history.get_all().await;
 
     // Filter by time range
@@ -893,7 +1123,7 @@ async fn get_timeline(
     // Limit to 1000 results
     alerts.truncate(1000);
 
-    Json(alerts)
+    Json(app_state.redaction.redact_json(Destination::Api, &alerts))
 }
 
 // GET /api/metrics/system - Get current system metrics
@@ -996,7 +1226,17 @@ pub async fn get_recent_insights(
     Query(query): Query<RecentInsightsQuery>,
 ) -> Json<Vec<InsightRecord>> {
     let limit = query.limit.clamp(1, 200);
-    let records = app_state.insights.recent(limit);
+    let records = app_state
+        .insights
+        .recent(limit)
+        .into_iter()
+        .map(|mut rec| {
+            rec.insight = app_state
+                .redaction
+                .redact_insight(Destination::Api, &rec.insight);
+            rec
+        })
+        .collect();
     Json(records)
 }
 
@@ -1055,11 +1295,55 @@ pub async fn get_insights(
             .join(", ")
     };
 
+    // CFS throttling makes pods slow while node CPU looks idle, so call it
+    // out explicitly.
+    let throttling_summary = if system.cgroup_throttling.is_empty() {
+        "none".to_string()
+    } else {
+        system
+            .cgroup_throttling
+            .iter()
+            .take(3)
+            .map(|t| format!("{} ({:.0}% of periods)", t.label(), t.throttled_pct))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let thermal_summary = match &system.cpu_thermal {
+        Some(t) if t.throttle_events > 0 => format!(
+            "{} thermal throttle events on {} CPUs",
+            t.throttle_events, t.throttled_cpus
+        ),
+        Some(t) => t
+            .freq_pct
+            .map(|f| format!("none; frequency {f:.0}% of base"))
+            .unwrap_or_else(|| "none".to_string()),
+        None => "unknown".to_string(),
+    };
+
+    // Lets the model say "3x normal for this time of day" rather than
+    // judge absolute numbers.
+    let baseline_summary = app_state
+        .baselines
+        .as_ref()
+        .and_then(|b| b.prompt_context(system.cpu_percent))
+        .unwrap_or_else(|| "not enough history yet".to_string());
+
+    let pod_pressure_summary = app_state
+        .pod_pressure
+        .as_ref()
+        .and_then(|p| p.prompt_context())
+        .unwrap_or_else(|| "not measured".to_string());
+
     let prompt = format!(
         "System Health Analysis:\n\
          CPU: {:.1}% | Memory: {:.1}% | Load Avg: [{:.2}, {:.2}, {:.2}]\n\
+         Baseline (same hour): {}\n\
          Top CPU Consumers: {}\n\
          Top Memory Consumers: {}\n\
+         Pod CPU Stall Contribution: {}\n\
+         CPU Throttled Cgroups: {}\n\
+         CPU Thermal Throttling: {}\n\
          Alerts: {}\n\n\
          Analyze the system state and provide: 1) Overall health assessment, 2) Key risks or anomalies, 3) Recommended actions.",
         system.cpu_percent,
@@ -1067,10 +1351,15 @@ pub async fn get_insights(
         system.load_avg[0],
         system.load_avg[1],
         system.load_avg[2],
+        baseline_summary,
         top_cpu_summary,
         top_mem_summary,
+        pod_pressure_summary,
+        throttling_summary,
+        thermal_summary,
         alert_summary
     );
+    let prompt = app_state.redaction.redact_str(Destination::Llm, &prompt);
 
     // Call LLM - supports both local models and OpenAI
     // Default to local Linnix model if available
@@ -1096,8 +1385,8 @@ pub async fn get_insights(
         "max_tokens": 200  // Limit response for faster generation on CPU
     });
 
-    let client = Client::new();
-    let res = client
+    let res = app_state
+        .http
         .post(&llm_endpoint)
         .bearer_auth(api_key)
         .json(&req_body)
@@ -1639,6 +1928,10 @@ pub struct MetricsResponse {
     alerts_emitted: u64,
     lineage_hits: u64,
     lineage_misses: u64,
+    quota_suppressed: u64,
+    alerts_silenced: u64,
+    sched_latency_p95_us: u64,
+    sched_latency_samples: u64,
     drops_by_type: Vec<DropBreakdown>,
     rss_probe_mode: String,
     kernel_btf_available: bool,
@@ -1651,6 +1944,25 @@ pub struct MetricsResponse {
     pub slack_sent: u64,
     pub slack_failed: u64,
     pub alerts_generated: u64,
+    pub detector_state_keys: usize,
+    pub detector_state_evictions: u64,
+    pub handlers: Vec<cognitod::metrics::HandlerStats>,
+    pub watches: Vec<cognitod::metrics::WatchStats>,
+}
+
+/// Escape a Prometheus label value: backslash, double quote and newline
+/// (rule names come from the rules file and may contain any of them).
+fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 pub async fn prometheus_metrics(State(app_state): State<Arc<AppState>>) -> Response {
@@ -1673,6 +1985,8 @@ pub async fn prometheus_metrics(State(app_state): State<Arc<AppState>>) -> Respo
     let queue_depth = app_state.context.queue_depth() as u64;
     let lineage_hits = metrics.lineage_hits();
     let lineage_misses = metrics.lineage_misses();
+    let quota_suppressed = metrics.quota_suppressed();
+    let alerts_silenced = metrics.alerts_silenced();
     let ilm_windows = metrics.ilm_windows();
     let ilm_timeouts = metrics.ilm_timeouts();
     let ilm_insights = metrics.ilm_insights();
@@ -1717,6 +2031,28 @@ pub async fn prometheus_metrics(State(app_state): State<Arc<AppState>>) -> Respo
     let _ = writeln!(body, "# TYPE linnix_alerts_emitted_total counter");
     let _ = writeln!(body, "linnix_alerts_emitted_total {}", alerts_emitted);
 
+    let _ = writeln!(
+        body,
+        "# HELP linnix_detector_state_keys Per-key entries held by rule detectors."
+    );
+    let _ = writeln!(body, "# TYPE linnix_detector_state_keys gauge");
+    let _ = writeln!(
+        body,
+        "linnix_detector_state_keys {}",
+        metrics.detector_state_keys()
+    );
+
+    let _ = writeln!(
+        body,
+        "# HELP linnix_detector_state_evictions_total Detector state entries evicted by size caps."
+    );
+    let _ = writeln!(body, "# TYPE linnix_detector_state_evictions_total counter");
+    let _ = writeln!(
+        body,
+        "linnix_detector_state_evictions_total {}",
+        metrics.detector_state_evictions()
+    );
+
     let _ = writeln!(
         body,
         "# HELP linnix_dropped_events_total Total events dropped (sampling/backpressure)."
@@ -1731,6 +2067,17 @@ pub async fn prometheus_metrics(State(app_state): State<Arc<AppState>>) -> Respo
     let _ = writeln!(body, "# TYPE linnix_ringbuf_overflows_total counter");
     let _ = writeln!(body, "linnix_ringbuf_overflows_total {}", rb_overflows);
 
+    let _ = writeln!(
+        body,
+        "# HELP linnix_kernel_dropped_events_total Events lost in the kernel because the perf or ring buffer was full."
+    );
+    let _ = writeln!(body, "# TYPE linnix_kernel_dropped_events_total counter");
+    let _ = writeln!(
+        body,
+        "linnix_kernel_dropped_events_total {}",
+        metrics.kernel_dropped_events()
+    );
+
     let _ = writeln!(
         body,
         "# HELP linnix_rate_limited_total Events skipped due to configured rate caps."
@@ -1745,6 +2092,35 @@ pub async fn prometheus_metrics(State(app_state): State<Arc<AppState>>) -> Respo
     let _ = writeln!(body, "# TYPE linnix_perf_poll_errors_total counter");
     let _ = writeln!(body, "linnix_perf_poll_errors_total {}", perf_errors);
 
+    let _ = writeln!(
+        body,
+        "# HELP linnix_quota_suppressed_events_total Events dropped by per-pod and per-namespace quotas."
+    );
+    let _ = writeln!(body, "# TYPE linnix_quota_suppressed_events_total counter");
+    let _ = writeln!(
+        body,
+        "linnix_quota_suppressed_events_total {}",
+        quota_suppressed
+    );
+
+    let _ = writeln!(
+        body,
+        "# HELP linnix_alerts_silenced_total Rule alerts dropped by silences."
+    );
+    let _ = writeln!(body, "# TYPE linnix_alerts_silenced_total counter");
+    let _ = writeln!(body, "linnix_alerts_silenced_total {}", alerts_silenced);
+
+    let _ = writeln!(
+        body,
+        "# HELP linnix_sched_latency_p95_microseconds p95 runqueue delay over the last probe read."
+    );
+    let _ = writeln!(body, "# TYPE linnix_sched_latency_p95_microseconds gauge");
+    let _ = writeln!(
+        body,
+        "linnix_sched_latency_p95_microseconds {}",
+        metrics.sched_latency_p95_us()
+    );
+
     let _ = writeln!(body, "# HELP linnix_lineage_hits_total Lineage cache hits.");
     let _ = writeln!(body, "# TYPE linnix_lineage_hits_total counter");
     let _ = writeln!(body, "linnix_lineage_hits_total {}", lineage_hits);
@@ -1830,6 +2206,22 @@ pub async fn prometheus_metrics(State(app_state): State<Arc<AppState>>) -> Respo
     let _ = writeln!(body, "# TYPE linnix_ilm_schema_errors_total counter");
     let _ = writeln!(body, "linnix_ilm_schema_errors_total {}", ilm_schema_errors);
 
+    let _ = writeln!(
+        body,
+        "# HELP linnix_ilm_latency_ms LLM analysis request latency in milliseconds."
+    );
+    let _ = writeln!(body, "# TYPE linnix_ilm_latency_ms summary");
+    let _ = writeln!(
+        body,
+        "linnix_ilm_latency_ms_sum {}",
+        metrics.ilm_latency_ms_total()
+    );
+    let _ = writeln!(
+        body,
+        "linnix_ilm_latency_ms_count {}",
+        metrics.ilm_requests()
+    );
+
     let _ = writeln!(
         body,
         "# HELP linnix_ilm_enabled ILM handler state (1=enabled)."
@@ -1854,6 +2246,122 @@ pub async fn prometheus_metrics(State(app_state): State<Arc<AppState>>) -> Respo
         );
     }
 
+    let handler_stats = metrics.handler_stats();
+    if !handler_stats.is_empty() {
+        let _ = writeln!(
+            body,
+            "# HELP linnix_handler_errors_total Errors returned by each event handler."
+        );
+        let _ = writeln!(body, "# TYPE linnix_handler_errors_total counter");
+        for h in &handler_stats {
+            let _ = writeln!(
+                body,
+                "linnix_handler_errors_total{{handler=\"{}\"}} {}",
+                escape_label_value(&h.handler),
+                h.errors
+            );
+        }
+        let _ = writeln!(
+            body,
+            "# HELP linnix_handler_panics_total Panics caught in each event handler."
+        );
+        let _ = writeln!(body, "# TYPE linnix_handler_panics_total counter");
+        for h in &handler_stats {
+            let _ = writeln!(
+                body,
+                "linnix_handler_panics_total{{handler=\"{}\"}} {}",
+                escape_label_value(&h.handler),
+                h.panics
+            );
+        }
+    }
+
+    let watch_stats = metrics.watch_stats();
+    if !watch_stats.is_empty() {
+        let _ = writeln!(
+            body,
+            "# HELP linnix_watch_reloads_total Successful reloads of each watched file."
+        );
+        let _ = writeln!(body, "# TYPE linnix_watch_reloads_total counter");
+        for w in &watch_stats {
+            let _ = writeln!(
+                body,
+                "linnix_watch_reloads_total{{watch=\"{}\"}} {}",
+                escape_label_value(&w.watch),
+                w.reloads
+            );
+        }
+        let _ = writeln!(
+            body,
+            "# HELP linnix_watch_failures_total Failed reloads of each watched file."
+        );
+        let _ = writeln!(body, "# TYPE linnix_watch_failures_total counter");
+        for w in &watch_stats {
+            let _ = writeln!(
+                body,
+                "linnix_watch_failures_total{{watch=\"{}\"}} {}",
+                escape_label_value(&w.watch),
+                w.failures
+            );
+        }
+    }
+
+    let rule_stats = metrics.rule_stats();
+    if !rule_stats.is_empty() {
+        let _ = writeln!(
+            body,
+            "# HELP linnix_rule_evaluations_total Times each rule was evaluated."
+        );
+        let _ = writeln!(body, "# TYPE linnix_rule_evaluations_total counter");
+        for r in &rule_stats {
+            let _ = writeln!(
+                body,
+                "linnix_rule_evaluations_total{{rule=\"{}\"}} {}",
+                escape_label_value(&r.rule),
+                r.evaluations
+            );
+        }
+        let _ = writeln!(
+            body,
+            "# HELP linnix_rule_fires_total Alerts emitted by each rule."
+        );
+        let _ = writeln!(body, "# TYPE linnix_rule_fires_total counter");
+        for r in &rule_stats {
+            let _ = writeln!(
+                body,
+                "linnix_rule_fires_total{{rule=\"{}\"}} {}",
+                escape_label_value(&r.rule),
+                r.fires
+            );
+        }
+        let _ = writeln!(
+            body,
+            "# HELP linnix_rule_shadow_matches_total Alerts held back by each rule in shadow mode."
+        );
+        let _ = writeln!(body, "# TYPE linnix_rule_shadow_matches_total counter");
+        for r in &rule_stats {
+            let _ = writeln!(
+                body,
+                "linnix_rule_shadow_matches_total{{rule=\"{}\"}} {}",
+                escape_label_value(&r.rule),
+                r.shadow_matches
+            );
+        }
+        let _ = writeln!(
+            body,
+            "# HELP linnix_rule_eval_seconds_total Time spent evaluating each rule."
+        );
+        let _ = writeln!(body, "# TYPE linnix_rule_eval_seconds_total counter");
+        for r in &rule_stats {
+            let _ = writeln!(
+                body,
+                "linnix_rule_eval_seconds_total{{rule=\"{}\"}} {:.6}",
+                escape_label_value(&r.rule),
+                r.eval_time_us as f64 / 1e6
+            );
+        }
+    }
+
     // Claw SLO metrics (§10.5)
     body.push_str(&app_state.claw_metrics.render_prometheus());
 
@@ -1893,6 +2401,10 @@ pub async fn metrics_handler(State(app_state): State<Arc<AppState>>) -> Json<Met
         alerts_emitted: metrics.alerts_emitted(),
         lineage_hits: metrics.lineage_hits(),
         lineage_misses: metrics.lineage_misses(),
+        quota_suppressed: metrics.quota_suppressed(),
+        alerts_silenced: metrics.alerts_silenced(),
+        sched_latency_p95_us: metrics.sched_latency_p95_us(),
+        sched_latency_samples: metrics.sched_latency_samples(),
         drops_by_type: metrics
             .drops_by_type()
             .into_iter()
@@ -1909,6 +2421,10 @@ pub async fn metrics_handler(State(app_state): State<Arc<AppState>>) -> Json<Met
         slack_sent: metrics.slack_sent(),
         slack_failed: metrics.slack_failed(),
         alerts_generated: metrics.alerts_generated(),
+        detector_state_keys: metrics.detector_state_keys(),
+        detector_state_evictions: metrics.detector_state_evictions(),
+        handlers: metrics.handler_stats(),
+        watches: metrics.watch_stats(),
     };
     Json(resp)
 }
@@ -1926,6 +2442,34 @@ pub struct AppState {
     pub context: Arc<ContextStore>,
     pub metrics: Arc<Metrics>,
     pub alerts: Option<broadcast::Sender<Alert>>,
+    /// Loaded rule engine, for runtime tuning via `PATCH /rules/{name}`.
+    pub rules: Option<Arc<cognitod::alerts::RuleEngine>>,
+    /// Disk usage and retention of the insight/alert/feedback stores.
+    pub storage: Option<Arc<cognitod::retention::Storage>>,
+    /// Per-hour-of-day history served at `/baselines` and fed to prompts.
+    pub baselines: Option<Arc<cognitod::baselines::Baselines>>,
+    /// Login session lookup for process listings.
+    pub sessions: Option<Arc<cognitod::sessions::SessionResolver>>,
+    /// Static host facts collected at startup.
+    pub inventory: Option<Arc<cognitod::inventory::HostFacts>>,
+    /// Measured per-pod CPU stall contribution, fed to the insights prompt.
+    pub pod_pressure: Option<Arc<cognitod::collectors::pod_pressure::PodPressure>>,
+    /// Set when leader election is enabled; standbys don't notify.
+    pub leadership: Option<Arc<cognitod::leader::Leadership>>,
+    /// Set when `[update_check]` is enabled; reported in `/status`.
+    pub updates: Option<Arc<cognitod::update::UpdateChecker>>,
+    /// Records notifications and enforcement actions; holds them back in observe mode.
+    pub observe: Option<Arc<cognitod::observe::ObserveRecorder>>,
+    /// Set when `[quotas]` is enabled.
+    pub quota: Option<Arc<cognitod::quota::EventQuota>>,
+    /// Deploys and config changes posted to `/changes`.
+    pub changes: Option<Arc<cognitod::changes::ChangeLog>>,
+    /// Per-process emission intervals of the probes.
+    pub throttle: Option<Arc<ThrottleControl>>,
+    /// Set when `[malloc_sampler]` is enabled.
+    pub malloc_sampler: Option<cognitod::config::MallocSamplerConfig>,
+    /// Outbound client honoring `[network]` proxy settings.
+    pub http: Client,
     pub insights: Arc<InsightsStore>,
     pub offline: Arc<OfflineGuard>,
     pub transport: &'static str,
@@ -1950,9 +2494,13 @@ pub struct AppState {
     pub compliance_engine: Option<Arc<cognitod::compliance::ComplianceEngine>>,
     /// Privacy redactor for receipt responses (§9).
     pub receipt_redactor: Option<cognitod::privacy::ReceiptRedactor>,
+    /// Outbound redaction policy for API responses, exports and LLM prompts.
+    pub redaction: Arc<cognitod::redaction::RedactionPolicy>,
     /// On-chain payment adapter for settlement (§8).
     #[allow(dead_code)]
     pub payment_adapter: Option<Arc<dyn cognitod::payment::PaymentAdapter>>,
+    /// Verifies `/api/slack/interactions` in place of the API token.
+    pub slack_signing_secret: Option<String>,
 }
 
 pub fn all_routes(app_state: Arc<AppState>) -> Router {
@@ -1966,27 +2514,63 @@ pub fn all_routes(app_state: Arc<AppState>) -> Router {
         .route("/processes", get(get_processes))
         .route("/processes/live", get(stream_processes_live))
         .route("/processes/{pid}", get(get_process_by_pid))
+        .route(
+            "/processes/{pid}/malloc-sample",
+            post(malloc_sample::malloc_sample),
+        )
         .route("/ppid/{ppid}", get(get_by_ppid))
         .route("/graph/{pid}", get(get_graph))
         .route("/events", get(stream_events))
+        .route("/events/history", get(history::events_history))
         .route("/stream", get(stream_events))
         .route("/system", get(system_snapshot))
         .route("/timeline", get(get_timeline))
+        .route("/grafana", get(grafana::health))
+        .route("/grafana/search", post(grafana::search))
+        .route("/grafana/metrics", post(grafana::metrics))
+        .route("/grafana/query", post(grafana::query))
         .route("/metrics/system", get(get_system_metrics))
         .route("/alerts", get(stream_alerts))
         .route("/insights", get(get_insights))
         .route("/insights/recent", get(get_recent_insights))
         .route("/insights/{id}", get(get_insight_by_id))
         .route("/insights/{id}/feedback", post(submit_feedback))
+        .route("/insights/{id}/evidence", get(get_insight_evidence))
         .route("/api/feedback", post(submit_feedback_api))
-        .route("/api/slack/interactions", post(handle_slack_interaction))
         .route("/incidents", get(get_incidents))
         .route("/incidents/summary", get(get_incident_summary))
         .route("/incidents/stats", get(get_incident_stats))
         .route("/incidents/{id}", get(get_incident_by_id))
+        .route("/incidents/{id}/profile", get(get_incident_profile))
+        .route(
+            "/incidents/{id}/timeline",
+            get(incident_timeline::incident_timeline),
+        )
         .route("/attribution", get(get_attributions))
+        .route("/changes", post(changes::post_change))
+        .route("/changes", get(changes::list_changes))
+        .route("/rollups", get(get_rollups))
+        .route("/rules/schema", get(get_rules_schema))
+        .route("/rules/stats", get(get_rule_stats))
+        .route("/rules/suggestions", get(get_rule_suggestions))
+        .route("/rules/{name}", axum::routing::patch(patch_rule))
+        .route("/silences", get(silences::list_silences))
+        .route("/silences", post(silences::post_silence))
+        .route(
+            "/silences/{id}",
+            axum::routing::delete(silences::delete_silence),
+        )
+        .route("/config/throttle", get(get_throttle))
+        .route("/config/throttle", axum::routing::patch(patch_throttle))
+        .route("/stats/windows", get(get_window_stats))
+        .route("/storage/stats", get(get_storage_stats))
+        .route("/topology/cpu", get(get_cpu_topology))
+        .route("/baselines", get(get_baselines))
+        .route("/inventory", get(get_inventory))
         .route("/metrics", get(metrics_handler))
         .route("/status", get(status_handler))
+        .route("/observe/report", get(observe_report::observe_report))
+        .route("/quotas", get(quotas::get_quotas))
         .route("/healthz", get(healthz))
         // .route("/insights/schema", get(get_insight_schema_route)) // Removed (YAGNI cleanup)
         .route("/actions", get(get_actions))
@@ -2007,6 +2591,13 @@ pub fn all_routes(app_state: Arc<AppState>) -> Router {
         router = router.route("/metrics/prometheus", get(prometheus_metrics));
     }
 
+    // Slack can't send the API token: with a signing secret, interactions
+    // are checked against their signature instead and skip token auth.
+    let slack_signed = app_state.slack_signing_secret.is_some();
+    if !slack_signed {
+        router = router.route("/api/slack/interactions", post(handle_slack_interaction));
+    }
+
     if auth_token.is_some() {
         router = router.layer(axum::middleware::from_fn_with_state(
             auth_token,
@@ -2014,6 +2605,10 @@ pub fn all_routes(app_state: Arc<AppState>) -> Router {
         ));
     }
 
+    if slack_signed {
+        router = router.route("/api/slack/interactions", post(handle_slack_interaction));
+    }
+
     router.with_state(app_state)
 }
 
@@ -2032,27 +2627,64 @@ pub fn uds_routes(app_state: Arc<AppState>) -> Router {
         .route("/processes", get(get_processes))
         .route("/processes/live", get(stream_processes_live))
         .route("/processes/{pid}", get(get_process_by_pid))
+        .route(
+            "/processes/{pid}/malloc-sample",
+            post(malloc_sample::malloc_sample),
+        )
         .route("/ppid/{ppid}", get(get_by_ppid))
         .route("/graph/{pid}", get(get_graph))
         .route("/events", get(stream_events))
+        .route("/events/history", get(history::events_history))
         .route("/stream", get(stream_events))
         .route("/system", get(system_snapshot))
         .route("/timeline", get(get_timeline))
+        .route("/grafana", get(grafana::health))
+        .route("/grafana/search", post(grafana::search))
+        .route("/grafana/metrics", post(grafana::metrics))
+        .route("/grafana/query", post(grafana::query))
         .route("/metrics/system", get(get_system_metrics))
         .route("/alerts", get(stream_alerts))
         .route("/insights", get(get_insights))
         .route("/insights/recent", get(get_recent_insights))
         .route("/insights/{id}", get(get_insight_by_id))
         .route("/insights/{id}/feedback", post(submit_feedback))
+        .route("/insights/{id}/evidence", get(get_insight_evidence))
         .route("/api/feedback", post(submit_feedback_api))
         .route("/api/slack/interactions", post(handle_slack_interaction))
         .route("/incidents", get(get_incidents))
         .route("/incidents/summary", get(get_incident_summary))
         .route("/incidents/stats", get(get_incident_stats))
         .route("/incidents/{id}", get(get_incident_by_id))
+        .route("/incidents/{id}/profile", get(get_incident_profile))
+        .route(
+            "/incidents/{id}/timeline",
+            get(incident_timeline::incident_timeline),
+        )
         .route("/attribution", get(get_attributions))
+        .route("/changes", post(changes::post_change))
+        .route("/changes", get(changes::list_changes))
+        .route("/rollups", get(get_rollups))
+        .route("/rules/schema", get(get_rules_schema))
+        .route("/rules/stats", get(get_rule_stats))
+        .route("/rules/suggestions", get(get_rule_suggestions))
+        .route("/rules/{name}", axum::routing::patch(patch_rule))
+        .route("/silences", get(silences::list_silences))
+        .route("/silences", post(silences::post_silence))
+        .route(
+            "/silences/{id}",
+            axum::routing::delete(silences::delete_silence),
+        )
+        .route("/config/throttle", get(get_throttle))
+        .route("/config/throttle", axum::routing::patch(patch_throttle))
+        .route("/stats/windows", get(get_window_stats))
+        .route("/storage/stats", get(get_storage_stats))
+        .route("/topology/cpu", get(get_cpu_topology))
+        .route("/baselines", get(get_baselines))
+        .route("/inventory", get(get_inventory))
         .route("/metrics", get(metrics_handler))
         .route("/status", get(status_handler))
+        .route("/observe/report", get(observe_report::observe_report))
+        .route("/quotas", get(quotas::get_quotas))
         .route("/healthz", get(healthz))
         .route("/actions", get(get_actions))
         .route("/actions/{id}", get(get_action_by_id))
@@ -2125,7 +2757,12 @@ fn dependency_version(target: &str) -> Option<String> {
 
 #[derive(Deserialize)]
 struct FeedbackRequest {
+    /// Empty when labelling a rule's alerts.
+    #[serde(default)]
     insight_id: String,
+    /// Rule whose alerts are labelled; feeds `/rules/suggestions`.
+    #[serde(default)]
+    rule: Option<String>,
     label: String,  // "useful", "noise", "wrong"
     source: String, // "cli", "slack", "web"
     user_id: Option<String>,
@@ -2135,6 +2772,12 @@ async fn submit_feedback_api(
     State(app): State<Arc<AppState>>,
     Json(req): Json<FeedbackRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if req.insight_id.is_empty() && req.rule.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "set insight_id or rule".to_string(),
+        ));
+    }
     let store = app.incident_store.as_ref().ok_or_else(|| {
         (
             StatusCode::SERVICE_UNAVAILABLE,
@@ -2148,6 +2791,7 @@ async fn submit_feedback_api(
             &req.label,
             &req.source,
             req.user_id.as_deref(),
+            req.rule.as_deref(),
         )
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
@@ -2180,7 +2824,7 @@ fn default_limit() -> i64 {
 async fn get_incidents(
     Query(params): Query<IncidentQueryParams>,
     State(app): State<Arc<AppState>>,
-) -> Result<Json<Vec<Incident>>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
     let store = app.incident_store.as_ref().ok_or_else(|| {
         (
             StatusCode::SERVICE_UNAVAILABLE,
@@ -2203,73 +2847,396 @@ async fn get_incidents(
         incidents
     };
 
-    Ok(Json(filtered))
+    Ok(Json(app.redaction.redact_json(Destination::Api, &filtered)))
 }
 
-/// GET /incidents/:id - Get incident by ID
-async fn get_incident_by_id(
-    Path(id): Path<i64>,
+#[derive(Deserialize)]
+struct RollupQueryParams {
+    /// Bucket width in seconds: 60 or 300.
+    #[serde(default = "default_rollup_resolution")]
+    resolution: i64,
+    #[serde(default)]
+    metric: Option<String>,
+    /// Unix seconds; defaults to 24h ago.
+    #[serde(default)]
+    from: Option<i64>,
+    /// Unix seconds; defaults to now.
+    #[serde(default)]
+    to: Option<i64>,
+}
+
+fn default_rollup_resolution() -> i64 {
+    cognitod::rollups::RESOLUTION_1M
+}
+
+/// GET /rules/schema - JSON Schema for the rules file
+async fn get_rules_schema() -> Json<serde_json::Value> {
+    Json(cognitod::alerts::rules_schema())
+}
+
+/// GET /rules/stats - Per-rule evaluation counts, fires and time, most
+/// expensive first
+async fn get_rule_stats(
     State(app): State<Arc<AppState>>,
-) -> Result<Json<Incident>, (StatusCode, String)> {
-    let store = app.incident_store.as_ref().ok_or_else(|| {
+) -> Json<Vec<cognitod::metrics::RuleStats>> {
+    Json(app.metrics.rule_stats())
+}
+
+/// GET /stats/windows - Detector window counts against each rule's
+/// threshold
+async fn get_window_stats(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<cognitod::alerts::WindowStats>, (StatusCode, String)> {
+    let engine = app.rules.as_ref().ok_or_else(|| {
         (
             StatusCode::SERVICE_UNAVAILABLE,
-            "Incident store not available".to_string(),
+            "rules engine not loaded".to_string(),
         )
     })?;
+    Ok(Json(engine.window_stats().await))
+}
 
-    let incident = store
-        .get(id)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or_else(|| (StatusCode::NOT_FOUND, "Incident not found".to_string()))?;
+#[derive(Deserialize)]
+struct RuleSuggestionParams {
+    /// Days of alert history and feedback to score.
+    #[serde(default = "default_suggestion_days")]
+    days: u64,
+}
 
-    Ok(Json(incident))
+fn default_suggestion_days() -> u64 {
+    7
 }
 
-/// GET /incidents/stats - Get incident statistics
-async fn get_incident_stats(
+/// GET /rules/suggestions - Noise score per rule from alert volume and
+/// feedback labels, with threshold/cooldown suggestions for noisy rules
+async fn get_rule_suggestions(
+    Query(params): Query<RuleSuggestionParams>,
     State(app): State<Arc<AppState>>,
-) -> Result<Json<IncidentStats>, (StatusCode, String)> {
-    let store = app.incident_store.as_ref().ok_or_else(|| {
+) -> Result<Json<Vec<cognitod::tuning::RuleQuality>>, (StatusCode, String)> {
+    let engine = app.rules.as_ref().ok_or_else(|| {
         (
             StatusCode::SERVICE_UNAVAILABLE,
-            "Incident store not available".to_string(),
+            "rules engine not loaded".to_string(),
         )
     })?;
+    let lookback = params.days.clamp(1, 90) * 86_400;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let since = now.saturating_sub(lookback);
 
-    let stats = store
-        .stats()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    Ok(Json(stats))
+    let mut fires = std::collections::HashMap::new();
+    for record in app.alert_history.get_all().await {
+        if record.timestamp >= since {
+            *fires.entry(record.rule).or_insert(0) += 1;
+        }
+    }
+    let mut labels: std::collections::HashMap<String, cognitod::tuning::Labels> =
+        std::collections::HashMap::new();
+    if let Some(store) = &app.incident_store {
+        let rows = store
+            .rule_feedback(since as i64)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        for (rule, label, count) in rows {
+            labels.entry(rule).or_default().add(&label, count as u64);
+        }
+    }
+    // Alert history is in memory, so it covers this run at most.
+    let observed = Duration::from_secs(lookback.min(app.metrics.uptime_seconds()));
+    Ok(Json(cognitod::tuning::assess(
+        &engine.rule_views(),
+        &fires,
+        &labels,
+        observed,
+    )))
 }
 
-#[derive(Serialize)]
-struct IncidentSummary {
-    total: u64,
-    analyzed: u64,
-    pending_analysis: u64,
-    by_event_type: std::collections::HashMap<String, u64>,
-    recent: Vec<Incident>,
+/// PATCH /rules/{name} - Adjust a loaded rule's threshold/cooldown, optionally
+/// writing it back to the rules file
+async fn patch_rule(
+    Path(name): Path<String>,
+    State(app): State<Arc<AppState>>,
+    Json(patch): Json<cognitod::alerts::RulePatch>,
+) -> Result<Json<cognitod::alerts::RuleView>, (StatusCode, String)> {
+    use cognitod::alerts::RulePatchError;
+
+    let engine = app.rules.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "rules engine not loaded".to_string(),
+        )
+    })?;
+    if patch.threshold.is_none() && patch.cooldown.is_none() && patch.mode.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "nothing to change; set threshold, cooldown and/or mode".to_string(),
+        ));
+    }
+    engine
+        .update_rule(&name, &patch)
+        .await
+        .map(Json)
+        .map_err(|err| {
+            let status = match err {
+                RulePatchError::NotFound => StatusCode::NOT_FOUND,
+                RulePatchError::Invalid(_) => StatusCode::BAD_REQUEST,
+                RulePatchError::Persist(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, err.to_string())
+        })
 }
 
-/// GET /incidents/summary - Get comprehensive incident summary
-async fn get_incident_summary(
+/// GET /config/throttle - Per-process emission intervals of the probes
+async fn get_throttle(
     State(app): State<Arc<AppState>>,
-) -> Result<Json<IncidentSummary>, (StatusCode, String)> {
-    let store = app.incident_store.as_ref().ok_or_else(|| {
+) -> Result<Json<ThrottleIntervals>, (StatusCode, String)> {
+    let throttle = app.throttle.as_ref().ok_or_else(|| {
         (
             StatusCode::SERVICE_UNAVAILABLE,
-            "Incident store not available".to_string(),
+            "probe throttling unavailable".to_string(),
         )
     })?;
+    Ok(Json(throttle.intervals()))
+}
 
-    let stats = store
-        .stats()
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+/// PATCH /config/throttle - Change the emission intervals of the running
+/// probes until restart
+async fn patch_throttle(
+    State(app): State<Arc<AppState>>,
+    Json(patch): Json<ThrottlePatch>,
+) -> Result<Json<ThrottleIntervals>, (StatusCode, String)> {
+    let throttle = app.throttle.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "probe throttling unavailable".to_string(),
+        )
+    })?;
+    if patch.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "nothing to change; set page_fault_ms, block_io_ms and/or net_ms".to_string(),
+        ));
+    }
+    throttle
+        .update(&patch)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))
+}
+
+/// GET /storage/stats - Disk usage and retention limits per store
+async fn get_storage_stats(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<cognitod::retention::StorageStats>, (StatusCode, String)> {
+    let storage = app.storage.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "storage stats unavailable".to_string(),
+        )
+    })?;
+    Ok(Json(storage.stats()))
+}
+
+async fn get_baselines(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<cognitod::baselines::BaselinesView>, (StatusCode, String)> {
+    let baselines = app.baselines.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "baselines disabled".to_string(),
+        )
+    })?;
+    Ok(Json(baselines.view()))
+}
+
+/// GET /inventory - Kernel, distro, CPU, memory, cloud instance and GPUs of
+/// this host
+async fn get_inventory(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<cognitod::inventory::HostFacts>, (StatusCode, String)> {
+    let facts = app.inventory.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "inventory disabled".to_string(),
+        )
+    })?;
+    Ok(Json(facts.as_ref().clone()))
+}
+
+#[derive(Deserialize)]
+struct TopologyQuery {
+    pid: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct CpuTopologyResponse {
+    #[serde(flatten)]
+    topology: cognitod::topology::CpuTopology,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    placement: Option<cognitod::topology::Placement>,
+}
+
+/// GET /topology/cpu - CPUs, NUMA nodes and packages; with `?pid=` also where
+/// that process's threads are running
+async fn get_cpu_topology(
+    Query(query): Query<TopologyQuery>,
+) -> Result<Json<CpuTopologyResponse>, (StatusCode, String)> {
+    let topology = cognitod::topology::CpuTopology::read();
+    let placement = match query.pid {
+        Some(pid) => Some(
+            cognitod::topology::placement(pid, &topology)
+                .ok_or_else(|| (StatusCode::NOT_FOUND, format!("process {pid} not found")))?,
+        ),
+        None => None,
+    };
+    Ok(Json(CpuTopologyResponse {
+        topology,
+        placement,
+    }))
+}
+
+/// GET /rollups - Downsampled event, alert and top-process history
+async fn get_rollups(
+    Query(params): Query<RollupQueryParams>,
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<Vec<cognitod::RollupRow>>, (StatusCode, String)> {
+    let store = app.incident_store.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Incident store not available".to_string(),
+        )
+    })?;
+    if ![
+        cognitod::rollups::RESOLUTION_1M,
+        cognitod::rollups::RESOLUTION_5M,
+    ]
+    .contains(&params.resolution)
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "resolution must be 60 or 300".to_string(),
+        ));
+    }
+
+    let to = params.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = params.from.unwrap_or(to - 86_400);
+    let rows = store
+        .query_rollups(params.resolution, params.metric.as_deref(), from, to)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(rows))
+}
+
+/// GET /incidents/:id/profile - Sampled stack profile
+///
+/// Returns folded stacks (`flamegraph.pl` / `inferno-flamegraph` input) by
+/// default, or the full profile with `?format=json`.
+async fn get_incident_profile(
+    Path(id): Path<i64>,
+    Query(params): Query<ProfileQueryParams>,
+    State(app): State<Arc<AppState>>,
+) -> Result<Response, (StatusCode, String)> {
+    let store = app.incident_store.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Incident store not available".to_string(),
+        )
+    })?;
+    let incident = store
+        .get(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Incident {} not found", id)))?;
+    let raw = incident.profile.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("Incident {} has no profile", id),
+        )
+    })?;
+    let profile: cognitod::profiler::StackProfile = serde_json::from_str(&raw)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if params.format.as_deref() == Some("json") {
+        return Ok(Json(profile).into_response());
+    }
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        profile.folded(),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct ProfileQueryParams {
+    #[serde(default)]
+    format: Option<String>,
+}
+
+/// GET /incidents/:id - Get incident by ID
+async fn get_incident_by_id(
+    Path(id): Path<i64>,
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let store = app.incident_store.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Incident store not available".to_string(),
+        )
+    })?;
+
+    let incident = store
+        .get(id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Incident not found".to_string()))?;
+
+    Ok(Json(app.redaction.redact_json(Destination::Api, &incident)))
+}
+
+/// GET /incidents/stats - Get incident statistics
+async fn get_incident_stats(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<IncidentStats>, (StatusCode, String)> {
+    let store = app.incident_store.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Incident store not available".to_string(),
+        )
+    })?;
+
+    let stats = store
+        .stats()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(stats))
+}
+
+#[derive(Serialize)]
+struct IncidentSummary {
+    total: u64,
+    analyzed: u64,
+    pending_analysis: u64,
+    by_event_type: std::collections::HashMap<String, u64>,
+    recent: Vec<Incident>,
+}
+
+/// GET /incidents/summary - Get comprehensive incident summary
+async fn get_incident_summary(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<IncidentSummary>, (StatusCode, String)> {
+    let store = app.incident_store.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Incident store not available".to_string(),
+        )
+    })?;
+
+    let stats = store
+        .stats()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     let recent = store
         .recent(10)
@@ -2306,9 +3273,18 @@ struct SlackAction {
     value: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct SlackUser {
+    id: String,
+    #[serde(default)]
+    username: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct SlackPayload {
     actions: Vec<SlackAction>,
+    #[serde(default)]
+    user: Option<SlackUser>,
 }
 
 async fn get_insight_by_id(
@@ -2316,7 +3292,10 @@ async fn get_insight_by_id(
     Query(params): Query<std::collections::HashMap<String, String>>,
     State(app): State<Arc<AppState>>,
 ) -> Response {
-    let record = app.insights.get_by_id(&id);
+    let record = app.insights.get_by_id(&id).map(|mut rec| {
+        rec.insight = app.redaction.redact_insight(Destination::Api, &rec.insight);
+        rec
+    });
 
     match record {
         Some(rec) => {
@@ -2403,6 +3382,22 @@ async fn get_insight_by_id(
     }
 }
 
+/// GET /insights/{id}/evidence - Download the evidence bundle of an insight
+async fn get_insight_evidence(
+    Path(id): Path<String>,
+    State(app): State<Arc<AppState>>,
+) -> Response {
+    let Some(bundle) = app.insights.evidence(&id) else {
+        return (StatusCode::NOT_FOUND, "Evidence not found").into_response();
+    };
+    let disposition = format!("attachment; filename=\"linnix-evidence-{id}.json\"");
+    (
+        [(header::CONTENT_DISPOSITION, disposition)],
+        Json(app.redaction.redact_json(Destination::Exports, &*bundle)),
+    )
+        .into_response()
+}
+
 #[derive(Deserialize)]
 struct FeedbackPayload {
     feedback: crate::insights::Feedback,
@@ -2434,8 +3429,23 @@ async fn submit_feedback(
 
 async fn handle_slack_interaction(
     State(state): State<Arc<AppState>>,
-    Form(form): Form<SlackInteractionPayload>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> impl IntoResponse {
+    if let Some(secret) = &state.slack_signing_secret {
+        let now = chrono::Utc::now().timestamp();
+        if let Err(e) = slack::verify(secret, &headers, &body, now) {
+            log::warn!("Rejected Slack interaction: {}", e);
+            return (StatusCode::UNAUTHORIZED, "Invalid signature").into_response();
+        }
+    }
+    let form: SlackInteractionPayload = match serde_urlencoded::from_bytes(&body) {
+        Ok(form) => form,
+        Err(e) => {
+            log::warn!("Failed to parse Slack interaction form: {}", e);
+            return (StatusCode::BAD_REQUEST, "Invalid payload").into_response();
+        }
+    };
     let payload: SlackPayload = match serde_json::from_str(&form.payload) {
         Ok(p) => p,
         Err(e) => {
@@ -2444,13 +3454,67 @@ async fn handle_slack_interaction(
         }
     };
 
+    // Alert buttons label the rule, independent of enforcement.
+    for action in &payload.actions {
+        let label = match action.action_id.as_str() {
+            "alert_useful" => "useful",
+            "alert_noise" => "noise",
+            _ => continue,
+        };
+        let Some(store) = &state.incident_store else {
+            log::warn!("Received alert feedback via Slack but the incident store is disabled");
+            break;
+        };
+        match store
+            .insert_feedback("", label, "slack", None, Some(&action.value))
+            .await
+        {
+            Ok(_) => {
+                log::info!(
+                    "Marked alerts of rule {} as {} via Slack",
+                    action.value,
+                    label
+                );
+                state.metrics.inc_feedback_entry();
+            }
+            Err(e) => log::warn!(
+                "Failed to record Slack feedback for {}: {}",
+                action.value,
+                e
+            ),
+        }
+    }
+
+    for action in &payload.actions {
+        let (id, feedback) = match action.action_id.as_str() {
+            "feedback_useful" => (
+                action.value.strip_prefix("useful:"),
+                crate::insights::Feedback::Useful,
+            ),
+            "feedback_noise" => (
+                action.value.strip_prefix("noise:"),
+                crate::insights::Feedback::Noise,
+            ),
+            _ => continue,
+        };
+        if let Some(id) = id
+            && state.insights.update_feedback(id, feedback.clone())
+        {
+            log::info!("Marked insight {} as {:?} via Slack", id, feedback);
+        }
+    }
+
+    let approver = match &payload.user {
+        Some(user) => format!("slack:{}", user.username.as_deref().unwrap_or(&user.id)),
+        None => "slack_user".to_string(),
+    };
     if let Some(enforcement) = &state.enforcement {
         for action in payload.actions {
             if action.action_id == "approve_action" {
                 if let Some(ids_str) = action.value.strip_prefix("approve:") {
                     for id in ids_str.split('|') {
                         if !id.is_empty() {
-                            match enforcement.approve(id, "slack_user".to_string()).await {
+                            match enforcement.approve(id, approver.clone()).await {
                                 Ok(_) => {
                                     log::info!("Approved action {} via Slack", id);
                                     state.metrics.inc_slack_approved();
@@ -2462,39 +3526,29 @@ async fn handle_slack_interaction(
                         }
                     }
                 }
-            } else if action.action_id == "deny_action" {
-                if let Some(ids_str) = action.value.strip_prefix("deny:") {
-                    for id in ids_str.split('|') {
-                        if !id.is_empty() {
-                            match enforcement.reject(id, "slack_user".to_string()).await {
-                                Ok(_) => {
-                                    log::info!("Rejected action {} via Slack", id);
-                                    state.metrics.inc_slack_denied();
-                                }
-                                Err(e) => {
-                                    log::warn!("Failed to reject action {} via Slack: {}", id, e)
-                                }
+            } else if action.action_id == "deny_action"
+                && let Some(ids_str) = action.value.strip_prefix("deny:")
+            {
+                for id in ids_str.split('|') {
+                    if !id.is_empty() {
+                        match enforcement.reject(id, approver.clone()).await {
+                            Ok(_) => {
+                                log::info!("Rejected action {} via Slack", id);
+                                state.metrics.inc_slack_denied();
+                            }
+                            Err(e) => {
+                                log::warn!("Failed to reject action {} via Slack: {}", id, e)
                             }
                         }
                     }
                 }
-            } else if action.action_id == "feedback_useful"
-                && let Some(id) = action.value.strip_prefix("useful:")
-                && state
-                    .insights
-                    .update_feedback(id, crate::insights::Feedback::Useful)
-            {
-                log::info!("Marked insight {} as Useful", id);
-            } else if action.action_id == "feedback_noise"
-                && let Some(id) = action.value.strip_prefix("noise:")
-                && state
-                    .insights
-                    .update_feedback(id, crate::insights::Feedback::Noise)
-            {
-                log::info!("Marked insight {} as Noise", id);
             }
         }
-    } else {
+    } else if payload
+        .actions
+        .iter()
+        .any(|a| matches!(a.action_id.as_str(), "approve_action" | "deny_action"))
+    {
         log::warn!("Received Slack interaction but enforcement is disabled");
     }
 
@@ -2505,7 +3559,7 @@ async fn handle_slack_interaction(
 mod tests {
     use super::*;
     use crate::insights::InsightStore;
-    use crate::runtime::probes::{ProbeState, RssProbeMode};
+    use crate::runtime::probes::{BlockProbeMode, ProbeState, RssProbeMode};
     use crate::{PERCENT_MILLI_UNKNOWN, ProcessEvent};
     use axum::body::{Body, to_bytes};
     use axum::http::Request;
@@ -2574,6 +3628,7 @@ mod tests {
             data2: 0,
             aux: 0,
             aux2: 0,
+            cgroup_id: 0,
         };
         let base_event = ProcessEvent::new(base_wire);
         for _ in 0..1500 {
@@ -2591,6 +3646,20 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2608,7 +3677,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let Json(resp) = super::status_handler(State(app_state)).await;
@@ -2622,6 +3693,7 @@ mod tests {
             "rss_mb",
             "events_per_sec",
             "rb_overflows",
+            "kernel_dropped_events",
             "rate_limited",
             "kernel_version",
             "aya_version",
@@ -2644,11 +3716,26 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "tracepoint",
             probe_state: ProbeState {
                 rss_probe: RssProbeMode::CoreMm,
+                block_probe: BlockProbeMode::Btf,
                 btf_available: true,
             },
             enforcement: None,
@@ -2664,7 +3751,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
 
@@ -2686,6 +3775,20 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2703,7 +3806,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(Arc::clone(&app_state));
@@ -2724,10 +3829,25 @@ mod tests {
         let ctx = Arc::new(ContextStore::new(Duration::from_secs(60), 10, None));
         let metrics = Arc::new(Metrics::new());
         metrics.events_total.fetch_add(42, Ordering::Relaxed);
+        metrics.register_rule("say \"hi\" \\ bye\nnow");
         let app_state = Arc::new(AppState {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2745,7 +3865,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(Arc::clone(&app_state));
@@ -2774,6 +3896,10 @@ mod tests {
             body_text.contains("linnix_events_total"),
             "expected metric missing: {body_text}"
         );
+        assert!(
+            body_text.contains("linnix_rule_fires_total{rule=\"say \\\"hi\\\" \\\\ bye\\nnow\"} 0"),
+            "rule label not escaped: {body_text}"
+        );
     }
 
     #[tokio::test]
@@ -2784,6 +3910,20 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2801,7 +3941,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(app_state);
@@ -2825,6 +3967,20 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2842,7 +3998,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(app_state);
@@ -2866,6 +4024,20 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2883,7 +4055,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(app_state);
@@ -2908,6 +4082,20 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2925,7 +4113,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(app_state);
@@ -2950,6 +4140,20 @@ mod tests {
             context: Arc::clone(&ctx),
             metrics: Arc::clone(&metrics),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -2967,7 +4171,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(app_state);
@@ -2998,6 +4204,20 @@ mod tests {
             context: Arc::new(ContextStore::new(Duration::from_secs(60), 10, None)),
             metrics: Arc::new(Metrics::new()),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -3015,7 +4235,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         })
     }
@@ -3047,6 +4269,20 @@ mod tests {
             context: Arc::new(ContextStore::new(Duration::from_secs(60), 10, None)),
             metrics: Arc::new(Metrics::new()),
             alerts: None,
+            rules: None,
+            storage: None,
+            baselines: None,
+            sessions: None,
+            inventory: None,
+            pod_pressure: None,
+            leadership: None,
+            updates: None,
+            observe: None,
+            quota: None,
+            changes: None,
+            throttle: None,
+            malloc_sampler: None,
+            http: Client::new(),
             insights: Arc::new(InsightStore::new(16, None)),
             offline: Arc::new(OfflineGuard::new(false)),
             transport: "perf",
@@ -3064,7 +4300,9 @@ mod tests {
             spend_tracker: None,
             compliance_engine: None,
             receipt_redactor: None,
+            redaction: Arc::new(cognitod::redaction::RedactionPolicy::default()),
             payment_adapter: None,
+            slack_signing_secret: None,
             claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         });
         let router = super::all_routes(app_state);
@@ -3356,4 +4594,714 @@ mod tests {
         let err: serde_json::Value = serde_json::from_slice(&resp_body).unwrap();
         assert!(err["error"].as_str().unwrap().contains("999999999"));
     }
+
+    #[tokio::test]
+    async fn grafana_query_returns_event_and_alert_series() {
+        let app_state = app_state_with_mandate();
+        let wire = ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::Fork as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        app_state.context.add(ProcessEvent::new(wire));
+        app_state
+            .alert_history
+            .add_alert(Alert {
+                rule: "fork_storm".to_string(),
+                severity: cognitod::alerts::Severity::High,
+                message: "boom".to_string(),
+                host: "h".to_string(),
+                resolved_after_secs: None,
+                labels: Default::default(),
+            })
+            .await;
+
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let body = serde_json::json!({
+            "range": {
+                "from": (now_ms - 60_000).to_string(),
+                "to": (now_ms + 1_000).to_string(),
+            },
+            "intervalMs": 60_000,
+            "targets": [
+                {"target": "events.fork", "refId": "A"},
+                {"target": "events.exec", "refId": "B"},
+                {"target": "alerts.high", "refId": "C"},
+                {"target": "top_cpu", "refId": "D"},
+            ],
+        });
+        let resp = super::all_routes(app_state)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/grafana/query")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let bytes = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let out: Vec<serde_json::Value> = serde_json::from_slice(&bytes).unwrap();
+        let total = |v: &serde_json::Value| -> u64 {
+            v["datapoints"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|p| p[0].as_u64().unwrap())
+                .sum()
+        };
+        assert_eq!(out[0]["target"], "events.fork");
+        assert_eq!(total(&out[0]), 1);
+        assert_eq!(total(&out[1]), 0);
+        assert_eq!(total(&out[2]), 1);
+        assert_eq!(out[3]["type"], "table");
+    }
+
+    #[tokio::test]
+    async fn grafana_query_rejects_unknown_target() {
+        let body = serde_json::json!({"targets": [{"target": "nope", "refId": "A"}]});
+        let resp = super::all_routes(app_state_with_mandate())
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/grafana/query")
+                    .header("content-type", "application/json")
+                    .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn rule_stats_lists_registered_rules() {
+        let state = app_state_with_mandate();
+        let counters = state.metrics.register_rule("fork_storm");
+        counters.record_eval(std::time::Duration::from_micros(5));
+        counters.inc_fired();
+
+        let resp = super::all_routes(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/rules/stats")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats[0]["rule"], "fork_storm");
+        assert_eq!(stats[0]["evaluations"], 1);
+        assert_eq!(stats[0]["fires"], 1);
+    }
+
+    #[tokio::test]
+    async fn event_stream_rejects_unknown_projection_fields() {
+        let router = super::all_routes(app_state_with_mandate());
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let resp = router
+            .clone()
+            .oneshot(get("/events?fields=pid,bogus"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("unknown event field 'bogus'"));
+
+        let resp = router
+            .oneshot(get("/events?group_by=comm&agg=avg:cpu_pct&interval=10"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/event-stream");
+    }
+
+    #[tokio::test]
+    async fn event_history_filters_recent_events() {
+        let app_state = app_state_with_mandate();
+        for (pid, event_type, comm) in [
+            (10, EventType::Exec, *b"cron\0\0\0\0\0\0\0\0\0\0\0\0"),
+            (11, EventType::Fork, *b"cron\0\0\0\0\0\0\0\0\0\0\0\0"),
+            (12, EventType::Exec, *b"backup.sh\0\0\0\0\0\0\0"),
+        ] {
+            app_state.context.add(ProcessEvent::new(ProcessEventWire {
+                pid,
+                ppid: 1,
+                uid: 0,
+                gid: 0,
+                event_type: event_type as u32,
+                ts_ns: 0,
+                seq: 0,
+                comm,
+                exit_time_ns: 0,
+                cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+                mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+                data: 0,
+                data2: 0,
+                aux: 0,
+                aux2: 0,
+                cgroup_id: 0,
+            }));
+        }
+        let router = super::all_routes(app_state);
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let resp = router
+            .clone()
+            .oneshot(get("/events/history?since=5m&type=exec&limit=1"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let out: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(out["truncated"], true);
+        assert_eq!(out["partial"], true, "history doesn't reach back 5m");
+        assert!(out["oldest"].is_string());
+        assert_eq!(out["events"][0]["pid"], 10);
+        assert_eq!(out["events"][0]["event_type_name"], "exec");
+        assert!(out["events"][0]["time"].is_string());
+
+        let resp = router
+            .clone()
+            .oneshot(get("/events/history?comm=backup&until=now"))
+            .await
+            .unwrap();
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let out: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(out["events"].as_array().unwrap().len(), 1);
+        assert_eq!(out["events"][0]["pid"], 12);
+
+        let resp = router
+            .oneshot(get("/events/history?since=soon"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn window_stats_need_a_rules_engine() {
+        let resp = super::all_routes(app_state_with_mandate())
+            .oneshot(
+                Request::builder()
+                    .uri("/stats/windows")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            "[[rules]]\nname = \"fork_burst\"\ndetector = \"fork_burst\"\nthreshold = 30\nwindow_seconds = 5\n",
+        )
+        .unwrap();
+        let engine = cognitod::alerts::RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.rules = Some(Arc::new(engine));
+        let resp = super::all_routes(Arc::new(state))
+            .oneshot(
+                Request::builder()
+                    .uri("/stats/windows")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["forks"], 0);
+        assert_eq!(stats["rules"][0]["rule"], "fork_burst");
+        assert_eq!(stats["rules"][0]["metric"], "forks");
+        assert_eq!(stats["rules"][0]["threshold"], 30.0);
+        assert_eq!(stats["rules"][0]["window_seconds"], 5);
+    }
+
+    #[tokio::test]
+    async fn rule_suggestions_score_alert_volume_and_feedback() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            "[[rules]]\nname = \"fork_burst\"\ndetector = \"fork_burst\"\nthreshold = 30\nwindow_seconds = 5\n",
+        )
+        .unwrap();
+        let engine = cognitod::alerts::RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+        let store = IncidentStore::new(dir.path().join("incidents.db"))
+            .await
+            .unwrap();
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.rules = Some(Arc::new(engine));
+        state.incident_store = Some(Arc::new(store));
+        state
+            .alert_history
+            .add_alert(Alert {
+                rule: "fork_burst".to_string(),
+                severity: cognitod::alerts::Severity::Medium,
+                message: "fork burst: 31 forks in 5s".to_string(),
+                host: "h".to_string(),
+                resolved_after_secs: None,
+                labels: Default::default(),
+            })
+            .await;
+        let router = super::all_routes(Arc::new(state));
+
+        let post = |body: serde_json::Value| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/feedback")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap()
+        };
+        let resp = router
+            .clone()
+            .oneshot(post(serde_json::json!({"label": "noise", "source": "cli"})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "no insight or rule");
+        let resp = router
+            .clone()
+            .oneshot(post(
+                serde_json::json!({"rule": "fork_burst", "label": "noise", "source": "cli"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = router
+            .oneshot(
+                Request::builder()
+                    .uri("/rules/suggestions")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let rules: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rules[0]["rule"], "fork_burst");
+        assert_eq!(rules[0]["fires"], 1);
+        assert_eq!(rules[0]["noise"], 1);
+        assert!(rules[0]["noise_score"].as_f64().unwrap() > 0.6);
+        assert!(rules[0]["suggestion"]["threshold"].as_f64().unwrap() > 30.0);
+    }
+
+    #[tokio::test]
+    async fn patch_rule_tunes_threshold_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        std::fs::write(
+            &path,
+            "[[rules]]\nname = \"fork_burst\"\ndetector = \"fork_burst\"\nthreshold = 30\nwindow_seconds = 5\n",
+        )
+        .unwrap();
+        let metrics = Arc::new(Metrics::new());
+        let engine = cognitod::alerts::RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::clone(&metrics),
+        )
+        .unwrap();
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.rules = Some(Arc::new(engine));
+        let router = super::all_routes(Arc::new(state));
+
+        let patch = |uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method("PATCH")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap()
+        };
+
+        let resp = router
+            .clone()
+            .oneshot(patch(
+                "/rules/fork_burst",
+                serde_json::json!({"threshold": 300, "cooldown": 900, "persist": true}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let rule: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(rule["threshold"], 300);
+        assert_eq!(rule["cooldown"], 900);
+
+        let reloaded = cognitod::alerts::RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            metrics,
+        )
+        .unwrap();
+        let persisted = reloaded.rule("fork_burst").unwrap();
+        assert_eq!(persisted.threshold.unwrap().as_u64(), Some(300));
+        assert_eq!(persisted.cooldown, 900);
+
+        let resp = router
+            .clone()
+            .oneshot(patch(
+                "/rules/fork_burst",
+                serde_json::json!({"threshold": 1.5}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = router
+            .oneshot(patch("/rules/nope", serde_json::json!({"cooldown": 1})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn patch_throttle_changes_probe_intervals() {
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.throttle = Some(Arc::new(ThrottleControl::new(
+            ThrottleIntervals::from_probes(&cognitod::config::ProbesConfig::default()),
+        )));
+        let router = super::all_routes(Arc::new(state));
+        let patch = |body: serde_json::Value| {
+            Request::builder()
+                .method("PATCH")
+                .uri("/config/throttle")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap()
+        };
+
+        let resp = router
+            .clone()
+            .oneshot(patch(serde_json::json!({"page_fault_ms": 500})))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/config/throttle")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let intervals: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            intervals,
+            serde_json::json!({"page_fault_ms": 500, "block_io_ms": 100, "net_ms": 100})
+        );
+
+        let resp = router.oneshot(patch(serde_json::json!({}))).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn storage_stats_report_per_store_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let alerts = dir.path().join("alerts.ndjson");
+        std::fs::write(&alerts, "{\"rule\":\"x\"}\n").unwrap();
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.storage = Some(Arc::new(cognitod::retention::Storage::new(
+            cognitod::config::RetentionConfig::default(),
+            cognitod::retention::StorePaths {
+                alerts: Some(alerts),
+                ..Default::default()
+            },
+            Arc::clone(&state.insights),
+            None,
+        )));
+
+        let resp = super::all_routes(Arc::new(state))
+            .oneshot(
+                Request::builder()
+                    .uri("/storage/stats")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats["stores"][1]["store"], "alerts");
+        assert_eq!(stats["stores"][1]["bytes"], 13);
+        assert_eq!(stats["total_bytes"], 13);
+        assert_eq!(stats["stores"][1]["max_age_days"], 30);
+    }
+
+    #[tokio::test]
+    async fn baselines_list_every_hour_or_503_when_disabled() {
+        let request = || {
+            Request::builder()
+                .uri("/baselines")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let resp = super::all_routes(app_state_with_mandate())
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let dir = tempfile::tempdir().unwrap();
+        let baselines = cognitod::baselines::Baselines::new(cognitod::config::BaselineConfig {
+            path: dir.path().join("baselines.json").display().to_string(),
+            ..Default::default()
+        });
+        baselines.sample(Duration::from_secs(10), 100, 20, 5, 42.0);
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.baselines = Some(Arc::new(baselines));
+
+        let resp = super::all_routes(Arc::new(state))
+            .oneshot(request())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let view: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(view["cpu_pct"].as_array().unwrap().len(), 24);
+        assert_eq!(view["current"]["fork_rate"], 2.0);
+        assert_eq!(view["current"]["events_per_sec"], 10.0);
+        let hour = view["hour"].as_u64().unwrap() as usize;
+        assert_eq!(view["cpu_pct"][hour]["samples"], 1);
+        assert_eq!(view["cpu_pct"][hour]["ready"], false);
+    }
+
+    #[tokio::test]
+    async fn cpu_topology_includes_placement_for_pid() {
+        let get = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let router = super::all_routes(app_state_with_mandate());
+        let resp = router
+            .clone()
+            .oneshot(get(format!("/topology/cpu?pid={}", std::process::id())))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let topo: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(topo["cpus"].is_array());
+        assert!(topo["placement"]["threads"].as_u64().unwrap() >= 1);
+
+        let resp = router
+            .oneshot(get("/topology/cpu?pid=4294967295".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn rules_schema_is_served() {
+        let resp = super::all_routes(app_state_with_mandate())
+            .oneshot(
+                Request::builder()
+                    .uri("/rules/schema")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = to_bytes(resp.into_body(), usize::MAX).await.unwrap();
+        let schema: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(schema["title"], "Linnix rules file");
+        assert!(
+            body.windows(b"fork_burst".len())
+                .any(|w| w == b"fork_burst")
+        );
+    }
+
+    #[tokio::test]
+    async fn slack_interactions_are_signed_not_token_authed() {
+        let mut state = Arc::try_unwrap(app_state_with_mandate()).ok().unwrap();
+        state.auth_token = Some("secret123".to_string());
+        state.slack_signing_secret = Some("signing".to_string());
+        let router = super::all_routes(Arc::new(state));
+        let body = "payload=%7B%22actions%22%3A%5B%5D%7D";
+        let request = |signature: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/slack/interactions")
+                .header("x-slack-request-timestamp", chrono::Utc::now().timestamp())
+                .header("x-slack-signature", signature)
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let signed = super::slack::sign("signing", chrono::Utc::now().timestamp(), body.as_bytes());
+        let resp = router.clone().oneshot(request(&signed)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let forged = super::slack::sign("other", chrono::Utc::now().timestamp(), body.as_bytes());
+        let resp = router.clone().oneshot(request(&forged)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // Everything else still needs the token.
+        let resp = router
+            .oneshot(
+                Request::builder()
+                    .uri("/rules/schema")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    /// A Slack button click for `action_id`/`value`, signed with "signing".
+    fn slack_click(action_id: &str, value: &str) -> Request<Body> {
+        let payload = serde_json::json!({
+            "actions": [{"action_id": action_id, "value": value}],
+            "user": {"id": "U1", "username": "oncall"},
+        });
+        let body = serde_urlencoded::to_string([("payload", payload.to_string())]).unwrap();
+        let now = chrono::Utc::now().timestamp();
+        Request::builder()
+            .method("POST")
+            .uri("/api/slack/interactions")
+            .header("x-slack-request-timestamp", now)
+            .header(
+                "x-slack-signature",
+                super::slack::sign("signing", now, body.as_bytes()),
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn slack_buttons_approve_and_deny_queued_actions() {
+        let queue = Arc::new(crate::enforcement::EnforcementQueue::new(300));
+        let propose = || {
+            queue.propose(
+                crate::enforcement::ActionType::AuthorizeExec {
+                    pid: 4242,
+                    cmd_hash: 7,
+                    expires_at: 0,
+                },
+                "test".to_string(),
+                "test".to_string(),
+                None,
+            )
+        };
+        let (first, second, third) = (
+            propose().await.unwrap(),
+            propose().await.unwrap(),
+            propose().await.unwrap(),
+        );
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.slack_signing_secret = Some("signing".to_string());
+        state.enforcement = Some(Arc::clone(&queue));
+        let state = Arc::new(state);
+        let router = super::all_routes(Arc::clone(&state));
+
+        let resp = router
+            .clone()
+            .oneshot(slack_click(
+                "approve_action",
+                &format!("approve:{first}|{second}"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = router
+            .oneshot(slack_click("deny_action", &format!("deny:{third}")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        for id in [&first, &second] {
+            let action = queue.get_by_id(id).await.unwrap();
+            assert_eq!(action.status, crate::enforcement::ActionStatus::Approved);
+            assert_eq!(action.approved_by.as_deref(), Some("slack:oncall"));
+        }
+        let denied = queue.get_by_id(&third).await.unwrap();
+        assert_eq!(denied.status, crate::enforcement::ActionStatus::Rejected);
+        assert_eq!(
+            (state.metrics.slack_approved(), state.metrics.slack_denied()),
+            (2, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn slack_alert_buttons_record_rule_feedback() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(
+            IncidentStore::new(dir.path().join("incidents.db"))
+                .await
+                .unwrap(),
+        );
+        let mut state = Arc::into_inner(app_state_with_mandate()).unwrap();
+        state.slack_signing_secret = Some("signing".to_string());
+        state.incident_store = Some(Arc::clone(&store));
+        let state = Arc::new(state);
+        let router = super::all_routes(Arc::clone(&state));
+
+        for (action_id, rule) in [
+            ("alert_useful", "fork_burst"),
+            ("alert_noise", "fork_burst"),
+            ("alert_noise", "cpu_spike"),
+        ] {
+            let resp = router
+                .clone()
+                .oneshot(slack_click(action_id, rule))
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let mut feedback = store.rule_feedback(0).await.unwrap();
+        feedback.sort();
+        assert_eq!(
+            feedback,
+            [
+                ("cpu_spike".to_string(), "noise".to_string(), 1),
+                ("fork_burst".to_string(), "noise".to_string(), 1),
+                ("fork_burst".to_string(), "useful".to_string(), 1),
+            ]
+        );
+        assert_eq!(state.metrics.feedback_entries(), 3);
+    }
 }
@@ -0,0 +1,69 @@
+//! `GET /observe/report`: what the detectors produced since startup, next to
+//! the notifications and enforcement actions that followed from it — held
+//! back in observe mode, performed in live mode. Reports from an observe
+//! fleet and a live fleet have the same shape and can be compared directly.
+
+use super::AppState;
+use axum::{Json, extract::State, http::StatusCode};
+use cognitod::observe::ObserveReport;
+use cognitod::redaction::Destination;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct Detected {
+    alerts: usize,
+    insights: usize,
+    /// None when the incident store is unavailable.
+    incidents: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct ReportResponse {
+    #[serde(flatten)]
+    report: ObserveReport,
+    /// Output of detectors, the circuit breaker and the ILM, which is the
+    /// same in both modes.
+    detected: Detected,
+}
+
+pub async fn observe_report(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let recorder = app.observe.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Observe recorder not available".to_string(),
+        )
+    })?;
+    let report = recorder.report();
+    let since = report.since;
+
+    let alerts = app
+        .alert_history
+        .get_all()
+        .await
+        .iter()
+        .filter(|a| a.timestamp >= since)
+        .count();
+    let insights = app
+        .insights
+        .recent(usize::MAX)
+        .iter()
+        .filter(|r| r.timestamp >= since)
+        .count();
+    let incidents = match &app.incident_store {
+        Some(store) => store.since(since as i64, None).await.ok().map(|v| v.len()),
+        None => None,
+    };
+
+    let response = ReportResponse {
+        report,
+        detected: Detected {
+            alerts,
+            insights,
+            incidents,
+        },
+    };
+    Ok(Json(app.redaction.redact_json(Destination::Api, &response)))
+}
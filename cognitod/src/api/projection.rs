@@ -0,0 +1,350 @@
+//! Field projection and aggregation for the event stream.
+//!
+//! `?fields=pid,comm,cpu_pct` trims each event to the named fields;
+//! `?group_by=comm&agg=count` replaces per-event messages with one
+//! `aggregate` message per interval holding a row per group. Dashboards that
+//! only chart counts then receive a few rows every few seconds instead of
+//! every event.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Fields of an event message, as serialized.
+const EVENT_FIELDS: &[&str] = &[
+    "pid",
+    "ppid",
+    "uid",
+    "gid",
+    "comm",
+    "event_type",
+    "event_type_name",
+    "ts_ns",
+    "seq",
+    "exit_time_ns",
+    "cpu_pct_milli",
+    "mem_pct_milli",
+    "cpu_percent",
+    "mem_percent",
+    "data",
+    "data2",
+    "aux",
+    "aux2",
+    "argv",
+];
+
+/// Fields that can't be summed or averaged.
+const NON_NUMERIC_FIELDS: &[&str] = &["comm", "event_type_name", "argv"];
+
+/// Groups kept per interval; events of further groups are counted as dropped.
+const MAX_GROUPS: usize = 10_000;
+
+const DEFAULT_INTERVAL_SECS: u64 = 5;
+const MAX_INTERVAL_SECS: u64 = 300;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EventQuery {
+    /// Comma-separated fields to keep.
+    #[serde(default)]
+    fields: Option<String>,
+    /// Comma-separated fields to aggregate by.
+    #[serde(default)]
+    group_by: Option<String>,
+    /// `count` (default), or `sum:<field>`, `avg:<field>`, `max:<field>`.
+    #[serde(default)]
+    agg: Option<String>,
+    /// Seconds between aggregate messages.
+    #[serde(default)]
+    interval: Option<u64>,
+}
+
+/// A requested field: the name it was asked for and the event field it
+/// reads.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Field {
+    name: String,
+    source: &'static str,
+}
+
+fn field(name: &str) -> Result<Field, String> {
+    let name = name.trim();
+    let source = match name {
+        "cpu_pct" => "cpu_percent",
+        "mem_pct" => "mem_percent",
+        "type" => "event_type_name",
+        other => EVENT_FIELDS
+            .iter()
+            .copied()
+            .find(|f| *f == other)
+            .ok_or_else(|| {
+                format!(
+                    "unknown event field '{other}'; known fields: {}",
+                    EVENT_FIELDS.join(", ")
+                )
+            })?,
+    };
+    Ok(Field {
+        name: name.to_string(),
+        source,
+    })
+}
+
+fn fields(list: &str) -> Result<Vec<Field>, String> {
+    let fields = list
+        .split(',')
+        .filter(|f| !f.trim().is_empty())
+        .map(field)
+        .collect::<Result<Vec<_>, _>>()?;
+    if fields.is_empty() {
+        return Err("empty field list".to_string());
+    }
+    Ok(fields)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AggFn {
+    Count,
+    Sum,
+    Avg,
+    Max,
+}
+
+/// How the stream should render events.
+#[derive(Debug)]
+pub(super) enum EventView {
+    /// Every event, optionally projected to some fields.
+    Events(Option<Vec<Field>>),
+    Aggregate(Aggregator),
+}
+
+impl EventView {
+    pub(super) fn parse(query: &EventQuery) -> Result<Self, String> {
+        let Some(group_by) = query.group_by.as_deref() else {
+            if query.agg.is_some() || query.interval.is_some() {
+                return Err("agg and interval need group_by".to_string());
+            }
+            return Ok(EventView::Events(
+                query.fields.as_deref().map(fields).transpose()?,
+            ));
+        };
+        if query.fields.is_some() {
+            return Err("fields can't be combined with group_by".to_string());
+        }
+        let keys = fields(group_by)?;
+        let (agg, value) = match query.agg.as_deref().unwrap_or("count") {
+            "count" => (AggFn::Count, None),
+            spec => {
+                let (func, name) = spec.split_once(':').ok_or_else(|| {
+                    format!(
+                        "unknown agg '{spec}'; use count, sum:<field>, avg:<field> or max:<field>"
+                    )
+                })?;
+                let func = match func {
+                    "sum" => AggFn::Sum,
+                    "avg" => AggFn::Avg,
+                    "max" => AggFn::Max,
+                    other => return Err(format!("unknown agg function '{other}'")),
+                };
+                let value = field(name)?;
+                if NON_NUMERIC_FIELDS.contains(&value.source) {
+                    return Err(format!(
+                        "can't aggregate non-numeric field '{}'",
+                        value.name
+                    ));
+                }
+                (func, Some(value))
+            }
+        };
+        let interval_secs = query
+            .interval
+            .unwrap_or(DEFAULT_INTERVAL_SECS)
+            .clamp(1, MAX_INTERVAL_SECS);
+        Ok(EventView::Aggregate(Aggregator {
+            keys,
+            agg,
+            value,
+            interval_secs,
+            groups: HashMap::new(),
+            dropped: 0,
+        }))
+    }
+}
+
+/// Keep only `fields` of an event message, under the requested names.
+pub(super) fn project(event: &Map<String, Value>, fields: &[Field]) -> Value {
+    let mut out = Map::with_capacity(fields.len());
+    for f in fields {
+        if let Some(v) = event.get(f.source) {
+            out.insert(f.name.clone(), v.clone());
+        }
+    }
+    Value::Object(out)
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    key: Vec<Value>,
+    count: u64,
+    /// Events that had the aggregated value (cpu/mem may be unknown).
+    samples: u64,
+    sum: f64,
+    max: Option<f64>,
+}
+
+#[derive(Debug)]
+pub(super) struct Aggregator {
+    keys: Vec<Field>,
+    agg: AggFn,
+    value: Option<Field>,
+    pub(super) interval_secs: u64,
+    groups: HashMap<String, Group>,
+    dropped: u64,
+}
+
+impl Aggregator {
+    pub(super) fn add(&mut self, event: &Map<String, Value>) {
+        let key: Vec<Value> = self
+            .keys
+            .iter()
+            .map(|f| event.get(f.source).cloned().unwrap_or(Value::Null))
+            .collect();
+        let id = key
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\0");
+        if !self.groups.contains_key(&id) && self.groups.len() >= MAX_GROUPS {
+            self.dropped += 1;
+            return;
+        }
+        let group = self.groups.entry(id).or_insert_with(|| Group {
+            key,
+            ..Default::default()
+        });
+        group.count += 1;
+        if let Some(value) = self
+            .value
+            .as_ref()
+            .and_then(|f| event.get(f.source))
+            .and_then(Value::as_f64)
+        {
+            group.samples += 1;
+            group.sum += value;
+            group.max = Some(group.max.map_or(value, |m| m.max(value)));
+        }
+    }
+
+    /// Rows for the interval just ended, largest count first; resets the
+    /// groups.
+    pub(super) fn flush(&mut self) -> Value {
+        let mut groups: Vec<(String, Group)> = self.groups.drain().collect();
+        groups.sort_by(|(a_id, a), (b_id, b)| b.count.cmp(&a.count).then_with(|| a_id.cmp(b_id)));
+        let value_name = self.value.as_ref().map(|f| {
+            let func = match self.agg {
+                AggFn::Sum => "sum",
+                AggFn::Avg => "avg",
+                AggFn::Max => "max",
+                AggFn::Count => "count",
+            };
+            format!("{func}_{}", f.name)
+        });
+        let rows: Vec<Value> = groups
+            .into_iter()
+            .map(|(_, g)| {
+                let mut row = Map::new();
+                for (f, v) in self.keys.iter().zip(g.key) {
+                    row.insert(f.name.clone(), v);
+                }
+                row.insert("count".to_string(), g.count.into());
+                if let Some(name) = &value_name {
+                    let value = match self.agg {
+                        AggFn::Sum => Some(g.sum),
+                        AggFn::Avg => (g.samples > 0).then(|| g.sum / g.samples as f64),
+                        AggFn::Max => g.max,
+                        AggFn::Count => None,
+                    };
+                    row.insert(
+                        name.clone(),
+                        value
+                            .and_then(serde_json::Number::from_f64)
+                            .map_or(Value::Null, Value::Number),
+                    );
+                }
+                Value::Object(row)
+            })
+            .collect();
+        let mut out = serde_json::json!({
+            "interval_secs": self.interval_secs,
+            "groups": rows,
+        });
+        if self.dropped > 0 {
+            out["dropped"] = std::mem::take(&mut self.dropped).into();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn query(pairs: &str) -> EventQuery {
+        let uri = format!("/events?{pairs}").parse().unwrap();
+        axum::extract::Query::try_from_uri(&uri).unwrap().0
+    }
+
+    fn event(comm: &str, cpu: Option<f64>) -> Map<String, Value> {
+        let mut v = json!({"pid": 1, "comm": comm, "event_type_name": "exec", "ts_ns": 5});
+        if let Some(cpu) = cpu {
+            v["cpu_percent"] = cpu.into();
+        }
+        v.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn projects_requested_fields_under_their_names() {
+        let EventView::Events(Some(fields)) =
+            EventView::parse(&query("fields=pid,comm,cpu_pct")).unwrap()
+        else {
+            panic!("expected a projection");
+        };
+        let projected = project(&event("bash", Some(1.5)), &fields);
+        assert_eq!(projected, json!({"pid": 1, "comm": "bash", "cpu_pct": 1.5}));
+
+        assert!(EventView::parse(&query("fields=pid,nope")).is_err());
+        assert!(EventView::parse(&query("agg=count")).is_err());
+        assert!(EventView::parse(&query("group_by=comm&agg=sum:comm")).is_err());
+        assert!(matches!(
+            EventView::parse(&query("")).unwrap(),
+            EventView::Events(None)
+        ));
+    }
+
+    #[test]
+    fn aggregates_per_group_and_resets_each_interval() {
+        let EventView::Aggregate(mut agg) =
+            EventView::parse(&query("group_by=comm&agg=max:cpu_pct&interval=2")).unwrap()
+        else {
+            panic!("expected aggregation");
+        };
+        agg.add(&event("bash", Some(1.0)));
+        agg.add(&event("bash", Some(3.0)));
+        agg.add(&event("bash", None));
+        agg.add(&event("curl", None));
+        assert_eq!(
+            agg.flush(),
+            json!({
+                "interval_secs": 2,
+                "groups": [
+                    {"comm": "bash", "count": 3, "max_cpu_pct": 3.0},
+                    {"comm": "curl", "count": 1, "max_cpu_pct": null},
+                ]
+            })
+        );
+        assert_eq!(agg.flush()["groups"], json!([]));
+    }
+}
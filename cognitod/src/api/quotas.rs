@@ -0,0 +1,37 @@
+//! `GET /quotas`: per-pod and per-namespace event quotas and the recent
+//! "suppressed N events" summaries.
+
+use super::AppState;
+use axum::{Json, extract::State, http::StatusCode};
+use cognitod::quota::SuppressionSummary;
+use cognitod::redaction::Destination;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+struct QuotasResponse {
+    per_pod_events_per_sec: u64,
+    per_namespace_events_per_sec: u64,
+    /// Events dropped for exceeding a quota since startup.
+    suppressed_total: u64,
+    /// Most recent summaries, newest first.
+    recent: Vec<SuppressionSummary>,
+}
+
+pub async fn get_quotas(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let quota = app.quota.as_ref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Event quotas not enabled".to_string(),
+        )
+    })?;
+    let response = QuotasResponse {
+        per_pod_events_per_sec: quota.per_pod_limit(),
+        per_namespace_events_per_sec: quota.per_namespace_limit(),
+        suppressed_total: quota.suppressed_total(),
+        recent: quota.recent(),
+    };
+    Ok(Json(app.redaction.redact_json(Destination::Api, &response)))
+}
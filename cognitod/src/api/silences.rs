@@ -0,0 +1,80 @@
+//! `GET /silences`, `POST /silences` and `DELETE /silences/{id}`: mute rules
+//! during deploys and batch jobs.
+
+use super::AppState;
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use cognitod::silences::{NewSilence, RemoveError, Silence, Silences};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub struct SilenceView {
+    #[serde(flatten)]
+    silence: Silence,
+    /// Whether the silence applies right now.
+    active: bool,
+}
+
+fn silences(app: &AppState) -> Result<&Arc<Silences>, (StatusCode, String)> {
+    app.rules
+        .as_ref()
+        .map(|engine| engine.silences())
+        .ok_or_else(|| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "rules engine not loaded".to_string(),
+            )
+        })
+}
+
+pub async fn list_silences(
+    State(app): State<Arc<AppState>>,
+) -> Result<Json<Vec<SilenceView>>, (StatusCode, String)> {
+    let silences = silences(&app)?;
+    let views = silences
+        .list(chrono::Local::now())
+        .into_iter()
+        .map(|(silence, active)| SilenceView { silence, active })
+        .collect();
+    Ok(Json(views))
+}
+
+pub async fn post_silence(
+    State(app): State<Arc<AppState>>,
+    Json(new): Json<NewSilence>,
+) -> Result<(StatusCode, Json<Silence>), (StatusCode, String)> {
+    let silence = silences(&app)?
+        .add(new, chrono::Local::now())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    log::info!(
+        "[silences] added {} for {}{}",
+        silence.id,
+        match silence.rules.as_slice() {
+            [] => "all rules".to_string(),
+            rules => rules.join(", "),
+        },
+        silence
+            .comment
+            .as_ref()
+            .map_or_else(String::new, |c| format!(": {c}"))
+    );
+    Ok((StatusCode::CREATED, Json(silence)))
+}
+
+pub async fn delete_silence(
+    State(app): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match silences(&app)?.remove(&id) {
+        Ok(_) => {
+            log::info!("[silences] removed {id}");
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(e @ RemoveError::NotFound) => Err((StatusCode::NOT_FOUND, e.to_string())),
+        Err(e @ RemoveError::FromConfig) => Err((StatusCode::CONFLICT, e.to_string())),
+    }
+}
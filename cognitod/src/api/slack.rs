@@ -0,0 +1,83 @@
+//! Verification of Slack interaction callbacks.
+//!
+//! Slack signs each request with the app's signing secret:
+//! `X-Slack-Signature: v0=<hex HMAC-SHA256 of "v0:<timestamp>:<body>">`.
+//! Requests whose timestamp is more than five minutes off are refused, so a
+//! captured request can't be replayed later.
+
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const MAX_SKEW_SECS: i64 = 300;
+
+/// Check the signature of a request with `body` against `secret`, at unix
+/// time `now`.
+pub(super) fn verify(
+    secret: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+    now: i64,
+) -> Result<(), &'static str> {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    let timestamp = header("x-slack-request-timestamp").ok_or("missing timestamp")?;
+    let sent: i64 = timestamp.parse().map_err(|_| "invalid timestamp")?;
+    if (now - sent).abs() > MAX_SKEW_SECS {
+        return Err("stale timestamp");
+    }
+    let signature = header("x-slack-signature")
+        .and_then(|s| s.strip_prefix("v0="))
+        .ok_or("missing signature")?;
+    let signature = hex::decode(signature).map_err(|_| "invalid signature")?;
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(b"v0:");
+    mac.update(timestamp.as_bytes());
+    mac.update(b":");
+    mac.update(body);
+    mac.verify_slice(&signature)
+        .map_err(|_| "signature mismatch")
+}
+
+#[cfg(test)]
+pub(super) fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("v0:{timestamp}:").as_bytes());
+    mac.update(body);
+    format!("v0={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(timestamp: i64, signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-slack-request-timestamp", timestamp.into());
+        headers.insert("x-slack-signature", signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn checks_signature_and_age() {
+        let body = b"payload=%7B%7D";
+        let signed = headers(1_000, &sign("secret", 1_000, body));
+        assert_eq!(verify("secret", &signed, body, 1_100), Ok(()));
+        assert_eq!(
+            verify("other", &signed, body, 1_100),
+            Err("signature mismatch")
+        );
+        assert_eq!(
+            verify("secret", &signed, b"payload=x", 1_100),
+            Err("signature mismatch")
+        );
+        assert_eq!(
+            verify("secret", &signed, body, 1_400),
+            Err("stale timestamp")
+        );
+        assert_eq!(
+            verify("secret", &HeaderMap::new(), body, 1_000),
+            Err("missing timestamp")
+        );
+    }
+}
@@ -0,0 +1,543 @@
+//! Historical baselines: what's normal on this host.
+//!
+//! A fork rate of 40/s is an incident on a database box and a quiet minute
+//! on a CI runner. This job keeps a rolling mean and spread of fork rate,
+//! exec rate, events/sec and CPU for each hour of the day, persists them to
+//! disk so they survive restarts, and lets the LLM prompts say "3.0x normal
+//! for this time of day" instead of guessing from absolute numbers. `baseline`
+//! rules alert on samples far above the spread.
+
+use crate::config::BaselineConfig;
+use crate::context::ContextStore;
+use anyhow::Context as _;
+use chrono::{Local, TimeZone, Timelike};
+use log::{debug, info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const FILE_VERSION: u32 = 1;
+
+/// Rolling statistics for one metric in one hour of the day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct HourStat {
+    pub samples: u64,
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl HourStat {
+    /// Exponentially weighted update. Until the window is full this is a
+    /// plain running mean, afterwards old samples decay with weight `1 - alpha`.
+    fn update(&mut self, value: f64, window_samples: f64) {
+        self.samples += 1;
+        let alpha = (1.0 / self.samples as f64).max(1.0 / window_samples.max(1.0));
+        let diff = value - self.mean;
+        self.mean += alpha * diff;
+        self.variance = (1.0 - alpha) * (self.variance + alpha * diff * diff);
+    }
+
+    pub fn stddev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct HourlyMetrics {
+    fork_rate: [HourStat; 24],
+    events_per_sec: [HourStat; 24],
+    cpu_pct: [HourStat; 24],
+    #[serde(default)]
+    exec_rate: [HourStat; 24],
+}
+
+impl HourlyMetrics {
+    fn of(&self, metric: Metric) -> &[HourStat; 24] {
+        match metric {
+            Metric::ForkRate => &self.fork_rate,
+            Metric::ExecRate => &self.exec_rate,
+            Metric::EventsPerSec => &self.events_per_sec,
+            Metric::CpuPct => &self.cpu_pct,
+        }
+    }
+}
+
+/// A baselined metric, as `baseline` rules name it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    ForkRate,
+    ExecRate,
+    EventsPerSec,
+    CpuPct,
+}
+
+impl Metric {
+    /// Label and unit, as in prompts and alerts.
+    fn label(self) -> (&'static str, &'static str) {
+        match self {
+            Metric::ForkRate => ("forks", "/s"),
+            Metric::ExecRate => ("execs", "/s"),
+            Metric::EventsPerSec => ("events", "/s"),
+            Metric::CpuPct => ("CPU", "%"),
+        }
+    }
+
+    fn value(self, observed: &Observed) -> Option<f64> {
+        match self {
+            Metric::ForkRate => observed.fork_rate,
+            Metric::ExecRate => observed.exec_rate,
+            Metric::EventsPerSec => observed.events_per_sec,
+            Metric::CpuPct => observed.cpu_pct,
+        }
+    }
+}
+
+/// The latest sample of a metric next to its hour's baseline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Deviation {
+    pub metric: Metric,
+    pub hour: u32,
+    pub value: f64,
+    pub mean: f64,
+    pub stddev: f64,
+}
+
+impl Deviation {
+    /// Standard deviations above the mean. The spread counts as at least
+    /// 5% of the mean (and 0.01), so a flat baseline doesn't turn noise
+    /// into a huge deviation.
+    pub fn sigmas(&self) -> f64 {
+        let spread = self.stddev.max(self.mean.abs() * 0.05).max(0.01);
+        (self.value - self.mean) / spread
+    }
+}
+
+impl std::fmt::Display for Deviation {
+    /// e.g. "forks 42.0/s vs 3.2/s normal at 14:00 (6.1 sigma)".
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (label, unit) = self.metric.label();
+        write!(
+            f,
+            "{label} {:.1}{unit} vs {:.1}{unit} normal at {:02}:00 ({:.1} sigma)",
+            self.value,
+            self.mean,
+            self.hour,
+            self.sigmas()
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BaselineFile {
+    version: u32,
+    metrics: HourlyMetrics,
+}
+
+/// Values to compare against the baseline. Missing values are left out of
+/// the comparison.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Observed {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events_per_sec: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_pct: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HourBaseline {
+    pub hour: u32,
+    pub samples: u64,
+    pub mean: f64,
+    pub stddev: f64,
+    /// Whether enough samples exist for the hour to be used in prompts.
+    pub ready: bool,
+}
+
+/// Response body of `GET /baselines`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselinesView {
+    /// Local hour of day (0-23) the `current` values belong to.
+    pub hour: u32,
+    pub min_samples: u64,
+    pub current: Option<Observed>,
+    pub fork_rate: Vec<HourBaseline>,
+    pub events_per_sec: Vec<HourBaseline>,
+    pub cpu_pct: Vec<HourBaseline>,
+    pub exec_rate: Vec<HourBaseline>,
+}
+
+pub struct Baselines {
+    cfg: BaselineConfig,
+    metrics: Mutex<HourlyMetrics>,
+    latest: Mutex<Option<Observed>>,
+    /// The latest sample against the ready baselines as they were before
+    /// it was folded in, so a spike doesn't widen its own yardstick.
+    deviations: Mutex<Vec<Deviation>>,
+}
+
+impl Baselines {
+    pub fn new(cfg: BaselineConfig) -> Self {
+        Self {
+            cfg,
+            metrics: Mutex::new(HourlyMetrics::default()),
+            latest: Mutex::new(None),
+            deviations: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Restore saved baselines from `cfg.path`; starts empty when the file is
+    /// missing or unreadable.
+    pub fn load(cfg: BaselineConfig) -> Self {
+        let baselines = Self::new(cfg);
+        match std::fs::read_to_string(&baselines.cfg.path) {
+            Ok(text) => match serde_json::from_str::<BaselineFile>(&text) {
+                Ok(file) if file.version == FILE_VERSION => {
+                    *baselines.metrics.lock().unwrap() = file.metrics;
+                    info!("[baselines] restored from {}", baselines.cfg.path);
+                }
+                Ok(file) => warn!(
+                    "[baselines] ignoring {} (version {})",
+                    baselines.cfg.path, file.version
+                ),
+                Err(e) => warn!("[baselines] ignoring {}: {e}", baselines.cfg.path),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("[baselines] failed to read {}: {e}", baselines.cfg.path),
+        }
+        baselines
+    }
+
+    /// Write the baselines to `cfg.path` via a temporary file, so a crash
+    /// mid-write never leaves a truncated file behind.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let file = BaselineFile {
+            version: FILE_VERSION,
+            metrics: self.metrics.lock().unwrap().clone(),
+        };
+        let path = PathBuf::from(&self.cfg.path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&file)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path).with_context(|| format!("renaming to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Fold one sample covering `elapsed` into the current hour's baseline.
+    pub fn sample(&self, elapsed: Duration, events: u64, forks: u64, execs: u64, cpu_pct: f32) {
+        let secs = elapsed.as_secs_f64();
+        if secs <= 0.0 {
+            return;
+        }
+        self.record(
+            Local::now().hour(),
+            Observed {
+                fork_rate: Some(forks as f64 / secs),
+                events_per_sec: Some(events as f64 / secs),
+                cpu_pct: Some(cpu_pct as f64),
+                exec_rate: Some(execs as f64 / secs),
+            },
+        );
+    }
+
+    pub(crate) fn record(&self, hour: u32, observed: Observed) {
+        let window = self.cfg.window_days as f64 * self.samples_per_hour();
+        let hour = hour as usize % 24;
+        let mut guard = self.metrics.lock().unwrap();
+        *self.deviations.lock().unwrap() = [
+            Metric::ForkRate,
+            Metric::ExecRate,
+            Metric::EventsPerSec,
+            Metric::CpuPct,
+        ]
+        .into_iter()
+        .filter_map(|metric| {
+            let value = metric.value(&observed)?;
+            let stat = guard.of(metric)[hour];
+            (stat.samples >= self.cfg.min_samples).then_some(Deviation {
+                metric,
+                hour: hour as u32,
+                value,
+                mean: stat.mean,
+                stddev: stat.stddev(),
+            })
+        })
+        .collect();
+        let metrics = &mut *guard;
+        for (stats, value) in [
+            (&mut metrics.fork_rate, observed.fork_rate),
+            (&mut metrics.events_per_sec, observed.events_per_sec),
+            (&mut metrics.cpu_pct, observed.cpu_pct),
+            (&mut metrics.exec_rate, observed.exec_rate),
+        ] {
+            if let Some(value) = value {
+                stats[hour].update(value, window);
+            }
+        }
+        *self.latest.lock().unwrap() = Some(observed);
+    }
+
+    fn samples_per_hour(&self) -> f64 {
+        3600.0 / self.cfg.sample_interval_secs.max(1) as f64
+    }
+
+    pub fn view(&self) -> BaselinesView {
+        let metrics = self.metrics.lock().unwrap();
+        let hours = |stats: &[HourStat; 24]| {
+            stats
+                .iter()
+                .enumerate()
+                .map(|(hour, s)| HourBaseline {
+                    hour: hour as u32,
+                    samples: s.samples,
+                    mean: s.mean,
+                    stddev: s.stddev(),
+                    ready: s.samples >= self.cfg.min_samples,
+                })
+                .collect()
+        };
+        BaselinesView {
+            hour: Local::now().hour(),
+            min_samples: self.cfg.min_samples,
+            current: *self.latest.lock().unwrap(),
+            fork_rate: hours(&metrics.fork_rate),
+            events_per_sec: hours(&metrics.events_per_sec),
+            cpu_pct: hours(&metrics.cpu_pct),
+            exec_rate: hours(&metrics.exec_rate),
+        }
+    }
+
+    /// The latest sample of `metric` against its hour's baseline; `None`
+    /// until the hour has `min_samples` samples.
+    pub fn deviation(&self, metric: Metric) -> Option<Deviation> {
+        self.deviations
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|d| d.metric == metric)
+            .copied()
+    }
+
+    /// Prompt line comparing `cpu_pct` and the latest event rates with the
+    /// baseline for the current hour.
+    pub fn prompt_context(&self, cpu_pct: f32) -> Option<String> {
+        let latest = self.latest.lock().unwrap().unwrap_or_default();
+        self.compare(
+            Local::now().hour(),
+            &Observed {
+                cpu_pct: Some(cpu_pct as f64),
+                ..latest
+            },
+        )
+    }
+
+    /// Prompt line for something that happened at `timestamp` (unix seconds).
+    pub fn prompt_context_at(&self, timestamp: i64, observed: &Observed) -> Option<String> {
+        let hour = Local.timestamp_opt(timestamp, 0).single()?.hour();
+        self.compare(hour, observed)
+    }
+
+    /// e.g. "CPU 36.0% vs 12.0% normal at 14:00 (3.0x); forks 2.0/s vs 1.9/s
+    /// normal (1.1x)". `None` until the hour has `min_samples` samples.
+    pub fn compare(&self, hour: u32, observed: &Observed) -> Option<String> {
+        let metrics = self.metrics.lock().unwrap();
+        let hour = hour % 24;
+        let mut parts = Vec::new();
+        for metric in [
+            Metric::CpuPct,
+            Metric::ForkRate,
+            Metric::ExecRate,
+            Metric::EventsPerSec,
+        ] {
+            let (label, unit) = metric.label();
+            let (Some(value), stat) = (metric.value(observed), metrics.of(metric)[hour as usize])
+            else {
+                continue;
+            };
+            if stat.samples < self.cfg.min_samples {
+                continue;
+            }
+            let mut part = format!("{label} {value:.1}{unit} vs {:.1}{unit} normal", stat.mean);
+            if parts.is_empty() {
+                part.push_str(&format!(" at {hour:02}:00"));
+            }
+            if stat.mean > 0.0 {
+                part.push_str(&format!(" ({:.1}x)", value / stat.mean));
+            }
+            parts.push(part);
+        }
+        (!parts.is_empty()).then(|| parts.join("; "))
+    }
+}
+
+/// Sample and periodically save baselines until the event stream closes.
+pub async fn run(baselines: Arc<Baselines>, context: Arc<ContextStore>) {
+    let cfg = baselines.cfg.clone();
+    info!(
+        "[baselines] started (sample every {}s, {}d window)",
+        cfg.sample_interval_secs, cfg.window_days
+    );
+    let mut events_rx = context.broadcaster().subscribe();
+    let mut sample_tick =
+        tokio::time::interval(Duration::from_secs(cfg.sample_interval_secs.max(1)));
+    let mut save_tick = tokio::time::interval(Duration::from_secs(cfg.save_interval_secs.max(1)));
+    // Both intervals fire immediately; skip that so the first sample covers
+    // a full interval.
+    sample_tick.tick().await;
+    save_tick.tick().await;
+    let mut last = Instant::now();
+    let (mut events, mut forks, mut execs) = (0u64, 0u64, 0u64);
+
+    loop {
+        tokio::select! {
+            ev = events_rx.recv() => match ev {
+                Ok(event) => {
+                    events += 1;
+                    match event.event_type {
+                        0 => execs += 1,
+                        1 => forks += 1,
+                        _ => {}
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    debug!("[baselines] event stream lagged by {n}");
+                    events += n;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = sample_tick.tick() => {
+                let cpu = context.get_system_snapshot().cpu_percent;
+                baselines.sample(last.elapsed(), events, forks, execs, cpu);
+                last = Instant::now();
+                (events, forks, execs) = (0, 0, 0);
+            }
+            _ = save_tick.tick() => {
+                if let Err(e) = baselines.save() {
+                    warn!("[baselines] failed to save: {e:#}");
+                }
+            }
+        }
+    }
+    if let Err(e) = baselines.save() {
+        warn!("[baselines] failed to save: {e:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(path: &std::path::Path) -> BaselineConfig {
+        BaselineConfig {
+            path: path.display().to_string(),
+            min_samples: 3,
+            ..BaselineConfig::default()
+        }
+    }
+
+    fn cpu(value: f64) -> Observed {
+        Observed {
+            cpu_pct: Some(value),
+            ..Observed::default()
+        }
+    }
+
+    #[test]
+    fn running_mean_then_decay() {
+        let mut stat = HourStat::default();
+        for v in [10.0, 20.0, 30.0] {
+            stat.update(v, 4.0);
+        }
+        assert_eq!(stat.mean, 20.0);
+        assert!((stat.variance - 200.0 / 3.0).abs() < 1e-9);
+        // Window full: new samples move the mean by 1/4 of the difference.
+        stat.update(20.0, 4.0);
+        stat.update(60.0, 4.0);
+        assert_eq!(stat.mean, 30.0);
+    }
+
+    #[test]
+    fn compares_only_ready_hours() {
+        let dir = tempfile::tempdir().unwrap();
+        let baselines = Baselines::new(config(&dir.path().join("b.json")));
+        for _ in 0..2 {
+            baselines.record(14, cpu(12.0));
+        }
+        assert_eq!(baselines.compare(14, &cpu(36.0)), None);
+
+        baselines.record(
+            14,
+            Observed {
+                fork_rate: Some(0.0),
+                ..cpu(12.0)
+            },
+        );
+        let observed = Observed {
+            fork_rate: Some(5.0),
+            events_per_sec: Some(100.0),
+            cpu_pct: Some(36.0),
+            exec_rate: None,
+        };
+        assert_eq!(
+            baselines.compare(14, &observed).as_deref(),
+            Some("CPU 36.0% vs 12.0% normal at 14:00 (3.0x)")
+        );
+        assert_eq!(baselines.compare(15, &observed), None);
+    }
+
+    #[test]
+    fn deviation_is_judged_before_the_sample_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let baselines = Baselines::new(config(&dir.path().join("b.json")));
+        for v in [10.0, 12.0, 14.0] {
+            baselines.record(9, cpu(v));
+            assert_eq!(baselines.deviation(Metric::CpuPct), None);
+        }
+        baselines.record(9, cpu(40.0));
+        let deviation = baselines.deviation(Metric::CpuPct).unwrap();
+        assert_eq!((deviation.hour, deviation.mean), (9, 12.0));
+        assert!((deviation.sigmas() - 28.0 / (8.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert_eq!(
+            deviation.to_string(),
+            "CPU 40.0% vs 12.0% normal at 09:00 (17.1 sigma)"
+        );
+        assert_eq!(baselines.deviation(Metric::ForkRate), None);
+
+        // A flat baseline still needs a change of 5% of its mean per sigma.
+        let flat = Deviation {
+            stddev: 0.0,
+            value: 12.6,
+            ..deviation
+        };
+        assert!((flat.sigmas() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn saves_and_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = config(&dir.path().join("state/baselines.json"));
+        let baselines = Baselines::new(cfg.clone());
+        for v in [10.0, 20.0, 30.0] {
+            baselines.record(3, cpu(v));
+        }
+        baselines.save().unwrap();
+
+        let restored = Baselines::load(cfg);
+        let view = restored.view();
+        assert_eq!(view.cpu_pct[3].samples, 3);
+        assert_eq!(view.cpu_pct[3].mean, 20.0);
+        assert!(view.cpu_pct[3].ready);
+        assert_eq!(view.fork_rate[3].samples, 0);
+        assert_eq!(view.current, None);
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::{Context, Result, anyhow};
 use btf::btf::{Array, Btf, Struct, Type};
-use linnix_ai_ebpf_common::{TelemetryConfig, rss_source};
+use linnix_ai_ebpf_common::{TelemetryConfig, block_source, rss_source};
 use std::convert::TryFrom;
 use std::env;
 use std::io::Read;
@@ -111,6 +111,92 @@ pub fn derive_telemetry_config() -> Result<TelemetryConfigResult> {
     } else {
         0
     };
+    match member_offset(task_struct, "exit_code").and_then(|(bits, _)| to_bytes(bits)) {
+        Ok(offset) => telemetry.task_exit_code_offset = offset,
+        Err(e) => log::warn!("exit status of exit events disabled: {e:#}"),
+    }
+    // Renamed from `state` to `__state` in 5.14; the low 32 bits hold the
+    // sleep states either way.
+    match member_offset(task_struct, "__state")
+        .or_else(|_| member_offset(task_struct, "state"))
+        .and_then(|(bits, _)| to_bytes(bits))
+    {
+        Ok(offset) => telemetry.task_state_offset = offset,
+        Err(e) => log::warn!("off-CPU events disabled: {e:#}"),
+    }
+
+    match file_io_offsets(&btf) {
+        Ok([file_inode, inode_mode, inode_ino, inode_sb, sb_dev]) => {
+            telemetry.file_inode_offset = file_inode;
+            telemetry.inode_mode_offset = inode_mode;
+            telemetry.inode_ino_offset = inode_ino;
+            telemetry.inode_sb_offset = inode_sb;
+            telemetry.sb_dev_offset = sb_dev;
+        }
+        Err(e) => log::warn!("file I/O events disabled: {e:#}"),
+    }
+
+    match file_access_offsets(&btf) {
+        Ok([file_dentry, file_mode, parent, name, inode]) => {
+            telemetry.file_dentry_offset = file_dentry;
+            telemetry.file_mode_offset = file_mode;
+            telemetry.dentry_parent_offset = parent;
+            telemetry.dentry_name_offset = name;
+            telemetry.dentry_inode_offset = inode;
+            telemetry.unlink_dentry_arg = std::fs::read_to_string("/proc/sys/kernel/osrelease")
+                .map(|release| unlink_dentry_arg(&release))
+                .unwrap_or(2);
+        }
+        Err(e) => log::warn!("file access events disabled: {e:#}"),
+    }
+
+    match oom_offsets(&btf) {
+        Ok((chosen, memcg)) => {
+            telemetry.oom_chosen_offset = chosen;
+            telemetry.oom_memcg_offset = memcg;
+        }
+        Err(e) => log::warn!("OOM kill events disabled: {e:#}"),
+    }
+
+    match sock_offsets(&btf) {
+        Ok([family, dport, num, daddr, v6_daddr]) => {
+            telemetry.skc_family_offset = family;
+            telemetry.skc_dport_offset = dport;
+            telemetry.skc_num_offset = num;
+            telemetry.skc_daddr_offset = daddr;
+            telemetry.skc_v6_daddr_offset = v6_daddr;
+            telemetry.sock_offsets_known = 1;
+        }
+        Err(e) => log::warn!("inbound connection events disabled: {e:#}"),
+    }
+
+    match cred_offsets(&btf, task_struct) {
+        Ok([real_cred, euid, egid, cap_effective]) => {
+            telemetry.task_real_cred_offset = real_cred;
+            telemetry.cred_euid_offset = euid;
+            telemetry.cred_egid_offset = egid;
+            telemetry.cred_cap_effective_offset = cap_effective;
+        }
+        Err(e) => log::warn!("credential change events disabled: {e:#}"),
+    }
+
+    match block_offsets(&btf) {
+        Ok(offsets) => {
+            let [bdev, sector, size, dev, part, rq_sector, rq_len] = offsets;
+            telemetry.bio_bdev_offset = bdev;
+            telemetry.bio_sector_offset = sector;
+            telemetry.bio_size_offset = size;
+            telemetry.bdev_dev_offset = dev;
+            telemetry.rq_part_offset = part;
+            telemetry.rq_sector_offset = rq_sector;
+            telemetry.rq_data_len_offset = rq_len;
+            telemetry.block_source = block_source::BTF;
+        }
+        Err(e) => {
+            log::warn!("block I/O probes fall back to the block tracepoint records: {e:#}");
+            telemetry.block_source = block_source::TRACEPOINT;
+        }
+    }
 
     if let Some(bits) = signal_bits {
         telemetry.task_signal_offset = to_bytes(bits)?;
@@ -138,6 +224,122 @@ pub fn derive_telemetry_config() -> Result<TelemetryConfigResult> {
     })
 }
 
+/// Byte offsets of `file.f_inode`, `inode.i_mode`, `inode.i_ino`,
+/// `inode.i_sb` and `super_block.s_dev`, which the VFS probes follow to
+/// attribute reads and writes to a device and inode.
+fn file_io_offsets(btf: &Btf) -> Result<[u32; 5]> {
+    let file = expect_named_struct(btf, "file")?;
+    let inode = expect_named_struct(btf, "inode")?;
+    let super_block = expect_named_struct(btf, "super_block")?;
+    Ok([
+        to_bytes(member_offset(file, "f_inode")?.0)?,
+        to_bytes(member_offset(inode, "i_mode")?.0)?,
+        to_bytes(member_offset(inode, "i_ino")?.0)?,
+        to_bytes(member_offset(inode, "i_sb")?.0)?,
+        to_bytes(member_offset(super_block, "s_dev")?.0)?,
+    ])
+}
+
+/// Byte offsets of `file.f_path.dentry`, `file.f_mode`, `dentry.d_parent`,
+/// `dentry.d_name.name` and `dentry.d_inode`, which the file access probes
+/// follow to build the path of an opened or unlinked file.
+fn file_access_offsets(btf: &Btf) -> Result<[u32; 5]> {
+    let file = expect_named_struct(btf, "file")?;
+    let dentry = expect_named_struct(btf, "dentry")?;
+    let (f_path_bits, f_path_type) = member_offset(file, "f_path")?;
+    let path = resolve_struct_deep(btf, f_path_type)?;
+    let (d_name_bits, d_name_type) = member_offset(dentry, "d_name")?;
+    let qstr = resolve_struct_deep(btf, d_name_type)?;
+    Ok([
+        to_bytes(f_path_bits + member_offset(path, "dentry")?.0)?,
+        to_bytes(member_offset(file, "f_mode")?.0)?,
+        to_bytes(member_offset(dentry, "d_parent")?.0)?,
+        to_bytes(d_name_bits + member_offset(qstr, "name")?.0)?,
+        to_bytes(member_offset(dentry, "d_inode")?.0)?,
+    ])
+}
+
+/// Argument of `vfs_unlink` holding the dentry: 5.12 added an idmapping
+/// argument in front of the directory inode.
+fn unlink_dentry_arg(release: &str) -> u32 {
+    let mut version = release.trim().split(['.', '-']);
+    let major: u32 = version.next().and_then(|v| v.parse().ok()).unwrap_or(0);
+    let minor: u32 = version.next().and_then(|v| v.parse().ok()).unwrap_or(0);
+    if (major, minor) >= (5, 12) { 2 } else { 1 }
+}
+
+/// Byte offsets of `oom_control.chosen` and `oom_control.memcg`, read by
+/// the `oom_kill_process` probe.
+fn oom_offsets(btf: &Btf) -> Result<(u32, u32)> {
+    let oom_control = expect_named_struct(btf, "oom_control")?;
+    Ok((
+        to_bytes(member_offset(oom_control, "chosen")?.0)?,
+        to_bytes(member_offset(oom_control, "memcg")?.0)?,
+    ))
+}
+
+/// `sock_common` offsets of the address family, remote port, local port
+/// and IPv4 and IPv6 remote addresses. Most sit in anonymous unions.
+fn sock_offsets(btf: &Btf) -> Result<[u32; 5]> {
+    let sock_common = expect_named_struct(btf, "sock_common")?;
+    let mut offsets = [0; 5];
+    for (offset, name) in offsets.iter_mut().zip([
+        "skc_family",
+        "skc_dport",
+        "skc_num",
+        "skc_daddr",
+        "skc_v6_daddr",
+    ]) {
+        let (bits, _) = find_member_recursive(btf, sock_common, 0, name)?
+            .with_context(|| format!("member {name} not found"))?;
+        *offset = to_bytes(bits)?;
+    }
+    Ok(offsets)
+}
+
+/// Byte offsets of `task_struct.real_cred` and the `cred` fields compared
+/// by the `commit_creds` probe: `euid`, `egid` and `cap_effective`.
+fn cred_offsets(btf: &Btf, task_struct: &Struct) -> Result<[u32; 4]> {
+    let cred = expect_named_struct(btf, "cred")?;
+    Ok([
+        to_bytes(member_offset(task_struct, "real_cred")?.0)?,
+        to_bytes(member_offset(cred, "euid")?.0)?,
+        to_bytes(member_offset(cred, "egid")?.0)?,
+        to_bytes(member_offset(cred, "cap_effective")?.0)?,
+    ])
+}
+
+/// Offsets read by the block BTF tracepoints: `bio.bi_bdev`, the sector
+/// and size in `bio.bi_iter`, `block_device.bd_dev`, `request.part`,
+/// `request.__sector` and `request.__data_len`. Needs 5.12+, where `bio`
+/// points at its `block_device` and `request.part` is one as well (it was
+/// an `hd_struct` before 5.11).
+fn block_offsets(btf: &Btf) -> Result<[u32; 7]> {
+    let bio = expect_named_struct(btf, "bio")?;
+    let request = expect_named_struct(btf, "request")?;
+    let (bdev_bits, bdev_type) = member_offset(bio, "bi_bdev")?;
+    let (iter_bits, iter_type) = member_offset(bio, "bi_iter")?;
+    let bvec_iter = resolve_struct_deep(btf, iter_type)?;
+    let block_device = resolve_struct_deep(btf, bdev_type)?;
+    let (part_bits, part_type) = member_offset(request, "part")?;
+    let part_dev_bits = member_offset(resolve_struct_deep(btf, part_type)?, "bd_dev")
+        .context("request.part is not a block_device")?
+        .0;
+    let dev_bits = member_offset(block_device, "bd_dev")?.0;
+    if part_dev_bits != dev_bits {
+        return Err(anyhow!("request.part is not a block_device"));
+    }
+    Ok([
+        to_bytes(bdev_bits)?,
+        to_bytes(iter_bits + member_offset(bvec_iter, "bi_sector")?.0)?,
+        to_bytes(iter_bits + member_offset(bvec_iter, "bi_size")?.0)?,
+        to_bytes(dev_bits)?,
+        to_bytes(part_bits)?,
+        to_bytes(member_offset(request, "__sector")?.0)?,
+        to_bytes(member_offset(request, "__data_len")?.0)?,
+    ])
+}
+
 #[derive(Clone)]
 struct RssLayout {
     field_offset: u32,
@@ -320,7 +522,9 @@ fn struct_if_inline(btf: &Btf, mut type_id: u32) -> Result<Option<&Struct>> {
             .get_type_by_id(type_id)
             .with_context(|| format!("failed to resolve nested type id {type_id}"))?;
         match &ty.base_type {
-            Type::Struct(st) => return Ok(Some(st)),
+            // Anonymous union members share the union's offset, so they
+            // are searched like those of an anonymous struct.
+            Type::Struct(st) | Type::Union(st) => return Ok(Some(st)),
             Type::Const(map)
             | Type::Volatile(map)
             | Type::Restrict(map)
@@ -328,7 +532,7 @@ fn struct_if_inline(btf: &Btf, mut type_id: u32) -> Result<Option<&Struct>> {
             | Type::TypeTag(map) => {
                 type_id = map.type_id;
             }
-            Type::Pointer(_) | Type::Array(_) | Type::Union(_) => return Ok(None),
+            Type::Pointer(_) | Type::Array(_) => return Ok(None),
             _ => return Ok(None),
         }
     }
@@ -496,6 +700,13 @@ mod tests {
         assert!(to_bytes(3).is_err());
     }
 
+    #[test]
+    fn unlink_dentry_arg_follows_idmapping_change() {
+        assert_eq!(unlink_dentry_arg("5.10.0-21-amd64\n"), 1);
+        assert_eq!(unlink_dentry_arg("5.12.0"), 2);
+        assert_eq!(unlink_dentry_arg("6.8.0-45-generic"), 2);
+    }
+
     #[test]
     fn lsm_detection_returns_result() {
         // This test just verifies the function doesn't panic.
@@ -0,0 +1,226 @@
+//! Deploys and config changes reported by CI/CD through `POST /changes`.
+//!
+//! A change shortly before an anomaly is its most common cause, so insights,
+//! incident analyses and incident timelines list the changes recorded in the
+//! `[changes]` window before them.
+
+use crate::config::ChangesConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Changes listed with one insight or analysis.
+const MAX_PRECEDING: usize = 5;
+
+/// One deploy or config change. Only `service` is required when posting;
+/// `id` is assigned and `timestamp` defaults to the time of receipt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    #[serde(default)]
+    pub id: String,
+    /// Epoch seconds.
+    #[serde(default)]
+    pub timestamp: u64,
+    pub service: String,
+    /// `deploy`, `config`, `rollback`, `feature_flag`, ...
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Link to the pipeline run, PR or release.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+fn default_kind() -> String {
+    "deploy".to_string()
+}
+
+impl fmt::Display for ChangeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of ", self.kind)?;
+        match &self.namespace {
+            Some(ns) => write!(f, "{ns}/{}", self.service)?,
+            None => write!(f, "{}", self.service)?,
+        }
+        if let Some(version) = &self.version {
+            write!(f, " {version}")?;
+        }
+        if let Some(author) = &self.author {
+            write!(f, " by {author}")?;
+        }
+        if let Some(description) = &self.description {
+            write!(f, ": {description}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ChangeEvent {
+    /// Hash the namespace and service, as [`crate::schema::Insight::redact`]
+    /// does for pods.
+    pub fn redact(&mut self) {
+        use sha2::{Digest, Sha256};
+
+        let hash = |s: &str| -> String {
+            let mut hasher = Sha256::new();
+            hasher.update(s);
+            format!("{:x}", hasher.finalize())[..8].to_string()
+        };
+
+        self.service = hash(&self.service);
+        if let Some(ns) = &mut self.namespace {
+            *ns = hash(ns);
+        }
+    }
+}
+
+/// Recently recorded changes, oldest first.
+pub struct ChangeLog {
+    inner: Mutex<VecDeque<ChangeEvent>>,
+    capacity: usize,
+    window_secs: u64,
+}
+
+impl Default for ChangeLog {
+    fn default() -> Self {
+        Self::new(&ChangesConfig::default())
+    }
+}
+
+impl ChangeLog {
+    pub fn new(config: &ChangesConfig) -> Self {
+        Self {
+            inner: Mutex::new(VecDeque::new()),
+            capacity: config.capacity.max(1),
+            window_secs: config.window_minutes * 60,
+        }
+    }
+
+    /// Record `change`, filling in its ID and, if unset, its timestamp.
+    pub fn record(&self, mut change: ChangeEvent) -> ChangeEvent {
+        change.id = uuid::Uuid::new_v4().to_string();
+        if change.timestamp == 0 {
+            change.timestamp = now_secs();
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.len() == self.capacity {
+            inner.pop_front();
+        }
+        // Keep the log ordered even when CI reports a change late.
+        let at = inner.partition_point(|c| c.timestamp <= change.timestamp);
+        inner.insert(at, change.clone());
+        change
+    }
+
+    /// Changes at or after `since`, optionally of one service, newest first.
+    pub fn list(&self, since: u64, service: Option<&str>) -> Vec<ChangeEvent> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .iter()
+            .rev()
+            .take_while(|c| c.timestamp >= since)
+            .filter(|c| service.is_none_or(|s| c.service == s))
+            .cloned()
+            .collect()
+    }
+
+    /// Changes in `from..=to`, oldest first.
+    pub fn between(&self, from: u64, to: u64) -> Vec<ChangeEvent> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .iter()
+            .filter(|c| (from..=to).contains(&c.timestamp))
+            .cloned()
+            .collect()
+    }
+
+    /// The most recent changes within the window before `at`, newest first.
+    pub fn preceding(&self, at: u64) -> Vec<ChangeEvent> {
+        let mut changes = self.between(at.saturating_sub(self.window_secs), at);
+        changes.reverse();
+        changes.truncate(MAX_PRECEDING);
+        changes
+    }
+
+    /// One line per change preceding `at`, e.g. `- 4 min before: deploy of
+    /// shop/checkout v1.4.2 by ci-bot`, for prompts; `None` without any.
+    pub fn describe_preceding(&self, at: u64) -> Option<String> {
+        let changes = self.preceding(at);
+        if changes.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = changes
+            .iter()
+            .map(|c| format!("- {}: {c}", before(at, c.timestamp)))
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    pub fn window_minutes(&self) -> u64 {
+        self.window_secs / 60
+    }
+}
+
+fn before(at: u64, timestamp: u64) -> String {
+    match at.saturating_sub(timestamp) {
+        secs if secs < 60 => format!("{secs}s before"),
+        secs => format!("{} min before", secs / 60),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(service: &str, timestamp: u64) -> ChangeEvent {
+        serde_json::from_value(serde_json::json!({
+            "service": service,
+            "timestamp": timestamp,
+            "namespace": "shop",
+            "version": "v1.4.2",
+            "author": "ci-bot",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn lists_changes_in_the_window_before_an_anomaly() {
+        let log = ChangeLog::new(&ChangesConfig {
+            window_minutes: 30,
+            capacity: 3,
+        });
+        log.record(change("cart", 1_000));
+        log.record(change("checkout", 10_000));
+        // Reported late, but still ordered by when it happened.
+        log.record(change("search", 9_500));
+        log.record(change("checkout", 10_200));
+
+        assert_eq!(log.list(0, None).len(), 3, "capacity drops the oldest");
+        let preceding = log.preceding(10_240);
+        let services: Vec<_> = preceding.iter().map(|c| c.service.as_str()).collect();
+        assert_eq!(services, ["checkout", "checkout", "search"]);
+        assert!(log.preceding(20_000).is_empty());
+        assert_eq!(
+            log.describe_preceding(10_240).unwrap().lines().next(),
+            Some("- 40s before: deploy of shop/checkout v1.4.2 by ci-bot")
+        );
+        assert_eq!(log.list(0, Some("search")).len(), 1);
+        assert!(!preceding[0].id.is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+//! Per-process GPU memory trend.
+//!
+//! Long-running training jobs that leak device memory (tensors kept alive
+//! across steps, a growing cache) run for hours before hitting CUDA OOM.
+//! Each sample lists compute processes with `nvidia-smi --query-compute-apps`
+//! and tracks, per PID, how far and for how long its GPU memory has grown
+//! without shrinking.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const NVIDIA_SMI_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// GPU memory growth of one process since its usage last went down.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GpuMemoryTrend {
+    pub pid: u32,
+    #[serde(default)]
+    pub comm: String,
+    /// `namespace/pod` when the process belongs to a known Kubernetes pod.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod: Option<String>,
+    /// Device memory in use, summed over GPUs.
+    pub used_mib: u64,
+    pub growth_mib: u64,
+    /// How long it has been growing without shrinking.
+    pub growing_secs: u64,
+    pub rate_mib_per_min: f32,
+}
+
+impl GpuMemoryTrend {
+    /// Pod name if known, otherwise `comm (pid N)`.
+    pub fn label(&self) -> String {
+        match &self.pod {
+            Some(pod) => format!("{} (pid {}) in {pod}", self.comm, self.pid),
+            None => format!("{} (pid {})", self.comm, self.pid),
+        }
+    }
+}
+
+struct Track {
+    start_mib: u64,
+    start_secs: u64,
+    last_mib: u64,
+}
+
+pub struct GpuMonitor {
+    /// False on hosts without the NVIDIA driver, so `nvidia-smi` is never
+    /// spawned there.
+    available: bool,
+    tracks: HashMap<u32, Track>,
+}
+
+impl Default for GpuMonitor {
+    fn default() -> Self {
+        Self::new(Path::new("/proc/driver/nvidia/gpus").exists())
+    }
+}
+
+impl GpuMonitor {
+    pub fn new(available: bool) -> Self {
+        Self {
+            available,
+            tracks: HashMap::new(),
+        }
+    }
+
+    /// Processes whose GPU memory grew since the previous samples, fastest
+    /// first. Empty without a GPU or when `nvidia-smi` doesn't answer.
+    pub fn sample(&mut self, now_secs: u64) -> Vec<GpuMemoryTrend> {
+        if !self.available {
+            return Vec::new();
+        }
+        let Some(csv) = nvidia_smi(&[
+            "--query-compute-apps=pid,used_memory",
+            "--format=csv,noheader,nounits",
+        ]) else {
+            return Vec::new();
+        };
+        self.update(&parse_compute_apps(&csv), now_secs)
+    }
+
+    fn update(&mut self, usage: &HashMap<u32, u64>, now_secs: u64) -> Vec<GpuMemoryTrend> {
+        self.tracks.retain(|pid, _| usage.contains_key(pid));
+        let mut trends = Vec::new();
+        for (&pid, &used) in usage {
+            let track = self.tracks.entry(pid).or_insert(Track {
+                start_mib: used,
+                start_secs: now_secs,
+                last_mib: used,
+            });
+            if used < track.last_mib {
+                track.start_mib = used;
+                track.start_secs = now_secs;
+            }
+            track.last_mib = used;
+
+            let growth_mib = used - track.start_mib;
+            let growing_secs = now_secs.saturating_sub(track.start_secs);
+            if growth_mib == 0 || growing_secs == 0 {
+                continue;
+            }
+            trends.push(GpuMemoryTrend {
+                pid,
+                comm: String::new(),
+                pod: None,
+                used_mib: used,
+                growth_mib,
+                growing_secs,
+                rate_mib_per_min: growth_mib as f32 * 60.0 / growing_secs as f32,
+            });
+        }
+        trends.sort_by(|a, b| b.rate_mib_per_min.total_cmp(&a.rate_mib_per_min));
+        trends
+    }
+}
+
+/// MiB per PID from `pid, used_memory` rows; a process using several GPUs
+/// has one row per GPU.
+fn parse_compute_apps(csv: &str) -> HashMap<u32, u64> {
+    let mut usage = HashMap::new();
+    for line in csv.lines() {
+        let Some((pid, mib)) = line.split_once(',') else {
+            continue;
+        };
+        if let (Ok(pid), Ok(mib)) = (pid.trim().parse::<u32>(), mib.trim().parse::<u64>()) {
+            *usage.entry(pid).or_insert(0) += mib;
+        }
+    }
+    usage
+}
+
+/// Stdout of `nvidia-smi` with `args`, or `None` if it isn't installed,
+/// fails or doesn't answer within a few seconds.
+pub fn nvidia_smi(args: &[&str]) -> Option<String> {
+    let mut child = Command::new("nvidia-smi")
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let started = Instant::now();
+    loop {
+        match child.try_wait().ok()? {
+            Some(status) if status.success() => break,
+            Some(_) => return None,
+            None if started.elapsed() > NVIDIA_SMI_TIMEOUT => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            None => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+    let mut stdout = String::new();
+    std::io::Read::read_to_string(&mut child.stdout.take()?, &mut stdout).ok()?;
+    Some(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_growth_until_memory_shrinks() {
+        let mut monitor = GpuMonitor::new(true);
+        let usage = |rows: &str| parse_compute_apps(rows);
+
+        assert!(
+            monitor
+                .update(&usage("4242, 1000\n77, 500\n"), 0)
+                .is_empty()
+        );
+        // 4242 uses two GPUs; 77 is flat.
+        let trends = monitor.update(&usage("4242, 1200\n4242, 100\n77, 500\n"), 60);
+        assert_eq!(trends.len(), 1);
+        assert_eq!(
+            (trends[0].pid, trends[0].used_mib, trends[0].growth_mib),
+            (4242, 1300, 300)
+        );
+        assert_eq!(trends[0].rate_mib_per_min, 300.0);
+
+        let trends = monitor.update(&usage("4242, 1500\n4242, 100\n"), 120);
+        assert_eq!((trends[0].growth_mib, trends[0].growing_secs), (600, 120));
+
+        // Freeing memory restarts the trend.
+        assert!(
+            monitor
+                .update(&usage("4242, 200\n4242, 100\n"), 180)
+                .is_empty()
+        );
+        let trends = monitor.update(&usage("4242, 300\n4242, 100\n"), 240);
+        assert_eq!((trends[0].growth_mib, trends[0].growing_secs), (100, 60));
+
+        assert!(usage("No running processes found\n").is_empty());
+    }
+}
@@ -1 +1,5 @@
+pub mod gpu;
+pub mod pod_pressure;
 pub mod psi;
+pub mod thermal;
+pub mod throttle;
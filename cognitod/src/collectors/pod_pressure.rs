@@ -0,0 +1,377 @@
+//! Per-pod CPU stall attribution.
+//!
+//! `PodContribution.psi_contribution` used to be whatever the model made up.
+//! This samples every pod cgroup's `cpu.pressure` (stall time), `cpu.stat`
+//! (usage) and `cpu.weight` (shares) and, for each interval, attributes the
+//! stall time all pods accumulated to the pods using more than their
+//! weighted fair share of the CPU time used. A pod's `psi_contribution` is
+//! its share of that stall time in percent; `cpu_usage` is its usage in
+//! percent of one CPU. Only cgroup v2 exposes per-cgroup pressure.
+
+use crate::collectors::psi::parse_psi_file;
+use crate::k8s::K8sContext;
+use crate::schema::PodContribution;
+use log::info;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// Matches the `avg10` window PSI is usually read at.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// Pods kept in an estimate.
+const MAX_PODS: usize = 10;
+/// Pods added to an insight that didn't name them.
+const MAX_ADDED_PODS: usize = 3;
+/// `cpu.weight` when the file is missing.
+const DEFAULT_WEIGHT: u64 = 100;
+
+/// Cumulative CPU counters of one cgroup.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CgroupCpu {
+    pub usage_usec: u64,
+    pub stall_usec: u64,
+    pub weight: u64,
+}
+
+impl CgroupCpu {
+    fn read(dir: &Path) -> Option<Self> {
+        let pressure = std::fs::read_to_string(dir.join("cpu.pressure")).ok()?;
+        let stat = std::fs::read_to_string(dir.join("cpu.stat")).ok()?;
+        let usage_usec = stat.lines().find_map(|l| {
+            l.strip_prefix("usage_usec ")
+                .and_then(|v| v.trim().parse().ok())
+        })?;
+        let weight = std::fs::read_to_string(dir.join("cpu.weight"))
+            .ok()
+            .and_then(|w| w.trim().parse().ok())
+            .unwrap_or(DEFAULT_WEIGHT);
+        Some(Self {
+            usage_usec,
+            stall_usec: parse_psi_file(&pressure).ok()?.some_total,
+            weight,
+        })
+    }
+}
+
+/// Usage and stall of one pod over an interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PodDelta {
+    /// `namespace/pod`.
+    pub pod: String,
+    pub usage_usec: u64,
+    pub stall_usec: u64,
+    pub weight: u64,
+}
+
+pub struct PodPressure {
+    root: PathBuf,
+    k8s: Arc<K8sContext>,
+    prev: Mutex<Option<(Instant, HashMap<PathBuf, CgroupCpu>)>>,
+    latest: Mutex<Vec<PodContribution>>,
+}
+
+impl PodPressure {
+    pub fn new(k8s: Arc<K8sContext>) -> Self {
+        Self {
+            root: PathBuf::from("/sys/fs/cgroup"),
+            k8s,
+            prev: Mutex::new(None),
+            latest: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Sample pod cgroups until the process exits.
+    pub async fn run(self: Arc<Self>) {
+        info!("[psi] attributing CPU stall time to pods");
+        let mut tick = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            tick.tick().await;
+            self.sample();
+        }
+    }
+
+    fn sample(&self) {
+        let now = Instant::now();
+        let pods = pod_cgroups(&self.root, |id| {
+            self.k8s
+                .get_metadata(id)
+                .map(|m| format!("{}/{}", m.namespace, m.pod_name))
+        });
+        let current: HashMap<PathBuf, CgroupCpu> = pods
+            .keys()
+            .filter_map(|dir| Some((dir.clone(), CgroupCpu::read(dir)?)))
+            .collect();
+
+        let mut prev = self.prev.lock().unwrap();
+        if let Some((at, before)) = prev.as_ref() {
+            let deltas: Vec<PodDelta> = current
+                .iter()
+                .filter_map(|(dir, cur)| {
+                    let old = before.get(dir)?;
+                    Some(PodDelta {
+                        pod: pods.get(dir)?.clone(),
+                        // Counters go backwards when a pod is recreated.
+                        usage_usec: cur.usage_usec.checked_sub(old.usage_usec)?,
+                        stall_usec: cur.stall_usec.checked_sub(old.stall_usec)?,
+                        weight: cur.weight,
+                    })
+                })
+                .collect();
+            let elapsed = now.duration_since(*at).as_micros() as u64;
+            *self.latest.lock().unwrap() = estimate(&deltas, elapsed);
+        }
+        *prev = Some((now, current));
+    }
+
+    /// Pods by share of stall time over the last interval, largest first.
+    pub fn estimate(&self) -> Vec<PodContribution> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// One-line summary for LLM prompts, `None` until there is a sample.
+    pub fn prompt_context(&self) -> Option<String> {
+        let pods = self.estimate();
+        if pods.is_empty() {
+            return None;
+        }
+        Some(
+            pods.iter()
+                .take(5)
+                .map(|p| {
+                    format!(
+                        "{}/{} {:.0}% of pod CPU stall (CPU {:.0}%)",
+                        p.namespace, p.pod, p.psi_contribution, p.cpu_usage
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+
+    /// Replace model-provided pod numbers with the measured ones, see
+    /// [`attribute`].
+    pub fn attribute(&self, pods: &mut Vec<PodContribution>) {
+        attribute(&self.estimate(), pods);
+    }
+}
+
+/// Pods in `measured` get their real usage and contribution, others a
+/// contribution of 0, and the top measured contributors are added if
+/// missing. Leaves `pods` alone without measurements.
+pub fn attribute(measured: &[PodContribution], pods: &mut Vec<PodContribution>) {
+    if measured.is_empty() {
+        return;
+    }
+    for pod in pods.iter_mut() {
+        match measured
+            .iter()
+            .find(|m| m.namespace == pod.namespace && m.pod == pod.pod)
+        {
+            Some(m) => {
+                pod.cpu_usage = m.cpu_usage;
+                pod.psi_contribution = m.psi_contribution;
+            }
+            None => pod.psi_contribution = 0.0,
+        }
+    }
+    for m in measured
+        .iter()
+        .filter(|m| m.psi_contribution > 0.0)
+        .take(MAX_ADDED_PODS)
+    {
+        if !pods
+            .iter()
+            .any(|p| p.namespace == m.namespace && p.pod == m.pod)
+        {
+            pods.push(m.clone());
+        }
+    }
+    pods.sort_by(|a, b| b.psi_contribution.total_cmp(&a.psi_contribution));
+}
+
+/// Pod-level cgroup directories below `root`, with the pod each belongs to.
+/// Pods are named via the container scopes inside them; `resolve` maps a
+/// container ID to `namespace/pod`.
+pub fn pod_cgroups(
+    root: &Path,
+    resolve: impl Fn(&str) -> Option<String>,
+) -> HashMap<PathBuf, String> {
+    let mut pods = HashMap::new();
+    let walker = WalkDir::new(root).max_depth(4).into_iter();
+    for entry in walker.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        // systemd driver: kubepods-burstable-pod<uid>.slice; cgroupfs: pod<uid>
+        let is_pod = name.starts_with("pod") || name.contains("-pod");
+        if !is_pod || !entry.path().to_string_lossy().contains("kubepods") {
+            continue;
+        }
+        let Ok(children) = std::fs::read_dir(entry.path()) else {
+            continue;
+        };
+        let pod = children.filter_map(|c| c.ok()).find_map(|c| {
+            let name = c.file_name();
+            crate::pidmap::container_id_from_cgroup(&name.to_string_lossy()).and_then(&resolve)
+        });
+        if let Some(pod) = pod {
+            pods.insert(entry.path().to_path_buf(), pod);
+        }
+    }
+    pods
+}
+
+/// Attribute the stall time of all pods to those using more than their
+/// weighted fair share of the CPU time used, in proportion to the excess.
+/// When nobody exceeds its share, blame follows usage.
+pub fn estimate(deltas: &[PodDelta], elapsed_usec: u64) -> Vec<PodContribution> {
+    let total_usage: u64 = deltas.iter().map(|d| d.usage_usec).sum();
+    let total_stall: u64 = deltas.iter().map(|d| d.stall_usec).sum();
+    let total_weight: u64 = deltas.iter().map(|d| d.weight.max(1)).sum();
+    if elapsed_usec == 0 || total_usage == 0 {
+        return Vec::new();
+    }
+    let excess: Vec<f64> = deltas
+        .iter()
+        .map(|d| {
+            let fair = total_usage as f64 * d.weight.max(1) as f64 / total_weight as f64;
+            (d.usage_usec as f64 - fair).max(0.0)
+        })
+        .collect();
+    let total_excess: f64 = excess.iter().sum();
+
+    let mut pods: Vec<PodContribution> = deltas
+        .iter()
+        .zip(&excess)
+        .filter(|(d, _)| d.usage_usec > 0)
+        .map(|(d, excess)| {
+            let blame = if total_excess > 0.0 {
+                excess / total_excess
+            } else {
+                d.usage_usec as f64 / total_usage as f64
+            };
+            let (namespace, pod) = d.pod.split_once('/').unwrap_or(("", &d.pod));
+            PodContribution {
+                namespace: namespace.to_string(),
+                pod: pod.to_string(),
+                cpu_usage: (d.usage_usec as f64 * 100.0 / elapsed_usec as f64) as f32,
+                psi_contribution: if total_stall > 0 {
+                    (blame * 100.0) as f32
+                } else {
+                    0.0
+                },
+            }
+        })
+        .collect();
+    pods.sort_by(|a, b| {
+        b.psi_contribution
+            .total_cmp(&a.psi_contribution)
+            .then(b.cpu_usage.total_cmp(&a.cpu_usage))
+    });
+    pods.truncate(MAX_PODS);
+    pods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delta(pod: &str, usage_usec: u64, stall_usec: u64, weight: u64) -> PodDelta {
+        PodDelta {
+            pod: pod.to_string(),
+            usage_usec,
+            stall_usec,
+            weight,
+        }
+    }
+
+    #[test]
+    fn blames_pods_over_their_weighted_share() {
+        // 10s on 4 CPUs: batch burns 3 CPUs at equal weight while api stalls.
+        let pods = estimate(
+            &[
+                delta("prod/batch-7", 30_000_000, 200_000, 100),
+                delta("prod/api-0", 5_000_000, 4_000_000, 100),
+                delta("prod/cron-1", 5_000_000, 0, 100),
+            ],
+            10_000_000,
+        );
+        assert_eq!(pods[0].pod, "batch-7");
+        assert_eq!(pods[0].namespace, "prod");
+        assert_eq!(pods[0].cpu_usage, 300.0);
+        assert!((pods[0].psi_contribution - 100.0).abs() < 0.01);
+        assert_eq!(pods[1].psi_contribution, 0.0);
+
+        // A higher weight entitles batch to its usage: blame follows usage.
+        let pods = estimate(
+            &[
+                delta("prod/batch-7", 30_000_000, 0, 600),
+                delta("prod/api-0", 5_000_000, 1_000_000, 100),
+            ],
+            10_000_000,
+        );
+        assert!((pods[0].psi_contribution - 85.71).abs() < 0.01);
+
+        // Nothing stalled: usage only.
+        let pods = estimate(&[delta("prod/api-0", 1_000_000, 0, 100)], 10_000_000);
+        assert_eq!(pods[0].psi_contribution, 0.0);
+        assert_eq!(pods[0].cpu_usage, 10.0);
+    }
+
+    #[test]
+    fn measured_numbers_replace_model_guesses() {
+        let measured = estimate(
+            &[
+                delta("prod/batch-7", 30_000_000, 0, 100),
+                delta("prod/api-0", 5_000_000, 4_000_000, 100),
+            ],
+            10_000_000,
+        );
+        let guess = |ns: &str, pod: &str| PodContribution {
+            namespace: ns.to_string(),
+            pod: pod.to_string(),
+            cpu_usage: 80.5,
+            psi_contribution: 10.2,
+        };
+        let mut pods = vec![guess("prod", "api-0"), guess("default", "foo-123")];
+        attribute(&measured, &mut pods);
+        let names: Vec<_> = pods.iter().map(|p| p.pod.as_str()).collect();
+        assert_eq!(names, ["batch-7", "api-0", "foo-123"]);
+        assert_eq!(pods[1].cpu_usage, 50.0);
+        assert_eq!(pods[1].psi_contribution, 0.0);
+        assert_eq!(pods[2].psi_contribution, 0.0);
+
+        let mut untouched = vec![guess("default", "foo-123")];
+        attribute(&[], &mut untouched);
+        assert_eq!(untouched[0].psi_contribution, 10.2);
+    }
+
+    #[test]
+    fn finds_pod_cgroups_by_container_scope() {
+        let root = tempfile::tempdir().unwrap();
+        let id = "e".repeat(64);
+        let pod = root
+            .path()
+            .join("kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod1234.slice");
+        std::fs::create_dir_all(pod.join(format!("cri-containerd-{id}.scope"))).unwrap();
+        std::fs::write(pod.join("cpu.pressure"), "some avg10=1.00 avg60=0.50 avg300=0.10 total=7000\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=10\n").unwrap();
+        std::fs::write(pod.join("cpu.stat"), "usage_usec 12345\nuser_usec 1\n").unwrap();
+        std::fs::write(pod.join("cpu.weight"), "40\n").unwrap();
+        std::fs::create_dir_all(root.path().join("system.slice/sshd.service")).unwrap();
+
+        let pods = pod_cgroups(root.path(), |c| (c == id).then(|| "prod/api-0".to_string()));
+        assert_eq!(pods.len(), 1);
+        assert_eq!(pods[&pod], "prod/api-0");
+        assert_eq!(
+            CgroupCpu::read(&pod),
+            Some(CgroupCpu {
+                usage_usec: 12345,
+                stall_usec: 7000,
+                weight: 40
+            })
+        );
+    }
+}
@@ -439,7 +439,8 @@ mod tests {
             std::env::set_var("K8S_TOKEN", "dummy");
         }
 
-        let k8s_ctx = K8sContext::new().expect("Failed to create K8sContext");
+        let k8s_ctx = K8sContext::new(&crate::network::Network::default())
+            .expect("Failed to create K8sContext");
         let monitor = PsiMonitor::new(
             k8s_ctx.clone(),
             Arc::new(ContextStore::new(
@@ -0,0 +1,164 @@
+//! CPU thermal and frequency throttling.
+//!
+//! An overheating or power-capped host runs every workload slower, which
+//! looks like the workloads misbehaving. Each sample reads the per-CPU
+//! thermal throttle counters (`thermal_throttle/*_throttle_count`, x86 only)
+//! and the current frequency relative to the base frequency from cpufreq.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Throttling observed since the previous sample.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CpuThermal {
+    /// Core and package thermal throttle events (x86 only).
+    pub throttle_events: u64,
+    /// CPUs with at least one throttle event.
+    pub throttled_cpus: usize,
+    /// Mean current frequency as a percentage of base frequency (or max
+    /// frequency where the driver doesn't report a base).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freq_pct: Option<f32>,
+}
+
+pub struct ThermalSampler {
+    root: PathBuf,
+    prev: HashMap<u32, u64>,
+}
+
+impl Default for ThermalSampler {
+    fn default() -> Self {
+        Self::new("/sys/devices/system/cpu")
+    }
+}
+
+impl ThermalSampler {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            prev: HashMap::new(),
+        }
+    }
+
+    /// `None` when the host exposes neither throttle counters nor cpufreq
+    /// (e.g. most VMs).
+    pub fn sample(&mut self) -> Option<CpuThermal> {
+        let mut counts = HashMap::new();
+        let mut sample = CpuThermal::default();
+        let mut freq_sum = 0.0f64;
+        let mut freq_cpus = 0u32;
+
+        for cpu in cpu_ids(&self.root) {
+            let dir = self.root.join(format!("cpu{cpu}"));
+            let throttle = dir.join("thermal_throttle");
+            let core = read_u64(&throttle.join("core_throttle_count"));
+            let package = read_u64(&throttle.join("package_throttle_count"));
+            if core.is_some() || package.is_some() {
+                let count = core.unwrap_or(0) + package.unwrap_or(0);
+                if let Some(prev) = self.prev.get(&cpu) {
+                    let delta = count.saturating_sub(*prev);
+                    sample.throttle_events += delta;
+                    if delta > 0 {
+                        sample.throttled_cpus += 1;
+                    }
+                }
+                counts.insert(cpu, count);
+            }
+
+            let freq = dir.join("cpufreq");
+            let base = read_u64(&freq.join("base_frequency"))
+                .or_else(|| read_u64(&freq.join("cpuinfo_max_freq")));
+            if let (Some(cur), Some(base)) = (read_u64(&freq.join("scaling_cur_freq")), base)
+                && base > 0
+            {
+                freq_sum += cur as f64 * 100.0 / base as f64;
+                freq_cpus += 1;
+            }
+        }
+
+        if counts.is_empty() && freq_cpus == 0 {
+            return None;
+        }
+        self.prev = counts;
+        if freq_cpus > 0 {
+            sample.freq_pct = Some((freq_sum / freq_cpus as f64) as f32);
+        }
+        Some(sample)
+    }
+}
+
+fn cpu_ids(root: &Path) -> Vec<u32> {
+    let mut ids: Vec<u32> = fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str()?.strip_prefix("cpu")?.parse().ok())
+        .collect();
+    ids.sort_unstable();
+    ids
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str, value: u64) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("{value}\n")).unwrap();
+    }
+
+    #[test]
+    fn counts_throttle_events_and_frequency_between_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for cpu in 0..2 {
+            write(
+                root,
+                &format!("cpu{cpu}/thermal_throttle/core_throttle_count"),
+                10,
+            );
+            write(
+                root,
+                &format!("cpu{cpu}/thermal_throttle/package_throttle_count"),
+                5,
+            );
+            write(root, &format!("cpu{cpu}/cpufreq/base_frequency"), 2_000_000);
+            write(
+                root,
+                &format!("cpu{cpu}/cpufreq/cpuinfo_max_freq"),
+                4_000_000,
+            );
+            write(
+                root,
+                &format!("cpu{cpu}/cpufreq/scaling_cur_freq"),
+                1_000_000,
+            );
+        }
+        // Not a CPU directory.
+        fs::create_dir_all(root.join("cpufreq")).unwrap();
+
+        let mut sampler = ThermalSampler::new(root);
+        let first = sampler.sample().unwrap();
+        assert_eq!(first.throttle_events, 0, "first sample is a baseline");
+        assert_eq!(first.freq_pct, Some(50.0));
+
+        write(root, "cpu1/thermal_throttle/core_throttle_count", 13);
+        write(root, "cpu1/thermal_throttle/package_throttle_count", 6);
+        let second = sampler.sample().unwrap();
+        assert_eq!((second.throttle_events, second.throttled_cpus), (4, 1));
+    }
+
+    #[test]
+    fn hosts_without_counters_or_cpufreq_report_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("cpu0/topology")).unwrap();
+        assert_eq!(ThermalSampler::new(dir.path()).sample(), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! CFS bandwidth throttling per cgroup.
+//!
+//! A container at its CPU limit gets throttled long before node CPU looks
+//! busy, which shows up as "slow but not busy". Each sample reads `cpu.stat`
+//! for every cgroup with a CPU quota (`cpu.max` on v2, `cpu.cfs_quota_us` on
+//! v1) and reports the cgroups throttled since the previous sample.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Most throttled cgroups kept per sample.
+pub const MAX_REPORTED_CGROUPS: usize = 32;
+
+/// Cumulative counters from one `cpu.stat`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuStat {
+    pub nr_periods: u64,
+    pub nr_throttled: u64,
+    pub throttled_usec: u64,
+}
+
+/// Throttling of one cgroup between two samples.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CgroupThrottle {
+    /// Path below the cgroup mount, e.g. `/kubepods.slice/.../cri-containerd-<id>.scope`.
+    pub cgroup: String,
+    /// `namespace/pod` when the cgroup belongs to a known Kubernetes pod.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod: Option<String>,
+    pub periods: u64,
+    pub throttled_periods: u64,
+    /// Share of enforcement periods that hit the quota.
+    pub throttled_pct: f32,
+    pub throttled_usec: u64,
+}
+
+impl CgroupThrottle {
+    /// Pod name if known, otherwise the cgroup path.
+    pub fn label(&self) -> &str {
+        self.pod.as_deref().unwrap_or(&self.cgroup)
+    }
+}
+
+/// Parse `cpu.stat`. cgroup v2 reports `throttled_usec`, v1 `throttled_time`
+/// in nanoseconds.
+pub fn parse_cpu_stat(content: &str) -> CpuStat {
+    let mut stat = CpuStat::default();
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let Ok(value) = value.trim().parse::<u64>() else {
+            continue;
+        };
+        match key {
+            "nr_periods" => stat.nr_periods = value,
+            "nr_throttled" => stat.nr_throttled = value,
+            "throttled_usec" => stat.throttled_usec = value,
+            "throttled_time" => stat.throttled_usec = value / 1000,
+            _ => {}
+        }
+    }
+    stat
+}
+
+/// Whether the cgroup directory has a CFS quota.
+fn has_quota(dir: &Path) -> bool {
+    if let Ok(max) = std::fs::read_to_string(dir.join("cpu.max")) {
+        return max.split_whitespace().next().is_some_and(|q| q != "max");
+    }
+    std::fs::read_to_string(dir.join("cpu.cfs_quota_us"))
+        .ok()
+        .and_then(|q| q.trim().parse::<i64>().ok())
+        .is_some_and(|q| q > 0)
+}
+
+pub struct ThrottleSampler {
+    root: PathBuf,
+    prev: HashMap<PathBuf, CpuStat>,
+}
+
+impl Default for ThrottleSampler {
+    fn default() -> Self {
+        Self::new("/sys/fs/cgroup")
+    }
+}
+
+impl ThrottleSampler {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            prev: HashMap::new(),
+        }
+    }
+
+    /// Cgroups throttled since the previous call, most throttled first. The
+    /// first call only records baselines.
+    pub fn sample(&mut self) -> Vec<CgroupThrottle> {
+        let mut current = HashMap::with_capacity(self.prev.len());
+        let mut throttled = Vec::new();
+
+        for entry in WalkDir::new(&self.root).into_iter().filter_map(|e| e.ok()) {
+            if entry.file_name() != "cpu.stat" {
+                continue;
+            }
+            let Some(dir) = entry.path().parent() else {
+                continue;
+            };
+            if dir == self.root || !has_quota(dir) {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let stat = parse_cpu_stat(&content);
+            if let Some(prev) = self.prev.get(dir)
+                && let Some(delta) = throttle_delta(prev, &stat)
+            {
+                let cgroup = dir.strip_prefix(&self.root).unwrap_or(dir);
+                throttled.push(CgroupThrottle {
+                    cgroup: format!("/{}", cgroup.display()),
+                    ..delta
+                });
+            }
+            current.insert(dir.to_path_buf(), stat);
+        }
+
+        // Only cgroups still present carry over, so removed pods don't leak.
+        self.prev = current;
+        throttled.sort_by(|a, b| b.throttled_pct.total_cmp(&a.throttled_pct));
+        throttled.truncate(MAX_REPORTED_CGROUPS);
+        throttled
+    }
+}
+
+/// Throttling between two readings, or `None` if there was none (or the
+/// counters went backwards because the cgroup was recreated).
+fn throttle_delta(prev: &CpuStat, cur: &CpuStat) -> Option<CgroupThrottle> {
+    let periods = cur.nr_periods.checked_sub(prev.nr_periods)?;
+    let throttled_periods = cur.nr_throttled.checked_sub(prev.nr_throttled)?;
+    if periods == 0 || throttled_periods == 0 {
+        return None;
+    }
+    Some(CgroupThrottle {
+        cgroup: String::new(),
+        pod: None,
+        periods,
+        throttled_periods,
+        throttled_pct: (throttled_periods as f64 * 100.0 / periods as f64).min(100.0) as f32,
+        throttled_usec: cur.throttled_usec.saturating_sub(prev.throttled_usec),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_cgroup(dir: &Path, quota: &str, periods: u64, throttled: u64, usec: u64) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("cpu.max"), format!("{quota} 100000\n")).unwrap();
+        std::fs::write(
+            dir.join("cpu.stat"),
+            format!(
+                "usage_usec 1000\nnr_periods {periods}\nnr_throttled {throttled}\nthrottled_usec {usec}\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn parses_v1_and_v2_cpu_stat() {
+        let v2 =
+            parse_cpu_stat("usage_usec 5\nnr_periods 10\nnr_throttled 4\nthrottled_usec 900\n");
+        assert_eq!(
+            v2,
+            CpuStat {
+                nr_periods: 10,
+                nr_throttled: 4,
+                throttled_usec: 900
+            }
+        );
+        let v1 = parse_cpu_stat("nr_periods 10\nnr_throttled 4\nthrottled_time 2000000\n");
+        assert_eq!(v1.throttled_usec, 2000);
+    }
+
+    #[test]
+    fn reports_throttled_cgroups_with_quota_between_samples() {
+        let root = tempfile::tempdir().unwrap();
+        let limited = root.path().join("kubepods.slice/app.scope");
+        let idle = root.path().join("kubepods.slice/idle.scope");
+        let unlimited = root.path().join("system.slice");
+        write_cgroup(&limited, "50000", 100, 10, 1_000);
+        write_cgroup(&idle, "50000", 100, 0, 0);
+        write_cgroup(&unlimited, "max", 100, 0, 0);
+
+        let mut sampler = ThrottleSampler::new(root.path());
+        assert!(sampler.sample().is_empty(), "first sample is a baseline");
+
+        write_cgroup(&limited, "50000", 150, 40, 301_000);
+        write_cgroup(&idle, "50000", 150, 0, 0);
+        write_cgroup(&unlimited, "max", 150, 50, 9_000);
+        let throttled = sampler.sample();
+        assert_eq!(throttled.len(), 1);
+        let app = &throttled[0];
+        assert_eq!(app.cgroup, "/kubepods.slice/app.scope");
+        assert_eq!((app.periods, app.throttled_periods), (50, 30));
+        assert_eq!(app.throttled_pct, 60.0);
+        assert_eq!(app.throttled_usec, 300_000);
+
+        // Recreated cgroup: counters reset, no bogus delta.
+        write_cgroup(&limited, "50000", 5, 1, 10);
+        assert!(sampler.sample().is_empty());
+    }
+}
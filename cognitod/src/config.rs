@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -37,6 +38,123 @@ fn default_listen_addr() -> String {
 pub struct NotificationConfig {
     pub apprise: Option<AppriseConfig>,
     pub slack: Option<SlackConfig>,
+    #[serde(default)]
+    pub email: Option<EmailConfig>,
+    #[serde(default)]
+    pub teams: Option<TeamsConfig>,
+    #[serde(default)]
+    pub telegram: Option<TelegramConfig>,
+    #[serde(default)]
+    pub opsgenie: Option<OpsgenieConfig>,
+    #[serde(default)]
+    pub syslog: Option<SyslogConfig>,
+    #[serde(default)]
+    pub nats: Option<NatsConfig>,
+    #[serde(default)]
+    pub webhooks: Option<InsightWebhookConfig>,
+    #[serde(default)]
+    pub budget: AlertBudgetConfig,
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    #[serde(default)]
+    pub digest: DigestConfig,
+}
+
+/// `[notifications.digest]` section: lower-severity alerts batched into
+/// one message per channel and interval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestConfig {
+    /// Seconds between digests; 0 sends every alert right away.
+    #[serde(default)]
+    pub interval_secs: u64,
+    /// Alerts at or above this severity skip the digest.
+    #[serde(default = "default_digest_immediate_severity")]
+    pub immediate_severity: String,
+    /// Channels that batch; empty is every channel.
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            immediate_severity: default_digest_immediate_severity(),
+            channels: Vec::new(),
+        }
+    }
+}
+
+fn default_digest_immediate_severity() -> String {
+    "high".to_string()
+}
+
+/// `[notifications.routing]` section: quiet hours and per-rule overrides
+/// of the channels' `min_severity`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHoursConfig>,
+    /// Overrides by rule name.
+    #[serde(default)]
+    pub rules: BTreeMap<String, RuleRoutingConfig>,
+}
+
+/// One `[[notifications.routing.quiet_hours]]` window, in host local time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHoursConfig {
+    /// `HH:MM`; a window ending before it starts runs past midnight.
+    pub start: String,
+    pub end: String,
+    /// Days the window starts on (`mon`, `tue`, ...); empty is every day.
+    #[serde(default)]
+    pub days: Vec<String>,
+    /// Channels kept quiet; empty is every channel.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Alerts below this severity aren't sent while the window is open.
+    #[serde(default = "default_quiet_hours_min_severity")]
+    pub min_severity: String,
+}
+
+fn default_quiet_hours_min_severity() -> String {
+    "high".to_string()
+}
+
+/// `[notifications.routing.rules.<rule>]` section.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleRoutingConfig {
+    /// Replaces every channel's `min_severity` for the rule's alerts.
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    /// Whether quiet hours apply to the rule; `false` always notifies.
+    #[serde(default = "default_rule_routing_quiet_hours")]
+    pub quiet_hours: bool,
+}
+
+fn default_rule_routing_quiet_hours() -> bool {
+    true
+}
+
+/// `[notifications.budget]` section: how many alerts a host notifies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertBudgetConfig {
+    /// Alerts sent to the notifiers per minute; the rest are folded
+    /// into one summary alert at the end of the minute. 0 sends every alert.
+    #[serde(default = "default_budget_max_alerts_per_minute")]
+    pub max_alerts_per_minute: u32,
+}
+
+impl Default for AlertBudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_alerts_per_minute: default_budget_max_alerts_per_minute(),
+        }
+    }
+}
+
+fn default_budget_max_alerts_per_minute() -> u32 {
+    30
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,14 +169,346 @@ pub struct SlackConfig {
     pub webhook_url: String,
     #[serde(default)]
     pub channel: Option<String>,
+    #[serde(default)]
+    pub min_severity: Option<String>,
     #[serde(default = "default_dashboard_url")]
     pub dashboard_base_url: String,
+    /// Signing secret of the Slack app, to verify its button callbacks.
+    #[serde(default)]
+    pub signing_secret: Option<String>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+/// `[notifications.slack.retry]` section: alerts that failed to post,
+/// retried with exponential backoff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Alerts held for retry; when full the oldest is dropped. 0 disables
+    /// retries.
+    #[serde(default = "default_retry_max_queued")]
+    pub max_queued: usize,
+    #[serde(default = "default_retry_initial_backoff_secs")]
+    pub initial_backoff_secs: u64,
+    #[serde(default = "default_retry_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+    /// Queued alerts older than this are dropped.
+    #[serde(default = "default_retry_max_age_secs")]
+    pub max_age_secs: u64,
+    /// File keeping the queue across restarts; in memory only without it.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_queued: default_retry_max_queued(),
+            initial_backoff_secs: default_retry_initial_backoff_secs(),
+            max_backoff_secs: default_retry_max_backoff_secs(),
+            max_age_secs: default_retry_max_age_secs(),
+            path: None,
+        }
+    }
+}
+
+fn default_retry_max_queued() -> usize {
+    500
+}
+
+fn default_retry_initial_backoff_secs() -> u64 {
+    5
+}
+
+fn default_retry_max_backoff_secs() -> u64 {
+    600
+}
+
+fn default_retry_max_age_secs() -> u64 {
+    86_400
 }
 
 fn default_dashboard_url() -> String {
     "http://localhost:3000".to_string()
 }
 
+/// `[notifications.email]` section: alerts sent through an SMTP relay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    /// Defaults to 587 for `starttls`, 465 for `tls` and 25 for `none`.
+    #[serde(default)]
+    pub smtp_port: Option<u16>,
+    /// `starttls`, `tls` (implicit TLS) or `none`.
+    #[serde(default = "default_email_tls")]
+    pub tls: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    /// Send the alerts of each interval as one email; 0 sends each alert
+    /// on its own.
+    #[serde(default)]
+    pub digest_secs: u64,
+    #[serde(default = "default_email_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// `[notifications.teams]` section: alerts and insights posted to a
+/// Microsoft Teams webhook as Adaptive Cards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TeamsConfig {
+    pub webhook_url: String,
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    /// Also post recorded insights.
+    #[serde(default = "default_teams_insights")]
+    pub insights: bool,
+    #[serde(default = "default_dashboard_url")]
+    pub dashboard_base_url: String,
+}
+
+fn default_teams_insights() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    /// Numeric chat id, or `@name` for a public channel.
+    pub chat_id: String,
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    /// Also send a summary of each recorded insight.
+    #[serde(default)]
+    pub insights: bool,
+    #[serde(default = "default_dashboard_url")]
+    pub dashboard_base_url: String,
+    /// Bot API server, for a self-hosted one.
+    #[serde(default = "default_telegram_api_url")]
+    pub api_url: String,
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpsgenieConfig {
+    /// Key of an Opsgenie API integration.
+    pub api_key: String,
+    /// `https://api.eu.opsgenie.com` for the EU instance.
+    #[serde(default = "default_opsgenie_api_url")]
+    pub api_url: String,
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    #[serde(default)]
+    pub priorities: OpsgeniePriorities,
+    /// Close the Opsgenie alert when the alert resolves.
+    #[serde(default = "default_opsgenie_close_on_resolve")]
+    pub close_on_resolve: bool,
+}
+
+/// `[notifications.opsgenie.priorities]` section: Opsgenie priority, `P1`
+/// to `P5`, of each severity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpsgeniePriorities {
+    #[serde(default = "default_opsgenie_priorities_info")]
+    pub info: String,
+    #[serde(default = "default_opsgenie_priorities_low")]
+    pub low: String,
+    #[serde(default = "default_opsgenie_priorities_medium")]
+    pub medium: String,
+    #[serde(default = "default_opsgenie_priorities_high")]
+    pub high: String,
+}
+
+impl Default for OpsgeniePriorities {
+    fn default() -> Self {
+        Self {
+            info: default_opsgenie_priorities_info(),
+            low: default_opsgenie_priorities_low(),
+            medium: default_opsgenie_priorities_medium(),
+            high: default_opsgenie_priorities_high(),
+        }
+    }
+}
+
+fn default_opsgenie_api_url() -> String {
+    "https://api.opsgenie.com".to_string()
+}
+
+fn default_opsgenie_close_on_resolve() -> bool {
+    true
+}
+
+fn default_opsgenie_priorities_info() -> String {
+    "P5".to_string()
+}
+
+fn default_opsgenie_priorities_low() -> String {
+    "P4".to_string()
+}
+
+fn default_opsgenie_priorities_medium() -> String {
+    "P3".to_string()
+}
+
+fn default_opsgenie_priorities_high() -> String {
+    "P1".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyslogConfig {
+    /// `host:port` of the syslog receiver.
+    pub address: String,
+    /// `udp`, `tcp` or `tls`.
+    #[serde(default = "default_syslog_transport")]
+    pub transport: String,
+    /// Syslog facility name, such as `daemon` or `local0` to `local7`.
+    #[serde(default = "default_syslog_facility")]
+    pub facility: String,
+    #[serde(default = "default_syslog_app_name")]
+    pub app_name: String,
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    #[serde(default)]
+    pub severities: SyslogSeverities,
+    /// PEM bundle to verify the receiver's certificate with; the system
+    /// roots otherwise.
+    #[serde(default)]
+    pub ca_file: Option<String>,
+}
+
+/// `[notifications.syslog.severities]` section: syslog severity name of
+/// each alert severity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyslogSeverities {
+    #[serde(default = "default_syslog_severities_info")]
+    pub info: String,
+    #[serde(default = "default_syslog_severities_low")]
+    pub low: String,
+    #[serde(default = "default_syslog_severities_medium")]
+    pub medium: String,
+    #[serde(default = "default_syslog_severities_high")]
+    pub high: String,
+}
+
+impl Default for SyslogSeverities {
+    fn default() -> Self {
+        Self {
+            info: default_syslog_severities_info(),
+            low: default_syslog_severities_low(),
+            medium: default_syslog_severities_medium(),
+            high: default_syslog_severities_high(),
+        }
+    }
+}
+
+fn default_syslog_transport() -> String {
+    "udp".to_string()
+}
+
+fn default_syslog_facility() -> String {
+    "daemon".to_string()
+}
+
+fn default_syslog_app_name() -> String {
+    "linnix".to_string()
+}
+
+fn default_syslog_severities_info() -> String {
+    "info".to_string()
+}
+
+fn default_syslog_severities_low() -> String {
+    "notice".to_string()
+}
+
+fn default_syslog_severities_medium() -> String {
+    "warning".to_string()
+}
+
+fn default_syslog_severities_high() -> String {
+    "err".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsConfig {
+    /// `nats://host:4222`, or `tls://...` to require TLS; several servers
+    /// may be given comma-separated.
+    #[serde(default = "default_nats_url")]
+    pub url: String,
+    /// Alerts go to `<subject_prefix>.<severity>`.
+    #[serde(default = "default_nats_subject_prefix")]
+    pub subject_prefix: String,
+    #[serde(default)]
+    pub min_severity: Option<String>,
+    /// `.creds` file (JWT and nkey seed) to authenticate with.
+    #[serde(default)]
+    pub credentials_file: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Publish through JetStream; a stream must capture the subjects.
+    #[serde(default)]
+    pub jetstream: bool,
+    /// With `jetstream`, wait for the stream to acknowledge each alert.
+    #[serde(default = "default_nats_await_ack")]
+    pub await_ack: bool,
+}
+
+fn default_nats_url() -> String {
+    "nats://localhost:4222".to_string()
+}
+
+fn default_nats_subject_prefix() -> String {
+    "linnix.alerts".to_string()
+}
+
+fn default_nats_await_ack() -> bool {
+    true
+}
+
+fn default_email_tls() -> String {
+    "starttls".to_string()
+}
+
+fn default_email_timeout_secs() -> u64 {
+    30
+}
+
+/// Generic JSON webhooks fired for every recorded insight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InsightWebhookConfig {
+    pub urls: Vec<String>,
+    /// Insights below this confidence (0.0-1.0) are not posted.
+    #[serde(default)]
+    pub min_confidence: f32,
+    /// Hash pod/namespace names before the payload leaves the host.
+    #[serde(default = "default_webhook_redact")]
+    pub redact: bool,
+    /// Extra HTTP headers (e.g. `Authorization`) added to every request.
+    #[serde(default)]
+    pub headers: std::collections::HashMap<String, String>,
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Base URL used to build evidence links in the payload.
+    #[serde(default = "default_dashboard_url")]
+    pub dashboard_base_url: String,
+}
+
+fn default_webhook_redact() -> bool {
+    true
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    5000
+}
+
 #[derive(Debug, Deserialize, Clone, Default)]
 #[allow(dead_code)]
 pub struct Config {
@@ -99,6 +549,56 @@ pub struct Config {
     pub receipt_privacy: ReceiptPrivacyConfig,
     #[serde(default)]
     pub chain: ChainConfig,
+    #[serde(default)]
+    pub redaction: crate::redaction::RedactionConfig,
+    #[serde(default)]
+    pub rollups: RollupConfig,
+    #[serde(default)]
+    pub profiler: ProfilerConfig,
+    #[serde(default)]
+    pub malloc_sampler: MallocSamplerConfig,
+    #[serde(default)]
+    pub exec_compression: ExecCompressionConfig,
+    #[serde(default)]
+    pub handlers: HandlersConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub baselines: BaselineConfig,
+    #[serde(default)]
+    pub sessions: SessionConfig,
+    #[serde(default)]
+    pub watch: WatchConfig,
+    #[serde(default)]
+    pub inventory: InventoryConfig,
+    #[serde(default)]
+    pub leader_election: LeaderElectionConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub evidence: EvidenceConfig,
+    #[serde(default)]
+    pub statsd: StatsdConfig,
+    #[serde(default)]
+    pub kafka: KafkaConfig,
+    #[serde(default)]
+    pub update_check: UpdateCheckConfig,
+    #[serde(default)]
+    pub quotas: QuotaConfig,
+    #[serde(default)]
+    pub changes: ChangesConfig,
+    #[serde(default)]
+    pub aggregator: AggregatorConfig,
+    /// `[[silences]]`: maintenance windows during which matching rules
+    /// don't alert.
+    #[serde(default)]
+    pub silences: Vec<crate::silences::SilenceWindow>,
+    /// Top-level `mode`: `"live"` (default) or `"observe"`, where detectors
+    /// and the ILM run but nothing is notified or enforced.
+    #[serde(default)]
+    pub mode: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -108,245 +608,1160 @@ pub struct PrivacyConfig {
     pub redact_sensitive_data: bool,
 }
 
-impl Default for PrivacyConfig {
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            redact_sensitive_data: default_redact_sensitive_data(),
+        }
+    }
+}
+
+fn default_redact_sensitive_data() -> bool {
+    false
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NoiseBudgetConfig {
+    /// Maximum number of alerts allowed per hour
+    #[serde(default = "default_max_alerts_per_hour")]
+    pub max_alerts_per_hour: u32,
+    /// If true, suppress alerts when budget is exceeded (default: true)
+    #[serde(default = "default_noise_budget_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for NoiseBudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_alerts_per_hour: default_max_alerts_per_hour(),
+            enabled: default_noise_budget_enabled(),
+        }
+    }
+}
+
+fn default_max_alerts_per_hour() -> u32 {
+    10 // Default to 10 alerts per hour to prevent spam
+}
+
+fn default_noise_budget_enabled() -> bool {
+    true
+}
+
+impl Config {
+    /// True when `mode = "observe"`: record instead of notifying or acting.
+    pub fn observe_only(&self) -> bool {
+        self.mode == "observe"
+    }
+
+    /// Load configuration from file. The path can be overridden
+    /// with the `LINNIX_CONFIG` environment variable. If the file
+    /// is missing or fails to parse, defaults are returned.
+    pub fn load() -> Self {
+        let path =
+            std::env::var(ENV_CONFIG_PATH).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+        let path = PathBuf::from(path);
+        match fs::read_to_string(&path) {
+            Ok(contents) => match toml::from_str(&contents) {
+                Ok(config) => config,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to parse config file at {}: {}. Using defaults.",
+                        path.display(),
+                        e
+                    );
+                    Config::default()
+                }
+            },
+            Err(_) => Config::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+pub struct RuntimeConfig {
+    #[serde(default = "default_offline")]
+    pub offline: bool,
+    #[serde(default = "default_cpu_target_pct")]
+    pub cpu_target_pct: u64,
+    #[serde(default = "default_rss_cap_mb")]
+    pub rss_cap_mb: u64,
+    #[serde(default = "default_events_rate_cap")]
+    pub events_rate_cap: u64,
+    /// Read argv from `/proc/<pid>/cmdline` on exec events.
+    #[serde(default = "default_capture_argv")]
+    pub capture_argv: bool,
+    /// How long argv read at exec is kept for later events of the process.
+    #[serde(default = "default_argv_cache_ttl_secs")]
+    pub argv_cache_ttl_secs: u64,
+    /// Reload the BPF programs when the object file changes (e.g. after a
+    /// package upgrade), as SIGUSR2 does.
+    #[serde(default)]
+    pub reload_bpf_on_change: bool,
+    /// Drop cognitod's own events in the kernel.
+    #[serde(default = "default_exclude_self")]
+    pub exclude_self: bool,
+    /// PIDs (TGIDs) whose events are dropped in the kernel.
+    #[serde(default)]
+    pub exclude_pids: Vec<u32>,
+    /// cgroup v2 paths, relative to `/sys/fs/cgroup` unless absolute, whose
+    /// subtrees' events are dropped in the kernel.
+    #[serde(default)]
+    pub exclude_cgroups: Vec<String>,
+    /// When set, only events of these PIDs (and of `include_cgroups`) are
+    /// emitted.
+    #[serde(default)]
+    pub include_pids: Vec<u32>,
+    #[serde(default)]
+    pub include_cgroups: Vec<String>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            offline: default_offline(),
+            cpu_target_pct: default_cpu_target_pct(),
+            rss_cap_mb: default_rss_cap_mb(),
+            events_rate_cap: default_events_rate_cap(),
+            capture_argv: default_capture_argv(),
+            argv_cache_ttl_secs: default_argv_cache_ttl_secs(),
+            reload_bpf_on_change: false,
+            exclude_self: default_exclude_self(),
+            exclude_pids: Vec::new(),
+            exclude_cgroups: Vec::new(),
+            include_pids: Vec::new(),
+            include_cgroups: Vec::new(),
+        }
+    }
+}
+
+fn default_offline() -> bool {
+    true
+}
+fn default_cpu_target_pct() -> u64 {
+    25
+}
+fn default_rss_cap_mb() -> u64 {
+    512
+}
+fn default_events_rate_cap() -> u64 {
+    100_000
+}
+fn default_capture_argv() -> bool {
+    true
+}
+fn default_argv_cache_ttl_secs() -> u64 {
+    10
+}
+fn default_exclude_self() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+pub struct LoggingConfig {
+    #[serde(default = "default_alerts_file")]
+    pub alerts_file: String,
+    #[serde(default = "default_journald")]
+    pub journald: bool,
+    #[serde(default = "default_insights_file")]
+    pub insights_file: String,
+    #[serde(default)]
+    pub incident_context_file: Option<String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            alerts_file: default_alerts_file(),
+            journald: default_journald(),
+            insights_file: default_insights_file(),
+            incident_context_file: None,
+        }
+    }
+}
+
+fn default_alerts_file() -> String {
+    "/var/log/linnix/alerts.ndjson".to_string()
+}
+fn default_journald() -> bool {
+    true
+}
+fn default_insights_file() -> String {
+    "/var/log/linnix/insights.ndjson".to_string()
+}
+
+/// `[rules]` section: the rules file of the rules handler.
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+pub struct RulesFileConfig {
+    #[serde(default = "default_rules_file")]
+    pub path: String,
+    /// Reload the rules when the file changes. SIGHUP always reloads them.
+    #[serde(default = "default_rules_watch")]
+    pub watch: bool,
+}
+
+impl Default for RulesFileConfig {
+    fn default() -> Self {
+        Self {
+            path: default_rules_file(),
+            watch: default_rules_watch(),
+        }
+    }
+}
+
+fn default_rules_file() -> String {
+    "/etc/linnix/rules.toml".to_string()
+}
+
+fn default_rules_watch() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[allow(dead_code)]
+pub struct ReasonerConfig {
+    #[serde(default = "default_reasoner_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_reasoner_endpoint")]
+    pub endpoint: String,
+    #[serde(default = "default_reasoner_timeout")]
+    pub timeout_ms: u64,
+}
+
+impl Default for ReasonerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_reasoner_enabled(),
+            endpoint: default_reasoner_endpoint(),
+            timeout_ms: default_reasoner_timeout(),
+        }
+    }
+}
+
+fn default_reasoner_enabled() -> bool {
+    true
+}
+
+fn default_reasoner_endpoint() -> String {
+    "http://127.0.0.1:8087/v1/chat/completions".to_string()
+}
+
+fn default_reasoner_timeout() -> u64 {
+    150
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[allow(dead_code)]
+pub struct OutputConfig {
+    #[serde(default)]
+    pub slack: bool,
+    #[serde(default)]
+    pub pagerduty: bool,
+    #[serde(default)]
+    pub prometheus: bool,
+}
+
+#[derive(Clone)]
+pub struct OfflineGuard {
+    offline: bool,
+}
+
+impl OfflineGuard {
+    pub fn new(offline: bool) -> Self {
+        Self { offline }
+    }
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+    /// Returns true if network operations are allowed.
+    #[allow(dead_code)]
+    pub fn check(&self, sink: &str) -> bool {
+        if self.offline {
+            log::warn!("offline mode: blocking {sink} sink");
+            false
+        } else {
+            true
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PsiConfig {
+    /// Duration in seconds of sustained pressure required to trigger attribution
+    #[serde(default = "default_psi_sustained_pressure_seconds")]
+    pub sustained_pressure_seconds: u64,
+    /// Measure each pod's share of CPU stall time from cgroup pressure files
+    /// and use it for `psi_contribution` in prompts and insights.
+    #[serde(default = "default_psi_pod_contribution")]
+    pub pod_contribution: bool,
+}
+
+impl Default for PsiConfig {
+    fn default() -> Self {
+        Self {
+            sustained_pressure_seconds: default_psi_sustained_pressure_seconds(),
+            pod_contribution: default_psi_pod_contribution(),
+        }
+    }
+}
+
+fn default_psi_sustained_pressure_seconds() -> u64 {
+    15
+}
+
+fn default_psi_pod_contribution() -> bool {
+    true
+}
+
+/// `[rollups]` section: downsampled telemetry kept in the incident database.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RollupConfig {
+    #[serde(default = "default_rollups_enabled")]
+    pub enabled: bool,
+    /// How long 1-minute buckets are kept.
+    #[serde(default = "default_rollup_retention_1m_hours")]
+    pub retention_1m_hours: u64,
+    /// How long 5-minute buckets are kept.
+    #[serde(default = "default_rollup_retention_5m_days")]
+    pub retention_5m_days: u64,
+    /// Number of top CPU / RSS processes sampled into each bucket.
+    #[serde(default = "default_rollup_top_processes")]
+    pub top_processes: usize,
+}
+
+impl Default for RollupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_rollups_enabled(),
+            retention_1m_hours: default_rollup_retention_1m_hours(),
+            retention_5m_days: default_rollup_retention_5m_days(),
+            top_processes: default_rollup_top_processes(),
+        }
+    }
+}
+
+fn default_rollups_enabled() -> bool {
+    true
+}
+
+fn default_rollup_retention_1m_hours() -> u64 {
+    48
+}
+
+fn default_rollup_retention_5m_days() -> u64 {
+    30
+}
+
+fn default_rollup_top_processes() -> usize {
+    5
+}
+
+/// `[profiler]` section: stack sampling of CPU-spinning processes.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProfilerConfig {
+    #[serde(default = "default_profiler_enabled")]
+    pub enabled: bool,
+    /// How long to sample the target.
+    #[serde(default = "default_profiler_duration_ms")]
+    pub duration_ms: u64,
+    #[serde(default = "default_profiler_frequency_hz")]
+    pub frequency_hz: u64,
+    /// Threads of the target that get their own sampler.
+    #[serde(default = "default_profiler_max_threads")]
+    pub max_threads: usize,
+    /// Distinct stacks kept in the stored profile.
+    #[serde(default = "default_profiler_max_stacks")]
+    pub max_stacks: usize,
+}
+
+impl Default for ProfilerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_profiler_enabled(),
+            duration_ms: default_profiler_duration_ms(),
+            frequency_hz: default_profiler_frequency_hz(),
+            max_threads: default_profiler_max_threads(),
+            max_stacks: default_profiler_max_stacks(),
+        }
+    }
+}
+
+fn default_profiler_enabled() -> bool {
+    true
+}
+
+fn default_profiler_duration_ms() -> u64 {
+    3000
+}
+
+fn default_profiler_frequency_hz() -> u64 {
+    99
+}
+
+fn default_profiler_max_threads() -> usize {
+    64
+}
+
+fn default_profiler_max_stacks() -> usize {
+    200
+}
+
+/// `[malloc_sampler]` section: on-demand malloc/free uprobes on one process.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MallocSamplerConfig {
+    #[serde(default = "default_malloc_sampler_enabled")]
+    pub enabled: bool,
+    /// Smallest allocation recorded.
+    #[serde(default = "default_malloc_sampler_min_bytes")]
+    pub min_bytes: u64,
+    /// Sampling window when the request doesn't give one.
+    #[serde(default = "default_malloc_sampler_duration_secs")]
+    pub duration_secs: u64,
+    /// Longest window a request may ask for.
+    #[serde(default = "default_malloc_sampler_max_duration_secs")]
+    pub max_duration_secs: u64,
+    /// Distinct stacks kept in the report.
+    #[serde(default = "default_malloc_sampler_max_stacks")]
+    pub max_stacks: usize,
+}
+
+impl Default for MallocSamplerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_malloc_sampler_enabled(),
+            min_bytes: default_malloc_sampler_min_bytes(),
+            duration_secs: default_malloc_sampler_duration_secs(),
+            max_duration_secs: default_malloc_sampler_max_duration_secs(),
+            max_stacks: default_malloc_sampler_max_stacks(),
+        }
+    }
+}
+
+fn default_malloc_sampler_enabled() -> bool {
+    false
+}
+
+fn default_malloc_sampler_min_bytes() -> u64 {
+    64 * 1024
+}
+
+fn default_malloc_sampler_duration_secs() -> u64 {
+    10
+}
+
+fn default_malloc_sampler_max_duration_secs() -> u64 {
+    120
+}
+
+fn default_malloc_sampler_max_stacks() -> usize {
+    50
+}
+
+/// `[exec_compression]` section: folding of short-job fork/exec/exit floods.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExecCompressionConfig {
+    #[serde(default = "default_exec_compression_enabled")]
+    pub enabled: bool,
+    /// Length of one aggregation window.
+    #[serde(default = "default_exec_compression_window_ms")]
+    pub window_ms: u64,
+    /// Execs of one pattern passed through unchanged per window before folding.
+    #[serde(default = "default_exec_compression_threshold")]
+    pub threshold: u32,
+    /// Upper bound on patterns tracked at once.
+    #[serde(default = "default_exec_compression_max_patterns")]
+    pub max_patterns: usize,
+}
+
+impl Default for ExecCompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_exec_compression_enabled(),
+            window_ms: default_exec_compression_window_ms(),
+            threshold: default_exec_compression_threshold(),
+            max_patterns: default_exec_compression_max_patterns(),
+        }
+    }
+}
+
+fn default_exec_compression_enabled() -> bool {
+    true
+}
+
+fn default_exec_compression_window_ms() -> u64 {
+    5000
+}
+
+fn default_exec_compression_threshold() -> u32 {
+    5
+}
+
+fn default_exec_compression_max_patterns() -> usize {
+    4096
+}
+
+/// `[handlers]` section: which event handlers run, and in what order.
+///
+/// Handler names are `jsonl`, `rules`, `oom-kill` and `mandate-receipt`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct HandlersConfig {
+    /// Handlers that are never registered.
+    #[serde(default)]
+    pub disabled: Vec<String>,
+    /// Handlers listed here run first, in this order; the rest follow in
+    /// registration order.
+    #[serde(default)]
+    pub order: Vec<String>,
+}
+
+/// `[retention]` section: pruning of the insight, alert and feedback logs.
+///
+/// A `max_records` or `max_age_days` of 0 disables that limit.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetentionConfig {
+    #[serde(default = "default_retention_enabled")]
+    pub enabled: bool,
+    /// How often the stores are pruned.
+    #[serde(default = "default_retention_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_retention_insights_max_records")]
+    pub insights_max_records: usize,
+    #[serde(default = "default_retention_insights_max_age_days")]
+    pub insights_max_age_days: u64,
+    #[serde(default = "default_retention_alerts_max_records")]
+    pub alerts_max_records: usize,
+    #[serde(default = "default_retention_alerts_max_age_days")]
+    pub alerts_max_age_days: u64,
+    /// Applies to both the feedback log and the incident DB feedback table.
+    #[serde(default = "default_retention_feedback_max_records")]
+    pub feedback_max_records: usize,
+    #[serde(default = "default_retention_feedback_max_age_days")]
+    pub feedback_max_age_days: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_retention_enabled(),
+            interval_secs: default_retention_interval_secs(),
+            insights_max_records: default_retention_insights_max_records(),
+            insights_max_age_days: default_retention_insights_max_age_days(),
+            alerts_max_records: default_retention_alerts_max_records(),
+            alerts_max_age_days: default_retention_alerts_max_age_days(),
+            feedback_max_records: default_retention_feedback_max_records(),
+            feedback_max_age_days: default_retention_feedback_max_age_days(),
+        }
+    }
+}
+
+fn default_retention_enabled() -> bool {
+    true
+}
+
+fn default_retention_interval_secs() -> u64 {
+    3600
+}
+
+fn default_retention_insights_max_records() -> usize {
+    10_000
+}
+
+fn default_retention_insights_max_age_days() -> u64 {
+    30
+}
+
+fn default_retention_alerts_max_records() -> usize {
+    10_000
+}
+
+fn default_retention_alerts_max_age_days() -> u64 {
+    30
+}
+
+fn default_retention_feedback_max_records() -> usize {
+    10_000
+}
+
+fn default_retention_feedback_max_age_days() -> u64 {
+    90
+}
+
+/// `[plugins]` section: sandboxed WASM detector modules. Needs a build with
+/// the `wasm-plugins` feature.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PluginsConfig {
+    #[serde(default = "default_plugins_enabled")]
+    pub enabled: bool,
+    /// Directory scanned for `*.wasm` modules at startup.
+    #[serde(default = "default_plugins_dir")]
+    pub dir: String,
+    /// Instruction budget for one event; a plugin that runs out traps.
+    #[serde(default = "default_plugins_fuel_per_event")]
+    pub fuel_per_event: u64,
+    /// Upper bound on a plugin's linear memory.
+    #[serde(default = "default_plugins_max_memory_mb")]
+    pub max_memory_mb: usize,
+    /// Alerts beyond this per plugin are dropped.
+    #[serde(default = "default_plugins_max_alerts_per_minute")]
+    pub max_alerts_per_minute: usize,
+    /// A plugin that traps this many times in a row is disabled.
+    #[serde(default = "default_plugins_max_consecutive_traps")]
+    pub max_consecutive_traps: u32,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_plugins_enabled(),
+            dir: default_plugins_dir(),
+            fuel_per_event: default_plugins_fuel_per_event(),
+            max_memory_mb: default_plugins_max_memory_mb(),
+            max_alerts_per_minute: default_plugins_max_alerts_per_minute(),
+            max_consecutive_traps: default_plugins_max_consecutive_traps(),
+        }
+    }
+}
+
+fn default_plugins_enabled() -> bool {
+    false
+}
+
+fn default_plugins_dir() -> String {
+    "/etc/linnix/plugins".to_string()
+}
+
+fn default_plugins_fuel_per_event() -> u64 {
+    1_000_000
+}
+
+fn default_plugins_max_memory_mb() -> usize {
+    16
+}
+
+fn default_plugins_max_alerts_per_minute() -> usize {
+    60
+}
+
+fn default_plugins_max_consecutive_traps() -> u32 {
+    10
+}
+
+/// `[baselines]` section: per-hour-of-day history of what's normal on this
+/// host, used to put current numbers in context for the LLM.
+#[derive(Debug, Deserialize, Clone)]
+pub struct BaselineConfig {
+    #[serde(default = "default_baselines_enabled")]
+    pub enabled: bool,
+    /// JSON file the baselines are saved to and restored from.
+    #[serde(default = "default_baselines_path")]
+    pub path: String,
+    #[serde(default = "default_baselines_sample_interval_secs")]
+    pub sample_interval_secs: u64,
+    #[serde(default = "default_baselines_save_interval_secs")]
+    pub save_interval_secs: u64,
+    /// Roughly how many days of history each hour's baseline reflects;
+    /// older samples decay away.
+    #[serde(default = "default_baselines_window_days")]
+    pub window_days: u32,
+    /// Samples an hour needs before it is reported as a baseline.
+    #[serde(default = "default_baselines_min_samples")]
+    pub min_samples: u64,
+}
+
+impl Default for BaselineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_baselines_enabled(),
+            path: default_baselines_path(),
+            sample_interval_secs: default_baselines_sample_interval_secs(),
+            save_interval_secs: default_baselines_save_interval_secs(),
+            window_days: default_baselines_window_days(),
+            min_samples: default_baselines_min_samples(),
+        }
+    }
+}
+
+fn default_baselines_enabled() -> bool {
+    true
+}
+
+fn default_baselines_path() -> String {
+    "/var/lib/linnix/baselines.json".to_string()
+}
+
+fn default_baselines_sample_interval_secs() -> u64 {
+    60
+}
+
+fn default_baselines_save_interval_secs() -> u64 {
+    300
+}
+
+fn default_baselines_window_days() -> u32 {
+    7
+}
+
+fn default_baselines_min_samples() -> u64 {
+    30
+}
+
+/// `[sessions]` section: attribute processes and alerts to the login session
+/// (user, SSH client) they belong to.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SessionConfig {
+    #[serde(default = "default_sessions_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_sessions_enabled(),
+        }
+    }
+}
+
+fn default_sessions_enabled() -> bool {
+    true
+}
+
+/// `[watch]` section: how reloadable files (rules, config) are watched for
+/// changes. inotify is used when available, polling otherwise.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WatchConfig {
+    /// Quiet period after the last change before reloading, so an editor's
+    /// save (truncate, write, rename) reloads once.
+    #[serde(default = "default_watch_debounce_ms")]
+    pub debounce_ms: u64,
+    /// How often files are checked when inotify is unavailable.
+    #[serde(default = "default_watch_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Poll even when inotify works, e.g. on network filesystems where
+    /// inotify doesn't see remote writes.
+    #[serde(default)]
+    pub force_polling: bool,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            debounce_ms: default_watch_debounce_ms(),
+            poll_interval_ms: default_watch_poll_interval_ms(),
+            force_polling: false,
+        }
+    }
+}
+
+fn default_watch_debounce_ms() -> u64 {
+    500
+}
+
+fn default_watch_poll_interval_ms() -> u64 {
+    2000
+}
+
+/// `[inventory]` section: static host facts (kernel, distro, CPU, memory,
+/// cloud instance type, GPUs) collected at startup and attached to alerts,
+/// insights and exports.
+#[derive(Debug, Deserialize, Clone)]
+pub struct InventoryConfig {
+    #[serde(default = "default_inventory_enabled")]
+    pub enabled: bool,
+    /// Ask the cloud metadata service for the instance type and region. Only
+    /// done when DMI identifies the host as an AWS, GCP or Azure VM.
+    #[serde(default = "default_inventory_cloud_metadata")]
+    pub cloud_metadata: bool,
+    #[serde(default = "default_inventory_metadata_timeout_ms")]
+    pub metadata_timeout_ms: u64,
+}
+
+impl Default for InventoryConfig {
     fn default() -> Self {
         Self {
-            redact_sensitive_data: default_redact_sensitive_data(),
+            enabled: default_inventory_enabled(),
+            cloud_metadata: default_inventory_cloud_metadata(),
+            metadata_timeout_ms: default_inventory_metadata_timeout_ms(),
         }
     }
 }
 
-fn default_redact_sensitive_data() -> bool {
-    false
+fn default_inventory_enabled() -> bool {
+    true
+}
+
+fn default_inventory_cloud_metadata() -> bool {
+    true
+}
+
+fn default_inventory_metadata_timeout_ms() -> u64 {
+    500
 }
 
+/// `[leader_election]` section: with several instances deployed for HA, only
+/// the elected leader sends notifications.
 #[derive(Debug, Deserialize, Clone)]
-pub struct NoiseBudgetConfig {
-    /// Maximum number of alerts allowed per hour
-    #[serde(default = "default_max_alerts_per_hour")]
-    pub max_alerts_per_hour: u32,
-    /// If true, suppress alerts when budget is exceeded (default: true)
-    #[serde(default = "default_noise_budget_enabled")]
+pub struct LeaderElectionConfig {
+    #[serde(default)]
     pub enabled: bool,
+    /// `lock_file` (flock on `lock_path`) or `k8s_lease`.
+    #[serde(default = "default_leader_election_backend")]
+    pub backend: String,
+    #[serde(default = "default_leader_election_lock_path")]
+    pub lock_path: String,
+    #[serde(default = "default_leader_election_lease_name")]
+    pub lease_name: String,
+    /// Defaults to the pod's service account namespace.
+    #[serde(default)]
+    pub lease_namespace: Option<String>,
+    /// Defaults to `$POD_NAME` or the hostname, plus the PID.
+    #[serde(default)]
+    pub identity: Option<String>,
+    /// How long a lease stays valid without renewal before a standby takes it.
+    #[serde(default = "default_leader_election_lease_duration_secs")]
+    pub lease_duration_secs: u64,
+    #[serde(default = "default_leader_election_renew_interval_secs")]
+    pub renew_interval_secs: u64,
 }
 
-impl Default for NoiseBudgetConfig {
+impl Default for LeaderElectionConfig {
     fn default() -> Self {
         Self {
-            max_alerts_per_hour: default_max_alerts_per_hour(),
-            enabled: default_noise_budget_enabled(),
+            enabled: false,
+            backend: default_leader_election_backend(),
+            lock_path: default_leader_election_lock_path(),
+            lease_name: default_leader_election_lease_name(),
+            lease_namespace: None,
+            identity: None,
+            lease_duration_secs: default_leader_election_lease_duration_secs(),
+            renew_interval_secs: default_leader_election_renew_interval_secs(),
         }
     }
 }
 
-fn default_max_alerts_per_hour() -> u32 {
-    10 // Default to 10 alerts per hour to prevent spam
+fn default_leader_election_backend() -> String {
+    "lock_file".to_string()
 }
 
-fn default_noise_budget_enabled() -> bool {
-    true
+fn default_leader_election_lock_path() -> String {
+    "/run/linnix/leader.lock".to_string()
 }
 
-impl Config {
-    /// Load configuration from file. The path can be overridden
-    /// with the `LINNIX_CONFIG` environment variable. If the file
-    /// is missing or fails to parse, defaults are returned.
-    pub fn load() -> Self {
-        let path =
-            std::env::var(ENV_CONFIG_PATH).unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
-        let path = PathBuf::from(path);
-        match fs::read_to_string(&path) {
-            Ok(contents) => match toml::from_str(&contents) {
-                Ok(config) => config,
-                Err(e) => {
-                    log::warn!(
-                        "Failed to parse config file at {}: {}. Using defaults.",
-                        path.display(),
-                        e
-                    );
-                    Config::default()
-                }
-            },
-            Err(_) => Config::default(),
-        }
-    }
+fn default_leader_election_lease_name() -> String {
+    "linnix-cognitod".to_string()
+}
+
+fn default_leader_election_lease_duration_secs() -> u64 {
+    15
+}
+
+fn default_leader_election_renew_interval_secs() -> u64 {
+    5
 }
 
+/// `[quotas]` section: caps on how many events per second one pod (or
+/// container, without Kubernetes metadata) and one namespace may feed into
+/// the pipeline. Excess events are dropped and reported as periodic
+/// "suppressed N events" summaries.
 #[derive(Debug, Deserialize, Clone)]
-#[allow(dead_code)]
-pub struct RuntimeConfig {
-    #[serde(default = "default_offline")]
-    pub offline: bool,
-    #[serde(default = "default_cpu_target_pct")]
-    pub cpu_target_pct: u64,
-    #[serde(default = "default_rss_cap_mb")]
-    pub rss_cap_mb: u64,
-    #[serde(default = "default_events_rate_cap")]
-    pub events_rate_cap: u64,
+pub struct QuotaConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 0 disables the per-pod quota.
+    #[serde(default = "default_quotas_per_pod_events_per_sec")]
+    pub per_pod_events_per_sec: u64,
+    /// 0 disables the per-namespace quota. Needs Kubernetes metadata.
+    #[serde(default)]
+    pub per_namespace_events_per_sec: u64,
+    /// Namespaces never throttled, e.g. `kube-system`.
+    #[serde(default)]
+    pub exempt_namespaces: Vec<String>,
+    /// How often suppressed counts are summarized and logged.
+    #[serde(default = "default_quotas_summary_interval_secs")]
+    pub summary_interval_secs: u64,
 }
 
-impl Default for RuntimeConfig {
+impl Default for QuotaConfig {
     fn default() -> Self {
         Self {
-            offline: default_offline(),
-            cpu_target_pct: default_cpu_target_pct(),
-            rss_cap_mb: default_rss_cap_mb(),
-            events_rate_cap: default_events_rate_cap(),
+            enabled: false,
+            per_pod_events_per_sec: default_quotas_per_pod_events_per_sec(),
+            per_namespace_events_per_sec: 0,
+            exempt_namespaces: Vec::new(),
+            summary_interval_secs: default_quotas_summary_interval_secs(),
         }
     }
 }
 
-fn default_offline() -> bool {
-    true
+fn default_quotas_per_pod_events_per_sec() -> u64 {
+    500
 }
-fn default_cpu_target_pct() -> u64 {
-    25
+
+fn default_quotas_summary_interval_secs() -> u64 {
+    30
 }
-fn default_rss_cap_mb() -> u64 {
-    512
+
+/// `[changes]` section: deploys and config changes reported via
+/// `POST /changes` and mentioned with anomalies that follow them.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChangesConfig {
+    /// How far before an insight or incident a change is mentioned.
+    #[serde(default = "default_changes_window_minutes")]
+    pub window_minutes: u64,
+    /// Changes kept in memory.
+    #[serde(default = "default_changes_capacity")]
+    pub capacity: usize,
 }
-fn default_events_rate_cap() -> u64 {
-    100_000
+
+impl Default for ChangesConfig {
+    fn default() -> Self {
+        Self {
+            window_minutes: default_changes_window_minutes(),
+            capacity: default_changes_capacity(),
+        }
+    }
+}
+
+fn default_changes_window_minutes() -> u64 {
+    30
+}
+
+fn default_changes_capacity() -> usize {
+    500
 }
 
+/// `[aggregator]` section: collect the alerts agents publish over NATS
+/// (`[notifications.nats]`) and notify once, with the host list, when a
+/// rule fires on several hosts at the same time.
 #[derive(Debug, Deserialize, Clone)]
-#[allow(dead_code)]
-pub struct LoggingConfig {
-    #[serde(default = "default_alerts_file")]
-    pub alerts_file: String,
-    #[serde(default = "default_journald")]
-    pub journald: bool,
-    #[serde(default = "default_insights_file")]
-    pub insights_file: String,
+pub struct AggregatorConfig {
     #[serde(default)]
-    pub incident_context_file: Option<String>,
+    pub enabled: bool,
+    /// Seconds a rule's alerts are held while other hosts report it.
+    #[serde(default = "default_aggregator_window_secs")]
+    pub window_secs: u64,
+    /// Hosts a rule must fire on within the window to be sent as one
+    /// cluster-wide alert; below that its alerts go out one by one.
+    #[serde(default = "default_aggregator_min_hosts")]
+    pub min_hosts: usize,
 }
 
-impl Default for LoggingConfig {
+impl Default for AggregatorConfig {
     fn default() -> Self {
         Self {
-            alerts_file: default_alerts_file(),
-            journald: default_journald(),
-            insights_file: default_insights_file(),
-            incident_context_file: None,
+            enabled: false,
+            window_secs: default_aggregator_window_secs(),
+            min_hosts: default_aggregator_min_hosts(),
         }
     }
 }
 
-fn default_alerts_file() -> String {
-    "/var/log/linnix/alerts.ndjson".to_string()
+fn default_aggregator_window_secs() -> u64 {
+    30
 }
-fn default_journald() -> bool {
-    true
+
+fn default_aggregator_min_hosts() -> usize {
+    3
 }
-fn default_insights_file() -> String {
-    "/var/log/linnix/insights.ndjson".to_string()
+
+/// `[network]` section: outbound HTTP(S) proxy and extra trusted CAs for the
+/// notification, LLM and Kubernetes clients. When a proxy is set here the
+/// `HTTP(S)_PROXY` environment variables are ignored.
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct NetworkConfig {
+    /// Proxy for `http://` URLs, e.g. `http://proxy.corp:3128`.
+    #[serde(default)]
+    pub http_proxy: Option<String>,
+    /// Proxy for `https://` URLs.
+    #[serde(default)]
+    pub https_proxy: Option<String>,
+    /// Comma-separated hosts, domains and CIDRs reached directly.
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    /// PEM file of CA certificates trusted in addition to the system roots,
+    /// e.g. for a TLS-intercepting corporate proxy.
+    #[serde(default)]
+    pub ca_bundle: Option<String>,
 }
 
+/// `[evidence]` section: evidence bundles (process tree, top offenders,
+/// recent events, PSI timeline, GPU state) stored with anomaly insights.
 #[derive(Debug, Deserialize, Clone)]
-#[allow(dead_code)]
-pub struct RulesFileConfig {
-    #[serde(default = "default_rules_file")]
-    pub path: String,
+pub struct EvidenceConfig {
+    #[serde(default = "default_evidence_enabled")]
+    pub enabled: bool,
+    /// Most recent offender events kept per bundle.
+    #[serde(default = "default_evidence_max_events")]
+    pub max_events: usize,
+    /// Seconds of PSI history included.
+    #[serde(default = "default_evidence_psi_window_secs")]
+    pub psi_window_secs: u64,
 }
 
-impl Default for RulesFileConfig {
+impl Default for EvidenceConfig {
     fn default() -> Self {
         Self {
-            path: default_rules_file(),
+            enabled: default_evidence_enabled(),
+            max_events: default_evidence_max_events(),
+            psi_window_secs: default_evidence_psi_window_secs(),
         }
     }
 }
 
-fn default_rules_file() -> String {
-    "/etc/linnix/rules.toml".to_string()
+fn default_evidence_enabled() -> bool {
+    true
+}
+
+fn default_evidence_max_events() -> usize {
+    200
 }
 
+fn default_evidence_psi_window_secs() -> u64 {
+    600
+}
+
+/// `[statsd]` section: push agent health counters and gauges to a statsd
+/// or DogStatsD listener (Datadog agent, Telegraf).
 #[derive(Debug, Deserialize, Clone)]
-#[allow(dead_code)]
-pub struct ReasonerConfig {
-    #[serde(default = "default_reasoner_enabled")]
+pub struct StatsdConfig {
+    #[serde(default)]
     pub enabled: bool,
-    #[serde(default = "default_reasoner_endpoint")]
-    pub endpoint: String,
-    #[serde(default = "default_reasoner_timeout")]
-    pub timeout_ms: u64,
+    /// `host:port` of the statsd listener (UDP).
+    #[serde(default = "default_statsd_address")]
+    pub address: String,
+    /// Prepended to every metric name, followed by a dot.
+    #[serde(default = "default_statsd_prefix")]
+    pub prefix: String,
+    #[serde(default = "default_statsd_interval_secs")]
+    pub interval_secs: u64,
+    /// Append DogStatsD `|#tag` sections; plain statsd servers reject them.
+    #[serde(default)]
+    pub dogstatsd: bool,
+    /// Tags (`key:value`) added to every metric in DogStatsD mode.
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-impl Default for ReasonerConfig {
+impl Default for StatsdConfig {
     fn default() -> Self {
         Self {
-            enabled: default_reasoner_enabled(),
-            endpoint: default_reasoner_endpoint(),
-            timeout_ms: default_reasoner_timeout(),
+            enabled: false,
+            address: default_statsd_address(),
+            prefix: default_statsd_prefix(),
+            interval_secs: default_statsd_interval_secs(),
+            dogstatsd: false,
+            tags: Vec::new(),
         }
     }
 }
 
-fn default_reasoner_enabled() -> bool {
-    true
+fn default_statsd_address() -> String {
+    "127.0.0.1:8125".to_string()
 }
 
-fn default_reasoner_endpoint() -> String {
-    "http://127.0.0.1:8087/v1/chat/completions".to_string()
+fn default_statsd_prefix() -> String {
+    "linnix".to_string()
 }
 
-fn default_reasoner_timeout() -> u64 {
-    150
+fn default_statsd_interval_secs() -> u64 {
+    10
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
-#[allow(dead_code)]
-pub struct OutputConfig {
+/// `[kafka]` section: publish alerts, insights and optionally raw events to
+/// Kafka topics. Needs a build with the `kafka` feature.
+#[derive(Debug, Deserialize, Clone)]
+pub struct KafkaConfig {
     #[serde(default)]
-    pub slack: bool,
+    pub enabled: bool,
+    /// Comma-separated `host:port` bootstrap brokers.
+    #[serde(default = "default_kafka_brokers")]
+    pub brokers: String,
+    /// `json` or `avro`.
+    #[serde(default = "default_kafka_encoding")]
+    pub encoding: String,
+    #[serde(default = "default_kafka_alerts_topic")]
+    pub alerts_topic: String,
+    #[serde(default = "default_kafka_insights_topic")]
+    pub insights_topic: String,
+    /// Raw events are only published when this is set.
     #[serde(default)]
-    pub pagerduty: bool,
+    pub events_topic: Option<String>,
+    /// Extra librdkafka properties (`security.protocol`, `sasl.*`,
+    /// `compression.type`, ...).
     #[serde(default)]
-    pub prometheus: bool,
-}
-
-#[derive(Clone)]
-pub struct OfflineGuard {
-    offline: bool,
+    pub properties: BTreeMap<String, String>,
 }
 
-impl OfflineGuard {
-    pub fn new(offline: bool) -> Self {
-        Self { offline }
-    }
-    pub fn is_offline(&self) -> bool {
-        self.offline
-    }
-    /// Returns true if network operations are allowed.
-    #[allow(dead_code)]
-    pub fn check(&self, sink: &str) -> bool {
-        if self.offline {
-            log::warn!("offline mode: blocking {sink} sink");
-            false
-        } else {
-            true
+impl Default for KafkaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            brokers: default_kafka_brokers(),
+            encoding: default_kafka_encoding(),
+            alerts_topic: default_kafka_alerts_topic(),
+            insights_topic: default_kafka_insights_topic(),
+            events_topic: None,
+            properties: BTreeMap::new(),
         }
     }
 }
 
+fn default_kafka_brokers() -> String {
+    "localhost:9092".to_string()
+}
+
+fn default_kafka_encoding() -> String {
+    "json".to_string()
+}
+
+fn default_kafka_alerts_topic() -> String {
+    "linnix.alerts".to_string()
+}
+
+fn default_kafka_insights_topic() -> String {
+    "linnix.insights".to_string()
+}
+
+/// `[update_check]` section: look for newer releases and report one only
+/// after its signed checksums verify.
 #[derive(Debug, Deserialize, Clone)]
-pub struct PsiConfig {
-    /// Duration in seconds of sustained pressure required to trigger attribution
-    #[serde(default = "default_psi_sustained_pressure_seconds")]
-    pub sustained_pressure_seconds: u64,
+pub struct UpdateCheckConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Latest-release endpoint in GitHub releases API format.
+    #[serde(default = "default_update_check_feed_url")]
+    pub feed_url: String,
+    #[serde(default = "default_update_check_interval_secs")]
+    pub interval_secs: u64,
+    /// minisign public key that signs the release `SHA256SUMS` (the key
+    /// line of the `.pub` file). Required when enabled.
+    #[serde(default)]
+    pub public_key: String,
+    /// Release asset to download and check against the signed checksums;
+    /// `{version}` is replaced. Empty verifies only the checksum file.
+    #[serde(default)]
+    pub artifact: String,
 }
 
-impl Default for PsiConfig {
+impl Default for UpdateCheckConfig {
     fn default() -> Self {
         Self {
-            sustained_pressure_seconds: default_psi_sustained_pressure_seconds(),
+            enabled: false,
+            feed_url: default_update_check_feed_url(),
+            interval_secs: default_update_check_interval_secs(),
+            public_key: String::new(),
+            artifact: String::new(),
         }
     }
 }
 
-fn default_psi_sustained_pressure_seconds() -> u64 {
-    15
+fn default_update_check_feed_url() -> String {
+    "https://api.github.com/repos/linnix-os/linnix/releases/latest".to_string()
+}
+
+fn default_update_check_interval_secs() -> u64 {
+    86_400
 }
 
 // =============================================================================
@@ -411,9 +1826,169 @@ fn default_identity_path() -> String {
     "/var/lib/linnix/identity.key".to_string()
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+/// `[probes]` section: how the kernel probes hand events to the daemon.
+#[derive(Debug, Deserialize, Clone)]
 pub struct ProbesConfig {
-    // Configuration for probe settings (reserved for future use)
+    /// "auto" (ring buffer, falling back to perf buffers if it can't be
+    /// created), "ringbuf" or "perf".
+    #[serde(default = "default_probes_event_transport")]
+    pub event_transport: String,
+    /// Size of the shared event ring buffer; rounded up to a power of two.
+    #[serde(default = "default_probes_ringbuf_size_kb")]
+    pub ringbuf_size_kb: u32,
+    /// Runqueue delay from which a SchedLatency event is emitted. 0 disables
+    /// the events; with `offcpu_interval_ms` also 0 the scheduler probes
+    /// stay detached.
+    #[serde(default = "default_probes_sched_latency_threshold_us")]
+    pub sched_latency_threshold_us: u64,
+    /// Interval of the per-process OffCpu summaries. 0 disables off-CPU
+    /// accounting.
+    #[serde(default = "default_probes_offcpu_interval_ms")]
+    pub offcpu_interval_ms: u64,
+    /// Direct reclaim stall from which a DirectReclaim event is emitted. 0
+    /// leaves the vmscan probes detached.
+    #[serde(default = "default_probes_direct_reclaim_min_us")]
+    pub direct_reclaim_min_us: u64,
+    /// Anonymous `mmap` and `brk` growth of a process from which a
+    /// MemGrowth event (with a fresh RSS sample) is emitted. 0 leaves the
+    /// probes detached.
+    #[serde(default = "default_probes_mem_growth_mb")]
+    pub mem_growth_mb: u64,
+    /// Signals whose sending is reported as Signal events, by name
+    /// (`SIGKILL`, `TERM`) or number. Empty leaves the probe detached.
+    #[serde(default = "default_probes_signals")]
+    pub signals: Vec<String>,
+    /// Files and directories whose opens and unlinks (of anything up to
+    /// eight levels below a directory) are reported as FileAccess events.
+    /// Paths that don't exist at startup are skipped; empty leaves the
+    /// probes detached.
+    #[serde(default = "default_probes_watched_paths")]
+    pub watched_paths: Vec<String>,
+    /// Report kernel module loads and BPF program load attempts as
+    /// KernelLoad events.
+    #[serde(default = "default_probes_kernel_loads")]
+    pub kernel_loads: bool,
+    /// Window over which syscalls are counted per process and syscall. 0
+    /// leaves the `sys_enter` probe detached.
+    #[serde(default = "default_probes_syscall_interval_ms")]
+    pub syscall_interval_ms: u64,
+    /// Calls within a window (per CPU) from which a Syscall event is sent.
+    #[serde(default = "default_probes_syscall_min_calls")]
+    pub syscall_min_calls: u64,
+    /// Minimum interval between two PageFault events of a process; faults
+    /// in between are dropped. 0 sends every fault.
+    #[serde(default = "default_probes_page_fault_interval_ms")]
+    pub page_fault_interval_ms: u64,
+    /// Interval over which block I/O bytes are summed per process, device
+    /// and operation before a BlockIo event. 0 sends one per request.
+    #[serde(default = "default_probes_block_io_interval_ms")]
+    pub block_io_interval_ms: u64,
+    /// Interval over which socket bytes are summed per process and
+    /// operation before a Net event. 0 sends one per call.
+    #[serde(default = "default_probes_net_interval_ms")]
+    pub net_interval_ms: u64,
+}
+
+impl ProbesConfig {
+    /// `signals` as the kernel-side bitmask, bit N for signal N. Unknown
+    /// names are logged and skipped.
+    pub fn signal_mask(&self) -> u64 {
+        self.signals.iter().fold(0, |mask, name| {
+            match crate::crashloop::signal_number(name) {
+                Some(sig) => mask | (1u64 << sig),
+                None => {
+                    log::warn!("[cognitod] unknown signal {name:?} in probes.signals; ignoring");
+                    mask
+                }
+            }
+        })
+    }
+}
+
+impl Default for ProbesConfig {
+    fn default() -> Self {
+        Self {
+            event_transport: default_probes_event_transport(),
+            ringbuf_size_kb: default_probes_ringbuf_size_kb(),
+            sched_latency_threshold_us: default_probes_sched_latency_threshold_us(),
+            offcpu_interval_ms: default_probes_offcpu_interval_ms(),
+            direct_reclaim_min_us: default_probes_direct_reclaim_min_us(),
+            mem_growth_mb: default_probes_mem_growth_mb(),
+            signals: default_probes_signals(),
+            watched_paths: default_probes_watched_paths(),
+            kernel_loads: default_probes_kernel_loads(),
+            syscall_interval_ms: default_probes_syscall_interval_ms(),
+            syscall_min_calls: default_probes_syscall_min_calls(),
+            page_fault_interval_ms: default_probes_page_fault_interval_ms(),
+            block_io_interval_ms: default_probes_block_io_interval_ms(),
+            net_interval_ms: default_probes_net_interval_ms(),
+        }
+    }
+}
+
+fn default_probes_event_transport() -> String {
+    "auto".to_string()
+}
+
+fn default_probes_ringbuf_size_kb() -> u32 {
+    4096
+}
+
+fn default_probes_sched_latency_threshold_us() -> u64 {
+    10_000
+}
+
+fn default_probes_offcpu_interval_ms() -> u64 {
+    5_000
+}
+
+fn default_probes_direct_reclaim_min_us() -> u64 {
+    1_000
+}
+
+fn default_probes_mem_growth_mb() -> u64 {
+    64
+}
+
+fn default_probes_signals() -> Vec<String> {
+    vec!["SIGKILL".to_string(), "SIGTERM".to_string()]
+}
+
+fn default_probes_watched_paths() -> Vec<String> {
+    [
+        "/etc/shadow",
+        "/etc/sudoers",
+        "/etc/sudoers.d",
+        "/etc/ssh",
+        "/root/.ssh",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_probes_kernel_loads() -> bool {
+    true
+}
+
+fn default_probes_syscall_interval_ms() -> u64 {
+    1_000
+}
+
+fn default_probes_syscall_min_calls() -> u64 {
+    10_000
+}
+
+fn default_probes_page_fault_interval_ms() -> u64 {
+    50
+}
+
+fn default_probes_block_io_interval_ms() -> u64 {
+    100
+}
+
+fn default_probes_net_interval_ms() -> u64 {
+    100
 }
 
 /// Circuit breaker configuration for automatic remediation based on PSI (Pressure Stall Information)
@@ -834,6 +2409,14 @@ offline = true
         assert!(cfg.api.auth_token.is_none());
     }
 
+    #[test]
+    fn probe_signals_become_a_mask() {
+        assert_eq!(ProbesConfig::default().signal_mask(), (1 << 9) | (1 << 15));
+        let cfg: Config =
+            toml::from_str("[probes]\nsignals = [\"INT\", \"nope\", \"10\"]\n").unwrap();
+        assert_eq!(cfg.probes.signal_mask(), (1 << 2) | (1 << 10));
+    }
+
     #[test]
     fn parse_api_config() {
         let toml = r#"[api]
@@ -0,0 +1,114 @@
+//! TCP connections opened and accepted by processes (Connection events).
+
+use crate::ProcessEvent;
+use linnix_ai_ebpf_common::{EventType, conn_direction, connection_addr_octets};
+use std::fmt;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The process called `connect`.
+    Outbound,
+    /// The process accepted the connection.
+    Inbound,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Outbound => "outbound",
+            Direction::Inbound => "inbound",
+        }
+    }
+}
+
+/// A decoded Connection event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Connection {
+    pub direction: Direction,
+    pub remote: SocketAddr,
+    /// Port the connection was accepted on; `None` for outbound ones.
+    pub local_port: Option<u16>,
+}
+
+impl Connection {
+    pub fn from_event(event: &ProcessEvent) -> Option<Self> {
+        if event.event_type != EventType::Connection as u32 {
+            return None;
+        }
+        let direction = match event.aux2 & 0xffff {
+            conn_direction::OUTBOUND => Direction::Outbound,
+            conn_direction::INBOUND => Direction::Inbound,
+            _ => return None,
+        };
+        let ip = Ipv6Addr::from(connection_addr_octets(event.data, event.data2));
+        let ip = match ip.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(ip),
+        };
+        let local_port = (direction == Direction::Inbound).then_some((event.aux2 >> 16) as u16);
+        Some(Self {
+            direction,
+            remote: SocketAddr::new(ip, event.aux as u16),
+            local_port,
+        })
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.direction, self.local_port) {
+            (Direction::Inbound, Some(port)) => {
+                write!(f, "inbound from {} on port {port}", self.remote)
+            }
+            (Direction::Inbound, None) => write!(f, "inbound from {}", self.remote),
+            (Direction::Outbound, _) => write!(f, "outbound to {}", self.remote),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+    use linnix_ai_ebpf_common::connection_addr_fields;
+
+    fn connection(addr: Ipv6Addr, aux: u32, aux2: u32) -> ProcessEvent {
+        let (data, data2) = connection_addr_fields(addr.octets());
+        ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::Connection as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data,
+            data2,
+            aux,
+            aux2,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn decodes_ipv4_and_ipv6_remotes() {
+        let v4 = "203.0.113.9".parse::<std::net::Ipv4Addr>().unwrap();
+        let out = Connection::from_event(&connection(v4.to_ipv6_mapped(), 443, 0)).unwrap();
+        assert_eq!(out.to_string(), "outbound to 203.0.113.9:443");
+        assert_eq!(out.local_port, None);
+
+        let v6: Ipv6Addr = "2001:db8::7".parse().unwrap();
+        let inbound = conn_direction::INBOUND | (22 << 16);
+        let accepted = Connection::from_event(&connection(v6, 51234, inbound)).unwrap();
+        assert_eq!(accepted.direction, Direction::Inbound);
+        assert_eq!(
+            accepted.to_string(),
+            "inbound from [2001:db8::7]:51234 on port 22"
+        );
+    }
+}
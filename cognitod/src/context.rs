@@ -7,6 +7,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::broadcast;
 
 use crate::ProcessEvent;
+use crate::collectors::gpu::{GpuMemoryTrend, GpuMonitor};
+use crate::collectors::thermal::ThermalSampler;
+use crate::collectors::throttle::{CgroupThrottle, ThrottleSampler};
 use crate::k8s::{K8sContext, K8sMetadata};
 use crate::types::SystemSnapshot;
 use crate::utils::psi::PsiMetrics;
@@ -33,6 +36,9 @@ pub struct ContextStore {
     seq: AtomicU64,
     system_snapshot: Mutex<SystemSnapshot>,
     sys: Mutex<System>,
+    throttle: Mutex<ThrottleSampler>,
+    thermal: Mutex<ThermalSampler>,
+    gpu: Mutex<GpuMonitor>,
     k8s_ctx: Option<Arc<K8sContext>>,
 }
 
@@ -67,7 +73,13 @@ impl ContextStore {
                 psi_memory_full_avg10: 0.0,
                 psi_io_some_avg10: 0.0,
                 psi_io_full_avg10: 0.0,
+                cgroup_throttling: Vec::new(),
+                cpu_thermal: None,
+                gpu_memory: Vec::new(),
             }),
+            throttle: Mutex::new(ThrottleSampler::default()),
+            thermal: Mutex::new(ThermalSampler::default()),
+            gpu: Mutex::new(GpuMonitor::default()),
             sys: Mutex::new(System::new_all()),
             k8s_ctx,
         }
@@ -87,10 +99,11 @@ impl ContextStore {
         let mut metadata: Option<Arc<K8sMetadata>> = None;
 
         if let Some(ctx) = &self.k8s_ctx {
+            ctx.pid_map().observe(&event);
             match event.event_type {
                 0 | 1 => {
                     // Exec or Fork: try to get fresh metadata
-                    if let Some(meta) = ctx.get_metadata_for_pid(event.pid) {
+                    if let Some(meta) = ctx.get_metadata_for_event(&event) {
                         metadata = Some(Arc::new(meta));
                     } else if event.event_type == 1 {
                         // Fork fallback: inherit parent's metadata if we can't find child's yet
@@ -133,7 +146,7 @@ impl ContextStore {
         if metadata.is_none()
             && event.event_type != 2
             && let Some(ctx) = &self.k8s_ctx
-            && let Some(meta) = ctx.get_metadata_for_pid(event.pid)
+            && let Some(meta) = ctx.get_metadata_for_event(&event)
         {
             metadata = Some(Arc::new(meta));
         }
@@ -195,6 +208,33 @@ impl ContextStore {
             .collect()
     }
 
+    /// Arrival time (ns since epoch) and event type of every history entry
+    /// recorded within `[from_ns, to_ns]`.
+    pub fn event_times(&self, from_ns: u64, to_ns: u64) -> Vec<(u64, u32)> {
+        let queue = self.inner.lock().unwrap();
+        queue
+            .iter()
+            .filter(|(t, _, _)| *t >= from_ns && *t <= to_ns)
+            .map(|(t, e, _)| (*t, e.event_type))
+            .collect()
+    }
+
+    /// History entries recorded within `[from_ns, to_ns]`, oldest first,
+    /// with their arrival time (ns since epoch).
+    pub fn events_between(&self, from_ns: u64, to_ns: u64) -> Vec<(u64, ProcessEvent)> {
+        let queue = self.inner.lock().unwrap();
+        queue
+            .iter()
+            .filter(|(t, _, _)| *t >= from_ns && *t <= to_ns)
+            .map(|(t, e, _)| (*t, e.clone()))
+            .collect()
+    }
+
+    /// Arrival time of the oldest event still in history.
+    pub fn oldest_event_ns(&self) -> Option<u64> {
+        self.inner.lock().unwrap().front().map(|(t, _, _)| *t)
+    }
+
     fn prune_locked(queue: &mut VecDeque<ProcessHistoryEntry>, max_age: Duration, max_len: usize) {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -359,13 +399,17 @@ impl ContextStore {
         // PSI (Pressure Stall Information) - measures stall time, not just usage
         // Gracefully degrades to zeros if kernel doesn't support PSI (< 4.20)
         let psi = PsiMetrics::read().unwrap_or_default();
+        let cgroup_throttling = self.sample_throttling();
+        let cpu_thermal = self.thermal.lock().unwrap().sample();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let gpu_memory = self.sample_gpu_memory(timestamp);
 
         let mut snapshot = self.system_snapshot.lock().unwrap();
         *snapshot = SystemSnapshot {
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            timestamp,
             cpu_percent,
             mem_percent,
             load_avg: [load.one as f32, load.five as f32, load.fifteen as f32],
@@ -378,9 +422,57 @@ impl ContextStore {
             psi_memory_full_avg10: psi.memory_full_avg10,
             psi_io_some_avg10: psi.io_some_avg10,
             psi_io_full_avg10: psi.io_full_avg10,
+            cgroup_throttling,
+            cpu_thermal,
+            gpu_memory,
         };
     }
 
+    /// CFS throttling since the previous snapshot, with pod names attached
+    /// when Kubernetes metadata is available.
+    fn sample_throttling(&self) -> Vec<CgroupThrottle> {
+        let mut throttled = self.throttle.lock().unwrap().sample();
+        if let Some(ctx) = &self.k8s_ctx {
+            for entry in &mut throttled {
+                entry.pod = crate::pidmap::container_id_from_cgroup(&entry.cgroup)
+                    .and_then(|id| ctx.get_metadata(id))
+                    .map(|meta| format!("{}/{}", meta.namespace, meta.pod_name));
+            }
+        }
+        throttled
+    }
+
+    /// GPU memory growth per process, named from the live process table
+    /// (or procfs) and attributed to pods when Kubernetes metadata is
+    /// available.
+    fn sample_gpu_memory(&self, now_secs: u64) -> Vec<GpuMemoryTrend> {
+        let mut trends = self.gpu.lock().unwrap().sample(now_secs);
+        if trends.is_empty() {
+            return trends;
+        }
+        let live = self.live.lock().unwrap();
+        for trend in &mut trends {
+            let entry = live.get(&trend.pid);
+            trend.comm = match entry {
+                Some((event, _)) => String::from_utf8_lossy(&event.comm)
+                    .trim_end_matches('\0')
+                    .to_string(),
+                None => std::fs::read_to_string(format!("/proc/{}/comm", trend.pid))
+                    .map(|c| c.trim().to_string())
+                    .unwrap_or_default(),
+            };
+            let meta = entry
+                .and_then(|(_, meta)| meta.as_deref().cloned())
+                .or_else(|| {
+                    self.k8s_ctx
+                        .as_ref()
+                        .and_then(|ctx| ctx.get_metadata_for_pid(trend.pid))
+                });
+            trend.pod = meta.map(|meta| format!("{}/{}", meta.namespace, meta.pod_name));
+        }
+        trends
+    }
+
     pub fn get_system_snapshot(&self) -> SystemSnapshot {
         self.system_snapshot.lock().unwrap().clone()
     }
@@ -511,6 +603,7 @@ mod tests {
             data2: 0,
             aux: 0,
             aux2: 0,
+            cgroup_id: 0,
         };
         ProcessEvent::new(base)
     }
@@ -0,0 +1,255 @@
+//! Cross-host alert correlation for aggregator mode.
+//!
+//! An aggregator subscribes to the alerts its agents publish over NATS and
+//! holds each rule's alerts for a short window. If the rule fired on
+//! `min_hosts` or more hosts in that window, one cluster-wide alert listing
+//! the hosts is sent instead of one per host, so a deploy that sets off
+//! fork storms on 50 nodes pages once. Rules that fired on fewer hosts are
+//! passed through unchanged when the window closes.
+
+use crate::alerts::Alert;
+use crate::config::{AggregatorConfig, NatsConfig};
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// How often windows are checked for closing.
+const DRAIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Alerts of one rule collected during a window.
+struct Group {
+    opened: Instant,
+    hosts: BTreeSet<String>,
+    alerts: Vec<Alert>,
+}
+
+pub struct AlertCorrelator {
+    window: Duration,
+    min_hosts: usize,
+    /// By rule and whether the alerts are resolutions, so a rule clearing
+    /// across the fleet folds too but never into its firing alerts.
+    pending: HashMap<(String, bool), Group>,
+}
+
+impl AlertCorrelator {
+    /// `min_hosts` below 2 is raised to 2: one host is never cluster-wide.
+    pub fn new(window: Duration, min_hosts: usize) -> Self {
+        Self {
+            window,
+            min_hosts: min_hosts.max(2),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Add `alert` to its rule's window, opening one if needed.
+    pub fn ingest(&mut self, alert: Alert, now: Instant) {
+        let key = (alert.rule.clone(), alert.resolved_after_secs.is_some());
+        let group = self.pending.entry(key).or_insert_with(|| Group {
+            opened: now,
+            hosts: BTreeSet::new(),
+            alerts: Vec::new(),
+        });
+        group.hosts.insert(alert.host.clone());
+        group.alerts.push(alert);
+    }
+
+    /// The alerts of every window that has closed by `now`: one per
+    /// cluster-wide rule, the originals for the rest.
+    pub fn drain_ready(&mut self, now: Instant) -> Vec<Alert> {
+        let window = self.window;
+        self.drain(|group| now.duration_since(group.opened) >= window)
+    }
+
+    /// The alerts of every open window, closing them early.
+    pub fn drain_all(&mut self) -> Vec<Alert> {
+        self.drain(|_| true)
+    }
+
+    fn drain(&mut self, closed: impl Fn(&Group) -> bool) -> Vec<Alert> {
+        let keys: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, group)| closed(group))
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut out = Vec::new();
+        for key in keys {
+            let Some(group) = self.pending.remove(&key) else {
+                continue;
+            };
+            if group.hosts.len() >= self.min_hosts {
+                out.push(cluster_alert(group));
+            } else {
+                out.extend(group.alerts);
+            }
+        }
+        out
+    }
+}
+
+/// One alert for a rule that fired on every host of `group`: the highest
+/// severity seen, the hosts in a `hosts` label, and the first host's
+/// message as a sample.
+fn cluster_alert(group: Group) -> Alert {
+    let mut alerts = group.alerts.into_iter();
+    let first = alerts.next().expect("a group holds at least one alert");
+    let (severity, resolved_after_secs) = alerts.fold(
+        (first.severity.clone(), first.resolved_after_secs),
+        |(severity, resolved), alert| {
+            let severity = if alert.severity > severity {
+                alert.severity
+            } else {
+                severity
+            };
+            (severity, resolved.max(alert.resolved_after_secs))
+        },
+    );
+    let hosts: Vec<_> = group.hosts.into_iter().collect();
+    Alert {
+        message: format!(
+            "{} on {} hosts ({}): {}",
+            first.rule,
+            hosts.len(),
+            hosts.join(", "),
+            first.message
+        ),
+        rule: first.rule,
+        severity,
+        host: "cluster".to_string(),
+        resolved_after_secs,
+        labels: [("hosts".to_string(), hosts.join(","))].into(),
+    }
+}
+
+/// Correlate this host's alerts from `local` with the ones agents publish
+/// over NATS and send the result to `tx`, which the notifiers read.
+pub async fn run(
+    config: AggregatorConfig,
+    nats: NatsConfig,
+    mut local: broadcast::Receiver<Alert>,
+    tx: broadcast::Sender<Alert>,
+) {
+    let mut remote = match crate::notifications::subscribe_alerts(&nats).await {
+        Ok(subscriber) => {
+            info!(
+                "[aggregator] correlating alerts from {} over a {}s window",
+                nats.url, config.window_secs
+            );
+            Some(subscriber)
+        }
+        Err(e) => {
+            warn!("[aggregator] not receiving agents' alerts: {e:#}");
+            None
+        }
+    };
+    let mut correlator =
+        AlertCorrelator::new(Duration::from_secs(config.window_secs), config.min_hosts);
+    let mut tick = tokio::time::interval(DRAIN_INTERVAL);
+
+    loop {
+        tokio::select! {
+            alert = local.recv() => match alert {
+                Ok(alert) => correlator.ingest(alert, Instant::now()),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("[aggregator] local alert stream lagged by {n}");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = async {
+                match remote.as_mut() {
+                    Some(subscriber) => subscriber.next().await,
+                    None => std::future::pending().await,
+                }
+            } => match message {
+                Some(message) => match serde_json::from_slice::<Alert>(&message.payload) {
+                    Ok(alert) => correlator.ingest(alert, Instant::now()),
+                    Err(e) => debug!("[aggregator] ignoring bad alert on {}: {e}", message.subject),
+                },
+                None => {
+                    warn!("[aggregator] NATS subscription closed");
+                    remote = None;
+                }
+            },
+            _ = tick.tick() => {
+                for alert in correlator.drain_ready(Instant::now()) {
+                    let _ = tx.send(alert);
+                }
+            }
+        }
+    }
+    for alert in correlator.drain_all() {
+        let _ = tx.send(alert);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+
+    fn alert(rule: &str, host: &str, severity: Severity) -> Alert {
+        Alert {
+            rule: rule.to_string(),
+            severity,
+            message: format!("{rule} fired"),
+            host: host.to_string(),
+            resolved_after_secs: None,
+            labels: Default::default(),
+        }
+    }
+
+    #[test]
+    fn same_rule_on_many_hosts_folds_into_one_alert() {
+        let mut correlator = AlertCorrelator::new(Duration::from_secs(30), 3);
+        let t0 = Instant::now();
+        for i in 0..50 {
+            correlator.ingest(
+                alert("fork_burst", &format!("node-{i:02}"), Severity::Medium),
+                t0,
+            );
+        }
+        correlator.ingest(alert("fork_burst", "node-07", Severity::High), t0);
+        assert!(
+            correlator
+                .drain_ready(t0 + Duration::from_secs(10))
+                .is_empty()
+        );
+
+        let out = correlator.drain_ready(t0 + Duration::from_secs(30));
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].host, "cluster");
+        assert_eq!(out[0].severity, Severity::High);
+        assert_eq!(out[0].labels["hosts"].split(',').count(), 50);
+        assert!(
+            out[0]
+                .message
+                .starts_with("fork_burst on 50 hosts (node-00, node-01, "),
+            "{}",
+            out[0].message
+        );
+        assert!(out[0].message.ends_with("): fork_burst fired"));
+        assert!(correlator.drain_all().is_empty());
+    }
+
+    #[test]
+    fn fewer_hosts_pass_through() {
+        let mut correlator = AlertCorrelator::new(Duration::from_secs(5), 3);
+        let t0 = Instant::now();
+        correlator.ingest(alert("cpu_spin", "a", Severity::Low), t0);
+        correlator.ingest(alert("cpu_spin", "a", Severity::Low), t0);
+        correlator.ingest(alert("cpu_spin", "b", Severity::Low), t0);
+        correlator.ingest(
+            Alert {
+                resolved_after_secs: Some(60),
+                ..alert("cpu_spin", "c", Severity::Low)
+            },
+            t0,
+        );
+
+        let out = correlator.drain_ready(t0 + Duration::from_secs(5));
+        assert_eq!(out.len(), 4);
+        assert!(out.iter().all(|alert| alert.host != "cluster"));
+    }
+}
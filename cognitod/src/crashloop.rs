@@ -0,0 +1,403 @@
+//! Crash-loop tracking for host daemons.
+//!
+//! The bare-metal equivalent of CrashLoopBackOff: a service manager (usually
+//! systemd) restarting the same binary over and over, each instance dying
+//! shortly after exec. Execs are remembered per PID with their parent and
+//! binary path; quick exits are folded into a per-(parent, binary) restart
+//! history that the `daemon_crash_loop` detector counts.
+
+use crate::ProcessEvent;
+use linnix_ai_ebpf_common::{EXIT_STATUS_KNOWN, EventType};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Upper bound on restarts kept per (parent, binary).
+const MAX_RESTARTS_PER_BINARY: usize = 1024;
+
+/// How a process ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ExitStatus {
+    Code(i32),
+    Signal(i32),
+    Unknown,
+}
+
+impl ExitStatus {
+    /// Decode a `wait(2)` status word (`task->exit_code`).
+    pub fn from_wait_status(status: u32) -> Self {
+        match status & 0x7f {
+            0 => ExitStatus::Code(((status >> 8) & 0xff) as i32),
+            sig => ExitStatus::Signal(sig as i32),
+        }
+    }
+
+    /// Status carried by an Exit event, when the probe could read it.
+    pub fn from_event(event: &ProcessEvent) -> Option<Self> {
+        (event.event_type == EventType::Exit as u32 && event.aux2 & EXIT_STATUS_KNOWN != 0)
+            .then(|| Self::from_wait_status(event.aux))
+    }
+
+    /// Killed by a signal or exited non-zero.
+    pub fn is_failure(&self) -> bool {
+        matches!(self, ExitStatus::Signal(_) | ExitStatus::Code(1..))
+    }
+
+    /// Exit status of a process that has exited but not been reaped yet.
+    /// Usually `Unknown`: the parent tends to reap before the event arrives.
+    pub fn read_zombie(pid: u32) -> Self {
+        std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .ok()
+            .and_then(|stat| {
+                // Fields are counted from the last ')' since comm may
+                // contain spaces; exit_code is field 52.
+                let rest = &stat[stat.rfind(')')? + 1..];
+                rest.split_whitespace().nth(49)?.parse::<u32>().ok()
+            })
+            .map_or(ExitStatus::Unknown, Self::from_wait_status)
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitStatus::Code(code) => write!(f, "exit {code}"),
+            ExitStatus::Signal(sig) => match signal_name(*sig) {
+                Some(name) => f.write_str(name),
+                None => write!(f, "signal {sig}"),
+            },
+            ExitStatus::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+pub fn signal_name(sig: i32) -> Option<&'static str> {
+    Some(match sig {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        7 => "SIGBUS",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        24 => "SIGXCPU",
+        25 => "SIGXFSZ",
+        31 => "SIGSYS",
+        _ => return None,
+    })
+}
+
+/// Signal number from a name such as `SIGTERM` or `term`, or a number.
+pub fn signal_number(name: &str) -> Option<i32> {
+    let name = name.trim().to_ascii_uppercase();
+    if let Ok(sig) = name.parse::<i32>() {
+        return (1..64).contains(&sig).then_some(sig);
+    }
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    (1..32).find(|&sig| signal_name(sig).is_some_and(|n| n[3..] == *name))
+}
+
+/// Binary path of a running process, or `None` once it is gone.
+pub fn read_exe(pid: u32) -> Option<String> {
+    let path = std::fs::read_link(format!("/proc/{pid}/exe")).ok()?;
+    let path = path.to_string_lossy();
+    Some(path.trim_end_matches(" (deleted)").to_string())
+}
+
+pub fn read_comm(pid: u32) -> Option<String> {
+    let comm = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(comm.trim_end().to_string())
+}
+
+/// Parent PID from `/proc/{pid}/stat`, or `None` once the process is gone.
+pub fn read_ppid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The comm in parentheses may itself contain spaces and parentheses.
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DaemonKey {
+    pub ppid: u32,
+    pub binary: String,
+}
+
+struct RunningExec {
+    started: Instant,
+    key: DaemonKey,
+    parent: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Restart {
+    pub at: Instant,
+    pub lifetime: Duration,
+    pub status: ExitStatus,
+}
+
+pub struct RestartHistory {
+    /// Parent's comm at the last exec, e.g. `systemd`.
+    pub parent: String,
+    pub restarts: VecDeque<Restart>,
+}
+
+impl RestartHistory {
+    /// Exits within `window` that happened at most `max_lifetime` after exec.
+    pub fn quick_exits(
+        &self,
+        window: Duration,
+        max_lifetime: Duration,
+        now: Instant,
+    ) -> impl Iterator<Item = &Restart> {
+        self.restarts
+            .iter()
+            .rev()
+            .take_while(move |r| now.duration_since(r.at) <= window)
+            .filter(move |r| r.lifetime <= max_lifetime)
+    }
+}
+
+/// "3x SIGSEGV, 2x exit 1", most frequent first.
+pub fn describe_exits<'a>(restarts: impl IntoIterator<Item = &'a Restart>) -> String {
+    describe_statuses(restarts.into_iter().map(|r| r.status))
+}
+
+/// `4x SIGSEGV, 1x exit 1`, most frequent first.
+pub fn describe_statuses(statuses: impl IntoIterator<Item = ExitStatus>) -> String {
+    let mut counts: HashMap<ExitStatus, usize> = HashMap::new();
+    for status in statuses {
+        *counts.entry(status).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+        .iter()
+        .map(|(status, n)| format!("{n}x {status}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub struct CrashLoopTracker {
+    running: HashMap<u32, RunningExec>,
+    history: HashMap<DaemonKey, RestartHistory>,
+    keep: Duration,
+    max_running: usize,
+    max_binaries: usize,
+}
+
+impl CrashLoopTracker {
+    pub fn new(keep: Duration, max_running: usize, max_binaries: usize) -> Self {
+        Self {
+            running: HashMap::new(),
+            history: HashMap::new(),
+            keep,
+            max_running,
+            max_binaries,
+        }
+    }
+
+    pub fn on_exec(&mut self, pid: u32, key: DaemonKey, parent: String, now: Instant) {
+        // Bounded like the pid map: if exits were lost, stop tracking new
+        // execs rather than grow.
+        if self.running.len() >= self.max_running && !self.running.contains_key(&pid) {
+            return;
+        }
+        self.running.insert(
+            pid,
+            RunningExec {
+                started: now,
+                key,
+                parent,
+            },
+        );
+    }
+
+    /// Record the exit of `pid` if its exec was seen. `status` is only
+    /// called for tracked processes.
+    pub fn on_exit(
+        &mut self,
+        pid: u32,
+        status: impl FnOnce() -> ExitStatus,
+        now: Instant,
+    ) -> Option<DaemonKey> {
+        let exec = self.running.remove(&pid)?;
+        let keep = self.keep;
+        if self.history.len() >= self.max_binaries && !self.history.contains_key(&exec.key) {
+            // Make room by dropping binaries that haven't exited recently.
+            self.history.retain(|_, h| {
+                h.restarts
+                    .back()
+                    .is_some_and(|r| now.duration_since(r.at) <= keep)
+            });
+            if self.history.len() >= self.max_binaries {
+                return None;
+            }
+        }
+        let history = self
+            .history
+            .entry(exec.key.clone())
+            .or_insert_with(|| RestartHistory {
+                parent: String::new(),
+                restarts: VecDeque::new(),
+            });
+        history.parent = exec.parent;
+        history.restarts.push_back(Restart {
+            at: now,
+            lifetime: now.saturating_duration_since(exec.started),
+            status: status(),
+        });
+        while history.restarts.len() > MAX_RESTARTS_PER_BINARY
+            || history
+                .restarts
+                .front()
+                .is_some_and(|r| now.duration_since(r.at) > keep)
+        {
+            history.restarts.pop_front();
+        }
+        Some(exec.key)
+    }
+
+    pub fn history(&self, key: &DaemonKey) -> Option<&RestartHistory> {
+        self.history.get(key)
+    }
+
+    pub fn histories(&self) -> impl Iterator<Item = (&DaemonKey, &RestartHistory)> {
+        self.history.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.running.len() + self.history.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_signal_names() {
+        assert_eq!(signal_number("SIGKILL"), Some(9));
+        assert_eq!(signal_number("term"), Some(15));
+        assert_eq!(signal_number("34"), Some(34));
+        assert_eq!(signal_number("SIGFOO"), None);
+        assert_eq!(signal_number("64"), None);
+    }
+
+    #[test]
+    fn reads_parent_pid() {
+        assert_eq!(
+            read_ppid(std::process::id()),
+            Some(std::os::unix::process::parent_id())
+        );
+    }
+
+    #[test]
+    fn decodes_wait_status() {
+        assert_eq!(ExitStatus::from_wait_status(0), ExitStatus::Code(0));
+        assert_eq!(ExitStatus::from_wait_status(1 << 8), ExitStatus::Code(1));
+        assert_eq!(ExitStatus::from_wait_status(11), ExitStatus::Signal(11));
+        // Core dump flag set.
+        assert_eq!(
+            ExitStatus::from_wait_status(0x80 | 6),
+            ExitStatus::Signal(6)
+        );
+        assert_eq!(ExitStatus::Signal(11).to_string(), "SIGSEGV");
+        assert_eq!(ExitStatus::Signal(40).to_string(), "signal 40");
+        assert_eq!(ExitStatus::Code(3).to_string(), "exit 3");
+        assert!(ExitStatus::Signal(9).is_failure());
+        assert!(ExitStatus::Code(2).is_failure());
+        assert!(!ExitStatus::Code(0).is_failure());
+        assert!(!ExitStatus::Unknown.is_failure());
+    }
+
+    #[test]
+    fn reads_status_from_exit_events() {
+        use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+        let mut wire = ProcessEventWire {
+            pid: 1,
+            ppid: 0,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::Exit as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 9,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        assert_eq!(ExitStatus::from_event(&ProcessEvent::new(wire)), None);
+        wire.aux2 = EXIT_STATUS_KNOWN;
+        assert_eq!(
+            ExitStatus::from_event(&ProcessEvent::new(wire)),
+            Some(ExitStatus::Signal(9))
+        );
+    }
+
+    #[test]
+    fn own_process_has_exe_and_comm() {
+        let pid = std::process::id();
+        assert!(read_exe(pid).is_some_and(|p| p.starts_with('/')));
+        assert!(read_comm(pid).is_some());
+    }
+
+    #[tokio::test]
+    async fn keeps_restart_history_per_parent_and_binary() {
+        tokio::time::pause();
+        let mut tracker = CrashLoopTracker::new(Duration::from_secs(60), 16, 16);
+        let key = DaemonKey {
+            ppid: 1,
+            binary: "/usr/sbin/flaky".into(),
+        };
+        let statuses = [
+            ExitStatus::Signal(11),
+            ExitStatus::Code(1),
+            ExitStatus::Signal(11),
+        ];
+        for (pid, status) in (100..).zip(statuses) {
+            tracker.on_exec(pid, key.clone(), "systemd".into(), Instant::now());
+            tokio::time::advance(Duration::from_secs(2)).await;
+            assert_eq!(
+                tracker.on_exit(pid, || status, Instant::now()).as_ref(),
+                Some(&key)
+            );
+        }
+        // Exit of an untracked process.
+        assert_eq!(
+            tracker.on_exit(999, || unreachable!(), Instant::now()),
+            None
+        );
+
+        let history = tracker.history(&key).unwrap();
+        assert_eq!(history.parent, "systemd");
+        let now = Instant::now();
+        let quick: Vec<_> = history
+            .quick_exits(Duration::from_secs(60), Duration::from_secs(5), now)
+            .collect();
+        assert_eq!(quick.len(), 3);
+        assert_eq!(describe_exits(quick), "2x SIGSEGV, 1x exit 1");
+        assert_eq!(
+            history
+                .quick_exits(Duration::from_secs(60), Duration::from_secs(1), now)
+                .count(),
+            0
+        );
+    }
+}
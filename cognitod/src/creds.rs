@@ -0,0 +1,168 @@
+//! Effective UID, GID and capability changes (Creds events).
+
+use crate::ProcessEvent;
+use linnix_ai_ebpf_common::EventType;
+use std::fmt;
+
+/// Capability names by bit, as in `linux/capability.h`.
+const CAPABILITIES: [&str; 41] = [
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETPCAP",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_ADMIN",
+    "CAP_NET_RAW",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_MKNOD",
+    "CAP_LEASE",
+    "CAP_AUDIT_WRITE",
+    "CAP_AUDIT_CONTROL",
+    "CAP_SETFCAP",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_AUDIT_READ",
+    "CAP_PERFMON",
+    "CAP_BPF",
+    "CAP_CHECKPOINT_RESTORE",
+];
+
+/// Names of the capabilities set in `caps`, lowest bit first.
+pub fn capability_names(caps: u64) -> Vec<String> {
+    (0..64)
+        .filter(|bit| caps & (1u64 << bit) != 0)
+        .map(|bit| match CAPABILITIES.get(bit) {
+            Some(name) => name.to_string(),
+            None => format!("cap {bit}"),
+        })
+        .collect()
+}
+
+/// A decoded Creds event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CredChange {
+    pub old_uid: u32,
+    pub new_uid: u32,
+    pub old_gid: u32,
+    pub new_gid: u32,
+    pub caps_gained: u64,
+    pub caps_lost: u64,
+}
+
+impl CredChange {
+    pub fn from_event(event: &ProcessEvent) -> Option<Self> {
+        if event.event_type != EventType::Creds as u32 {
+            return None;
+        }
+        Some(Self {
+            old_uid: event.uid,
+            new_uid: event.aux,
+            old_gid: event.gid,
+            new_gid: event.aux2,
+            caps_gained: event.data & !event.data2,
+            caps_lost: event.data2 & !event.data,
+        })
+    }
+
+    /// Became root, joined the root group or gained capabilities.
+    pub fn is_escalation(&self) -> bool {
+        (self.new_uid == 0 && self.old_uid != 0)
+            || (self.new_gid == 0 && self.old_gid != 0)
+            || self.caps_gained != 0
+    }
+}
+
+impl fmt::Display for CredChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.old_uid != self.new_uid {
+            parts.push(format!("euid {} -> {}", self.old_uid, self.new_uid));
+        }
+        if self.old_gid != self.new_gid {
+            parts.push(format!("egid {} -> {}", self.old_gid, self.new_gid));
+        }
+        if self.caps_gained != 0 {
+            parts.push(format!(
+                "gained {}",
+                capability_names(self.caps_gained).join(", ")
+            ));
+        }
+        if self.caps_lost != 0 {
+            parts.push(format!(
+                "dropped {} capabilities",
+                self.caps_lost.count_ones()
+            ));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn creds(old: (u32, u32, u64), new: (u32, u32, u64)) -> ProcessEvent {
+        ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid: old.0,
+            gid: old.1,
+            event_type: EventType::Creds as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: new.2,
+            data2: old.2,
+            aux: new.0,
+            aux2: new.1,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn decodes_escalations_and_drops() {
+        let full = (1u64 << 41) - 1;
+        let setuid_root = CredChange::from_event(&creds((1000, 1000, 0), (0, 1000, full))).unwrap();
+        assert!(setuid_root.is_escalation());
+        assert!(setuid_root.to_string().starts_with(
+            "euid 1000 -> 0, gained CAP_CHOWN, CAP_DAC_OVERRIDE, CAP_DAC_READ_SEARCH"
+        ));
+
+        let raw = 1u64 << 13;
+        let ping = CredChange::from_event(&creds((1000, 1000, 0), (1000, 1000, raw))).unwrap();
+        assert_eq!(ping.to_string(), "gained CAP_NET_RAW");
+
+        let drop_root = CredChange::from_event(&creds((0, 0, full), (33, 33, 0))).unwrap();
+        assert!(!drop_root.is_escalation());
+        assert_eq!(
+            drop_root.to_string(),
+            "euid 0 -> 33, egid 0 -> 33, dropped 41 capabilities"
+        );
+    }
+}
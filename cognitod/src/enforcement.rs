@@ -28,6 +28,9 @@ pub enum ActionStatus {
     Rejected,
     Expired,
     Executed,
+    /// Approved, but held back because the agent runs in observe mode or
+    /// is a standby.
+    Observed,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -204,6 +207,21 @@ impl EnforcementQueue {
         Ok(())
     }
 
+    /// Close an approved action without executing it (observe mode, or a
+    /// standby that must leave enforcement to the leader).
+    pub async fn observe(&self, id: &str) -> Result<(), String> {
+        let mut actions = self.actions.write().await;
+        let action = actions.get_mut(id).ok_or("action not found")?;
+
+        if action.status != ActionStatus::Approved {
+            return Err(format!("not approved: {:?}", action.status));
+        }
+
+        action.status = ActionStatus::Observed;
+        log::info!("[enforcement] observed {id} (not executed)");
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn get_pending(&self) -> Vec<EnforcementAction> {
         let now = current_epoch_secs();
@@ -352,4 +370,34 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("not pending"));
     }
+
+    #[tokio::test]
+    async fn observed_actions_are_closed_without_execution() {
+        // Given: A kill action approved while the agent runs in observe mode
+        let queue = EnforcementQueue::new(300);
+        let action_id = queue
+            .propose(
+                ActionType::KillProcess {
+                    pid: 123,
+                    signal: 9,
+                },
+                "runaway build".to_string(),
+                "circuit_breaker".to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+        queue
+            .approve(&action_id, "alice".to_string())
+            .await
+            .unwrap();
+
+        // When: The executor records it instead of killing
+        queue.observe(&action_id).await.unwrap();
+
+        // Then: It is no longer approved and cannot be executed later
+        let action = queue.get_by_id(&action_id).await.unwrap();
+        assert_eq!(action.status, ActionStatus::Observed);
+        assert!(queue.complete(&action_id).await.is_err());
+    }
 }
@@ -0,0 +1,412 @@
+//! Evidence bundles for anomaly insights.
+//!
+//! A one-line summary in Slack is hard to act on. When an anomaly insight is
+//! recorded, this assembles what an operator would have looked at: the
+//! process tree around the top offenders, the top CPU and memory consumers,
+//! recent events of those processes, the PSI timeline leading up to it and,
+//! on GPU hosts, `nvidia-smi` state. The bundle is stored with the insight
+//! and served at `/insights/{id}/evidence`.
+
+use crate::collectors::gpu::nvidia_smi;
+use crate::config::EvidenceConfig;
+use crate::context::ContextStore;
+use crate::inventory::HostFacts;
+use crate::schema::{Insight, InsightReason};
+use crate::utils::psi::PsiMetrics;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often PSI is sampled for the timeline.
+const PSI_INTERVAL: Duration = Duration::from_secs(10);
+/// Processes listed as top CPU / memory offenders.
+const TOP_OFFENDERS: usize = 5;
+/// Ancestors walked above each offender.
+const MAX_TREE_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceBundle {
+    pub insight_id: String,
+    pub reason_code: String,
+    pub collected_at: u64,
+    /// `ps`-style tree of the offenders and their ancestors; offenders are
+    /// marked with `*`.
+    pub process_tree: String,
+    pub top_cpu: Vec<Offender>,
+    pub top_rss: Vec<Offender>,
+    /// Recent events of the offenders and their children, oldest first.
+    pub events: Vec<EvidenceEvent>,
+    /// PSI avg10 samples covering `psi_window_secs`, oldest first.
+    pub psi: Vec<PsiSample>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpus: Option<Vec<GpuState>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Offender {
+    pub pid: u32,
+    pub comm: String,
+    pub percent: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceEvent {
+    pub ts_ns: u64,
+    pub pid: u32,
+    pub ppid: u32,
+    pub comm: String,
+    pub event_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_percent: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mem_percent: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argv: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PsiSample {
+    pub timestamp: u64,
+    pub cpu_some: f32,
+    pub memory_some: f32,
+    pub memory_full: f32,
+    pub io_some: f32,
+    pub io_full: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GpuState {
+    pub index: u32,
+    pub name: String,
+    pub utilization_pct: Option<f32>,
+    pub memory_used_mib: Option<u64>,
+    pub memory_total_mib: Option<u64>,
+    pub temperature_c: Option<f32>,
+}
+
+pub struct EvidenceCollector {
+    config: EvidenceConfig,
+    context: Arc<ContextStore>,
+    inventory: Option<Arc<HostFacts>>,
+    psi: Mutex<VecDeque<PsiSample>>,
+}
+
+impl EvidenceCollector {
+    pub fn new(
+        config: EvidenceConfig,
+        context: Arc<ContextStore>,
+        inventory: Option<Arc<HostFacts>>,
+    ) -> Self {
+        Self {
+            config,
+            context,
+            inventory,
+            psi: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Sample PSI into the timeline until the process exits.
+    pub async fn run(self: Arc<Self>) {
+        let mut tick = tokio::time::interval(PSI_INTERVAL);
+        loop {
+            tick.tick().await;
+            if let Ok(psi) = PsiMetrics::read() {
+                self.push_psi(PsiSample {
+                    timestamp: now_secs(),
+                    cpu_some: psi.cpu_some_avg10,
+                    memory_some: psi.memory_some_avg10,
+                    memory_full: psi.memory_full_avg10,
+                    io_some: psi.io_some_avg10,
+                    io_full: psi.io_full_avg10,
+                });
+            }
+        }
+    }
+
+    fn push_psi(&self, sample: PsiSample) {
+        let mut psi = self.psi.lock().unwrap();
+        psi.push_back(sample);
+        let cutoff = sample.timestamp.saturating_sub(self.config.psi_window_secs);
+        while psi.front().is_some_and(|s| s.timestamp < cutoff) {
+            psi.pop_front();
+        }
+    }
+
+    /// Bundle for an anomaly insight; `None` for `normal` ones.
+    pub fn collect(&self, insight: &Insight) -> Option<EvidenceBundle> {
+        if insight.reason_code == InsightReason::Normal {
+            return None;
+        }
+        let offender = |p: crate::context::ProcessMemorySummary| Offender {
+            pid: p.pid,
+            comm: p.comm,
+            percent: p.mem_percent,
+        };
+        let top_cpu: Vec<Offender> = self
+            .context
+            .top_cpu_processes(TOP_OFFENDERS)
+            .into_iter()
+            .map(offender)
+            .collect();
+        let top_rss: Vec<Offender> = self
+            .context
+            .top_rss_processes(TOP_OFFENDERS)
+            .into_iter()
+            .map(offender)
+            .collect();
+
+        let mut suspects: BTreeSet<u32> = top_cpu.iter().chain(&top_rss).map(|o| o.pid).collect();
+        if let Some(pid) = insight.primary_process.as_deref().and_then(pid_in) {
+            suspects.insert(pid);
+        }
+
+        let process_tree = render_tree(&suspects, |pid| {
+            match self.context.get_process_by_pid(pid) {
+                Some(e) => Some((e.ppid, comm_of(&e))),
+                None => read_proc_parent(pid),
+            }
+        });
+
+        let mut events: Vec<EvidenceEvent> = self
+            .context
+            .get_recent()
+            .into_iter()
+            .filter(|e| suspects.contains(&e.pid) || suspects.contains(&e.ppid))
+            .map(|e| EvidenceEvent {
+                ts_ns: e.ts_ns,
+                pid: e.pid,
+                ppid: e.ppid,
+                comm: comm_of(&e),
+                event_type: crate::script::event_type_name(e.event_type).to_string(),
+                cpu_percent: e.cpu_percent(),
+                mem_percent: e.mem_percent(),
+                argv: e.argv.clone(),
+            })
+            .collect();
+        events.sort_by_key(|e| e.ts_ns);
+        let skip = events.len().saturating_sub(self.config.max_events);
+        events.drain(..skip);
+
+        let gpu_relevant = self.inventory.as_ref().is_some_and(|f| !f.gpus.is_empty())
+            || insight.summary.to_ascii_lowercase().contains("gpu");
+
+        Some(EvidenceBundle {
+            insight_id: insight.id.clone(),
+            reason_code: insight.reason_code.as_str().to_string(),
+            collected_at: now_secs(),
+            process_tree,
+            top_cpu,
+            top_rss,
+            events,
+            psi: self.psi.lock().unwrap().iter().copied().collect(),
+            gpus: gpu_relevant.then(query_gpus).flatten(),
+        })
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn comm_of(event: &crate::ProcessEvent) -> String {
+    String::from_utf8_lossy(&event.comm)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+/// PID named in a `primary_process` string such as `java (pid 4242)`.
+fn pid_in(process: &str) -> Option<u32> {
+    let (_, rest) = process.split_once("pid")?;
+    let digits: String = rest
+        .trim_start_matches([' ', ':', '='])
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Parent and comm of a process the context doesn't track (e.g. started
+/// before the daemon).
+fn read_proc_parent(pid: u32) -> Option<(u32, String)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let comm = stat.get(open + 1..close)?.to_string();
+    let ppid = stat
+        .get(close + 1..)?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()?;
+    Some((ppid, comm))
+}
+
+/// Indented tree of `suspects` and their ancestors:
+///
+/// ```text
+/// 1 systemd
+///   812 containerd-shim
+///     4242 java *
+/// ```
+fn render_tree(suspects: &BTreeSet<u32>, lookup: impl Fn(u32) -> Option<(u32, String)>) -> String {
+    let mut nodes: BTreeMap<u32, (u32, String)> = BTreeMap::new();
+    for &pid in suspects {
+        let mut current = pid;
+        for _ in 0..MAX_TREE_DEPTH {
+            if current == 0 || nodes.contains_key(&current) {
+                break;
+            }
+            let Some((ppid, comm)) = lookup(current) else {
+                break;
+            };
+            nodes.insert(current, (ppid, comm));
+            current = ppid;
+        }
+    }
+    let mut children: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+    for (&pid, (ppid, _)) in &nodes {
+        let parent = if nodes.contains_key(ppid) { *ppid } else { 0 };
+        children.entry(parent).or_default().push(pid);
+    }
+
+    fn walk(
+        pid: u32,
+        depth: usize,
+        nodes: &BTreeMap<u32, (u32, String)>,
+        children: &BTreeMap<u32, Vec<u32>>,
+        suspects: &BTreeSet<u32>,
+        out: &mut String,
+    ) {
+        let (_, comm) = &nodes[&pid];
+        let mark = if suspects.contains(&pid) { " *" } else { "" };
+        out.push_str(&format!(
+            "{:indent$}{pid} {comm}{mark}\n",
+            "",
+            indent = depth * 2
+        ));
+        for &child in children.get(&pid).into_iter().flatten() {
+            walk(child, depth + 1, nodes, children, suspects, out);
+        }
+    }
+    let mut out = String::new();
+    for &root in children.get(&0).into_iter().flatten() {
+        walk(root, 0, &nodes, &children, suspects, &mut out);
+    }
+    out
+}
+
+/// Per-GPU state from `nvidia-smi`, or `None` if it isn't installed or
+/// doesn't answer in time.
+fn query_gpus() -> Option<Vec<GpuState>> {
+    let csv = nvidia_smi(&[
+        "--query-gpu=index,name,utilization.gpu,memory.used,memory.total,temperature.gpu",
+        "--format=csv,noheader,nounits",
+    ])?;
+    Some(parse_nvidia_smi(&csv))
+}
+
+fn parse_nvidia_smi(csv: &str) -> Vec<GpuState> {
+    csv.lines()
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split(',').map(str::trim).collect();
+            if cols.len() < 6 {
+                return None;
+            }
+            Some(GpuState {
+                index: cols[0].parse().ok()?,
+                name: cols[1].to_string(),
+                utilization_pct: cols[2].parse().ok(),
+                memory_used_mib: cols[3].parse().ok(),
+                memory_total_mib: cols[4].parse().ok(),
+                temperature_c: cols[5].parse().ok(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_joins_offenders_under_shared_ancestors() {
+        let procs: BTreeMap<u32, (u32, &str)> = BTreeMap::from([
+            (1, (0, "systemd")),
+            (812, (1, "containerd-shim")),
+            (4242, (812, "java")),
+            (4300, (812, "python3")),
+            (900, (1, "sshd")),
+        ]);
+        let suspects = BTreeSet::from([4242, 4300]);
+        let tree = render_tree(&suspects, |pid| {
+            procs
+                .get(&pid)
+                .map(|(ppid, comm)| (*ppid, comm.to_string()))
+        });
+        assert_eq!(
+            tree,
+            "1 systemd\n  812 containerd-shim\n    4242 java *\n    4300 python3 *\n"
+        );
+    }
+
+    #[test]
+    fn parses_nvidia_smi_and_primary_process_pids() {
+        let gpus = parse_nvidia_smi(
+            "0, NVIDIA A10G, 97, 22100, 23028, 71\n1, NVIDIA A10G, [N/A], 3, 23028, 40\n",
+        );
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].name, "NVIDIA A10G");
+        assert_eq!(gpus[0].memory_used_mib, Some(22100));
+        assert_eq!(gpus[1].utilization_pct, None);
+
+        assert_eq!(pid_in("java (pid 4242)"), Some(4242));
+        assert_eq!(pid_in("pid=17"), Some(17));
+        assert_eq!(pid_in("python3"), None);
+    }
+
+    #[test]
+    fn normal_insights_get_no_bundle_and_psi_window_is_trimmed() {
+        let context = Arc::new(ContextStore::new(Duration::from_secs(60), 100, None));
+        let collector = EvidenceCollector::new(
+            EvidenceConfig {
+                psi_window_secs: 60,
+                ..Default::default()
+            },
+            context,
+            None,
+        );
+        for timestamp in [1000, 1030, 1070, 1100] {
+            collector.push_psi(PsiSample {
+                timestamp,
+                cpu_some: 1.0,
+                memory_some: 0.0,
+                memory_full: 0.0,
+                io_some: 0.0,
+                io_full: 0.0,
+            });
+        }
+        let mut insight = Insight {
+            reason_code: InsightReason::Normal,
+            summary: "fine".into(),
+            confidence: 0.9,
+            id: "abc".into(),
+            top_pods: Vec::new(),
+            suggested_next_step: String::new(),
+            primary_process: None,
+            k8s: None,
+        };
+        assert!(collector.collect(&insight).is_none());
+
+        insight.reason_code = InsightReason::CpuSpin;
+        let bundle = collector.collect(&insight).unwrap();
+        assert_eq!(bundle.insight_id, "abc");
+        assert_eq!(bundle.reason_code, "cpu_spin");
+        let times: Vec<_> = bundle.psi.iter().map(|s| s.timestamp).collect();
+        assert_eq!(times, [1070, 1100]);
+        assert!(bundle.gpus.is_none());
+    }
+}
@@ -0,0 +1,417 @@
+//! Exec lineage compression for short-job floods.
+//!
+//! A build farm or a crash-looping cron job produces thousands of nearly
+//! identical fork → exec → exit triples. Past a per-pattern threshold this
+//! compressor folds them away and emits one [`ExecAggregate`] per window with
+//! counts, keeping the first exec of the pattern as a full exemplar.
+//!
+//! A pattern is `(ppid, comm, argv hash)`. A fork of a flooding parent is
+//! held until the child's exec shows whether it belongs to the pattern: it
+//! folds along with a matching exec and is forwarded (ahead of the exec)
+//! otherwise. Rule evaluation still sees every event; only downstream
+//! storage is compressed.
+
+use crate::ProcessEvent;
+use crate::config::ExecCompressionConfig;
+use crate::runtime::argv::read_cmdline;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const EVENT_EXEC: u32 = 0;
+const EVENT_FORK: u32 = 1;
+const EVENT_EXIT: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PatternKey {
+    ppid: u32,
+    comm: [u8; 16],
+    argv_hash: u64,
+}
+
+struct Pattern {
+    opened: Instant,
+    execs: u32,
+    folded_execs: u64,
+    folded_forks: u64,
+    folded_exits: u64,
+    first_ts_ns: u64,
+    last_ts_ns: u64,
+    exemplar: ProcessEvent,
+}
+
+/// What to forward downstream for one observed event.
+#[derive(Debug)]
+pub struct Verdict {
+    /// A held fork of the event's process, to forward before the event.
+    pub released: Option<ProcessEvent>,
+    /// Whether the event itself is forwarded.
+    pub forward: bool,
+}
+
+impl Verdict {
+    fn forward() -> Self {
+        Self {
+            released: None,
+            forward: true,
+        }
+    }
+
+    fn fold() -> Self {
+        Self {
+            released: None,
+            forward: false,
+        }
+    }
+}
+
+/// Folded fork/exec/exit triples for one pattern over one window.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "exec_aggregate")]
+pub struct ExecAggregate {
+    pub ppid: u32,
+    pub comm: String,
+    pub argv_hash: u64,
+    pub execs: u64,
+    pub forks: u64,
+    pub exits: u64,
+    pub first_ts_ns: u64,
+    pub last_ts_ns: u64,
+    pub window_ms: u64,
+    /// First exec of the pattern in this window, forwarded unchanged.
+    pub exemplar: ProcessEvent,
+}
+
+pub struct ExecCompressor {
+    cfg: ExecCompressionConfig,
+    patterns: HashMap<PatternKey, Pattern>,
+    /// Children of folded patterns, so their exits fold too.
+    folded_pids: HashMap<u32, PatternKey>,
+    /// Patterns past the threshold, per parent.
+    flooding: HashMap<u32, u32>,
+    /// Forks of flooding parents whose child hasn't exec'd yet, by child.
+    held_forks: HashMap<u32, (Instant, ProcessEvent)>,
+}
+
+impl ExecCompressor {
+    pub fn new(cfg: ExecCompressionConfig) -> Self {
+        Self {
+            cfg,
+            patterns: HashMap::new(),
+            folded_pids: HashMap::new(),
+            flooding: HashMap::new(),
+            held_forks: HashMap::new(),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_millis(self.cfg.window_ms)
+    }
+
+    /// Feed one event and learn what to forward downstream: folded events
+    /// reach the aggregate instead.
+    pub fn observe(&mut self, event: &ProcessEvent, argv_hash: u64, now: Instant) -> Verdict {
+        match event.event_type {
+            EVENT_EXEC => {
+                let held = self.held_forks.remove(&event.pid).map(|(_, fork)| fork);
+                let forward = self.observe_exec(event, argv_hash, now);
+                match (held, forward) {
+                    (Some(_), false) => {
+                        // The fork folds with its child's exec.
+                        if let Some(key) = self.folded_pids.get(&event.pid)
+                            && let Some(pattern) = self.patterns.get_mut(key)
+                        {
+                            pattern.folded_forks += 1;
+                        }
+                        Verdict::fold()
+                    }
+                    (released, forward) => Verdict { released, forward },
+                }
+            }
+            EVENT_FORK => self.observe_fork(event, now),
+            EVENT_EXIT => match self.held_forks.remove(&event.pid) {
+                // Exited without exec'ing: nothing to fold it into.
+                Some((_, fork)) => Verdict {
+                    released: Some(fork),
+                    forward: true,
+                },
+                None => self.observe_exit(event),
+            },
+            _ => Verdict::forward(),
+        }
+    }
+
+    fn observe_exec(&mut self, event: &ProcessEvent, argv_hash: u64, now: Instant) -> bool {
+        let key = PatternKey {
+            ppid: event.ppid,
+            comm: event.comm,
+            argv_hash,
+        };
+        if !self.patterns.contains_key(&key) && self.patterns.len() >= self.cfg.max_patterns {
+            // Table full: don't track, just forward.
+            return true;
+        }
+        let pattern = self.patterns.entry(key.clone()).or_insert_with(|| Pattern {
+            opened: now,
+            execs: 0,
+            folded_execs: 0,
+            folded_forks: 0,
+            folded_exits: 0,
+            first_ts_ns: event.ts_ns,
+            last_ts_ns: event.ts_ns,
+            exemplar: event.clone(),
+        });
+        pattern.execs = pattern.execs.saturating_add(1);
+        pattern.last_ts_ns = event.ts_ns;
+        if pattern.execs <= self.cfg.threshold {
+            return true;
+        }
+        if pattern.execs == self.cfg.threshold.saturating_add(1) {
+            *self.flooding.entry(key.ppid).or_default() += 1;
+        }
+        pattern.folded_execs += 1;
+        self.folded_pids.insert(event.pid, key);
+        false
+    }
+
+    fn observe_fork(&mut self, event: &ProcessEvent, now: Instant) -> Verdict {
+        // The child's exec hasn't happened yet; hold the fork if its parent
+        // is currently flooding, until the exec shows whether it folds.
+        if !self.flooding.contains_key(&event.ppid)
+            || self.held_forks.len() >= self.cfg.max_patterns
+        {
+            return Verdict::forward();
+        }
+        self.held_forks.insert(event.pid, (now, event.clone()));
+        Verdict::fold()
+    }
+
+    fn observe_exit(&mut self, event: &ProcessEvent) -> Verdict {
+        let Some(key) = self.folded_pids.remove(&event.pid) else {
+            return Verdict::forward();
+        };
+        match self.patterns.get_mut(&key) {
+            Some(pattern) => {
+                pattern.folded_exits += 1;
+                Verdict::fold()
+            }
+            // Window already closed; the aggregate is gone, so keep the exit.
+            None => Verdict::forward(),
+        }
+    }
+
+    /// Forks held for longer than a window without their child exec'ing
+    /// or exiting (e.g. forked workers), to forward now.
+    pub fn release_stale(&mut self, now: Instant) -> Vec<ProcessEvent> {
+        let window = self.window();
+        let stale: Vec<u32> = self
+            .held_forks
+            .iter()
+            .filter(|(_, (held, _))| now.duration_since(*held) >= window)
+            .map(|(pid, _)| *pid)
+            .collect();
+        stale
+            .into_iter()
+            .filter_map(|pid| self.held_forks.remove(&pid))
+            .map(|(_, fork)| fork)
+            .collect()
+    }
+
+    /// Close every window that has elapsed, returning aggregates for the
+    /// ones that actually folded something.
+    pub fn drain_closed(&mut self, now: Instant) -> Vec<ExecAggregate> {
+        let window = self.window();
+        let closed: Vec<PatternKey> = self
+            .patterns
+            .iter()
+            .filter(|(_, p)| now.duration_since(p.opened) >= window)
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        let mut out = Vec::new();
+        for key in closed {
+            let Some(p) = self.patterns.remove(&key) else {
+                continue;
+            };
+            if p.execs > self.cfg.threshold
+                && let Some(count) = self.flooding.get_mut(&key.ppid)
+            {
+                *count -= 1;
+                if *count == 0 {
+                    self.flooding.remove(&key.ppid);
+                }
+            }
+            if p.folded_execs + p.folded_forks + p.folded_exits > 0 {
+                out.push(ExecAggregate {
+                    ppid: key.ppid,
+                    comm: String::from_utf8_lossy(&key.comm)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                    argv_hash: key.argv_hash,
+                    execs: p.folded_execs + p.execs.min(self.cfg.threshold) as u64,
+                    forks: p.folded_forks,
+                    exits: p.folded_exits,
+                    first_ts_ns: p.first_ts_ns,
+                    last_ts_ns: p.last_ts_ns,
+                    window_ms: self.cfg.window_ms,
+                    exemplar: p.exemplar,
+                });
+            }
+        }
+        let patterns = &self.patterns;
+        self.folded_pids.retain(|_, k| patterns.contains_key(k));
+        out
+    }
+
+    pub fn tracked_patterns(&self) -> usize {
+        self.patterns.len()
+    }
+}
+
+/// Hash of an exec's argv, from the event when enrichment captured it and
+/// from `/proc/<pid>/cmdline` otherwise; 0 if the process has already
+/// exited (common for short jobs).
+pub fn argv_hash(event: &ProcessEvent) -> u64 {
+    let hash = |argv: &[String]| {
+        let mut hasher = DefaultHasher::new();
+        argv.hash(&mut hasher);
+        hasher.finish()
+    };
+    match &event.argv {
+        Some(argv) => hash(argv),
+        None => read_cmdline(Path::new("/proc"), event.pid).map_or(0, |argv| hash(&argv)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn event(event_type: u32, pid: u32, ppid: u32, comm: &str) -> ProcessEvent {
+        let mut c = [0u8; 16];
+        c[..comm.len()].copy_from_slice(comm.as_bytes());
+        ProcessEvent::new(ProcessEventWire {
+            pid,
+            ppid,
+            uid: 0,
+            gid: 0,
+            event_type,
+            ts_ns: pid as u64,
+            seq: 0,
+            comm: c,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    fn compressor() -> ExecCompressor {
+        ExecCompressor::new(ExecCompressionConfig {
+            threshold: 2,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn flood_is_folded_after_threshold_with_one_exemplar() {
+        let mut c = compressor();
+        let t0 = Instant::now();
+        let mut forwarded = 0;
+        for pid in 100..150 {
+            for (kind, comm, hash) in [
+                (EVENT_FORK, "make", 0),
+                (EVENT_EXEC, "cc1", 7),
+                (EVENT_EXIT, "cc1", 0),
+            ] {
+                let verdict = c.observe(&event(kind, pid, 10, comm), hash, t0);
+                assert!(verdict.released.is_none());
+                forwarded += verdict.forward as u32;
+            }
+        }
+        // Two full triples pass, plus the third fork (folding starts at the
+        // third exec).
+        assert_eq!(forwarded, 7);
+
+        assert!(c.drain_closed(t0 + Duration::from_secs(1)).is_empty());
+        let aggs = c.drain_closed(t0 + Duration::from_secs(5));
+        assert_eq!(aggs.len(), 1);
+        let a = &aggs[0];
+        assert_eq!((a.ppid, a.comm.as_str(), a.argv_hash), (10, "cc1", 7));
+        assert_eq!(a.execs, 50);
+        assert_eq!(a.forks, 47);
+        assert_eq!(a.exits, 48);
+        assert_eq!(a.exemplar.pid, 100);
+        assert_eq!(c.tracked_patterns(), 0);
+    }
+
+    #[test]
+    fn distinct_argv_is_not_folded() {
+        let mut c = compressor();
+        let t0 = Instant::now();
+        for pid in 0..10 {
+            assert!(
+                c.observe(&event(EVENT_EXEC, pid, 10, "sh"), pid as u64, t0)
+                    .forward
+            );
+        }
+        assert!(c.drain_closed(t0 + Duration::from_secs(5)).is_empty());
+    }
+
+    #[test]
+    fn pattern_table_is_bounded() {
+        let mut c = ExecCompressor::new(ExecCompressionConfig {
+            threshold: 0,
+            max_patterns: 2,
+            ..Default::default()
+        });
+        let t0 = Instant::now();
+        assert!(!c.observe(&event(EVENT_EXEC, 1, 10, "a"), 1, t0).forward);
+        assert!(!c.observe(&event(EVENT_EXEC, 2, 10, "a"), 2, t0).forward);
+        assert!(c.observe(&event(EVENT_EXEC, 3, 10, "a"), 3, t0).forward);
+        assert_eq!(c.tracked_patterns(), 2);
+    }
+
+    #[test]
+    fn children_of_a_flooding_parent_that_do_something_else_are_kept() {
+        let mut c = compressor();
+        let t0 = Instant::now();
+        for pid in 100..103 {
+            c.observe(&event(EVENT_EXEC, pid, 10, "cc1"), 7, t0);
+        }
+
+        // The fork is held, then forwarded ahead of a non-matching exec.
+        let verdict = c.observe(&event(EVENT_FORK, 200, 10, "make"), 0, t0);
+        assert!(verdict.released.is_none() && !verdict.forward);
+        let verdict = c.observe(&event(EVENT_EXEC, 200, 10, "ld"), 9, t0);
+        assert_eq!(verdict.released.map(|e| e.pid), Some(200));
+        assert!(verdict.forward);
+
+        // A child that exits without exec'ing keeps its fork too, as does
+        // one that does neither within the window.
+        c.observe(&event(EVENT_FORK, 201, 10, "make"), 0, t0);
+        let verdict = c.observe(&event(EVENT_EXIT, 201, 10, "make"), 0, t0);
+        assert_eq!(verdict.released.map(|e| e.pid), Some(201));
+        assert!(verdict.forward);
+        c.observe(&event(EVENT_FORK, 202, 10, "make"), 0, t0);
+        assert!(c.release_stale(t0 + Duration::from_secs(1)).is_empty());
+        let stale = c.release_stale(t0 + Duration::from_secs(5));
+        assert_eq!(stale.iter().map(|e| e.pid).collect::<Vec<_>>(), [202]);
+
+        // Once the pattern's window closes, the parent's forks pass again.
+        let aggs = c.drain_closed(t0 + Duration::from_secs(5));
+        assert_eq!((aggs[0].execs, aggs[0].forks), (3, 0));
+        assert!(
+            c.observe(&event(EVENT_FORK, 203, 10, "make"), 0, t0)
+                .forward
+        );
+    }
+}
@@ -0,0 +1,504 @@
+//! Expression detectors (`detector: expr` in the rules file).
+//!
+//! A one-line condition over the event's fields and the rule's window
+//! counters, for detectors that don't need a whole script:
+//!
+//! ```yaml
+//! - name: make_fork_storm
+//!   detector: expr
+//!   expr: forks_per_sec(ppid) > 20 && comm =~ "make"
+//! ```
+//!
+//! The event's fields (those scripts see as `event`) are plain variables;
+//! `a =~ "re"`, also spelled `a matches "re"`, tests a regex. Counters are
+//! functions: `forks()`, `execs()`, `exits()` and `short_jobs()` over the
+//! window, `forks(ppid)` and `forks(pid)` for the forks of the event's
+//! parent or process, and a `_per_sec` variant of each.
+//!
+//! Expressions are compiled with Rhai as a single expression, so there are
+//! no statements or loops, and unknown variables or counters fail the rules
+//! load instead of every evaluation.
+
+use crate::ProcessEvent;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rhai::{AST, Dynamic, Engine, EvalAltResult, ImmutableString, Scope};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Rhai operations one evaluation may perform.
+pub const MAX_OPERATIONS: u64 = 10_000;
+
+/// Compiled `=~` patterns kept for reuse.
+const MAX_CACHED_PATTERNS: usize = 256;
+
+/// Fields of every event, as `script::event_map` sets them.
+const EVENT_FIELDS: [&str; 15] = [
+    "type",
+    "event_type",
+    "pid",
+    "ppid",
+    "uid",
+    "gid",
+    "comm",
+    "ts_ns",
+    "data",
+    "data2",
+    "aux",
+    "aux2",
+    "cgroup_id",
+    "cpu_pct",
+    "mem_pct",
+];
+
+/// Event fields only some event types have, with the value they read as on
+/// the others, so expressions can name them on any event.
+const OPTIONAL_FIELDS: [(&str, OptionalDefault); 13] = [
+    ("direction", OptionalDefault::Str),
+    ("remote_ip", OptionalDefault::Str),
+    ("remote_port", OptionalDefault::Int),
+    ("signal", OptionalDefault::Str),
+    ("target_pid", OptionalDefault::Int),
+    ("new_uid", OptionalDefault::Int),
+    ("new_gid", OptionalDefault::Int),
+    ("caps_gained", OptionalDefault::Int),
+    ("escalation", OptionalDefault::Bool),
+    ("file_op", OptionalDefault::Str),
+    ("path", OptionalDefault::Str),
+    ("load_kind", OptionalDefault::Str),
+    ("load_name", OptionalDefault::Str),
+];
+
+#[derive(Clone, Copy)]
+enum OptionalDefault {
+    Str,
+    Int,
+    Bool,
+}
+
+impl OptionalDefault {
+    fn value(self) -> Dynamic {
+        match self {
+            OptionalDefault::Str => "".into(),
+            OptionalDefault::Int => (-1i64).into(),
+            OptionalDefault::Bool => false.into(),
+        }
+    }
+}
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4096)
+        .set_strict_variables(true)
+        .disable_symbol("eval");
+    engine
+        .register_custom_operator("matches", 90)
+        .expect("`matches` is a valid operator name");
+    engine.register_fn("matches", matches);
+    engine
+});
+
+static PATTERNS: Lazy<RwLock<HashMap<String, Regex>>> = Lazy::new(Default::default);
+
+fn matches(text: ImmutableString, pattern: ImmutableString) -> Result<bool, Box<EvalAltResult>> {
+    if let Some(re) = PATTERNS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(pattern.as_str())
+    {
+        return Ok(re.is_match(&text));
+    }
+    let re = Regex::new(&pattern).map_err(|e| e.to_string())?;
+    let matched = re.is_match(&text);
+    let mut patterns = PATTERNS.write().unwrap_or_else(|e| e.into_inner());
+    if patterns.len() < MAX_CACHED_PATTERNS {
+        patterns.insert(pattern.to_string(), re);
+    }
+    Ok(matched)
+}
+
+/// What a counter function counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    Forks,
+    Execs,
+    /// Exits of processes whose exec was seen.
+    Exits,
+    /// Of those, processes that lived at most 1s.
+    ShortJobs,
+}
+
+/// Whose events a counter counts: the host's, or the forks of the event's
+/// process or parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Host,
+    Pid,
+    Ppid,
+}
+
+/// A counter function an expression calls, e.g. `forks_per_sec(ppid)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aggregate {
+    pub counter: Counter,
+    pub key: Key,
+    pub per_sec: bool,
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.counter {
+            Counter::Forks => "forks",
+            Counter::Execs => "execs",
+            Counter::Exits => "exits",
+            Counter::ShortJobs => "short_jobs",
+        };
+        let key = match self.key {
+            Key::Host => "",
+            Key::Pid => "pid",
+            Key::Ppid => "ppid",
+        };
+        let rate = if self.per_sec { "_per_sec" } else { "" };
+        write!(f, "{name}{rate}({key})")
+    }
+}
+
+fn counter_named(name: &str) -> Option<(Counter, bool)> {
+    let (base, per_sec) = match name.strip_suffix("_per_sec") {
+        Some(base) => (base, true),
+        None => (name, false),
+    };
+    let counter = match base {
+        "forks" => Counter::Forks,
+        "execs" => Counter::Execs,
+        "exits" => Counter::Exits,
+        "short_jobs" => Counter::ShortJobs,
+        _ => return None,
+    };
+    Some((counter, per_sec))
+}
+
+/// Scope variable holding the value of aggregate `index`.
+fn aggregate_var(index: usize) -> String {
+    format!("agg_{index}_")
+}
+
+pub struct Expr {
+    source: String,
+    ast: AST,
+    aggregates: Vec<Aggregate>,
+    errors: AtomicU64,
+}
+
+/// Shows the source only, so two compilations of the same expression
+/// compare equal (rules reloads tell changed rules apart by their Debug
+/// form).
+impl fmt::Debug for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Expr")
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Expr {
+    pub fn compile(source: &str) -> anyhow::Result<Self> {
+        let (rewritten, aggregates, patterns) =
+            rewrite(source).map_err(|e| anyhow::anyhow!("expression '{source}': {e}"))?;
+        for pattern in patterns {
+            Regex::new(&pattern)
+                .map_err(|e| anyhow::anyhow!("expression '{source}': invalid regex: {e}"))?;
+        }
+        // Plain variables rather than constants, which the optimizer would
+        // fold into the expression.
+        let mut scope = Scope::new();
+        let names = EVENT_FIELDS
+            .into_iter()
+            .chain(OPTIONAL_FIELDS.map(|(name, _)| name));
+        for name in names {
+            scope.push(name, ());
+        }
+        for index in 0..aggregates.len() {
+            scope.push(aggregate_var(index), ());
+        }
+        let ast = ENGINE
+            .compile_expression_with_scope(&scope, &rewritten)
+            .map_err(|e| anyhow::anyhow!("expression '{source}' does not compile: {e}"))?;
+        Ok(Self {
+            source: source.to_string(),
+            ast,
+            aggregates,
+            errors: AtomicU64::new(0),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Counters the expression calls, in the order `eval` reports them.
+    pub fn aggregates(&self) -> &[Aggregate] {
+        &self.aggregates
+    }
+
+    /// Evaluate against one event, with `count` giving each counter's
+    /// count over the last `window_seconds`. `Some` with the counters'
+    /// values when the expression holds. Runtime errors, including a
+    /// result that isn't a bool, count as not holding and are logged on
+    /// the first occurrence and every 1000th after.
+    pub fn eval(
+        &self,
+        rule: &str,
+        event: &ProcessEvent,
+        window_seconds: u64,
+        mut count: impl FnMut(Aggregate) -> u64,
+    ) -> Option<Vec<(Aggregate, f64)>> {
+        let mut scope = Scope::new();
+        let fields = crate::script::event_map(event);
+        for (name, default) in OPTIONAL_FIELDS {
+            if !fields.contains_key(name) {
+                scope.push_constant_dynamic(name, default.value());
+            }
+        }
+        for (name, value) in fields {
+            scope.push_constant_dynamic(name.as_str(), value);
+        }
+        let mut values = Vec::with_capacity(self.aggregates.len());
+        for (index, aggregate) in self.aggregates.iter().enumerate() {
+            let n = count(*aggregate);
+            let value = if aggregate.per_sec {
+                let rate = n as f64 / window_seconds.max(1) as f64;
+                scope.push_constant(aggregate_var(index), rate);
+                rate
+            } else {
+                scope.push_constant(aggregate_var(index), n as i64);
+                n as f64
+            };
+            values.push((*aggregate, value));
+        }
+        match ENGINE.eval_ast_with_scope::<bool>(&mut scope, &self.ast) {
+            Ok(true) => Some(values),
+            Ok(false) => None,
+            Err(e) => {
+                let n = self.errors.fetch_add(1, Ordering::Relaxed) + 1;
+                #[allow(clippy::manual_is_multiple_of)]
+                // is_multiple_of not stable in nightly-2024-12-10
+                if n == 1 || n % 1000 == 0 {
+                    log::warn!("[rules] expr rule '{rule}' failed ({n} errors so far): {e}");
+                }
+                None
+            }
+        }
+    }
+
+    /// Runtime errors since the rule was loaded.
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+/// Rewrite `source` for Rhai: `=~` becomes the `matches` operator and each
+/// counter call a variable holding its value. Also returns the counters
+/// called and the regexes given as literals, to check at load time.
+fn rewrite(source: &str) -> Result<(String, Vec<Aggregate>, Vec<String>), String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut aggregates = Vec::new();
+    let mut patterns = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' || c == '`' || c == '\'' {
+            let end = literal_end(&chars, i)?;
+            out.extend(&chars[i..end]);
+            i = end;
+        } else if c == '=' && chars.get(i + 1) == Some(&'~') {
+            out.push_str(" matches ");
+            i += 2;
+            patterns.extend(pattern_at(&chars, i)?);
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            let Some((counter, per_sec)) = counter_named(&name) else {
+                if name == "matches" {
+                    patterns.extend(pattern_at(&chars, i)?);
+                }
+                out.push_str(&name);
+                continue;
+            };
+            let (arg, end) = call_arg(&chars, i).ok_or_else(|| format!("{name} needs ()"))?;
+            let key = match arg.as_str() {
+                "" => Key::Host,
+                "pid" if counter == Counter::Forks => Key::Pid,
+                "ppid" if counter == Counter::Forks => Key::Ppid,
+                _ => return Err(format!("{name}({arg}) is not a counter")),
+            };
+            let aggregate = Aggregate {
+                counter,
+                key,
+                per_sec,
+            };
+            let index = match aggregates.iter().position(|a| *a == aggregate) {
+                Some(index) => index,
+                None => {
+                    aggregates.push(aggregate);
+                    aggregates.len() - 1
+                }
+            };
+            out.push_str(&aggregate_var(index));
+            i = end;
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    Ok((out, aggregates, patterns))
+}
+
+/// Index just past the string or character literal starting at `start`.
+fn literal_end(chars: &[char], start: usize) -> Result<usize, String> {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => return Ok(i + 1),
+            _ => i += 1,
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+/// Contents of the string literal after `from`, if the next token is one.
+fn pattern_at(chars: &[char], from: usize) -> Result<Option<String>, String> {
+    let start = from
+        + chars[from..]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count();
+    if chars.get(start) != Some(&'"') {
+        return Ok(None);
+    }
+    let end = literal_end(chars, start)?;
+    let mut pattern = String::new();
+    let mut inner = chars[start + 1..end - 1].iter();
+    while let Some(&c) = inner.next() {
+        match (c, inner.clone().next()) {
+            ('\\', Some(&next)) if next == '\\' || next == '"' => {
+                pattern.push(next);
+                inner.next();
+            }
+            _ => pattern.push(c),
+        }
+    }
+    Ok(Some(pattern))
+}
+
+/// The identifier (possibly empty) in the `( ... )` after `from`, and the
+/// index past the closing parenthesis.
+fn call_arg(chars: &[char], from: usize) -> Option<(String, usize)> {
+    let skip = |i: usize| i + chars[i..].iter().take_while(|c| c.is_whitespace()).count();
+    let mut i = skip(from);
+    if chars.get(i) != Some(&'(') {
+        return None;
+    }
+    i = skip(i + 1);
+    let start = i;
+    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+        i += 1;
+    }
+    let arg = chars[start..i].iter().collect();
+    i = skip(i);
+    (chars.get(i) == Some(&')')).then_some((arg, i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn fork(ppid: u32, comm: &str) -> ProcessEvent {
+        let mut c = [0u8; 16];
+        c[..comm.len()].copy_from_slice(comm.as_bytes());
+        ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid,
+            uid: 1000,
+            gid: 0,
+            event_type: 1,
+            ts_ns: 0,
+            seq: 0,
+            comm: c,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn expressions_see_fields_and_counters() {
+        let expr = Expr::compile(r#"forks_per_sec(ppid) > 20 && comm =~ "^ma?ke$""#).unwrap();
+        let parent_forks = |forks: u64| {
+            move |a: Aggregate| {
+                assert_eq!((a.counter, a.key), (Counter::Forks, Key::Ppid));
+                forks
+            }
+        };
+        let values = expr.eval("t", &fork(7, "make"), 10, parent_forks(210));
+        assert_eq!(values.unwrap()[0].1, 21.0);
+        assert_eq!(expr.aggregates()[0].to_string(), "forks_per_sec(ppid)");
+        assert!(
+            expr.eval("t", &fork(7, "make"), 10, parent_forks(200))
+                .is_none()
+        );
+        assert!(
+            expr.eval("t", &fork(7, "cargo"), 10, parent_forks(400))
+                .is_none()
+        );
+
+        let expr = Expr::compile(
+            r#"type == "fork" && uid >= 1000 && forks() - forks(pid) >= 5 && path == """#,
+        )
+        .unwrap();
+        assert_eq!(expr.aggregates().len(), 2);
+        let count = |a: Aggregate| if a.key == Key::Host { 6 } else { 1 };
+        assert!(expr.eval("t", &fork(7, "sh"), 10, count).is_some());
+        assert_eq!(expr.errors(), 0);
+        for name in crate::script::event_map(&fork(7, "sh")).keys() {
+            assert!(EVENT_FIELDS.contains(&name.as_str()), "{name}");
+        }
+
+        let not_bool = Expr::compile("pid + 1").unwrap();
+        assert!(not_bool.eval("t", &fork(7, "sh"), 10, |_| 0).is_none());
+        assert_eq!(not_bool.errors(), 1);
+    }
+
+    #[test]
+    fn mistakes_fail_compilation() {
+        for bad in [
+            "commm == \"make\"",
+            "execs(ppid) > 1",
+            "forks > 1",
+            "comm =~ \"(\"",
+            "let x = 1; x > 0",
+            "while true {}",
+            "comm == \"make",
+        ] {
+            assert!(Expr::compile(bad).is_err(), "{bad}");
+        }
+        assert!(Expr::compile("comm matches \"^ma\" || new_uid == 0").is_ok());
+    }
+}
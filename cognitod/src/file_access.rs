@@ -0,0 +1,103 @@
+//! Opens and unlinks of watched paths (FileAccess events).
+
+use crate::ProcessEvent;
+use linnix_ai_ebpf_common::{EventType, FileAccessOp};
+use std::fmt;
+
+/// Name of an operation as used in rules and the API.
+pub fn op_name(op: FileAccessOp) -> &'static str {
+    match op {
+        FileAccessOp::Open => "open",
+        FileAccessOp::OpenWrite => "open_write",
+        FileAccessOp::Unlink => "unlink",
+    }
+}
+
+/// A decoded FileAccess event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileAccess {
+    pub op: FileAccessOp,
+    pub inode: u64,
+    /// Full path, when the listener could still read it from the probes.
+    pub path: Option<String>,
+}
+
+impl FileAccess {
+    pub fn from_event(event: &ProcessEvent) -> Option<Self> {
+        if event.event_type != EventType::FileAccess as u32 {
+            return None;
+        }
+        let op = match event.aux {
+            0 => FileAccessOp::Open,
+            1 => FileAccessOp::OpenWrite,
+            2 => FileAccessOp::Unlink,
+            _ => return None,
+        };
+        Some(Self {
+            op,
+            inode: event.data,
+            path: event.path.clone(),
+        })
+    }
+
+    /// Opened for writing or unlinked.
+    pub fn is_modification(&self) -> bool {
+        self.op != FileAccessOp::Open
+    }
+}
+
+impl fmt::Display for FileAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match self.op {
+            FileAccessOp::Open => "opened",
+            FileAccessOp::OpenWrite => "opened for writing",
+            FileAccessOp::Unlink => "unlinked",
+        };
+        match &self.path {
+            Some(path) => write!(f, "{verb} {path}"),
+            None => write!(f, "{verb} inode {}", self.inode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn access(op: u32, path: Option<&str>) -> ProcessEvent {
+        let mut event = ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::FileAccess as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 1234,
+            data2: 7,
+            aux: op,
+            aux2: 0,
+            cgroup_id: 0,
+        });
+        event.path = path.map(str::to_string);
+        event
+    }
+
+    #[test]
+    fn decodes_operations_and_paths() {
+        let write = FileAccess::from_event(&access(1, Some("/etc/shadow"))).unwrap();
+        assert!(write.is_modification());
+        assert_eq!(write.to_string(), "opened for writing /etc/shadow");
+
+        let read = FileAccess::from_event(&access(0, None)).unwrap();
+        assert!(!read.is_modification());
+        assert_eq!(read.to_string(), "opened inode 1234");
+
+        assert!(FileAccess::from_event(&access(9, None)).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! Workload fingerprint: which programs normally run on this host.
+//!
+//! Servers run the same handful of binaries under the same parents day in,
+//! day out. During a learning period every exec is recorded as a
+//! (comm, argv hash, parent comm) pattern; afterwards the
+//! `new_binary_executed` and `unusual_parent` detectors flag execs that fall
+//! outside what was learned, e.g. a miner dropped into /tmp or a shell spawned
+//! by a web server. Patterns are remembered once seen, so each novelty is
+//! reported once.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// What was normal for one binary during learning.
+#[derive(Debug, Default)]
+struct BinaryProfile {
+    parents: HashSet<String>,
+    /// Hashes of the command lines seen; 0 (unknown argv) is never stored.
+    argv: HashSet<u64>,
+}
+
+/// How an exec differs from the fingerprint. All false for a known pattern.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Novelty {
+    /// The binary has never been seen.
+    pub new_binary: bool,
+    /// Known binary, but not with this command line.
+    pub new_argv: bool,
+    /// Known binary under a parent it was never started by.
+    pub unusual_parent: bool,
+}
+
+pub struct WorkloadFingerprint {
+    started: Instant,
+    binaries: HashMap<String, BinaryProfile>,
+    patterns: usize,
+    max_patterns: usize,
+}
+
+impl WorkloadFingerprint {
+    pub fn new(now: Instant, max_patterns: usize) -> Self {
+        Self {
+            started: now,
+            binaries: HashMap::new(),
+            patterns: 0,
+            max_patterns,
+        }
+    }
+
+    /// How long execs have been recorded.
+    pub fn age(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.started)
+    }
+
+    /// Number of distinct binaries, parents and command lines recorded.
+    pub fn len(&self) -> usize {
+        self.patterns
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns == 0
+    }
+
+    /// Compare an exec with the fingerprint, then add it. An empty `parent`
+    /// (it exited before it could be read) is never unusual. Once the
+    /// pattern cap is reached nothing new is added, but novelty is still
+    /// reported.
+    pub fn observe(&mut self, comm: &str, argv_hash: u64, parent: &str) -> Novelty {
+        let Some(profile) = self.binaries.get(comm) else {
+            if self.patterns < self.max_patterns {
+                let mut profile = BinaryProfile::default();
+                if !parent.is_empty() {
+                    profile.parents.insert(parent.to_string());
+                }
+                if argv_hash != 0 {
+                    profile.argv.insert(argv_hash);
+                }
+                self.patterns += 1 + profile.parents.len() + profile.argv.len();
+                self.binaries.insert(comm.to_string(), profile);
+            }
+            return Novelty {
+                new_binary: true,
+                ..Novelty::default()
+            };
+        };
+        let novelty = Novelty {
+            new_binary: false,
+            new_argv: argv_hash != 0 && !profile.argv.contains(&argv_hash),
+            unusual_parent: !parent.is_empty() && !profile.parents.contains(parent),
+        };
+        if self.patterns < self.max_patterns
+            && let Some(profile) = self.binaries.get_mut(comm)
+        {
+            if novelty.unusual_parent {
+                profile.parents.insert(parent.to_string());
+                self.patterns += 1;
+            }
+            if novelty.new_argv {
+                profile.argv.insert(argv_hash);
+                self.patterns += 1;
+            }
+        }
+        novelty
+    }
+
+    /// Parents `comm` has been seen under, sorted, for alert messages.
+    pub fn parents_of(&self, comm: &str) -> Vec<&str> {
+        let mut parents: Vec<&str> = self
+            .binaries
+            .get(comm)
+            .map(|p| p.parents.iter().map(String::as_str).collect())
+            .unwrap_or_default();
+        parents.sort_unstable();
+        parents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_novelty_once() {
+        let mut fp = WorkloadFingerprint::new(Instant::now(), 100);
+
+        assert!(fp.observe("nginx", 1, "systemd").new_binary);
+        assert_eq!(fp.observe("nginx", 1, "systemd"), Novelty::default());
+        assert_eq!(fp.len(), 3);
+
+        let novelty = fp.observe("nginx", 2, "bash");
+        assert!(novelty.unusual_parent && novelty.new_argv && !novelty.new_binary);
+        assert_eq!(fp.observe("nginx", 2, "bash"), Novelty::default());
+        assert_eq!(fp.parents_of("nginx"), ["bash", "systemd"]);
+
+        // Unknown argv or parent (already gone) is not novel.
+        assert_eq!(fp.observe("nginx", 0, ""), Novelty::default());
+    }
+
+    #[test]
+    fn stops_growing_at_the_cap() {
+        let mut fp = WorkloadFingerprint::new(Instant::now(), 2);
+        assert!(fp.observe("a", 0, "init").new_binary);
+        assert!(fp.observe("b", 0, "init").new_binary);
+        assert!(fp.observe("b", 0, "init").new_binary);
+        assert_eq!(fp.len(), 2);
+    }
+}
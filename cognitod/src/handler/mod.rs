@@ -1,7 +1,12 @@
 #[cfg(test)]
 use crate::ProcessEventWire;
+use crate::config::HandlersConfig;
+use crate::exec_compress::{ExecAggregate, ExecCompressor, argv_hash};
+use crate::metrics::{HandlerCounters, Metrics};
 use crate::{ProcessEvent, types::SystemSnapshot};
 use async_trait::async_trait;
+use futures_util::FutureExt;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
@@ -9,16 +14,56 @@ use tokio::sync::Mutex;
 
 use linnix_ai_ebpf_common::EventType;
 
+/// Receives every event from the pipeline.
+///
+/// Errors are counted per handler by [`HandlerList`] and never stop the
+/// remaining handlers from running.
 #[async_trait]
 pub trait Handler: Send + Sync {
-    #[allow(dead_code)]
+    /// Name used in `[handlers]` config, logs and per-handler metrics.
     fn name(&self) -> &'static str;
-    async fn on_event(&self, event: &ProcessEvent);
-    async fn on_snapshot(&self, snapshot: &SystemSnapshot);
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()>;
+    async fn on_snapshot(&self, snapshot: &SystemSnapshot) -> anyhow::Result<()>;
+    /// Folded short-job events; only delivered by [`CompressingHandler`].
+    async fn on_aggregate(&self, _aggregate: &ExecAggregate) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Lets a handler be registered while another component (e.g. the API) keeps
+/// a reference to it.
+#[async_trait]
+impl<H: Handler + ?Sized> Handler for Arc<H> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
+        (**self).on_event(event).await
+    }
+
+    async fn on_snapshot(&self, snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+        (**self).on_snapshot(snapshot).await
+    }
+
+    async fn on_aggregate(&self, aggregate: &ExecAggregate) -> anyhow::Result<()> {
+        (**self).on_aggregate(aggregate).await
+    }
+}
+
+struct Registered {
+    handler: Arc<dyn Handler>,
+    counters: Arc<HandlerCounters>,
 }
 
+/// Ordered set of handlers the event loop dispatches to.
+///
+/// Each call is isolated: an error or a panic in one handler is counted
+/// and logged, and the next handler still runs.
 pub struct HandlerList {
-    handlers: Vec<Arc<dyn Handler>>,
+    handlers: Vec<Registered>,
+    config: HandlersConfig,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl Default for HandlerList {
@@ -31,22 +76,90 @@ impl HandlerList {
     pub fn new() -> Self {
         Self {
             handlers: Vec::new(),
+            config: HandlersConfig::default(),
+            metrics: None,
         }
     }
 
-    pub fn register<H: Handler + 'static>(&mut self, handler: H) {
-        self.handlers.push(Arc::new(handler));
+    /// Handler list honouring `[handlers]` and reporting into `metrics`.
+    pub fn with_config(config: HandlersConfig, metrics: Arc<Metrics>) -> Self {
+        Self {
+            handlers: Vec::new(),
+            config,
+            metrics: Some(metrics),
+        }
+    }
+
+    /// Add a handler unless it is disabled in config. Returns whether it
+    /// was registered.
+    pub fn register<H: Handler + 'static>(&mut self, handler: H) -> bool {
+        let name = handler.name();
+        if self.config.disabled.iter().any(|d| d == name) {
+            log::info!("[handlers] '{name}' disabled by config");
+            return false;
+        }
+        let counters = match &self.metrics {
+            Some(m) => m.register_handler(name),
+            None => Arc::default(),
+        };
+        self.handlers.push(Registered {
+            handler: Arc::new(handler),
+            counters,
+        });
+        // Stable sort: unlisted handlers keep registration order.
+        let order = &self.config.order;
+        self.handlers.sort_by_key(|r| {
+            order
+                .iter()
+                .position(|n| n == r.handler.name())
+                .unwrap_or(usize::MAX)
+        });
+        true
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.handlers.iter().map(|r| r.handler.name()).collect()
     }
 
     pub async fn on_event(&self, event: &ProcessEvent) {
-        for h in &self.handlers {
-            h.on_event(event).await;
+        for r in &self.handlers {
+            r.counters.inc_event();
+            dispatch(r, "event", r.handler.on_event(event)).await;
         }
     }
 
     pub async fn on_snapshot(&self, snapshot: &SystemSnapshot) {
-        for h in &self.handlers {
-            h.on_snapshot(snapshot).await;
+        for r in &self.handlers {
+            dispatch(r, "snapshot", r.handler.on_snapshot(snapshot)).await;
+        }
+    }
+}
+
+#[allow(clippy::manual_is_multiple_of)] // is_multiple_of not stable in nightly-2024-12-10
+async fn dispatch(
+    r: &Registered,
+    kind: &str,
+    fut: impl std::future::Future<Output = anyhow::Result<()>>,
+) {
+    let name = r.handler.name();
+    match AssertUnwindSafe(fut).catch_unwind().await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            // Log the first error and then every 1000th so a broken sink
+            // doesn't flood the log.
+            let n = r.counters.inc_error();
+            if n == 1 || n % 1000 == 0 {
+                log::warn!("[handlers] '{name}' failed on {kind} ({n} errors so far): {e:#}");
+            }
+        }
+        Err(panic) => {
+            r.counters.inc_panic();
+            let msg = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "non-string panic payload".to_string());
+            log::error!("[handlers] '{name}' panicked on {kind}: {msg}");
         }
     }
 }
@@ -68,26 +181,115 @@ impl JsonlHandler {
     }
 }
 
+impl JsonlHandler {
+    async fn write_line<T: serde::Serialize + ?Sized>(&self, value: &T) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+        let mut f = self.file.lock().await;
+        f.write_all(&line).await?;
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler for JsonlHandler {
     fn name(&self) -> &'static str {
         "jsonl"
     }
 
-    async fn on_event(&self, event: &ProcessEvent) {
-        if let Ok(json) = serde_json::to_string(event) {
-            let mut f = self.file.lock().await;
-            let _ = f.write_all(json.as_bytes()).await;
-            let _ = f.write_all(b"\n").await;
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
+        self.write_line(event).await
+    }
+
+    async fn on_snapshot(&self, snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+        self.write_line(snapshot).await
+    }
+
+    async fn on_aggregate(&self, aggregate: &ExecAggregate) -> anyhow::Result<()> {
+        self.write_line(aggregate).await
+    }
+}
+
+/// Wraps a storage/notification handler so that repeated short-job
+/// fork/exec/exit triples reach it as periodic [`ExecAggregate`]s instead of
+/// one event each. Handlers that need every event (the rule engine) should
+/// be registered unwrapped.
+pub struct CompressingHandler<H> {
+    inner: H,
+    compressor: std::sync::Mutex<ExecCompressor>,
+}
+
+impl<H: Handler> CompressingHandler<H> {
+    pub fn new(inner: H, compressor: ExecCompressor) -> Self {
+        Self {
+            inner,
+            compressor: std::sync::Mutex::new(compressor),
         }
     }
 
-    async fn on_snapshot(&self, snapshot: &SystemSnapshot) {
-        if let Ok(json) = serde_json::to_string(snapshot) {
-            let mut f = self.file.lock().await;
-            let _ = f.write_all(json.as_bytes()).await;
-            let _ = f.write_all(b"\n").await;
+    /// Forward held forks that never folded, then closed aggregates.
+    async fn flush(
+        &self,
+        stale: Vec<ProcessEvent>,
+        aggregates: Vec<ExecAggregate>,
+    ) -> anyhow::Result<()> {
+        for fork in &stale {
+            self.inner.on_event(fork).await?;
+        }
+        for agg in &aggregates {
+            self.inner.on_aggregate(agg).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<H: Handler> Handler for CompressingHandler<H> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
+        // Read argv before taking the lock; short jobs may already be gone.
+        let hash = if event.event_type == EventType::Exec as u32 {
+            argv_hash(event)
+        } else {
+            0
+        };
+        let now = std::time::Instant::now();
+        let (verdict, stale, closed) = {
+            let mut c = self
+                .compressor
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let verdict = c.observe(event, hash, now);
+            (verdict, c.release_stale(now), c.drain_closed(now))
+        };
+        self.flush(stale, closed).await?;
+        if let Some(fork) = &verdict.released {
+            self.inner.on_event(fork).await?;
+        }
+        if verdict.forward {
+            self.inner.on_event(event).await?;
         }
+        Ok(())
+    }
+
+    async fn on_snapshot(&self, snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+        let now = std::time::Instant::now();
+        let (stale, closed) = {
+            let mut c = self
+                .compressor
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            (c.release_stale(now), c.drain_closed(now))
+        };
+        self.flush(stale, closed).await?;
+        self.inner.on_snapshot(snapshot).await
+    }
+
+    async fn on_aggregate(&self, aggregate: &ExecAggregate) -> anyhow::Result<()> {
+        self.inner.on_aggregate(aggregate).await
     }
 }
 
@@ -125,15 +327,15 @@ impl Handler for MandateReceiptHandler {
         "mandate-receipt"
     }
 
-    async fn on_event(&self, event: &ProcessEvent) {
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
         // Only process MandateAllow events (event_type = 8)
         if event.event_type != EventType::MandateAllow as u32 {
-            return;
+            return Ok(());
         }
 
         let mandate_seq = event.data2;
         if mandate_seq == 0 {
-            return; // No mandate matched (shouldn't happen for MandateAllow)
+            return Ok(()); // No mandate matched (shouldn't happen for MandateAllow)
         }
 
         // Resolve mandate_seq → mandate_id via reverse index
@@ -144,7 +346,7 @@ impl Handler for MandateReceiptHandler {
                     "[mandate-receipt] no mandate found for seq={} (may have expired)",
                     mandate_seq
                 );
-                return;
+                return Ok(());
             }
         };
 
@@ -156,7 +358,7 @@ impl Handler for MandateReceiptHandler {
                     "[mandate-receipt] mandate {} no longer active for receipt",
                     mandate_id
                 );
-                return;
+                return Ok(());
             }
         };
 
@@ -183,32 +385,86 @@ impl Handler for MandateReceiptHandler {
             .enforcement_mode(enforcement_mode)
             .sign(&self.identity);
 
-        match receipt {
-            Ok(signed_receipt) => {
-                if let Err(e) = self
-                    .mandate
-                    .mark_executed(&mandate_id, signed_receipt)
-                    .await
-                {
-                    log::warn!(
-                        "[mandate-receipt] failed to mark mandate {} as executed: {}",
-                        mandate_id,
-                        e
-                    );
-                }
-            }
-            Err(e) => {
-                log::warn!(
-                    "[mandate-receipt] failed to sign receipt for mandate {}: {}",
-                    mandate_id,
-                    e
-                );
-            }
-        }
+        let signed_receipt = receipt
+            .map_err(|e| anyhow::anyhow!("failed to sign receipt for mandate {mandate_id}: {e}"))?;
+        self.mandate
+            .mark_executed(&mandate_id, signed_receipt)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to mark mandate {mandate_id} as executed: {e}"))
     }
 
-    async fn on_snapshot(&self, _snapshot: &SystemSnapshot) {
+    async fn on_snapshot(&self, _snapshot: &SystemSnapshot) -> anyhow::Result<()> {
         // No-op: MandateReceiptHandler only processes events.
+        Ok(())
+    }
+}
+
+// ── OomKillHandler ──────────────────────────────────────────────────────────
+//
+// Records an `oom_risk` insight for every OomKill event. The kernel already
+// chose and killed the victim, so these carry full confidence, unlike the
+// insights inferred from memory pressure.
+
+pub struct OomKillHandler {
+    insights: Arc<crate::insights::InsightStore>,
+    k8s: Option<Arc<crate::k8s::K8sContext>>,
+}
+
+impl OomKillHandler {
+    pub fn new(
+        insights: Arc<crate::insights::InsightStore>,
+        k8s: Option<Arc<crate::k8s::K8sContext>>,
+    ) -> Self {
+        Self { insights, k8s }
+    }
+}
+
+#[async_trait]
+impl Handler for OomKillHandler {
+    fn name(&self) -> &'static str {
+        "oom-kill"
+    }
+
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
+        if event.event_type != EventType::OomKill as u32 {
+            return Ok(());
+        }
+        let comm = String::from_utf8_lossy(&event.comm)
+            .trim_end_matches('\0')
+            .to_string();
+        let rss_mib = event.data / (1024 * 1024);
+        let (scope, next_step) = if event.aux == 1 {
+            (
+                "its cgroup hit its memory limit",
+                "Raise the container's memory limit or reduce its working set",
+            )
+        } else {
+            (
+                "the host ran out of memory",
+                "Find what grew before the kill and add memory limits to the largest consumers",
+            )
+        };
+        self.insights.record(crate::schema::Insight {
+            reason_code: crate::schema::InsightReason::OomRisk,
+            summary: format!(
+                "OOM killer killed {comm} (pid {}) holding {rss_mib} MiB RSS: {scope}",
+                event.pid
+            ),
+            confidence: 1.0,
+            id: uuid::Uuid::new_v4().to_string(),
+            top_pods: Vec::new(),
+            suggested_next_step: next_step.to_string(),
+            primary_process: Some(comm),
+            k8s: self
+                .k8s
+                .as_ref()
+                .and_then(|k8s| k8s.get_metadata_for_event(event)),
+        });
+        Ok(())
+    }
+
+    async fn on_snapshot(&self, _snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+        Ok(())
     }
 }
 
@@ -239,9 +495,10 @@ mod tests {
             data2: 0,
             aux: 0,
             aux2: 0,
+            cgroup_id: 0,
         };
         let event = ProcessEvent::new(base);
-        handler.on_event(&event).await;
+        handler.on_event(&event).await.unwrap();
         let snap = SystemSnapshot {
             timestamp: 0,
             cpu_percent: 0.0,
@@ -256,9 +513,156 @@ mod tests {
             psi_memory_full_avg10: 0.0,
             psi_io_some_avg10: 0.0,
             psi_io_full_avg10: 0.0,
+            cgroup_throttling: Vec::new(),
+            cpu_thermal: None,
+            gpu_memory: Vec::new(),
         };
-        handler.on_snapshot(&snap).await;
+        handler.on_snapshot(&snap).await.unwrap();
         let content = tokio::fs::read_to_string(file.path()).await.unwrap();
         assert_eq!(content.lines().count(), 2);
     }
+
+    #[tokio::test]
+    async fn oom_kill_events_become_oom_risk_insights() {
+        let store = Arc::new(crate::insights::InsightStore::new(4, None));
+        let handler = OomKillHandler::new(Arc::clone(&store), None);
+        let mut comm = [0u8; 16];
+        comm[..5].copy_from_slice(b"redis");
+        let mut wire = ProcessEventWire {
+            pid: 77,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::Exit as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 512 * 1024 * 1024,
+            data2: 90,
+            aux: 1,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        handler.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        assert!(store.recent(10).is_empty());
+
+        wire.event_type = EventType::OomKill as u32;
+        handler.on_event(&ProcessEvent::new(wire)).await.unwrap();
+        let recorded = store.recent(10);
+        assert_eq!(recorded.len(), 1);
+        let insight = &recorded[0].insight;
+        assert_eq!(insight.reason_code, crate::schema::InsightReason::OomRisk);
+        assert_eq!(insight.confidence, 1.0);
+        assert_eq!(insight.primary_process.as_deref(), Some("redis"));
+        assert_eq!(
+            insight.summary,
+            "OOM killer killed redis (pid 77) holding 512 MiB RSS: its cgroup hit its memory limit"
+        );
+    }
+
+    enum Behaviour {
+        Ok,
+        Fail,
+        Panic,
+    }
+
+    struct Probe {
+        name: &'static str,
+        behaviour: Behaviour,
+        calls: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl Handler for Probe {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn on_event(&self, _event: &ProcessEvent) -> anyhow::Result<()> {
+            self.calls.lock().unwrap().push(self.name);
+            match self.behaviour {
+                Behaviour::Ok => Ok(()),
+                Behaviour::Fail => anyhow::bail!("sink unavailable"),
+                Behaviour::Panic => panic!("probe panic"),
+            }
+        }
+
+        async fn on_snapshot(&self, _snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn event() -> ProcessEvent {
+        ProcessEvent::new(ProcessEventWire {
+            pid: 1,
+            ppid: 0,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::Fork as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn config_orders_and_disables_handlers() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = HandlersConfig {
+            disabled: vec!["c".into()],
+            order: vec!["b".into()],
+        };
+        let mut list = HandlerList::with_config(config, Arc::new(Metrics::new()));
+        for name in ["a", "b", "c", "d"] {
+            list.register(Probe {
+                name,
+                behaviour: Behaviour::Ok,
+                calls: Arc::clone(&calls),
+            });
+        }
+        assert_eq!(list.names(), vec!["b", "a", "d"]);
+        list.on_event(&event()).await;
+        assert_eq!(*calls.lock().unwrap(), vec!["b", "a", "d"]);
+    }
+
+    #[tokio::test]
+    async fn failing_and_panicking_handlers_are_isolated_and_counted() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let metrics = Arc::new(Metrics::new());
+        let mut list = HandlerList::with_config(HandlersConfig::default(), Arc::clone(&metrics));
+        for (name, behaviour) in [
+            ("panics", Behaviour::Panic),
+            ("fails", Behaviour::Fail),
+            ("ok", Behaviour::Ok),
+        ] {
+            list.register(Probe {
+                name,
+                behaviour,
+                calls: Arc::clone(&calls),
+            });
+        }
+        list.on_event(&event()).await;
+        list.on_event(&event()).await;
+
+        assert_eq!(
+            calls.lock().unwrap().iter().filter(|n| **n == "ok").count(),
+            2
+        );
+        let stats = metrics.handler_stats();
+        let by = |n: &str| stats.iter().find(|s| s.handler == n).unwrap();
+        assert_eq!((by("panics").events, by("panics").panics), (2, 2));
+        assert_eq!((by("fails").errors, by("fails").panics), (2, 0));
+        assert_eq!((by("ok").errors, by("ok").panics), (0, 0));
+    }
 }
@@ -7,11 +7,20 @@ mod analyzer;
 
 pub use analyzer::{IncidentAnalysis, IncidentAnalyzer};
 
+use crate::context::ContextStore;
+use crate::types::SystemSnapshot;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, SqlitePool, sqlite::SqlitePoolOptions};
 use std::path::Path;
-use tracing::{debug, info};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// How often and how long [`watch_recovery`] checks the system after an
+/// incident.
+const RECOVERY_POLL: Duration = Duration::from_secs(5);
+const RECOVERY_TIMEOUT: Duration = Duration::from_secs(1800);
 
 /// Represents a circuit breaker incident or system event
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +51,14 @@ pub struct Incident {
     // Outcome
     pub recovery_time_ms: Option<i64>,
     pub psi_after: Option<f32>,
+
+    // Sampled stack profile of the target (JSON, see crate::profiler)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    // When the trigger conditions were first met (Unix epoch seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breach_started_at: Option<i64>,
 }
 
 /// Represents a stall attribution event
@@ -58,6 +75,18 @@ pub struct StallAttribution {
     pub short_job_count: u64,
 }
 
+/// One downsampled telemetry bucket (see `crate::rollups`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollupRow {
+    pub resolution: i64, // bucket width in seconds (60, 300)
+    pub bucket: i64,     // bucket start, Unix epoch seconds
+    pub metric: String,  // "events.exec", "alerts.high", "process.cpu", ...
+    pub key: String,     // process name for per-process metrics, otherwise empty
+    pub count: i64,
+    pub sum: f64,
+    pub max: f64,
+}
+
 /// Incident storage backed by SQLite
 pub struct IncidentStore {
     pool: SqlitePool,
@@ -91,7 +120,9 @@ impl IncidentStore {
                 llm_analysis TEXT,
                 llm_analyzed_at INTEGER,
                 recovery_time_ms INTEGER,
-                psi_after REAL
+                psi_after REAL,
+                profile TEXT,
+                breach_started_at INTEGER
             );
             CREATE INDEX IF NOT EXISTS idx_timestamp ON incidents(timestamp);
             CREATE INDEX IF NOT EXISTS idx_event_type ON incidents(event_type);
@@ -102,7 +133,8 @@ impl IncidentStore {
                 timestamp INTEGER NOT NULL,
                 label TEXT NOT NULL,
                 source TEXT NOT NULL,
-                user_id TEXT
+                user_id TEXT,
+                rule TEXT
             );
             CREATE INDEX IF NOT EXISTS idx_feedback_insight_id ON feedback(insight_id);
             CREATE TABLE IF NOT EXISTS stall_attributions (
@@ -121,6 +153,17 @@ impl IncidentStore {
             CREATE INDEX IF NOT EXISTS idx_victim_time ON stall_attributions(victim_pod, victim_namespace, timestamp);
             CREATE INDEX IF NOT EXISTS idx_offender_time ON stall_attributions(offender_pod, offender_namespace, timestamp);
             CREATE INDEX IF NOT EXISTS idx_timestamp_attr ON stall_attributions(timestamp);
+            CREATE TABLE IF NOT EXISTS telemetry_rollups (
+                resolution INTEGER NOT NULL,
+                bucket INTEGER NOT NULL,
+                metric TEXT NOT NULL,
+                key TEXT NOT NULL DEFAULT '',
+                count INTEGER NOT NULL,
+                sum REAL NOT NULL,
+                max REAL NOT NULL,
+                PRIMARY KEY (resolution, bucket, metric, key)
+            );
+            CREATE INDEX IF NOT EXISTS idx_rollup_metric_time ON telemetry_rollups(resolution, metric, bucket);
             "#,
         )
         .execute(&pool)
@@ -140,6 +183,18 @@ impl IncidentStore {
         )
         .execute(&pool)
         .await;
+        let _ = sqlx::query("ALTER TABLE incidents ADD COLUMN profile TEXT")
+            .execute(&pool)
+            .await;
+        let _ = sqlx::query("ALTER TABLE incidents ADD COLUMN breach_started_at INTEGER")
+            .execute(&pool)
+            .await;
+        let _ = sqlx::query("ALTER TABLE feedback ADD COLUMN rule TEXT")
+            .execute(&pool)
+            .await;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_feedback_rule ON feedback(rule, timestamp)")
+            .execute(&pool)
+            .await?;
 
         info!(
             "Incident store initialized at {}",
@@ -155,8 +210,8 @@ impl IncidentStore {
             INSERT INTO incidents (
                 timestamp, event_type, psi_cpu, psi_memory, cpu_percent, load_avg,
                 action, target_pid, target_name, system_snapshot,
-                recovery_time_ms, psi_after
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                recovery_time_ms, psi_after, profile, breach_started_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(incident.timestamp)
@@ -171,6 +226,8 @@ impl IncidentStore {
         .bind(&incident.system_snapshot)
         .bind(incident.recovery_time_ms)
         .bind(incident.psi_after)
+        .bind(&incident.profile)
+        .bind(incident.breach_started_at)
         .execute(&self.pool)
         .await?;
 
@@ -194,19 +251,73 @@ impl IncidentStore {
         Ok(())
     }
 
-    /// Insert user feedback for an insight
+    /// Record how long after the incident conditions returned to normal,
+    /// and the pressure at that point
+    pub async fn record_recovery(
+        &self,
+        id: i64,
+        recovery_time_ms: i64,
+        psi_after: f32,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE incidents SET recovery_time_ms = ?, psi_after = ? WHERE id = ?")
+            .bind(recovery_time_ms)
+            .bind(psi_after)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        debug!("Recorded recovery of incident #{}", id);
+        Ok(())
+    }
+
+    /// Attach a sampled stack profile (JSON) to an existing incident
+    pub async fn attach_profile(&self, id: i64, profile: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE incidents SET profile = ? WHERE id = ?")
+            .bind(profile)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        debug!("Attached stack profile to incident #{}", id);
+        Ok(())
+    }
+
+    /// Newest incident targeting `pid` recorded since `start_timestamp` that
+    /// has no stack profile yet
+    pub async fn unprofiled_for_pid(
+        &self,
+        pid: u32,
+        start_timestamp: i64,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT id FROM incidents
+            WHERE target_pid = ? AND timestamp >= ? AND profile IS NULL
+            ORDER BY timestamp DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(pid as i32)
+        .bind(start_timestamp)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Insert user feedback for an insight or, with `rule` set, for the
+    /// alerts of a rule (`insight_id` is then empty)
     pub async fn insert_feedback(
         &self,
         insight_id: &str,
         label: &str,
         source: &str,
         user_id: Option<&str>,
+        rule: Option<&str>,
     ) -> Result<i64, sqlx::Error> {
         let now = Utc::now().timestamp();
         let result = sqlx::query(
             r#"
-            INSERT INTO feedback (insight_id, timestamp, label, source, user_id)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO feedback (insight_id, timestamp, label, source, user_id, rule)
+            VALUES (?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(insight_id)
@@ -214,14 +325,41 @@ impl IncidentStore {
         .bind(label)
         .bind(source)
         .bind(user_id)
+        .bind(rule)
         .execute(&self.pool)
         .await?;
 
         let id = result.last_insert_rowid();
-        debug!("Inserted feedback #{} for insight {}", id, insight_id);
+        match rule {
+            Some(rule) => debug!("Inserted feedback #{} for rule {}", id, rule),
+            None => debug!("Inserted feedback #{} for insight {}", id, insight_id),
+        }
         Ok(id)
     }
 
+    /// Feedback labels per rule since `since`, as (rule, label, count)
+    pub async fn rule_feedback(
+        &self,
+        since: i64,
+    ) -> Result<Vec<(String, String, i64)>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT rule, label, COUNT(*)
+            FROM feedback
+            WHERE rule IS NOT NULL AND timestamp >= ?
+            GROUP BY rule, label
+            "#,
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get(0), row.get(1), row.get(2)))
+            .collect())
+    }
+
     /// Insert stall attribution event
     #[allow(clippy::too_many_arguments)]
     pub async fn insert_stall_attribution(
@@ -316,7 +454,8 @@ impl IncidentStore {
             r#"
             SELECT id, timestamp, event_type, psi_cpu, psi_memory, cpu_percent, load_avg,
                    action, target_pid, target_name, system_snapshot,
-                   llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after
+                   llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after, profile,
+                   breach_started_at
             FROM incidents WHERE id = ?
             "#,
         )
@@ -340,6 +479,8 @@ impl IncidentStore {
             llm_analyzed_at: r.get(12),
             recovery_time_ms: r.get(13),
             psi_after: r.get(14),
+            profile: r.get(15),
+            breach_started_at: r.get(16),
         }))
     }
 
@@ -349,7 +490,8 @@ impl IncidentStore {
             r#"
             SELECT id, timestamp, event_type, psi_cpu, psi_memory, cpu_percent, load_avg,
                    action, target_pid, target_name, system_snapshot,
-                   llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after
+                   llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after, profile,
+                   breach_started_at
             FROM incidents
             ORDER BY timestamp DESC
             LIMIT ?
@@ -377,6 +519,8 @@ impl IncidentStore {
                 llm_analyzed_at: r.get(12),
                 recovery_time_ms: r.get(13),
                 psi_after: r.get(14),
+                profile: r.get(15),
+                breach_started_at: r.get(16),
             })
             .collect())
     }
@@ -392,7 +536,8 @@ impl IncidentStore {
                 r#"
                 SELECT id, timestamp, event_type, psi_cpu, psi_memory, cpu_percent, load_avg,
                        action, target_pid, target_name, system_snapshot,
-                       llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after
+                       llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after, profile,
+                       breach_started_at
                 FROM incidents
                 WHERE timestamp >= ? AND event_type = ?
                 ORDER BY timestamp DESC
@@ -407,7 +552,8 @@ impl IncidentStore {
                 r#"
                 SELECT id, timestamp, event_type, psi_cpu, psi_memory, cpu_percent, load_avg,
                        action, target_pid, target_name, system_snapshot,
-                       llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after
+                       llm_analysis, llm_analyzed_at, recovery_time_ms, psi_after, profile,
+                       breach_started_at
                 FROM incidents
                 WHERE timestamp >= ?
                 ORDER BY timestamp DESC
@@ -436,6 +582,144 @@ impl IncidentStore {
                 llm_analyzed_at: r.get(12),
                 recovery_time_ms: r.get(13),
                 psi_after: r.get(14),
+                profile: r.get(15),
+                breach_started_at: r.get(16),
+            })
+            .collect())
+    }
+
+    /// Merge rows into their buckets, adding to any existing counts
+    pub async fn upsert_rollups(&self, rows: &[RollupRow]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        for row in rows {
+            sqlx::query(
+                r#"
+                INSERT INTO telemetry_rollups (resolution, bucket, metric, key, count, sum, max)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(resolution, bucket, metric, key) DO UPDATE SET
+                    count = count + excluded.count,
+                    sum = sum + excluded.sum,
+                    max = MAX(max, excluded.max)
+                "#,
+            )
+            .bind(row.resolution)
+            .bind(row.bucket)
+            .bind(&row.metric)
+            .bind(&row.key)
+            .bind(row.count)
+            .bind(row.sum)
+            .bind(row.max)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        debug!("Wrote {} rollup rows", rows.len());
+        Ok(())
+    }
+
+    /// Re-aggregate `from_resolution` buckets in `[start, end)` into
+    /// `to_resolution` buckets, replacing any previous result
+    pub async fn compact_rollups(
+        &self,
+        from_resolution: i64,
+        to_resolution: i64,
+        start: i64,
+        end: i64,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO telemetry_rollups (resolution, bucket, metric, key, count, sum, max)
+            SELECT ?, (bucket / ?) * ?, metric, key, SUM(count), SUM(sum), MAX(max)
+            FROM telemetry_rollups
+            WHERE resolution = ? AND bucket >= ? AND bucket < ?
+            GROUP BY 2, metric, key
+            ON CONFLICT(resolution, bucket, metric, key) DO UPDATE SET
+                count = excluded.count,
+                sum = excluded.sum,
+                max = excluded.max
+            "#,
+        )
+        .bind(to_resolution)
+        .bind(to_resolution)
+        .bind(to_resolution)
+        .bind(from_resolution)
+        .bind(start)
+        .bind(end)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Delete buckets of `resolution` that start before `before`
+    pub async fn prune_rollups(&self, resolution: i64, before: i64) -> Result<u64, sqlx::Error> {
+        let result =
+            sqlx::query("DELETE FROM telemetry_rollups WHERE resolution = ? AND bucket < ?")
+                .bind(resolution)
+                .bind(before)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Delete feedback older than `before`, then all but the newest
+    /// `max_records` rows (0 = no count limit)
+    pub async fn prune_feedback(
+        &self,
+        before: i64,
+        max_records: usize,
+    ) -> Result<u64, sqlx::Error> {
+        let mut removed = sqlx::query("DELETE FROM feedback WHERE timestamp < ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if max_records > 0 {
+            removed += sqlx::query(
+                "DELETE FROM feedback WHERE id NOT IN (SELECT id FROM feedback ORDER BY id DESC LIMIT ?)",
+            )
+            .bind(max_records as i64)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        }
+        Ok(removed)
+    }
+
+    /// Buckets of `resolution` in `[from, to]`, optionally for one metric
+    pub async fn query_rollups(
+        &self,
+        resolution: i64,
+        metric: Option<&str>,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<RollupRow>, sqlx::Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT resolution, bucket, metric, key, count, sum, max
+            FROM telemetry_rollups
+            WHERE resolution = ? AND bucket >= ? AND bucket <= ?
+              AND (? IS NULL OR metric = ?)
+            ORDER BY bucket ASC, metric ASC, key ASC
+            "#,
+        )
+        .bind(resolution)
+        .bind(from)
+        .bind(to)
+        .bind(metric)
+        .bind(metric)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RollupRow {
+                resolution: row.get(0),
+                bucket: row.get(1),
+                metric: row.get(2),
+                key: row.get(3),
+                count: row.get(4),
+                sum: row.get(5),
+                max: row.get(6),
             })
             .collect())
     }
@@ -482,3 +766,33 @@ pub struct IncidentStats {
     pub avg_recovery_time_ms: Option<u64>,
     pub feedback_entries: u64,
 }
+
+/// Poll the system after incident `id` until `recovered` holds, then record
+/// how long that took and the CPU pressure at that point. Gives up after
+/// 30 minutes, leaving the recovery unset.
+pub async fn watch_recovery(
+    store: Arc<IncidentStore>,
+    id: i64,
+    context: Arc<ContextStore>,
+    recovered: impl Fn(&SystemSnapshot) -> bool + Send + 'static,
+) {
+    let started = Instant::now();
+    while started.elapsed() < RECOVERY_TIMEOUT {
+        tokio::time::sleep(RECOVERY_POLL).await;
+        let snapshot = context.get_system_snapshot();
+        if recovered(&snapshot) {
+            let elapsed_ms = started.elapsed().as_millis() as i64;
+            if let Err(e) = store
+                .record_recovery(id, elapsed_ms, snapshot.psi_cpu_some_avg10)
+                .await
+            {
+                warn!("Failed to record recovery of incident #{}: {}", id, e);
+            }
+            return;
+        }
+    }
+    debug!(
+        "Incident #{} did not recover within {:?}",
+        id, RECOVERY_TIMEOUT
+    );
+}
@@ -7,8 +7,16 @@
 //! - Detect patterns across multiple incidents
 
 use super::Incident;
+use crate::baselines::{Baselines, Observed};
+use crate::changes::ChangeLog;
+use crate::collectors::pod_pressure::PodPressure;
+use crate::inventory::HostFacts;
+use crate::network::Network;
+use crate::offcpu::OffCpuTracker;
+use crate::redaction::{Destination, RedactionPolicy};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, info};
 
@@ -34,14 +42,73 @@ pub struct PodContribution {
 pub struct IncidentAnalyzer {
     endpoint: String,
     client: reqwest::Client,
+    baselines: Option<Arc<Baselines>>,
+    inventory: Option<Arc<HostFacts>>,
+    pod_pressure: Option<Arc<PodPressure>>,
+    changes: Option<Arc<ChangeLog>>,
+    offcpu: Option<Arc<OffCpuTracker>>,
+    redaction: Arc<RedactionPolicy>,
 }
 
 impl IncidentAnalyzer {
     /// Create a new incident analyzer
-    pub fn new(endpoint: String, timeout: Duration) -> Result<Self, reqwest::Error> {
-        let client = reqwest::Client::builder().timeout(timeout).build()?;
+    pub fn new(
+        endpoint: String,
+        timeout: Duration,
+        network: &Network,
+    ) -> Result<Self, reqwest::Error> {
+        let client = network.client_builder().timeout(timeout).build()?;
+
+        Ok(Self {
+            endpoint,
+            client,
+            baselines: None,
+            inventory: None,
+            pod_pressure: None,
+            changes: None,
+            offcpu: None,
+            redaction: Arc::new(RedactionPolicy::default()),
+        })
+    }
+
+    /// Compare incident metrics with this host's history for the same hour.
+    pub fn with_baselines(mut self, baselines: Arc<Baselines>) -> Self {
+        self.baselines = Some(baselines);
+        self
+    }
+
+    /// Tell the model what the host is (instance size, kernel, GPUs), so
+    /// advice fits it.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Give the model measured per-pod CPU stall contributions to report in
+    /// TOP_PODS instead of estimating them.
+    pub fn with_pod_pressure(mut self, pod_pressure: Arc<PodPressure>) -> Self {
+        self.pod_pressure = Some(pod_pressure);
+        self
+    }
+
+    /// Tell the model which deploys and config changes preceded the
+    /// incident.
+    pub fn with_changes(mut self, changes: Arc<ChangeLog>) -> Self {
+        self.changes = Some(changes);
+        self
+    }
 
-        Ok(Self { endpoint, client })
+    /// Give the model the target's measured off-CPU time, so it can tell
+    /// `cpu_spin` from `io_saturation`.
+    pub fn with_offcpu(mut self, offcpu: Arc<OffCpuTracker>) -> Self {
+        self.offcpu = Some(offcpu);
+        self
+    }
+
+    /// Scrub process names, command lines and paths in the prompt.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
     }
 
     /// Analyze an incident using the LLM
@@ -112,11 +179,54 @@ impl IncidentAnalyzer {
             .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
             .unwrap_or_else(|| "unknown".to_string());
 
+        let baseline = self
+            .baselines
+            .as_ref()
+            .and_then(|b| {
+                b.prompt_context_at(
+                    incident.timestamp,
+                    &Observed {
+                        cpu_pct: Some(incident.cpu_percent as f64),
+                        ..Observed::default()
+                    },
+                )
+            })
+            .unwrap_or_else(|| "not enough history yet".to_string());
+        let host = self
+            .inventory
+            .as_ref()
+            .map_or_else(|| "unknown".to_string(), |f| f.to_string());
+        let pod_pressure = self
+            .pod_pressure
+            .as_ref()
+            .and_then(|p| p.prompt_context())
+            .unwrap_or_else(|| "not measured".to_string());
+        let (window, changes) = match &self.changes {
+            Some(log) => (
+                log.window_minutes(),
+                log.describe_preceding(incident.timestamp.max(0) as u64)
+                    .unwrap_or_else(|| "none reported".to_string()),
+            ),
+            None => (0, "not tracked".to_string()),
+        };
+        let offcpu = self
+            .offcpu
+            .as_ref()
+            .and_then(|t| t.prompt_context(incident.target_pid.map(|pid| pid as u32)))
+            .unwrap_or_else(|| "not measured".to_string());
+        // Process names, command lines and change descriptions can carry
+        // credentials.
+        let llm = |text: &str| self.redaction.redact_str(Destination::Llm, text);
+        let target = llm(incident.target_name.as_deref().unwrap_or("unknown"));
+        let changes = llm(&changes);
+        let offcpu = llm(&offcpu);
+
         format!(
             r#"INCIDENT REPORT
 
 Timestamp: {}
 Event Type: {}
+Host: {}
 
 ACTION TAKEN BY CIRCUIT BREAKER:
 {} - Target Process: {} (PID: {})
@@ -127,6 +237,18 @@ SYSTEM METRICS AT INCIDENT TIME:
 - Memory PSI (Full): {:.1}%
 - Load Average: {}
 
+HISTORICAL BASELINE (same hour of day on this host):
+{}
+
+MEASURED POD CPU STALL CONTRIBUTION (last 10s, from cgroup pressure):
+{}
+
+DEPLOYS AND CONFIG CHANGES IN THE {} MINUTES BEFORE:
+{}
+
+OFF-CPU TIME (last 60s, from the scheduler):
+{}
+
 CIRCUIT BREAKER TRIGGER REASON:
 {}
 
@@ -135,10 +257,10 @@ You are analyzing a circuit breaker incident where an automated action was taken
 
 Provide a concise analysis covering:
 1. REASON_CODE: One of [fork_storm, short_job_flood, runaway_tree, cpu_spin, io_saturation, oom_risk, normal]
-2. SUMMARY: A concise explanation of what happened and why (1-2 sentences)
+2. SUMMARY: A concise explanation of what happened and why (1-2 sentences); name a recent change if it plausibly caused it. Use the off-CPU time to decide: a target that never slept is cpu_spin, one blocked in uninterruptible sleep is io_saturation
 3. CONFIDENCE: Your confidence level (0.0-1.0)
 4. SUGGESTED_NEXT_STEP: What should the operator do next? (1 sentence)
-5. TOP_PODS: JSON array of pods contributing to the issue (if applicable)
+5. TOP_PODS: JSON array of pods contributing to the issue (if applicable), using the measured cpu_usage and psi_contribution above when given
 
 Format your response as a JSON object:
 {{
@@ -153,13 +275,19 @@ Format your response as a JSON object:
 "#,
             timestamp,
             incident.event_type,
+            host,
             incident.action,
-            incident.target_name.as_deref().unwrap_or("unknown"),
+            target,
             incident.target_pid.unwrap_or(0),
             incident.cpu_percent,
             incident.psi_cpu,
             incident.psi_memory,
             incident.load_avg,
+            baseline,
+            pod_pressure,
+            window,
+            changes,
+            offcpu,
             self.explain_event_type(&incident.event_type, incident.psi_cpu, incident.cpu_percent)
         )
     }
@@ -188,7 +316,7 @@ Format your response as a JSON object:
     pub fn parse_analysis(text: &str) -> Option<IncidentAnalysis> {
         // Find the first '{' and last '}' to extract JSON
         let start = text.find('{')?;
-        let end = text.rfind('}')?;
+        let end = text.rfind('}').filter(|&end| end > start)?;
         let json_str = &text[start..=end];
 
         match serde_json::from_str::<IncidentAnalysis>(json_str) {
@@ -204,6 +332,7 @@ Format your response as a JSON object:
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{TimeZone, Timelike};
 
     #[test]
     fn test_parse_analysis() {
@@ -222,11 +351,14 @@ Here is the analysis:
         assert_eq!(analysis.reason_code, "fork_storm");
         assert_eq!(analysis.confidence, 0.95);
         assert!(analysis.summary.contains("fork bomb"));
+
+        // Braces in the wrong order used to panic on the slice.
+        assert!(IncidentAnalyzer::parse_analysis("} no json {").is_none());
+        assert!(IncidentAnalyzer::parse_analysis("{").is_none());
     }
 
-    #[test]
-    fn test_build_prompt() {
-        let incident = Incident {
+    fn stress_incident() -> Incident {
+        Incident {
             id: Some(1),
             timestamp: 1732242135,
             event_type: "circuit_breaker_cpu".to_string(),
@@ -242,13 +374,24 @@ Here is the analysis:
             llm_analyzed_at: None,
             recovery_time_ms: None,
             psi_after: None,
-        };
+            profile: None,
+            breach_started_at: None,
+        }
+    }
 
-        let analyzer = IncidentAnalyzer::new(
+    fn analyzer() -> IncidentAnalyzer {
+        IncidentAnalyzer::new(
             "http://localhost:8090/v1/chat/completions".to_string(),
             Duration::from_secs(30),
+            &Network::default(),
         )
-        .unwrap();
+        .unwrap()
+    }
+
+    #[test]
+    fn test_build_prompt() {
+        let incident = stress_incident();
+        let analyzer = analyzer();
 
         let prompt = analyzer.build_analysis_prompt(&incident);
 
@@ -256,4 +399,147 @@ Here is the analysis:
         assert!(prompt.contains("aggressive-stress.sh"));
         assert!(prompt.contains("Dual-signal CPU thrashing"));
     }
+
+    #[test]
+    fn test_build_prompt_with_inventory() {
+        let incident = stress_incident();
+        assert!(
+            analyzer()
+                .build_analysis_prompt(&incident)
+                .contains("Host: unknown")
+        );
+
+        let analyzer = analyzer().with_inventory(Arc::new(HostFacts {
+            kernel: "5.15.0".to_string(),
+            cpus: 2,
+            memory_bytes: 4 << 30,
+            ..Default::default()
+        }));
+        assert!(
+            analyzer
+                .build_analysis_prompt(&incident)
+                .contains("Host: 2 CPUs, 4.0 GiB, kernel 5.15.0")
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_without_pod_pressure() {
+        let prompt = analyzer().build_analysis_prompt(&stress_incident());
+        assert!(prompt.contains("CONTRIBUTION (last 10s, from cgroup pressure):\nnot measured"));
+    }
+
+    #[test]
+    fn test_build_prompt_with_changes() {
+        let incident = stress_incident();
+        let changes = Arc::new(ChangeLog::default());
+        changes.record(
+            serde_json::from_value(serde_json::json!({
+                "service": "stress-runner",
+                "kind": "config",
+                "timestamp": incident.timestamp - 240,
+            }))
+            .unwrap(),
+        );
+        let analyzer = analyzer().with_changes(changes);
+        assert!(analyzer.build_analysis_prompt(&incident).contains(
+            "CHANGES IN THE 30 MINUTES BEFORE:\n- 4 min before: config of stress-runner"
+        ));
+    }
+
+    #[test]
+    fn test_build_prompt_with_offcpu() {
+        let incident = stress_incident();
+        let analyzer = analyzer();
+        assert!(
+            analyzer
+                .build_analysis_prompt(&incident)
+                .contains("OFF-CPU TIME (last 60s, from the scheduler):\nnot measured")
+        );
+
+        let offcpu = Arc::new(OffCpuTracker::new());
+        let mut wire = crate::ProcessEventWire {
+            pid: 9,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: linnix_ai_ebpf_common::EventType::OffCpu as u32,
+            ts_ns: 1_000_000_000,
+            seq: 0,
+            comm: *b"jbd2/sda1-8\0\0\0\0\0",
+            exit_time_ns: 0,
+            cpu_pct_milli: crate::PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: crate::PERCENT_MILLI_UNKNOWN,
+            data: 4_000_000_000,
+            data2: 0,
+            aux: 5_000,
+            aux2: 0,
+            cgroup_id: 0,
+        };
+        offcpu.record(&crate::ProcessEvent::new(wire));
+        wire.pid = 472693;
+        wire.comm = *b"aggressive-stre\0";
+        wire.data = 200_000_000;
+        wire.data2 = 300_000_000;
+        offcpu.record(&crate::ProcessEvent::new(wire));
+        let analyzer = analyzer.with_offcpu(offcpu);
+        assert!(analyzer.build_analysis_prompt(&incident).contains(
+            "OFF-CPU TIME (last 60s, from the scheduler):\n\
+             - Target: 0.2s blocked in uninterruptible sleep (I/O), 0.3s in interruptible sleep\n\
+             - Most blocked on I/O: jbd2/sda1-8 (pid 9) 4.0s, aggressive-stre (pid 472693) 0.2s"
+        ));
+    }
+
+    #[test]
+    fn test_build_prompt_redacts_secrets() {
+        let incident = Incident {
+            target_pid: Some(4242),
+            target_name: Some("backup.sh --password=hunter2 s3://ci:s3cr3t@bucket/db".to_string()),
+            ..stress_incident()
+        };
+        let policy = RedactionPolicy::from_config(&crate::redaction::RedactionConfig {
+            enabled: true,
+            ..Default::default()
+        });
+        let analyzer = analyzer().with_redaction(Arc::new(policy));
+
+        let prompt = analyzer.build_analysis_prompt(&incident);
+        assert!(prompt.contains("Target Process: backup.sh --[REDACTED] [REDACTED]bucket/db"));
+        assert!(!prompt.contains("hunter2"), "{prompt}");
+        assert!(!prompt.contains("s3cr3t"), "{prompt}");
+    }
+
+    #[test]
+    fn test_build_prompt_with_baselines() {
+        let dir = tempfile::tempdir().unwrap();
+        let baselines = Arc::new(Baselines::new(crate::config::BaselineConfig {
+            path: dir.path().join("baselines.json").display().to_string(),
+            min_samples: 1,
+            ..Default::default()
+        }));
+        let incident = Incident {
+            cpu_percent: 90.0,
+            ..stress_incident()
+        };
+        let analyzer = analyzer().with_baselines(Arc::clone(&baselines));
+
+        assert!(
+            analyzer
+                .build_analysis_prompt(&incident)
+                .contains("not enough history yet")
+        );
+
+        let hour = chrono::Local
+            .timestamp_opt(incident.timestamp, 0)
+            .unwrap()
+            .hour();
+        baselines.record(
+            hour,
+            Observed {
+                cpu_pct: Some(30.0),
+                ..Observed::default()
+            },
+        );
+        let prompt = analyzer.build_analysis_prompt(&incident);
+        assert!(prompt.contains(&format!("CPU 90.0% vs 30.0% normal at {hour:02}:00 (3.0x)")));
+    }
 }
@@ -1,3 +1,6 @@
+use crate::changes::{ChangeEvent, ChangeLog};
+use crate::collectors::pod_pressure::PodPressure;
+use crate::evidence::{EvidenceBundle, EvidenceCollector};
 use crate::schema::Insight;
 use log::warn;
 use serde::{Deserialize, Serialize};
@@ -5,8 +8,9 @@ use std::collections::VecDeque;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -20,12 +24,23 @@ pub struct InsightRecord {
     pub timestamp: u64,
     pub insight: Insight,
     pub feedback: Option<Feedback>,
+    /// Deploys and config changes reported shortly before, newest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub recent_changes: Vec<ChangeEvent>,
+    /// Evidence collected when an anomaly insight was recorded; persisted
+    /// separately, see [`evidence_dir`].
+    #[serde(skip)]
+    pub evidence: Option<Arc<EvidenceBundle>>,
 }
 
 pub struct InsightStore {
     inner: Mutex<VecDeque<InsightRecord>>,
     capacity: usize,
     file_path: Option<PathBuf>,
+    tx: broadcast::Sender<InsightRecord>,
+    evidence: Option<Arc<EvidenceCollector>>,
+    pod_pressure: Option<Arc<PodPressure>>,
+    changes: Option<Arc<ChangeLog>>,
 }
 
 impl InsightStore {
@@ -34,14 +49,61 @@ impl InsightStore {
             inner: Mutex::new(VecDeque::with_capacity(capacity)),
             capacity,
             file_path,
+            tx: broadcast::channel(64).0,
+            evidence: None,
+            pod_pressure: None,
+            changes: None,
         }
     }
 
-    pub fn record(&self, insight: Insight) {
+    /// Replace the pod CPU usage and stall contribution in recorded insights
+    /// with measured values.
+    pub fn with_pod_pressure(mut self, pod_pressure: Arc<PodPressure>) -> Self {
+        self.pod_pressure = Some(pod_pressure);
+        self
+    }
+
+    /// Attach the changes reported shortly before each recorded insight.
+    pub fn with_changes(mut self, changes: Arc<ChangeLog>) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
+    /// Collect an evidence bundle for each anomaly insight recorded.
+    pub fn with_evidence(mut self, collector: Arc<EvidenceCollector>) -> Self {
+        self.evidence = Some(collector);
+        self
+    }
+
+    /// Subscribe to newly recorded insights (used by outbound webhooks).
+    pub fn subscribe(&self) -> broadcast::Receiver<InsightRecord> {
+        self.tx.subscribe()
+    }
+
+    pub fn record(&self, mut insight: Insight) {
+        if let Some(pod_pressure) = &self.pod_pressure {
+            pod_pressure.attribute(&mut insight.top_pods);
+        }
+        // Collected before broadcasting so notifiers can link to it.
+        let evidence = self
+            .evidence
+            .as_ref()
+            .and_then(|c| c.collect(&insight))
+            .map(Arc::new);
+        if let (Some(bundle), Some(path)) = (&evidence, &self.file_path) {
+            self.persist_evidence(path, bundle);
+        }
+        let timestamp = current_epoch_secs();
         let record = InsightRecord {
-            timestamp: current_epoch_secs(),
+            timestamp,
             insight: insight.clone(),
             feedback: None,
+            recent_changes: self
+                .changes
+                .as_ref()
+                .map(|c| c.preceding(timestamp))
+                .unwrap_or_default(),
+            evidence,
         };
 
         {
@@ -52,6 +114,9 @@ impl InsightStore {
             inner.push_back(record.clone());
         }
 
+        // No subscribers is the common case; ignore the send error.
+        let _ = self.tx.send(record.clone());
+
         if let Some(path) = &self.file_path {
             if let Err(err) = ensure_parent(path) {
                 warn!("[insights] failed to create directory {:?}: {}", path, err);
@@ -75,11 +140,68 @@ impl InsightStore {
         inner.iter().rev().take(limit).cloned().collect::<Vec<_>>()
     }
 
+    /// Drop in-memory insights recorded before `cutoff` (epoch seconds).
+    pub fn prune_before(&self, cutoff: u64) -> usize {
+        let mut inner = self.inner.lock().unwrap();
+        let before = inner.len();
+        inner.retain(|r| r.timestamp >= cutoff);
+        before - inner.len()
+    }
+
+    pub fn file_path(&self) -> Option<&Path> {
+        self.file_path.as_deref()
+    }
+
     pub fn get_by_id(&self, id: &str) -> Option<InsightRecord> {
         let inner = self.inner.lock().unwrap();
         inner.iter().find(|r| r.insight.id == id).cloned()
     }
 
+    /// Evidence bundle of an insight, from memory or, after it was evicted
+    /// or the daemon restarted, from disk.
+    pub fn evidence(&self, id: &str) -> Option<Arc<EvidenceBundle>> {
+        if let Some(bundle) = self.get_by_id(id).and_then(|r| r.evidence) {
+            return Some(bundle);
+        }
+        let path = evidence_dir(self.file_path.as_deref()?).join(evidence_file_name(id)?);
+        let data = std::fs::read(path).ok()?;
+        serde_json::from_slice(&data).ok().map(Arc::new)
+    }
+
+    /// Write a bundle to the evidence directory, keeping at most `capacity`
+    /// bundles there.
+    fn persist_evidence(&self, insights_path: &Path, bundle: &EvidenceBundle) {
+        let Some(name) = evidence_file_name(&bundle.insight_id) else {
+            return;
+        };
+        let dir = evidence_dir(insights_path);
+        let result = std::fs::create_dir_all(&dir).and_then(|_| {
+            let data = serde_json::to_vec(bundle).map_err(std::io::Error::other)?;
+            std::fs::write(dir.join(name), data)
+        });
+        if let Err(err) = result {
+            warn!(
+                "[insights] failed to write evidence to {}: {}",
+                dir.display(),
+                err
+            );
+            return;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return;
+        };
+        let mut files: Vec<(SystemTime, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect();
+        if files.len() > self.capacity {
+            files.sort();
+            for (_, path) in &files[..files.len() - self.capacity] {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
     pub fn update_feedback(&self, id: &str, rating: Feedback) -> bool {
         let mut inner = self.inner.lock().unwrap();
         if let Some(record) = inner.iter_mut().find(|r| r.insight.id == id) {
@@ -92,13 +214,7 @@ impl InsightStore {
 
             // Persist feedback to disk
             if let Some(path) = &self.file_path {
-                // Convert PathBuf to parent dir + stem, then append _feedback.json
-                let parent = path.parent().unwrap_or_else(|| Path::new("."));
-                let stem = path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("insights");
-                let feedback_path = parent.join(format!("{}_feedback.json", stem));
+                let feedback_path = feedback_path(path);
 
                 let feedback_entry = serde_json::json!({
                     "insight_id": id,
@@ -124,6 +240,37 @@ impl InsightStore {
     }
 }
 
+/// Feedback log written next to the insights file:
+/// `<dir>/<stem>_feedback.json`.
+pub fn feedback_path(insights_path: &Path) -> PathBuf {
+    let parent = insights_path.parent().unwrap_or_else(|| Path::new("."));
+    let stem = insights_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("insights");
+    parent.join(format!("{}_feedback.json", stem))
+}
+
+/// Evidence bundles written next to the insights file, one per insight:
+/// `<dir>/<stem>_evidence/<id>.json`.
+pub fn evidence_dir(insights_path: &Path) -> PathBuf {
+    let parent = insights_path.parent().unwrap_or_else(|| Path::new("."));
+    let stem = insights_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("insights");
+    parent.join(format!("{}_evidence", stem))
+}
+
+/// File name for an insight id; `None` for ids that aren't safe as one.
+fn evidence_file_name(id: &str) -> Option<String> {
+    let safe = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    safe.then(|| format!("{id}.json"))
+}
+
 fn current_epoch_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -201,4 +348,66 @@ mod tests {
             "Audit trail should contain the insight explanation"
         );
     }
+
+    #[test]
+    fn anomaly_evidence_is_kept_on_disk_up_to_capacity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("insights.jsonl");
+        let context = Arc::new(crate::context::ContextStore::new(
+            std::time::Duration::from_secs(60),
+            100,
+            None,
+        ));
+        let collector = Arc::new(EvidenceCollector::new(Default::default(), context, None));
+        let store = InsightStore::new(2, Some(path.clone())).with_evidence(collector);
+
+        store.record(sample_insight(0));
+        assert!(store.evidence("test-id-0").is_none(), "normal insight");
+        for i in 1..=3 {
+            let mut insight = sample_insight(i);
+            insight.reason_code = InsightReason::OomRisk;
+            store.record(insight);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(store.get_by_id("test-id-3").unwrap().evidence.is_some());
+
+        // A fresh store (daemon restart) reads bundles back from disk.
+        let reopened = InsightStore::new(2, Some(path.clone()));
+        assert_eq!(
+            reopened.evidence("test-id-3").unwrap().reason_code,
+            "oom_risk"
+        );
+        assert!(reopened.evidence("test-id-1").is_none(), "pruned");
+        assert!(reopened.evidence("../insights").is_none());
+        assert_eq!(std::fs::read_dir(evidence_dir(&path)).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn recorded_insights_are_broadcast() {
+        let store = InsightStore::new(4, None);
+        let mut rx = store.subscribe();
+
+        store.record(sample_insight(7));
+
+        let record = rx.try_recv().expect("subscriber should see the insight");
+        assert_eq!(record.insight.id, "test-id-7");
+    }
+
+    #[test]
+    fn insights_list_changes_just_before_them() {
+        let changes = Arc::new(ChangeLog::default());
+        let deploy: ChangeEvent =
+            serde_json::from_str(r#"{"service": "checkout", "version": "v2"}"#).unwrap();
+        changes.record(deploy);
+        let store = InsightStore::new(4, None).with_changes(changes);
+
+        store.record(sample_insight(1));
+
+        let record = store.get_by_id("test-id-1").unwrap();
+        assert_eq!(record.recent_changes.len(), 1);
+        assert_eq!(
+            record.recent_changes[0].to_string(),
+            "deploy of checkout v2"
+        );
+    }
 }
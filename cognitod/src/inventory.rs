@@ -0,0 +1,414 @@
+//! Static host facts: kernel, distro, CPU, memory, cloud instance and GPUs.
+//!
+//! Collected once at startup and attached to alert notifications, insight
+//! webhooks, incident analysis prompts and exports, since remediation advice
+//! often depends on them ("upgrade past 5.15", "a t3.small can't hold this
+//! working set"). The instance type comes from the cloud metadata service,
+//! which is only asked when DMI identifies the host as a VM on that cloud, so
+//! bare metal never waits on a link-local timeout.
+
+use crate::config::InventoryConfig;
+use log::debug;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Link-local address of the AWS, GCP and Azure metadata services.
+const METADATA_BASE: &str = "http://169.254.169.254";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostFacts {
+    pub kernel: String,
+    /// `PRETTY_NAME` from os-release, e.g. `Ubuntu 22.04.4 LTS`.
+    pub distro: Option<String>,
+    pub arch: String,
+    pub cpu_model: Option<String>,
+    pub cpus: usize,
+    pub memory_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cloud: Option<CloudInstance>,
+    #[serde(default)]
+    pub gpus: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CloudInstance {
+    pub provider: CloudProvider,
+    /// `m5.xlarge`, `e2-standard-4`, `Standard_D4s_v5`; unknown until the
+    /// metadata service answers.
+    pub instance_type: Option<String>,
+    /// Region on AWS and Azure, zone on GCP.
+    pub region: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloudProvider {
+    Aws,
+    Gcp,
+    Azure,
+}
+
+impl CloudProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CloudProvider::Aws => "aws",
+            CloudProvider::Gcp => "gcp",
+            CloudProvider::Azure => "azure",
+        }
+    }
+
+    /// Cloud this VM runs on, from the DMI strings its hypervisor sets.
+    fn from_dmi(sys_vendor: &str, product_name: &str, bios_version: &str) -> Option<Self> {
+        if sys_vendor.starts_with("Amazon") || bios_version.contains("amazon") {
+            Some(CloudProvider::Aws)
+        } else if sys_vendor == "Google" || product_name == "Google Compute Engine" {
+            Some(CloudProvider::Gcp)
+        } else if sys_vendor == "Microsoft Corporation" && product_name == "Virtual Machine" {
+            // Also local Hyper-V; the metadata query then just times out.
+            Some(CloudProvider::Azure)
+        } else {
+            None
+        }
+    }
+}
+
+impl HostFacts {
+    /// Facts about this host, asking the metadata service for the instance
+    /// type when configured to.
+    pub async fn collect(config: &InventoryConfig) -> Self {
+        let mut facts = Self::read_from(Path::new("/"));
+        if config.cloud_metadata
+            && let Some(cloud) = facts.cloud.as_mut()
+        {
+            let timeout = Duration::from_millis(config.metadata_timeout_ms);
+            match query_metadata(cloud.provider, timeout).await {
+                Ok((instance_type, region)) => {
+                    cloud.instance_type = instance_type;
+                    cloud.region = region;
+                }
+                Err(e) => debug!(
+                    "[inventory] {} metadata service unavailable: {e}",
+                    cloud.provider.as_str()
+                ),
+            }
+        }
+        facts
+    }
+
+    fn read_from(root: &Path) -> Self {
+        let read = |p: &str| fs::read_to_string(root.join(p)).ok();
+        let dmi = |f: &str| {
+            read(&format!("sys/class/dmi/id/{f}"))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        let cpuinfo = read("proc/cpuinfo").unwrap_or_default();
+        let cpus = cpuinfo
+            .lines()
+            .filter(|l| l.starts_with("processor"))
+            .count();
+
+        HostFacts {
+            kernel: read("proc/sys/kernel/osrelease")
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            distro: read("etc/os-release")
+                .or_else(|| read("usr/lib/os-release"))
+                .and_then(|s| os_release_name(&s)),
+            arch: std::env::consts::ARCH.to_string(),
+            cpu_model: cpu_model(&cpuinfo),
+            cpus: if cpus > 0 {
+                cpus
+            } else {
+                std::thread::available_parallelism().map_or(0, |n| n.get())
+            },
+            memory_bytes: read("proc/meminfo")
+                .and_then(|s| mem_total_bytes(&s))
+                .unwrap_or(0),
+            cloud: CloudProvider::from_dmi(
+                &dmi("sys_vendor"),
+                &dmi("product_name"),
+                &dmi("bios_version"),
+            )
+            .map(|provider| CloudInstance {
+                provider,
+                instance_type: None,
+                region: None,
+            }),
+            gpus: nvidia_gpus(&root.join("proc/driver/nvidia/gpus")),
+        }
+    }
+}
+
+/// One line for prompts and notifications, e.g.
+/// `aws m5.xlarge (us-east-1), 4x Intel Xeon, 15.6 GiB, Ubuntu 22.04, kernel 6.1.0, 1x NVIDIA A10G`.
+impl fmt::Display for HostFacts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(cloud) = &self.cloud {
+            let mut s = cloud.provider.as_str().to_string();
+            if let Some(t) = &cloud.instance_type {
+                s.push(' ');
+                s.push_str(t);
+            }
+            if let Some(r) = &cloud.region {
+                s.push_str(&format!(" ({r})"));
+            }
+            parts.push(s);
+        }
+        parts.push(match &self.cpu_model {
+            Some(model) => format!("{}x {model}", self.cpus),
+            None => format!("{} CPUs", self.cpus),
+        });
+        parts.push(format!(
+            "{:.1} GiB",
+            self.memory_bytes as f64 / (1u64 << 30) as f64
+        ));
+        if let Some(distro) = &self.distro {
+            parts.push(distro.clone());
+        }
+        let mut kernel = format!("kernel {}", self.kernel);
+        if !self.arch.is_empty() {
+            kernel.push(' ');
+            kernel.push_str(&self.arch);
+        }
+        parts.push(kernel);
+        let mut gpus: Vec<(&str, usize)> = Vec::new();
+        for gpu in &self.gpus {
+            match gpus.iter_mut().find(|(m, _)| m == gpu) {
+                Some((_, n)) => *n += 1,
+                None => gpus.push((gpu, 1)),
+            }
+        }
+        for (model, n) in gpus {
+            parts.push(format!("{n}x {model}"));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+fn os_release_name(content: &str) -> Option<String> {
+    let field = |key: &str| {
+        content.lines().find_map(|l| {
+            let value = l.strip_prefix(key)?.strip_prefix('=')?;
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            (!value.is_empty()).then(|| value.to_string())
+        })
+    };
+    field("PRETTY_NAME").or_else(|| field("NAME"))
+}
+
+fn cpu_model(cpuinfo: &str) -> Option<String> {
+    // x86 has "model name"; many arm64 kernels only say "Hardware" or
+    // nothing at all.
+    ["model name", "Hardware", "cpu model"]
+        .iter()
+        .find_map(|key| {
+            cpuinfo.lines().find_map(|l| {
+                let (k, v) = l.split_once(':')?;
+                (k.trim() == *key).then(|| v.split_whitespace().collect::<Vec<_>>().join(" "))
+            })
+        })
+        .filter(|m| !m.is_empty())
+}
+
+fn mem_total_bytes(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Models from `/proc/driver/nvidia/gpus/<bus id>/information`.
+fn nvidia_gpus(dir: &Path) -> Vec<String> {
+    let mut gpus: Vec<(String, String)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let info = fs::read_to_string(e.path().join("information")).ok()?;
+            let model = info.lines().find_map(|l| {
+                let (k, v) = l.split_once(':')?;
+                (k.trim() == "Model").then(|| v.trim().to_string())
+            })?;
+            Some((e.file_name().to_string_lossy().into_owned(), model))
+        })
+        .collect();
+    gpus.sort();
+    gpus.into_iter().map(|(_, model)| model).collect()
+}
+
+/// Instance type and region (zone on GCP) from the metadata service.
+async fn query_metadata(
+    provider: CloudProvider,
+    timeout: Duration,
+) -> reqwest::Result<(Option<String>, Option<String>)> {
+    let client = Client::builder().timeout(timeout).no_proxy().build()?;
+    match provider {
+        CloudProvider::Aws => {
+            // IMDSv2: session token first, then the attributes.
+            let token = client
+                .put(format!("{METADATA_BASE}/latest/api/token"))
+                .header("X-aws-ec2-metadata-token-ttl-seconds", "60")
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let get = |path: &str| {
+                client
+                    .get(format!("{METADATA_BASE}/latest/meta-data/{path}"))
+                    .header("X-aws-ec2-metadata-token", &token)
+                    .send()
+            };
+            let instance_type = get("instance-type")
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let region = get("placement/region").await?.text().await.ok();
+            Ok((Some(instance_type), region))
+        }
+        CloudProvider::Gcp => {
+            let get = |path: &str| {
+                client
+                    .get(format!(
+                        "{METADATA_BASE}/computeMetadata/v1/instance/{path}"
+                    ))
+                    .header("Metadata-Flavor", "Google")
+                    .send()
+            };
+            // Both come back as full resource paths:
+            // `projects/<n>/machineTypes/e2-medium`.
+            let machine_type = get("machine-type")
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let zone = get("zone").await?.text().await.ok();
+            Ok((
+                last_segment(&machine_type),
+                zone.as_deref().and_then(last_segment),
+            ))
+        }
+        CloudProvider::Azure => {
+            let compute: serde_json::Value = client
+                .get(format!(
+                    "{METADATA_BASE}/metadata/instance/compute?api-version=2021-02-01"
+                ))
+                .header("Metadata", "true")
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            let field = |k: &str| compute[k].as_str().map(str::to_string);
+            Ok((field("vmSize"), field("location")))
+        }
+    }
+}
+
+fn last_segment(path: &str) -> Option<String> {
+    path.trim()
+        .rsplit('/')
+        .next()
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn reads_facts_from_proc_sys_and_os_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        write(root, "proc/sys/kernel/osrelease", "6.1.0-18-cloud-amd64\n");
+        write(
+            root,
+            "etc/os-release",
+            "NAME=\"Debian GNU/Linux\"\nPRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\n",
+        );
+        let cpu =
+            "processor\t: {n}\nmodel name\t: Intel(R) Xeon(R)  Platinum 8259CL CPU @ 2.50GHz\n\n";
+        write(
+            root,
+            "proc/cpuinfo",
+            &(0..4)
+                .map(|n| cpu.replace("{n}", &n.to_string()))
+                .collect::<String>(),
+        );
+        write(
+            root,
+            "proc/meminfo",
+            "MemTotal:       16384000 kB\nMemFree: 1 kB\n",
+        );
+        write(root, "sys/class/dmi/id/sys_vendor", "Amazon EC2\n");
+        for (bus, model) in [
+            ("0000:00:1f.0", "NVIDIA A10G"),
+            ("0000:00:1e.0", "NVIDIA A10G"),
+        ] {
+            write(
+                root,
+                &format!("proc/driver/nvidia/gpus/{bus}/information"),
+                &format!("Model: \t\t {model}\nIRQ:   \t\t 42\n"),
+            );
+        }
+
+        let mut facts = HostFacts::read_from(root);
+        assert_eq!(facts.kernel, "6.1.0-18-cloud-amd64");
+        assert_eq!(
+            facts.distro.as_deref(),
+            Some("Debian GNU/Linux 12 (bookworm)")
+        );
+        assert_eq!(
+            facts.cpu_model.as_deref(),
+            Some("Intel(R) Xeon(R) Platinum 8259CL CPU @ 2.50GHz")
+        );
+        assert_eq!(facts.cpus, 4);
+        assert_eq!(facts.memory_bytes, 16_384_000 * 1024);
+        assert_eq!(facts.gpus, ["NVIDIA A10G", "NVIDIA A10G"]);
+        let cloud = facts.cloud.as_mut().unwrap();
+        assert_eq!(cloud.provider, CloudProvider::Aws);
+        cloud.instance_type = Some("g5.xlarge".into());
+        cloud.region = Some("us-east-1".into());
+
+        facts.arch = "x86_64".into();
+        assert_eq!(
+            facts.to_string(),
+            "aws g5.xlarge (us-east-1), 4x Intel(R) Xeon(R) Platinum 8259CL CPU @ 2.50GHz, \
+             15.6 GiB, Debian GNU/Linux 12 (bookworm), kernel 6.1.0-18-cloud-amd64 x86_64, \
+             2x NVIDIA A10G"
+        );
+    }
+
+    #[test]
+    fn bare_metal_has_no_cloud_and_tolerates_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "sys/class/dmi/id/sys_vendor", "Dell Inc.\n");
+        let facts = HostFacts::read_from(dir.path());
+        assert_eq!(facts.kernel, "unknown");
+        assert!(facts.cloud.is_none());
+        assert!(facts.gpus.is_empty());
+        assert!(facts.cpus > 0, "falls back to available parallelism");
+
+        assert_eq!(
+            CloudProvider::from_dmi("Google", "Google Compute Engine", ""),
+            Some(CloudProvider::Gcp)
+        );
+        assert_eq!(
+            last_segment("projects/123/machineTypes/e2-standard-4"),
+            Some("e2-standard-4".to_string())
+        );
+    }
+}
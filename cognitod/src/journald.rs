@@ -0,0 +1,116 @@
+//! Alerts to the systemd journal, as structured entries.
+//!
+//! Entries go straight to journald's native socket rather than through
+//! `logger`, so each carries its own fields (`RULE`, `SEVERITY`, `HOST`,
+//! `PID`, `POD`, ...) that `journalctl RULE=fork_burst` and journald scrapers
+//! such as Loki's can filter on, and no process is spawned per alert.
+
+use crate::alerts::{Alert, Severity};
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+
+/// journald's native protocol socket.
+pub const SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Alert labels passed on as fields, with their field names.
+const LABEL_FIELDS: [(&str, &str); 4] = [
+    ("pid", "PID"),
+    ("pod", "POD"),
+    ("namespace", "NAMESPACE"),
+    ("container", "CONTAINER"),
+];
+
+/// Send `alert` to the journal at `socket`. Never blocks: when journald is
+/// backed up the entry is dropped (the alerts file still has it).
+pub fn send(socket: &Path, alert: &Alert) -> io::Result<()> {
+    let sock = UnixDatagram::unbound()?;
+    sock.set_nonblocking(true)?;
+    sock.send_to(&entry(alert), socket)?;
+    Ok(())
+}
+
+/// Native-protocol datagram of `alert`.
+fn entry(alert: &Alert) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let message = format!("{} - {}", alert.rule, alert.message);
+    field(&mut buf, "MESSAGE", &message);
+    field(&mut buf, "PRIORITY", priority(&alert.severity));
+    field(&mut buf, "SYSLOG_IDENTIFIER", "linnix");
+    field(&mut buf, "RULE", &alert.rule);
+    field(&mut buf, "SEVERITY", alert.severity.as_str());
+    field(&mut buf, "HOST", &alert.host);
+    for (label, name) in LABEL_FIELDS {
+        if let Some(value) = alert.labels.get(label) {
+            field(&mut buf, name, value);
+        }
+    }
+    if let Some(secs) = alert.resolved_after_secs {
+        field(&mut buf, "RESOLVED_AFTER_SECS", &secs.to_string());
+    }
+    buf
+}
+
+/// Syslog priority of a severity.
+fn priority(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::High => "3",
+        Severity::Medium => "4",
+        Severity::Low => "5",
+        Severity::Info => "6",
+    }
+}
+
+/// Append `name=value`; a value with a newline is sent length-prefixed.
+fn field(buf: &mut Vec<u8>, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_structured_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("socket");
+        let journal = UnixDatagram::bind(&path).unwrap();
+        let alert = Alert {
+            rule: "fork_burst".to_string(),
+            severity: Severity::High,
+            message: "line one\nline two".to_string(),
+            host: "node-1".to_string(),
+            resolved_after_secs: None,
+            labels: [
+                ("pid".to_string(), "42".to_string()),
+                ("pod".to_string(), "api-1".to_string()),
+                ("comm".to_string(), "bash".to_string()),
+            ]
+            .into(),
+        };
+        send(&path, &alert).unwrap();
+
+        let mut received = vec![0; 4096];
+        let len = journal.recv(&mut received).unwrap();
+        let message = "fork_burst - line one\nline two";
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&(message.len() as u64).to_le_bytes());
+        expected.extend_from_slice(message.as_bytes());
+        expected.extend_from_slice(
+            b"\nPRIORITY=3\nSYSLOG_IDENTIFIER=linnix\nRULE=fork_burst\nSEVERITY=high\n\
+              HOST=node-1\nPID=42\nPOD=api-1\n",
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&received[..len]),
+            String::from_utf8_lossy(&expected)
+        );
+    }
+}
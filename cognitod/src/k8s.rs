@@ -1,3 +1,6 @@
+use crate::ProcessEvent;
+use crate::network::Network;
+use crate::pidmap::PidContainerMap;
 use log::{debug, info, warn};
 use reqwest::Client;
 use serde::Deserialize;
@@ -42,6 +45,8 @@ pub struct K8sMetadata {
 pub struct K8sContext {
     // Map from Container ID (stripped) to Metadata
     container_map: RwLock<HashMap<String, K8sMetadata>>,
+    // PID to container ID, maintained from the event stream
+    pids: Arc<PidContainerMap>,
     client: Client,
     api_url: String,
     token: String,
@@ -49,7 +54,7 @@ pub struct K8sContext {
 }
 
 impl K8sContext {
-    pub fn new() -> Option<Arc<Self>> {
+    pub fn new(network: &Network) -> Option<Arc<Self>> {
         let (api_url, token, ca_cert) = if let (Ok(url), Ok(t)) =
             (std::env::var("K8S_API_URL"), std::env::var("K8S_TOKEN"))
         {
@@ -72,7 +77,7 @@ impl K8sContext {
             .or_else(|| std::env::var("HOSTNAME").ok())
             .unwrap_or_else(|| "localhost".to_string());
 
-        let mut builder = Client::builder();
+        let mut builder = network.client_builder();
         if let Some(ca) = ca_cert {
             builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&ca).ok()?);
         } else {
@@ -84,6 +89,7 @@ impl K8sContext {
 
         Some(Arc::new(Self {
             container_map: RwLock::new(HashMap::new()),
+            pids: Arc::new(PidContainerMap::default()),
             client,
             api_url,
             token,
@@ -194,32 +200,29 @@ impl K8sContext {
         Ok(())
     }
 
+    /// Authenticated request to the API server; `path` starts with `/`.
+    pub fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", self.api_url, path))
+            .header("Authorization", format!("Bearer {}", self.token))
+    }
+
+    /// Shared PID → container map; feed it events via
+    /// [`PidContainerMap::observe`].
+    pub fn pid_map(&self) -> &Arc<PidContainerMap> {
+        &self.pids
+    }
+
     pub fn get_metadata_for_pid(&self, pid: u32) -> Option<K8sMetadata> {
-        // Read /proc/<pid>/cgroup
-        let content = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
-
-        // Parse cgroup to find container ID
-        // Format: 0::/kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod<uid>.slice/cri-containerd-<id>.scope
-        // Or similar. We look for a 64-char hex string.
-
-        for line in content.lines() {
-            // Simple heuristic: look for last part that looks like a container ID
-            if let Some(last_part) = line.split('/').next_back() {
-                // Remove .scope suffix if present
-                let clean = last_part.trim_end_matches(".scope");
-                // Remove prefix like "cri-containerd-" or "docker-"
-                let id = if let Some(idx) = clean.rfind('-') {
-                    &clean[idx + 1..]
-                } else {
-                    clean
-                };
+        let container_id = self.pids.container_id(pid)?;
+        self.get_metadata(&container_id)
+    }
 
-                if id.len() == 64 {
-                    return self.get_metadata(id);
-                }
-            }
-        }
-        None
+    /// Metadata of the process an event was recorded in; uses the event's
+    /// cgroup ID, so it works after the process has exited.
+    pub fn get_metadata_for_event(&self, event: &ProcessEvent) -> Option<K8sMetadata> {
+        let container_id = self.pids.container_for_event(event)?;
+        self.get_metadata(&container_id)
     }
 
     pub fn get_metadata(&self, container_id: &str) -> Option<K8sMetadata> {
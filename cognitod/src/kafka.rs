@@ -0,0 +1,420 @@
+//! Kafka export of alerts, insights and raw events.
+//!
+//! For teams that fan observability data into stream processors: alerts and
+//! insights are published to their own topics as they are recorded and,
+//! with `events_topic` set, every pipeline event is too. Messages are keyed
+//! by host so one host's records stay ordered on one partition, and encoded
+//! as JSON or as Avro single-object encoding (schemas below).
+
+use crate::ProcessEvent;
+use crate::alerts::Alert;
+use crate::config::KafkaConfig;
+use crate::handler::Handler;
+use crate::insights::InsightRecord;
+use crate::redaction::{Destination, RedactionPolicy};
+use crate::types::SystemSnapshot;
+use anyhow::{Context, Result, bail};
+use apache_avro::rabin::Rabin;
+use apache_avro::types::Value as Avro;
+use apache_avro::{Schema, to_avro_datum};
+use async_trait::async_trait;
+use log::{debug, error, info};
+use rdkafka::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// How long an alert or insight may wait for room in the producer queue.
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const ALERT_SCHEMA: &str = r#"{
+  "type": "record", "name": "Alert", "namespace": "io.linnix",
+  "fields": [
+    {"name": "rule", "type": "string"},
+    {"name": "severity", "type": "string"},
+    {"name": "message", "type": "string"},
+    {"name": "host", "type": "string"},
+    {"name": "resolved_after_secs", "type": ["null", "long"], "default": null},
+    {"name": "labels", "type": {"type": "map", "values": "string"}}
+  ]
+}"#;
+
+pub const INSIGHT_SCHEMA: &str = r#"{
+  "type": "record", "name": "Insight", "namespace": "io.linnix",
+  "fields": [
+    {"name": "timestamp", "type": "long"},
+    {"name": "host", "type": "string"},
+    {"name": "id", "type": "string"},
+    {"name": "reason_code", "type": "string"},
+    {"name": "summary", "type": "string"},
+    {"name": "confidence", "type": "float"},
+    {"name": "suggested_next_step", "type": "string"},
+    {"name": "primary_process", "type": ["null", "string"], "default": null},
+    {"name": "top_pods", "type": {"type": "array", "items": {
+      "type": "record", "name": "PodContribution",
+      "fields": [
+        {"name": "namespace", "type": "string"},
+        {"name": "pod", "type": "string"},
+        {"name": "cpu_usage", "type": "float"},
+        {"name": "psi_contribution", "type": "float"}
+      ]
+    }}}
+  ]
+}"#;
+
+pub const EVENT_SCHEMA: &str = r#"{
+  "type": "record", "name": "ProcessEvent", "namespace": "io.linnix",
+  "fields": [
+    {"name": "host", "type": "string"},
+    {"name": "event_type", "type": "int"},
+    {"name": "ts_ns", "type": "long"},
+    {"name": "seq", "type": "long"},
+    {"name": "pid", "type": "long"},
+    {"name": "ppid", "type": "long"},
+    {"name": "uid", "type": "long"},
+    {"name": "gid", "type": "long"},
+    {"name": "comm", "type": "string"},
+    {"name": "exit_time_ns", "type": "long"},
+    {"name": "cpu_pct_milli", "type": "int"},
+    {"name": "mem_pct_milli", "type": "int"},
+    {"name": "data", "type": "long"},
+    {"name": "data2", "type": "long"},
+    {"name": "aux", "type": "long"},
+    {"name": "aux2", "type": "long"},
+    {"name": "cgroup_id", "type": "long"},
+    {"name": "argv", "type": ["null", {"type": "array", "items": "string"}], "default": null},
+    {"name": "path", "type": ["null", "string"], "default": null}
+  ]
+}"#;
+
+/// A parsed schema and its single-object header (marker and fingerprint).
+struct Writer {
+    schema: Schema,
+    header: Vec<u8>,
+}
+
+impl Writer {
+    fn new(json: &str) -> Result<Self> {
+        let schema = Schema::parse_str(json)?;
+        let mut header = vec![0xC3, 0x01];
+        header.extend_from_slice(&schema.fingerprint::<Rabin>().bytes);
+        Ok(Self { schema, header })
+    }
+
+    fn encode(&self, value: Avro) -> Result<Vec<u8>> {
+        let mut buf = self.header.clone();
+        buf.extend(to_avro_datum(&self.schema, value)?);
+        Ok(buf)
+    }
+}
+
+enum Encoding {
+    Json,
+    Avro {
+        alert: Writer,
+        insight: Writer,
+        event: Writer,
+    },
+}
+
+impl Encoding {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "json" => Encoding::Json,
+            "avro" => Encoding::Avro {
+                alert: Writer::new(ALERT_SCHEMA)?,
+                insight: Writer::new(INSIGHT_SCHEMA)?,
+                event: Writer::new(EVENT_SCHEMA)?,
+            },
+            other => bail!("unknown encoding '{other}' (expected json or avro)"),
+        })
+    }
+
+    fn alert(&self, alert: &Alert) -> Result<Vec<u8>> {
+        let Encoding::Avro { alert: writer, .. } = self else {
+            return Ok(serde_json::to_vec(alert)?);
+        };
+        writer.encode(Avro::Record(vec![
+            ("rule".into(), alert.rule.as_str().into()),
+            ("severity".into(), alert.severity.as_str().into()),
+            ("message".into(), alert.message.as_str().into()),
+            ("host".into(), alert.host.as_str().into()),
+            (
+                "resolved_after_secs".into(),
+                optional(alert.resolved_after_secs.map(|s| Avro::Long(s as i64))),
+            ),
+            (
+                "labels".into(),
+                Avro::Map(
+                    alert
+                        .labels
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.as_str().into()))
+                        .collect(),
+                ),
+            ),
+        ]))
+    }
+
+    fn insight(&self, record: &InsightRecord, host: &str) -> Result<Vec<u8>> {
+        let Encoding::Avro {
+            insight: writer, ..
+        } = self
+        else {
+            return Ok(serde_json::to_vec(record)?);
+        };
+        let insight = &record.insight;
+        let top_pods = insight
+            .top_pods
+            .iter()
+            .map(|pod| {
+                Avro::Record(vec![
+                    ("namespace".into(), pod.namespace.as_str().into()),
+                    ("pod".into(), pod.pod.as_str().into()),
+                    ("cpu_usage".into(), Avro::Float(pod.cpu_usage)),
+                    ("psi_contribution".into(), Avro::Float(pod.psi_contribution)),
+                ])
+            })
+            .collect();
+        writer.encode(Avro::Record(vec![
+            ("timestamp".into(), Avro::Long(record.timestamp as i64)),
+            ("host".into(), host.into()),
+            ("id".into(), insight.id.as_str().into()),
+            ("reason_code".into(), insight.reason_code.as_str().into()),
+            ("summary".into(), insight.summary.as_str().into()),
+            ("confidence".into(), Avro::Float(insight.confidence)),
+            (
+                "suggested_next_step".into(),
+                insight.suggested_next_step.as_str().into(),
+            ),
+            (
+                "primary_process".into(),
+                optional(insight.primary_process.as_deref().map(Avro::from)),
+            ),
+            ("top_pods".into(), Avro::Array(top_pods)),
+        ]))
+    }
+
+    fn event(&self, event: &ProcessEvent, host: &str) -> Result<Vec<u8>> {
+        let Encoding::Avro { event: writer, .. } = self else {
+            return Ok(serde_json::to_vec(event)?);
+        };
+        let comm = String::from_utf8_lossy(&event.comm);
+        writer.encode(Avro::Record(vec![
+            ("host".into(), host.into()),
+            ("event_type".into(), Avro::Int(event.event_type as i32)),
+            ("ts_ns".into(), Avro::Long(event.ts_ns as i64)),
+            ("seq".into(), Avro::Long(event.seq as i64)),
+            ("pid".into(), Avro::Long(event.pid.into())),
+            ("ppid".into(), Avro::Long(event.ppid.into())),
+            ("uid".into(), Avro::Long(event.uid.into())),
+            ("gid".into(), Avro::Long(event.gid.into())),
+            ("comm".into(), comm.trim_end_matches('\0').into()),
+            ("exit_time_ns".into(), Avro::Long(event.exit_time_ns as i64)),
+            (
+                "cpu_pct_milli".into(),
+                Avro::Int(event.cpu_pct_milli.into()),
+            ),
+            (
+                "mem_pct_milli".into(),
+                Avro::Int(event.mem_pct_milli.into()),
+            ),
+            ("data".into(), Avro::Long(event.data as i64)),
+            ("data2".into(), Avro::Long(event.data2 as i64)),
+            ("aux".into(), Avro::Long(event.aux.into())),
+            ("aux2".into(), Avro::Long(event.aux2.into())),
+            ("cgroup_id".into(), Avro::Long(event.cgroup_id as i64)),
+            (
+                "argv".into(),
+                optional(
+                    event.argv.as_ref().map(|argv| {
+                        Avro::Array(argv.iter().map(|arg| arg.as_str().into()).collect())
+                    }),
+                ),
+            ),
+            (
+                "path".into(),
+                optional(event.path.as_deref().map(Avro::from)),
+            ),
+        ]))
+    }
+}
+
+/// Value of a `["null", T]` union.
+fn optional(value: Option<Avro>) -> Avro {
+    match value {
+        Some(value) => Avro::Union(1, Box::new(value)),
+        None => Avro::Union(0, Box::new(Avro::Null)),
+    }
+}
+
+/// Kafka producer for alerts, insights and, as an event handler, raw events.
+pub struct KafkaSink {
+    producer: FutureProducer,
+    encoding: Encoding,
+    alerts_topic: String,
+    insights_topic: String,
+    events_topic: Option<String>,
+    host: String,
+    redaction: Arc<RedactionPolicy>,
+}
+
+impl KafkaSink {
+    /// Fails on an unknown encoding or a property librdkafka rejects; the
+    /// brokers are only contacted once something is published.
+    pub fn new(config: &KafkaConfig, redaction: Arc<RedactionPolicy>) -> Result<Self> {
+        let encoding = Encoding::from_name(&config.encoding)?;
+        let mut client = ClientConfig::new();
+        client
+            .set("bootstrap.servers", &config.brokers)
+            .set("client.id", "linnix");
+        for (key, value) in &config.properties {
+            client.set(key, value);
+        }
+        let producer = client.create().context("creating Kafka producer")?;
+        Ok(Self {
+            producer,
+            encoding,
+            alerts_topic: config.alerts_topic.clone(),
+            insights_topic: config.insights_topic.clone(),
+            events_topic: config.events_topic.clone(),
+            host: std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".into()),
+            redaction,
+        })
+    }
+
+    /// Publish every alert until the channel closes.
+    pub async fn run_alerts(self: Arc<Self>, mut rx: broadcast::Receiver<Alert>) {
+        info!("[kafka] publishing alerts to {}", self.alerts_topic);
+        loop {
+            match rx.recv().await {
+                Ok(alert) => {
+                    let alert = self.redaction.redact_alert(Destination::Exports, &alert);
+                    let sent = match self.encoding.alert(&alert) {
+                        Ok(payload) => self.publish(&self.alerts_topic, &payload).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = sent {
+                        error!("[kafka] failed to publish alert '{}': {e:#}", alert.rule);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("[kafka] alert publisher lagged by {n} alerts");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Publish every recorded insight until the channel closes.
+    pub async fn run_insights(self: Arc<Self>, mut rx: broadcast::Receiver<InsightRecord>) {
+        info!("[kafka] publishing insights to {}", self.insights_topic);
+        loop {
+            match rx.recv().await {
+                Ok(mut record) => {
+                    record.insight = self
+                        .redaction
+                        .redact_insight(Destination::Exports, &record.insight);
+                    let sent = match self.encoding.insight(&record, &self.host) {
+                        Ok(payload) => self.publish(&self.insights_topic, &payload).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = sent {
+                        error!(
+                            "[kafka] failed to publish insight {}: {e:#}",
+                            record.insight.id
+                        );
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("[kafka] insight publisher lagged by {n} insights");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Publish and wait for the broker's acknowledgement.
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<()> {
+        let record = FutureRecord::to(topic).key(&self.host).payload(payload);
+        self.producer
+            .send(record, Timeout::After(QUEUE_TIMEOUT))
+            .await
+            .map_err(|(e, _)| e)
+            .with_context(|| format!("publishing to {topic}"))?;
+        debug!("[kafka] published to {topic}");
+        Ok(())
+    }
+}
+
+/// Raw events only queue their message: the pipeline never waits on the
+/// brokers, and a full queue fails the event (counted per handler).
+#[async_trait]
+impl Handler for KafkaSink {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    async fn on_event(&self, event: &ProcessEvent) -> Result<()> {
+        let Some(topic) = &self.events_topic else {
+            return Ok(());
+        };
+        let mut event = event.clone();
+        if let Some(argv) = &mut event.argv {
+            for arg in argv.iter_mut() {
+                *arg = self.redaction.redact_str(Destination::Exports, arg);
+            }
+        }
+        let payload = self.encoding.event(&event, &self.host)?;
+        let record = FutureRecord::to(topic).key(&self.host).payload(&payload);
+        self.producer.send_result(record).map_err(|(e, _)| e)?;
+        Ok(())
+    }
+
+    async fn on_snapshot(&self, _snapshot: &SystemSnapshot) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+    use apache_avro::from_avro_datum;
+
+    #[test]
+    fn encodes_alerts_as_json_or_avro() {
+        let alert = Alert {
+            rule: "fork_burst".to_string(),
+            severity: Severity::High,
+            message: "forks spiking".to_string(),
+            host: "node-1".to_string(),
+            resolved_after_secs: Some(60),
+            labels: [("pid".to_string(), "42".to_string())].into(),
+        };
+
+        let json = Encoding::from_name("json").unwrap().alert(&alert).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["rule"], "fork_burst");
+
+        let encoding = Encoding::from_name("avro").unwrap();
+        let bytes = encoding.alert(&alert).unwrap();
+        let Encoding::Avro { alert: writer, .. } = &encoding else {
+            unreachable!()
+        };
+        assert_eq!(bytes[..10], writer.header[..]);
+        let decoded = from_avro_datum(&writer.schema, &mut &bytes[10..], None).unwrap();
+        let Avro::Record(fields) = decoded else {
+            panic!("not a record: {decoded:?}")
+        };
+        assert_eq!(fields[0], ("rule".into(), "fork_burst".into()));
+        assert_eq!(fields[4].1, optional(Some(Avro::Long(60))));
+
+        // The event and insight schemas parse too.
+        Writer::new(EVENT_SCHEMA).unwrap();
+        Writer::new(INSIGHT_SCHEMA).unwrap();
+        assert!(Encoding::from_name("protobuf").is_err());
+    }
+}
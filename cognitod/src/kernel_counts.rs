@@ -0,0 +1,79 @@
+//! Fork and exec counts kept by the probes (`LIFECYCLE_COUNTS`).
+//!
+//! A fork storm fills the event buffer, so fork and exec events get lost
+//! exactly when the fork-rate rules matter. The probes also count every fork
+//! and exec per CPU; the counts are polled once a second into
+//! [`KernelCounts`], and the rules take the larger of the counted and the
+//! received events over their windows.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// Per-second samples kept, enough for the longest rule windows.
+const MAX_SAMPLES: usize = 3600;
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: Instant,
+    forks: u64,
+    execs: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct KernelCounts {
+    samples: Mutex<VecDeque<Sample>>,
+}
+
+impl KernelCounts {
+    /// Forks and execs counted since the previous sample, taken at `at`.
+    pub fn record(&self, at: Instant, forks: u64, execs: u64) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples.push_back(Sample { at, forks, execs });
+        while samples.len() > MAX_SAMPLES {
+            samples.pop_front();
+        }
+    }
+
+    /// Forks counted in samples taken within `window` of `now`.
+    pub fn forks_within(&self, window: Duration, now: Instant) -> u64 {
+        self.sum_within(window, now, |s| s.forks)
+    }
+
+    /// Execs counted in samples taken within `window` of `now`.
+    pub fn execs_within(&self, window: Duration, now: Instant) -> u64 {
+        self.sum_within(window, now, |s| s.execs)
+    }
+
+    fn sum_within(&self, window: Duration, now: Instant, count: impl Fn(&Sample) -> u64) -> u64 {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples
+            .iter()
+            .rev()
+            .take_while(|s| now.saturating_duration_since(s.at) < window)
+            .map(count)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_samples_inside_the_window() {
+        let counts = KernelCounts::default();
+        let start = Instant::now();
+        for (i, forks) in [100, 2000, 3000].into_iter().enumerate() {
+            counts.record(start + Duration::from_secs(i as u64), forks, 10);
+        }
+        let now = start + Duration::from_millis(2500);
+        assert_eq!(counts.forks_within(Duration::from_secs(1), now), 3000);
+        assert_eq!(counts.forks_within(Duration::from_secs(2), now), 5000);
+        assert_eq!(counts.execs_within(Duration::from_secs(60), now), 30);
+        assert_eq!(
+            KernelCounts::default().forks_within(Duration::from_secs(5), now),
+            0
+        );
+    }
+}
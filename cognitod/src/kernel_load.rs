@@ -0,0 +1,175 @@
+//! Kernel module loads and BPF program load attempts (KernelLoad events).
+
+use crate::ProcessEvent;
+use linnix_ai_ebpf_common::{EventType, KernelLoadKind, kernel_load_name};
+use std::fmt;
+
+/// Name of a kind as used in rules and the API.
+pub fn kind_name(kind: KernelLoadKind) -> &'static str {
+    match kind {
+        KernelLoadKind::Module => "module",
+        KernelLoadKind::BpfProgram => "bpf",
+    }
+}
+
+/// Name of a BPF program type (`enum bpf_prog_type`), as in `bpftool`.
+pub fn prog_type_name(prog_type: u32) -> Option<&'static str> {
+    const NAMES: [&str; 33] = [
+        "unspec",
+        "socket_filter",
+        "kprobe",
+        "sched_cls",
+        "sched_act",
+        "tracepoint",
+        "xdp",
+        "perf_event",
+        "cgroup_skb",
+        "cgroup_sock",
+        "lwt_in",
+        "lwt_out",
+        "lwt_xmit",
+        "sock_ops",
+        "sk_skb",
+        "cgroup_device",
+        "sk_msg",
+        "raw_tracepoint",
+        "cgroup_sock_addr",
+        "lwt_seg6local",
+        "lirc_mode2",
+        "sk_reuseport",
+        "flow_dissector",
+        "cgroup_sysctl",
+        "raw_tracepoint_writable",
+        "cgroup_sockopt",
+        "tracing",
+        "struct_ops",
+        "ext",
+        "lsm",
+        "sk_lookup",
+        "syscall",
+        "netfilter",
+    ];
+    NAMES.get(prog_type as usize).copied()
+}
+
+/// Module taint flags as the kernel prints them after a module's name in
+/// `/proc/modules`, e.g. `OE` for an unsigned out-of-tree module.
+pub fn taint_letters(taints: u32) -> String {
+    // (TAINT_* bit, letter), in the order of `show_module_flags`.
+    const FLAGS: [(u32, char); 5] = [(0, 'P'), (12, 'O'), (1, 'F'), (10, 'C'), (13, 'E')];
+    FLAGS
+        .iter()
+        .filter(|(bit, _)| taints & (1 << bit) != 0)
+        .map(|&(_, letter)| letter)
+        .collect()
+}
+
+/// A decoded KernelLoad event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelLoad {
+    pub kind: KernelLoadKind,
+    /// Module or program name; empty for programs loaded without one.
+    pub name: String,
+    /// Modules: the taint flags the module brought.
+    pub taints: u32,
+    /// BPF programs: the program type.
+    pub prog_type: u32,
+}
+
+impl KernelLoad {
+    pub fn from_event(event: &ProcessEvent) -> Option<Self> {
+        if event.event_type != EventType::KernelLoad as u32 {
+            return None;
+        }
+        let kind = match event.aux {
+            0 => KernelLoadKind::Module,
+            1 => KernelLoadKind::BpfProgram,
+            _ => return None,
+        };
+        let name = kernel_load_name(event.data, event.data2);
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        let (taints, prog_type) = match kind {
+            KernelLoadKind::Module => (event.aux2, 0),
+            KernelLoadKind::BpfProgram => (0, event.aux2),
+        };
+        Some(Self {
+            kind,
+            name: String::from_utf8_lossy(&name[..len]).into_owned(),
+            taints,
+            prog_type,
+        })
+    }
+}
+
+impl fmt::Display for KernelLoad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            KernelLoadKind::Module => {
+                write!(f, "loaded module {}", self.name)?;
+                let letters = taint_letters(self.taints);
+                if !letters.is_empty() {
+                    write!(f, " [{letters}]")?;
+                }
+                Ok(())
+            }
+            KernelLoadKind::BpfProgram => {
+                let prog_type = prog_type_name(self.prog_type).unwrap_or("unknown");
+                if self.name.is_empty() {
+                    write!(f, "loading unnamed {prog_type} BPF program")
+                } else {
+                    write!(f, "loading {prog_type} BPF program {}", self.name)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+    use linnix_ai_ebpf_common::{KERNEL_LOAD_NAME_LEN, kernel_load_name_fields};
+
+    fn load(kind: u32, name: &str, aux2: u32) -> ProcessEvent {
+        let mut bytes = [0u8; KERNEL_LOAD_NAME_LEN];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        let (data, data2) = kernel_load_name_fields(bytes);
+        ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::KernelLoad as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data,
+            data2,
+            aux: kind,
+            aux2,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn decodes_modules_and_programs() {
+        let module = KernelLoad::from_event(&load(0, "nf_tables", 0)).unwrap();
+        assert_eq!(module.kind, KernelLoadKind::Module);
+        assert_eq!(module.to_string(), "loaded module nf_tables");
+
+        // Unsigned out-of-tree module: TAINT_OOT_MODULE | TAINT_UNSIGNED_MODULE.
+        let tainted = KernelLoad::from_event(&load(0, "diamorphine", 1 << 12 | 1 << 13)).unwrap();
+        assert_eq!(tainted.to_string(), "loaded module diamorphine [OE]");
+
+        let prog = KernelLoad::from_event(&load(1, "trace_exec", 2)).unwrap();
+        assert_eq!(prog.prog_type, 2);
+        assert_eq!(prog.to_string(), "loading kprobe BPF program trace_exec");
+        let unnamed = KernelLoad::from_event(&load(1, "", 99)).unwrap();
+        assert_eq!(unnamed.to_string(), "loading unnamed unknown BPF program");
+
+        assert!(KernelLoad::from_event(&load(7, "x", 0)).is_none());
+    }
+}
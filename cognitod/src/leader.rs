@@ -0,0 +1,394 @@
+//! Leader election for warm-standby deployments.
+//!
+//! When several cognitod instances watch the same thing (a standby next to
+//! the active daemon, or replicas of one deployment), only the elected
+//! leader sends notifications and enforces (circuit-breaker kills and
+//! approved actions), so an alert pages once and a process is killed once.
+//! Standbys keep detecting and recording locally and take over when the
+//! leader goes away.
+//!
+//! Two backends: an `flock` on a shared lock file (same host, or a
+//! filesystem with working locks), and a Kubernetes `coordination.k8s.io`
+//! Lease renewed every `renew_interval_secs`.
+
+use crate::config::LeaderElectionConfig;
+use crate::k8s::K8sContext;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use reqwest::{Method, StatusCode};
+use serde_json::{Value, json};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Whether this instance currently holds leadership.
+#[derive(Debug)]
+pub struct Leadership {
+    identity: String,
+    backend: &'static str,
+    leader: AtomicBool,
+}
+
+impl Leadership {
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    pub fn backend(&self) -> &'static str {
+        self.backend
+    }
+
+    fn set(&self, leader: bool) {
+        if self.leader.swap(leader, Ordering::Relaxed) != leader {
+            if leader {
+                info!("[leader] {} is now the leader", self.identity);
+            } else {
+                warn!("[leader] {} lost leadership; standing by", self.identity);
+            }
+        }
+    }
+}
+
+/// True unless leader election is on and another instance leads.
+pub fn is_leader(leadership: &Option<Arc<Leadership>>) -> bool {
+    leadership.as_ref().is_none_or(|l| l.is_leader())
+}
+
+enum Backend {
+    LockFile(LockFile),
+    Lease(Lease),
+}
+
+/// Start campaigning in the background. Every instance starts as a standby.
+pub fn spawn(
+    config: &LeaderElectionConfig,
+    k8s: Option<Arc<K8sContext>>,
+) -> Result<Arc<Leadership>> {
+    let identity = config.identity.clone().unwrap_or_else(default_identity);
+    let (name, mut backend) = match config.backend.as_str() {
+        "lock_file" => (
+            "lock_file",
+            Backend::LockFile(LockFile::new(&config.lock_path, &identity)),
+        ),
+        "k8s_lease" => {
+            let k8s = k8s.context("k8s_lease leader election needs Kubernetes API access")?;
+            if config.renew_interval_secs >= config.lease_duration_secs {
+                bail!("renew_interval_secs must be shorter than lease_duration_secs");
+            }
+            let namespace = config
+                .lease_namespace
+                .clone()
+                .unwrap_or_else(default_namespace);
+            (
+                "k8s_lease",
+                Backend::Lease(Lease {
+                    k8s,
+                    path: format!(
+                        "/apis/coordination.k8s.io/v1/namespaces/{namespace}/leases/{}",
+                        config.lease_name
+                    ),
+                    name: config.lease_name.clone(),
+                    identity: identity.clone(),
+                    duration_secs: config.lease_duration_secs,
+                    // A round is a read and a write: both fit in one interval.
+                    timeout: Duration::from_secs(config.renew_interval_secs.max(1)) / 2,
+                }),
+            )
+        }
+        other => bail!("unknown leader election backend '{other}' (use lock_file or k8s_lease)"),
+    };
+    let leadership = Arc::new(Leadership {
+        identity,
+        backend: name,
+        leader: AtomicBool::new(false),
+    });
+
+    let renew = Duration::from_secs(config.renew_interval_secs.max(1));
+    // How long a renewal keeps us leading: a round started later may end
+    // after the lease it renewed has expired. A lock file never expires.
+    let hold = match backend {
+        Backend::LockFile(_) => None,
+        Backend::Lease(_) => Some(Duration::from_secs(config.lease_duration_secs) - renew),
+    };
+    let state = Arc::clone(&leadership);
+    tokio::spawn(async move {
+        let mut last_renewed: Option<Instant> = None;
+        loop {
+            // The lease is stamped after this, so it lasts at least until
+            // `started` plus its duration.
+            let started = Instant::now();
+            let result = match &mut backend {
+                Backend::LockFile(lock) => lock.try_acquire(),
+                Backend::Lease(lease) => lease.try_acquire().await,
+            };
+            match result {
+                Ok(true) => {
+                    last_renewed = Some(started);
+                    state.set(true);
+                }
+                Ok(false) => state.set(false),
+                Err(e) => warn!("[leader] election failed: {e:#}"),
+            }
+            // Keep leading through brief API errors, but step down before a
+            // standby may take the lease over.
+            let next = Instant::now() + renew;
+            match last_renewed.zip(hold).map(|(t, hold)| t + hold) {
+                Some(step_down) if step_down < next => {
+                    tokio::time::sleep_until(step_down).await;
+                    state.set(false);
+                }
+                _ => {}
+            }
+            tokio::time::sleep_until(next).await;
+        }
+    });
+    Ok(leadership)
+}
+
+fn default_identity() -> String {
+    std::env::var("POD_NAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| {
+            std::fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .map(|h| h.trim().to_string())
+        })
+        .map_or_else(
+            || format!("cognitod-{}", std::process::id()),
+            |host| format!("{host}-{}", std::process::id()),
+        )
+}
+
+fn default_namespace() -> String {
+    std::fs::read_to_string("/var/run/secrets/kubernetes.io/serviceaccount/namespace")
+        .map(|ns| ns.trim().to_string())
+        .unwrap_or_else(|_| "default".to_string())
+}
+
+/// Exclusive `flock` held for as long as this instance leads. The kernel
+/// drops it when the process dies, however it dies.
+struct LockFile {
+    path: String,
+    identity: String,
+    held: Option<File>,
+}
+
+impl LockFile {
+    fn new(path: &str, identity: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            identity: identity.to_string(),
+            held: None,
+        }
+    }
+
+    fn try_acquire(&mut self) -> Result<bool> {
+        if self.held.is_some() {
+            return Ok(true);
+        }
+        if let Some(dir) = std::path::Path::new(&self.path).parent() {
+            std::fs::create_dir_all(dir).ok();
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&self.path)
+            .with_context(|| format!("opening {}", self.path))?;
+        // SAFETY: flock on a descriptor we own.
+        let rc = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+        if rc != 0 {
+            let err = std::io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+                return Ok(false);
+            }
+            return Err(err).with_context(|| format!("locking {}", self.path));
+        }
+        // Who holds it, for operators; the lock is what counts.
+        file.set_len(0).ok();
+        writeln!(file, "{}", self.identity).ok();
+        self.held = Some(file);
+        Ok(true)
+    }
+}
+
+/// A `coordination.k8s.io/v1` Lease, taken over once its holder stops
+/// renewing it. Updates carry the `resourceVersion` read, so two standbys
+/// racing for an expired lease can't both win.
+struct Lease {
+    k8s: Arc<K8sContext>,
+    path: String,
+    name: String,
+    identity: String,
+    duration_secs: u64,
+    /// Per request, so a hung API server can't stall the election.
+    timeout: Duration,
+}
+
+impl Lease {
+    async fn try_acquire(&self) -> Result<bool> {
+        let now = Utc::now();
+        let resp = self
+            .k8s
+            .request(Method::GET, &self.path)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            let collection = self.path.rsplit_once('/').map_or("", |(c, _)| c);
+            let body = json!({
+                "apiVersion": "coordination.k8s.io/v1",
+                "kind": "Lease",
+                "metadata": { "name": self.name },
+                "spec": self.spec(None, now),
+            });
+            let resp = self
+                .k8s
+                .request(Method::POST, collection)
+                .timeout(self.timeout)
+                .json(&body)
+                .send()
+                .await?;
+            return match resp.status() {
+                s if s.is_success() => Ok(true),
+                StatusCode::CONFLICT => Ok(false),
+                s => bail!("creating lease: {s}"),
+            };
+        }
+        if !resp.status().is_success() {
+            bail!("reading lease: {}", resp.status());
+        }
+        let mut lease: Value = resp.json().await?;
+        let spec = &lease["spec"];
+        if !can_take(spec, &self.identity, now) {
+            return Ok(false);
+        }
+        lease["spec"] = self.spec(Some(spec), now);
+        let resp = self
+            .k8s
+            .request(Method::PUT, &self.path)
+            .timeout(self.timeout)
+            .json(&lease)
+            .send()
+            .await?;
+        match resp.status() {
+            s if s.is_success() => Ok(true),
+            // Someone else updated it first.
+            StatusCode::CONFLICT => Ok(false),
+            s => bail!("updating lease: {s}"),
+        }
+    }
+
+    /// Spec naming us holder, keeping the acquire time while we renew.
+    fn spec(&self, current: Option<&Value>, now: DateTime<Utc>) -> Value {
+        let stamp: Value = now.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string().into();
+        let transitions = current
+            .and_then(|s| s["leaseTransitions"].as_i64())
+            .unwrap_or(0);
+        let (acquired, transitions) = match current {
+            Some(s) if s["holderIdentity"] == self.identity.as_str() => {
+                (s["acquireTime"].clone(), transitions)
+            }
+            Some(_) => (stamp.clone(), transitions + 1),
+            None => (stamp.clone(), 0),
+        };
+        json!({
+            "holderIdentity": self.identity,
+            "leaseDurationSeconds": self.duration_secs,
+            "acquireTime": acquired,
+            "renewTime": stamp,
+            "leaseTransitions": transitions,
+        })
+    }
+}
+
+/// Whether `identity` may hold a lease with `spec`: it is free, already
+/// ours, or its holder hasn't renewed within the lease duration.
+fn can_take(spec: &Value, identity: &str, now: DateTime<Utc>) -> bool {
+    let holder = spec["holderIdentity"].as_str().unwrap_or_default();
+    if holder.is_empty() || holder == identity {
+        return true;
+    }
+    let Some(renewed) = spec["renewTime"]
+        .as_str()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+    else {
+        return true;
+    };
+    let duration = spec["leaseDurationSeconds"].as_i64().unwrap_or(0);
+    now >= renewed.with_timezone(&Utc) + chrono::Duration::seconds(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_one_lock_file_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run/leader.lock");
+        let path = path.to_str().unwrap();
+        let mut active = LockFile::new(path, "a");
+        let mut standby = LockFile::new(path, "b");
+        assert!(active.try_acquire().unwrap());
+        assert!(!standby.try_acquire().unwrap());
+        assert!(active.try_acquire().unwrap(), "still held");
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "a\n");
+
+        // The active instance going away releases the lock.
+        drop(active);
+        assert!(standby.try_acquire().unwrap());
+    }
+
+    #[test]
+    fn lease_is_taken_when_free_ours_or_expired() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T12:00:30Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let held = |holder: &str, renewed: &str| {
+            json!({
+                "holderIdentity": holder,
+                "leaseDurationSeconds": 15,
+                "renewTime": renewed,
+            })
+        };
+        assert!(can_take(&json!({}), "b", now));
+        assert!(can_take(
+            &held("b", "2025-06-01T12:00:25.000000Z"),
+            "b",
+            now
+        ));
+        assert!(!can_take(
+            &held("a", "2025-06-01T12:00:25.000000Z"),
+            "b",
+            now
+        ));
+        assert!(can_take(
+            &held("a", "2025-06-01T12:00:10.000000Z"),
+            "b",
+            now
+        ));
+    }
+
+    #[test]
+    fn unset_election_always_leads() {
+        assert!(is_leader(&None));
+        let standby = Some(Arc::new(Leadership {
+            identity: "b".into(),
+            backend: "lock_file",
+            leader: AtomicBool::new(false),
+        }));
+        assert!(!is_leader(&standby));
+    }
+}
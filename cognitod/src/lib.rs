@@ -3,35 +3,77 @@
 
 pub mod agent_card;
 pub mod alerts;
+pub mod baselines;
 pub mod bpf_config;
+pub mod changes;
 pub mod claw_metrics;
 pub mod collectors;
 pub mod commerce;
 pub mod compliance;
 pub mod config;
+pub mod connection;
 pub mod context;
+pub mod correlation;
+pub mod crashloop;
+pub mod creds;
 pub mod enforcement;
+pub mod evidence;
+pub mod exec_compress;
+pub mod expr;
+pub mod file_access;
+pub mod fingerprint;
 pub mod handler;
 pub mod identity;
 pub mod incidents;
 pub mod insights;
+pub mod inventory;
+pub mod journald;
 pub mod k8s;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod kernel_counts;
+pub mod kernel_load;
+pub mod leader;
+pub mod malloc_sampler;
 pub mod mandate;
 pub mod metrics;
+pub mod network;
 pub mod notifications;
+pub mod observe;
+pub mod offcpu;
 pub mod onchain;
 pub mod payment;
+pub mod pidmap;
+#[cfg(feature = "wasm-plugins")]
+pub mod plugins;
 pub mod privacy;
+pub mod profiler;
+pub mod quota;
 pub mod receipt;
+pub mod redaction;
+pub mod retention;
+pub mod rollups;
+pub mod rule_scope;
 pub mod runtime;
 pub mod schema;
+pub mod script;
+pub mod sessions;
+pub mod signals;
+pub mod silences;
 pub mod spend;
+pub mod statsd;
+pub mod subtree_cpu;
+pub mod topology;
+pub mod trace;
+pub mod tuning;
 pub mod types;
 pub mod ui;
+pub mod update;
 pub mod utils;
+pub mod watch;
 
 pub use config::{Config, LoggingConfig, OfflineGuard, OutputConfig, RuntimeConfig};
-pub use incidents::{Incident, IncidentAnalyzer, IncidentStats, IncidentStore};
+pub use incidents::{Incident, IncidentAnalyzer, IncidentStats, IncidentStore, RollupRow};
 pub use metrics::Metrics;
 
 pub use linnix_ai_ebpf_common::PERCENT_MILLI_UNKNOWN;
@@ -4,11 +4,8 @@
 // Removed redundant import of ContextStore
 use anyhow::Context;
 use aya::Pod;
-use aya::maps::{
-    MapData,
-    perf::{PerfEventArray, PerfEventArrayBuffer},
-};
-use aya::programs::{KProbe, Lsm, TracePoint};
+use aya::maps::{PerCpuArray, perf::PerfEventArray, ring_buf::RingBuf};
+use aya::programs::{BtfTracePoint, KProbe, Lsm, TracePoint};
 use aya::util::online_cpus;
 use aya::{Ebpf, EbpfLoader};
 use aya_log::EbpfLogger;
@@ -21,11 +18,13 @@ use tokio::sync::broadcast;
 use tokio::time::{sleep, timeout};
 
 use crate::insights::InsightStore;
-use crate::runtime::start_perf_listener;
+use crate::runtime::{
+    EventBuffers, EventListener, EventPipeline, EventWindow, start_event_listener,
+};
 pub use linnix_ai_ebpf_common::PERCENT_MILLI_UNKNOWN;
 pub use linnix_ai_ebpf_common::ProcessEvent as ProcessEventWire;
 pub use linnix_ai_ebpf_common::ProcessEventExt as ProcessEvent;
-use linnix_ai_ebpf_common::TelemetryConfig;
+use linnix_ai_ebpf_common::{TelemetryConfig, block_source, event_transport};
 
 mod api;
 mod runtime;
@@ -37,7 +36,9 @@ use cognitod::context;
 use cognitod::enforcement;
 use cognitod::handler;
 use cognitod::insights;
+use cognitod::kernel_counts;
 use cognitod::metrics;
+use cognitod::quota;
 use cognitod::types;
 use cognitod::ui;
 
@@ -50,6 +51,13 @@ unsafe impl Pod for TelemetryConfigPod {}
 struct BpfRuntimeGuards {
     _bpf: Ebpf,
     _logger: Option<EbpfLogger>,
+    /// Taken when the listener for the programs starts.
+    sched_latency: Option<SchedLatencyHistogram>,
+    /// Taken when the listener for the programs starts.
+    lifecycle: Option<LifecycleCounters>,
+    /// Taken when the listener for the programs starts.
+    file_paths: Option<FilePaths>,
+    block_probe: BlockProbeMode,
 }
 
 const INSIGHT_STORE_CAPACITY: usize = 50;
@@ -91,6 +99,55 @@ fn attach_tracepoint_optional(bpf: &mut Ebpf, program: &str, category: &str, nam
     }
 }
 
+fn attach_btf_tracepoint_internal(bpf: &mut Ebpf, program: &str, name: &str) -> anyhow::Result<()> {
+    let prog: &mut BtfTracePoint = bpf
+        .program_mut(program)
+        .ok_or_else(|| anyhow::anyhow!("{program} program not found"))?
+        .try_into()?;
+    let btf = aya::Btf::from_sys_fs()?;
+    prog.load(name, &btf)?;
+    prog.attach()?;
+    Ok(())
+}
+
+/// Attach each block I/O hook as a BTF tracepoint when the struct offsets
+/// were found, falling back to the classic tracepoint one hook at a time.
+fn attach_block_probes(bpf: &mut Ebpf, telemetry: &TelemetryConfig) -> BlockProbeMode {
+    const HOOKS: [(&str, &str); 3] = [
+        ("trace_block_queue", "block_bio_queue"),
+        ("trace_block_issue", "block_rq_issue"),
+        ("trace_block_complete", "block_rq_complete"),
+    ];
+    let mut btf = 0;
+    let mut tracepoint = 0;
+    for (program, name) in HOOKS {
+        if telemetry.block_source == block_source::BTF {
+            match attach_btf_tracepoint_internal(bpf, &format!("{program}_btf"), name) {
+                Ok(()) => {
+                    btf += 1;
+                    continue;
+                }
+                Err(err) => warn!(
+                    "[cognitod] BTF tracepoint {name} not attached ({err:?}); using block:{name}"
+                ),
+            }
+        }
+        match attach_tracepoint_internal(bpf, program, "block", name) {
+            Ok(()) => tracepoint += 1,
+            Err(err) => warn!(
+                "[cognitod] optional tracepoint block:{name} ({program}) not attached: {err:?}"
+            ),
+        }
+    }
+    if btf == HOOKS.len() {
+        BlockProbeMode::Btf
+    } else if btf + tracepoint > 0 {
+        BlockProbeMode::Tracepoint
+    } else {
+        BlockProbeMode::Disabled
+    }
+}
+
 fn attach_lsm_internal(bpf: &mut Ebpf, program: &str, hook: &str) -> anyhow::Result<()> {
     let prog: &mut Lsm = bpf
         .program_mut(program)
@@ -113,11 +170,16 @@ fn attach_lsm_optional(bpf: &mut Ebpf, program: &str, hook: &str) {
 
 use crate::api::{AppState, all_routes};
 use crate::bpf_config::{CoreRssMode, derive_telemetry_config};
-use crate::runtime::probes::{ProbeState, RssProbeMode};
+use crate::runtime::file_paths::FilePaths;
+use crate::runtime::lifecycle_counts::LifecycleCounters;
+use crate::runtime::probes::{BlockProbeMode, ProbeState, RssProbeMode};
+use crate::runtime::sched_latency::SchedLatencyHistogram;
+use crate::runtime::throttle::{ThrottleControl, ThrottleIntervals};
 use clap::Parser;
 use cognitod::alerts::RuleEngine;
-use cognitod::config::{Config, OfflineGuard};
-use cognitod::handler::{HandlerList, JsonlHandler};
+use cognitod::config::{Config, OfflineGuard, ProbesConfig, RuntimeConfig};
+use cognitod::exec_compress::ExecCompressor;
+use cognitod::handler::{CompressingHandler, HandlerList, JsonlHandler};
 use cognitod::metrics::Metrics;
 use serde_json::json;
 use std::{fs, path::Path};
@@ -238,18 +300,102 @@ fn read_rss_trace_bytes() -> anyhow::Result<(Vec<u8>, String)> {
     read_bpf_object("LINNIX_RSS_TRACE_BPF_PATH", "rss_trace")
 }
 
+/// Locate and read the malloc_trace object used by `POST /processes/{pid}/malloc-sample`.
+fn read_malloc_trace_bytes() -> anyhow::Result<(Vec<u8>, String)> {
+    read_bpf_object("LINNIX_MALLOC_TRACE_BPF_PATH", "malloc_trace")
+}
+
+/// Byte size for the event ring buffer: a power of two of at least a page,
+/// as the kernel requires, capped at 1 GiB.
+fn ring_buf_bytes(size_kb: u32, page_size: u64) -> u32 {
+    (u64::from(size_kb) * 1024)
+        .max(page_size)
+        .next_power_of_two()
+        .min(1 << 30) as u32
+}
+
+/// Load the BPF object with events going to the ring buffer of `ring_bytes`
+/// bytes, or to the perf buffers when `None`.
+fn load_bpf(
+    bpf_bytes: &[u8],
+    telemetry: &TelemetryConfigPod,
+    ring_bytes: Option<u32>,
+    probes: &ProbesConfig,
+) -> anyhow::Result<Ebpf> {
+    let transport = if ring_bytes.is_some() {
+        event_transport::RING_BUF
+    } else {
+        event_transport::PERF
+    };
+    let mut loader = EbpfLoader::new();
+    loader.set_global("TELEMETRY_CONFIG", telemetry, true);
+    // Objects built before the ring buffer have neither; they use perf.
+    loader.set_global("EVENT_TRANSPORT", &transport, false);
+    let sched_latency_threshold_ns = match probes.sched_latency_threshold_us {
+        0 => u64::MAX,
+        us => us.saturating_mul(1000),
+    };
+    loader.set_global(
+        "SCHED_LATENCY_THRESHOLD_NS",
+        &sched_latency_threshold_ns,
+        false,
+    );
+    let offcpu_interval_ns = probes.offcpu_interval_ms.saturating_mul(1_000_000);
+    loader.set_global("OFFCPU_INTERVAL_NS", &offcpu_interval_ns, false);
+    let direct_reclaim_min_ns = probes.direct_reclaim_min_us.saturating_mul(1000);
+    loader.set_global("DIRECT_RECLAIM_MIN_NS", &direct_reclaim_min_ns, false);
+    let mem_growth_min_bytes = match probes.mem_growth_mb {
+        0 => u64::MAX,
+        mb => mb.saturating_mul(1024 * 1024),
+    };
+    loader.set_global("MEM_GROWTH_MIN_BYTES", &mem_growth_min_bytes, false);
+    let signal_mask = probes.signal_mask();
+    loader.set_global("SIGNAL_MASK", &signal_mask, false);
+    let syscall_interval_ns = probes.syscall_interval_ms.saturating_mul(1_000_000);
+    loader.set_global("SYSCALL_INTERVAL_NS", &syscall_interval_ns, false);
+    loader.set_global("SYSCALL_MIN_CALLS", &probes.syscall_min_calls, false);
+    // The map is created either way; keep it to one page when unused.
+    loader.set_max_entries(
+        "EVENTS_RB",
+        ring_bytes.unwrap_or(procfs::page_size() as u32),
+    );
+    Ok(loader.load(bpf_bytes)?)
+}
+
 fn init_ebpf(
     bpf_bytes: &[u8],
     telemetry_cfg: TelemetryConfig,
+    probes: &ProbesConfig,
+    runtime: &RuntimeConfig,
+    throttle: &ThrottleControl,
 ) -> anyhow::Result<(
     BpfRuntimeGuards,
-    Vec<PerfEventArrayBuffer<MapData>>,
+    EventBuffers,
     Option<cognitod::mandate::BpfMandateMaps>,
 )> {
     let telemetry = TelemetryConfigPod(telemetry_cfg);
-    let mut loader = EbpfLoader::new();
-    loader.set_global("TELEMETRY_CONFIG", &telemetry, true);
-    let mut bpf = loader.load(bpf_bytes)?;
+    let ring_bytes = ring_buf_bytes(probes.ringbuf_size_kb, procfs::page_size());
+    let (mut bpf, use_ring) = match probes.event_transport.as_str() {
+        "perf" => (load_bpf(bpf_bytes, &telemetry, None, probes)?, false),
+        "ringbuf" => (
+            load_bpf(bpf_bytes, &telemetry, Some(ring_bytes), probes)?,
+            true,
+        ),
+        mode => {
+            if mode != "auto" {
+                warn!("[cognitod] unknown probes.event_transport {mode:?}; using auto");
+            }
+            match load_bpf(bpf_bytes, &telemetry, Some(ring_bytes), probes) {
+                Ok(bpf) => (bpf, true),
+                Err(e) => {
+                    warn!(
+                        "[cognitod] BPF ring buffer unavailable ({e}); falling back to perf buffers"
+                    );
+                    (load_bpf(bpf_bytes, &telemetry, None, probes)?, false)
+                }
+            }
+        }
+    };
 
     let logger = match EbpfLogger::init(&mut bpf) {
         Ok(logger) => {
@@ -262,6 +408,19 @@ fn init_ebpf(
         }
     };
 
+    // Fill the filter before attaching so no unwanted event gets through.
+    let filter = crate::runtime::filter::FilterSpec::from_config(runtime);
+    if let Err(e) = crate::runtime::filter::apply(&mut bpf, &filter) {
+        warn!("[cognitod] kernel event filter not applied: {e:#}");
+    }
+    let throttled = match throttle.prepare(&mut bpf) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!("[cognitod] probe throttle intervals not applied: {e:#}");
+            false
+        }
+    };
+
     attach_tracepoint_internal(&mut bpf, "linnix_ai_ebpf", "sched", "sched_process_exec")?;
 
     attach_tracepoint_internal(&mut bpf, "handle_fork", "sched", "sched_process_fork").map_err(
@@ -277,9 +436,14 @@ fn init_ebpf(
 
     attach_kprobe_internal(&mut bpf, "trace_tcp_send", "tcp_sendmsg")?;
     attach_kprobe_internal(&mut bpf, "trace_tcp_recv", "tcp_recvmsg")?;
+    attach_kprobe_internal(&mut bpf, "trace_vfs_read_entry", "vfs_read")?;
     attach_kprobe_internal(&mut bpf, "trace_vfs_read", "vfs_read")?;
+    attach_kprobe_internal(&mut bpf, "trace_vfs_write_entry", "vfs_write")?;
     attach_kprobe_internal(&mut bpf, "trace_vfs_write", "vfs_write")?;
 
+    attach_kprobe_optional(&mut bpf, "trace_oom_kill", "oom_kill_process");
+    attach_kprobe_optional(&mut bpf, "trace_commit_creds", "commit_creds");
+
     attach_kprobe_optional(&mut bpf, "trace_udp_send", "udp_sendmsg");
     attach_kprobe_optional(&mut bpf, "trace_udp_recv", "udp_recvmsg");
     attach_kprobe_optional(&mut bpf, "trace_unix_stream_send", "unix_stream_sendmsg");
@@ -287,31 +451,113 @@ fn init_ebpf(
     attach_kprobe_optional(&mut bpf, "trace_unix_dgram_send", "unix_dgram_sendmsg");
     attach_kprobe_optional(&mut bpf, "trace_unix_dgram_recv", "unix_dgram_recvmsg");
 
-    attach_tracepoint_internal(&mut bpf, "trace_sys_enter", "raw_syscalls", "sys_enter")?;
+    attach_kprobe_optional(&mut bpf, "trace_tcp_v4_connect", "tcp_v4_connect");
+    attach_kprobe_optional(&mut bpf, "trace_tcp_v6_connect", "tcp_v6_connect");
+    attach_kprobe_optional(&mut bpf, "trace_inet_csk_accept", "inet_csk_accept");
 
-    attach_tracepoint_optional(&mut bpf, "trace_block_queue", "block", "block_bio_queue");
-    attach_tracepoint_optional(&mut bpf, "trace_block_issue", "block", "block_rq_issue");
-    attach_tracepoint_optional(
-        &mut bpf,
-        "trace_block_complete",
-        "block",
-        "block_rq_complete",
-    );
+    if probes.syscall_interval_ms > 0 {
+        attach_tracepoint_internal(&mut bpf, "trace_sys_enter", "raw_syscalls", "sys_enter")?;
+    }
+
+    let block_probe = attach_block_probes(&mut bpf, &telemetry_cfg);
+    let lifecycle = LifecycleCounters::take(&mut bpf);
+
+    if probes.mem_growth_mb > 0 {
+        attach_tracepoint_optional(&mut bpf, "trace_mmap", "syscalls", "sys_enter_mmap");
+        attach_tracepoint_optional(&mut bpf, "trace_brk", "syscalls", "sys_exit_brk");
+    }
+
+    let file_paths = if probes.watched_paths.is_empty() {
+        None
+    } else {
+        FilePaths::install(&mut bpf, &probes.watched_paths)
+    };
+    if file_paths.is_some() {
+        attach_kprobe_optional(&mut bpf, "trace_file_open", "security_file_open");
+        attach_kprobe_optional(&mut bpf, "trace_vfs_unlink", "vfs_unlink");
+    }
+
+    if probes.kernel_loads {
+        attach_tracepoint_optional(&mut bpf, "trace_module_load", "module", "module_load");
+        attach_tracepoint_optional(&mut bpf, "trace_bpf_prog_load", "syscalls", "sys_enter_bpf");
+    }
+
+    if probes.signal_mask() != 0 {
+        attach_tracepoint_optional(
+            &mut bpf,
+            "trace_signal_generate",
+            "signal",
+            "signal_generate",
+        );
+    }
+
+    if probes.direct_reclaim_min_us > 0 {
+        attach_tracepoint_optional(
+            &mut bpf,
+            "trace_direct_reclaim_begin",
+            "vmscan",
+            "mm_vmscan_direct_reclaim_begin",
+        );
+        attach_tracepoint_optional(
+            &mut bpf,
+            "trace_direct_reclaim_end",
+            "vmscan",
+            "mm_vmscan_direct_reclaim_end",
+        );
+    }
+
+    // sched_switch runs on every context switch, hence the opt-out.
+    let sched_latency = if probes.sched_latency_threshold_us == 0 && probes.offcpu_interval_ms == 0
+    {
+        None
+    } else {
+        let attached = ["sched_wakeup", "sched_wakeup_new", "sched_switch"]
+            .into_iter()
+            .try_for_each(|name| {
+                attach_btf_tracepoint_internal(&mut bpf, &format!("trace_{name}"), name)
+            });
+        match attached {
+            Ok(()) => SchedLatencyHistogram::take(&mut bpf),
+            Err(err) => {
+                warn!("[cognitod] scheduler probes not attached: {err:?}");
+                None
+            }
+        }
+    };
 
     // Attach LINNIX-CLAW LSM enforcement hooks (optional — need CONFIG_BPF_LSM=y).
     attach_lsm_optional(&mut bpf, "mandate_execve_check", "bprm_check_security");
     attach_lsm_optional(&mut bpf, "mandate_socket_connect", "socket_connect");
 
-    info!("[cognitod] Program attached. Setting up perf buffers...");
-
-    let events_map = bpf
-        .take_map("EVENTS")
-        .ok_or_else(|| anyhow::anyhow!("EVENTS map not found"))?;
-    let mut perf_array = PerfEventArray::try_from(events_map)?;
-    let mut perf_buffers = Vec::new();
-    for cpu in online_cpus().map_err(|(_, e)| e)? {
-        perf_buffers.push(perf_array.open(cpu, None)?);
-    }
+    let ring = if use_ring {
+        bpf.take_map("EVENTS_RB")
+    } else {
+        None
+    };
+    let buffers = match ring {
+        Some(ring) => {
+            info!("[cognitod] Program attached. Setting up ring buffer ({ring_bytes} bytes)...");
+            EventBuffers::RingBuf {
+                ring: RingBuf::try_from(ring)?,
+                drops: bpf
+                    .take_map("EVENTS_RB_DROPS")
+                    .map(PerCpuArray::try_from)
+                    .transpose()?,
+            }
+        }
+        None => {
+            info!("[cognitod] Program attached. Setting up perf buffers...");
+            let events_map = bpf
+                .take_map("EVENTS")
+                .ok_or_else(|| anyhow::anyhow!("EVENTS map not found"))?;
+            let mut perf_array = PerfEventArray::try_from(events_map)?;
+            let mut perf_buffers = Vec::new();
+            for cpu in online_cpus().map_err(|(_, e)| e)? {
+                perf_buffers.push(perf_array.open(cpu, None)?);
+            }
+            EventBuffers::Perf(perf_buffers)
+        }
+    };
 
     // Take LINNIX-CLAW BPF maps for mandate lifecycle management.
     // These are taken (not borrowed) so they outlive the Ebpf loader and can be
@@ -345,16 +591,91 @@ fn init_ebpf(
         }
     };
 
+    // Only now: a failed load must leave the running programs' map in place.
+    if throttled && let Err(e) = throttle.adopt(&mut bpf) {
+        warn!("[cognitod] probe throttle intervals fixed until restart: {e:#}");
+    }
+
     Ok((
         BpfRuntimeGuards {
             _bpf: bpf,
             _logger: logger,
+            sched_latency,
+            lifecycle,
+            file_paths,
+            block_probe,
         },
-        perf_buffers,
+        buffers,
         bpf_mandate_maps,
     ))
 }
 
+/// Start reading what freshly attached programs write, dispatching the
+/// events inside `window`.
+fn start_listener(
+    buffers: EventBuffers,
+    guards: &mut BpfRuntimeGuards,
+    pipeline: &EventPipeline,
+    window: EventWindow,
+) -> EventListener {
+    // The captured paths live in this object's maps.
+    let pipeline = &EventPipeline {
+        file_paths: guards.file_paths.take().map(Arc::new),
+        ..pipeline.clone()
+    };
+    let mut listener = start_event_listener(buffers, pipeline, window);
+    if let Some(histogram) = guards.sched_latency.take() {
+        listener.watch_sched_latency(histogram, Arc::clone(&pipeline.metrics));
+    }
+    if let Some(counters) = guards.lifecycle.take() {
+        listener.watch_lifecycle_counts(counters, Arc::clone(&pipeline.kernel_counts));
+    }
+    listener
+}
+
+/// Attached programs and the readers of their event buffers.
+struct ActiveProbes {
+    _guards: BpfRuntimeGuards,
+    listener: EventListener,
+}
+
+/// Blue/green reload: load the BPF object again (a newer one after an
+/// upgrade, or the same one against changed kernel offsets), attach it and
+/// start reading its buffers, and only then detach the running programs and
+/// drain what they wrote. While both sets are attached the old readers
+/// dispatch events up to the handover and the new ones the rest, so none
+/// are missed or dispatched twice. On error the running programs stay.
+async fn reload_probes(
+    active: &mut ActiveProbes,
+    pipeline: &EventPipeline,
+    probes: &ProbesConfig,
+    runtime: &RuntimeConfig,
+    throttle: &ThrottleControl,
+) -> anyhow::Result<String> {
+    let telemetry = derive_telemetry_config()?;
+    let (bpf_bytes, chosen_path) = read_bpf_bytes()?;
+    // The new object's mandate maps are left unused, as at startup without
+    // a mandate manager; reloads are refused when one holds the old maps.
+    // Cgroup paths are resolved again: their IDs change when they are recreated.
+    let (mut guards, buffers, _mandate_maps) =
+        init_ebpf(&bpf_bytes, telemetry.config, probes, runtime, throttle)?;
+    let listener = start_listener(buffers, &mut guards, pipeline, EventWindow::pending());
+    active.listener.hand_over(&listener);
+    let old_listener = {
+        // Dropping the old guards detaches the old programs.
+        let old = std::mem::replace(
+            active,
+            ActiveProbes {
+                _guards: guards,
+                listener,
+            },
+        );
+        old.listener
+    };
+    old_listener.drain_and_stop().await;
+    Ok(chosen_path)
+}
+
 fn init_rss_trace(bpf_bytes: &[u8]) -> anyhow::Result<BpfRuntimeGuards> {
     let mut loader = EbpfLoader::new();
     let mut bpf = loader.load(bpf_bytes)?;
@@ -375,6 +696,10 @@ fn init_rss_trace(bpf_bytes: &[u8]) -> anyhow::Result<BpfRuntimeGuards> {
     Ok(BpfRuntimeGuards {
         _bpf: bpf,
         _logger: logger,
+        sched_latency: None,
+        lifecycle: None,
+        file_paths: None,
+        block_probe: BlockProbeMode::Disabled,
     })
 }
 
@@ -460,17 +785,22 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Load configuration
     let config = Config::load();
     let offline_guard = Arc::new(OfflineGuard::new(config.runtime.offline));
+    let network = cognitod::network::Network::from_config(&config.network)?;
 
     // Initialize metrics and spawn background reporting tasks
     let metrics = Arc::new(Metrics::new());
     spawn_metrics_tasks(Arc::clone(&metrics));
 
     // --- Prepare kernel instrumentation with graceful fallback ---
-    let mut perf_buffers: Vec<PerfEventArrayBuffer<MapData>> = Vec::new();
+    let mut event_buffers: Option<EventBuffers> = None;
     let mut transport: &'static str = "userspace";
     let mut _bpf_runtime: Option<BpfRuntimeGuards> = None;
     let mut probe_state = ProbeState::disabled();
     let mut mandate_bpf_maps: Option<cognitod::mandate::BpfMandateMaps> = None;
+    let mut bpf_object_path: Option<String> = None;
+    let throttle = Arc::new(ThrottleControl::new(ThrottleIntervals::from_probes(
+        &config.probes,
+    )));
 
     let btf_path = std::env::var("LINNIX_KERNEL_BTF")
         .unwrap_or_else(|_| "/sys/kernel/btf/vmlinux".to_string());
@@ -488,19 +818,27 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 let telemetry_cfg = result.config;
                 let (bpf_bytes, chosen_path) = read_bpf_bytes()?;
                 println!("[cognitod] Using BPF object: {chosen_path}");
-                match init_ebpf(&bpf_bytes, telemetry_cfg) {
+                match init_ebpf(
+                    &bpf_bytes,
+                    telemetry_cfg,
+                    &config.probes,
+                    &config.runtime,
+                    &throttle,
+                ) {
                     Ok((guards, buffers, maps)) => {
-                        transport = "perf";
-                        perf_buffers = buffers;
-                        _bpf_runtime = Some(guards);
-                        mandate_bpf_maps = maps;
+                        transport = buffers.transport();
+                        bpf_object_path = Some(chosen_path);
+                        event_buffers = Some(buffers);
                         probe_state = ProbeState {
                             rss_probe: match result.mode {
                                 CoreRssMode::MmStruct => RssProbeMode::CoreMm,
                                 CoreRssMode::SignalStruct => RssProbeMode::CoreSignal,
                             },
+                            block_probe: guards.block_probe,
                             btf_available,
                         };
+                        _bpf_runtime = Some(guards);
+                        mandate_bpf_maps = maps;
                     }
                     Err(err) => {
                         warn!(
@@ -563,6 +901,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     );
     info!("final rss mode = {}", probe_state.rss_probe.as_str());
+    info!("block probe mode = {}", probe_state.block_probe.as_str());
 
     metrics.set_rss_probe_mode(probe_state.rss_probe.metric_value());
     metrics.set_kernel_btf_available(btf_available);
@@ -570,6 +909,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     if args.probe_only {
         let payload = json!({
             "rss_probe": probe_state.rss_probe.as_str(),
+            "block_probe": probe_state.block_probe.as_str(),
             "btf": probe_state.btf_available,
         });
         println!("{payload}");
@@ -581,13 +921,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
-    if perf_buffers.is_empty() && !matches!(probe_state.rss_probe, RssProbeMode::Tracepoint) {
+    if event_buffers.is_none() && !matches!(probe_state.rss_probe, RssProbeMode::Tracepoint) {
         info!(
             "[cognitod] Kernel instrumentation disabled; Cognitod will continue in userspace-only mode."
         );
     }
 
-    let k8s_context = cognitod::k8s::K8sContext::new();
+    let k8s_context = cognitod::k8s::K8sContext::new(&network);
     if let Some(ctx) = &k8s_context {
         info!(
             "[cognitod] K8s context initialized (node: {})",
@@ -598,11 +938,62 @@ async fn main() -> Result<(), Box<dyn Error>> {
         info!("[cognitod] K8s context not available (missing env/tokens)");
     }
 
+    // Only the elected leader notifies when several instances run for HA
+    let leadership = if config.leader_election.enabled {
+        let leadership = cognitod::leader::spawn(&config.leader_election, k8s_context.clone())?;
+        info!(
+            "[cognitod] leader election via {} as {}",
+            leadership.backend(),
+            leadership.identity()
+        );
+        Some(leadership)
+    } else {
+        None
+    };
+
+    // In observe mode notifiers and enforcement record instead of acting
+    let observe = Arc::new(cognitod::observe::ObserveRecorder::new(
+        config.observe_only(),
+    ));
+    if observe.is_observe() {
+        warn!(
+            "[cognitod] observe mode: notifications and enforcement actions are recorded, not performed"
+        );
+    }
+
+    // Static host facts attached to notifications, prompts and exports
+    let inventory = if config.inventory.enabled {
+        let mut inventory_cfg = config.inventory.clone();
+        inventory_cfg.cloud_metadata &= !offline_guard.is_offline();
+        let facts = cognitod::inventory::HostFacts::collect(&inventory_cfg).await;
+        info!("[cognitod] host: {facts}");
+        Some(Arc::new(facts))
+    } else {
+        None
+    };
+
     let context = Arc::new(context::ContextStore::new(
         Duration::from_secs(300),
         1000,
         k8s_context.clone(),
     ));
+    // Measured pod shares of CPU stall time, replacing model guesses
+    let pod_pressure = match &k8s_context {
+        Some(ctx) if config.psi.pod_contribution => {
+            let pod_pressure = Arc::new(cognitod::collectors::pod_pressure::PodPressure::new(
+                Arc::clone(ctx),
+            ));
+            tokio::spawn(Arc::clone(&pod_pressure).run());
+            Some(pod_pressure)
+        }
+        _ => None,
+    };
+    // Deploys and config changes from CI/CD, mentioned with the anomalies
+    // that follow them
+    let change_log = Arc::new(cognitod::changes::ChangeLog::new(&config.changes));
+    // Fed by OffCpu events; tells incident analyses whether the target was
+    // spinning or waiting on I/O.
+    let offcpu = Arc::new(cognitod::offcpu::OffCpuTracker::new());
     let insight_store = {
         let path = config.logging.insights_file.trim();
         let path = if path.is_empty() {
@@ -610,7 +1001,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
         } else {
             Some(PathBuf::from(path))
         };
-        Arc::new(InsightStore::new(INSIGHT_STORE_CAPACITY, path))
+        let mut store =
+            InsightStore::new(INSIGHT_STORE_CAPACITY, path).with_changes(Arc::clone(&change_log));
+        if let Some(pod_pressure) = &pod_pressure {
+            store = store.with_pod_pressure(Arc::clone(pod_pressure));
+        }
+        if config.evidence.enabled {
+            let collector = Arc::new(cognitod::evidence::EvidenceCollector::new(
+                config.evidence.clone(),
+                Arc::clone(&context),
+                inventory.clone(),
+            ));
+            tokio::spawn(Arc::clone(&collector).run());
+            store = store.with_evidence(collector);
+        }
+        Arc::new(store)
     };
 
     // Initialize incident store for circuit breaker events
@@ -672,14 +1077,52 @@ async fn main() -> Result<(), Box<dyn Error>> {
         None
     };
 
+    // Per-hour-of-day history of fork rate, events/sec and CPU
+    let baselines = config.baselines.enabled.then(|| {
+        Arc::new(cognitod::baselines::Baselines::load(
+            config.baselines.clone(),
+        ))
+    });
+
+    let sessions = config
+        .sessions
+        .enabled
+        .then(|| Arc::new(cognitod::sessions::SessionResolver::default()));
+
+    // Outbound redaction policy shared by notifiers, LLM prompts and the API
+    let redaction = Arc::new(cognitod::redaction::RedactionPolicy::from_config(
+        &config.redaction,
+    ));
+    if config.redaction.enabled {
+        info!("[cognitod] outbound redaction policy enabled");
+    }
+
     let incident_analyzer = if config.reasoner.enabled && !config.reasoner.endpoint.is_empty() {
         match cognitod::IncidentAnalyzer::new(
             config.reasoner.endpoint.clone(),
             Duration::from_millis(config.reasoner.timeout_ms),
+            &network,
         ) {
             Ok(analyzer) => {
                 info!("[incident_analyzer] LLM analysis enabled for incidents");
-                Some(Arc::new(analyzer))
+                let analyzer = match &baselines {
+                    Some(b) => analyzer.with_baselines(Arc::clone(b)),
+                    None => analyzer,
+                };
+                let analyzer = match &inventory {
+                    Some(facts) => analyzer.with_inventory(Arc::clone(facts)),
+                    None => analyzer,
+                };
+                let analyzer = match &pod_pressure {
+                    Some(p) => analyzer.with_pod_pressure(Arc::clone(p)),
+                    None => analyzer,
+                };
+                Some(Arc::new(
+                    analyzer
+                        .with_changes(Arc::clone(&change_log))
+                        .with_offcpu(Arc::clone(&offcpu))
+                        .with_redaction(Arc::clone(&redaction)),
+                ))
             }
             Err(e) => {
                 warn!("[incident_analyzer] Failed to initialize: {}", e);
@@ -691,13 +1134,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
     };
 
     // Handlers specified on the command line
-    let mut handler_list = HandlerList::new();
+    let mut handler_list = HandlerList::with_config(config.handlers.clone(), Arc::clone(&metrics));
     let enforcement_queue = Some(Arc::new(enforcement::EnforcementQueue::new(300)));
     let mut alert_tx = None;
+    let mut rule_engine = None;
+    let kernel_counts = Arc::new(kernel_counts::KernelCounts::default());
+    let silences = Arc::new(cognitod::silences::Silences::from_config(&config.silences));
     for h in handler {
         if let Some(path) = h.strip_prefix("jsonl:") {
             if let Ok(hdl) = JsonlHandler::new(path).await {
-                handler_list.register(hdl);
+                if config.exec_compression.enabled {
+                    let compressor = ExecCompressor::new(config.exec_compression.clone());
+                    handler_list.register(CompressingHandler::new(hdl, compressor));
+                } else {
+                    handler_list.register(hdl);
+                }
             }
         } else if let Some(path) = h.strip_prefix("rules:") {
             match RuleEngine::from_path(
@@ -707,15 +1158,32 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 Arc::clone(&metrics),
             ) {
                 Ok(engine) => {
+                    let engine = match &sessions {
+                        Some(s) => engine.with_sessions(Arc::clone(s)),
+                        None => engine,
+                    }
+                    .with_kernel_counts(Arc::clone(&kernel_counts))
+                    .with_silences(Arc::clone(&silences));
+                    let engine = match &k8s_context {
+                        Some(ctx) => engine.with_k8s(Arc::clone(ctx)),
+                        None => engine,
+                    };
+                    let engine = match &baselines {
+                        Some(b) => engine.with_baselines(Arc::clone(b)),
+                        None => engine,
+                    };
                     let rule_count = engine.rule_count();
                     let broadcaster = engine.broadcaster();
                     info!(
                         "[cognitod] Rules handler loaded from {} ({} rules)",
                         path, rule_count
                     );
-                    metrics.add_active_rules(rule_count);
-                    alert_tx = Some(broadcaster);
-                    handler_list.register(engine);
+                    let engine = Arc::new(engine);
+                    if handler_list.register(Arc::clone(&engine)) {
+                        metrics.add_active_rules(rule_count);
+                        alert_tx = Some(broadcaster);
+                        rule_engine = Some(engine);
+                    }
                 }
                 Err(e) => warn!("[cognitod] failed to load rules from {}: {e}", path),
             }
@@ -732,15 +1200,32 @@ async fn main() -> Result<(), Box<dyn Error>> {
             Arc::clone(&metrics),
         ) {
             Ok(engine) => {
+                let engine = match &sessions {
+                    Some(s) => engine.with_sessions(Arc::clone(s)),
+                    None => engine,
+                }
+                .with_kernel_counts(Arc::clone(&kernel_counts))
+                .with_silences(Arc::clone(&silences));
+                let engine = match &k8s_context {
+                    Some(ctx) => engine.with_k8s(Arc::clone(ctx)),
+                    None => engine,
+                };
+                let engine = match &baselines {
+                    Some(b) => engine.with_baselines(Arc::clone(b)),
+                    None => engine,
+                };
                 let rule_count = engine.rule_count();
                 let broadcaster = engine.broadcaster();
                 info!(
                     "[cognitod] Rules handler loaded from config {} ({} rules)",
                     rules_path, rule_count
                 );
-                metrics.add_active_rules(rule_count);
-                alert_tx = Some(broadcaster);
-                handler_list.register(engine);
+                let engine = Arc::new(engine);
+                if handler_list.register(Arc::clone(&engine)) {
+                    metrics.add_active_rules(rule_count);
+                    alert_tx = Some(broadcaster);
+                    rule_engine = Some(engine);
+                }
             }
             Err(e) => warn!(
                 "[cognitod] rules engine unavailable; failed to load {}: {e}",
@@ -749,6 +1234,70 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    // Rules reload on SIGHUP and, with `rules.watch`, when the file changes.
+    if let Some(engine) = &rule_engine
+        && let Some(rules_path) = engine.source().map(PathBuf::from)
+    {
+        let (reload_tx, mut reload_rx) = tokio::sync::mpsc::channel::<&'static str>(1);
+        if config.rules.watch {
+            let tx = reload_tx.clone();
+            cognitod::watch::watch("rules", &rules_path, &config.watch, &metrics, move || {
+                let _ = tx.try_send("file changed");
+                Ok(())
+            });
+        }
+        let mut sighup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        let engine = Arc::clone(engine);
+        tokio::spawn(async move {
+            loop {
+                let reason = tokio::select! {
+                    _ = sighup.recv() => "SIGHUP",
+                    Some(reason) = reload_rx.recv() => reason,
+                };
+                match engine.reload().await {
+                    Ok(summary) => info!(
+                        "[cognitod] rules reloaded from {} ({reason}): {summary}",
+                        rules_path.display()
+                    ),
+                    Err(e) => warn!(
+                        "[cognitod] rules reload ({reason}) failed, keeping running rules: {e:#}"
+                    ),
+                }
+            }
+        });
+    }
+
+    // WASM detector plugins; their alerts share the rules broadcast channel.
+    if config.plugins.enabled {
+        #[cfg(feature = "wasm-plugins")]
+        {
+            let tx = alert_tx
+                .get_or_insert_with(|| tokio::sync::broadcast::channel(128).0)
+                .clone();
+            match cognitod::plugins::PluginHost::load(
+                config.plugins.clone(),
+                tx,
+                config.logging.alerts_file.clone(),
+                config.logging.journald,
+                Arc::clone(&metrics),
+            ) {
+                Ok(host) => {
+                    info!(
+                        "[cognitod] {} WASM plugin(s) loaded from {}",
+                        host.len(),
+                        config.plugins.dir
+                    );
+                    if !host.is_empty() {
+                        handler_list.register(host);
+                    }
+                }
+                Err(e) => warn!("[cognitod] WASM plugins unavailable: {e:#}"),
+            }
+        }
+        #[cfg(not(feature = "wasm-plugins"))]
+        warn!("[cognitod] [plugins] is enabled but this build lacks the wasm-plugins feature");
+    }
+
     if let Some(path) = config.logging.incident_context_file.clone() {
         if let Some(sender) = alert_tx.clone() {
             let mut rx = sender.subscribe();
@@ -819,18 +1368,79 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    // An aggregator correlates its own alerts with its agents' before they
+    // reach the notifiers, folding a rule that fires across the fleet into
+    // one alert.
+    let notify_tx = match config.notifications.as_ref().and_then(|n| n.nats.clone()) {
+        Some(nats) if config.aggregator.enabled => {
+            let local = alert_tx
+                .get_or_insert_with(|| tokio::sync::broadcast::channel(128).0)
+                .subscribe();
+            let correlated = tokio::sync::broadcast::channel(128).0;
+            tokio::spawn(cognitod::correlation::run(
+                config.aggregator.clone(),
+                nats,
+                local,
+                correlated.clone(),
+            ));
+            Some(correlated)
+        }
+        None if config.aggregator.enabled => {
+            warn!("[cognitod] [aggregator] needs [notifications.nats] to receive agents' alerts");
+            alert_tx.clone()
+        }
+        _ => alert_tx.clone(),
+    };
+
+    // Alerts reach notifiers through the dispatcher, which applies each
+    // rule's `notify` channels, the routing (severity floors, quiet hours),
+    // digests and the host's alert budget.
+    let mut alert_dispatcher = notify_tx.as_ref().map(|tx| {
+        let notifications = config.notifications.clone().unwrap_or_default();
+        let dispatcher = cognitod::notifications::AlertDispatcher::new(tx.subscribe())
+            .with_router(cognitod::notifications::NotificationRouter::from_config(
+                &notifications,
+            ))
+            .with_digest(&notifications.digest)
+            .with_budget(notifications.budget.max_alerts_per_minute)
+            .with_observe(Arc::clone(&observe));
+        let dispatcher = match &leadership {
+            Some(leadership) => dispatcher.with_leadership(Arc::clone(leadership)),
+            None => dispatcher,
+        };
+        match &rule_engine {
+            Some(engine) => dispatcher.with_rules(Arc::clone(engine)),
+            None => dispatcher,
+        }
+    });
+    // Insights reach the notifiers that post them the same way.
+    let mut insight_dispatcher =
+        cognitod::notifications::InsightDispatcher::new(insight_store.subscribe())
+            .with_observe(Arc::clone(&observe));
+    if let Some(leadership) = &leadership {
+        insight_dispatcher = insight_dispatcher.with_leadership(Arc::clone(leadership));
+    }
+
     // Spawn Apprise notifier if configured
     if let Some(ref notif_config) = config.notifications
         && let Some(ref apprise_config) = notif_config.apprise
     {
-        if let Some(alert_tx) = &alert_tx {
-            let apprise_rx = alert_tx.subscribe();
+        if let Some(dispatcher) = &mut alert_dispatcher {
+            let apprise_rx = dispatcher.subscribe("apprise");
             let url_count = apprise_config.urls.len();
 
             let apprise_config_owned = apprise_config.clone();
+            let apprise_redaction = Arc::clone(&redaction);
+            let apprise_inventory = inventory.clone();
+            let apprise_network = network.clone();
             tokio::spawn(async move {
-                let notifier =
-                    cognitod::notifications::AppriseNotifier::new(apprise_config_owned, apprise_rx);
+                let mut notifier =
+                    cognitod::notifications::AppriseNotifier::new(apprise_config_owned, apprise_rx)
+                        .with_redaction(apprise_redaction)
+                        .with_network(&apprise_network);
+                if let Some(facts) = apprise_inventory {
+                    notifier = notifier.with_inventory(facts);
+                }
                 notifier.run().await;
             });
 
@@ -843,6 +1453,181 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
+    // Spawn email notifier if configured
+    if let Some(ref notif_config) = config.notifications
+        && let Some(ref email_config) = notif_config.email
+    {
+        if let Some(dispatcher) = &mut alert_dispatcher {
+            match cognitod::notifications::EmailNotifier::new(
+                email_config.clone(),
+                dispatcher.subscribe("email"),
+            ) {
+                Ok(notifier) => {
+                    let mut notifier = notifier.with_redaction(Arc::clone(&redaction));
+                    if let Some(facts) = &inventory {
+                        notifier = notifier.with_inventory(Arc::clone(facts));
+                    }
+                    tokio::spawn(notifier.run());
+                    info!(
+                        "[cognitod] Email notifier started via {}",
+                        email_config.smtp_host
+                    );
+                }
+                Err(e) => warn!("[cognitod] email notifications disabled: {e:#}"),
+            }
+        } else {
+            warn!("[cognitod] Email notifications requested but no alert handler is active");
+        }
+    }
+
+    // Spawn Opsgenie notifier if configured
+    if let Some(ref notif_config) = config.notifications
+        && let Some(ref opsgenie_config) = notif_config.opsgenie
+    {
+        if let Some(dispatcher) = &mut alert_dispatcher {
+            match cognitod::notifications::OpsgenieNotifier::new(
+                opsgenie_config.clone(),
+                dispatcher.subscribe("opsgenie"),
+            ) {
+                Ok(notifier) => {
+                    let mut notifier = notifier
+                        .with_redaction(Arc::clone(&redaction))
+                        .with_network(&network);
+                    if let Some(facts) = &inventory {
+                        notifier = notifier.with_inventory(Arc::clone(facts));
+                    }
+                    tokio::spawn(notifier.run());
+                    info!("[cognitod] Opsgenie notifier started");
+                }
+                Err(e) => warn!("[cognitod] Opsgenie notifications disabled: {e:#}"),
+            }
+        } else {
+            warn!("[cognitod] Opsgenie notifications requested but no alert handler is active");
+        }
+    }
+
+    // Spawn syslog notifier if configured
+    if let Some(ref notif_config) = config.notifications
+        && let Some(ref syslog_config) = notif_config.syslog
+    {
+        if let Some(dispatcher) = &mut alert_dispatcher {
+            match cognitod::notifications::SyslogNotifier::new(
+                syslog_config.clone(),
+                dispatcher.subscribe("syslog"),
+            ) {
+                Ok(notifier) => {
+                    let notifier = notifier.with_redaction(Arc::clone(&redaction));
+                    tokio::spawn(notifier.run());
+                    info!(
+                        "[cognitod] syslog notifier started ({} over {})",
+                        syslog_config.address, syslog_config.transport
+                    );
+                }
+                Err(e) => warn!("[cognitod] syslog notifications disabled: {e:#}"),
+            }
+        } else {
+            warn!("[cognitod] Syslog notifications requested but no alert handler is active");
+        }
+    }
+
+    // Spawn NATS notifier if configured; an aggregator subscribes to the
+    // subjects instead, and publishing there would feed its alerts back in.
+    if let Some(ref notif_config) = config.notifications
+        && let Some(ref nats_config) = notif_config.nats
+        && !config.aggregator.enabled
+    {
+        if let Some(dispatcher) = &mut alert_dispatcher {
+            match cognitod::notifications::NatsNotifier::new(
+                nats_config.clone(),
+                dispatcher.subscribe("nats"),
+            ) {
+                Ok(notifier) => {
+                    let notifier = notifier.with_redaction(Arc::clone(&redaction));
+                    tokio::spawn(notifier.run());
+                    info!("[cognitod] NATS notifier started ({})", nats_config.url);
+                }
+                Err(e) => warn!("[cognitod] NATS notifications disabled: {e:#}"),
+            }
+        } else {
+            warn!("[cognitod] NATS notifications requested but no alert handler is active");
+        }
+    }
+
+    // Spawn Teams notifier if configured; without rules it still posts
+    // insights.
+    if let Some(ref notif_config) = config.notifications
+        && let Some(ref teams_config) = notif_config.teams
+    {
+        // Without rules the alert channel is closed from the start.
+        let alert_rx = match &mut alert_dispatcher {
+            Some(dispatcher) => dispatcher.subscribe("teams"),
+            None => tokio::sync::broadcast::channel(1).1,
+        };
+        let mut notifier =
+            cognitod::notifications::TeamsNotifier::new(teams_config.clone(), alert_rx)
+                .with_redaction(Arc::clone(&redaction))
+                .with_network(&network);
+        if teams_config.insights {
+            notifier = notifier.with_insights(insight_dispatcher.subscribe("teams", 0.0));
+        }
+        if let Some(facts) = &inventory {
+            notifier = notifier.with_inventory(Arc::clone(facts));
+        }
+        tokio::spawn(notifier.run());
+        info!("[cognitod] Teams notifier started");
+    }
+
+    // Spawn Telegram notifier if configured; like Teams, it can send
+    // insights without rules.
+    if let Some(ref notif_config) = config.notifications
+        && let Some(ref telegram_config) = notif_config.telegram
+    {
+        let alert_rx = match &mut alert_dispatcher {
+            Some(dispatcher) => dispatcher.subscribe("telegram"),
+            None => tokio::sync::broadcast::channel(1).1,
+        };
+        let mut notifier =
+            cognitod::notifications::TelegramNotifier::new(telegram_config.clone(), alert_rx)
+                .with_redaction(Arc::clone(&redaction))
+                .with_network(&network);
+        if telegram_config.insights {
+            notifier = notifier.with_insights(insight_dispatcher.subscribe("telegram", 0.0));
+        }
+        if let Some(facts) = &inventory {
+            notifier = notifier.with_inventory(Arc::clone(facts));
+        }
+        tokio::spawn(notifier.run());
+        info!("[cognitod] Telegram notifier started");
+    }
+
+    // Spawn insight webhooks if configured
+    if let Some(ref notif_config) = config.notifications
+        && let Some(ref webhook_config) = notif_config.webhooks
+    {
+        let webhook_rx = insight_dispatcher.subscribe("webhook", webhook_config.min_confidence);
+        let url_count = webhook_config.urls.len();
+        let webhook_config_owned = webhook_config.clone();
+        let webhook_redaction = Arc::clone(&redaction);
+        let webhook_inventory = inventory.clone();
+        let webhook_network = network.clone();
+        tokio::spawn(async move {
+            let mut notifier = cognitod::notifications::InsightWebhookNotifier::new(
+                webhook_config_owned,
+                webhook_rx,
+            )
+            .with_redaction(webhook_redaction)
+            .with_network(&webhook_network);
+            if let Some(facts) = webhook_inventory {
+                notifier = notifier.with_inventory(facts);
+            }
+            notifier.run().await;
+        });
+        info!(
+            "[cognitod] Insight webhook notifier started with {} URL(s)",
+            url_count
+        );
+    }
+
     // KB Index removed (YAGNI cleanup)
 
     // Start PSI monitor (after incident store is ready)
@@ -861,17 +1646,25 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Initialize Slack Notifier
     let _slack_notifier = if let Some(ref notif_cfg) = config.notifications {
         if let Some(ref slack_cfg) = notif_cfg.slack {
-            if let Some(ref tx) = alert_tx {
+            if let Some(dispatcher) = &mut alert_dispatcher {
                 // SlackNotifier workaround: create two instances because run() consumes self.
                 // One for the alert loop, one for ILM insights (with dummy channel).
                 let (_dummy_tx, dummy_rx) = tokio::sync::broadcast::channel(1);
-                let notifier_ilm = Arc::new(cognitod::notifications::SlackNotifier::new(
-                    slack_cfg.clone(),
-                    dummy_rx,
-                ));
+                let notifier_ilm = Arc::new(
+                    cognitod::notifications::SlackNotifier::new(slack_cfg.clone(), dummy_rx)
+                        .with_redaction(Arc::clone(&redaction))
+                        .with_network(&network),
+                );
 
-                let notifier_alerts =
-                    cognitod::notifications::SlackNotifier::new(slack_cfg.clone(), tx.subscribe());
+                let mut notifier_alerts = cognitod::notifications::SlackNotifier::new(
+                    slack_cfg.clone(),
+                    dispatcher.subscribe("slack"),
+                )
+                .with_redaction(Arc::clone(&redaction))
+                .with_network(&network);
+                if let Some(facts) = &inventory {
+                    notifier_alerts = notifier_alerts.with_inventory(Arc::clone(facts));
+                }
                 tokio::spawn(async move {
                     notifier_alerts.run().await;
                 });
@@ -881,10 +1674,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 // No alert_tx (e.g. rules disabled), but we might still want ILM insights to go to Slack.
                 // We still need a dummy rx.
                 let (_dummy_tx, dummy_rx) = tokio::sync::broadcast::channel(1);
-                let notifier = Arc::new(cognitod::notifications::SlackNotifier::new(
-                    slack_cfg.clone(),
-                    dummy_rx,
-                ));
+                let notifier = Arc::new(
+                    cognitod::notifications::SlackNotifier::new(slack_cfg.clone(), dummy_rx)
+                        .with_redaction(Arc::clone(&redaction))
+                        .with_network(&network),
+                );
                 Some(notifier)
             }
         } else {
@@ -894,11 +1688,17 @@ async fn main() -> Result<(), Box<dyn Error>> {
         None
     };
 
+    if let Some(dispatcher) = alert_dispatcher {
+        tokio::spawn(dispatcher.run());
+    }
+    tokio::spawn(insight_dispatcher.run());
+
     // LocalIlmHandlerRag removed (YAGNI cleanup)
 
     // ── Linnix-Claw: initialize MandateManager ──────────────────────────
     // Moved before handler_list finalization so MandateReceiptHandler can be
     // registered in the handler pipeline.
+    let mut mandate_holds_bpf_maps = false;
     let mandate_manager: Option<Arc<cognitod::mandate::MandateManager>> = {
         let lsm_available = bpf_config::is_bpf_lsm_available();
         let allow_without_lsm = config.mandate.allow_commerce_without_lsm;
@@ -912,7 +1712,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         if enforcement_available || allow_without_lsm {
             match bpf_config::generate_siphash_key() {
                 Ok(key) => {
-                    let mode = if config.mandate.mode == "enforce" {
+                    let mode = if config.mandate.mode == "enforce" && !config.observe_only() {
                         linnix_ai_ebpf_common::MandateMode::Enforce
                     } else {
                         linnix_ai_ebpf_common::MandateMode::Monitor
@@ -931,6 +1731,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                     // Connect BPF maps if available (writes siphash key + mode to kernel).
                     if let Some(maps) = mandate_bpf_maps.take() {
                         mgr.connect_bpf_maps(maps).await;
+                        mandate_holds_bpf_maps = true;
                     }
 
                     Some(mgr)
@@ -977,26 +1778,142 @@ async fn main() -> Result<(), Box<dyn Error>> {
         None
     };
 
+    // Kafka export of alerts, insights and, with `events_topic`, raw events
+    if config.kafka.enabled && offline_guard.check("kafka") {
+        #[cfg(feature = "kafka")]
+        match cognitod::kafka::KafkaSink::new(&config.kafka, Arc::clone(&redaction)) {
+            Ok(sink) => {
+                let sink = Arc::new(sink);
+                if let Some(tx) = &alert_tx {
+                    tokio::spawn(Arc::clone(&sink).run_alerts(tx.subscribe()));
+                }
+                tokio::spawn(Arc::clone(&sink).run_insights(insight_store.subscribe()));
+                if config.kafka.events_topic.is_some() {
+                    handler_list.register(Arc::clone(&sink));
+                }
+                info!(
+                    "[cognitod] Kafka export to {} ({})",
+                    config.kafka.brokers, config.kafka.encoding
+                );
+            }
+            Err(e) => warn!("[cognitod] Kafka export disabled: {e:#}"),
+        }
+        #[cfg(not(feature = "kafka"))]
+        warn!("[cognitod] [kafka] is enabled but this build lacks the kafka feature");
+    }
+
+    // OomKill events are ground truth for the oom_risk insight class.
+    handler_list.register(cognitod::handler::OomKillHandler::new(
+        Arc::clone(&insight_store),
+        k8s_context.clone(),
+    ));
+    handler_list.register(Arc::clone(&offcpu));
+
     // ── Linnix-Claw: register MandateReceiptHandler ─────────────────────
-    if let (Some(mgr), Some(id)) = (&mandate_manager, &agent_identity) {
-        handler_list.register(cognitod::handler::MandateReceiptHandler::new(
+    if let (Some(mgr), Some(id)) = (&mandate_manager, &agent_identity)
+        && handler_list.register(cognitod::handler::MandateReceiptHandler::new(
             Arc::clone(mgr),
             Arc::clone(id),
-        ));
+        ))
+    {
         info!("[claw] MandateReceiptHandler registered in event pipeline");
     }
 
+    info!(
+        "[cognitod] event handlers (in order): {}",
+        handler_list.names().join(", ")
+    );
     let handlers = Arc::new(handler_list);
-    // Pass metrics to your listener
-    if !perf_buffers.is_empty() {
-        start_perf_listener(
-            perf_buffers,
-            Arc::clone(&context),
-            Arc::clone(&metrics),
-            Arc::clone(&handlers),
-            Arc::clone(&offline_guard),
-            config.runtime.events_rate_cap,
+
+    // Per-pod and per-namespace event quotas; what they drop is logged as
+    // one summary per source and interval.
+    let event_quota = config
+        .quotas
+        .enabled
+        .then(|| Arc::new(quota::EventQuota::new(&config.quotas, k8s_context.clone())));
+    if let Some(quota) = &event_quota {
+        info!(
+            "[quota] per-pod quota {}/s, per-namespace quota {}/s",
+            config.quotas.per_pod_events_per_sec, config.quotas.per_namespace_events_per_sec
         );
+        let quota = Arc::clone(quota);
+        let every = Duration::from_secs(config.quotas.summary_interval_secs.max(1));
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(every);
+            loop {
+                tick.tick().await;
+                for summary in quota.flush() {
+                    warn!("[quota] {summary}");
+                }
+            }
+        });
+    }
+
+    // Pass metrics to your listener
+    if let Some(buffers) = event_buffers
+        && let Some(mut guards) = _bpf_runtime.take()
+    {
+        let pipeline = EventPipeline {
+            context: Arc::clone(&context),
+            metrics: Arc::clone(&metrics),
+            handlers: Arc::clone(&handlers),
+            rate_cap: config.runtime.events_rate_cap,
+            argv: config.runtime.capture_argv.then(|| {
+                Arc::new(crate::runtime::argv::ArgvCache::new(Duration::from_secs(
+                    config.runtime.argv_cache_ttl_secs,
+                )))
+            }),
+            lineage: Arc::new(crate::runtime::lineage::LineageCache::default()),
+            quota: event_quota.clone(),
+            kernel_counts: Arc::clone(&kernel_counts),
+            file_paths: None,
+        };
+        let listener = start_listener(buffers, &mut guards, &pipeline, EventWindow::all());
+        let mut active = ActiveProbes {
+            _guards: guards,
+            listener,
+        };
+
+        // SIGUSR2 (and, if enabled, a changed object file) swaps in freshly
+        // loaded programs without a gap in events. SIGHUP is the rules'.
+        let (reload_tx, mut reload_rx) = tokio::sync::mpsc::channel::<&'static str>(1);
+        if config.runtime.reload_bpf_on_change
+            && let Some(bpf_path) = bpf_object_path
+        {
+            let tx = reload_tx.clone();
+            cognitod::watch::watch("bpf", bpf_path, &config.watch, &metrics, move || {
+                let _ = tx.try_send("object file changed");
+                Ok(())
+            });
+        }
+        let mut sigusr2 =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined2())?;
+        let probes_cfg = config.probes.clone();
+        let runtime_cfg = config.runtime.clone();
+        let throttle = Arc::clone(&throttle);
+        tokio::spawn(async move {
+            loop {
+                let reason = tokio::select! {
+                    _ = sigusr2.recv() => "SIGUSR2",
+                    Some(reason) = reload_rx.recv() => reason,
+                };
+                if mandate_holds_bpf_maps {
+                    warn!(
+                        "[cognitod] BPF reload ({reason}) skipped: mandate enforcement is bound to the running programs; restart to reload"
+                    );
+                    continue;
+                }
+                info!("[cognitod] reloading BPF programs ({reason})");
+                match reload_probes(&mut active, &pipeline, &probes_cfg, &runtime_cfg, &throttle)
+                    .await
+                {
+                    Ok(path) => info!("[cognitod] BPF programs reloaded from {path}"),
+                    Err(e) => {
+                        warn!("[cognitod] BPF reload failed, keeping running programs: {e:#}")
+                    }
+                }
+            }
+        });
     }
 
     // 🔁 Periodically refresh system snapshot (conditional on activity)
@@ -1048,6 +1965,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
         let queue_clone = Arc::clone(queue);
         let incident_store_clone = incident_store.clone();
         let incident_analyzer_clone = incident_analyzer.clone();
+        let profiler_cfg = config.profiler.enabled.then(|| config.profiler.clone());
+        let cb_leadership = leadership.clone();
 
         tokio::spawn(async move {
             if !cb_cfg.enabled {
@@ -1099,6 +2018,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
                             metrics_clone.inc_circuit_breaker_cpu_trip();
                             breach_started_at = None;
 
+                            if !cognitod::leader::is_leader(&cb_leadership) {
+                                info!("[circuit_breaker] standby: leaving the kill to the leader");
+                                sleep(Duration::from_secs(cb_cfg.check_interval_secs)).await;
+                                continue;
+                            }
+
                             let mut top_cpu_procs = ctx_clone.top_cpu_processes(1);
                             if top_cpu_procs.is_empty() {
                                 top_cpu_procs = ctx_clone.top_cpu_processes_systemwide(1);
@@ -1156,10 +2081,23 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                                 llm_analyzed_at: None,
                                                 recovery_time_ms: None,
                                                 psi_after: None,
+                                                profile: None,
+                                                breach_started_at: Some(
+                                                    chrono::Utc::now().timestamp()
+                                                        - duration as i64,
+                                                ),
                                             };
 
                                             let store_clone = Arc::clone(store);
                                             let analyzer_clone = incident_analyzer_clone.clone();
+                                            let profiler_cfg = profiler_cfg.clone();
+                                            let target_pid = proc.pid;
+                                            let metrics = Arc::clone(&metrics_clone);
+                                            let ctx = Arc::clone(&ctx_clone);
+                                            let (cpu_max, psi_max) = (
+                                                cb_cfg.cpu_usage_threshold,
+                                                cb_cfg.cpu_psi_threshold,
+                                            );
                                             tokio::spawn(async move {
                                                 if let Ok(id) = store_clone.insert(&incident).await
                                                 {
@@ -1168,10 +2106,39 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                                         id
                                                     );
 
+                                                    tokio::spawn(
+                                                        cognitod::incidents::watch_recovery(
+                                                            Arc::clone(&store_clone),
+                                                            id,
+                                                            ctx,
+                                                            move |s| {
+                                                                s.cpu_percent <= cpu_max
+                                                                    && s.psi_cpu_some_avg10
+                                                                        <= psi_max
+                                                            },
+                                                        ),
+                                                    );
+
+                                                    // Only useful if the target survived (monitor mode)
+                                                    if let Some(cfg) = profiler_cfg {
+                                                        let store = Arc::clone(&store_clone);
+                                                        tokio::spawn(async move {
+                                                            cognitod::profiler::profile_incident(
+                                                                &store, id, target_pid, &cfg,
+                                                            )
+                                                            .await
+                                                        });
+                                                    }
+
                                                     if let Some(analyzer) = analyzer_clone {
                                                         tokio::spawn(async move {
-                                                            match analyzer.analyze(&incident).await
-                                                            {
+                                                            let started = std::time::Instant::now();
+                                                            let result =
+                                                                analyzer.analyze(&incident).await;
+                                                            metrics.observe_ilm_latency(
+                                                                started.elapsed(),
+                                                            );
+                                                            match result {
                                                                 Ok(analysis) => {
                                                                     let _ = store_clone
                                                                         .add_llm_analysis(
@@ -1246,10 +2213,34 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Enforcement executor loop - actually executes approved actions
     if let Some(ref queue) = enforcement_queue {
         let queue_clone = Arc::clone(queue);
+        let observe = Arc::clone(&observe);
+        let enforcement_leadership = leadership.clone();
         tokio::spawn(async move {
             loop {
                 for action in queue_clone.get_all().await {
                     if action.status == cognitod::enforcement::ActionStatus::Approved {
+                        if !cognitod::leader::is_leader(&enforcement_leadership) {
+                            warn!(
+                                "[enforcement] standby: not executing approved action {}",
+                                action.id
+                            );
+                            let _ = queue_clone.observe(&action.id).await;
+                            continue;
+                        }
+                        let (kind, summary) = match action.action {
+                            cognitod::enforcement::ActionType::KillProcess { pid, signal } => (
+                                "kill_process",
+                                format!("signal {} to pid {}: {}", signal, pid, action.reason),
+                            ),
+                            cognitod::enforcement::ActionType::AuthorizeExec { pid, .. } => (
+                                "authorize_exec",
+                                format!("authorize exec for pid {}: {}", pid, action.reason),
+                            ),
+                        };
+                        if !observe.action(kind, summary) {
+                            let _ = queue_clone.observe(&action.id).await;
+                            continue;
+                        }
                         match action.action {
                             cognitod::enforcement::ActionType::KillProcess { pid, signal } => {
                                 info!("[enforcement] EXECUTING KILL pid={} signal={}", pid, signal);
@@ -1291,11 +2282,106 @@ async fn main() -> Result<(), Box<dyn Error>> {
         let history = Arc::clone(&alert_history);
         tokio::spawn(async move {
             while let Ok(alert) = alert_rx.recv().await {
-                history.add_alert(alert).await;
+                if !alert.is_resolution() {
+                    history.add_alert(alert).await;
+                }
             }
         });
     }
 
+    // Retention for the insight, alert and feedback logs
+    let storage = {
+        let insights = insight_store.file_path().map(Path::to_path_buf);
+        let alerts_file = config.logging.alerts_file.trim();
+        let paths = cognitod::retention::StorePaths {
+            feedback: insights.as_deref().map(cognitod::insights::feedback_path),
+            insights,
+            alerts: (!alerts_file.is_empty()).then(|| PathBuf::from(alerts_file)),
+            incident_db: incident_store.as_ref().map(|_| incident_db_path.clone()),
+        };
+        Arc::new(cognitod::retention::Storage::new(
+            config.retention.clone(),
+            paths,
+            Arc::clone(&insight_store),
+            incident_store.clone(),
+        ))
+    };
+    if config.retention.enabled {
+        let history = Arc::clone(&alert_history);
+        let storage = Arc::clone(&storage);
+        tokio::spawn(async move {
+            // The first tick fires immediately, so an agent restarted on a
+            // full disk recovers straight away.
+            let mut tick = tokio::time::interval(storage.interval());
+            loop {
+                tick.tick().await;
+                let report = storage.prune().await;
+                if let Some(cutoff) = storage.alerts_cutoff(report.at) {
+                    history.prune_before(cutoff).await;
+                }
+            }
+        });
+    }
+
+    // Downsampled telemetry history (1m/5m buckets in the incident DB)
+    if config.rollups.enabled
+        && let Some(store) = incident_store.clone()
+    {
+        let alerts_rx = alert_tx.as_ref().map(|tx| tx.subscribe());
+        tokio::spawn(cognitod::rollups::run(
+            store,
+            Arc::clone(&context),
+            alerts_rx,
+            config.rollups.clone(),
+        ));
+    }
+
+    if config.statsd.enabled {
+        tokio::spawn(cognitod::statsd::run(
+            Arc::clone(&metrics),
+            config.statsd.clone(),
+        ));
+    }
+
+    // Report newer releases once their signed checksums verify
+    let updates = if !config.update_check.enabled {
+        None
+    } else if offline_guard.is_offline() {
+        info!("[cognitod] update check disabled in offline mode");
+        None
+    } else {
+        match cognitod::update::UpdateChecker::new(config.update_check.clone(), network.client()) {
+            Ok(checker) => {
+                let checker = Arc::new(checker);
+                tokio::spawn(Arc::clone(&checker).run());
+                Some(checker)
+            }
+            Err(e) => {
+                warn!("[cognitod] update check disabled: {e:#}");
+                None
+            }
+        }
+    };
+
+    if let Some(baselines) = &baselines {
+        tokio::spawn(cognitod::baselines::run(
+            Arc::clone(baselines),
+            Arc::clone(&context),
+        ));
+    }
+
+    // Stack profiles for cpu_spin insights
+    if config.profiler.enabled
+        && let Some(store) = incident_store.clone()
+    {
+        tokio::spawn(cognitod::profiler::run_cpu_spin_profiler(
+            insight_store.subscribe(),
+            Arc::clone(&context),
+            store,
+            config.profiler.clone(),
+        ));
+    }
+
     let auth_token = std::env::var("LINNIX_API_TOKEN")
         .ok()
         .or(config.api.auth_token.clone());
@@ -1397,6 +2483,23 @@ async fn main() -> Result<(), Box<dyn Error>> {
         context: Arc::clone(&context),
         metrics: Arc::clone(&metrics),
         alerts: alert_tx,
+        rules: rule_engine,
+        storage: Some(storage),
+        baselines,
+        sessions,
+        inventory,
+        pod_pressure,
+        leadership,
+        updates,
+        observe: Some(observe),
+        quota: event_quota,
+        changes: Some(change_log),
+        throttle: Some(throttle),
+        malloc_sampler: config
+            .malloc_sampler
+            .enabled
+            .then(|| config.malloc_sampler.clone()),
+        http: network.client(),
         insights: Arc::clone(&insight_store),
         offline: Arc::clone(&offline_guard),
         transport,
@@ -1414,8 +2517,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
         spend_tracker,
         compliance_engine,
         receipt_redactor,
+        redaction,
         claw_metrics: Arc::new(cognitod::claw_metrics::ClawMetrics::new()),
         payment_adapter,
+        slack_signing_secret: config
+            .notifications
+            .as_ref()
+            .and_then(|n| n.slack.as_ref())
+            .and_then(|s| s.signing_secret.clone()),
     });
 
     let api = all_routes(app_state.clone());
@@ -1510,6 +2619,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn ring_buf_size_is_a_power_of_two_pages() {
+        assert_eq!(ring_buf_bytes(4096, 4096), 4 << 20);
+        assert_eq!(ring_buf_bytes(3000, 4096), 4 << 20);
+        assert_eq!(ring_buf_bytes(0, 4096), 4096);
+        assert_eq!(ring_buf_bytes(u32::MAX, 4096), 1 << 30);
+    }
+
     #[test]
     fn bpf_search_paths_canonical_order() {
         let paths = bpf_search_paths("test-bpf");
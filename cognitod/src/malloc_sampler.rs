@@ -0,0 +1,320 @@
+//! On-demand malloc sampling of one process.
+//!
+//! SubtreeRssMb says a process tree is growing, not where the memory goes.
+//! The sampler loads the `malloc_trace` object, attaches uprobes to `malloc`
+//! and `free` in the target's glibc for a few seconds, and reports the large
+//! allocations made in that window and not freed by its end, grouped by the
+//! user stack that made them. The probes only run for the sampling window,
+//! so the target doesn't have to be restarted under a heap profiler.
+//!
+//! Like the CPU profiler, stacks rely on frame pointers; without them the
+//! stack usually holds just the caller of `malloc`. Statically linked and
+//! musl binaries have no glibc mapping and can't be sampled.
+
+use crate::config::MallocSamplerConfig;
+use crate::profiler::{FoldedStack, Symbolizer, parse_maps};
+use anyhow::{Context, Result, bail};
+use aya::EbpfLoader;
+use aya::maps::{HashMap as AyaHashMap, MapData, StackTraceMap};
+use aya::programs::UProbe;
+use linnix_ai_ebpf_common::MallocAlloc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+
+/// `ALLOCS` values. MallocAlloc comes from the common crate, so aya::Pod
+/// can't be implemented for it directly.
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+struct BpfMallocAlloc(MallocAlloc);
+
+// SAFETY: MallocAlloc is #[repr(C)] with three 8-byte fields and no padding.
+unsafe impl aya::Pod for BpfMallocAlloc {}
+
+type AllocMap = AyaHashMap<MapData, u64, BpfMallocAlloc>;
+
+/// Uprobe programs of `malloc_trace` and the glibc functions they attach to.
+const PROBES: [(&str, &str); 3] = [
+    ("malloc_enter", "malloc"),
+    ("malloc_exit", "malloc"),
+    ("free_enter", "free"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MallocSample {
+    pub pid: u32,
+    pub comm: String,
+    /// glibc the probes were attached to, as mapped in the target.
+    pub libc: String,
+    pub duration_ms: u64,
+    pub min_bytes: u64,
+    /// Allocations made during the window and still live at its end.
+    pub allocations: u64,
+    pub outstanding_bytes: u64,
+    /// Outstanding bytes per stack, root first, highest first.
+    pub stacks: Vec<FoldedStack>,
+}
+
+impl MallocSample {
+    /// Build a report from outstanding allocations: (size, frames leaf
+    /// first).
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_allocations(
+        pid: u32,
+        comm: &str,
+        libc: &str,
+        duration_ms: u64,
+        min_bytes: u64,
+        allocations: &[(u64, Vec<String>)],
+        max_stacks: usize,
+    ) -> Self {
+        let mut stacks: HashMap<String, u64> = HashMap::new();
+        for (size, frames) in allocations {
+            let mut folded = comm.to_string();
+            for frame in frames.iter().rev() {
+                folded.push(';');
+                folded.push_str(frame);
+            }
+            *stacks.entry(folded).or_default() += size;
+        }
+        let mut stacks: Vec<FoldedStack> = stacks
+            .into_iter()
+            .map(|(stack, count)| FoldedStack { stack, count })
+            .collect();
+        stacks.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.stack.cmp(&b.stack)));
+        stacks.truncate(max_stacks);
+
+        Self {
+            pid,
+            comm: comm.to_string(),
+            libc: libc.to_string(),
+            duration_ms,
+            min_bytes,
+            allocations: allocations.len() as u64,
+            outstanding_bytes: allocations.iter().map(|(size, _)| size).sum(),
+            stacks,
+        }
+    }
+
+    /// Folded-stack text weighted by bytes, for flamegraph tooling.
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for s in &self.stacks {
+            out.push_str(&format!("{} {}\n", s.stack, s.count));
+        }
+        out
+    }
+
+    /// One-line summary, e.g. "48.0 MiB in 12 allocations not freed after
+    /// 10s, 75% from parse_row".
+    pub fn summary(&self) -> String {
+        if self.allocations == 0 {
+            return format!(
+                "no allocations of {}+ bytes outstanding after {}s",
+                self.min_bytes,
+                self.duration_ms / 1000
+            );
+        }
+        let mut summary = format!(
+            "{:.1} MiB in {} allocations not freed after {}s",
+            self.outstanding_bytes as f64 / (1024.0 * 1024.0),
+            self.allocations,
+            self.duration_ms / 1000
+        );
+        if let Some(top) = self.stacks.first() {
+            let caller = top.stack.rsplit(';').next().unwrap_or(&top.stack);
+            summary.push_str(&format!(
+                ", {:.0}% from {caller}",
+                top.count as f64 * 100.0 / self.outstanding_bytes as f64
+            ));
+        }
+        summary
+    }
+}
+
+/// Path of the glibc mapped into a process, from its `/proc/<pid>/maps`.
+fn find_libc(maps: &str) -> Option<String> {
+    maps.lines()
+        .filter_map(|line| line.split_whitespace().nth(5))
+        .find(|path| {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            name.starts_with("libc.so") || (name.starts_with("libc-") && name.ends_with(".so"))
+        })
+        .map(str::to_string)
+}
+
+/// Sample `pid` for `seconds` using the `malloc_trace` object in `bpf_bytes`.
+pub async fn sample(
+    pid: u32,
+    seconds: u64,
+    bpf_bytes: Vec<u8>,
+    cfg: &MallocSamplerConfig,
+) -> Result<MallocSample> {
+    let cfg = cfg.clone();
+    tokio::task::spawn_blocking(move || sample_blocking(pid, seconds, &bpf_bytes, &cfg))
+        .await
+        .context("malloc sampler task panicked")?
+}
+
+fn sample_blocking(
+    pid: u32,
+    seconds: u64,
+    bpf_bytes: &[u8],
+    cfg: &MallocSamplerConfig,
+) -> Result<MallocSample> {
+    let comm = fs::read_to_string(format!("/proc/{pid}/comm"))
+        .with_context(|| format!("process {pid} not found"))?
+        .trim()
+        .to_string();
+    let maps = fs::read_to_string(format!("/proc/{pid}/maps"))
+        .with_context(|| format!("cannot read memory maps of pid {pid}"))?;
+    let Some(libc) = find_libc(&maps) else {
+        bail!("pid {pid} ({comm}) has no glibc mapped (static or musl binary?)");
+    };
+    // Go through the process root so containerised processes resolve.
+    let target = format!("/proc/{pid}/root{libc}");
+
+    let (allocs, stack_map) = run_probes(pid, seconds, bpf_bytes, cfg.min_bytes, &target)
+        .with_context(|| format!("failed to probe {libc}"))?;
+
+    // Re-read the maps: libraries loaded during the window need them too.
+    let maps = fs::read_to_string(format!("/proc/{pid}/maps")).unwrap_or(maps);
+    let mut symbolizer = Symbolizer::new(pid, parse_maps(&maps));
+    let mut stacks: HashMap<i64, Vec<String>> = HashMap::new();
+    let mut outstanding = Vec::new();
+    for (_, BpfMallocAlloc(alloc)) in allocs.iter().flatten() {
+        let frames = stacks
+            .entry(alloc.stack_id)
+            .or_insert_with(|| {
+                u32::try_from(alloc.stack_id)
+                    .ok()
+                    .and_then(|id| stack_map.get(&id, 0).ok())
+                    .map(|trace| {
+                        trace
+                            .frames()
+                            .iter()
+                            .map(|frame| symbolizer.resolve(frame.ip))
+                            .collect()
+                    })
+                    .unwrap_or_else(|| vec!["[no stack]".to_string()])
+            })
+            .clone();
+        outstanding.push((alloc.size, frames));
+    }
+
+    Ok(MallocSample::from_allocations(
+        pid,
+        &comm,
+        &libc,
+        seconds * 1000,
+        cfg.min_bytes,
+        &outstanding,
+        cfg.max_stacks,
+    ))
+}
+
+/// Attach the probes to `target` for `seconds` and take the allocation and
+/// stack maps. The probes are detached when the object is dropped on
+/// return; the taken maps stay readable.
+fn run_probes(
+    pid: u32,
+    seconds: u64,
+    bpf_bytes: &[u8],
+    min_bytes: u64,
+    target: &str,
+) -> Result<(AllocMap, StackTraceMap<MapData>)> {
+    let mut bpf = EbpfLoader::new()
+        .set_global("MALLOC_MIN_BYTES", &min_bytes, true)
+        .load(bpf_bytes)
+        .context("failed to load malloc_trace")?;
+    for (program, function) in PROBES {
+        let probe: &mut UProbe = bpf
+            .program_mut(program)
+            .ok_or_else(|| anyhow::anyhow!("{program} program not found"))?
+            .try_into()?;
+        probe.load()?;
+        probe
+            .attach(Some(function), 0, target, Some(pid as i32))
+            .with_context(|| format!("failed to attach to {function}"))?;
+    }
+
+    std::thread::sleep(Duration::from_secs(seconds));
+
+    let allocs = bpf
+        .take_map("ALLOCS")
+        .context("ALLOCS map not found")?
+        .try_into()?;
+    let stacks = bpf
+        .take_map("STACKS")
+        .context("STACKS map not found")?
+        .try_into()?;
+    Ok((allocs, stacks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn groups_outstanding_bytes_by_stack() {
+        let mib = 1024 * 1024;
+        let allocations = vec![
+            (16 * mib, frames(&["parse_row", "load", "main"])),
+            (20 * mib, frames(&["parse_row", "load", "main"])),
+            (12 * mib, frames(&["cache_put", "main"])),
+            (mib, frames(&["[no stack]"])),
+        ];
+        let sample = MallocSample::from_allocations(
+            7,
+            "etl",
+            "/usr/lib/libc.so.6",
+            10_000,
+            65_536,
+            &allocations,
+            2,
+        );
+        assert_eq!(sample.allocations, 4);
+        assert_eq!(sample.outstanding_bytes, 49 * mib);
+        assert_eq!(
+            sample.folded(),
+            format!(
+                "etl;main;load;parse_row {}\netl;main;cache_put {}\n",
+                36 * mib,
+                12 * mib
+            )
+        );
+        assert_eq!(
+            sample.summary(),
+            "49.0 MiB in 4 allocations not freed after 10s, 73% from parse_row"
+        );
+
+        let empty = MallocSample::from_allocations(7, "etl", "", 5_000, 65_536, &[], 2);
+        assert_eq!(
+            empty.summary(),
+            "no allocations of 65536+ bytes outstanding after 5s"
+        );
+    }
+
+    #[test]
+    fn finds_glibc_in_maps() {
+        let maps = "\
+55d0c0000000-55d0c0001000 r-xp 00000000 08:01 100 /usr/bin/etl
+7f0000000000-7f0000028000 r--p 00000000 08:01 200 /usr/lib/x86_64-linux-gnu/libc.so.6
+7f0000100000-7f0000101000 r-xp 00000000 08:01 300 /usr/lib/libcrypto.so.3
+";
+        assert_eq!(
+            find_libc(maps).as_deref(),
+            Some("/usr/lib/x86_64-linux-gnu/libc.so.6")
+        );
+        assert_eq!(
+            find_libc("7f00-7f01 r-xp 0 08:01 1 /lib/libc-2.31.so").as_deref(),
+            Some("/lib/libc-2.31.so")
+        );
+        assert_eq!(find_libc("55d0-55d1 r-xp 0 08:01 1 /usr/bin/static"), None);
+    }
+}
@@ -1,10 +1,141 @@
-use std::sync::RwLock;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::atomic::AtomicU32;
 use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::SystemTime;
 
 const EVENT_TYPE_SLOTS: usize = 8;
 
+/// Evaluation counters for one rule. The rule engine holds an `Arc` to its
+/// own counters so the hot path never touches the registry lock.
+#[derive(Debug, Default)]
+pub struct RuleCounters {
+    evaluations: AtomicU64,
+    fires: AtomicU64,
+    shadow_matches: AtomicU64,
+    eval_ns: AtomicU64,
+    max_eval_ns: AtomicU64,
+    /// Keys the rule keeps counters for, by scope key.
+    scoped_keys: AtomicUsize,
+}
+
+impl RuleCounters {
+    pub fn record_eval(&self, elapsed: std::time::Duration) {
+        let ns = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+        self.eval_ns.fetch_add(ns, Ordering::Relaxed);
+        self.max_eval_ns.fetch_max(ns, Ordering::Relaxed);
+    }
+
+    pub fn inc_fired(&self) {
+        self.fires.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An alert a shadow rule would have sent.
+    pub fn inc_shadow_match(&self) {
+        self.shadow_matches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn eval_ns(&self) -> u64 {
+        self.eval_ns.load(Ordering::Relaxed)
+    }
+
+    pub fn set_scoped_keys(&self, n: usize) {
+        self.scoped_keys.store(n, Ordering::Relaxed);
+    }
+
+    pub fn scoped_keys(&self) -> usize {
+        self.scoped_keys.load(Ordering::Relaxed)
+    }
+}
+
+/// Dispatch counters for one event handler.
+#[derive(Debug, Default)]
+pub struct HandlerCounters {
+    events: AtomicU64,
+    errors: AtomicU64,
+    panics: AtomicU64,
+}
+
+impl HandlerCounters {
+    pub fn inc_event(&self) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the error count including this one.
+    pub fn inc_error(&self) -> u64 {
+        self.errors.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn inc_panic(&self) {
+        self.panics.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HandlerStats {
+    pub handler: String,
+    pub events: u64,
+    pub errors: u64,
+    pub panics: u64,
+}
+
+/// Reload counters for one watched file (see `watch`).
+#[derive(Debug, Default)]
+pub struct WatchCounters {
+    path: String,
+    polling: AtomicBool,
+    changes: AtomicU64,
+    reloads: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl WatchCounters {
+    pub fn set_polling(&self, polling: bool) {
+        self.polling.store(polling, Ordering::Relaxed);
+    }
+
+    pub fn inc_change(&self) {
+        self.changes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_reload(&self) {
+        self.reloads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn inc_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchStats {
+    pub watch: String,
+    pub path: String,
+    /// True when inotify was unavailable and the file is polled.
+    pub polling: bool,
+    pub changes: u64,
+    pub reloads: u64,
+    pub failures: u64,
+}
+
+/// Snapshot of one rule's counters, as served by `/rules/stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleStats {
+    pub rule: String,
+    pub evaluations: u64,
+    pub fires: u64,
+    /// Alerts held back because the rule is in shadow mode.
+    pub shadow_matches: u64,
+    pub eval_time_us: u64,
+    pub avg_eval_ns: u64,
+    pub max_eval_ns: u64,
+    /// Share of the total rule evaluation time spent in this rule.
+    pub share_pct: f64,
+}
+
 /// Global metrics for the cognition daemon.
 ///
 /// Counters are updated from the hot path so all fields are atomic.
@@ -20,8 +151,13 @@ pub struct Metrics {
     events_this_sec: AtomicU64,
     events_per_sec: AtomicU64,
     rb_overflows: AtomicU64,
+    kernel_dropped_events: AtomicU64,
     rate_limited_events: AtomicU64,
     lineage_hits: AtomicU64,
+    quota_suppressed: AtomicU64,
+    alerts_silenced: AtomicU64,
+    sched_latency_p95_us: AtomicU64,
+    sched_latency_samples: AtomicU64,
     lineage_misses: AtomicU64,
     drops_by_type: [AtomicU64; EVENT_TYPE_SLOTS],
     alerts_emitted_total: AtomicU64,
@@ -33,6 +169,8 @@ pub struct Metrics {
     ilm_timeouts: AtomicU64,
     ilm_insights: AtomicU64,
     ilm_schema_errors: AtomicU64,
+    ilm_requests: AtomicU64,
+    ilm_latency_ms_total: AtomicU64,
     ilm_enabled: AtomicBool,
     ilm_disabled_reason: RwLock<String>,
     // PSI (Pressure Stall Information) gauges - stored as f32 * 100 to use AtomicU32
@@ -54,6 +192,12 @@ pub struct Metrics {
     pub slack_failed_total: AtomicU64,
     pub alerts_generated_total: AtomicU64,
     pub feedback_entries_total: AtomicU64,
+    // Rule engine state guards
+    detector_state_evictions: AtomicU64,
+    detector_state_keys: AtomicUsize,
+    rule_counters: RwLock<HashMap<String, Arc<RuleCounters>>>,
+    handler_counters: RwLock<Vec<(String, Arc<HandlerCounters>)>>,
+    watch_counters: RwLock<Vec<(String, Arc<WatchCounters>)>>,
 }
 
 #[allow(dead_code)]
@@ -69,8 +213,13 @@ impl Metrics {
             events_this_sec: AtomicU64::new(0),
             events_per_sec: AtomicU64::new(0),
             rb_overflows: AtomicU64::new(0),
+            kernel_dropped_events: AtomicU64::new(0),
             rate_limited_events: AtomicU64::new(0),
             lineage_hits: AtomicU64::new(0),
+            quota_suppressed: AtomicU64::new(0),
+            alerts_silenced: AtomicU64::new(0),
+            sched_latency_p95_us: AtomicU64::new(0),
+            sched_latency_samples: AtomicU64::new(0),
             lineage_misses: AtomicU64::new(0),
             drops_by_type: std::array::from_fn(|_| AtomicU64::new(0)),
             alerts_emitted_total: AtomicU64::new(0),
@@ -82,6 +231,8 @@ impl Metrics {
             ilm_timeouts: AtomicU64::new(0),
             ilm_insights: AtomicU64::new(0),
             ilm_schema_errors: AtomicU64::new(0),
+            ilm_requests: AtomicU64::new(0),
+            ilm_latency_ms_total: AtomicU64::new(0),
             ilm_enabled: AtomicBool::new(false),
             ilm_disabled_reason: RwLock::new(String::new()),
             psi_cpu_some_avg10: AtomicU32::new(0),
@@ -100,6 +251,11 @@ impl Metrics {
             slack_failed_total: AtomicU64::new(0),
             alerts_generated_total: AtomicU64::new(0),
             feedback_entries_total: AtomicU64::new(0),
+            detector_state_evictions: AtomicU64::new(0),
+            detector_state_keys: AtomicUsize::new(0),
+            rule_counters: RwLock::new(HashMap::new()),
+            handler_counters: RwLock::new(Vec::new()),
+            watch_counters: RwLock::new(Vec::new()),
         }
     }
 
@@ -142,6 +298,22 @@ impl Metrics {
         self.rb_overflows.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Events the probes could not hand over because the perf or ring
+    /// buffer was full. Counts one overflow and `lost` dropped events.
+    pub fn record_kernel_drops(&self, lost: u64) {
+        if lost == 0 {
+            return;
+        }
+        self.inc_rb_overflow();
+        self.kernel_dropped_events
+            .fetch_add(lost, Ordering::Relaxed);
+        self.dropped_events_total.fetch_add(lost, Ordering::Relaxed);
+    }
+
+    pub fn kernel_dropped_events(&self) -> u64 {
+        self.kernel_dropped_events.load(Ordering::Relaxed)
+    }
+
     pub fn rate_limited_events(&self) -> u64 {
         self.rate_limited_events.load(Ordering::Relaxed)
     }
@@ -162,6 +334,39 @@ impl Metrics {
         self.lineage_hits.load(Ordering::Relaxed)
     }
 
+    /// An event was dropped for exceeding its pod's or namespace's quota.
+    pub fn inc_quota_suppressed(&self) {
+        self.quota_suppressed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn quota_suppressed(&self) -> u64 {
+        self.quota_suppressed.load(Ordering::Relaxed)
+    }
+
+    /// A rule alert was dropped by a silence.
+    pub fn inc_alerts_silenced(&self) {
+        self.alerts_silenced.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn alerts_silenced(&self) -> u64 {
+        self.alerts_silenced.load(Ordering::Relaxed)
+    }
+
+    /// p95 runqueue delay over the last read of the scheduler probe's
+    /// histogram, and how many delays it covered.
+    pub fn set_sched_latency(&self, p95_us: u64, samples: u64) {
+        self.sched_latency_p95_us.store(p95_us, Ordering::Relaxed);
+        self.sched_latency_samples.store(samples, Ordering::Relaxed);
+    }
+
+    pub fn sched_latency_p95_us(&self) -> u64 {
+        self.sched_latency_p95_us.load(Ordering::Relaxed)
+    }
+
+    pub fn sched_latency_samples(&self) -> u64 {
+        self.sched_latency_samples.load(Ordering::Relaxed)
+    }
+
     pub fn lineage_misses(&self) -> u64 {
         self.lineage_misses.load(Ordering::Relaxed)
     }
@@ -192,6 +397,10 @@ impl Metrics {
         self.active_rules.fetch_add(count, Ordering::Relaxed);
     }
 
+    pub fn sub_active_rules(&self, count: usize) {
+        self.active_rules.fetch_sub(count, Ordering::Relaxed);
+    }
+
     pub fn active_rules(&self) -> usize {
         self.active_rules.load(Ordering::Relaxed)
     }
@@ -257,6 +466,21 @@ impl Metrics {
         self.ilm_schema_errors.load(Ordering::Relaxed)
     }
 
+    /// Record how long one LLM analysis request took.
+    pub fn observe_ilm_latency(&self, elapsed: std::time::Duration) {
+        self.ilm_requests.fetch_add(1, Ordering::Relaxed);
+        self.ilm_latency_ms_total
+            .fetch_add(elapsed.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn ilm_requests(&self) -> u64 {
+        self.ilm_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn ilm_latency_ms_total(&self) -> u64 {
+        self.ilm_latency_ms_total.load(Ordering::Relaxed)
+    }
+
     pub fn set_ilm_enabled(&self, enabled: bool) {
         self.ilm_enabled.store(enabled, Ordering::Relaxed);
     }
@@ -414,6 +638,23 @@ impl Metrics {
         self.alerts_generated_total.load(Ordering::Relaxed)
     }
 
+    pub fn add_detector_state_evictions(&self, n: u64) {
+        self.detector_state_evictions
+            .fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn detector_state_evictions(&self) -> u64 {
+        self.detector_state_evictions.load(Ordering::Relaxed)
+    }
+
+    pub fn set_detector_state_keys(&self, n: usize) {
+        self.detector_state_keys.store(n, Ordering::Relaxed);
+    }
+
+    pub fn detector_state_keys(&self) -> usize {
+        self.detector_state_keys.load(Ordering::Relaxed)
+    }
+
     pub fn inc_feedback_entry(&self) {
         self.feedback_entries_total.fetch_add(1, Ordering::Relaxed);
     }
@@ -421,6 +662,124 @@ impl Metrics {
     pub fn feedback_entries(&self) -> u64 {
         self.feedback_entries_total.load(Ordering::Relaxed)
     }
+
+    /// Counters for `rule`, created on first use. Re-registering a name
+    /// keeps its history.
+    pub fn register_rule(&self, rule: &str) -> Arc<RuleCounters> {
+        let mut counters = self
+            .rule_counters
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        Arc::clone(counters.entry(rule.to_string()).or_default())
+    }
+
+    /// Forget the counters of a rule that is no longer loaded.
+    pub fn unregister_rule(&self, rule: &str) {
+        self.rule_counters
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(rule);
+    }
+
+    /// Per-rule stats, most expensive first.
+    pub fn rule_stats(&self) -> Vec<RuleStats> {
+        let counters = self.rule_counters.read().unwrap_or_else(|e| e.into_inner());
+        let total_ns: u64 = counters.values().map(|c| c.eval_ns()).sum();
+        let mut stats: Vec<RuleStats> = counters
+            .iter()
+            .map(|(rule, c)| {
+                let evaluations = c.evaluations.load(Ordering::Relaxed);
+                let eval_ns = c.eval_ns();
+                RuleStats {
+                    rule: rule.clone(),
+                    evaluations,
+                    fires: c.fires.load(Ordering::Relaxed),
+                    shadow_matches: c.shadow_matches.load(Ordering::Relaxed),
+                    eval_time_us: eval_ns / 1000,
+                    avg_eval_ns: eval_ns.checked_div(evaluations).unwrap_or(0),
+                    max_eval_ns: c.max_eval_ns.load(Ordering::Relaxed),
+                    share_pct: if total_ns > 0 {
+                        eval_ns as f64 * 100.0 / total_ns as f64
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.eval_time_us
+                .cmp(&a.eval_time_us)
+                .then_with(|| a.rule.cmp(&b.rule))
+        });
+        stats
+    }
+
+    /// Counters for a handler, created on first use.
+    pub fn register_handler(&self, handler: &str) -> Arc<HandlerCounters> {
+        let mut counters = self
+            .handler_counters
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some((_, c)) = counters.iter().find(|(name, _)| name == handler) {
+            return Arc::clone(c);
+        }
+        let c = Arc::new(HandlerCounters::default());
+        counters.push((handler.to_string(), Arc::clone(&c)));
+        c
+    }
+
+    /// Per-handler dispatch counters, in registration order.
+    pub fn handler_stats(&self) -> Vec<HandlerStats> {
+        let counters = self
+            .handler_counters
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        counters
+            .iter()
+            .map(|(handler, c)| HandlerStats {
+                handler: handler.clone(),
+                events: c.events.load(Ordering::Relaxed),
+                errors: c.errors.load(Ordering::Relaxed),
+                panics: c.panics.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Counters for a watched file, created on first use.
+    pub fn register_watch(&self, watch: &str, path: &Path) -> Arc<WatchCounters> {
+        let mut counters = self
+            .watch_counters
+            .write()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some((_, c)) = counters.iter().find(|(name, _)| name == watch) {
+            return Arc::clone(c);
+        }
+        let c = Arc::new(WatchCounters {
+            path: path.display().to_string(),
+            ..Default::default()
+        });
+        counters.push((watch.to_string(), Arc::clone(&c)));
+        c
+    }
+
+    /// Per-watch reload counters, in registration order.
+    pub fn watch_stats(&self) -> Vec<WatchStats> {
+        let counters = self
+            .watch_counters
+            .read()
+            .unwrap_or_else(|e| e.into_inner());
+        counters
+            .iter()
+            .map(|(watch, c)| WatchStats {
+                watch: watch.clone(),
+                path: c.path.clone(),
+                polling: c.polling.load(Ordering::Relaxed),
+                changes: c.changes.load(Ordering::Relaxed),
+                reloads: c.reloads.load(Ordering::Relaxed),
+                failures: c.failures.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
 }
 
 impl Default for Metrics {
@@ -453,4 +812,26 @@ mod tests {
             .unwrap_or(0);
         assert!(low_value_drops > 0);
     }
+
+    #[test]
+    fn rule_stats_report_counts_and_share() {
+        let m = Metrics::new();
+        let slow = m.register_rule("slow");
+        let fast = m.register_rule("fast");
+        slow.record_eval(std::time::Duration::from_micros(30));
+        slow.record_eval(std::time::Duration::from_micros(50));
+        slow.inc_fired();
+        fast.record_eval(std::time::Duration::from_micros(20));
+
+        // Re-registering returns the same counters.
+        assert_eq!(m.register_rule("slow").eval_ns(), 80_000);
+
+        let stats = m.rule_stats();
+        assert_eq!(stats[0].rule, "slow");
+        assert_eq!((stats[0].evaluations, stats[0].fires), (2, 1));
+        assert_eq!(stats[0].avg_eval_ns, 40_000);
+        assert_eq!(stats[0].max_eval_ns, 50_000);
+        assert!((stats[0].share_pct - 80.0).abs() < 1e-9);
+        assert_eq!((stats[1].rule.as_str(), stats[1].fires), ("fast", 0));
+    }
 }
@@ -0,0 +1,112 @@
+//! Outbound HTTP settings shared by every client.
+//!
+//! Many production nodes only reach Slack, the LLM endpoint or the internet
+//! through a proxy, often one that re-signs TLS with a corporate CA. The
+//! `[network]` section is parsed once at startup into [`Network`], which
+//! hands out `reqwest` client builders with the proxies and extra roots
+//! applied, and proxy variables for the Apprise subprocess.
+
+use crate::config::NetworkConfig;
+use anyhow::{Context, Result};
+use reqwest::{Certificate, Client, ClientBuilder, NoProxy, Proxy};
+use std::fs;
+
+#[derive(Clone, Default)]
+pub struct Network {
+    proxies: Vec<Proxy>,
+    roots: Vec<Certificate>,
+    /// `(name, value)` pairs for child processes.
+    env: Vec<(&'static str, String)>,
+}
+
+impl Network {
+    /// Validate proxy URLs and load the CA bundle.
+    pub fn from_config(config: &NetworkConfig) -> Result<Self> {
+        let no_proxy = config.no_proxy.as_deref().and_then(NoProxy::from_string);
+        let mut network = Network::default();
+        if let Some(url) = &config.http_proxy {
+            let proxy = Proxy::http(url).with_context(|| format!("invalid http_proxy '{url}'"))?;
+            network.proxies.push(proxy.no_proxy(no_proxy.clone()));
+            network.env.push(("HTTP_PROXY", url.clone()));
+        }
+        if let Some(url) = &config.https_proxy {
+            let proxy =
+                Proxy::https(url).with_context(|| format!("invalid https_proxy '{url}'"))?;
+            network.proxies.push(proxy.no_proxy(no_proxy.clone()));
+            network.env.push(("HTTPS_PROXY", url.clone()));
+        }
+        if let Some(list) = &config.no_proxy {
+            network.env.push(("NO_PROXY", list.clone()));
+        }
+        if let Some(path) = &config.ca_bundle {
+            let pem = fs::read(path).with_context(|| format!("reading ca_bundle {path}"))?;
+            network.roots = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("parsing ca_bundle {path}"))?;
+            // Python's requests (Apprise) replaces its roots with this file.
+            network.env.push(("REQUESTS_CA_BUNDLE", path.clone()));
+        }
+        Ok(network)
+    }
+
+    /// Client builder with the configured proxies and roots; without any,
+    /// reqwest's defaults (proxy environment variables, system roots) apply.
+    pub fn client_builder(&self) -> ClientBuilder {
+        let mut builder = Client::builder();
+        for proxy in &self.proxies {
+            builder = builder.proxy(proxy.clone());
+        }
+        for cert in &self.roots {
+            builder = builder.add_root_certificate(cert.clone());
+        }
+        builder
+    }
+
+    pub fn client(&self) -> Client {
+        self.client_builder()
+            .build()
+            .unwrap_or_else(|_| Client::new())
+    }
+
+    /// Environment variables that point child processes at the same proxy.
+    pub fn proxy_env(&self) -> &[(&'static str, String)] {
+        &self.env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_clients_and_child_env_from_config() {
+        let network = Network::from_config(&NetworkConfig {
+            https_proxy: Some("http://proxy.corp:3128".into()),
+            no_proxy: Some("localhost,.svc".into()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(network.client_builder().build().is_ok());
+        assert_eq!(
+            network.proxy_env(),
+            [
+                ("HTTPS_PROXY", "http://proxy.corp:3128".to_string()),
+                ("NO_PROXY", "localhost,.svc".to_string()),
+            ]
+        );
+
+        let err = Network::from_config(&NetworkConfig {
+            ca_bundle: Some("/nonexistent/ca.pem".into()),
+            ..Default::default()
+        })
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("ca_bundle"));
+        assert!(
+            Network::from_config(&NetworkConfig {
+                http_proxy: Some("not a url".into()),
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+}
@@ -1,7 +1,11 @@
-use crate::alerts::{Alert, Severity};
+use crate::alerts::Alert;
 use crate::config::AppriseConfig;
+use crate::inventory::HostFacts;
+use crate::network::Network;
+use crate::redaction::{Destination, RedactionPolicy};
 use anyhow::{Context, Result};
 use log::{debug, error, info};
+use std::sync::Arc;
 use tokio::process::Command;
 use tokio::sync::broadcast;
 
@@ -11,47 +15,53 @@ use tokio::sync::broadcast;
 /// which handles delivery to 100+ notification services (Slack, Discord, etc.)
 pub struct AppriseNotifier {
     urls: Vec<String>,
-    min_severity: Severity,
     rx: broadcast::Receiver<Alert>,
+    redaction: Arc<RedactionPolicy>,
+    inventory: Option<Arc<HostFacts>>,
+    /// Proxy variables for the `apprise` process.
+    env: Vec<(&'static str, String)>,
 }
 
 impl AppriseNotifier {
     /// Create a new Apprise notifier
     pub fn new(config: AppriseConfig, rx: broadcast::Receiver<Alert>) -> Self {
-        let min_severity = parse_severity(config.min_severity.as_deref().unwrap_or("info"));
-
         Self {
             urls: config.urls,
-            min_severity,
             rx,
+            redaction: Arc::new(RedactionPolicy::default()),
+            inventory: None,
+            env: Vec::new(),
         }
     }
 
+    /// Apply an outbound redaction policy to every notification.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Append the host's facts (instance type, kernel, ...) to each body.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Run `apprise` with the configured proxy and CA bundle.
+    pub fn with_network(mut self, network: &Network) -> Self {
+        self.env = network.proxy_env().to_vec();
+        self
+    }
+
     /// Run the notifier loop
     ///
     /// Listens for alerts on the broadcast channel and sends them via Apprise.
     /// Runs until the channel is closed.
     pub async fn run(mut self) {
-        info!(
-            "Apprise notifier started with {} URL(s), min severity: {}",
-            self.urls.len(),
-            self.min_severity.as_str()
-        );
+        info!("Apprise notifier started with {} URL(s)", self.urls.len());
 
         loop {
             match self.rx.recv().await {
                 Ok(alert) => {
-                    // Filter by severity
-                    if alert.severity < self.min_severity {
-                        debug!(
-                            "Skipping alert '{}' (severity {} < threshold {})",
-                            alert.rule,
-                            alert.severity.as_str(),
-                            self.min_severity.as_str()
-                        );
-                        continue;
-                    }
-
                     // Send notification
                     if let Err(e) = self.notify(&alert).await {
                         error!("Failed to send Apprise notification: {}", e);
@@ -73,12 +83,24 @@ impl AppriseNotifier {
 
     /// Send a single alert via Apprise CLI
     async fn notify(&self, alert: &Alert) -> Result<()> {
-        let title = format!(
-            "[{}] {}",
-            alert.severity.as_str().to_uppercase(),
-            alert.rule
-        );
-        let body = format!("Host: {}\n\n{}", alert.host, alert.message);
+        let alert = &self
+            .redaction
+            .redact_alert(Destination::Notifications, alert);
+        let title = match alert.is_resolution() {
+            true => format!("[RESOLVED] {}", alert.rule),
+            false => format!(
+                "[{}] {}",
+                alert.severity.as_str().to_uppercase(),
+                alert.rule
+            ),
+        };
+        let mut body = format!("Host: {}\n\n{}", alert.host, alert.message);
+        if !alert.labels.is_empty() {
+            body.push_str(&format!("\n\n{}", alert.labels_line()));
+        }
+        if let Some(facts) = &self.inventory {
+            body.push_str(&format!("\n\n{facts}"));
+        }
 
         debug!("Sending notification: '{}'", title);
 
@@ -95,6 +117,7 @@ impl AppriseNotifier {
     /// Send notification to a single Apprise URL
     async fn send_to_url(&self, url: &str, title: &str, body: &str) -> Result<()> {
         let output = Command::new("apprise")
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
             .arg("--title")
             .arg(title)
             .arg("--body")
@@ -114,16 +137,6 @@ impl AppriseNotifier {
     }
 }
 
-/// Parse severity string into Severity enum
-fn parse_severity(s: &str) -> Severity {
-    match s.to_lowercase().as_str() {
-        "high" => Severity::High,
-        "medium" => Severity::Medium,
-        "low" => Severity::Low,
-        _ => Severity::Info,
-    }
-}
-
 /// Mask sensitive information in URLs for logging
 fn mask_url(url: &str) -> String {
     if let Some(scheme_end) = url.find("://") {
@@ -138,16 +151,6 @@ fn mask_url(url: &str) -> String {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_severity() {
-        assert!(matches!(parse_severity("high"), Severity::High));
-        assert!(matches!(parse_severity("HIGH"), Severity::High));
-        assert!(matches!(parse_severity("medium"), Severity::Medium));
-        assert!(matches!(parse_severity("low"), Severity::Low));
-        assert!(matches!(parse_severity("info"), Severity::Info));
-        assert!(matches!(parse_severity("invalid"), Severity::Info));
-    }
-
     #[test]
     fn test_mask_url() {
         assert_eq!(mask_url("slack://token/channel"), "slack://***");
@@ -0,0 +1,691 @@
+//! Routes alerts from the rules broadcast channel to notifiers.
+//!
+//! A rule picks its channels with `notify: [slack, email]`; `notify: []`
+//! keeps its alerts off every channel (they still reach the alerts file and
+//! the API). Alerts of rules without `notify`, and of plugins, go to every
+//! channel.
+//!
+//! A [`NotificationRouter`] then drops channels below the alert's severity
+//! floor, or in quiet hours, for the alert.
+//!
+//! With a budget, alerts beyond `max_alerts_per_minute` are held back and
+//! folded into one summary alert per minute, so a flapping rule can't bury
+//! a channel during an incident.
+//!
+//! With a digest, alerts below `immediate_severity` are batched per channel
+//! and sent as one digest alert per interval; more severe ones go out
+//! right away.
+//!
+//! Both dispatchers send only while this instance is the elected leader,
+//! and in observe mode record each notification instead of sending it, so
+//! the notifiers themselves don't check either.
+
+use super::router::{NotificationRouter, parse_severity};
+use crate::alerts::{Alert, RuleEngine, Severity};
+use crate::config::DigestConfig;
+use crate::insights::InsightRecord;
+use crate::leader::{self, Leadership};
+use crate::observe::ObserveRecorder;
+use log::{debug, error, info, warn};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::{Duration, Instant};
+
+/// Channel names rules can route to.
+pub const CHANNELS: [&str; 8] = [
+    "slack", "apprise", "email", "teams", "telegram", "opsgenie", "syslog", "nats",
+];
+
+const CHANNEL_CAPACITY: usize = 128;
+const BUDGET_WINDOW: Duration = Duration::from_secs(60);
+/// Rule of the alert summarizing the alerts held back by the budget.
+const BUDGET_RULE: &str = "alert_budget";
+/// Rule of digest alerts.
+const DIGEST_RULE: &str = "digest";
+/// Alerts listed one per line in a digest; the rest are only counted.
+const DIGEST_MAX_LINES: usize = 20;
+
+/// Alerts sent in the current minute, and the ones held back.
+struct AlertBudget {
+    max_per_minute: u32,
+    window_start: Option<Instant>,
+    sent: u32,
+    /// Held back alerts by rule.
+    folded: BTreeMap<String, u64>,
+    severity: Option<Severity>,
+    host: String,
+}
+
+impl AlertBudget {
+    fn new(max_per_minute: u32) -> Self {
+        Self {
+            max_per_minute,
+            window_start: None,
+            sent: 0,
+            folded: BTreeMap::new(),
+            severity: None,
+            host: String::new(),
+        }
+    }
+
+    /// Whether `alert` fits in the budget; if not, it is folded into the
+    /// next summary.
+    fn admit(&mut self, alert: &Alert, now: Instant) -> bool {
+        if self
+            .window_start
+            .is_none_or(|start| now.duration_since(start) >= BUDGET_WINDOW)
+        {
+            self.window_start = Some(now);
+            self.sent = 0;
+        }
+        if self.sent < self.max_per_minute {
+            self.sent += 1;
+            return true;
+        }
+        if self.folded.is_empty() {
+            warn!(
+                "[notifications] alert budget of {}/min reached; folding further alerts into a summary",
+                self.max_per_minute
+            );
+        }
+        *self.folded.entry(alert.rule.clone()).or_default() += 1;
+        if self.severity.as_ref().is_none_or(|s| alert.severity > *s) {
+            self.severity = Some(alert.severity.clone());
+        }
+        self.host.clone_from(&alert.host);
+        false
+    }
+
+    /// When the summary of the current minute is due, if alerts were held.
+    fn summary_due(&self) -> Option<Instant> {
+        if self.folded.is_empty() {
+            return None;
+        }
+        self.window_start.map(|start| start + BUDGET_WINDOW)
+    }
+
+    /// One alert for everything held back since the last summary, e.g.
+    /// `alert budget of 30/min exceeded: 43 alerts held back (fork_burst
+    /// ×40, exec_rate ×3)`.
+    fn take_summary(&mut self) -> Option<Alert> {
+        if self.folded.is_empty() {
+            return None;
+        }
+        let folded = std::mem::take(&mut self.folded);
+        let total: u64 = folded.values().sum();
+        let mut by_rule: Vec<_> = folded.iter().collect();
+        by_rule.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let rules = by_rule
+            .iter()
+            .map(|(rule, count)| format!("{rule} ×{count}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut labels = BTreeMap::new();
+        labels.insert("folded".to_string(), total.to_string());
+        labels.insert(
+            "max_alerts_per_minute".to_string(),
+            self.max_per_minute.to_string(),
+        );
+        Some(Alert {
+            rule: BUDGET_RULE.to_string(),
+            severity: self.severity.take().unwrap_or(Severity::Info),
+            message: format!(
+                "alert budget of {}/min exceeded: {total} alerts held back ({rules})",
+                self.max_per_minute
+            ),
+            host: std::mem::take(&mut self.host),
+            resolved_after_secs: None,
+            labels,
+        })
+    }
+}
+
+/// Alerts batched per channel until the next digest.
+struct AlertDigest {
+    interval: Duration,
+    /// Alerts at or above this severity aren't batched.
+    immediate: Severity,
+    /// Channels that batch; empty is every channel.
+    channels: Vec<String>,
+    pending: BTreeMap<&'static str, Vec<Alert>>,
+    /// When the current batch is sent, once an alert is pending.
+    due: Option<Instant>,
+}
+
+impl AlertDigest {
+    fn new(config: &DigestConfig) -> Self {
+        Self {
+            interval: Duration::from_secs(config.interval_secs),
+            immediate: parse_severity(&config.immediate_severity),
+            channels: config.channels.clone(),
+            pending: BTreeMap::new(),
+            due: None,
+        }
+    }
+
+    /// Whether `alert` waits for the digest on `channel`.
+    fn batches(&self, channel: &str, alert: &Alert) -> bool {
+        alert.severity < self.immediate
+            && (self.channels.is_empty() || self.channels.iter().any(|c| c == channel))
+    }
+
+    fn push(&mut self, channel: &'static str, alert: Alert, now: Instant) {
+        self.pending.entry(channel).or_default().push(alert);
+        self.due.get_or_insert(now + self.interval);
+    }
+
+    /// One alert per channel for everything batched since the last digest:
+    /// the alert itself when there is only one.
+    fn take(&mut self) -> Vec<(&'static str, Alert)> {
+        self.due = None;
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .filter_map(|(channel, mut alerts)| {
+                let digest = match alerts.len() {
+                    0 => return None,
+                    1 => alerts.pop()?,
+                    _ => digest_alert(&alerts, self.interval),
+                };
+                Some((channel, digest))
+            })
+            .collect()
+    }
+}
+
+/// E.g. `3 alerts in the last 15m` followed by one line per alert, oldest
+/// first.
+fn digest_alert(alerts: &[Alert], interval: Duration) -> Alert {
+    let span = match interval.as_secs() {
+        secs if secs % 60 == 0 => format!("{}m", secs / 60),
+        secs => format!("{secs}s"),
+    };
+    let mut message = format!("{} alerts in the last {span}", alerts.len());
+    for alert in alerts.iter().take(DIGEST_MAX_LINES) {
+        let state = match alert.is_resolution() {
+            true => "RESOLVED".to_string(),
+            false => alert.severity.as_str().to_uppercase(),
+        };
+        message.push_str(&format!("\n[{state}] {}: {}", alert.rule, alert.message));
+    }
+    if alerts.len() > DIGEST_MAX_LINES {
+        message.push_str(&format!(
+            "\n... and {} more",
+            alerts.len() - DIGEST_MAX_LINES
+        ));
+    }
+    let mut labels = BTreeMap::new();
+    labels.insert("alerts".to_string(), alerts.len().to_string());
+    Alert {
+        rule: DIGEST_RULE.to_string(),
+        severity: alerts
+            .iter()
+            .map(|a| &a.severity)
+            .fold(&Severity::Info, |max, s| if s > max { s } else { max })
+            .clone(),
+        message,
+        host: alerts[0].host.clone(),
+        resolved_after_secs: None,
+        labels,
+    }
+}
+
+/// Leader and observe-mode checks a notification passes before fan-out.
+#[derive(Default)]
+struct SendGate {
+    leadership: Option<Arc<Leadership>>,
+    observe: Option<Arc<ObserveRecorder>>,
+}
+
+impl SendGate {
+    fn is_leader(&self) -> bool {
+        leader::is_leader(&self.leadership)
+    }
+
+    /// Whether the notification `summary` describes may go to `channel`.
+    fn admits(&self, channel: &str, summary: impl FnOnce() -> String) -> bool {
+        self.observe
+            .as_ref()
+            .is_none_or(|observe| observe.notification(channel, summary()))
+    }
+}
+
+pub struct AlertDispatcher {
+    rx: broadcast::Receiver<Alert>,
+    rules: Option<Arc<RuleEngine>>,
+    channels: Vec<(&'static str, broadcast::Sender<Alert>)>,
+    budget: Option<AlertBudget>,
+    router: Option<NotificationRouter>,
+    digest: Option<AlertDigest>,
+    gate: SendGate,
+}
+
+impl AlertDispatcher {
+    pub fn new(rx: broadcast::Receiver<Alert>) -> Self {
+        Self {
+            rx,
+            rules: None,
+            channels: Vec::new(),
+            budget: None,
+            router: None,
+            digest: None,
+            gate: SendGate::default(),
+        }
+    }
+
+    /// Only send while this instance is the elected leader.
+    pub fn with_leadership(mut self, leadership: Arc<Leadership>) -> Self {
+        self.gate.leadership = Some(leadership);
+        self
+    }
+
+    /// Record each notification; in observe mode, record it instead of sending.
+    pub fn with_observe(mut self, observe: Arc<ObserveRecorder>) -> Self {
+        self.gate.observe = Some(observe);
+        self
+    }
+
+    /// Batch alerts below `immediate_severity` into a digest every
+    /// `interval_secs`; 0 sends every alert right away.
+    pub fn with_digest(mut self, config: &DigestConfig) -> Self {
+        self.digest = (config.interval_secs > 0).then(|| AlertDigest::new(config));
+        self
+    }
+
+    /// Send at most `max_per_minute` alerts a minute, summarizing the
+    /// rest; 0 sends every alert.
+    pub fn with_budget(mut self, max_per_minute: u32) -> Self {
+        self.budget = (max_per_minute > 0).then(|| AlertBudget::new(max_per_minute));
+        self
+    }
+
+    /// Route by the `notify` lists of the engine's rules, as of each alert.
+    pub fn with_rules(mut self, engine: Arc<RuleEngine>) -> Self {
+        self.rules = Some(engine);
+        self
+    }
+
+    /// Apply channels' severity floors, quiet hours and per-rule overrides.
+    pub fn with_router(mut self, router: NotificationRouter) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// Receiver of the alerts routed to `channel`, one of [`CHANNELS`].
+    pub fn subscribe(&mut self, channel: &'static str) -> broadcast::Receiver<Alert> {
+        debug_assert!(CHANNELS.contains(&channel), "unknown channel {channel}");
+        if let Some((_, tx)) = self.channels.iter().find(|(name, _)| *name == channel) {
+            return tx.subscribe();
+        }
+        let (tx, rx) = broadcast::channel(CHANNEL_CAPACITY);
+        self.channels.push((channel, tx));
+        rx
+    }
+
+    /// Channels `alert` goes to.
+    fn route(&self, alert: &Alert) -> Vec<&'static str> {
+        let notify = self
+            .rules
+            .as_ref()
+            .and_then(|r| r.notify_channels(&alert.rule));
+        let now = chrono::Local::now().naive_local();
+        self.channels
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| notify.as_ref().is_none_or(|n| n.iter().any(|c| c == name)))
+            .filter(|name| {
+                self.router
+                    .as_ref()
+                    .is_none_or(|router| router.admits(name, alert, now))
+            })
+            .collect()
+    }
+
+    fn send(&self, alert: &Alert, route: &[&'static str]) {
+        if !self.gate.is_leader() {
+            debug!(
+                "[notifications] standby: not sending alert '{}'",
+                alert.rule
+            );
+            return;
+        }
+        for (name, tx) in &self.channels {
+            if route.contains(name)
+                && self.gate.admits(name, || {
+                    format!("[{}] {}", alert.severity.as_str(), alert.rule)
+                })
+            {
+                // No receiver only means the notifier stopped.
+                let _ = tx.send(alert.clone());
+            }
+        }
+    }
+
+    /// Send the pending digest of each channel.
+    fn send_digests(&mut self) {
+        let Some(digest) = self.digest.as_mut() else {
+            return;
+        };
+        for (channel, alert) in digest.take() {
+            self.send(&alert, &[channel]);
+        }
+    }
+
+    /// Send the summary of held back alerts to every channel the router
+    /// admits it to.
+    fn send_summary(&mut self) {
+        if let Some(summary) = self.budget.as_mut().and_then(AlertBudget::take_summary) {
+            let route = self.route(&summary);
+            self.send(&summary, &route);
+        }
+    }
+
+    pub async fn run(mut self) {
+        if self.channels.is_empty() {
+            return;
+        }
+        info!(
+            "[notifications] dispatching alerts to {}",
+            self.channels
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        loop {
+            let summary_due = self.budget.as_ref().and_then(AlertBudget::summary_due);
+            let digest_due = self.digest.as_ref().and_then(|d| d.due);
+            let wake = summary_due.into_iter().chain(digest_due).min();
+            let received = tokio::select! {
+                received = self.rx.recv() => received,
+                _ = tokio::time::sleep_until(wake.unwrap_or_else(Instant::now)),
+                    if wake.is_some() =>
+                {
+                    let now = Instant::now();
+                    if summary_due.is_some_and(|due| now >= due) {
+                        self.send_summary();
+                    }
+                    if digest_due.is_some_and(|due| now >= due) {
+                        self.send_digests();
+                    }
+                    continue;
+                }
+            };
+            match received {
+                Ok(alert) => {
+                    let mut route = self.route(&alert);
+                    if route.is_empty() {
+                        debug!("[notifications] alert '{}' routed nowhere", alert.rule);
+                        continue;
+                    }
+                    let now = Instant::now();
+                    if summary_due.is_some_and(|due| now >= due) {
+                        self.send_summary();
+                    }
+                    if let Some(digest) = self.digest.as_mut() {
+                        // Batched alerts count against the budget only
+                        // as part of their digest, which it doesn't cap.
+                        route.retain(|channel| {
+                            let batched = digest.batches(channel, &alert);
+                            if batched {
+                                digest.push(channel, alert.clone(), now);
+                            }
+                            !batched
+                        });
+                        if route.is_empty() {
+                            continue;
+                        }
+                    }
+                    if let Some(budget) = self.budget.as_mut()
+                        && !budget.admit(&alert, now)
+                    {
+                        continue;
+                    }
+                    self.send(&alert, &route);
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("[notifications] dispatcher lagged by {n} alerts");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        self.send_summary();
+        self.send_digests();
+    }
+}
+
+/// Fans the recorded insights out to the notifiers that post them.
+pub struct InsightDispatcher {
+    rx: broadcast::Receiver<InsightRecord>,
+    /// Channel, the confidence its insights need, and its sender.
+    channels: Vec<(&'static str, f32, broadcast::Sender<InsightRecord>)>,
+    gate: SendGate,
+}
+
+impl InsightDispatcher {
+    pub fn new(rx: broadcast::Receiver<InsightRecord>) -> Self {
+        Self {
+            rx,
+            channels: Vec::new(),
+            gate: SendGate::default(),
+        }
+    }
+
+    /// Only send while this instance is the elected leader.
+    pub fn with_leadership(mut self, leadership: Arc<Leadership>) -> Self {
+        self.gate.leadership = Some(leadership);
+        self
+    }
+
+    /// Record each insight sent, or in observe mode record it instead.
+    pub fn with_observe(mut self, observe: Arc<ObserveRecorder>) -> Self {
+        self.gate.observe = Some(observe);
+        self
+    }
+
+    /// Receiver of the insights with at least `min_confidence` for `channel`.
+    pub fn subscribe(
+        &mut self,
+        channel: &'static str,
+        min_confidence: f32,
+    ) -> broadcast::Receiver<InsightRecord> {
+        let (tx, rx) = broadcast::channel(CHANNEL_CAPACITY);
+        self.channels.push((channel, min_confidence, tx));
+        rx
+    }
+
+    fn send(&self, record: &InsightRecord) {
+        let insight = &record.insight;
+        if !self.gate.is_leader() {
+            debug!(
+                "[notifications] standby: not sending insight {}",
+                insight.id
+            );
+            return;
+        }
+        for (name, min_confidence, tx) in &self.channels {
+            if insight.confidence < *min_confidence {
+                debug!(
+                    "[notifications] skipping insight {} for {name} (confidence {:.2} < {:.2})",
+                    insight.id, insight.confidence, min_confidence
+                );
+                continue;
+            }
+            if self.gate.admits(name, || {
+                format!("insight {} ({})", insight.id, insight.reason_code.as_str())
+            }) {
+                // No receiver only means the notifier stopped.
+                let _ = tx.send(record.clone());
+            }
+        }
+    }
+
+    pub async fn run(mut self) {
+        if self.channels.is_empty() {
+            return;
+        }
+        loop {
+            match self.rx.recv().await {
+                Ok(record) => self.send(&record),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("[notifications] insight dispatcher lagged by {n} insights");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+    use crate::metrics::Metrics;
+
+    fn alert(rule: &str) -> Alert {
+        Alert {
+            rule: rule.to_string(),
+            severity: Severity::High,
+            message: "m".to_string(),
+            host: "h".to_string(),
+            resolved_after_secs: None,
+            labels: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn rules_pick_their_channels() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        std::fs::write(
+            &path,
+            "- name: paged\n  detector: oom_kill\n  notify: [apprise]\n\
+             - name: quiet\n  detector: oom_kill\n  notify: []\n\
+             - name: everywhere\n  detector: oom_kill\n",
+        )
+        .unwrap();
+        let engine = RuleEngine::from_path(
+            path.to_str().unwrap(),
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+        let (tx, rx) = broadcast::channel(16);
+        let mut dispatcher = AlertDispatcher::new(rx).with_rules(Arc::new(engine));
+        let mut slack = dispatcher.subscribe("slack");
+        let mut apprise = dispatcher.subscribe("apprise");
+        let task = tokio::spawn(dispatcher.run());
+        for rule in ["paged", "quiet", "everywhere", "plugin_rule"] {
+            tx.send(alert(rule)).unwrap();
+        }
+        drop(tx);
+        task.await.unwrap();
+
+        let drain = |rx: &mut broadcast::Receiver<Alert>| {
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|a| a.rule)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(drain(&mut slack), ["everywhere", "plugin_rule"]);
+        assert_eq!(drain(&mut apprise), ["paged", "everywhere", "plugin_rule"]);
+    }
+
+    #[tokio::test]
+    async fn observe_mode_records_instead_of_sending() {
+        let observe = Arc::new(ObserveRecorder::new(true));
+        let (tx, rx) = broadcast::channel(16);
+        let mut dispatcher = AlertDispatcher::new(rx).with_observe(Arc::clone(&observe));
+        let mut slack = dispatcher.subscribe("slack");
+        let mut email = dispatcher.subscribe("email");
+        let task = tokio::spawn(dispatcher.run());
+        tx.send(alert("fork_burst")).unwrap();
+        drop(tx);
+        task.await.unwrap();
+
+        assert!(slack.try_recv().is_err());
+        assert!(email.try_recv().is_err());
+        let report = observe.report();
+        assert_eq!(report.notifications["slack"], 1);
+        assert_eq!(report.notifications["email"], 1);
+        assert_eq!(report.recent[0].summary, "[high] fork_burst");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn digest_batches_below_high() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut dispatcher = AlertDispatcher::new(rx).with_digest(&DigestConfig {
+            interval_secs: 900,
+            channels: vec!["slack".to_string()],
+            ..DigestConfig::default()
+        });
+        let mut slack = dispatcher.subscribe("slack");
+        let mut email = dispatcher.subscribe("email");
+        let task = tokio::spawn(dispatcher.run());
+        for (rule, severity) in [
+            ("exec_rate", Severity::Medium),
+            ("fork_burst", Severity::High),
+            ("exec_rate", Severity::Low),
+        ] {
+            tx.send(Alert {
+                severity,
+                ..alert(rule)
+            })
+            .unwrap();
+        }
+        tokio::task::yield_now().await;
+        assert_eq!(slack.try_recv().unwrap().rule, "fork_burst");
+        assert!(slack.try_recv().is_err());
+        assert_eq!(std::iter::from_fn(|| email.try_recv().ok()).count(), 3);
+
+        tokio::time::sleep(Duration::from_secs(901)).await;
+        let digest = slack.try_recv().unwrap();
+        assert_eq!(digest.rule, DIGEST_RULE);
+        assert_eq!(digest.severity, Severity::Medium);
+        assert_eq!(
+            digest.message,
+            "2 alerts in the last 15m\n[MEDIUM] exec_rate: m\n[LOW] exec_rate: m"
+        );
+
+        // A lone batched alert goes out as itself when the channel closes.
+        tx.send(Alert {
+            severity: Severity::Low,
+            ..alert("oom")
+        })
+        .unwrap();
+        drop(tx);
+        task.await.unwrap();
+        assert_eq!(slack.try_recv().unwrap().rule, "oom");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn budget_folds_the_overflow_into_a_summary() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut dispatcher = AlertDispatcher::new(rx).with_budget(2);
+        let mut slack = dispatcher.subscribe("slack");
+        let task = tokio::spawn(dispatcher.run());
+        for rule in ["flappy", "flappy", "flappy", "other", "flappy"] {
+            tx.send(alert(rule)).unwrap();
+        }
+        tokio::task::yield_now().await;
+        let first: Vec<_> = std::iter::from_fn(|| slack.try_recv().ok()).collect();
+        assert_eq!(first.len(), 2);
+
+        // The summary follows once the minute is up; then the budget is
+        // fresh again.
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        let summary = slack.try_recv().unwrap();
+        assert_eq!(summary.rule, BUDGET_RULE);
+        assert_eq!(summary.severity, Severity::High);
+        assert_eq!(
+            summary.message,
+            "alert budget of 2/min exceeded: 3 alerts held back (flappy ×2, other ×1)"
+        );
+        assert_eq!(summary.labels["folded"], "3");
+        tx.send(alert("flappy")).unwrap();
+        drop(tx);
+        task.await.unwrap();
+        assert_eq!(slack.try_recv().unwrap().rule, "flappy");
+        assert!(slack.try_recv().is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::alerts::Alert;
+use crate::config::EmailConfig;
+use crate::inventory::HostFacts;
+use crate::redaction::{Destination, RedactionPolicy};
+use anyhow::{Context, Result, bail};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{debug, error, info};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+/// Email notification handler
+///
+/// Sends each alert as a plain-text email through an SMTP relay, or with a
+/// digest interval, the alerts of each interval as one email.
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    digest: Option<Duration>,
+    rx: broadcast::Receiver<Alert>,
+    redaction: Arc<RedactionPolicy>,
+    inventory: Option<Arc<HostFacts>>,
+}
+
+impl EmailNotifier {
+    /// Fails on an unknown `tls` mode, an invalid address or no recipients.
+    pub fn new(config: EmailConfig, rx: broadcast::Receiver<Alert>) -> Result<Self> {
+        let mut transport = match config.tls.as_str() {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+            other => bail!("unknown tls mode '{other}' (expected starttls, tls or none)"),
+        }
+        .timeout(Some(Duration::from_secs(config.timeout_secs)));
+        if let Some(port) = config.smtp_port {
+            transport = transport.port(port);
+        }
+        if let Some(username) = config.username {
+            transport = transport.credentials(Credentials::new(
+                username,
+                config.password.unwrap_or_default(),
+            ));
+        }
+        let from = config
+            .from
+            .parse()
+            .with_context(|| format!("invalid from address '{}'", config.from))?;
+        let to = config
+            .to
+            .iter()
+            .map(|addr| {
+                addr.parse()
+                    .with_context(|| format!("invalid to address '{addr}'"))
+            })
+            .collect::<Result<Vec<Mailbox>>>()?;
+        if to.is_empty() {
+            bail!("no recipients in to");
+        }
+
+        Ok(Self {
+            transport: transport.build(),
+            from,
+            to,
+            digest: (config.digest_secs > 0).then(|| Duration::from_secs(config.digest_secs)),
+            rx,
+            redaction: Arc::new(RedactionPolicy::default()),
+            inventory: None,
+        })
+    }
+
+    /// Apply an outbound redaction policy to every email.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Append the host's facts (instance type, kernel, ...) to each body.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Run the notifier loop until the alert channel is closed; a pending
+    /// digest is sent first.
+    pub async fn run(mut self) {
+        info!(
+            "Email notifier started with {} recipient(s), digest: {}",
+            self.to.len(),
+            self.digest
+                .map_or("off".to_string(), |d| format!("every {}s", d.as_secs()))
+        );
+
+        let mut pending: Vec<Alert> = Vec::new();
+        let mut digest_due: Option<Instant> = None;
+        loop {
+            let received = tokio::select! {
+                received = self.rx.recv() => received,
+                _ = tokio::time::sleep_until(digest_due.unwrap_or_else(Instant::now)),
+                    if digest_due.is_some() =>
+                {
+                    digest_due = None;
+                    self.send(&std::mem::take(&mut pending)).await;
+                    continue;
+                }
+            };
+            match received {
+                Ok(alert) => match self.digest {
+                    Some(interval) => {
+                        pending.push(alert);
+                        digest_due.get_or_insert_with(|| Instant::now() + interval);
+                    }
+                    None => self.send(&[alert]).await,
+                },
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("Email notifier lagged by {} alerts", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    info!("Alert channel closed, stopping email notifier");
+                    break;
+                }
+            }
+        }
+        if !pending.is_empty() {
+            self.send(&pending).await;
+        }
+    }
+
+    async fn send(&self, alerts: &[Alert]) {
+        if alerts.is_empty() {
+            return;
+        }
+        if let Err(e) = self.try_send(alerts).await {
+            error!("Failed to send email notification: {:#}", e);
+        }
+    }
+
+    async fn try_send(&self, alerts: &[Alert]) -> Result<()> {
+        let alerts: Vec<Alert> = alerts
+            .iter()
+            .map(|alert| {
+                self.redaction
+                    .redact_alert(Destination::Notifications, alert)
+            })
+            .collect();
+        let (subject, body) = render(&alerts, self.inventory.as_deref());
+        let mut message = Message::builder().from(self.from.clone());
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message
+            .subject(&subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .context("failed to build email")?;
+        debug!("Sending email: '{}'", subject);
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Subject and body of an email for `alerts`: the alert itself when there
+/// is one, otherwise a digest listing each in order.
+fn render(alerts: &[Alert], facts: Option<&HostFacts>) -> (String, String) {
+    let mut body = String::new();
+    let subject = match alerts {
+        [alert] => {
+            body.push_str(&format!("Host: {}\n\n{}", alert.host, alert.message));
+            if !alert.labels.is_empty() {
+                body.push_str(&format!("\n\n{}", alert.labels_line()));
+            }
+            format!("{} on {}", title(alert), alert.host)
+        }
+        _ => {
+            for alert in alerts {
+                if !body.is_empty() {
+                    body.push_str("\n\n");
+                }
+                body.push_str(&format!(
+                    "{} on {}\n{}",
+                    title(alert),
+                    alert.host,
+                    alert.message
+                ));
+                if !alert.labels.is_empty() {
+                    body.push_str(&format!("\n{}", alert.labels_line()));
+                }
+            }
+            let mut hosts: Vec<&str> = alerts.iter().map(|a| a.host.as_str()).collect();
+            hosts.sort_unstable();
+            hosts.dedup();
+            format!("[linnix] {} alerts on {}", alerts.len(), hosts.join(", "))
+        }
+    };
+    if let Some(facts) = facts {
+        body.push_str(&format!("\n\n{facts}"));
+    }
+    (subject, body)
+}
+
+fn title(alert: &Alert) -> String {
+    match alert.is_resolution() {
+        true => format!("[RESOLVED] {}", alert.rule),
+        false => format!(
+            "[{}] {}",
+            alert.severity.as_str().to_uppercase(),
+            alert.rule
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+
+    fn config() -> EmailConfig {
+        toml::from_str(
+            "smtp_host = \"smtp.example.com\"\nfrom = \"linnix <linnix@example.com>\"\nto = [\"oncall@example.com\"]\n",
+        )
+        .unwrap()
+    }
+
+    fn alert(rule: &str, resolved: bool) -> Alert {
+        Alert {
+            rule: rule.to_string(),
+            severity: Severity::High,
+            message: format!("{rule} fired"),
+            host: "node-1".to_string(),
+            resolved_after_secs: resolved.then_some(60),
+            labels: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_settings() {
+        let (_tx, rx) = broadcast::channel(1);
+        assert!(EmailNotifier::new(config(), rx.resubscribe()).is_ok());
+        for (edit, error) in [
+            (
+                (|c: &mut EmailConfig| c.tls = "ssl".into()) as fn(&mut EmailConfig),
+                "unknown tls mode 'ssl'",
+            ),
+            (
+                |c| c.to = vec!["not an address".into()],
+                "invalid to address",
+            ),
+            (|c| c.to.clear(), "no recipients"),
+        ] {
+            let mut cfg = config();
+            edit(&mut cfg);
+            let err = EmailNotifier::new(cfg, rx.resubscribe()).err().unwrap();
+            assert!(format!("{err:#}").contains(error), "{err:#}");
+        }
+    }
+
+    #[test]
+    fn renders_single_alerts_and_digests() {
+        let (subject, body) = render(&[alert("fork_burst", false)], None);
+        assert_eq!(subject, "[HIGH] fork_burst on node-1");
+        assert_eq!(body, "Host: node-1\n\nfork_burst fired");
+
+        let (subject, body) = render(&[alert("fork_burst", false), alert("oom", true)], None);
+        assert_eq!(subject, "[linnix] 2 alerts on node-1");
+        assert_eq!(
+            body,
+            "[HIGH] fork_burst on node-1\nfork_burst fired\n\n[RESOLVED] oom on node-1\noom fired"
+        );
+    }
+}
@@ -1,7 +1,27 @@
 //! Notification handlers for external alerting systems
 
 mod apprise;
+mod dispatch;
+mod email;
+mod nats;
+mod opsgenie;
+mod retry;
+mod router;
 mod slack;
+mod syslog;
+mod teams;
+mod telegram;
+mod webhook;
 
 pub use apprise::AppriseNotifier;
+pub use dispatch::{AlertDispatcher, CHANNELS, InsightDispatcher};
+pub use email::EmailNotifier;
+pub use nats::NatsNotifier;
+pub(crate) use nats::subscribe_alerts;
+pub use opsgenie::OpsgenieNotifier;
+pub use router::NotificationRouter;
 pub use slack::SlackNotifier;
+pub use syslog::SyslogNotifier;
+pub use teams::TeamsNotifier;
+pub use telegram::TelegramNotifier;
+pub use webhook::InsightWebhookNotifier;
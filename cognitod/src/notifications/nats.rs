@@ -0,0 +1,190 @@
+use crate::alerts::Alert;
+use crate::config::NatsConfig;
+use crate::redaction::{Destination, RedactionPolicy};
+use anyhow::{Context, Result, bail};
+use async_nats::jetstream;
+use log::{debug, error, info};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// NATS notification handler
+///
+/// Publishes each alert as JSON to a subject per severity
+/// (`linnix.alerts.high`, ...), through core NATS or JetStream, so edge
+/// fleets can aggregate alerts with a subscription instead of polling.
+pub struct NatsNotifier {
+    config: NatsConfig,
+    subject_prefix: String,
+    rx: broadcast::Receiver<Alert>,
+    redaction: Arc<RedactionPolicy>,
+}
+
+impl NatsNotifier {
+    /// Fails on a subject prefix that is empty or has wildcards or spaces.
+    pub fn new(config: NatsConfig, rx: broadcast::Receiver<Alert>) -> Result<Self> {
+        let subject_prefix = config.subject_prefix.trim_end_matches('.').to_string();
+        if subject_prefix.is_empty()
+            || subject_prefix
+                .split('.')
+                .any(|token| token.is_empty() || token.contains(['*', '>', ' ']))
+        {
+            bail!("invalid subject_prefix '{}'", config.subject_prefix);
+        }
+        Ok(Self {
+            config,
+            subject_prefix,
+            rx,
+            redaction: Arc::new(RedactionPolicy::default()),
+        })
+    }
+
+    /// Apply an outbound redaction policy to every alert.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    pub async fn run(mut self) {
+        let client = match connect(&self.config).await {
+            Ok(client) => client,
+            Err(e) => {
+                error!("NATS notifier disabled: {:#}", e);
+                return;
+            }
+        };
+        let jetstream = self
+            .config
+            .jetstream
+            .then(|| jetstream::new(client.clone()));
+        info!(
+            "NATS notifier started, publishing to {}.<severity>{}",
+            self.subject_prefix,
+            if self.config.jetstream {
+                " via JetStream"
+            } else {
+                ""
+            }
+        );
+
+        loop {
+            match self.rx.recv().await {
+                Ok(alert) => {
+                    let alert = self
+                        .redaction
+                        .redact_alert(Destination::Notifications, &alert);
+                    if let Err(e) = self.publish(&client, jetstream.as_ref(), &alert).await {
+                        error!("Failed to publish NATS alert: {:#}", e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("NATS notifier lagged by {} alerts", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    info!("Alert channel closed, stopping NATS notifier");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn publish(
+        &self,
+        client: &async_nats::Client,
+        jetstream: Option<&jetstream::Context>,
+        alert: &Alert,
+    ) -> Result<()> {
+        let subject = subject(&self.subject_prefix, alert);
+        let payload = serde_json::to_vec(alert)?;
+        match jetstream {
+            Some(jetstream) => {
+                let ack = jetstream
+                    .publish(subject.clone(), payload.into())
+                    .await
+                    .context("Failed to publish to JetStream")?;
+                if self.config.await_ack {
+                    ack.await
+                        .with_context(|| format!("No JetStream ack for {subject}"))?;
+                }
+            }
+            None => client
+                .publish(subject.clone(), payload.into())
+                .await
+                .context("Failed to publish to NATS")?,
+        }
+        debug!("Successfully published alert to {subject}");
+        Ok(())
+    }
+}
+
+/// Connect in the background: the client keeps retrying an unreachable
+/// server, buffering what is published meanwhile.
+async fn connect(config: &NatsConfig) -> Result<async_nats::Client> {
+    let mut options = async_nats::ConnectOptions::new()
+        .name("linnix")
+        .retry_on_initial_connect();
+    if let Some(path) = &config.credentials_file {
+        options = options
+            .credentials_file(path)
+            .await
+            .with_context(|| format!("reading credentials_file {path}"))?;
+    }
+    if let Some(token) = &config.token {
+        options = options.token(token.clone());
+    }
+    options
+        .connect(config.url.as_str())
+        .await
+        .with_context(|| format!("connecting to {}", config.url))
+}
+
+/// Subscribe to the alerts every agent publishes under the subject prefix,
+/// as an aggregator does.
+pub(crate) async fn subscribe_alerts(config: &NatsConfig) -> Result<async_nats::Subscriber> {
+    let subject = format!("{}.>", config.subject_prefix.trim_end_matches('.'));
+    connect(config)
+        .await?
+        .subscribe(subject.clone())
+        .await
+        .with_context(|| format!("subscribing to {subject}"))
+}
+
+/// Subject of `alert`: the prefix followed by its severity.
+fn subject(prefix: &str, alert: &Alert) -> String {
+    format!("{prefix}.{}", alert.severity.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+
+    fn config(prefix: &str) -> NatsConfig {
+        toml::from_str(&format!("subject_prefix = \"{prefix}\"\n")).unwrap()
+    }
+
+    #[test]
+    fn publishes_per_severity_subject() {
+        let (_tx, rx) = broadcast::channel(1);
+        let notifier = NatsNotifier::new(config("edge.alerts."), rx.resubscribe()).unwrap();
+        assert!(notifier.config.await_ack);
+        let alert = Alert {
+            rule: "fork_burst".to_string(),
+            severity: Severity::Medium,
+            message: "forks spiking".to_string(),
+            host: "node-1".to_string(),
+            resolved_after_secs: None,
+            labels: Default::default(),
+        };
+        assert_eq!(
+            subject(&notifier.subject_prefix, &alert),
+            "edge.alerts.medium"
+        );
+
+        for prefix in ["", "edge.*", "edge..alerts", "edge alerts"] {
+            let err = NatsNotifier::new(config(prefix), rx.resubscribe())
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("invalid subject_prefix"), "{err}");
+        }
+    }
+}
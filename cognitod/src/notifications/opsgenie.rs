@@ -0,0 +1,237 @@
+use crate::alerts::Alert;
+use crate::config::{OpsgenieConfig, OpsgeniePriorities};
+use crate::inventory::HostFacts;
+use crate::network::Network;
+use crate::redaction::{Destination, RedactionPolicy};
+use anyhow::{Context, Result, bail};
+use log::{debug, error, info};
+use reqwest::Client;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Longest `message` Opsgenie accepts.
+const MAX_MESSAGE_CHARS: usize = 130;
+
+/// Opsgenie notification handler
+///
+/// Creates an Opsgenie alert for each alert through the Alert API, with the
+/// severity mapped to a priority and the labels as details, and closes it
+/// when the alert resolves.
+pub struct OpsgenieNotifier {
+    api_url: String,
+    api_key: String,
+    /// Priority by severity, indexed by `Severity as usize`.
+    priorities: [String; 4],
+    close_on_resolve: bool,
+    rx: broadcast::Receiver<Alert>,
+    client: Client,
+    redaction: Arc<RedactionPolicy>,
+    inventory: Option<Arc<HostFacts>>,
+}
+
+impl OpsgenieNotifier {
+    /// Fails on a priority other than `P1` to `P5`.
+    pub fn new(config: OpsgenieConfig, rx: broadcast::Receiver<Alert>) -> Result<Self> {
+        let OpsgeniePriorities {
+            info,
+            low,
+            medium,
+            high,
+        } = config.priorities;
+        let priorities = [info, low, medium, high];
+        for priority in &priorities {
+            if !matches!(priority.as_str(), "P1" | "P2" | "P3" | "P4" | "P5") {
+                bail!("invalid priority '{priority}' (expected P1 to P5)");
+            }
+        }
+        Ok(Self {
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key,
+            priorities,
+            close_on_resolve: config.close_on_resolve,
+            rx,
+            client: Client::new(),
+            redaction: Arc::new(RedactionPolicy::default()),
+            inventory: None,
+        })
+    }
+
+    /// Apply an outbound redaction policy to every alert.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Append the host's facts (instance type, kernel, ...) to each description.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Send through the configured proxy and CA bundle.
+    pub fn with_network(mut self, network: &Network) -> Self {
+        self.client = network.client();
+        self
+    }
+
+    pub async fn run(mut self) {
+        info!(
+            "Opsgenie notifier started, close on resolve: {}",
+            self.close_on_resolve
+        );
+
+        loop {
+            match self.rx.recv().await {
+                Ok(alert) => {
+                    if alert.is_resolution() && !self.close_on_resolve {
+                        debug!("Not closing Opsgenie alert '{}'", alert.rule);
+                        continue;
+                    }
+                    let alert = self
+                        .redaction
+                        .redact_alert(Destination::Notifications, &alert);
+                    let sent = match alert.is_resolution() {
+                        true => self.close(&alert).await,
+                        false => self.create(&alert).await,
+                    };
+                    if let Err(e) = sent {
+                        error!("Failed to send Opsgenie alert: {}", e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("Opsgenie notifier lagged by {} alerts", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    info!("Alert channel closed, stopping Opsgenie notifier");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn create(&self, alert: &Alert) -> Result<()> {
+        let body = self.payload(alert);
+        self.post(&format!("{}/v2/alerts", self.api_url), &body)
+            .await
+    }
+
+    async fn close(&self, alert: &Alert) -> Result<()> {
+        let url = format!(
+            "{}/v2/alerts/{}/close?identifierType=alias",
+            self.api_url,
+            alias(alert)
+        );
+        self.post(&url, &json!({ "source": "linnix", "note": alert.message }))
+            .await
+    }
+
+    async fn post(&self, url: &str, body: &Value) -> Result<()> {
+        let res = self
+            .client
+            .post(url)
+            .header("Authorization", format!("GenieKey {}", self.api_key))
+            .json(body)
+            .send()
+            .await
+            .context("Failed to send request to Opsgenie")?;
+
+        if !res.status().is_success() {
+            let text = res.text().await.unwrap_or_default();
+            bail!("Opsgenie API error: {}", text);
+        }
+
+        debug!("Successfully sent alert to Opsgenie");
+        Ok(())
+    }
+
+    /// Create-alert request for `alert`.
+    fn payload(&self, alert: &Alert) -> Value {
+        let mut message = format!("{}: {}", alert.rule, alert.message);
+        if let Some((end, _)) = message.char_indices().nth(MAX_MESSAGE_CHARS) {
+            message.truncate(end);
+        }
+        let mut description = format!("Host: {}\n\n{}", alert.host, alert.message);
+        if let Some(facts) = &self.inventory {
+            description.push_str(&format!("\n\n{facts}"));
+        }
+        json!({
+            "message": message,
+            "alias": alias(alert),
+            "description": description,
+            "priority": self.priorities[alert.severity.clone() as usize],
+            "details": alert.labels,
+            "tags": ["linnix", alert.severity.as_str()],
+            "entity": alert.host,
+            "source": "linnix",
+        })
+    }
+}
+
+/// Opsgenie alias of an alert and its resolution: the same for repeats of
+/// the rule on one host and scope, so they deduplicate into one alert.
+fn alias(alert: &Alert) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        alert.host.as_str(),
+        alert.rule.as_str(),
+        alert.labels.get("scope").map_or("", String::as_str),
+        alert.labels.get("fingerprint").map_or("", String::as_str),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("linnix-{}", &hex::encode(hasher.finalize())[..32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+
+    fn config() -> OpsgenieConfig {
+        toml::from_str("api_key = \"key\"\n").unwrap()
+    }
+
+    fn alert(severity: Severity, resolved: bool) -> Alert {
+        Alert {
+            rule: "fork_burst".to_string(),
+            severity,
+            message: "x".repeat(200),
+            host: "node-1".to_string(),
+            resolved_after_secs: resolved.then_some(60),
+            labels: [
+                ("pid".to_string(), "42".to_string()),
+                ("scope".to_string(), "payments/api-1".to_string()),
+            ]
+            .into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn maps_severity_and_labels() {
+        let (_tx, rx) = broadcast::channel(1);
+        let notifier = OpsgenieNotifier::new(config(), rx.resubscribe()).unwrap();
+        let body = notifier.payload(&alert(Severity::High, false));
+        assert_eq!(body["priority"], "P1");
+        assert_eq!(body["details"]["pid"], "42");
+        assert_eq!(body["message"].as_str().unwrap().chars().count(), 130);
+        assert_eq!(
+            notifier.payload(&alert(Severity::Info, false))["priority"],
+            "P5"
+        );
+
+        // A resolution closes the alert it resolves, and only that one.
+        let fired = alert(Severity::High, false);
+        assert_eq!(alias(&fired), alias(&alert(Severity::High, true)));
+        let mut other = fired.clone();
+        other.labels.insert("scope".into(), "payments/api-2".into());
+        assert_ne!(alias(&fired), alias(&other));
+
+        let mut cfg = config();
+        cfg.priorities.high = "P0".into();
+        let err = OpsgenieNotifier::new(cfg, rx).err().unwrap();
+        assert!(err.to_string().contains("invalid priority 'P0'"), "{err}");
+    }
+}
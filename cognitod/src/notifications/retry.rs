@@ -0,0 +1,267 @@
+//! Queue of notifier requests that failed, retried with exponential backoff.
+//!
+//! Requests are retried in order, oldest first: while the oldest keeps
+//! failing the rest wait behind it, and new requests join the back of the
+//! queue rather than overtaking it. With a `path` the queue is saved after
+//! every change and reloaded at startup, so a restart during an outage
+//! doesn't lose it.
+
+use crate::config::RetryConfig;
+use anyhow::Context;
+use log::{info, warn};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+/// Error status of a notifier's HTTP request, to tell retryable failures
+/// (429 and 5xx) from requests that would fail again.
+#[derive(Debug)]
+pub(super) struct HttpStatus(pub StatusCode);
+
+impl std::fmt::Display for HttpStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP {}", self.0)
+    }
+}
+
+impl std::error::Error for HttpStatus {}
+
+/// Whether a failed request is worth retrying: network errors and timeouts,
+/// rate limiting and server errors are; other error statuses aren't.
+pub(super) fn retryable(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<HttpStatus>() {
+        Some(HttpStatus(status)) => {
+            status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+        }
+        None => true,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Queued {
+    payload: Value,
+    /// Unix seconds; entries older than `max_age_secs` are dropped.
+    queued_at: u64,
+}
+
+pub(super) struct RetryQueue {
+    queued: VecDeque<Queued>,
+    config: RetryConfig,
+    /// Wait after the next failure.
+    backoff: Duration,
+    /// When to retry the oldest request; `None` while the queue is empty.
+    next_attempt: Option<Instant>,
+}
+
+impl RetryQueue {
+    /// An empty queue, or the one saved at `config.path`.
+    pub fn new(config: RetryConfig) -> Self {
+        let mut queue = Self {
+            queued: VecDeque::new(),
+            backoff: Duration::from_secs(config.initial_backoff_secs),
+            config,
+            next_attempt: None,
+        };
+        if let Some(path) = &queue.config.path {
+            match load(path) {
+                Ok(queued) if !queued.is_empty() => {
+                    info!(
+                        "Loaded {} queued notification(s) from {}",
+                        queued.len(),
+                        path.display()
+                    );
+                    queue.queued = queued;
+                    queue.expire();
+                    queue.next_attempt = Some(Instant::now());
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Ignoring notification queue: {e:#}"),
+            }
+        }
+        queue
+    }
+
+    /// Whether failed requests are queued at all.
+    pub fn enabled(&self) -> bool {
+        self.config.max_queued > 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// When the oldest request is due to be retried.
+    pub fn due(&self) -> Option<Instant> {
+        self.next_attempt
+    }
+
+    /// Queue `payload` behind the others, dropping the oldest when full.
+    pub fn push(&mut self, payload: Value) {
+        if !self.enabled() {
+            return;
+        }
+        if self.queued.len() >= self.config.max_queued {
+            self.queued.pop_front();
+            warn!(
+                "Notification retry queue full ({}), dropped the oldest",
+                self.config.max_queued
+            );
+        }
+        self.queued.push_back(Queued {
+            payload,
+            queued_at: unix_now(),
+        });
+        if self.next_attempt.is_none() {
+            // The request failed once already.
+            self.failed();
+        }
+        self.save();
+    }
+
+    /// The oldest request still within `max_age_secs`.
+    pub fn front(&mut self) -> Option<&Value> {
+        if self.expire() {
+            self.save();
+        }
+        self.queued.front().map(|queued| &queued.payload)
+    }
+
+    /// The oldest request went through, or failed for good: remove it and
+    /// send the next one right away.
+    pub fn pop(&mut self) {
+        self.queued.pop_front();
+        self.backoff = Duration::from_secs(self.config.initial_backoff_secs);
+        self.next_attempt = (!self.queued.is_empty()).then(Instant::now);
+        self.save();
+    }
+
+    /// The oldest request failed again: wait, twice as long each time up to
+    /// `max_backoff_secs`, before the next try.
+    pub fn failed(&mut self) {
+        self.next_attempt = Some(Instant::now() + self.backoff);
+        self.backoff = (self.backoff * 2).min(Duration::from_secs(self.config.max_backoff_secs));
+    }
+
+    /// Drop requests older than `max_age_secs`; true if any were.
+    fn expire(&mut self) -> bool {
+        let oldest = unix_now().saturating_sub(self.config.max_age_secs);
+        let before = self.queued.len();
+        self.queued.retain(|queued| queued.queued_at >= oldest);
+        let expired = before - self.queued.len();
+        if expired > 0 {
+            warn!(
+                "Dropped {expired} queued notification(s) older than {}s",
+                self.config.max_age_secs
+            );
+        }
+        if self.queued.is_empty() {
+            self.next_attempt = None;
+        }
+        expired > 0
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.config.path
+            && let Err(e) = save(path, &self.queued)
+        {
+            warn!("Failed to save notification queue: {e:#}");
+        }
+    }
+}
+
+fn load(path: &Path) -> anyhow::Result<VecDeque<Queued>> {
+    match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .with_context(|| format!("invalid queue file {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(VecDeque::new()),
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+/// Write via a temporary file so a crash can't truncate the queue.
+fn save(path: &Path, queued: &VecDeque<Queued>) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec(queued)?)
+        .with_context(|| format!("writing {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("renaming to {}", path.display()))?;
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn config(path: Option<PathBuf>) -> RetryConfig {
+        RetryConfig {
+            max_queued: 2,
+            path,
+            ..RetryConfig::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_in_order_with_backoff() {
+        let mut queue = RetryQueue::new(config(None));
+        assert_eq!(queue.due(), None);
+        let start = Instant::now();
+        for n in 1..=3 {
+            queue.push(json!(n));
+        }
+        // Full: the first one made way for the third.
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.front(), Some(&json!(2)));
+        assert_eq!(queue.due(), Some(start + Duration::from_secs(5)));
+
+        queue.failed();
+        assert_eq!(queue.due(), Some(start + Duration::from_secs(10)));
+        queue.failed();
+        assert_eq!(queue.due(), Some(start + Duration::from_secs(20)));
+        queue.pop();
+        assert_eq!(queue.front(), Some(&json!(3)));
+        assert_eq!(queue.due(), Some(start));
+        queue.pop();
+        assert_eq!(queue.due(), None);
+
+        assert!(!retryable(&anyhow::Error::new(HttpStatus(
+            StatusCode::BAD_REQUEST
+        ))));
+        assert!(retryable(
+            &anyhow::Error::new(HttpStatus(StatusCode::TOO_MANY_REQUESTS)).context("Slack")
+        ));
+        assert!(retryable(&anyhow::anyhow!("connection refused")));
+    }
+
+    #[tokio::test]
+    async fn survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("queue.json");
+        let mut queue = RetryQueue::new(config(Some(path.clone())));
+        queue.push(json!({ "text": "one" }));
+        queue.push(json!({ "text": "two" }));
+        queue.pop();
+
+        let mut queue = RetryQueue::new(config(Some(path)));
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.front(), Some(&json!({ "text": "two" })));
+        assert!(queue.due().is_some());
+    }
+}
@@ -0,0 +1,269 @@
+//! Which channels an alert may reach, by severity and time of day.
+//!
+//! Each channel has a `min_severity` (from its own config section);
+//! `[[notifications.routing.quiet_hours]]` windows raise that floor on some
+//! or all channels at night or on weekends; `[notifications.routing.rules]`
+//! overrides either for a single rule. Rules' `notify` lists pick the
+//! channels first; the router only narrows them.
+
+use super::dispatch::CHANNELS;
+use crate::alerts::{Alert, Severity};
+use crate::config::{NotificationConfig, QuietHoursConfig};
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
+use std::collections::HashMap;
+
+/// Severity named by `s`; anything unknown is `info`.
+pub(super) fn parse_severity(s: &str) -> Severity {
+    match s.to_lowercase().as_str() {
+        "high" => Severity::High,
+        "medium" => Severity::Medium,
+        "low" => Severity::Low,
+        _ => Severity::Info,
+    }
+}
+
+/// Like [`parse_severity`], but rejects unknown names.
+fn strict_severity(s: &str) -> Result<Severity, String> {
+    match s.to_lowercase().as_str() {
+        "info" | "high" | "medium" | "low" => Ok(parse_severity(s)),
+        _ => Err(format!("unknown severity '{s}'")),
+    }
+}
+
+/// A loaded quiet-hours window.
+#[derive(Debug)]
+struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+    /// Days the window starts on; empty is every day.
+    days: Vec<Weekday>,
+    /// Empty is every channel.
+    channels: Vec<String>,
+    min_severity: Severity,
+}
+
+impl QuietHours {
+    fn from_config(config: &QuietHoursConfig) -> Result<Self, String> {
+        let time = |s: &str| {
+            NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| format!("invalid time '{s}'"))
+        };
+        let days = config
+            .days
+            .iter()
+            .map(|day| day.parse().map_err(|_| format!("invalid day '{day}'")))
+            .collect::<Result<_, _>>()?;
+        if let Some(channel) = config
+            .channels
+            .iter()
+            .find(|c| !CHANNELS.contains(&c.as_str()))
+        {
+            return Err(format!("unknown channel '{channel}'"));
+        }
+        Ok(Self {
+            start: time(&config.start)?,
+            end: time(&config.end)?,
+            days,
+            channels: config.channels.clone(),
+            min_severity: strict_severity(&config.min_severity)?,
+        })
+    }
+
+    /// Whether the window is open at `now` (host local time). A window
+    /// whose end isn't after its start runs past midnight (a whole day when
+    /// they are equal), and belongs to the day it started on.
+    fn is_open(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let started = match self.start < self.end {
+            true if (self.start..self.end).contains(&time) => now.date(),
+            false if time >= self.start => now.date(),
+            false if time < self.end => now.date() - Duration::days(1),
+            _ => return false,
+        };
+        self.days.is_empty() || self.days.contains(&started.weekday())
+    }
+
+    fn covers(&self, channel: &str) -> bool {
+        self.channels.is_empty() || self.channels.iter().any(|c| c == channel)
+    }
+}
+
+/// `[notifications.routing.rules]` entry of one rule.
+#[derive(Debug, Default)]
+struct RuleRoute {
+    min_severity: Option<Severity>,
+    quiet_hours: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct NotificationRouter {
+    /// Floor of each configured channel; channels without one take every
+    /// severity.
+    min_severity: HashMap<&'static str, Severity>,
+    quiet_hours: Vec<QuietHours>,
+    rules: HashMap<String, RuleRoute>,
+}
+
+impl NotificationRouter {
+    /// Load the routing of `config`; invalid entries are logged and skipped.
+    pub fn from_config(config: &NotificationConfig) -> Self {
+        let floors = [
+            ("slack", config.slack.as_ref().map(|c| &c.min_severity)),
+            ("apprise", config.apprise.as_ref().map(|c| &c.min_severity)),
+            ("email", config.email.as_ref().map(|c| &c.min_severity)),
+            ("teams", config.teams.as_ref().map(|c| &c.min_severity)),
+            (
+                "telegram",
+                config.telegram.as_ref().map(|c| &c.min_severity),
+            ),
+            (
+                "opsgenie",
+                config.opsgenie.as_ref().map(|c| &c.min_severity),
+            ),
+            ("syslog", config.syslog.as_ref().map(|c| &c.min_severity)),
+            ("nats", config.nats.as_ref().map(|c| &c.min_severity)),
+        ];
+        let min_severity = floors
+            .into_iter()
+            .filter_map(|(channel, floor)| Some((channel, parse_severity(floor?.as_deref()?))))
+            .collect();
+
+        let mut quiet_hours = Vec::new();
+        for (index, window) in config.routing.quiet_hours.iter().enumerate() {
+            match QuietHours::from_config(window) {
+                Ok(window) => quiet_hours.push(window),
+                Err(e) => log::warn!("[notifications] ignoring routing.quiet_hours[{index}]: {e}"),
+            }
+        }
+
+        let mut rules = HashMap::new();
+        for (rule, route) in &config.routing.rules {
+            let min_severity = match route.min_severity.as_deref().map(strict_severity) {
+                Some(Err(e)) => {
+                    log::warn!("[notifications] ignoring routing.rules.{rule}: {e}");
+                    continue;
+                }
+                floor => floor.and_then(Result::ok),
+            };
+            rules.insert(
+                rule.clone(),
+                RuleRoute {
+                    min_severity,
+                    quiet_hours: route.quiet_hours,
+                },
+            );
+        }
+
+        Self {
+            min_severity,
+            quiet_hours,
+            rules,
+        }
+    }
+
+    /// Whether `alert` may go to `channel` at `now` (host local time).
+    pub fn admits(&self, channel: &str, alert: &Alert, now: NaiveDateTime) -> bool {
+        let rule = self.rules.get(&alert.rule);
+        let floor = match rule.and_then(|r| r.min_severity.as_ref()) {
+            Some(floor) => Some(floor),
+            None => self.min_severity.get(channel),
+        };
+        if floor.is_some_and(|floor| alert.severity < *floor) {
+            return false;
+        }
+        if rule.is_some_and(|r| !r.quiet_hours) {
+            return true;
+        }
+        !self.quiet_hours.iter().any(|window| {
+            window.covers(channel) && window.is_open(now) && alert.severity < window.min_severity
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(rule: &str, severity: Severity) -> Alert {
+        Alert {
+            rule: rule.to_string(),
+            severity,
+            message: "m".to_string(),
+            host: "h".to_string(),
+            resolved_after_secs: None,
+            labels: Default::default(),
+        }
+    }
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse_severity() {
+        assert!(matches!(parse_severity("high"), Severity::High));
+        assert!(matches!(parse_severity("HIGH"), Severity::High));
+        assert!(matches!(parse_severity("medium"), Severity::Medium));
+        assert!(matches!(parse_severity("low"), Severity::Low));
+        assert!(matches!(parse_severity("info"), Severity::Info));
+        assert!(matches!(parse_severity("invalid"), Severity::Info));
+    }
+
+    #[test]
+    fn floors_quiet_hours_and_rule_overrides() {
+        let config: NotificationConfig = toml::from_str(
+            r#"
+            apprise = { urls = [], min_severity = "medium" }
+            [[routing.quiet_hours]]
+            start = "22:00"
+            end = "07:00"
+            days = ["fri"]
+            channels = ["slack"]
+            [[routing.quiet_hours]]
+            start = "25:00"
+            end = "07:00"
+            [routing.rules.oom_kill]
+            quiet_hours = false
+            [routing.rules.fork_burst]
+            min_severity = "info"
+            "#,
+        )
+        .unwrap();
+        let router = NotificationRouter::from_config(&config);
+        assert_eq!(router.quiet_hours.len(), 1);
+        let noon = at("2026-10-16 12:00");
+
+        // Per-channel floors, which a rule can lower.
+        assert!(!router.admits("apprise", &alert("exec_rate", Severity::Low), noon));
+        assert!(router.admits("apprise", &alert("exec_rate", Severity::Medium), noon));
+        assert!(router.admits("apprise", &alert("fork_burst", Severity::Low), noon));
+        assert!(router.admits("slack", &alert("exec_rate", Severity::Low), noon));
+
+        // Friday night into Saturday morning, on Slack only, below high.
+        for (now, open) in [
+            ("2026-10-16 23:00", true),
+            ("2026-10-17 06:59", true),
+            ("2026-10-17 07:00", false),
+            ("2026-10-17 23:00", false),
+        ] {
+            let medium = alert("exec_rate", Severity::Medium);
+            assert_eq!(!router.admits("slack", &medium, at(now)), open, "{now}");
+            assert!(router.admits("apprise", &medium, at(now)));
+        }
+        let late = at("2026-10-16 23:00");
+        assert!(router.admits("slack", &alert("exec_rate", Severity::High), late));
+        assert!(router.admits("slack", &alert("oom_kill", Severity::Low), late));
+
+        let weekend = QuietHours::from_config(&QuietHoursConfig {
+            start: "00:00".into(),
+            end: "00:00".into(),
+            days: vec!["sat".into(), "sun".into()],
+            channels: Vec::new(),
+            min_severity: "medium".into(),
+        })
+        .unwrap();
+        assert!(!weekend.is_open(at("2026-10-16 23:59")));
+        assert!(weekend.is_open(at("2026-10-17 00:00")));
+        assert!(weekend.is_open(at("2026-10-18 23:59")));
+        assert!(!weekend.is_open(at("2026-10-19 00:00")));
+    }
+}
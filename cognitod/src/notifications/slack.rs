@@ -1,19 +1,31 @@
+use super::retry::{self, HttpStatus, RetryQueue};
 use crate::alerts::{Alert, Severity};
 use crate::config::SlackConfig;
+use crate::inventory::HostFacts;
+use crate::network::Network;
+use crate::redaction::{Destination, RedactionPolicy};
 use crate::schema::Insight;
 use anyhow::{Context, Result};
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use reqwest::Client;
 use serde_json::json;
+use std::sync::Arc;
 use tokio::sync::broadcast;
+use tokio::time::Instant;
 
 /// Slack notification handler
+///
+/// Alerts that fail to post for a retryable reason (network errors, 429
+/// and 5xx responses) wait in a retry queue and are sent after recovery.
 pub struct SlackNotifier {
     webhook_url: String,
     channel: Option<String>,
     dashboard_base_url: String,
     rx: broadcast::Receiver<Alert>,
     client: Client,
+    redaction: Arc<RedactionPolicy>,
+    inventory: Option<Arc<HostFacts>>,
+    retry: RetryQueue,
 }
 
 impl SlackNotifier {
@@ -24,17 +36,58 @@ impl SlackNotifier {
             dashboard_base_url: config.dashboard_base_url,
             rx,
             client: Client::new(),
+            redaction: Arc::new(RedactionPolicy::default()),
+            inventory: None,
+            retry: RetryQueue::new(config.retry),
         }
     }
 
+    /// Apply an outbound redaction policy to every message sent.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Show the host's facts (instance type, kernel, ...) under each alert.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Send through the configured proxy and CA bundle.
+    pub fn with_network(mut self, network: &Network) -> Self {
+        self.client = network.client();
+        self
+    }
+
     pub async fn run(mut self) {
         info!("Slack notifier started");
 
         loop {
-            match self.rx.recv().await {
+            let received = tokio::select! {
+                received = self.rx.recv() => received,
+                _ = tokio::time::sleep_until(self.retry.due().unwrap_or_else(Instant::now)),
+                    if self.retry.due().is_some() =>
+                {
+                    self.retry_queued().await;
+                    continue;
+                }
+            };
+            match received {
                 Ok(alert) => {
-                    if let Err(e) = self.send_alert(&alert).await {
-                        error!("Failed to send Slack alert: {}", e);
+                    let payload = self.alert_payload(&alert);
+                    if !self.retry.is_empty() {
+                        // Behind the alerts still waiting, to keep the order.
+                        self.retry.push(payload);
+                        continue;
+                    }
+                    match self.post_to_slack(&payload).await {
+                        Ok(()) => {}
+                        Err(e) if self.retry.enabled() && retry::retryable(&e) => {
+                            warn!("Failed to send Slack alert, queued for retry: {:#}", e);
+                            self.retry.push(payload);
+                        }
+                        Err(e) => error!("Failed to send Slack alert: {:#}", e),
                     }
                 }
                 Err(broadcast::error::RecvError::Lagged(n)) => {
@@ -48,15 +101,44 @@ impl SlackNotifier {
         }
     }
 
-    async fn send_alert(&self, alert: &Alert) -> Result<()> {
+    /// Post the oldest queued alert; on success, the next is due at once.
+    async fn retry_queued(&mut self) {
+        let Some(payload) = self.retry.front().cloned() else {
+            return;
+        };
+        match self.post_to_slack(&payload).await {
+            Ok(()) => {
+                self.retry.pop();
+                info!("Sent queued Slack alert, {} still queued", self.retry.len());
+            }
+            Err(e) if retry::retryable(&e) => {
+                debug!("Slack retry failed: {:#}", e);
+                self.retry.failed();
+            }
+            Err(e) => {
+                error!("Dropping queued Slack alert: {:#}", e);
+                self.retry.pop();
+            }
+        }
+    }
+
+    fn alert_payload(&self, alert: &Alert) -> serde_json::Value {
+        let alert = &self
+            .redaction
+            .redact_alert(Destination::Notifications, alert);
         let color = match alert.severity {
-            Severity::High => "#FF0000",   // Red
-            Severity::Medium => "#FFA500", // Orange
-            Severity::Low => "#FFFF00",    // Yellow
-            Severity::Info => "#0000FF",   // Blue
+            _ if alert.is_resolution() => "#36a64f", // Green
+            Severity::High => "#FF0000",             // Red
+            Severity::Medium => "#FFA500",           // Orange
+            Severity::Low => "#FFFF00",              // Yellow
+            Severity::Info => "#0000FF",             // Blue
+        };
+        let header = match alert.is_resolution() {
+            true => format!("✅ Resolved: {}", alert.rule),
+            false => format!("🚨 Alert: {}", alert.rule),
         };
 
-        let payload = json!({
+        let mut payload = json!({
             "channel": self.channel,
             "attachments": [{
                 "color": color,
@@ -65,7 +147,7 @@ impl SlackNotifier {
                         "type": "header",
                         "text": {
                             "type": "plain_text",
-                            "text": format!("🚨 Alert: {}", alert.rule),
+                            "text": header,
                             "emoji": true
                         }
                     },
@@ -88,16 +170,72 @@ impl SlackNotifier {
                             "type": "mrkdwn",
                             "text": format!("*Message:*\n{}", alert.message)
                         }
+                    },
+                    {
+                        // Labels feed the rule's noise score (`/rules/suggestions`).
+                        "type": "actions",
+                        "elements": [
+                            {
+                                "type": "button",
+                                "text": {
+                                    "type": "plain_text",
+                                    "text": "👍 Useful",
+                                    "emoji": true
+                                },
+                                "value": alert.rule,
+                                "action_id": "alert_useful"
+                            },
+                            {
+                                "type": "button",
+                                "text": {
+                                    "type": "plain_text",
+                                    "text": "👎 Noise",
+                                    "emoji": true
+                                },
+                                "value": alert.rule,
+                                "action_id": "alert_noise"
+                            }
+                        ]
                     }
                 ]
             }]
         });
-
-        self.post_to_slack(&payload).await
+        if let Some(facts) = &self.inventory
+            && let Some(blocks) = payload["attachments"][0]["blocks"].as_array_mut()
+        {
+            // Above the feedback buttons.
+            blocks.insert(
+                blocks.len() - 1,
+                json!({
+                    "type": "context",
+                    "elements": [{ "type": "mrkdwn", "text": facts.to_string() }]
+                }),
+            );
+        }
+        if !alert.labels.is_empty()
+            && let Some(blocks) = payload["attachments"][0]["blocks"].as_array_mut()
+        {
+            blocks.insert(
+                blocks.len() - 1,
+                json!({
+                    "type": "context",
+                    "elements": [{ "type": "mrkdwn", "text": format!("`{}`", alert.labels_line()) }]
+                }),
+            );
+        }
+        if alert.is_resolution()
+            && let Some(blocks) = payload["attachments"][0]["blocks"].as_array_mut()
+        {
+            // Feedback was asked for with the alert itself.
+            blocks.pop();
+        }
+        payload
     }
 
     pub async fn send_insight(&self, insight: &Insight, action_ids: &[String]) -> Result<()> {
-        // Note: Redaction should be applied by caller before calling this method
+        let insight = &self
+            .redaction
+            .redact_insight(Destination::Notifications, insight);
 
         info!(target: "audit", "Sending insight notification to Slack. Reason: {:?}, ID: {}", insight.reason_code, insight.id);
 
@@ -206,6 +344,19 @@ impl SlackNotifier {
             "url": format!("{}/insights/{}", self.dashboard_base_url, insight.id)
         }));
 
+        // Anomaly insights carry an evidence bundle
+        if insight.reason_code != crate::schema::InsightReason::Normal {
+            elements.push(json!({
+                "type": "button",
+                "text": {
+                    "type": "plain_text",
+                    "text": "Evidence",
+                    "emoji": true
+                },
+                "url": format!("{}/insights/{}/evidence", self.dashboard_base_url, insight.id)
+            }));
+        }
+
         elements.push(json!({
             "type": "button",
             "text": {
@@ -256,9 +407,11 @@ impl SlackNotifier {
             .await
             .context("Failed to send request to Slack")?;
 
-        if !res.status().is_success() {
+        let status = res.status();
+        if !status.is_success() {
             let text = res.text().await.unwrap_or_default();
-            anyhow::bail!("Slack API error: {}", text);
+            return Err(anyhow::Error::new(HttpStatus(status))
+                .context(format!("Slack API error: {}", text)));
         }
 
         debug!("Successfully sent notification to Slack");
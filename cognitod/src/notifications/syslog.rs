@@ -0,0 +1,404 @@
+use crate::alerts::Alert;
+use crate::config::{SyslogConfig, SyslogSeverities};
+use crate::redaction::{Destination, RedactionPolicy};
+use anyhow::{Context, Result, bail};
+use chrono::{SecondsFormat, Utc};
+use log::{debug, error, info};
+use std::fs;
+use std::sync::Arc;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket, lookup_host};
+use tokio::sync::broadcast;
+use tokio::time::{Duration, timeout};
+use tokio_native_tls::{TlsConnector, native_tls};
+
+/// SD-ID of the alert's structured data (32473 is the enterprise number
+/// RFC 5612 reserves for documentation and private use).
+const SD_ID: &str = "linnix@32473";
+
+/// Largest UDP datagram every receiver must accept (RFC 5426).
+const MAX_UDP_BYTES: usize = 2048;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// RFC 5424 severity names, by code.
+const SEVERITY_NAMES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// Facility names, by code; `local0` to `local7` follow at 16.
+const FACILITY_NAMES: [&str; 16] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+];
+
+enum Transport {
+    Udp,
+    Tcp,
+    Tls(TlsConnector),
+}
+
+enum Connection {
+    Udp(UdpSocket),
+    Stream(Box<dyn AsyncWrite + Send + Sync + Unpin>),
+}
+
+/// Syslog notification handler
+///
+/// Sends each alert to a remote syslog receiver as an RFC 5424 message,
+/// over UDP, TCP or TLS, with the severity mapped to a syslog severity and
+/// the labels as structured data.
+pub struct SyslogNotifier {
+    address: String,
+    transport: Transport,
+    facility: u8,
+    app_name: String,
+    /// Syslog severity code by severity, indexed by `Severity as usize`.
+    severities: [u8; 4],
+    connection: Option<Connection>,
+    rx: broadcast::Receiver<Alert>,
+    redaction: Arc<RedactionPolicy>,
+}
+
+impl SyslogNotifier {
+    /// Fails on an address without a port, an unknown transport, facility
+    /// or severity, or an unreadable `ca_file`.
+    pub fn new(config: SyslogConfig, rx: broadcast::Receiver<Alert>) -> Result<Self> {
+        if !config
+            .address
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+        {
+            bail!("invalid address '{}' (expected host:port)", config.address);
+        }
+        let transport = match config.transport.as_str() {
+            "udp" => Transport::Udp,
+            "tcp" => Transport::Tcp,
+            "tls" => {
+                let mut builder = native_tls::TlsConnector::builder();
+                if let Some(path) = &config.ca_file {
+                    let pem = fs::read(path).with_context(|| format!("reading ca_file {path}"))?;
+                    let cert = native_tls::Certificate::from_pem(&pem)
+                        .with_context(|| format!("parsing ca_file {path}"))?;
+                    builder.add_root_certificate(cert);
+                }
+                Transport::Tls(builder.build()?.into())
+            }
+            other => bail!("unknown transport '{other}' (expected udp, tcp or tls)"),
+        };
+        let facility = facility_code(&config.facility)
+            .with_context(|| format!("unknown facility '{}'", config.facility))?;
+        let SyslogSeverities {
+            info,
+            low,
+            medium,
+            high,
+        } = &config.severities;
+        let mut severities = [0; 4];
+        for (code, name) in severities.iter_mut().zip([info, low, medium, high]) {
+            *code = SEVERITY_NAMES
+                .iter()
+                .position(|known| *known == name.as_str())
+                .with_context(|| format!("unknown severity '{name}'"))? as u8;
+        }
+        Ok(Self {
+            address: config.address,
+            transport,
+            facility,
+            app_name: config.app_name,
+            severities,
+            connection: None,
+            rx,
+            redaction: Arc::new(RedactionPolicy::default()),
+        })
+    }
+
+    /// Apply an outbound redaction policy to every alert.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    pub async fn run(mut self) {
+        info!("Syslog notifier started, sending to {}", self.address);
+
+        loop {
+            match self.rx.recv().await {
+                Ok(alert) => {
+                    let alert = self
+                        .redaction
+                        .redact_alert(Destination::Notifications, &alert);
+                    if let Err(e) = self.send(&alert).await {
+                        error!("Failed to send syslog alert: {:#}", e);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("Syslog notifier lagged by {} alerts", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    info!("Alert channel closed, stopping syslog notifier");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Send `alert`, reconnecting once if the receiver dropped the
+    /// connection since the last alert.
+    async fn send(&mut self, alert: &Alert) -> Result<()> {
+        let message = self.message(alert);
+        if let Some(connection) = self.connection.take() {
+            match write(connection, &message).await {
+                Ok(connection) => {
+                    self.connection = Some(connection);
+                    debug!("Successfully sent alert to syslog");
+                    return Ok(());
+                }
+                Err(e) => debug!("Syslog connection lost, reconnecting: {e}"),
+            }
+        }
+        let connection = write(self.connect().await?, &message)
+            .await
+            .context("Failed to write to syslog receiver")?;
+        self.connection = Some(connection);
+        debug!("Successfully sent alert to syslog");
+        Ok(())
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        let addr = lookup_host(&self.address)
+            .await
+            .with_context(|| format!("resolving {}", self.address))?
+            .next()
+            .with_context(|| format!("no address for {}", self.address))?;
+        let connecting = async {
+            Ok::<_, anyhow::Error>(match &self.transport {
+                Transport::Udp => {
+                    let local = if addr.is_ipv4() {
+                        "0.0.0.0:0"
+                    } else {
+                        "[::]:0"
+                    };
+                    let socket = UdpSocket::bind(local).await?;
+                    socket.connect(addr).await?;
+                    Connection::Udp(socket)
+                }
+                Transport::Tcp => Connection::Stream(Box::new(TcpStream::connect(addr).await?)),
+                Transport::Tls(connector) => {
+                    let stream = TcpStream::connect(addr).await?;
+                    let (host, _) = self.address.rsplit_once(':').unwrap_or_default();
+                    let domain = host.trim_start_matches('[').trim_end_matches(']');
+                    Connection::Stream(Box::new(connector.connect(domain, stream).await?))
+                }
+            })
+        };
+        timeout(CONNECT_TIMEOUT, connecting)
+            .await
+            .with_context(|| format!("timed out connecting to {}", self.address))?
+            .with_context(|| format!("connecting to {}", self.address))
+    }
+
+    /// RFC 5424 message of `alert`, without transport framing.
+    fn message(&self, alert: &Alert) -> String {
+        let priority = u16::from(self.facility) * 8
+            + u16::from(self.severities[alert.severity.clone() as usize]);
+        let mut data = format!("[{SD_ID} severity=\"{}\"", alert.severity.as_str());
+        if let Some(secs) = alert.resolved_after_secs {
+            data.push_str(&format!(" resolved_after_secs=\"{secs}\""));
+        }
+        for (key, value) in &alert.labels {
+            data.push_str(&format!(" {}=\"{}\"", param_name(key), param_value(value)));
+        }
+        data.push(']');
+        format!(
+            "<{priority}>1 {} {} {} {} {} {data} \u{feff}{}: {}",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            header(&alert.host, 255),
+            header(&self.app_name, 48),
+            std::process::id(),
+            header(&alert.rule, 32),
+            alert.rule,
+            alert.message,
+        )
+    }
+}
+
+/// Write `message` framed for the connection: one datagram over UDP
+/// (cut to [`MAX_UDP_BYTES`]), octet-counted over TCP and TLS (RFC 6587).
+async fn write(mut connection: Connection, message: &str) -> std::io::Result<Connection> {
+    match &mut connection {
+        Connection::Udp(socket) => {
+            let mut end = message.len().min(MAX_UDP_BYTES);
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            socket.send(&message.as_bytes()[..end]).await?;
+        }
+        Connection::Stream(stream) => {
+            let frame = format!("{} {message}", message.len());
+            stream.write_all(frame.as_bytes()).await?;
+            stream.flush().await?;
+        }
+    }
+    Ok(connection)
+}
+
+fn facility_code(name: &str) -> Option<u8> {
+    if let Some(n) = name.strip_prefix("local")
+        && let Ok(n @ 0..=7) = n.parse::<u8>()
+    {
+        return Some(16 + n);
+    }
+    FACILITY_NAMES
+        .iter()
+        .position(|known| *known == name)
+        .map(|code| code as u8)
+}
+
+/// Header field of at most `max` printable ASCII characters; `-` when empty.
+fn header(value: &str, max: usize) -> String {
+    let field: String = value
+        .chars()
+        .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+        .take(max)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+/// SD-PARAM name: at most 32 printable ASCII characters other than `=`,
+/// space, `]` and `"`.
+fn param_name(key: &str) -> String {
+    header(key, 32).replace(['=', ']', '"'], "_")
+}
+
+/// SD-PARAM value with `"`, `\` and `]` escaped.
+fn param_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn config(address: &str, transport: &str) -> SyslogConfig {
+        toml::from_str(&format!(
+            "address = \"{address}\"\ntransport = \"{transport}\"\nfacility = \"local3\"\n"
+        ))
+        .unwrap()
+    }
+
+    fn alert(severity: Severity) -> Alert {
+        Alert {
+            rule: "fork_burst".to_string(),
+            severity,
+            message: "forks spiking".to_string(),
+            host: "node-1".to_string(),
+            resolved_after_secs: None,
+            labels: [
+                ("pid".to_string(), "42".to_string()),
+                ("comm".to_string(), "ba\"sh]".to_string()),
+            ]
+            .into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn formats_rfc5424() {
+        let (_tx, rx) = broadcast::channel(1);
+        let notifier = SyslogNotifier::new(config("[::1]:514", "udp"), rx.resubscribe()).unwrap();
+
+        // local3 (19) * 8 + err (3)
+        let message = notifier.message(&alert(Severity::High));
+        assert!(message.starts_with("<155>1 "), "{message}");
+        let rest = message.split_once(" node-1 linnix ").unwrap().1;
+        let (procid, rest) = rest.split_once(' ').unwrap();
+        assert_eq!(procid, std::process::id().to_string());
+        assert_eq!(
+            rest,
+            "fork_burst [linnix@32473 severity=\"high\" comm=\"ba\\\"sh\\]\" pid=\"42\"] \
+             \u{feff}fork_burst: forks spiking"
+        );
+        // local3 (19) * 8 + info (6)
+        assert!(
+            notifier
+                .message(&alert(Severity::Info))
+                .starts_with("<158>1 ")
+        );
+
+        let mut cfg = config("[::1]:514", "udp");
+        cfg.severities.high = "fatal".into();
+        let err = SyslogNotifier::new(cfg, rx.resubscribe()).err().unwrap();
+        assert!(
+            err.to_string().contains("unknown severity 'fatal'"),
+            "{err}"
+        );
+        let err = SyslogNotifier::new(config("syslog.example.com", "udp"), rx.resubscribe())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("expected host:port"), "{err}");
+        let err = SyslogNotifier::new(config("[::1]:514", "relp"), rx)
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string().contains("unknown transport 'relp'"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn sends_over_udp_and_tcp() {
+        let (_tx, rx) = broadcast::channel(1);
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = receiver.local_addr().unwrap().to_string();
+        let mut notifier = SyslogNotifier::new(config(&address, "udp"), rx.resubscribe()).unwrap();
+        notifier.send(&alert(Severity::Medium)).await.unwrap();
+        let mut buf = vec![0; MAX_UDP_BYTES];
+        let len = receiver.recv(&mut buf).await.unwrap();
+        let datagram = String::from_utf8_lossy(&buf[..len]);
+        assert!(datagram.starts_with("<156>1 "), "{datagram}");
+
+        // Frames are octet-counted, and a closed connection is reopened.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut notifier = SyslogNotifier::new(config(&address, "tcp"), rx).unwrap();
+        for _ in 0..2 {
+            notifier.send(&alert(Severity::High)).await.unwrap();
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            drop(notifier.connection.take());
+            stream.read_to_end(&mut received).await.unwrap();
+            let received = String::from_utf8(received).unwrap();
+            let (len, message) = received.split_once(' ').unwrap();
+            assert_eq!(len.parse::<usize>().unwrap(), message.len());
+            assert!(message.starts_with("<155>1 "), "{message}");
+        }
+    }
+}
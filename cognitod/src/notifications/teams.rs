@@ -0,0 +1,340 @@
+use crate::alerts::{Alert, Severity};
+use crate::config::TeamsConfig;
+use crate::insights::InsightRecord;
+use crate::inventory::HostFacts;
+use crate::network::Network;
+use crate::redaction::{Destination, RedactionPolicy};
+use crate::schema::{Insight, InsightReason};
+use anyhow::{Context, Result};
+use log::{debug, error, info};
+use reqwest::Client;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Microsoft Teams notification handler
+///
+/// Posts alerts, and optionally insights, to a Teams incoming webhook (or a
+/// Workflows webhook) as Adaptive Cards laid out like the Slack messages.
+/// Teams webhooks can't call back, so buttons link to the dashboard.
+pub struct TeamsNotifier {
+    webhook_url: String,
+    dashboard_base_url: String,
+    rx: broadcast::Receiver<Alert>,
+    insights: Option<broadcast::Receiver<InsightRecord>>,
+    client: Client,
+    redaction: Arc<RedactionPolicy>,
+    inventory: Option<Arc<HostFacts>>,
+}
+
+impl TeamsNotifier {
+    pub fn new(config: TeamsConfig, rx: broadcast::Receiver<Alert>) -> Self {
+        Self {
+            webhook_url: config.webhook_url,
+            dashboard_base_url: config.dashboard_base_url.trim_end_matches('/').to_string(),
+            rx,
+            insights: None,
+            client: Client::new(),
+            redaction: Arc::new(RedactionPolicy::default()),
+            inventory: None,
+        }
+    }
+
+    /// Also post the insights recorded in the insight store.
+    pub fn with_insights(mut self, rx: broadcast::Receiver<InsightRecord>) -> Self {
+        self.insights = Some(rx);
+        self
+    }
+
+    /// Apply an outbound redaction policy to every card.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Show the host's facts (instance type, kernel, ...) under each alert.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Send through the configured proxy and CA bundle.
+    pub fn with_network(mut self, network: &Network) -> Self {
+        self.client = network.client();
+        self
+    }
+
+    pub async fn run(mut self) {
+        info!(
+            "Teams notifier started, insights: {}",
+            self.insights.is_some()
+        );
+
+        // Runs until both channels are closed.
+        let mut alerts_open = true;
+        while alerts_open || self.insights.is_some() {
+            tokio::select! {
+                received = self.rx.recv(), if alerts_open => match received {
+                    Ok(alert) => self.on_alert(&alert).await,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        error!("Teams notifier lagged by {} alerts", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => alerts_open = false,
+                },
+                received = next_insight(&mut self.insights) => match received {
+                    Ok(record) => self.on_insight(&record).await,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        error!("Teams notifier lagged by {} insights", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => self.insights = None,
+                },
+            }
+        }
+        info!("Alert and insight channels closed, stopping Teams notifier");
+    }
+
+    async fn on_alert(&self, alert: &Alert) {
+        let alert = self
+            .redaction
+            .redact_alert(Destination::Notifications, alert);
+        let card = alert_card(&alert, self.inventory.as_deref(), &self.dashboard_base_url);
+        if let Err(e) = self.post(&card).await {
+            error!("Failed to send Teams alert: {}", e);
+        }
+    }
+
+    async fn on_insight(&self, record: &InsightRecord) {
+        let insight = &record.insight;
+        let insight = self
+            .redaction
+            .redact_insight(Destination::Notifications, insight);
+        let card = insight_card(
+            &insight,
+            record.evidence.is_some(),
+            &self.dashboard_base_url,
+        );
+        if let Err(e) = self.post(&card).await {
+            error!("Failed to send Teams insight: {}", e);
+        }
+    }
+
+    async fn post(&self, card: &Value) -> Result<()> {
+        let res = self
+            .client
+            .post(&self.webhook_url)
+            .json(&message(card))
+            .send()
+            .await
+            .context("Failed to send request to Teams")?;
+
+        if !res.status().is_success() {
+            let text = res.text().await.unwrap_or_default();
+            anyhow::bail!("Teams webhook error: {}", text);
+        }
+
+        debug!("Successfully sent notification to Teams");
+        Ok(())
+    }
+}
+
+/// The next insight, or never without an insight channel.
+pub(super) async fn next_insight(
+    rx: &mut Option<broadcast::Receiver<InsightRecord>>,
+) -> Result<InsightRecord, broadcast::error::RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Webhook message carrying one Adaptive Card.
+fn message(card: &Value) -> Value {
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "contentUrl": null,
+            "content": card
+        }]
+    })
+}
+
+fn card(body: Vec<Value>, actions: Vec<Value>) -> Value {
+    json!({
+        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+        "type": "AdaptiveCard",
+        "version": "1.4",
+        "msteams": { "width": "Full" },
+        "body": body,
+        "actions": actions
+    })
+}
+
+fn open_url(title: &str, url: String) -> Value {
+    json!({ "type": "Action.OpenUrl", "title": title, "url": url })
+}
+
+fn alert_card(alert: &Alert, facts: Option<&HostFacts>, dashboard: &str) -> Value {
+    let color = match alert.severity {
+        _ if alert.is_resolution() => "Good",
+        Severity::High => "Attention",
+        Severity::Medium => "Warning",
+        Severity::Low => "Accent",
+        Severity::Info => "Default",
+    };
+    let header = match alert.is_resolution() {
+        true => format!("✅ Resolved: {}", alert.rule),
+        false => format!("🚨 Alert: {}", alert.rule),
+    };
+    let mut body = vec![
+        json!({
+            "type": "TextBlock",
+            "text": header,
+            "size": "Large",
+            "weight": "Bolder",
+            "color": color,
+            "wrap": true
+        }),
+        json!({
+            "type": "FactSet",
+            "facts": [
+                { "title": "Severity", "value": alert.severity.as_str().to_uppercase() },
+                { "title": "Host", "value": alert.host }
+            ]
+        }),
+        json!({ "type": "TextBlock", "text": alert.message, "wrap": true }),
+    ];
+    if !alert.labels.is_empty() {
+        body.push(json!({
+            "type": "TextBlock",
+            "text": alert.labels_line(),
+            "fontType": "Monospace",
+            "isSubtle": true,
+            "size": "Small",
+            "wrap": true
+        }));
+    }
+    if let Some(facts) = facts {
+        body.push(json!({
+            "type": "TextBlock",
+            "text": facts.to_string(),
+            "isSubtle": true,
+            "size": "Small",
+            "wrap": true
+        }));
+    }
+    card(
+        body,
+        vec![open_url("View Dashboard", dashboard.to_string())],
+    )
+}
+
+fn insight_card(insight: &Insight, has_evidence: bool, dashboard: &str) -> Value {
+    let color = match insight.reason_code {
+        InsightReason::Normal => "Good",
+        _ => "Attention",
+    };
+    let mut body = vec![
+        json!({
+            "type": "TextBlock",
+            "text": format!(
+                "🚨 {} | 🤖 {:.0}%",
+                insight.reason_code.as_str(),
+                insight.confidence * 100.0
+            ),
+            "size": "Large",
+            "weight": "Bolder",
+            "color": color,
+            "wrap": true
+        }),
+        json!({ "type": "TextBlock", "text": "Summary", "weight": "Bolder" }),
+        json!({ "type": "TextBlock", "text": insight.summary, "wrap": true }),
+    ];
+    if !insight.top_pods.is_empty() {
+        body.push(
+            json!({ "type": "TextBlock", "text": "Top Contributing Pods", "weight": "Bolder" }),
+        );
+        body.push(json!({
+            "type": "FactSet",
+            "facts": insight.top_pods.iter().map(|pod| json!({
+                "title": format!("{}/{}", pod.namespace, pod.pod),
+                "value": format!("CPU: {:.1}%, PSI: {:.1}%", pod.cpu_usage, pod.psi_contribution)
+            })).collect::<Vec<_>>()
+        }));
+    }
+    body.push(json!({ "type": "TextBlock", "text": "Suggested Next Step", "weight": "Bolder" }));
+    body.push(json!({ "type": "TextBlock", "text": insight.suggested_next_step, "wrap": true }));
+    if let Some(proc) = &insight.primary_process {
+        body.push(json!({
+            "type": "TextBlock",
+            "text": format!("Primary Process: {proc}"),
+            "isSubtle": true,
+            "size": "Small",
+            "wrap": true
+        }));
+    }
+
+    let mut actions = vec![open_url(
+        "View Dashboard",
+        format!("{dashboard}/insights/{}", insight.id),
+    )];
+    if has_evidence {
+        actions.push(open_url(
+            "Evidence",
+            format!("{dashboard}/insights/{}/evidence", insight.id),
+        ));
+    }
+    card(body, actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::PodContribution;
+
+    #[test]
+    fn cards_follow_the_slack_layout() {
+        let alert = Alert {
+            rule: "fork_burst".to_string(),
+            severity: Severity::High,
+            message: "fork burst: 200 forks in 5s".to_string(),
+            host: "node-1".to_string(),
+            resolved_after_secs: None,
+            labels: [("pid".to_string(), "42".to_string())].into(),
+        };
+        let card = alert_card(&alert, None, "http://dash");
+        assert_eq!(card["body"][0]["text"], "🚨 Alert: fork_burst");
+        assert_eq!(card["body"][0]["color"], "Attention");
+        assert_eq!(card["body"][1]["facts"][1]["value"], "node-1");
+        assert_eq!(card["body"][3]["text"], alert.labels_line());
+        assert_eq!(card["actions"][0]["url"], "http://dash");
+        assert_eq!(
+            message(&card)["attachments"][0]["contentType"],
+            "application/vnd.microsoft.card.adaptive"
+        );
+
+        let insight = Insight {
+            reason_code: InsightReason::ForkStorm,
+            summary: "fork storm in payments".to_string(),
+            confidence: 0.9,
+            id: "abc".to_string(),
+            top_pods: vec![PodContribution {
+                namespace: "payments".to_string(),
+                pod: "api-1".to_string(),
+                cpu_usage: 80.0,
+                psi_contribution: 12.5,
+            }],
+            suggested_next_step: "scale api down".to_string(),
+            primary_process: None,
+            k8s: None,
+        };
+        let card = insight_card(&insight, true, "http://dash");
+        assert_eq!(card["body"][4]["facts"][0]["title"], "payments/api-1");
+        assert_eq!(card["body"][6]["text"], "scale api down");
+        assert_eq!(card["actions"][0]["url"], "http://dash/insights/abc");
+        assert_eq!(
+            card["actions"][1]["url"],
+            "http://dash/insights/abc/evidence"
+        );
+    }
+}
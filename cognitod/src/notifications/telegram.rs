@@ -0,0 +1,257 @@
+use super::teams::next_insight;
+use crate::alerts::Alert;
+use crate::config::TelegramConfig;
+use crate::insights::InsightRecord;
+use crate::inventory::HostFacts;
+use crate::network::Network;
+use crate::redaction::{Destination, RedactionPolicy};
+use crate::schema::Insight;
+use anyhow::{Context, Result};
+use log::{debug, error, info};
+use reqwest::Client;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Telegram notification handler
+///
+/// Sends alerts, and optionally insight summaries, to a chat through the
+/// Telegram bot API, formatted with MarkdownV2.
+pub struct TelegramNotifier {
+    send_url: String,
+    chat_id: String,
+    dashboard_base_url: String,
+    rx: broadcast::Receiver<Alert>,
+    insights: Option<broadcast::Receiver<InsightRecord>>,
+    client: Client,
+    redaction: Arc<RedactionPolicy>,
+    inventory: Option<Arc<HostFacts>>,
+}
+
+impl TelegramNotifier {
+    pub fn new(config: TelegramConfig, rx: broadcast::Receiver<Alert>) -> Self {
+        Self {
+            send_url: format!(
+                "{}/bot{}/sendMessage",
+                config.api_url.trim_end_matches('/'),
+                config.bot_token
+            ),
+            chat_id: config.chat_id,
+            dashboard_base_url: config.dashboard_base_url.trim_end_matches('/').to_string(),
+            rx,
+            insights: None,
+            client: Client::new(),
+            redaction: Arc::new(RedactionPolicy::default()),
+            inventory: None,
+        }
+    }
+
+    /// Also send a summary of each insight recorded in the insight store.
+    pub fn with_insights(mut self, rx: broadcast::Receiver<InsightRecord>) -> Self {
+        self.insights = Some(rx);
+        self
+    }
+
+    /// Apply an outbound redaction policy to every message.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Append the host's facts (instance type, kernel, ...) to each alert.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Send through the configured proxy and CA bundle.
+    pub fn with_network(mut self, network: &Network) -> Self {
+        self.client = network.client();
+        self
+    }
+
+    pub async fn run(mut self) {
+        info!(
+            "Telegram notifier started, insights: {}",
+            self.insights.is_some()
+        );
+
+        // Runs until both channels are closed.
+        let mut alerts_open = true;
+        while alerts_open || self.insights.is_some() {
+            tokio::select! {
+                received = self.rx.recv(), if alerts_open => match received {
+                    Ok(alert) => self.on_alert(&alert).await,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        error!("Telegram notifier lagged by {} alerts", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => alerts_open = false,
+                },
+                received = next_insight(&mut self.insights) => match received {
+                    Ok(record) => self.on_insight(&record).await,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        error!("Telegram notifier lagged by {} insights", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => self.insights = None,
+                },
+            }
+        }
+        info!("Alert and insight channels closed, stopping Telegram notifier");
+    }
+
+    async fn on_alert(&self, alert: &Alert) {
+        let alert = self
+            .redaction
+            .redact_alert(Destination::Notifications, alert);
+        let text = alert_text(&alert, self.inventory.as_deref());
+        if let Err(e) = self.send(&text).await {
+            error!("Failed to send Telegram alert: {}", e);
+        }
+    }
+
+    async fn on_insight(&self, record: &InsightRecord) {
+        let insight = &record.insight;
+        let insight = self
+            .redaction
+            .redact_insight(Destination::Notifications, insight);
+        let text = insight_text(&insight, &self.dashboard_base_url);
+        if let Err(e) = self.send(&text).await {
+            error!("Failed to send Telegram insight: {}", e);
+        }
+    }
+
+    async fn send(&self, text: &str) -> Result<()> {
+        let res = self
+            .client
+            .post(&self.send_url)
+            .json(&json!({
+                "chat_id": self.chat_id,
+                "text": text,
+                "parse_mode": "MarkdownV2",
+                "disable_web_page_preview": true
+            }))
+            .send()
+            .await
+            // The URL holds the bot token.
+            .map_err(|e| e.without_url())
+            .context("Failed to send request to Telegram")?;
+
+        if !res.status().is_success() {
+            let text = res.text().await.unwrap_or_default();
+            anyhow::bail!("Telegram API error: {}", text);
+        }
+
+        debug!("Successfully sent notification to Telegram");
+        Ok(())
+    }
+}
+
+/// Escape text for MarkdownV2, outside code spans.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escape text for a MarkdownV2 code span.
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+fn alert_text(alert: &Alert, facts: Option<&HostFacts>) -> String {
+    let header = match alert.is_resolution() {
+        true => format!("✅ *Resolved: {}*", escape(&alert.rule)),
+        false => format!(
+            "🚨 *\\[{}\\] {}*",
+            alert.severity.as_str().to_uppercase(),
+            escape(&alert.rule)
+        ),
+    };
+    let mut text = format!(
+        "{header}\n*Host:* {}\n\n{}",
+        escape(&alert.host),
+        escape(&alert.message)
+    );
+    if !alert.labels.is_empty() {
+        text.push_str(&format!("\n\n`{}`", escape_code(&alert.labels_line())));
+    }
+    if let Some(facts) = facts {
+        text.push_str(&format!("\n_{}_", escape(&facts.to_string())));
+    }
+    text
+}
+
+fn insight_text(insight: &Insight, dashboard: &str) -> String {
+    let mut text = format!(
+        "🤖 *{}* \\({:.0}%\\)\n{}",
+        escape(insight.reason_code.as_str()),
+        insight.confidence * 100.0,
+        escape(&insight.summary)
+    );
+    for pod in &insight.top_pods {
+        text.push_str(&format!(
+            "\n• `{}/{}` CPU {}%, PSI {}%",
+            escape_code(&pod.namespace),
+            escape_code(&pod.pod),
+            escape(&format!("{:.1}", pod.cpu_usage)),
+            escape(&format!("{:.1}", pod.psi_contribution))
+        ));
+    }
+    let url = format!("{dashboard}/insights/{}", insight.id);
+    text.push_str(&format!(
+        "\n\n*Next step:* {}\n[View insight]({})",
+        escape(&insight.suggested_next_step),
+        url.replace('\\', "\\\\").replace(')', "\\)")
+    ));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+    use crate::schema::{InsightReason, PodContribution};
+
+    #[test]
+    fn formats_markdown_v2() {
+        let alert = Alert {
+            rule: "fork_burst".to_string(),
+            severity: Severity::High,
+            message: "fork burst: 200 forks in 5s (pid 42)".to_string(),
+            host: "node-1".to_string(),
+            resolved_after_secs: None,
+            labels: [("comm".to_string(), "a`b".to_string())].into(),
+        };
+        assert_eq!(
+            alert_text(&alert, None),
+            "🚨 *\\[HIGH\\] fork\\_burst*\n*Host:* node\\-1\n\n\
+             fork burst: 200 forks in 5s \\(pid 42\\)\n\n`comm=a\\`b`"
+        );
+
+        let insight = Insight {
+            reason_code: InsightReason::ForkStorm,
+            summary: "fork storm.".to_string(),
+            confidence: 0.9,
+            id: "abc".to_string(),
+            top_pods: vec![PodContribution {
+                namespace: "payments".to_string(),
+                pod: "api-1".to_string(),
+                cpu_usage: 80.0,
+                psi_contribution: 12.5,
+            }],
+            suggested_next_step: "scale down".to_string(),
+            primary_process: None,
+            k8s: None,
+        };
+        assert_eq!(
+            insight_text(&insight, "http://dash"),
+            "🤖 *fork\\_storm* \\(90%\\)\nfork storm\\.\n• `payments/api-1` CPU 80\\.0%, PSI 12\\.5%\n\n\
+             *Next step:* scale down\n[View insight](http://dash/insights/abc)"
+        );
+    }
+}
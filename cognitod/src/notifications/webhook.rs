@@ -0,0 +1,230 @@
+use crate::config::InsightWebhookConfig;
+use crate::insights::InsightRecord;
+use crate::inventory::HostFacts;
+use crate::network::Network;
+use crate::redaction::{Destination, RedactionPolicy};
+use anyhow::{Context, Result};
+use log::{debug, error, info};
+use reqwest::Client;
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Insight webhook handler
+///
+/// POSTs every recorded insight of at least `min_confidence` (filtered by
+/// the [`InsightDispatcher`](super::InsightDispatcher)) as JSON to the
+/// configured URLs, so users can drive their own automation (tickets,
+/// autoscaling, paging) off model output.
+pub struct InsightWebhookNotifier {
+    config: InsightWebhookConfig,
+    rx: broadcast::Receiver<InsightRecord>,
+    client: Client,
+    redaction: Arc<RedactionPolicy>,
+    inventory: Option<Arc<HostFacts>>,
+}
+
+impl InsightWebhookNotifier {
+    pub fn new(config: InsightWebhookConfig, rx: broadcast::Receiver<InsightRecord>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+        Self {
+            config,
+            rx,
+            client,
+            redaction: Arc::new(RedactionPolicy::default()),
+            inventory: None,
+        }
+    }
+
+    /// Apply an outbound redaction policy to every payload.
+    pub fn with_redaction(mut self, policy: Arc<RedactionPolicy>) -> Self {
+        self.redaction = policy;
+        self
+    }
+
+    /// Include the host's facts (instance type, kernel, ...) in each payload.
+    pub fn with_inventory(mut self, facts: Arc<HostFacts>) -> Self {
+        self.inventory = Some(facts);
+        self
+    }
+
+    /// Post through the configured proxy and CA bundle.
+    pub fn with_network(mut self, network: &Network) -> Self {
+        if let Ok(client) = network
+            .client_builder()
+            .timeout(Duration::from_millis(self.config.timeout_ms))
+            .build()
+        {
+            self.client = client;
+        }
+        self
+    }
+
+    pub async fn run(mut self) {
+        info!(
+            "Insight webhook notifier started with {} URL(s)",
+            self.config.urls.len()
+        );
+
+        loop {
+            match self.rx.recv().await {
+                Ok(record) => {
+                    let mut payload =
+                        build_payload(&record, &self.config, self.inventory.as_deref());
+                    self.redaction
+                        .redact_value(Destination::Notifications, &mut payload);
+                    for url in &self.config.urls {
+                        if let Err(e) = self.post(url, &payload).await {
+                            error!("Failed to post insight webhook: {}", e);
+                        }
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    error!("Insight webhook notifier lagged by {} insights", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    info!("Insight channel closed, stopping webhook notifier");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn post(&self, url: &str, payload: &Value) -> Result<()> {
+        let mut req = self.client.post(url).json(payload);
+        for (name, value) in &self.config.headers {
+            req = req.header(name, value);
+        }
+        let resp = req
+            .send()
+            .await
+            .context("Failed to send insight webhook request")?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Webhook returned error status: {}", resp.status());
+        }
+
+        debug!("Insight webhook delivered");
+        Ok(())
+    }
+}
+
+/// Build the JSON body sent to webhook receivers.
+fn build_payload(
+    record: &InsightRecord,
+    config: &InsightWebhookConfig,
+    inventory: Option<&HostFacts>,
+) -> Value {
+    let mut insight = record.insight.clone();
+    if config.redact {
+        insight.redact();
+    }
+    let base = config.dashboard_base_url.trim_end_matches('/');
+
+    let mut payload = json!({
+        "type": "insight",
+        "timestamp": record.timestamp,
+        "id": insight.id,
+        "class": insight.reason_code.as_str(),
+        "confidence": insight.confidence,
+        "summary": insight.summary,
+        "suggested_next_step": insight.suggested_next_step,
+        "top_pods": insight.top_pods,
+        "primary_process": insight.primary_process,
+        "redacted": config.redact,
+        "links": {
+            "json": format!("{}/insights/{}", base, insight.id),
+            "html": format!("{}/insights/{}?format=html", base, insight.id),
+            "feedback": format!("{}/insights/{}/feedback", base, insight.id),
+        }
+    });
+    if !record.recent_changes.is_empty() {
+        let mut changes = record.recent_changes.clone();
+        if config.redact {
+            changes.iter_mut().for_each(|c| c.redact());
+        }
+        payload["recent_changes"] = json!(changes);
+    }
+    if record.evidence.is_some() {
+        payload["links"]["evidence"] = format!("{}/insights/{}/evidence", base, insight.id).into();
+    }
+    if let Some(facts) = inventory {
+        payload["host_facts"] = json!(facts);
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Insight, InsightReason, PodContribution};
+
+    fn config(redact: bool) -> InsightWebhookConfig {
+        InsightWebhookConfig {
+            urls: vec!["http://127.0.0.1:9/hook".to_string()],
+            min_confidence: 0.0,
+            redact,
+            headers: Default::default(),
+            timeout_ms: 1000,
+            dashboard_base_url: "http://linnix.local:3000/".to_string(),
+        }
+    }
+
+    fn record() -> InsightRecord {
+        InsightRecord {
+            timestamp: 1_700_000_000,
+            insight: Insight {
+                reason_code: InsightReason::CpuSpin,
+                summary: "payments pod starved".to_string(),
+                confidence: 0.9,
+                id: "abc123".to_string(),
+                top_pods: vec![PodContribution {
+                    namespace: "prod".to_string(),
+                    pod: "payments-0".to_string(),
+                    cpu_usage: 80.0,
+                    psi_contribution: 40.0,
+                }],
+                suggested_next_step: "raise cpu limit".to_string(),
+                primary_process: None,
+                k8s: None,
+            },
+            feedback: None,
+            recent_changes: Vec::new(),
+            evidence: None,
+        }
+    }
+
+    #[test]
+    fn payload_carries_class_confidence_and_links() {
+        let payload = build_payload(&record(), &config(false), None);
+        assert_eq!(payload["class"], "cpu_spin");
+        assert_eq!(payload["id"], "abc123");
+        assert_eq!(
+            payload["links"]["html"],
+            "http://linnix.local:3000/insights/abc123?format=html"
+        );
+        assert_eq!(payload["top_pods"][0]["pod"], "payments-0");
+        assert!(payload.get("host_facts").is_none());
+        assert!(payload["links"].get("evidence").is_none());
+
+        let facts = HostFacts {
+            kernel: "6.1.0".to_string(),
+            memory_bytes: 1 << 30,
+            ..Default::default()
+        };
+        let payload = build_payload(&record(), &config(false), Some(&facts));
+        assert_eq!(payload["host_facts"]["kernel"], "6.1.0");
+    }
+
+    #[test]
+    fn payload_is_redacted_when_enabled() {
+        let payload = build_payload(&record(), &config(true), None);
+        assert_ne!(payload["top_pods"][0]["pod"], "payments-0");
+        assert_ne!(payload["top_pods"][0]["namespace"], "prod");
+        assert_eq!(payload["redacted"], true);
+    }
+}
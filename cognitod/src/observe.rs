@@ -0,0 +1,166 @@
+//! Observe-only mode (`mode = "observe"`).
+//!
+//! Detectors, the circuit breaker and the ILM run as usual, but notifiers and
+//! the enforcement executor hand their output to an [`ObserveRecorder`]
+//! instead of delivering it. The recorder also runs in live mode, where it
+//! counts what was actually sent and done, so `GET /observe/report` has the
+//! same shape on both and an observe fleet can be compared with a live one.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Decisions kept for the report.
+const MAX_RECENT: usize = 100;
+
+/// What a recorded decision was about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionKind {
+    Notification,
+    Action,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Decision {
+    pub timestamp: u64,
+    pub kind: DecisionKind,
+    /// Notifier (`slack`, `apprise`, `webhook`) or action type (`kill_process`, ...).
+    pub target: String,
+    pub summary: String,
+    /// False when observe mode held it back.
+    pub performed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ObserveReport {
+    pub mode: &'static str,
+    /// When counting started (epoch seconds).
+    pub since: u64,
+    /// Notifications per notifier: sent in live mode, held back in observe mode.
+    pub notifications: BTreeMap<String, u64>,
+    /// Enforcement actions per type: executed in live mode, held back in observe mode.
+    pub actions: BTreeMap<String, u64>,
+    /// Most recent decisions, newest first.
+    pub recent: Vec<Decision>,
+}
+
+#[derive(Default)]
+struct Tally {
+    notifications: BTreeMap<String, u64>,
+    actions: BTreeMap<String, u64>,
+    recent: VecDeque<Decision>,
+}
+
+/// Records what the notification dispatchers and the enforcement executor
+/// did, or in observe mode would have done.
+pub struct ObserveRecorder {
+    observe: bool,
+    since: u64,
+    tally: Mutex<Tally>,
+}
+
+impl ObserveRecorder {
+    pub fn new(observe: bool) -> Self {
+        Self {
+            observe,
+            since: now_secs(),
+            tally: Mutex::new(Tally::default()),
+        }
+    }
+
+    /// True in observe mode: nothing may be delivered or executed.
+    pub fn is_observe(&self) -> bool {
+        self.observe
+    }
+
+    pub fn mode(&self) -> &'static str {
+        if self.observe { "observe" } else { "live" }
+    }
+
+    /// Record a notification about to go to `notifier`. Returns whether it
+    /// may be sent.
+    pub fn notification(&self, notifier: &str, summary: impl Into<String>) -> bool {
+        self.record(DecisionKind::Notification, notifier, summary.into())
+    }
+
+    /// Record an approved enforcement action of type `action`. Returns
+    /// whether it may be executed.
+    pub fn action(&self, action: &str, summary: impl Into<String>) -> bool {
+        self.record(DecisionKind::Action, action, summary.into())
+    }
+
+    fn record(&self, kind: DecisionKind, target: &str, summary: String) -> bool {
+        let performed = !self.observe;
+        if !performed {
+            match kind {
+                DecisionKind::Notification => {
+                    log::info!("[observe] would have notified {target}: {summary}")
+                }
+                DecisionKind::Action => {
+                    log::info!("[observe] would have executed {target}: {summary}")
+                }
+            }
+        }
+        let mut tally = self.tally.lock().unwrap_or_else(|e| e.into_inner());
+        let counts = match kind {
+            DecisionKind::Notification => &mut tally.notifications,
+            DecisionKind::Action => &mut tally.actions,
+        };
+        *counts.entry(target.to_string()).or_default() += 1;
+        if tally.recent.len() == MAX_RECENT {
+            tally.recent.pop_back();
+        }
+        tally.recent.push_front(Decision {
+            timestamp: now_secs(),
+            kind,
+            target: target.to_string(),
+            summary,
+            performed,
+        });
+        performed
+    }
+
+    pub fn report(&self) -> ObserveReport {
+        let tally = self.tally.lock().unwrap_or_else(|e| e.into_inner());
+        ObserveReport {
+            mode: self.mode(),
+            since: self.since,
+            notifications: tally.notifications.clone(),
+            actions: tally.actions.clone(),
+            recent: tally.recent.iter().cloned().collect(),
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn observe_mode_holds_back_and_counts() {
+        let recorder = ObserveRecorder::new(true);
+        assert!(!recorder.notification("slack", "[high] cpu_spin"));
+        assert!(!recorder.notification("slack", "[high] fork_storm"));
+        assert!(!recorder.action("kill_process", "kill pid 42"));
+
+        let report = recorder.report();
+        assert_eq!(report.mode, "observe");
+        assert_eq!(report.notifications["slack"], 2);
+        assert_eq!(report.actions["kill_process"], 1);
+        assert_eq!(report.recent[0].summary, "kill pid 42");
+        assert!(report.recent.iter().all(|d| !d.performed));
+
+        let live = ObserveRecorder::new(false);
+        assert!(live.notification("webhook", "insight"));
+        assert!(live.report().recent[0].performed);
+    }
+}
@@ -0,0 +1,242 @@
+//! Off-CPU time per process, from the OffCpu summaries of the `sched_switch`
+//! probe.
+//!
+//! A process with high CPU usage and no sleep is spinning; one whose
+//! threads spend their time in uninterruptible sleep is waiting on I/O.
+//! Incident analyses get both for the target process, so the model does not
+//! have to guess between `cpu_spin` and `io_saturation`.
+
+use crate::ProcessEvent;
+use crate::handler::Handler;
+use crate::types::SystemSnapshot;
+use async_trait::async_trait;
+use linnix_ai_ebpf_common::EventType;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Summaries kept per process, and the span they are summed over.
+const WINDOW_NS: u64 = 60_000_000_000;
+/// Processes tracked before those without a recent summary are dropped.
+const MAX_PROCESSES: usize = 4096;
+/// Processes listed as most blocked.
+const TOP_BLOCKED: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    ts_ns: u64,
+    blocked_ns: u64,
+    sleep_ns: u64,
+}
+
+#[derive(Debug, Default)]
+struct Process {
+    comm: String,
+    samples: VecDeque<Sample>,
+}
+
+/// Off-CPU time of one process within the window, summed over its threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffCpuSummary {
+    pub comm: String,
+    /// Uninterruptible sleep (D), mostly I/O.
+    pub blocked_ns: u64,
+    /// Interruptible sleep (S): waiting on locks, sockets, timers.
+    pub sleep_ns: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    processes: HashMap<u32, Process>,
+    /// Timestamp of the newest summary; the window ends here.
+    latest_ns: u64,
+}
+
+#[derive(Default)]
+pub struct OffCpuTracker {
+    inner: Mutex<Inner>,
+}
+
+impl OffCpuTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, event: &ProcessEvent) {
+        if event.event_type != EventType::OffCpu as u32 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.latest_ns = inner.latest_ns.max(event.ts_ns);
+        let cutoff = inner.latest_ns.saturating_sub(WINDOW_NS);
+        if inner.processes.len() >= MAX_PROCESSES {
+            inner
+                .processes
+                .retain(|_, p| p.samples.back().is_some_and(|s| s.ts_ns >= cutoff));
+        }
+        // Kept past exit: analyses of a killed process run afterwards.
+        let process = inner.processes.entry(event.pid).or_default();
+        process.comm = String::from_utf8_lossy(&event.comm)
+            .trim_end_matches('\0')
+            .to_string();
+        process.samples.push_back(Sample {
+            ts_ns: event.ts_ns,
+            blocked_ns: event.data,
+            sleep_ns: event.data2,
+        });
+        while process.samples.front().is_some_and(|s| s.ts_ns < cutoff) {
+            process.samples.pop_front();
+        }
+    }
+
+    /// Off-CPU time of `pid` within the window; `None` if it has not slept.
+    pub fn summary(&self, pid: u32) -> Option<OffCpuSummary> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        summarize(&inner, inner.processes.get(&pid)?)
+    }
+
+    /// Processes with the most time blocked in uninterruptible sleep.
+    pub fn top_blocked(&self, limit: usize) -> Vec<(u32, OffCpuSummary)> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        top_blocked(&inner, limit)
+    }
+
+    /// Off-CPU time of `target` and of the most blocked processes, for
+    /// prompts; `None` until a summary has arrived.
+    pub fn prompt_context(&self, target: Option<u32>) -> Option<String> {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.latest_ns == 0 {
+            return None;
+        }
+        let mut lines = Vec::new();
+        if let Some(pid) = target {
+            let summary = inner.processes.get(&pid).and_then(|p| summarize(&inner, p));
+            lines.push(match summary {
+                Some(s) => format!(
+                    "- Target: {} blocked in uninterruptible sleep (I/O), {} in interruptible sleep",
+                    secs(s.blocked_ns),
+                    secs(s.sleep_ns)
+                ),
+                None => "- Target: never slept, it was on CPU or runnable throughout".to_string(),
+            });
+        }
+        let top: Vec<String> = top_blocked(&inner, TOP_BLOCKED)
+            .into_iter()
+            .map(|(pid, s)| format!("{} (pid {pid}) {}", s.comm, secs(s.blocked_ns)))
+            .collect();
+        lines.push(if top.is_empty() {
+            "- No process blocked on I/O".to_string()
+        } else {
+            format!("- Most blocked on I/O: {}", top.join(", "))
+        });
+        Some(lines.join("\n"))
+    }
+}
+
+fn summarize(inner: &Inner, process: &Process) -> Option<OffCpuSummary> {
+    let cutoff = inner.latest_ns.saturating_sub(WINDOW_NS);
+    let recent = process.samples.iter().filter(|s| s.ts_ns >= cutoff);
+    let (blocked_ns, sleep_ns) = recent.fold((0, 0), |(b, s), sample| {
+        (b + sample.blocked_ns, s + sample.sleep_ns)
+    });
+    if blocked_ns == 0 && sleep_ns == 0 {
+        return None;
+    }
+    Some(OffCpuSummary {
+        comm: process.comm.clone(),
+        blocked_ns,
+        sleep_ns,
+    })
+}
+
+fn top_blocked(inner: &Inner, limit: usize) -> Vec<(u32, OffCpuSummary)> {
+    let mut top: Vec<(u32, OffCpuSummary)> = inner
+        .processes
+        .iter()
+        .filter_map(|(&pid, p)| Some((pid, summarize(inner, p)?)))
+        .filter(|(_, s)| s.blocked_ns > 0)
+        .collect();
+    top.sort_by(|a, b| b.1.blocked_ns.cmp(&a.1.blocked_ns).then(a.0.cmp(&b.0)));
+    top.truncate(limit);
+    top
+}
+
+fn secs(ns: u64) -> String {
+    format!("{:.1}s", ns as f64 / 1e9)
+}
+
+#[async_trait]
+impl Handler for OffCpuTracker {
+    fn name(&self) -> &'static str {
+        "offcpu"
+    }
+
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
+        self.record(event);
+        Ok(())
+    }
+
+    async fn on_snapshot(&self, _snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn offcpu(pid: u32, comm: &str, ts_s: u64, blocked_ms: u64, sleep_ms: u64) -> ProcessEvent {
+        let mut name = [0u8; 16];
+        name[..comm.len()].copy_from_slice(comm.as_bytes());
+        ProcessEvent::new(ProcessEventWire {
+            pid,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::OffCpu as u32,
+            ts_ns: ts_s * 1_000_000_000,
+            seq: 0,
+            comm: name,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: blocked_ms * 1_000_000,
+            data2: sleep_ms * 1_000_000,
+            aux: 5_000,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn sums_recent_summaries_and_ranks_blocked_processes() {
+        let tracker = OffCpuTracker::new();
+        assert_eq!(tracker.prompt_context(Some(7)), None);
+
+        tracker.record(&offcpu(10, "postgres", 100, 9_000, 0));
+        tracker.record(&offcpu(10, "postgres", 150, 4_000, 500));
+        tracker.record(&offcpu(10, "postgres", 200, 4_500, 1_000));
+        tracker.record(&offcpu(20, "nginx", 205, 0, 4_900));
+        tracker.record(&offcpu(30, "rsync", 210, 1_200, 0));
+
+        // The sample at 100s is outside the 60s window ending at 210s.
+        let postgres = tracker.summary(10).unwrap();
+        assert_eq!(postgres.blocked_ns, 8_500_000_000);
+        assert_eq!(postgres.sleep_ns, 1_500_000_000);
+        assert_eq!(tracker.summary(7), None);
+
+        let top: Vec<u32> = tracker.top_blocked(3).iter().map(|(pid, _)| *pid).collect();
+        assert_eq!(top, [10, 30]);
+        assert_eq!(
+            tracker.prompt_context(Some(7)).unwrap(),
+            "- Target: never slept, it was on CPU or runnable throughout\n\
+             - Most blocked on I/O: postgres (pid 10) 8.5s, rsync (pid 30) 1.2s"
+        );
+        assert!(
+            tracker
+                .prompt_context(Some(20))
+                .unwrap()
+                .starts_with("- Target: 0.0s blocked in uninterruptible sleep (I/O), 4.9s")
+        );
+    }
+}
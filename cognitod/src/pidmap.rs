@@ -0,0 +1,451 @@
+//! Event-driven PID → container map.
+//!
+//! Container enrichment used to read `/proc/<pid>/cgroup` for every event and
+//! every API lookup. This map is kept current from the event stream instead:
+//! a fork inherits its parent's container, an exec re-reads the cgroup once
+//! (runtimes move `runc init` into the container cgroup before exec'ing the
+//! entrypoint), and an exit drops the entry. Processes that predate the
+//! daemon are resolved from `/proc` on first lookup and then cached, so each
+//! PID costs at most one read per exec.
+//!
+//! Events also carry the cgroup v2 ID of the process, which is the inode
+//! number of its cgroup directory. Those are indexed by walking the cgroup
+//! mount, so processes that exit before `/proc` could be read (a container's
+//! short-lived hooks and probes) are attributed too; `/proc` is only read
+//! when the ID is unknown, e.g. on cgroup v1 hosts.
+
+use crate::ProcessEvent;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+const EVENT_EXEC: u32 = 0;
+const EVENT_FORK: u32 = 1;
+const EVENT_EXIT: u32 = 2;
+
+/// Default cap on tracked PIDs; roughly the default `pid_max`.
+pub const DEFAULT_MAX_PIDS: usize = 65_536;
+
+/// cgroup v2 mount walked to index cgroup IDs.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Directory levels below the cgroup mount that are indexed; Kubernetes
+/// container cgroups sit four or five levels down.
+const MAX_CGROUP_DEPTH: usize = 8;
+
+/// Unknown cgroup IDs trigger a new walk at most this often.
+const MIN_RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+type CgroupReader = fn(u32) -> Option<String>;
+
+/// Lists `(cgroup ID, path)` of every cgroup.
+type CgroupScanner = fn() -> Vec<(u64, String)>;
+
+pub struct PidContainerMap {
+    /// `None` records "not in a container" so host processes are not
+    /// re-read on every lookup.
+    pids: RwLock<HashMap<u32, Option<Arc<str>>>>,
+    max_pids: usize,
+    read_cgroup: CgroupReader,
+    proc_reads: AtomicU64,
+    cgroups: RwLock<CgroupIndex>,
+    scan_cgroups: CgroupScanner,
+    cgroup_scans: AtomicU64,
+}
+
+/// cgroup ID → cgroup found under the cgroup mount.
+#[derive(Default)]
+struct CgroupIndex {
+    ids: HashMap<u64, CgroupEntry>,
+    scanned_at: Option<Instant>,
+}
+
+#[derive(Clone)]
+struct CgroupEntry {
+    /// Path below the mount, e.g. `/kubepods.slice/...`; `/` for the root.
+    path: Arc<str>,
+    /// `None` for cgroups outside containers.
+    container: Option<Arc<str>>,
+}
+
+impl Default for PidContainerMap {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PIDS)
+    }
+}
+
+impl PidContainerMap {
+    pub fn new(max_pids: usize) -> Self {
+        Self::with_reader(max_pids, read_proc_cgroup)
+    }
+
+    pub(crate) fn with_reader(max_pids: usize, read_cgroup: CgroupReader) -> Self {
+        Self::with_readers(max_pids, read_cgroup, scan_cgroup_root)
+    }
+
+    pub(crate) fn with_readers(
+        max_pids: usize,
+        read_cgroup: CgroupReader,
+        scan_cgroups: CgroupScanner,
+    ) -> Self {
+        Self {
+            pids: RwLock::new(HashMap::new()),
+            max_pids,
+            read_cgroup,
+            proc_reads: AtomicU64::new(0),
+            cgroups: RwLock::new(CgroupIndex::default()),
+            scan_cgroups,
+            cgroup_scans: AtomicU64::new(0),
+        }
+    }
+
+    /// Update the map from one pipeline event.
+    pub fn observe(&self, event: &ProcessEvent) {
+        match event.event_type {
+            EVENT_FORK => {
+                let parent = self.read().get(&event.ppid).cloned();
+                match parent.or_else(|| self.cgroup_container(event.cgroup_id)) {
+                    Some(container) => self.insert(event.pid, container),
+                    // Unknown parent: resolve lazily on first lookup.
+                    None => {
+                        self.write().remove(&event.pid);
+                    }
+                }
+            }
+            EVENT_EXEC => {
+                let container = self
+                    .cgroup_container(event.cgroup_id)
+                    .unwrap_or_else(|| self.resolve(event.pid));
+                self.insert(event.pid, container);
+            }
+            EVENT_EXIT => {
+                self.write().remove(&event.pid);
+            }
+            _ => {}
+        }
+    }
+
+    /// Container ID of `pid`, or `None` for host processes.
+    pub fn container_id(&self, pid: u32) -> Option<Arc<str>> {
+        if let Some(known) = self.read().get(&pid) {
+            return known.clone();
+        }
+        let container = self.resolve(pid);
+        self.insert(pid, container.clone());
+        container
+    }
+
+    /// Container of the process an event was recorded in, from its cgroup
+    /// ID when that is known and its PID otherwise.
+    pub fn container_for_event(&self, event: &ProcessEvent) -> Option<Arc<str>> {
+        match self.cgroup_container(event.cgroup_id) {
+            Some(container) => container,
+            None => self.container_id(event.pid),
+        }
+    }
+
+    /// cgroup path, below the cgroup mount, of the process an event was
+    /// recorded in: from its cgroup ID when that is known and from
+    /// `/proc/<pid>/cgroup` otherwise. Not cached per PID.
+    pub fn cgroup_path_for_event(&self, event: &ProcessEvent) -> Option<Arc<str>> {
+        if let Some(entry) = self.cgroup_entry(event.cgroup_id) {
+            return Some(entry.path);
+        }
+        self.proc_reads.fetch_add(1, Ordering::Relaxed);
+        let content = (self.read_cgroup)(event.pid)?;
+        cgroup_v2_path(&content).map(Arc::from)
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `/proc/<pid>/cgroup` reads performed so far.
+    pub fn proc_reads(&self) -> u64 {
+        self.proc_reads.load(Ordering::Relaxed)
+    }
+
+    /// Walks of the cgroup mount performed so far.
+    pub fn cgroup_scans(&self) -> u64 {
+        self.cgroup_scans.load(Ordering::Relaxed)
+    }
+
+    /// Container of cgroup `id`: `Some(None)` for a cgroup outside any
+    /// container, `None` when the ID is 0 or not found under the cgroup
+    /// mount (cgroup v1, or a cgroup already removed).
+    fn cgroup_container(&self, id: u64) -> Option<Option<Arc<str>>> {
+        self.cgroup_entry(id).map(|entry| entry.container)
+    }
+
+    fn cgroup_entry(&self, id: u64) -> Option<CgroupEntry> {
+        if id == 0 {
+            return None;
+        }
+        if let Some(known) = self.cgroups_read().ids.get(&id) {
+            return Some(known.clone());
+        }
+        // Most likely a cgroup created since the last walk.
+        let mut index = self.cgroups.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(known) = index.ids.get(&id) {
+            return Some(known.clone());
+        }
+        if index
+            .scanned_at
+            .is_some_and(|at| at.elapsed() < MIN_RESCAN_INTERVAL)
+        {
+            return None;
+        }
+        self.cgroup_scans.fetch_add(1, Ordering::Relaxed);
+        index.ids = (self.scan_cgroups)()
+            .into_iter()
+            .map(|(id, path)| {
+                let entry = CgroupEntry {
+                    container: container_id_from_cgroup(&path).map(Arc::from),
+                    path: match path.strip_prefix(CGROUP_ROOT) {
+                        Some("") => Arc::from("/"),
+                        Some(below) => Arc::from(below),
+                        None => Arc::from(path.as_str()),
+                    },
+                };
+                (id, entry)
+            })
+            .collect();
+        index.scanned_at = Some(Instant::now());
+        index.ids.get(&id).cloned()
+    }
+
+    fn cgroups_read(&self) -> std::sync::RwLockReadGuard<'_, CgroupIndex> {
+        self.cgroups.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn resolve(&self, pid: u32) -> Option<Arc<str>> {
+        self.proc_reads.fetch_add(1, Ordering::Relaxed);
+        let content = (self.read_cgroup)(pid)?;
+        container_id_from_cgroup(&content).map(Arc::from)
+    }
+
+    fn insert(&self, pid: u32, container: Option<Arc<str>>) {
+        let mut pids = self.write();
+        // Exits normally keep this bounded; if they were lost, stop caching
+        // rather than grow without limit.
+        if pids.len() >= self.max_pids && !pids.contains_key(&pid) {
+            return;
+        }
+        pids.insert(pid, container);
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<u32, Option<Arc<str>>>> {
+        self.pids.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<u32, Option<Arc<str>>>> {
+        self.pids.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn read_proc_cgroup(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()
+}
+
+fn scan_cgroup_root() -> Vec<(u64, String)> {
+    let mut cgroups = Vec::new();
+    scan_cgroup_dir(Path::new(CGROUP_ROOT), 0, &mut cgroups);
+    cgroups
+}
+
+fn scan_cgroup_dir(dir: &Path, depth: usize, out: &mut Vec<(u64, String)>) {
+    use std::os::unix::fs::MetadataExt;
+    let Ok(meta) = std::fs::metadata(dir) else {
+        return;
+    };
+    out.push((meta.ino(), dir.to_string_lossy().into_owned()));
+    if depth == MAX_CGROUP_DEPTH {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            scan_cgroup_dir(&entry.path(), depth + 1, out);
+        }
+    }
+}
+
+/// The cgroup v2 path in `/proc/<pid>/cgroup` content (the `0::` line).
+pub fn cgroup_v2_path(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(str::trim_end)
+}
+
+/// Extract a 64-character container ID from `/proc/<pid>/cgroup` content,
+/// e.g. `0::/kubepods.slice/.../cri-containerd-<id>.scope`.
+pub fn container_id_from_cgroup(content: &str) -> Option<&str> {
+    content.lines().find_map(|line| {
+        let last = line.split('/').next_back()?;
+        let clean = last.trim_end_matches(".scope");
+        let id = match clean.rfind('-') {
+            Some(idx) => &clean[idx + 1..],
+            None => clean,
+        };
+        (id.len() == 64).then_some(id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    const CONTAINER: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    /// PIDs >= 1000 live in CONTAINER, lower PIDs on the host.
+    fn fake_cgroup(pid: u32) -> Option<String> {
+        Some(if pid >= 1000 {
+            format!("0::/kubepods.slice/cri-containerd-{CONTAINER}.scope\n")
+        } else {
+            "0::/system.slice/sshd.service\n".to_string()
+        })
+    }
+
+    const POD_CGROUP: u64 = 4242;
+
+    fn fake_scan() -> Vec<(u64, String)> {
+        vec![
+            (1, "/sys/fs/cgroup".to_string()),
+            (
+                POD_CGROUP,
+                format!("/sys/fs/cgroup/kubepods.slice/cri-containerd-{CONTAINER}.scope"),
+            ),
+        ]
+    }
+
+    fn event(event_type: u32, pid: u32, ppid: u32) -> ProcessEvent {
+        ProcessEvent::new(ProcessEventWire {
+            pid,
+            ppid,
+            uid: 0,
+            gid: 0,
+            event_type,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn parses_container_ids_from_cgroup_lines() {
+        let docker = format!("0::/system.slice/docker-{CONTAINER}.scope");
+        assert_eq!(container_id_from_cgroup(&docker), Some(CONTAINER));
+        let raw = format!("12:memory:/docker/{CONTAINER}\n");
+        assert_eq!(container_id_from_cgroup(&raw), Some(CONTAINER));
+        assert_eq!(container_id_from_cgroup("0::/user.slice"), None);
+    }
+
+    #[test]
+    fn lookups_are_cached_including_host_processes() {
+        let map = PidContainerMap::with_reader(16, fake_cgroup);
+        for _ in 0..5 {
+            assert_eq!(map.container_id(1500).as_deref(), Some(CONTAINER));
+            assert_eq!(map.container_id(10), None);
+        }
+        assert_eq!(map.proc_reads(), 2);
+    }
+
+    #[test]
+    fn fork_inherits_exec_rereads_exit_removes() {
+        let map = PidContainerMap::with_reader(16, fake_cgroup);
+        map.observe(&event(EVENT_EXEC, 1000, 1));
+        assert_eq!(map.proc_reads(), 1);
+
+        // The child of a container process inherits without a /proc read,
+        // even though the fake reader would place pid 5 on the host.
+        map.observe(&event(EVENT_FORK, 5, 1000));
+        assert_eq!(map.container_id(5).as_deref(), Some(CONTAINER));
+        assert_eq!(map.proc_reads(), 1);
+
+        // Exec re-resolves (e.g. runc init moving into the container).
+        map.observe(&event(EVENT_EXEC, 5, 1000));
+        assert_eq!(map.container_id(5), None);
+        assert_eq!(map.proc_reads(), 2);
+
+        map.observe(&event(EVENT_EXIT, 5, 1000));
+        map.observe(&event(EVENT_EXIT, 1000, 1));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn cgroup_ids_resolve_exited_processes() {
+        let map = PidContainerMap::with_readers(16, |_| None, fake_scan);
+        // The process is gone, so /proc has nothing; the cgroup ID still
+        // places it in the container.
+        let mut exec = event(EVENT_EXEC, 7, 1);
+        exec.cgroup_id = POD_CGROUP;
+        map.observe(&exec);
+        assert_eq!(map.container_id(7).as_deref(), Some(CONTAINER));
+        assert_eq!(map.proc_reads(), 0);
+
+        let mut other = event(3, 8, 1);
+        other.cgroup_id = POD_CGROUP;
+        assert_eq!(map.container_for_event(&other).as_deref(), Some(CONTAINER));
+        other.cgroup_id = 1;
+        assert_eq!(map.container_for_event(&other), None);
+        assert_eq!(map.proc_reads(), 0);
+        assert_eq!(map.cgroup_scans(), 1);
+
+        // Unknown IDs fall back to /proc, and don't rescan right away.
+        other.cgroup_id = 99;
+        assert_eq!(map.container_for_event(&other), None);
+        assert_eq!(map.proc_reads(), 1);
+        assert_eq!(map.cgroup_scans(), 1);
+    }
+
+    #[test]
+    fn cgroup_paths_are_relative_to_the_mount() {
+        let map = PidContainerMap::with_readers(16, fake_cgroup, fake_scan);
+        let mut pod = event(3, 1500, 1);
+        pod.cgroup_id = POD_CGROUP;
+        assert_eq!(
+            map.cgroup_path_for_event(&pod).as_deref(),
+            Some(format!("/kubepods.slice/cri-containerd-{CONTAINER}.scope").as_str())
+        );
+        pod.cgroup_id = 1;
+        assert_eq!(map.cgroup_path_for_event(&pod).as_deref(), Some("/"));
+        assert_eq!(map.proc_reads(), 0);
+
+        // No cgroup ID: read from /proc.
+        let host = event(3, 10, 1);
+        assert_eq!(
+            map.cgroup_path_for_event(&host).as_deref(),
+            Some("/system.slice/sshd.service")
+        );
+        assert_eq!(map.proc_reads(), 1);
+    }
+
+    #[test]
+    fn map_is_bounded() {
+        let map = PidContainerMap::with_reader(2, fake_cgroup);
+        for pid in 1000..1010 {
+            map.observe(&event(EVENT_EXEC, pid, 1));
+        }
+        assert_eq!(map.len(), 2);
+        // Uncached PIDs still resolve.
+        assert_eq!(map.container_id(1009).as_deref(), Some(CONTAINER));
+    }
+}
@@ -0,0 +1,559 @@
+//! Sandboxed WASM detector plugins.
+//!
+//! Every `*.wasm` file in `[plugins] dir` is loaded into its own interpreter
+//! instance (wasmi) with a fuel budget per event and a cap on linear memory,
+//! so a buggy or hostile module can neither stall the pipeline nor exhaust
+//! the host. Plugins see every event through a fixed binary record and raise
+//! alerts through host calls; those alerts go out like rule alerts.
+//!
+//! # ABI version 1
+//!
+//! The module must export:
+//!
+//! - `memory`
+//! - `linnix_abi_version() -> i32`, returning 1
+//! - `linnix_event_buffer() -> i32`, the address of an [`EVENT_RECORD_LEN`]-byte
+//!   buffer the host writes each event into
+//! - `linnix_on_event()`, called once per event
+//!
+//! and may export `linnix_event_mask() -> i64`: bit `n` set means "deliver
+//! events with `event_type == n`" (default: all).
+//!
+//! Event record, little-endian:
+//!
+//! | Offset | Type | Field |
+//! |--------|------|-------|
+//! | 0 | u32 | event_type |
+//! | 4 | u32 | pid |
+//! | 8 | u32 | ppid |
+//! | 12 | u32 | uid |
+//! | 16 | u32 | gid |
+//! | 20 | u32 | aux |
+//! | 24 | u64 | ts_ns |
+//! | 32 | u64 | seq |
+//! | 40 | u64 | exit_time_ns |
+//! | 48 | u64 | data |
+//! | 56 | u64 | data2 |
+//! | 64 | u16 | cpu_pct_milli |
+//! | 66 | u16 | mem_pct_milli |
+//! | 68 | u32 | aux2 |
+//! | 72 | [u8; 16] | comm (NUL-padded) |
+//! | 88 | u64 | cgroup_id (0 if unknown) |
+//!
+//! Host functions, imported from module `linnix`:
+//!
+//! - `emit_alert(severity: i32, rule_ptr: i32, rule_len: i32, msg_ptr: i32, msg_len: i32)`;
+//!   severity 0 = info, 1 = low, 2 = medium, 3 = high. The alert's rule is
+//!   `<plugin>/<rule>`.
+//! - `log(level: i32, ptr: i32, len: i32)`; level 0 = debug … 3 = error.
+
+use crate::alerts::{Alert, Severity, write_alert};
+use crate::config::PluginsConfig;
+use crate::handler::Handler;
+use crate::metrics::Metrics;
+use crate::{ProcessEvent, types::SystemSnapshot};
+use anyhow::{Context, anyhow, bail};
+use async_trait::async_trait;
+use log::{info, warn};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use wasmi::{
+    Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+pub const ABI_VERSION: i32 = 1;
+pub const EVENT_RECORD_LEN: usize = 96;
+
+/// Longest rule name / message / log line accepted from a plugin.
+const MAX_STRING_LEN: usize = 1024;
+/// Alerts a single `linnix_on_event` call may queue.
+const MAX_ALERTS_PER_CALL: usize = 16;
+const ALERT_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+struct HostState {
+    plugin: String,
+    limits: StoreLimits,
+    pending: Vec<(Severity, String, String)>,
+}
+
+struct Plugin {
+    name: String,
+    store: Store<HostState>,
+    memory: Memory,
+    buffer: usize,
+    mask: u64,
+    on_event: TypedFunc<(), ()>,
+    consecutive_traps: u32,
+    disabled: bool,
+    recent_alerts: VecDeque<Instant>,
+}
+
+pub struct PluginHost {
+    plugins: Vec<Mutex<Plugin>>,
+    cfg: PluginsConfig,
+    tx: broadcast::Sender<Alert>,
+    alerts_file: String,
+    journald: bool,
+    host: String,
+    metrics: Arc<Metrics>,
+}
+
+impl PluginHost {
+    /// Load every `*.wasm` module in `cfg.dir`. A module that fails to load
+    /// is logged and skipped.
+    pub fn load(
+        cfg: PluginsConfig,
+        tx: broadcast::Sender<Alert>,
+        alerts_file: String,
+        journald: bool,
+        metrics: Arc<Metrics>,
+    ) -> anyhow::Result<Self> {
+        let mut paths: Vec<_> = std::fs::read_dir(&cfg.dir)
+            .with_context(|| format!("failed to read plugin directory {}", cfg.dir))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "wasm"))
+            .collect();
+        paths.sort();
+
+        let engine = engine();
+        let mut plugins = Vec::new();
+        for path in paths {
+            match std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| instantiate(&engine, &cfg, plugin_name(&path), &bytes))
+            {
+                Ok(plugin) => {
+                    info!("[plugins] loaded '{}' from {}", plugin.name, path.display());
+                    plugins.push(Mutex::new(plugin));
+                }
+                Err(e) => warn!("[plugins] skipping {}: {e:#}", path.display()),
+            }
+        }
+
+        Ok(Self {
+            plugins,
+            cfg,
+            tx,
+            alerts_file,
+            journald,
+            host: std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".into()),
+            metrics,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    fn emit(&self, plugin: &str, severity: Severity, rule: String, message: String) {
+        let alert = Alert {
+            rule: format!("{plugin}/{rule}"),
+            severity,
+            message,
+            host: self.host.clone(),
+            resolved_after_secs: None,
+            labels: [("plugin".to_string(), plugin.to_string())].into(),
+        };
+        info!(
+            "[plugins] emitting alert rule={} severity={} message={}",
+            alert.rule,
+            alert.severity.as_str(),
+            alert.message
+        );
+        write_alert(&self.alerts_file, self.journald, &alert);
+        let _ = self.tx.send(alert);
+        self.metrics.inc_alerts_emitted();
+    }
+}
+
+#[async_trait]
+impl Handler for PluginHost {
+    fn name(&self) -> &'static str {
+        "plugins"
+    }
+
+    async fn on_event(&self, event: &ProcessEvent) -> anyhow::Result<()> {
+        let record = encode_event(event);
+        let mut trapped = Vec::new();
+        for slot in &self.plugins {
+            let (name, alerts) = {
+                let mut plugin = slot.lock().unwrap_or_else(|e| e.into_inner());
+                if plugin.disabled
+                    || event.event_type >= 64
+                    || plugin.mask & (1 << event.event_type) == 0
+                {
+                    continue;
+                }
+                if let Err(e) = plugin.call(&record, self.cfg.fuel_per_event) {
+                    plugin.consecutive_traps += 1;
+                    if plugin.consecutive_traps >= self.cfg.max_consecutive_traps {
+                        plugin.disabled = true;
+                        warn!(
+                            "[plugins] '{}' disabled after {} consecutive traps; last: {e}",
+                            plugin.name, plugin.consecutive_traps
+                        );
+                    }
+                    trapped.push(format!("{}: {e}", plugin.name));
+                    continue;
+                }
+                plugin.consecutive_traps = 0;
+                let alerts = plugin.take_alerts(self.cfg.max_alerts_per_minute);
+                (plugin.name.clone(), alerts)
+            };
+            for (severity, rule, message) in alerts {
+                self.emit(&name, severity, rule, message);
+            }
+        }
+        if trapped.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("plugin trapped: {}", trapped.join("; ")))
+        }
+    }
+
+    async fn on_snapshot(&self, _snapshot: &SystemSnapshot) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl Plugin {
+    fn call(&mut self, record: &[u8; EVENT_RECORD_LEN], fuel: u64) -> anyhow::Result<()> {
+        self.memory
+            .write(&mut self.store, self.buffer, record)
+            .map_err(|e| anyhow!("event buffer out of bounds: {e}"))?;
+        self.store.set_fuel(fuel).map_err(|e| anyhow!("{e}"))?;
+        self.on_event.call(&mut self.store, ())?;
+        Ok(())
+    }
+
+    /// Drain alerts queued by the last call, dropping any over the per-minute
+    /// budget.
+    fn take_alerts(&mut self, per_minute: usize) -> Vec<(Severity, String, String)> {
+        let pending = std::mem::take(&mut self.store.data_mut().pending);
+        if pending.is_empty() {
+            return pending;
+        }
+        let now = Instant::now();
+        while self
+            .recent_alerts
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= ALERT_RATE_WINDOW)
+        {
+            self.recent_alerts.pop_front();
+        }
+        let budget = per_minute.saturating_sub(self.recent_alerts.len());
+        if pending.len() > budget {
+            warn!(
+                "[plugins] '{}' exceeded {per_minute} alerts/min; dropping {}",
+                self.name,
+                pending.len() - budget
+            );
+        }
+        let allowed: Vec<_> = pending.into_iter().take(budget).collect();
+        self.recent_alerts
+            .extend(std::iter::repeat_n(now, allowed.len()));
+        allowed
+    }
+}
+
+fn engine() -> Engine {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    Engine::new(&config)
+}
+
+fn plugin_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "plugin".into())
+}
+
+fn instantiate(
+    engine: &Engine,
+    cfg: &PluginsConfig,
+    name: String,
+    wasm: &[u8],
+) -> anyhow::Result<Plugin> {
+    let module = Module::new(engine, wasm)?;
+    let state = HostState {
+        plugin: name.clone(),
+        limits: StoreLimitsBuilder::new()
+            .memory_size(cfg.max_memory_mb.saturating_mul(1024 * 1024))
+            .instances(1)
+            .build(),
+        pending: Vec::new(),
+    };
+    let mut store = Store::new(engine, state);
+    store.limiter(|state| &mut state.limits);
+    // Start functions and the setup exports below run under the same budget.
+    store
+        .set_fuel(cfg.fuel_per_event)
+        .map_err(|e| anyhow!("{e}"))?;
+
+    let mut linker = Linker::<HostState>::new(engine);
+    linker.func_wrap(
+        "linnix",
+        "emit_alert",
+        |mut caller: Caller<'_, HostState>,
+         severity: i32,
+         rule_ptr: i32,
+         rule_len: i32,
+         msg_ptr: i32,
+         msg_len: i32| {
+            let (Some(rule), Some(message)) = (
+                read_string(&caller, rule_ptr, rule_len),
+                read_string(&caller, msg_ptr, msg_len),
+            ) else {
+                return;
+            };
+            let pending = &mut caller.data_mut().pending;
+            if pending.len() < MAX_ALERTS_PER_CALL {
+                pending.push((severity_from_abi(severity), rule, message));
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "linnix",
+        "log",
+        |caller: Caller<'_, HostState>, level: i32, ptr: i32, len: i32| {
+            if let Some(line) = read_string(&caller, ptr, len) {
+                let level = match level {
+                    0 => log::Level::Debug,
+                    1 => log::Level::Info,
+                    2 => log::Level::Warn,
+                    _ => log::Level::Error,
+                };
+                log::log!(level, "[plugins:{}] {line}", caller.data().plugin);
+            }
+        },
+    )?;
+
+    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+    let memory = instance
+        .get_memory(&store, "memory")
+        .ok_or_else(|| anyhow!("module does not export 'memory'"))?;
+    let version = instance
+        .get_typed_func::<(), i32>(&store, "linnix_abi_version")
+        .context("missing export 'linnix_abi_version'")?
+        .call(&mut store, ())?;
+    if version != ABI_VERSION {
+        bail!("unsupported ABI version {version} (host supports {ABI_VERSION})");
+    }
+    let buffer = instance
+        .get_typed_func::<(), i32>(&store, "linnix_event_buffer")
+        .context("missing export 'linnix_event_buffer'")?
+        .call(&mut store, ())? as u32 as usize;
+    if buffer + EVENT_RECORD_LEN > memory.data(&store).len() {
+        bail!("event buffer at {buffer} is outside linear memory");
+    }
+    let mask = match instance.get_typed_func::<(), i64>(&store, "linnix_event_mask") {
+        Ok(f) => f.call(&mut store, ())? as u64,
+        Err(_) => u64::MAX,
+    };
+    let on_event = instance
+        .get_typed_func::<(), ()>(&store, "linnix_on_event")
+        .context("missing export 'linnix_on_event'")?;
+
+    Ok(Plugin {
+        name,
+        store,
+        memory,
+        buffer,
+        mask,
+        on_event,
+        consecutive_traps: 0,
+        disabled: false,
+        recent_alerts: VecDeque::new(),
+    })
+}
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return None;
+    };
+    let start = ptr as u32 as usize;
+    let len = (len as u32 as usize).min(MAX_STRING_LEN);
+    let bytes = memory.data(caller).get(start..start.checked_add(len)?)?;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn severity_from_abi(level: i32) -> Severity {
+    match level {
+        1 => Severity::Low,
+        2 => Severity::Medium,
+        3 => Severity::High,
+        _ => Severity::Info,
+    }
+}
+
+/// ABI v1 event record; see the module docs for the layout.
+fn encode_event(event: &ProcessEvent) -> [u8; EVENT_RECORD_LEN] {
+    let mut out = [0u8; EVENT_RECORD_LEN];
+    let mut put = |offset: usize, bytes: &[u8]| {
+        out[offset..offset + bytes.len()].copy_from_slice(bytes);
+    };
+    put(0, &event.event_type.to_le_bytes());
+    put(4, &event.pid.to_le_bytes());
+    put(8, &event.ppid.to_le_bytes());
+    put(12, &event.uid.to_le_bytes());
+    put(16, &event.gid.to_le_bytes());
+    put(20, &event.aux.to_le_bytes());
+    put(24, &event.ts_ns.to_le_bytes());
+    put(32, &event.seq.to_le_bytes());
+    put(40, &event.exit_time_ns.to_le_bytes());
+    put(48, &event.data.to_le_bytes());
+    put(56, &event.data2.to_le_bytes());
+    put(64, &event.cpu_pct_milli.to_le_bytes());
+    put(66, &event.mem_pct_milli.to_le_bytes());
+    put(68, &event.aux2.to_le_bytes());
+    put(72, &event.comm);
+    put(88, &event.cgroup_id.to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    /// Alerts on every exec of pid 42; ignores forks via the event mask.
+    const PID_WATCH: &str = r#"
+        (module
+          (import "linnix" "emit_alert" (func $alert (param i32 i32 i32 i32 i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 200) "pid42")
+          (data (i32.const 220) "pid 42 exec'd")
+          (func (export "linnix_abi_version") (result i32) i32.const 1)
+          (func (export "linnix_event_buffer") (result i32) i32.const 0)
+          (func (export "linnix_event_mask") (result i64) i64.const 1)
+          (func (export "linnix_on_event")
+            (if (i32.eq (i32.load (i32.const 4)) (i32.const 42))
+              (then (call $alert (i32.const 3) (i32.const 200) (i32.const 5)
+                                 (i32.const 220) (i32.const 13))))))
+    "#;
+
+    const SPIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "linnix_abi_version") (result i32) i32.const 1)
+          (func (export "linnix_event_buffer") (result i32) i32.const 0)
+          (func (export "linnix_on_event") (loop $l (br $l))))
+    "#;
+
+    fn event(event_type: u32, pid: u32) -> ProcessEvent {
+        ProcessEvent::new(ProcessEventWire {
+            pid,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type,
+            ts_ns: 0,
+            seq: 0,
+            comm: *b"worker\0\0\0\0\0\0\0\0\0\0",
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    fn host(
+        modules: &[(&str, &str)],
+        cfg: PluginsConfig,
+    ) -> (PluginHost, broadcast::Receiver<Alert>) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, wat) in modules {
+            std::fs::write(
+                dir.path().join(format!("{name}.wasm")),
+                wat::parse_str(wat).unwrap(),
+            )
+            .unwrap();
+        }
+        let (tx, rx) = broadcast::channel(16);
+        let host = PluginHost::load(
+            PluginsConfig {
+                dir: dir.path().display().to_string(),
+                ..cfg
+            },
+            tx,
+            "/dev/null".into(),
+            false,
+            Arc::new(Metrics::new()),
+        )
+        .unwrap();
+        (host, rx)
+    }
+
+    #[tokio::test]
+    async fn plugin_alerts_on_matching_events() {
+        let (host, mut rx) = host(&[("pidwatch", PID_WATCH)], PluginsConfig::default());
+        assert_eq!(host.len(), 1);
+
+        host.on_event(&event(0, 7)).await.unwrap();
+        host.on_event(&event(1, 42)).await.unwrap(); // fork: masked out
+        assert!(rx.try_recv().is_err());
+
+        host.on_event(&event(0, 42)).await.unwrap();
+        let alert = rx.try_recv().unwrap();
+        assert_eq!(alert.rule, "pidwatch/pid42");
+        assert_eq!(alert.message, "pid 42 exec'd");
+        assert_eq!(alert.severity, Severity::High);
+    }
+
+    #[tokio::test]
+    async fn alerts_are_rate_limited_per_plugin() {
+        let cfg = PluginsConfig {
+            max_alerts_per_minute: 2,
+            ..Default::default()
+        };
+        let (host, mut rx) = host(&[("pidwatch", PID_WATCH)], cfg);
+        for _ in 0..5 {
+            host.on_event(&event(0, 42)).await.unwrap();
+        }
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn runaway_plugin_is_stopped_and_disabled() {
+        let cfg = PluginsConfig {
+            fuel_per_event: 10_000,
+            max_consecutive_traps: 2,
+            ..Default::default()
+        };
+        let (host, mut rx) = host(&[("a_spin", SPIN), ("pidwatch", PID_WATCH)], cfg);
+        assert!(host.on_event(&event(0, 42)).await.is_err());
+        assert!(host.on_event(&event(0, 42)).await.is_err());
+        // Disabled now; the other plugin kept running throughout.
+        host.on_event(&event(0, 42)).await.unwrap();
+        assert_eq!(std::iter::from_fn(|| rx.try_recv().ok()).count(), 3);
+    }
+
+    #[test]
+    fn invalid_modules_are_skipped() {
+        let too_big = r#"(module (memory (export "memory") 1000))"#;
+        let wrong_abi = r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "linnix_abi_version") (result i32) i32.const 2))
+        "#;
+        let (host, _rx) = host(
+            &[("big", too_big), ("future", wrong_abi)],
+            PluginsConfig::default(),
+        );
+        assert!(host.is_empty());
+    }
+}
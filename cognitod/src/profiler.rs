@@ -0,0 +1,719 @@
+//! Sampled stack profiles for CPU-spin incidents.
+//!
+//! When a process is caught spinning on CPU we take a few seconds of
+//! perf-style samples from each of its threads (`perf_event_open` on the CPU
+//! clock with user callchains), symbolize them against the ELF symbol tables
+//! of the mapped binaries, and keep the result in folded-stack form — the
+//! input format of `flamegraph.pl` and `inferno-flamegraph`. That turns
+//! "cpu pegged" into "pegged in function X".
+//!
+//! User callchains rely on frame pointers. Binaries built without them
+//! usually yield only the leaf frame, which still names the hot function.
+
+use crate::config::ProfilerConfig;
+use crate::context::ContextStore;
+use crate::incidents::IncidentStore;
+use crate::insights::InsightRecord;
+use crate::schema::InsightReason;
+use crate::topology::{CpuTopology, Placement};
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
+use object::{Object, ObjectSegment, ObjectSymbol, SymbolKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Don't profile the same process again within this window.
+const REPROFILE_COOLDOWN: Duration = Duration::from_secs(60);
+/// How far before a cpu_spin insight its incident may have been recorded.
+const INCIDENT_LOOKBACK: Duration = Duration::from_secs(300);
+/// Data pages per thread ring buffer (must be a power of two).
+const RING_DATA_PAGES: usize = 64;
+const DRAIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// One distinct stack, root first, separated by `;`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoldedStack {
+    pub stack: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StackProfile {
+    pub pid: u32,
+    pub comm: String,
+    pub duration_ms: u64,
+    pub frequency_hz: u64,
+    pub samples: u64,
+    /// Samples the kernel dropped because a ring buffer was full.
+    pub lost: u64,
+    /// Most frequent stacks, highest count first.
+    pub stacks: Vec<FoldedStack>,
+    /// Leaf functions by self samples, highest first.
+    pub top_functions: Vec<FoldedStack>,
+    /// CPUs/NUMA nodes the threads ran on when sampling ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placement: Option<Placement>,
+}
+
+impl StackProfile {
+    /// Build a profile from raw samples (frames leaf first).
+    pub fn from_samples(
+        pid: u32,
+        comm: &str,
+        duration_ms: u64,
+        frequency_hz: u64,
+        samples: &[Vec<String>],
+        lost: u64,
+        max_stacks: usize,
+    ) -> Self {
+        let mut stacks: HashMap<String, u64> = HashMap::new();
+        let mut leaves: HashMap<String, u64> = HashMap::new();
+        for frames in samples {
+            let Some(leaf) = frames.first() else {
+                continue;
+            };
+            *leaves.entry(leaf.clone()).or_default() += 1;
+            let mut folded = comm.to_string();
+            for frame in frames.iter().rev() {
+                folded.push(';');
+                folded.push_str(frame);
+            }
+            *stacks.entry(folded).or_default() += 1;
+        }
+
+        Self {
+            pid,
+            comm: comm.to_string(),
+            duration_ms,
+            frequency_hz,
+            samples: samples.len() as u64,
+            lost,
+            stacks: ranked(stacks, max_stacks),
+            top_functions: ranked(leaves, 10),
+            placement: None,
+        }
+    }
+
+    /// Folded-stack text (`a;b;c 42` per line) for flamegraph tooling.
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for s in &self.stacks {
+            out.push_str(&format!("{} {}\n", s.stack, s.count));
+        }
+        out
+    }
+
+    /// One-line human summary, e.g. "hot in parse_row (62% of 297 samples)",
+    /// followed by a placement hint such as "all 8 threads on NUMA node 0".
+    pub fn summary(&self) -> String {
+        let summary = match self.top_functions.first() {
+            Some(top) if self.samples > 0 => format!(
+                "hot in {} ({:.0}% of {} samples)",
+                top.stack,
+                top.count as f64 * 100.0 / self.samples as f64,
+                self.samples
+            ),
+            _ => "no samples captured".to_string(),
+        };
+        match self.placement.as_ref().and_then(|p| p.hint.as_deref()) {
+            Some(hint) => format!("{summary}; {hint}"),
+            None => summary,
+        }
+    }
+}
+
+fn ranked(counts: HashMap<String, u64>, limit: usize) -> Vec<FoldedStack> {
+    let mut out: Vec<FoldedStack> = counts
+        .into_iter()
+        .map(|(stack, count)| FoldedStack { stack, count })
+        .collect();
+    out.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.stack.cmp(&b.stack)));
+    out.truncate(limit);
+    out
+}
+
+/// Sample `pid` for the configured duration.
+pub async fn capture(pid: u32, cfg: &ProfilerConfig) -> Result<StackProfile> {
+    let cfg = cfg.clone();
+    tokio::task::spawn_blocking(move || capture_blocking(pid, &cfg))
+        .await
+        .context("profiler task panicked")?
+}
+
+fn capture_blocking(pid: u32, cfg: &ProfilerConfig) -> Result<StackProfile> {
+    let comm = fs::read_to_string(format!("/proc/{pid}/comm"))
+        .with_context(|| format!("process {pid} not found"))?
+        .trim()
+        .to_string();
+    let maps = fs::read_to_string(format!("/proc/{pid}/maps"))
+        .map(|m| parse_maps(&m))
+        .unwrap_or_default();
+
+    let mut rings = Vec::new();
+    let mut last_err = None;
+    for tid in thread_ids(pid).into_iter().take(cfg.max_threads) {
+        match PerfRing::open(tid, cfg.frequency_hz) {
+            Ok(ring) => rings.push(ring),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    if rings.is_empty() {
+        match last_err {
+            Some(e) => bail!(
+                "perf_event_open failed for pid {pid}: {e} (needs CAP_PERFMON or kernel.perf_event_paranoid <= 1)"
+            ),
+            None => bail!("process {pid} has no threads"),
+        }
+    }
+
+    let duration = Duration::from_millis(cfg.duration_ms);
+    let started = Instant::now();
+    let mut chains: Vec<Vec<u64>> = Vec::new();
+    let mut lost = 0;
+    while started.elapsed() < duration {
+        std::thread::sleep(DRAIN_INTERVAL);
+        for ring in &mut rings {
+            ring.drain(&mut chains, &mut lost);
+        }
+    }
+    for ring in &mut rings {
+        ring.disable();
+        ring.drain(&mut chains, &mut lost);
+    }
+    drop(rings);
+
+    let mut symbolizer = Symbolizer::new(pid, maps);
+    let samples: Vec<Vec<String>> = chains
+        .iter()
+        .map(|chain| chain.iter().map(|&ip| symbolizer.resolve(ip)).collect())
+        .collect();
+
+    let mut profile = StackProfile::from_samples(
+        pid,
+        &comm,
+        cfg.duration_ms,
+        cfg.frequency_hz,
+        &samples,
+        lost,
+        cfg.max_stacks,
+    );
+    profile.placement = crate::topology::placement(pid, &CpuTopology::read());
+    Ok(profile)
+}
+
+fn thread_ids(pid: u32) -> Vec<i32> {
+    let Ok(dir) = fs::read_dir(format!("/proc/{pid}/task")) else {
+        return Vec::new();
+    };
+    let mut tids: Vec<i32> = dir
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.parse().ok())
+        .collect();
+    tids.sort_unstable();
+    tids
+}
+
+// ── perf_event_open ─────────────────────────────────────────────────────────
+
+const PERF_TYPE_SOFTWARE: u32 = 1;
+const PERF_COUNT_SW_CPU_CLOCK: u64 = 0;
+const PERF_SAMPLE_IP: u64 = 1 << 0;
+const PERF_SAMPLE_TID: u64 = 1 << 1;
+const PERF_SAMPLE_CALLCHAIN: u64 = 1 << 5;
+const ATTR_EXCLUDE_KERNEL: u64 = 1 << 5;
+const ATTR_EXCLUDE_HV: u64 = 1 << 6;
+const ATTR_FREQ: u64 = 1 << 10;
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+const PERF_RECORD_LOST: u32 = 2;
+const PERF_RECORD_SAMPLE: u32 = 9;
+/// Callchain entries at or above this value are context markers.
+const PERF_CONTEXT_MAX: u64 = -4095i64 as u64;
+/// Offsets of `data_head` / `data_tail` in `struct perf_event_mmap_page`.
+const DATA_HEAD_OFFSET: usize = 1024;
+const DATA_TAIL_OFFSET: usize = 1032;
+
+/// `struct perf_event_attr` up to PERF_ATTR_SIZE_VER5.
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_freq: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+    config2: u64,
+    branch_sample_type: u64,
+    sample_regs_user: u64,
+    sample_stack_user: u32,
+    clockid: i32,
+    sample_regs_intr: u64,
+    aux_watermark: u32,
+    sample_max_stack: u16,
+    reserved: u16,
+}
+
+struct PerfRing {
+    fd: OwnedFd,
+    mmap: memmap2::MmapMut,
+    page_size: usize,
+}
+
+impl PerfRing {
+    fn open(tid: i32, frequency_hz: u64) -> io::Result<Self> {
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_SOFTWARE,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config: PERF_COUNT_SW_CPU_CLOCK,
+            sample_freq: frequency_hz,
+            sample_type: PERF_SAMPLE_IP | PERF_SAMPLE_TID | PERF_SAMPLE_CALLCHAIN,
+            flags: ATTR_EXCLUDE_KERNEL | ATTR_EXCLUDE_HV | ATTR_FREQ,
+            ..Default::default()
+        };
+        // SAFETY: attr is a valid, fully initialised perf_event_attr.
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                tid,
+                -1i32,
+                -1i32,
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the syscall returned a new descriptor that we now own.
+        let fd = unsafe { OwnedFd::from_raw_fd(ret as i32) };
+
+        // SAFETY: sysconf has no preconditions.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        // SAFETY: the kernel owns this mapping's layout; we only read the
+        // header page and data area and write data_tail as documented.
+        let mmap = unsafe {
+            memmap2::MmapOptions::new()
+                .len(page_size * (1 + RING_DATA_PAGES))
+                .map_mut(&fd)?
+        };
+        Ok(Self {
+            fd,
+            mmap,
+            page_size,
+        })
+    }
+
+    fn disable(&self) {
+        // SAFETY: fd is a valid perf event descriptor.
+        unsafe {
+            libc::ioctl(self.fd.as_raw_fd(), PERF_EVENT_IOC_DISABLE, 0);
+        }
+    }
+
+    /// Consume all complete records, appending callchains (leaf first).
+    fn drain(&mut self, out: &mut Vec<Vec<u64>>, lost: &mut u64) {
+        let base = self.mmap.as_mut_ptr();
+        // SAFETY: both offsets lie inside the first (header) page.
+        let head = unsafe { std::ptr::read_volatile(base.add(DATA_HEAD_OFFSET) as *const u64) };
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Acquire);
+        let mut tail = unsafe { std::ptr::read_volatile(base.add(DATA_TAIL_OFFSET) as *const u64) };
+
+        let data = &self.mmap[self.page_size..];
+        let size = data.len() as u64;
+        while tail + 8 <= head {
+            let header = ring_bytes(data, tail, 8);
+            let kind = u32::from_ne_bytes(header[0..4].try_into().unwrap());
+            let len = u16::from_ne_bytes(header[6..8].try_into().unwrap()) as u64;
+            if len < 8 || tail + len > head || len > size {
+                break;
+            }
+            let record = ring_bytes(data, tail + 8, (len - 8) as usize);
+            match kind {
+                PERF_RECORD_SAMPLE => {
+                    if let Some(chain) = parse_sample(&record) {
+                        out.push(chain);
+                    }
+                }
+                PERF_RECORD_LOST if record.len() >= 16 => {
+                    *lost += u64::from_ne_bytes(record[8..16].try_into().unwrap());
+                }
+                _ => {}
+            }
+            tail += len;
+        }
+
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Release);
+        // SAFETY: data_tail is the one header field userspace may write.
+        unsafe {
+            std::ptr::write_volatile(base.add(DATA_TAIL_OFFSET) as *mut u64, tail);
+        }
+    }
+}
+
+/// Copy `len` bytes starting at ring offset `pos`, handling wrap-around.
+fn ring_bytes(data: &[u8], pos: u64, len: usize) -> Vec<u8> {
+    let size = data.len();
+    let start = (pos % size as u64) as usize;
+    let mut out = Vec::with_capacity(len);
+    let first = len.min(size - start);
+    out.extend_from_slice(&data[start..start + first]);
+    out.extend_from_slice(&data[..len - first]);
+    out
+}
+
+/// Body of a PERF_RECORD_SAMPLE with IP | TID | CALLCHAIN.
+fn parse_sample(body: &[u8]) -> Option<Vec<u64>> {
+    let word = |i: usize| -> Option<u64> {
+        body.get(i * 8..i * 8 + 8)
+            .map(|b| u64::from_ne_bytes(b.try_into().unwrap()))
+    };
+    let ip = word(0)?;
+    // word(1) is pid/tid
+    let nr = word(2)? as usize;
+    let mut chain: Vec<u64> = (0..nr)
+        .filter_map(|i| word(3 + i))
+        .filter(|&addr| addr < PERF_CONTEXT_MAX && addr != 0)
+        .collect();
+    if chain.is_empty() && ip != 0 {
+        chain.push(ip);
+    }
+    (!chain.is_empty()).then_some(chain)
+}
+
+// ── symbolization ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Mapping {
+    start: u64,
+    end: u64,
+    offset: u64,
+    path: String,
+}
+
+pub(crate) fn parse_maps(text: &str) -> Vec<Mapping> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let (start, end) = parts.next()?.split_once('-')?;
+            let perms = parts.next()?;
+            if !perms.contains('x') {
+                return None;
+            }
+            let offset = parts.next()?;
+            let path = parts.nth(2)?;
+            if !path.starts_with('/') {
+                return None;
+            }
+            Some(Mapping {
+                start: u64::from_str_radix(start, 16).ok()?,
+                end: u64::from_str_radix(end, 16).ok()?,
+                offset: u64::from_str_radix(offset, 16).ok()?,
+                path: path.to_string(),
+            })
+        })
+        .collect()
+}
+
+struct ElfSymbols {
+    /// (file offset, virtual address, file size) of loadable segments.
+    segments: Vec<(u64, u64, u64)>,
+    /// (address, size, name) sorted by address.
+    symbols: Vec<(u64, u64, String)>,
+}
+
+impl ElfSymbols {
+    fn load(data: &[u8]) -> Option<Self> {
+        let file = object::File::parse(data).ok()?;
+        let segments = file
+            .segments()
+            .map(|s| {
+                let (off, size) = s.file_range();
+                (off, s.address(), size)
+            })
+            .collect();
+        let mut symbols: Vec<(u64, u64, String)> = file
+            .symbols()
+            .chain(file.dynamic_symbols())
+            .filter(|s| s.kind() == SymbolKind::Text && s.address() != 0)
+            .filter_map(|s| Some((s.address(), s.size(), s.name().ok()?.to_string())))
+            .collect();
+        symbols.sort_by_key(|s| s.0);
+        symbols.dedup_by_key(|s| s.0);
+        Some(Self { segments, symbols })
+    }
+
+    fn lookup(&self, file_offset: u64) -> Option<&str> {
+        let vaddr = self
+            .segments
+            .iter()
+            .find(|(off, _, size)| file_offset >= *off && file_offset < off + size)
+            .map(|(off, addr, _)| addr + (file_offset - off))?;
+        let idx = self
+            .symbols
+            .partition_point(|s| s.0 <= vaddr)
+            .checked_sub(1)?;
+        let (addr, size, name) = &self.symbols[idx];
+        (*size == 0 || vaddr < addr + size).then_some(name.as_str())
+    }
+}
+
+pub(crate) struct Symbolizer {
+    pid: u32,
+    maps: Vec<Mapping>,
+    elves: HashMap<String, Option<ElfSymbols>>,
+    resolved: HashMap<u64, String>,
+}
+
+impl Symbolizer {
+    pub(crate) fn new(pid: u32, maps: Vec<Mapping>) -> Self {
+        Self {
+            pid,
+            maps,
+            elves: HashMap::new(),
+            resolved: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn resolve(&mut self, ip: u64) -> String {
+        if let Some(name) = self.resolved.get(&ip) {
+            return name.clone();
+        }
+        let name = self.resolve_uncached(ip);
+        self.resolved.insert(ip, name.clone());
+        name
+    }
+
+    fn resolve_uncached(&mut self, ip: u64) -> String {
+        let Some(map) = self
+            .maps
+            .iter()
+            .find(|m| ip >= m.start && ip < m.end)
+            .cloned()
+        else {
+            return "[unknown]".to_string();
+        };
+        let file_offset = ip - map.start + map.offset;
+        let pid = self.pid;
+        let elf = self.elves.entry(map.path.clone()).or_insert_with(|| {
+            // Go through the process root so containerised binaries resolve.
+            fs::read(format!("/proc/{pid}/root{}", map.path))
+                .or_else(|_| fs::read(&map.path))
+                .ok()
+                .and_then(|data| ElfSymbols::load(&data))
+        });
+        let module = map.path.rsplit('/').next().unwrap_or(&map.path);
+        match elf.as_ref().and_then(|e| e.lookup(file_offset)) {
+            Some(sym) => format!("{:#}", rustc_demangle::demangle(sym)),
+            None => format!("[{module}+{file_offset:#x}]"),
+        }
+    }
+}
+
+// ── incident integration ────────────────────────────────────────────────────
+
+/// Capture a profile of `pid` and attach it to incident `id`.
+pub async fn profile_incident(store: &IncidentStore, id: i64, pid: u32, cfg: &ProfilerConfig) {
+    match capture(pid, cfg).await {
+        Ok(profile) => {
+            info!(
+                "[profiler] incident #{id}: {} {}",
+                profile.comm,
+                profile.summary()
+            );
+            match serde_json::to_string(&profile) {
+                Ok(json) => {
+                    if let Err(e) = store.attach_profile(id, &json).await {
+                        warn!("[profiler] failed to store profile for incident #{id}: {e}");
+                    }
+                }
+                Err(e) => warn!("[profiler] failed to encode profile: {e}"),
+            }
+        }
+        Err(e) => debug!("[profiler] incident #{id}: no profile for pid {pid}: {e}"),
+    }
+}
+
+/// Pick the process a cpu_spin insight refers to: a PID, a process name
+/// (highest-CPU match wins), or failing that the top CPU consumer.
+fn resolve_target(primary: Option<&str>, context: &ContextStore) -> Option<(u32, String)> {
+    let top = context.top_cpu_processes(usize::MAX);
+    if let Some(p) = primary.map(str::trim).filter(|p| !p.is_empty()) {
+        if let Ok(pid) = p.parse::<u32>() {
+            let comm = top
+                .iter()
+                .find(|t| t.pid == pid)
+                .map(|t| t.comm.clone())
+                .unwrap_or_default();
+            return Some((pid, comm));
+        }
+        if let Some(t) = top.iter().find(|t| t.comm == p) {
+            return Some((t.pid, t.comm.clone()));
+        }
+    }
+    top.first().map(|t| (t.pid, t.comm.clone()))
+}
+
+/// Attach a stack profile to the incident a CPU-spin insight is about: the
+/// newest unprofiled incident targeting the same process.
+pub async fn run_cpu_spin_profiler(
+    mut rx: broadcast::Receiver<InsightRecord>,
+    context: Arc<ContextStore>,
+    store: Arc<IncidentStore>,
+    cfg: ProfilerConfig,
+) {
+    info!(
+        "[profiler] profiling cpu_spin targets for {}ms at {}Hz",
+        cfg.duration_ms, cfg.frequency_hz
+    );
+    let mut last_profiled: HashMap<u32, Instant> = HashMap::new();
+
+    loop {
+        let record = match rx.recv().await {
+            Ok(record) => record,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!("[profiler] lagged by {n} insights");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if record.insight.reason_code != InsightReason::CpuSpin {
+            continue;
+        }
+        let Some((pid, comm)) = resolve_target(record.insight.primary_process.as_deref(), &context)
+        else {
+            debug!(
+                "[profiler] cpu_spin insight {} has no target",
+                record.insight.id
+            );
+            continue;
+        };
+        last_profiled.retain(|_, t| t.elapsed() < REPROFILE_COOLDOWN);
+        if last_profiled.contains_key(&pid) {
+            continue;
+        }
+
+        let since = record.timestamp as i64 - INCIDENT_LOOKBACK.as_secs() as i64;
+        match store.unprofiled_for_pid(pid, since).await {
+            Ok(Some(id)) => {
+                last_profiled.insert(pid, Instant::now());
+                let store = Arc::clone(&store);
+                let cfg = cfg.clone();
+                tokio::spawn(async move { profile_incident(&store, id, pid, &cfg).await });
+            }
+            Ok(None) => debug!(
+                "[profiler] cpu_spin insight {}: no incident for {comm} (pid {pid})",
+                record.insight.id
+            ),
+            Err(e) => warn!("[profiler] failed to look up incident for pid {pid}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn samples_fold_root_first_and_rank_leaves() {
+        let samples = vec![
+            frames(&["parse_row", "load", "main"]),
+            frames(&["parse_row", "load", "main"]),
+            frames(&["parse_row", "load", "main"]),
+            frames(&["write", "flush", "main"]),
+        ];
+        let p = StackProfile::from_samples(7, "etl", 3000, 99, &samples, 0, 10);
+        assert_eq!(p.samples, 4);
+        assert_eq!(p.stacks[0].stack, "etl;main;load;parse_row");
+        assert_eq!(p.stacks[0].count, 3);
+        assert_eq!(p.top_functions[0].stack, "parse_row");
+        assert_eq!(
+            p.folded(),
+            "etl;main;load;parse_row 3\netl;main;flush;write 1\n"
+        );
+        assert_eq!(p.summary(), "hot in parse_row (75% of 4 samples)");
+    }
+
+    #[test]
+    fn summary_includes_placement_hint() {
+        let mut p = StackProfile::from_samples(7, "etl", 3000, 99, &[frames(&["spin"])], 0, 10);
+        p.placement = Some(Placement {
+            threads: 4,
+            cpus: vec![0, 1],
+            nodes: vec![0],
+            allowed_cpus: vec![0, 1, 2, 3],
+            hint: Some("all 4 threads on NUMA node 0".to_string()),
+        });
+        assert_eq!(
+            p.summary(),
+            "hot in spin (100% of 1 samples); all 4 threads on NUMA node 0"
+        );
+    }
+
+    #[test]
+    fn max_stacks_truncates_rarest() {
+        let samples = vec![frames(&["a"]), frames(&["a"]), frames(&["b"])];
+        let p = StackProfile::from_samples(1, "x", 1000, 99, &samples, 0, 1);
+        assert_eq!(p.stacks.len(), 1);
+        assert_eq!(p.stacks[0].stack, "x;a");
+    }
+
+    #[test]
+    fn parses_executable_file_mappings_only() {
+        let maps = "\
+55d0c0a00000-55d0c0a21000 r--p 00000000 fd:01 1234 /usr/bin/etl
+55d0c0a21000-55d0c0b00000 r-xp 00021000 fd:01 1234 /usr/bin/etl
+7ffd1c5e1000-7ffd1c5e3000 r-xp 00000000 00:00 0    [vdso]
+7f00aa000000-7f00aa100000 rw-p 00000000 00:00 0
+";
+        let m = parse_maps(maps);
+        assert_eq!(
+            m,
+            vec![Mapping {
+                start: 0x55d0c0a21000,
+                end: 0x55d0c0b00000,
+                offset: 0x21000,
+                path: "/usr/bin/etl".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn callchain_skips_context_markers() {
+        let mut body = Vec::new();
+        for w in [0x1000u64, 0, 3, (-512i64) as u64, 0x1000, 0x2000] {
+            body.extend_from_slice(&w.to_ne_bytes());
+        }
+        assert_eq!(parse_sample(&body), Some(vec![0x1000, 0x2000]));
+    }
+
+    #[test]
+    fn ring_reads_wrap_around() {
+        let data = [1u8, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(ring_bytes(&data, 14, 4), vec![7, 8, 1, 2]);
+    }
+
+    #[test]
+    fn symbolizes_own_binary() {
+        let pid = std::process::id();
+        let maps = parse_maps(&fs::read_to_string("/proc/self/maps").unwrap());
+        let ip = symbolizes_own_binary as *const () as u64;
+        let mut s = Symbolizer::new(pid, maps);
+        let name = s.resolve(ip);
+        assert!(name.contains("symbolizes_own_binary"), "{name}");
+    }
+}
@@ -0,0 +1,420 @@
+//! Per-pod and per-namespace event rate quotas (`[quotas]`).
+//!
+//! `runtime.events_rate_cap` protects the daemon, but one pod in a fork loop
+//! can use up the whole budget and hide everything else on the node. Quotas
+//! cap how many events per second a single pod — or container, when there is
+//! no Kubernetes metadata — and a single namespace may feed into the
+//! pipeline. Events over quota are counted rather than dispatched and
+//! reported as one "suppressed N events" summary per source and interval.
+//! Host processes outside any container are never throttled.
+
+use crate::ProcessEvent;
+use crate::config::QuotaConfig;
+use crate::k8s::K8sContext;
+use crate::pidmap::PidContainerMap;
+use crate::runtime::stream_listener::event_label;
+use linnix_ai_ebpf_common::EventType;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Summaries kept for `GET /quotas`.
+const MAX_RECENT: usize = 100;
+
+/// Sources idle for this long are forgotten.
+const IDLE_SECS: u64 = 60;
+
+/// Which quota an event exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaScope {
+    Pod,
+    Namespace,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SuppressionSummary {
+    pub container_id: String,
+    pub namespace: Option<String>,
+    pub pod: Option<String>,
+    /// The quota hit first in the interval.
+    pub scope: QuotaScope,
+    /// Events per second allowed by that quota.
+    pub limit: u64,
+    pub suppressed: u64,
+    /// Suppressed events per event type.
+    pub by_type: BTreeMap<&'static str, u64>,
+    /// First and last suppressed event (epoch seconds).
+    pub first_ts: u64,
+    pub last_ts: u64,
+}
+
+impl fmt::Display for SuppressionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "suppressed {} events from ", self.suppressed)?;
+        match (&self.namespace, &self.pod) {
+            (Some(ns), Some(pod)) => write!(f, "pod {ns}/{pod}")?,
+            _ => write!(f, "container {}", short_id(&self.container_id))?,
+        }
+        let scope = match self.scope {
+            QuotaScope::Pod => "pod",
+            QuotaScope::Namespace => "namespace",
+        };
+        write!(f, " over the {scope} quota of {}/s", self.limit)?;
+        let mut by_type: Vec<_> = self.by_type.iter().collect();
+        by_type.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (i, (kind, n)) in by_type.iter().enumerate() {
+            let sep = if i == 0 { ": " } else { ", " };
+            write!(f, "{sep}{n} {kind}")?;
+        }
+        Ok(())
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    &id[..id.len().min(12)]
+}
+
+/// Who an event is charged to.
+struct Source {
+    /// `namespace/pod` with Kubernetes metadata, else the container ID.
+    key: Arc<str>,
+    namespace: Option<Arc<str>>,
+    pod: Option<String>,
+}
+
+#[derive(Default)]
+struct Window {
+    second: u64,
+    count: u64,
+}
+
+impl Window {
+    /// Count one event in `now` and report whether it is within `limit`.
+    fn admit(&mut self, now: u64, limit: u64) -> bool {
+        if self.second != now {
+            self.second = now;
+            self.count = 0;
+        }
+        if limit > 0 && self.count >= limit {
+            return false;
+        }
+        self.count += 1;
+        true
+    }
+}
+
+struct Suppressed {
+    container_id: Arc<str>,
+    namespace: Option<Arc<str>>,
+    pod: Option<String>,
+    scope: QuotaScope,
+    count: u64,
+    by_type: BTreeMap<&'static str, u64>,
+    first_ts: u64,
+    last_ts: u64,
+}
+
+#[derive(Default)]
+struct State {
+    /// Container ID → source, resolved once per container.
+    sources: HashMap<Arc<str>, Arc<Source>>,
+    pods: HashMap<Arc<str>, Window>,
+    namespaces: HashMap<Arc<str>, Window>,
+    /// Suppressed since the last summary, by source key.
+    suppressed: HashMap<Arc<str>, Suppressed>,
+}
+
+pub struct EventQuota {
+    per_pod: u64,
+    per_namespace: u64,
+    exempt_namespaces: HashSet<String>,
+    pids: Arc<PidContainerMap>,
+    /// False when the pid map belongs to the K8s context, which keeps it
+    /// current from the event stream itself.
+    owns_pids: bool,
+    k8s: Option<Arc<K8sContext>>,
+    state: Mutex<State>,
+    recent: Mutex<VecDeque<SuppressionSummary>>,
+    suppressed_total: AtomicU64,
+}
+
+impl EventQuota {
+    pub fn new(config: &QuotaConfig, k8s: Option<Arc<K8sContext>>) -> Self {
+        let (pids, owns_pids) = match &k8s {
+            Some(ctx) => (Arc::clone(ctx.pid_map()), false),
+            None => (Arc::new(PidContainerMap::default()), true),
+        };
+        Self::with_pids(config, pids, owns_pids, k8s)
+    }
+
+    fn with_pids(
+        config: &QuotaConfig,
+        pids: Arc<PidContainerMap>,
+        owns_pids: bool,
+        k8s: Option<Arc<K8sContext>>,
+    ) -> Self {
+        Self {
+            per_pod: config.per_pod_events_per_sec,
+            per_namespace: config.per_namespace_events_per_sec,
+            exempt_namespaces: config.exempt_namespaces.iter().cloned().collect(),
+            pids,
+            owns_pids,
+            k8s,
+            state: Mutex::new(State::default()),
+            recent: Mutex::new(VecDeque::new()),
+            suppressed_total: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether `event` fits in its pod's and namespace's quota. Events over
+    /// quota are counted for the next summary.
+    pub fn admit(&self, event: &ProcessEvent) -> bool {
+        self.admit_at(event, now_secs())
+    }
+
+    fn admit_at(&self, event: &ProcessEvent, now: u64) -> bool {
+        if self.owns_pids {
+            self.pids.observe(event);
+        }
+        // Rare and too important to drop.
+        if matches!(
+            event.event_type,
+            x if x == EventType::OomKill as u32
+                || x == EventType::MandateAllow as u32
+                || x == EventType::MandateDeny as u32
+        ) {
+            return true;
+        }
+        let Some(container) = self.pids.container_for_event(event) else {
+            return true;
+        };
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let source = match state.sources.get(&container) {
+            Some(source) => Arc::clone(source),
+            None => {
+                let source = Arc::new(self.resolve(&container));
+                // Without metadata yet, retry on the next event rather than
+                // charging the pod's events to the bare container for good.
+                if source.namespace.is_some() || self.k8s.is_none() {
+                    state
+                        .sources
+                        .insert(Arc::clone(&container), Arc::clone(&source));
+                }
+                source
+            }
+        };
+        self.charge(&mut state, &container, &source, now, event.event_type)
+    }
+
+    fn charge(
+        &self,
+        state: &mut State,
+        container: &Arc<str>,
+        source: &Source,
+        now: u64,
+        event_type: u32,
+    ) -> bool {
+        if let Some(ns) = &source.namespace
+            && self.exempt_namespaces.contains(&**ns)
+        {
+            return true;
+        }
+        let scope = if !state
+            .pods
+            .entry(Arc::clone(&source.key))
+            .or_default()
+            .admit(now, self.per_pod)
+        {
+            QuotaScope::Pod
+        } else if let Some(ns) = &source.namespace
+            && !state
+                .namespaces
+                .entry(Arc::clone(ns))
+                .or_default()
+                .admit(now, self.per_namespace)
+        {
+            QuotaScope::Namespace
+        } else {
+            return true;
+        };
+
+        self.suppressed_total.fetch_add(1, Ordering::Relaxed);
+        let entry = state
+            .suppressed
+            .entry(Arc::clone(&source.key))
+            .or_insert_with(|| Suppressed {
+                container_id: Arc::clone(container),
+                namespace: source.namespace.clone(),
+                pod: source.pod.clone(),
+                scope,
+                count: 0,
+                by_type: BTreeMap::new(),
+                first_ts: now,
+                last_ts: now,
+            });
+        entry.count += 1;
+        entry.last_ts = now;
+        *entry.by_type.entry(event_label(event_type)).or_default() += 1;
+        false
+    }
+
+    fn resolve(&self, container: &Arc<str>) -> Source {
+        let meta = self
+            .k8s
+            .as_ref()
+            .and_then(|k8s| k8s.get_metadata(container));
+        match meta {
+            Some(meta) => Source {
+                key: Arc::from(format!("{}/{}", meta.namespace, meta.pod_name)),
+                namespace: Some(Arc::from(meta.namespace)),
+                pod: Some(meta.pod_name),
+            },
+            None => Source {
+                key: Arc::clone(container),
+                namespace: None,
+                pod: None,
+            },
+        }
+    }
+
+    /// Summarize what was suppressed since the last call, newest source
+    /// first, and forget idle sources.
+    pub fn flush(&self) -> Vec<SuppressionSummary> {
+        self.flush_at(now_secs())
+    }
+
+    fn flush_at(&self, now: u64) -> Vec<SuppressionSummary> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut summaries: Vec<_> = state
+            .suppressed
+            .drain()
+            .map(|(_, s)| SuppressionSummary {
+                container_id: s.container_id.to_string(),
+                namespace: s.namespace.map(|ns| ns.to_string()),
+                pod: s.pod,
+                scope: s.scope,
+                limit: match s.scope {
+                    QuotaScope::Pod => self.per_pod,
+                    QuotaScope::Namespace => self.per_namespace,
+                },
+                suppressed: s.count,
+                by_type: s.by_type,
+                first_ts: s.first_ts,
+                last_ts: s.last_ts,
+            })
+            .collect();
+        let cutoff = now.saturating_sub(IDLE_SECS);
+        state.pods.retain(|_, w| w.second >= cutoff);
+        state.namespaces.retain(|_, w| w.second >= cutoff);
+        let State { sources, pods, .. } = &mut *state;
+        sources.retain(|_, s| pods.contains_key(&s.key));
+        drop(state);
+
+        summaries.sort_by(|a, b| b.suppressed.cmp(&a.suppressed));
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        for summary in &summaries {
+            if recent.len() == MAX_RECENT {
+                recent.pop_back();
+            }
+            recent.push_front(summary.clone());
+        }
+        summaries
+    }
+
+    /// Most recent summaries, newest first.
+    pub fn recent(&self) -> Vec<SuppressionSummary> {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent.iter().cloned().collect()
+    }
+
+    pub fn suppressed_total(&self) -> u64 {
+        self.suppressed_total.load(Ordering::Relaxed)
+    }
+
+    pub fn per_pod_limit(&self) -> u64 {
+        self.per_pod
+    }
+
+    pub fn per_namespace_limit(&self) -> u64 {
+        self.per_namespace
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProcessEventWire;
+
+    const NOISY: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const QUIET: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    /// Pids 1000-1999 run in the noisy container, 2000-2999 in the quiet
+    /// one, everything else on the host.
+    fn read_cgroup(pid: u32) -> Option<String> {
+        let id = match pid {
+            1000..=1999 => NOISY,
+            2000..=2999 => QUIET,
+            _ => return Some("0::/system.slice/sshd.service\n".into()),
+        };
+        Some(format!("0::/kubepods.slice/cri-containerd-{id}.scope\n"))
+    }
+
+    fn quota(per_pod: u64) -> EventQuota {
+        let config = QuotaConfig {
+            enabled: true,
+            per_pod_events_per_sec: per_pod,
+            ..QuotaConfig::default()
+        };
+        let pids = Arc::new(PidContainerMap::with_reader(1024, read_cgroup));
+        EventQuota::with_pids(&config, pids, true, None)
+    }
+
+    fn event(pid: u32, event_type: EventType) -> ProcessEvent {
+        let mut wire: ProcessEventWire = unsafe { std::mem::zeroed() };
+        wire.pid = pid;
+        wire.event_type = event_type as u32;
+        ProcessEvent::new(wire)
+    }
+
+    #[test]
+    fn noisy_container_is_capped_without_starving_others() {
+        let quota = quota(5);
+        let admitted = (0..20)
+            .filter(|i| quota.admit_at(&event(1000 + i, EventType::Fork), 100))
+            .count();
+        assert_eq!(admitted, 5);
+        assert!(quota.admit_at(&event(2000, EventType::Exec), 100));
+        // Host processes are never throttled.
+        assert!((0..20).all(|i| quota.admit_at(&event(10 + i, EventType::Fork), 100)));
+        // OOM kills get through even over quota.
+        assert!(quota.admit_at(&event(1500, EventType::OomKill), 100));
+        // The next second starts a fresh window.
+        assert!(quota.admit_at(&event(1001, EventType::Exit), 101));
+
+        let summaries = quota.flush_at(101);
+        assert_eq!(summaries.len(), 1);
+        let summary = &summaries[0];
+        assert_eq!(summary.container_id, NOISY);
+        assert_eq!(summary.suppressed, 15);
+        assert_eq!(summary.scope, QuotaScope::Pod);
+        assert_eq!(
+            summary.to_string(),
+            "suppressed 15 events from container aaaaaaaaaaaa over the pod quota of 5/s: 15 Fork"
+        );
+        assert_eq!(quota.suppressed_total(), 15);
+        assert!(quota.flush_at(102).is_empty());
+        assert_eq!(quota.recent().len(), 1);
+    }
+}
@@ -0,0 +1,396 @@
+//! Retention for the insight, alert and feedback logs.
+//!
+//! The NDJSON logs under `/var/log/linnix` are append-only, so a long-running
+//! agent would eventually fill the disk. A periodic pass drops records older
+//! than the configured age and then all but the newest `max_records`, in the
+//! files, the in-memory insight buffer and the incident DB feedback table.
+//! Lines without a `timestamp` are only subject to the count limit.
+
+use crate::IncidentStore;
+use crate::config::RetentionConfig;
+use crate::insights::InsightStore;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Files owned by each store; `None` when that store isn't persisted.
+#[derive(Debug, Clone, Default)]
+pub struct StorePaths {
+    pub insights: Option<PathBuf>,
+    pub alerts: Option<PathBuf>,
+    pub feedback: Option<PathBuf>,
+    pub incident_db: Option<PathBuf>,
+}
+
+/// Records removed by one pruning pass.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub at: u64,
+    pub insights: u64,
+    pub alerts: u64,
+    pub feedback: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreUsage {
+    pub store: &'static str,
+    pub path: Option<String>,
+    /// On-disk size; for the incident DB this includes its WAL/journal.
+    pub bytes: u64,
+    /// 0 = unlimited. Not set for the incident DB.
+    pub max_records: Option<usize>,
+    pub max_age_days: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageStats {
+    pub retention_enabled: bool,
+    pub stores: Vec<StoreUsage>,
+    pub total_bytes: u64,
+    pub last_prune: Option<PruneReport>,
+}
+
+pub struct Storage {
+    cfg: RetentionConfig,
+    paths: StorePaths,
+    insights: Arc<InsightStore>,
+    incidents: Option<Arc<IncidentStore>>,
+    last_prune: Mutex<Option<PruneReport>>,
+}
+
+impl Storage {
+    pub fn new(
+        cfg: RetentionConfig,
+        paths: StorePaths,
+        insights: Arc<InsightStore>,
+        incidents: Option<Arc<IncidentStore>>,
+    ) -> Self {
+        Self {
+            cfg,
+            paths,
+            insights,
+            incidents,
+            last_prune: Mutex::new(None),
+        }
+    }
+
+    /// Time between pruning passes (at least a minute).
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.cfg.interval_secs.max(60))
+    }
+
+    /// Epoch-seconds cutoff for alerts, if an age limit is set.
+    pub fn alerts_cutoff(&self, now: u64) -> Option<u64> {
+        cutoff(now, self.cfg.alerts_max_age_days)
+    }
+
+    /// Prune every store once. Failures are logged and don't stop the
+    /// remaining stores from being pruned.
+    pub async fn prune(&self) -> PruneReport {
+        let now = now_secs();
+        let mut report = PruneReport {
+            at: now,
+            ..Default::default()
+        };
+
+        let insights_cutoff = cutoff(now, self.cfg.insights_max_age_days);
+        if let Some(cutoff) = insights_cutoff {
+            report.insights += self.insights.prune_before(cutoff) as u64;
+        }
+        report.insights += prune_file(
+            "insights",
+            self.paths.insights.as_deref(),
+            self.cfg.insights_max_records,
+            insights_cutoff,
+        );
+        report.alerts += prune_file(
+            "alerts",
+            self.paths.alerts.as_deref(),
+            self.cfg.alerts_max_records,
+            self.alerts_cutoff(now),
+        );
+        let feedback_cutoff = cutoff(now, self.cfg.feedback_max_age_days);
+        report.feedback += prune_file(
+            "feedback",
+            self.paths.feedback.as_deref(),
+            self.cfg.feedback_max_records,
+            feedback_cutoff,
+        );
+        if let Some(store) = &self.incidents {
+            match store
+                .prune_feedback(
+                    feedback_cutoff.unwrap_or(0) as i64,
+                    self.cfg.feedback_max_records,
+                )
+                .await
+            {
+                Ok(removed) => report.feedback += removed,
+                Err(e) => warn!("[retention] failed to prune feedback table: {e}"),
+            }
+        }
+
+        if report.insights + report.alerts + report.feedback > 0 {
+            info!(
+                "[retention] pruned {} insights, {} alerts, {} feedback entries",
+                report.insights, report.alerts, report.feedback
+            );
+        } else {
+            debug!("[retention] nothing to prune");
+        }
+        *self.last_prune.lock().unwrap() = Some(report.clone());
+        report
+    }
+
+    pub fn stats(&self) -> StorageStats {
+        let cfg = &self.cfg;
+        let mut stores = vec![
+            file_usage(
+                "insights",
+                self.paths.insights.as_deref(),
+                cfg.insights_max_records,
+                cfg.insights_max_age_days,
+            ),
+            file_usage(
+                "alerts",
+                self.paths.alerts.as_deref(),
+                cfg.alerts_max_records,
+                cfg.alerts_max_age_days,
+            ),
+            file_usage(
+                "feedback",
+                self.paths.feedback.as_deref(),
+                cfg.feedback_max_records,
+                cfg.feedback_max_age_days,
+            ),
+        ];
+        if let Some(db) = self.paths.incident_db.as_deref() {
+            let bytes = ["", "-wal", "-shm", "-journal"]
+                .iter()
+                .map(|suffix| {
+                    let mut path = db.as_os_str().to_owned();
+                    path.push(suffix);
+                    file_size(Path::new(&path))
+                })
+                .sum();
+            stores.push(StoreUsage {
+                store: "incident_db",
+                path: Some(db.display().to_string()),
+                bytes,
+                max_records: None,
+                max_age_days: None,
+            });
+        }
+        StorageStats {
+            retention_enabled: cfg.enabled,
+            total_bytes: stores.iter().map(|s| s.bytes).sum(),
+            stores,
+            last_prune: self.last_prune.lock().unwrap().clone(),
+        }
+    }
+}
+
+fn cutoff(now: u64, max_age_days: u64) -> Option<u64> {
+    (max_age_days > 0).then(|| now.saturating_sub(max_age_days * SECS_PER_DAY))
+}
+
+fn prune_file(store: &str, path: Option<&Path>, max_records: usize, cutoff: Option<u64>) -> u64 {
+    let Some(path) = path else {
+        return 0;
+    };
+    match prune_ndjson(path, max_records, cutoff) {
+        Ok(removed) => removed,
+        Err(e) => {
+            warn!(
+                "[retention] failed to prune {store} log {}: {e}",
+                path.display()
+            );
+            0
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Stamped {
+    timestamp: Option<u64>,
+}
+
+/// Rewrite an NDJSON file without lines whose `timestamp` is before
+/// `cutoff`, keeping at most the newest `max_records` (0 = no limit).
+/// Returns the number of lines removed; a missing file is not an error.
+///
+/// Writers reopen the file for every append, so after the rename new records
+/// go to the pruned file.
+pub fn prune_ndjson(path: &Path, max_records: usize, cutoff: Option<u64>) -> std::io::Result<u64> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+
+    let mut kept: VecDeque<String> = VecDeque::new();
+    let mut removed = 0u64;
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(cutoff) = cutoff
+            && let Ok(Stamped {
+                timestamp: Some(ts),
+            }) = serde_json::from_str::<Stamped>(&line)
+            && ts < cutoff
+        {
+            removed += 1;
+            continue;
+        }
+        kept.push_back(line);
+        if max_records > 0 && kept.len() > max_records {
+            kept.pop_front();
+            removed += 1;
+        }
+    }
+    if removed == 0 {
+        return Ok(0);
+    }
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".prune");
+    let tmp = PathBuf::from(tmp_name);
+    {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(&tmp)?);
+        for line in &kept {
+            out.write_all(line.as_bytes())?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(removed)
+}
+
+fn file_usage(
+    store: &'static str,
+    path: Option<&Path>,
+    max_records: usize,
+    max_age_days: u64,
+) -> StoreUsage {
+    StoreUsage {
+        store,
+        path: path.map(|p| p.display().to_string()),
+        bytes: path.map(file_size).unwrap_or(0),
+        max_records: Some(max_records),
+        max_age_days: Some(max_age_days),
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_lines(path: &Path, stamps: &[Option<u64>]) {
+        let text: String = stamps
+            .iter()
+            .enumerate()
+            .map(|(i, ts)| match ts {
+                Some(ts) => format!("{{\"n\":{i},\"timestamp\":{ts}}}\n"),
+                None => format!("{{\"n\":{i}}}\n"),
+            })
+            .collect();
+        std::fs::write(path, text).unwrap();
+    }
+
+    fn ids(path: &Path) -> Vec<u64> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| {
+                serde_json::from_str::<serde_json::Value>(l).unwrap()["n"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prunes_by_age_then_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alerts.ndjson");
+        write_lines(&path, &[Some(10), None, Some(100), Some(200), Some(300)]);
+
+        // Age: drops n=0; the unstamped line stays.
+        assert_eq!(prune_ndjson(&path, 0, Some(50)).unwrap(), 1);
+        assert_eq!(ids(&path), vec![1, 2, 3, 4]);
+
+        // Count: keeps the newest two.
+        assert_eq!(prune_ndjson(&path, 2, None).unwrap(), 2);
+        assert_eq!(ids(&path), vec![3, 4]);
+
+        assert_eq!(prune_ndjson(&path, 2, Some(50)).unwrap(), 0);
+    }
+
+    #[test]
+    fn missing_file_is_not_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            prune_ndjson(&dir.path().join("nope"), 1, Some(1)).unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn prune_reports_and_stats_cover_all_stores() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = StorePaths {
+            insights: Some(dir.path().join("insights.ndjson")),
+            alerts: Some(dir.path().join("alerts.ndjson")),
+            feedback: Some(dir.path().join("insights_feedback.json")),
+            incident_db: None,
+        };
+        let now = now_secs();
+        write_lines(
+            paths.alerts.as_ref().unwrap(),
+            &[Some(1), Some(now), Some(now)],
+        );
+        write_lines(paths.feedback.as_ref().unwrap(), &[Some(now); 4]);
+
+        let storage = Storage::new(
+            RetentionConfig {
+                feedback_max_records: 3,
+                ..Default::default()
+            },
+            paths,
+            Arc::new(InsightStore::new(4, None)),
+            None,
+        );
+        let report = storage.prune().await;
+        assert_eq!((report.insights, report.alerts, report.feedback), (0, 1, 1));
+
+        let stats = storage.stats();
+        let names: Vec<_> = stats.stores.iter().map(|s| s.store).collect();
+        assert_eq!(names, vec!["insights", "alerts", "feedback"]);
+        assert_eq!(stats.stores[0].bytes, 0);
+        assert!(stats.stores[1].bytes > 0);
+        assert_eq!(
+            stats.total_bytes,
+            stats.stores.iter().map(|s| s.bytes).sum::<u64>()
+        );
+        assert_eq!(stats.last_prune.unwrap().alerts, 1);
+    }
+}
@@ -0,0 +1,331 @@
+//! Downsampled telemetry rollups.
+//!
+//! Raw events only live in memory for a few minutes. This background job
+//! folds them into 1-minute buckets in the incident database (event counts
+//! by type, alert counts by severity, CPU/RSS of the top processes), then
+//! compacts those into 5-minute buckets that are kept much longer. Both
+//! resolutions are pruned on a schedule so disk usage stays bounded while
+//! trend data survives restarts.
+
+use crate::alerts::Alert;
+use crate::config::RollupConfig;
+use crate::context::ContextStore;
+use crate::incidents::{IncidentStore, RollupRow};
+use log::{debug, info, warn};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+pub const RESOLUTION_1M: i64 = 60;
+pub const RESOLUTION_5M: i64 = 300;
+
+/// How often the top processes are sampled within a bucket.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// How often expired buckets are deleted.
+const PRUNE_INTERVAL_SECS: i64 = 3600;
+
+/// Metric name used for an event type, matching the Grafana target names.
+pub fn event_metric(event_type: u32) -> &'static str {
+    match event_type {
+        0 => "events.exec",
+        1 => "events.fork",
+        2 => "events.exit",
+        3 => "events.net",
+        4 => "events.file_io",
+        5 => "events.syscall",
+        6 => "events.block_io",
+        7 => "events.page_fault",
+        10 => "events.oom_kill",
+        11 => "events.sched_latency",
+        12 => "events.off_cpu",
+        13 => "events.connection",
+        14 => "events.direct_reclaim",
+        15 => "events.signal",
+        16 => "events.creds",
+        17 => "events.mem_growth",
+        18 => "events.file_access",
+        19 => "events.kernel_load",
+        _ => "events.other",
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Agg {
+    count: i64,
+    sum: f64,
+    max: f64,
+}
+
+/// In-memory totals for the bucket currently being filled.
+#[derive(Debug, Default)]
+pub struct RollupAccumulator {
+    cells: HashMap<(String, String), Agg>,
+}
+
+impl RollupAccumulator {
+    pub fn record_event(&mut self, event_type: u32) {
+        self.add(event_metric(event_type), "", 1.0);
+    }
+
+    pub fn record_alert(&mut self, alert: &Alert) {
+        // A resolution closes an alert already counted.
+        if alert.is_resolution() {
+            return;
+        }
+        self.add(&format!("alerts.{}", alert.severity.as_str()), "", 1.0);
+    }
+
+    /// Add one gauge sample (e.g. CPU% of a process) under `metric`/`key`.
+    pub fn record_sample(&mut self, metric: &str, key: &str, value: f64) {
+        self.add(metric, key, value);
+    }
+
+    fn add(&mut self, metric: &str, key: &str, value: f64) {
+        let agg = self
+            .cells
+            .entry((metric.to_string(), key.to_string()))
+            .or_default();
+        agg.count += 1;
+        agg.sum += value;
+        if value > agg.max {
+            agg.max = value;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Drain everything recorded so far as rows for `bucket`.
+    pub fn take(&mut self, resolution: i64, bucket: i64) -> Vec<RollupRow> {
+        self.cells
+            .drain()
+            .map(|((metric, key), agg)| RollupRow {
+                resolution,
+                bucket,
+                metric,
+                key,
+                count: agg.count,
+                sum: agg.sum,
+                max: agg.max,
+            })
+            .collect()
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Flush `bucket` and, when it closes a 5-minute window, compact that window.
+pub async fn flush_bucket(
+    store: &IncidentStore,
+    acc: &mut RollupAccumulator,
+    bucket: i64,
+) -> Result<(), sqlx::Error> {
+    if !acc.is_empty() {
+        store
+            .upsert_rollups(&acc.take(RESOLUTION_1M, bucket))
+            .await?;
+    }
+    let next = bucket + RESOLUTION_1M;
+    if next % RESOLUTION_5M == 0 {
+        store
+            .compact_rollups(RESOLUTION_1M, RESOLUTION_5M, next - RESOLUTION_5M, next)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Delete buckets older than their configured retention.
+pub async fn prune(
+    store: &IncidentStore,
+    cfg: &RollupConfig,
+    now: i64,
+) -> Result<u64, sqlx::Error> {
+    let removed_1m = store
+        .prune_rollups(RESOLUTION_1M, now - cfg.retention_1m_hours as i64 * 3600)
+        .await?;
+    let removed_5m = store
+        .prune_rollups(RESOLUTION_5M, now - cfg.retention_5m_days as i64 * 86_400)
+        .await?;
+    Ok(removed_1m + removed_5m)
+}
+
+/// Run the rollup job until the event stream closes.
+pub async fn run(
+    store: Arc<IncidentStore>,
+    context: Arc<ContextStore>,
+    alerts: Option<broadcast::Receiver<Alert>>,
+    cfg: RollupConfig,
+) {
+    info!(
+        "[rollups] started (1m kept {}h, 5m kept {}d)",
+        cfg.retention_1m_hours, cfg.retention_5m_days
+    );
+    let mut events = context.broadcaster().subscribe();
+    let mut alerts = alerts;
+    let mut tick = tokio::time::interval(SAMPLE_INTERVAL);
+    let mut acc = RollupAccumulator::default();
+    let mut bucket = now_secs() / RESOLUTION_1M * RESOLUTION_1M;
+    let mut last_prune = 0i64;
+
+    loop {
+        tokio::select! {
+            ev = events.recv() => match ev {
+                Ok(event) => acc.record_event(event.event_type),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    debug!("[rollups] event stream lagged by {n}");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            alert = async {
+                match alerts.as_mut() {
+                    Some(rx) => rx.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => match alert {
+                Ok(alert) => acc.record_alert(&alert),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    debug!("[rollups] alert stream lagged by {n}");
+                }
+                Err(broadcast::error::RecvError::Closed) => alerts = None,
+            },
+            _ = tick.tick() => {
+                let now = now_secs();
+                let current = now / RESOLUTION_1M * RESOLUTION_1M;
+                if current != bucket {
+                    if let Err(e) = flush_bucket(&store, &mut acc, bucket).await {
+                        warn!("[rollups] failed to write bucket {bucket}: {e}");
+                    }
+                    bucket = current;
+                }
+                for p in context.top_cpu_processes(cfg.top_processes) {
+                    acc.record_sample("process.cpu", &p.comm, p.mem_percent as f64);
+                }
+                for p in context.top_rss_processes(cfg.top_processes) {
+                    acc.record_sample("process.rss", &p.comm, p.mem_percent as f64);
+                }
+                if now - last_prune >= PRUNE_INTERVAL_SECS {
+                    match prune(&store, &cfg, now).await {
+                        Ok(n) if n > 0 => debug!("[rollups] pruned {n} expired buckets"),
+                        Ok(_) => {}
+                        Err(e) => warn!("[rollups] prune failed: {e}"),
+                    }
+                    last_prune = now;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Severity;
+
+    async fn store() -> (tempfile::TempDir, IncidentStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = IncidentStore::new(dir.path().join("incidents.db"))
+            .await
+            .unwrap();
+        (dir, store)
+    }
+
+    fn alert(severity: Severity) -> Alert {
+        Alert {
+            rule: "r".to_string(),
+            severity,
+            message: String::new(),
+            host: "h".to_string(),
+            resolved_after_secs: None,
+            labels: Default::default(),
+        }
+    }
+
+    #[test]
+    fn accumulator_aggregates_counts_and_samples() {
+        let mut acc = RollupAccumulator::default();
+        acc.record_event(0);
+        acc.record_event(0);
+        acc.record_event(1);
+        acc.record_alert(&alert(Severity::High));
+        acc.record_sample("process.cpu", "java", 40.0);
+        acc.record_sample("process.cpu", "java", 80.0);
+
+        let mut rows = acc.take(RESOLUTION_1M, 120);
+        rows.sort_by(|a, b| a.metric.cmp(&b.metric));
+        assert!(acc.is_empty());
+        let by = |m: &str| rows.iter().find(|r| r.metric == m).unwrap();
+        assert_eq!(by("events.exec").count, 2);
+        assert_eq!(by("events.fork").count, 1);
+        assert_eq!(by("alerts.high").count, 1);
+        let cpu = by("process.cpu");
+        assert_eq!((cpu.key.as_str(), cpu.count), ("java", 2));
+        assert_eq!((cpu.sum, cpu.max), (120.0, 80.0));
+    }
+
+    #[tokio::test]
+    async fn closing_a_five_minute_window_compacts_it() {
+        let (_dir, store) = store().await;
+        let mut acc = RollupAccumulator::default();
+        for minute in 0..5 {
+            acc.record_event(0);
+            acc.record_sample("process.cpu", "java", 10.0 * (minute + 1) as f64);
+            flush_bucket(&store, &mut acc, 600 + minute * 60)
+                .await
+                .unwrap();
+        }
+
+        let fine = store
+            .query_rollups(RESOLUTION_1M, Some("events.exec"), 0, i64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(fine.len(), 5);
+
+        let coarse = store
+            .query_rollups(RESOLUTION_5M, None, 0, i64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(coarse.len(), 2);
+        let exec = coarse.iter().find(|r| r.metric == "events.exec").unwrap();
+        assert_eq!((exec.bucket, exec.count), (600, 5));
+        let cpu = coarse.iter().find(|r| r.metric == "process.cpu").unwrap();
+        assert_eq!((cpu.sum, cpu.max), (150.0, 50.0));
+    }
+
+    #[tokio::test]
+    async fn prune_honours_per_resolution_retention() {
+        let (_dir, store) = store().await;
+        let now = 10 * 86_400;
+        let row = |resolution, bucket| RollupRow {
+            resolution,
+            bucket,
+            metric: "events.exec".to_string(),
+            key: String::new(),
+            count: 1,
+            sum: 1.0,
+            max: 1.0,
+        };
+        store
+            .upsert_rollups(&[
+                row(RESOLUTION_1M, now - 3 * 86_400),
+                row(RESOLUTION_1M, now - 60),
+                row(RESOLUTION_5M, now - 3 * 86_400),
+            ])
+            .await
+            .unwrap();
+
+        let removed = prune(&store, &RollupConfig::default(), now).await.unwrap();
+        assert_eq!(removed, 1);
+        let kept_5m = store
+            .query_rollups(RESOLUTION_5M, None, 0, i64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(kept_5m.len(), 1);
+    }
+}
@@ -0,0 +1,390 @@
+//! Rule scopes and selectors: which processes a rule watches, and whether
+//! its counters are kept per cgroup, pod, namespace or UID.
+//!
+//! Without a scope, detectors like `fork_burst` count every fork on the
+//! host, so one pod in a fork loop fires the rule for everyone and a quiet
+//! tenant's burst disappears in the noise of a busy one. A `scope` block
+//! restricts a rule to matching processes and, with `per`, keeps separate
+//! counters for each cgroup, pod, namespace or UID among them. A `match`
+//! block selects processes by what they are instead: their name, user or
+//! parent.
+
+use crate::ProcessEvent;
+use crate::k8s::K8sContext;
+use crate::pidmap::PidContainerMap;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::cell::OnceCell;
+use std::sync::Arc;
+
+/// Key of the processes outside any container with `per: pod` or
+/// `per: namespace`.
+pub const HOST_KEY: &str = "host";
+
+/// `scope:` block of a rule. All selectors given must match.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema)]
+pub struct RuleScope {
+    /// cgroup path below the cgroup mount, e.g. `/kubepods.slice`; matches
+    /// that cgroup and everything below it.
+    #[serde(default)]
+    pub cgroup: Option<String>,
+    /// Kubernetes namespace of the process's pod.
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Pod name; a trailing `*` matches by prefix (`api-*`).
+    #[serde(default)]
+    pub pod: Option<String>,
+    /// User ID of the process.
+    #[serde(default)]
+    pub uid: Option<u32>,
+    /// Keep separate counters per `cgroup`, `pod`, `namespace` or `uid`
+    /// instead of one for all matching processes.
+    #[serde(default)]
+    pub per: Option<ScopePer>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScopePer {
+    Cgroup,
+    Pod,
+    Namespace,
+    Uid,
+}
+
+impl RuleScope {
+    /// Key of the counters an event is charged to: `""` unless `per` is
+    /// set, `None` when the event is outside the scope.
+    pub fn key(&self, target: &ScopeTarget<'_>) -> Option<String> {
+        if self.uid.is_some_and(|uid| uid != target.event.uid) {
+            return None;
+        }
+        if let Some(prefix) = &self.cgroup {
+            let prefix = prefix.trim_end_matches('/');
+            let path = target.cgroup()?;
+            let below = path.strip_prefix(prefix)?;
+            if !(below.is_empty() || below.starts_with('/') || prefix.is_empty()) {
+                return None;
+            }
+        }
+        if let Some(namespace) = &self.namespace
+            && target.pod().map(|(ns, _)| ns) != Some(namespace.as_str())
+        {
+            return None;
+        }
+        if let Some(pattern) = &self.pod {
+            let (_, pod) = target.pod()?;
+            let matches = match pattern.strip_suffix('*') {
+                Some(prefix) => pod.starts_with(prefix),
+                None => pod == pattern,
+            };
+            if !matches {
+                return None;
+            }
+        }
+        Some(match self.per {
+            None => String::new(),
+            Some(ScopePer::Uid) => target.event.uid.to_string(),
+            Some(ScopePer::Cgroup) => target.cgroup().unwrap_or("unknown").to_string(),
+            Some(ScopePer::Pod) => match target.pod() {
+                Some((ns, pod)) => format!("{ns}/{pod}"),
+                None => target
+                    .container()
+                    .map_or_else(|| HOST_KEY.to_string(), str::to_string),
+            },
+            Some(ScopePer::Namespace) => target.pod().map_or(HOST_KEY, |(ns, _)| ns).to_string(),
+        })
+    }
+}
+
+/// `match:` block of a rule as written. All criteria given must match.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct RawRuleMatch {
+    /// Regex on the process name (comm).
+    #[serde(default)]
+    pub comm: Option<String>,
+    /// User IDs of the process; any when empty.
+    #[serde(default)]
+    pub uid: Vec<u32>,
+    /// Name of the parent process; a trailing `*` matches by prefix.
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+/// Per-process criteria an event must meet to be counted by a rule at all.
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    pub comm: Option<Regex>,
+    pub uid: Vec<u32>,
+    pub parent: Option<String>,
+}
+
+impl TryFrom<RawRuleMatch> for RuleMatch {
+    type Error = regex::Error;
+
+    fn try_from(raw: RawRuleMatch) -> Result<Self, Self::Error> {
+        Ok(Self {
+            comm: raw.comm.as_deref().map(Regex::new).transpose()?,
+            uid: raw.uid,
+            parent: raw.parent,
+        })
+    }
+}
+
+impl RuleMatch {
+    pub fn matches(&self, target: &ScopeTarget<'_>) -> bool {
+        if !self.uid.is_empty() && !self.uid.contains(&target.event.uid) {
+            return false;
+        }
+        if let Some(regex) = &self.comm {
+            let comm = String::from_utf8_lossy(&target.event.comm);
+            if !regex.is_match(comm.trim_end_matches('\0')) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.parent {
+            let Some(parent) = target.parent() else {
+                return false;
+            };
+            let matches = match pattern.strip_suffix('*') {
+                Some(prefix) => parent.starts_with(prefix),
+                None => parent == pattern,
+            };
+            if !matches {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Resolves what scopes select on: cgroup paths from the PID map, pods
+/// from the Kubernetes context when there is one.
+pub struct ScopeResolver {
+    pids: Arc<PidContainerMap>,
+    /// False when the pid map belongs to the K8s context, which keeps it
+    /// current from the event stream itself.
+    owns_pids: bool,
+    k8s: Option<Arc<K8sContext>>,
+}
+
+impl Default for ScopeResolver {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ScopeResolver {
+    pub fn new(k8s: Option<Arc<K8sContext>>) -> Self {
+        let (pids, owns_pids) = match &k8s {
+            Some(ctx) => (Arc::clone(ctx.pid_map()), false),
+            None => (Arc::new(PidContainerMap::default()), true),
+        };
+        Self {
+            pids,
+            owns_pids,
+            k8s,
+        }
+    }
+
+    /// Keep the PID map current; call for every event when a scoped rule
+    /// is loaded.
+    pub fn observe(&self, event: &ProcessEvent) {
+        if self.owns_pids {
+            self.pids.observe(event);
+        }
+    }
+
+    /// What `event` can be matched on, resolved as the scopes ask for it.
+    pub fn target<'a>(&'a self, event: &'a ProcessEvent) -> ScopeTarget<'a> {
+        ScopeTarget {
+            event,
+            resolver: Some(self),
+            cgroup: OnceCell::new(),
+            container: OnceCell::new(),
+            pod: OnceCell::new(),
+            parent: OnceCell::new(),
+        }
+    }
+}
+
+/// The process behind one event, as seen by scopes. Each attribute is
+/// looked up at most once, and only if a scope needs it.
+pub struct ScopeTarget<'a> {
+    event: &'a ProcessEvent,
+    resolver: Option<&'a ScopeResolver>,
+    cgroup: OnceCell<Option<Arc<str>>>,
+    container: OnceCell<Option<Arc<str>>>,
+    /// (namespace, pod name).
+    pod: OnceCell<Option<(String, String)>>,
+    /// comm of the parent process.
+    parent: OnceCell<Option<String>>,
+}
+
+impl ScopeTarget<'_> {
+    pub(crate) fn cgroup(&self) -> Option<&str> {
+        self.cgroup
+            .get_or_init(|| self.resolver?.pids.cgroup_path_for_event(self.event))
+            .as_deref()
+    }
+
+    pub(crate) fn container(&self) -> Option<&str> {
+        self.container
+            .get_or_init(|| self.resolver?.pids.container_for_event(self.event))
+            .as_deref()
+    }
+
+    pub(crate) fn pod(&self) -> Option<(&str, &str)> {
+        self.pod
+            .get_or_init(|| {
+                let resolver = self.resolver?;
+                let container = self.container()?;
+                let meta = resolver.k8s.as_ref()?.get_metadata(container)?;
+                Some((meta.namespace, meta.pod_name))
+            })
+            .as_ref()
+            .map(|(ns, pod)| (ns.as_str(), pod.as_str()))
+    }
+
+    fn parent(&self) -> Option<&str> {
+        self.parent
+            .get_or_init(|| {
+                self.resolver?;
+                crate::crashloop::read_comm(self.event.ppid)
+            })
+            .as_deref()
+    }
+}
+
+#[cfg(test)]
+impl<'a> ScopeTarget<'a> {
+    /// A target with fixed attributes, for tests without a PID map.
+    pub(crate) fn fixed(
+        event: &'a ProcessEvent,
+        cgroup: Option<&str>,
+        container: Option<&str>,
+        pod: Option<(&str, &str)>,
+    ) -> Self {
+        Self {
+            event,
+            resolver: None,
+            cgroup: OnceCell::from(cgroup.map(Arc::from)),
+            container: OnceCell::from(container.map(Arc::from)),
+            pod: OnceCell::from(pod.map(|(ns, pod)| (ns.to_string(), pod.to_string()))),
+            parent: OnceCell::new(),
+        }
+    }
+
+    pub(crate) fn with_parent(self, parent: &str) -> Self {
+        Self {
+            parent: OnceCell::from(Some(parent.to_string())),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn event(uid: u32) -> ProcessEvent {
+        ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid,
+            gid: 0,
+            event_type: 1,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    fn scope(yaml: &str) -> RuleScope {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn selectors_must_all_match() {
+        let event = event(1000);
+        let api = ScopeTarget::fixed(
+            &event,
+            Some("/kubepods.slice/kubepods-burstable.slice/pod1/cri-containerd-abc.scope"),
+            Some("abc"),
+            Some(("payments", "api-7d9f")),
+        );
+        let host = ScopeTarget::fixed(&event, Some("/system.slice/cron.service"), None, None);
+
+        let pods = scope("cgroup: /kubepods.slice/\n");
+        assert_eq!(pods.key(&api).as_deref(), Some(""));
+        assert_eq!(pods.key(&host), None);
+        // A prefix of a component isn't a parent cgroup.
+        assert_eq!(scope("cgroup: /kube\n").key(&api), None);
+
+        let api_pods = scope("namespace: payments\npod: api-*\nuid: 1000\n");
+        assert!(api_pods.key(&api).is_some());
+        assert_eq!(api_pods.key(&host), None);
+        assert_eq!(scope("pod: api\n").key(&api), None);
+        assert_eq!(scope("uid: 0\n").key(&api), None);
+        assert_eq!(scope("{}").key(&host).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn per_splits_counters() {
+        let event = event(1000);
+        let api = ScopeTarget::fixed(
+            &event,
+            Some("/kubepods.slice/pod1"),
+            Some("abc"),
+            Some(("payments", "api-7d9f")),
+        );
+        let bare = ScopeTarget::fixed(&event, Some("/docker/abc"), Some("abc"), None);
+        let host = ScopeTarget::fixed(&event, None, None, None);
+
+        let per_pod = scope("per: pod\n");
+        assert_eq!(per_pod.key(&api).as_deref(), Some("payments/api-7d9f"));
+        assert_eq!(per_pod.key(&bare).as_deref(), Some("abc"));
+        assert_eq!(per_pod.key(&host).as_deref(), Some(HOST_KEY));
+
+        assert_eq!(
+            scope("per: namespace\n").key(&api).as_deref(),
+            Some("payments")
+        );
+        assert_eq!(
+            scope("per: cgroup\n").key(&bare).as_deref(),
+            Some("/docker/abc")
+        );
+        assert_eq!(scope("per: uid\n").key(&host).as_deref(), Some("1000"));
+        assert!(serde_yaml::from_str::<RuleScope>("per: node\n").is_err());
+    }
+
+    #[test]
+    fn match_checks_comm_uid_and_parent() {
+        let mut event = event(1000);
+        event.comm[..7].copy_from_slice(b"build.s");
+        let target = ScopeTarget::fixed(&event, None, None, None).with_parent("make");
+        let orphan = ScopeTarget::fixed(&event, None, None, None);
+        let matcher = |yaml: &str| {
+            RuleMatch::try_from(serde_yaml::from_str::<RawRuleMatch>(yaml).unwrap()).unwrap()
+        };
+
+        assert!(matcher("{}").matches(&target));
+        assert!(matcher("comm: ^build\\.s$\nuid: [0, 1000]\n").matches(&target));
+        assert!(!matcher("comm: ^make$\n").matches(&target));
+        assert!(!matcher("uid: [0]\n").matches(&target));
+        assert!(matcher("parent: ma*\n").matches(&target));
+        assert!(!matcher("parent: ma\n").matches(&target));
+        // Without a resolver the parent is unknown, which never matches.
+        assert!(!matcher("parent: '*'\n").matches(&orphan));
+    }
+}
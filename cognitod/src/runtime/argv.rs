@@ -0,0 +1,151 @@
+//! argv for exec events, read from `/proc/<pid>/cmdline` while the process is
+//! still around. The probes don't capture argv in-kernel yet, so this is
+//! best effort: short jobs may have exited (or the PID been reused) before
+//! the event is processed, in which case the event has no argv. What was
+//! read at exec is kept briefly so the exit of the same process carries it
+//! too.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+const DEFAULT_CAPACITY: usize = 8_192;
+
+/// Longest command line kept; the rest is dropped.
+const MAX_CMDLINE_BYTES: usize = 4096;
+
+pub struct ArgvCache {
+    proc_root: PathBuf,
+    inner: Mutex<ArgvInner>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+struct ArgvInner {
+    entries: HashMap<u32, (Vec<String>, Instant)>,
+    order: VecDeque<(u32, Instant)>,
+}
+
+impl ArgvCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_proc_root("/proc", ttl, DEFAULT_CAPACITY)
+    }
+
+    pub(crate) fn with_proc_root(
+        proc_root: impl Into<PathBuf>,
+        ttl: Duration,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            proc_root: proc_root.into(),
+            inner: Mutex::new(ArgvInner {
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Read the argv of a process that just exec'd `comm`. Returns `None`
+    /// when the process is gone or `/proc` no longer shows that image (PID
+    /// reuse, or another exec in between).
+    pub fn on_exec(&self, pid: u32, comm: &str) -> Option<Vec<String>> {
+        let argv = read_cmdline(&self.proc_root, pid)?;
+        let current = std::fs::read_to_string(self.proc_root.join(format!("{pid}/comm"))).ok()?;
+        if current.trim_end_matches('\n') != comm {
+            return None;
+        }
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.entries.insert(pid, (argv.clone(), now));
+        inner.order.push_back((pid, now));
+        inner.purge(now, self.ttl, self.capacity);
+        Some(argv)
+    }
+
+    /// argv recorded at the last exec of `pid`, if recent.
+    pub fn lookup(&self, pid: u32) -> Option<Vec<String>> {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.purge(now, self.ttl, self.capacity);
+        inner.entries.get(&pid).map(|(argv, _)| argv.clone())
+    }
+}
+
+impl ArgvInner {
+    fn purge(&mut self, now: Instant, ttl: Duration, capacity: usize) {
+        while let Some(&(pid, recorded_at)) = self.order.front() {
+            let expired = now.duration_since(recorded_at) > ttl;
+            let over_capacity = self.entries.len() > capacity;
+            match self.entries.get(&pid) {
+                Some((_, current)) if *current == recorded_at => {
+                    if !(expired || over_capacity) {
+                        break;
+                    }
+                    self.entries.remove(&pid);
+                }
+                // Superseded by a later exec of the same PID.
+                _ => {}
+            }
+            self.order.pop_front();
+        }
+    }
+}
+
+/// `/proc/<pid>/cmdline` split into arguments; `None` for processes that
+/// have exited, zombies and kernel threads (all of which read as empty).
+pub fn read_cmdline(proc_root: &std::path::Path, pid: u32) -> Option<Vec<String>> {
+    let mut raw = std::fs::read(proc_root.join(format!("{pid}/cmdline"))).ok()?;
+    raw.truncate(MAX_CMDLINE_BYTES);
+    let raw = raw.strip_suffix(b"\0").unwrap_or(&raw);
+    if raw.is_empty() {
+        return None;
+    }
+    Some(
+        raw.split(|&b| b == 0)
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_proc(root: &std::path::Path, pid: u32, cmdline: &[u8], comm: &str) {
+        let dir = root.join(pid.to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cmdline"), cmdline).unwrap();
+        std::fs::write(dir.join("comm"), format!("{comm}\n")).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reads_argv_at_exec_and_keeps_it_briefly() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ArgvCache::with_proc_root(dir.path(), Duration::from_secs(5), 16);
+        fake_proc(dir.path(), 42, b"curl\0-s\0http://x\0", "curl");
+        fake_proc(dir.path(), 43, b"", "kworker/0:1");
+        fake_proc(dir.path(), 44, b"python3\0app.py\0", "python3");
+
+        let argv = vec!["curl".to_string(), "-s".into(), "http://x".into()];
+        assert_eq!(cache.on_exec(42, "curl"), Some(argv.clone()));
+        assert_eq!(cache.on_exec(43, "kworker/0:1"), None, "kernel thread");
+        assert_eq!(cache.on_exec(44, "sh"), None, "pid reused by another image");
+        assert_eq!(cache.on_exec(45, "gone"), None);
+
+        // The process exits: /proc no longer has it, the cache still does.
+        std::fs::remove_dir_all(dir.path().join("42")).unwrap();
+        assert_eq!(cache.lookup(42), Some(argv));
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(cache.lookup(42), None);
+    }
+
+    #[test]
+    fn own_cmdline_is_readable() {
+        let argv = read_cmdline(std::path::Path::new("/proc"), std::process::id()).unwrap();
+        assert!(!argv[0].is_empty());
+    }
+}
@@ -0,0 +1,150 @@
+//! Watched paths of the file access probes and the paths they capture.
+//!
+//! The probes identify a watched file or directory by its device and inode,
+//! so at load time each entry of `probes.watched_paths` is stat'ed and put
+//! in `WATCHED_PATHS` under its index. When an open or unlink below one is
+//! seen, the probe leaves the names it walked through in `FILE_PATHS`, and
+//! the listener joins them onto the watched path before the event goes out.
+
+use crate::ProcessEvent;
+use aya::Ebpf;
+use aya::maps::{HashMap, MapData};
+use linnix_ai_ebpf_common::{EventType, FILE_PATH_DEPTH, FilePath, WatchedPathKey};
+use std::os::unix::fs::MetadataExt;
+use std::sync::Mutex;
+
+/// `FILE_PATHS` values. FilePath comes from the common crate, so aya::Pod
+/// can't be implemented for it directly.
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+struct BpfFilePath(FilePath);
+
+// SAFETY: FilePath is #[repr(C)] with explicit padding and byte arrays.
+unsafe impl aya::Pod for BpfFilePath {}
+
+#[repr(transparent)]
+#[derive(Copy, Clone)]
+struct BpfWatchedPathKey(WatchedPathKey);
+
+// SAFETY: WatchedPathKey is #[repr(C)] with explicit padding.
+unsafe impl aya::Pod for BpfWatchedPathKey {}
+
+pub struct FilePaths {
+    map: Mutex<HashMap<MapData, u64, BpfFilePath>>,
+    /// Watched paths by the ID the probes report.
+    watched: Vec<String>,
+}
+
+impl FilePaths {
+    /// Register `paths` in `WATCHED_PATHS` and take `FILE_PATHS`. `None`
+    /// when no path could be registered or the object lacks the maps.
+    pub fn install(bpf: &mut Ebpf, paths: &[String]) -> Option<Self> {
+        let mut watched_map: HashMap<_, BpfWatchedPathKey, u32> =
+            HashMap::try_from(bpf.map_mut("WATCHED_PATHS")?).ok()?;
+        let mut watched = Vec::new();
+        for path in paths {
+            let meta = match std::fs::metadata(path) {
+                Ok(meta) => meta,
+                Err(e) => {
+                    log::warn!("[file_access] not watching {path}: {e}");
+                    continue;
+                }
+            };
+            let key = WatchedPathKey {
+                dev: kernel_dev(meta.dev()),
+                _pad: 0,
+                ino: meta.ino(),
+            };
+            let id = watched.len() as u32;
+            if let Err(e) = watched_map.insert(BpfWatchedPathKey(key), id, 0) {
+                log::warn!("[file_access] not watching {path}: {e}");
+                continue;
+            }
+            watched.push(path.trim_end_matches('/').to_string());
+        }
+        if watched.is_empty() {
+            return None;
+        }
+        let map = HashMap::try_from(bpf.take_map("FILE_PATHS")?).ok()?;
+        log::info!("[file_access] watching {}", watched.join(", "));
+        Some(Self {
+            map: Mutex::new(map),
+            watched,
+        })
+    }
+
+    /// Path of a FileAccess event, removed from `FILE_PATHS`. `None` for
+    /// other events, or when the entry was already evicted.
+    pub fn resolve(&self, event: &ProcessEvent) -> Option<String> {
+        if event.event_type != EventType::FileAccess as u32 {
+            return None;
+        }
+        let watched = self.watched.get(event.aux2 as usize)?;
+        let mut map = self.map.lock().unwrap_or_else(|e| e.into_inner());
+        let BpfFilePath(path) = map.get(&event.data2, 0).ok()?;
+        let _ = map.remove(&event.data2);
+        Some(join_path(watched, &path))
+    }
+}
+
+/// The watched path followed by the recorded components, which the probe
+/// stores leaf first.
+fn join_path(watched: &str, path: &FilePath) -> String {
+    let mut joined = watched.to_string();
+    let depth = (path.depth as usize).min(FILE_PATH_DEPTH);
+    for name in path.names[..depth].iter().rev() {
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        joined.push('/');
+        joined.push_str(&String::from_utf8_lossy(&name[..len]));
+    }
+    joined
+}
+
+/// `st_dev` as the kernel keeps it in `super_block.s_dev`: userspace
+/// splits major and minor over the 64 bits, the kernel packs them as
+/// 12 bits of major over 20 of minor.
+fn kernel_dev(st_dev: u64) -> u32 {
+    let major = ((st_dev >> 8) & 0xfff) | ((st_dev >> 32) & !0xfff);
+    let minor = (st_dev & 0xff) | ((st_dev >> 12) & !0xff);
+    ((major << 20) | (minor & 0xf_ffff)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linnix_ai_ebpf_common::FILE_PATH_NAME_LEN;
+
+    fn file_path(names: &[&str]) -> FilePath {
+        let mut path = FilePath {
+            depth: names.len() as u32,
+            _pad: 0,
+            names: [[0; FILE_PATH_NAME_LEN]; FILE_PATH_DEPTH],
+        };
+        for (slot, name) in path.names.iter_mut().zip(names) {
+            slot[..name.len()].copy_from_slice(name.as_bytes());
+        }
+        path
+    }
+
+    #[test]
+    fn joins_components_below_the_watched_path() {
+        assert_eq!(
+            join_path("/root/.ssh", &file_path(&["authorized_keys"])),
+            "/root/.ssh/authorized_keys"
+        );
+        assert_eq!(
+            join_path("/etc/sudoers.d", &file_path(&["rules", "extra"])),
+            "/etc/sudoers.d/extra/rules"
+        );
+        assert_eq!(join_path("/etc/shadow", &file_path(&[])), "/etc/shadow");
+    }
+
+    #[test]
+    fn converts_st_dev_to_kernel_encoding() {
+        // 8:1 (sda1) and 259:3 (nvme0n1p3).
+        assert_eq!(kernel_dev(0x801), (8 << 20) | 1);
+        assert_eq!(kernel_dev(0x10303), (259 << 20) | 3);
+        // Minor numbers above 255 use the high bits of st_dev.
+        assert_eq!(kernel_dev(0x1_00_0f_00), (15 << 20) | 0x1000);
+    }
+}
@@ -0,0 +1,200 @@
+//! Kernel-side event filter (`[runtime]` `exclude_*` and `include_*`).
+//!
+//! The probes look up the current task's TGID in `FILTER_PIDS` and its
+//! cgroup and every ancestor in `FILTER_CGROUPS` before building an event,
+//! so excluded processes and cgroup subtrees cost no buffer space or
+//! userspace work. With any include entry, only included ones are emitted.
+//! OOM kills and mandate decisions are never filtered.
+
+use crate::config::RuntimeConfig;
+use anyhow::Context;
+use aya::Ebpf;
+use aya::maps::{Array, HashMap, MapData};
+use linnix_ai_ebpf_common::{filter_action, filter_mode};
+use log::warn;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// cgroup v2 mount; `*_cgroups` paths are relative to it.
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Filter entries resolved from the config.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FilterSpec {
+    pids: BTreeMap<u32, u8>,
+    cgroups: BTreeMap<u64, u8>,
+}
+
+impl FilterSpec {
+    pub fn from_config(runtime: &RuntimeConfig) -> Self {
+        Self::resolve(runtime, Path::new(CGROUP_ROOT), std::process::id())
+    }
+
+    fn resolve(runtime: &RuntimeConfig, root: &Path, self_pid: u32) -> Self {
+        let mut spec = Self::default();
+        // Excludes go in last so they win over includes of the same entry.
+        for &pid in &runtime.include_pids {
+            spec.pids.insert(pid, filter_action::INCLUDE);
+        }
+        for id in cgroup_ids(&runtime.include_cgroups, root) {
+            spec.cgroups.insert(id, filter_action::INCLUDE);
+        }
+        let own = runtime.exclude_self.then_some(self_pid);
+        for &pid in runtime.exclude_pids.iter().chain(own.iter()) {
+            spec.pids.insert(pid, filter_action::EXCLUDE);
+        }
+        for id in cgroup_ids(&runtime.exclude_cgroups, root) {
+            spec.cgroups.insert(id, filter_action::EXCLUDE);
+        }
+        spec
+    }
+
+    /// Value for the `FILTER_MODE` map.
+    pub fn mode(&self) -> u32 {
+        let mut mode = filter_mode::OFF;
+        let actions = || self.pids.values().chain(self.cgroups.values());
+        if actions().any(|&a| a == filter_action::EXCLUDE) {
+            mode |= filter_mode::EXCLUDE;
+        }
+        if actions().any(|&a| a == filter_action::INCLUDE) {
+            mode |= filter_mode::INCLUDE_ONLY;
+        }
+        mode
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pids.is_empty() && self.cgroups.is_empty()
+    }
+}
+
+/// cgroup v2 IDs of `paths`: the inode numbers of their directories.
+/// Cgroups that don't exist (yet) are skipped with a warning.
+fn cgroup_ids<'a>(paths: &'a [String], root: &'a Path) -> impl Iterator<Item = u64> + 'a {
+    paths.iter().filter_map(move |path| {
+        let dir: PathBuf = if Path::new(path).is_absolute() {
+            path.into()
+        } else {
+            root.join(path)
+        };
+        match std::fs::metadata(&dir) {
+            Ok(meta) if meta.is_dir() => Some(meta.ino()),
+            Ok(_) => {
+                warn!("[filter] {} is not a cgroup directory", dir.display());
+                None
+            }
+            Err(e) => {
+                warn!("[filter] skipping cgroup {}: {e}", dir.display());
+                None
+            }
+        }
+    })
+}
+
+/// Write `spec` to the filter maps of a loaded BPF object, replacing what
+/// they held. The mode is written last so the probes never act on a
+/// half-written filter.
+pub fn apply(bpf: &mut Ebpf, spec: &FilterSpec) -> anyhow::Result<()> {
+    let Some(mode_map) = bpf.map_mut("FILTER_MODE") else {
+        if spec.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!("BPF object has no FILTER_MODE map; rebuild it to filter in-kernel");
+    };
+    let mut mode: Array<&mut MapData, u32> = Array::try_from(mode_map)?;
+    mode.set(0, filter_mode::OFF, 0)
+        .context("failed to reset FILTER_MODE")?;
+
+    let mut pids: HashMap<&mut MapData, u32, u8> = HashMap::try_from(
+        bpf.map_mut("FILTER_PIDS")
+            .context("FILTER_PIDS not found")?,
+    )?;
+    sync(&mut pids, &spec.pids).context("failed to update FILTER_PIDS")?;
+    let mut cgroups: HashMap<&mut MapData, u64, u8> = HashMap::try_from(
+        bpf.map_mut("FILTER_CGROUPS")
+            .context("FILTER_CGROUPS not found")?,
+    )?;
+    sync(&mut cgroups, &spec.cgroups).context("failed to update FILTER_CGROUPS")?;
+
+    let mut mode: Array<&mut MapData, u32> = Array::try_from(
+        bpf.map_mut("FILTER_MODE")
+            .context("FILTER_MODE not found")?,
+    )?;
+    mode.set(0, spec.mode(), 0)
+        .context("failed to set FILTER_MODE")?;
+    Ok(())
+}
+
+fn sync<K>(map: &mut HashMap<&mut MapData, K, u8>, want: &BTreeMap<K, u8>) -> anyhow::Result<()>
+where
+    K: aya::Pod + Ord + Hash + Eq,
+{
+    let stale: Vec<K> = map
+        .keys()
+        .filter_map(Result::ok)
+        .filter(|key| !want.contains_key(key))
+        .collect();
+    for key in stale {
+        map.remove(&key)?;
+    }
+    for (key, action) in want {
+        map.insert(key, action, 0)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_win_and_set_the_mode() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("system.slice/noisy.service")).unwrap();
+        std::fs::create_dir_all(root.path().join("kubepods.slice")).unwrap();
+        let ino = |p: &str| std::fs::metadata(root.path().join(p)).unwrap().ino();
+
+        let runtime = RuntimeConfig {
+            exclude_pids: vec![42],
+            exclude_cgroups: vec!["system.slice/noisy.service".into(), "missing.slice".into()],
+            ..RuntimeConfig::default()
+        };
+        let spec = FilterSpec::resolve(&runtime, root.path(), 7);
+        assert_eq!(spec.pids.get(&42), Some(&filter_action::EXCLUDE));
+        assert_eq!(spec.pids.get(&7), Some(&filter_action::EXCLUDE));
+        assert_eq!(
+            spec.cgroups.get(&ino("system.slice/noisy.service")),
+            Some(&filter_action::EXCLUDE)
+        );
+        assert_eq!(spec.cgroups.len(), 1);
+        assert_eq!(spec.mode(), filter_mode::EXCLUDE);
+
+        let runtime = RuntimeConfig {
+            exclude_self: false,
+            include_cgroups: vec!["kubepods.slice".into()],
+            include_pids: vec![42],
+            exclude_pids: vec![42],
+            ..RuntimeConfig::default()
+        };
+        let spec = FilterSpec::resolve(&runtime, root.path(), 7);
+        assert_eq!(spec.pids.get(&42), Some(&filter_action::EXCLUDE));
+        assert!(!spec.pids.contains_key(&7));
+        assert_eq!(
+            spec.cgroups.get(&ino("kubepods.slice")),
+            Some(&filter_action::INCLUDE)
+        );
+        assert_eq!(
+            spec.mode(),
+            filter_mode::EXCLUDE | filter_mode::INCLUDE_ONLY
+        );
+
+        let runtime = RuntimeConfig {
+            exclude_self: false,
+            ..RuntimeConfig::default()
+        };
+        let spec = FilterSpec::resolve(&runtime, root.path(), 7);
+        assert!(spec.is_empty());
+        assert_eq!(spec.mode(), filter_mode::OFF);
+    }
+}
@@ -0,0 +1,77 @@
+//! Poller of the `LIFECYCLE_COUNTS` per-CPU map.
+//!
+//! The fork and exec probes count every process they see per CPU, before
+//! the event is written. Once a second the totals are summed over the CPUs
+//! and the increase is recorded in [`KernelCounts`] for the rule engine.
+
+use crate::kernel_counts::KernelCounts;
+use crate::runtime::EventWindow;
+use aya::Ebpf;
+use aya::maps::{MapData, PerCpuArray};
+use linnix_ai_ebpf_common::lifecycle_counter;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct LifecycleCounters {
+    map: PerCpuArray<MapData, u64>,
+    /// Fork and exec totals at the previous read.
+    seen: [u64; lifecycle_counter::COUNT as usize],
+}
+
+impl LifecycleCounters {
+    /// Take the counter map out of a loaded BPF object. `None` for objects
+    /// built without it.
+    pub fn take(bpf: &mut Ebpf) -> Option<Self> {
+        let map = bpf.take_map("LIFECYCLE_COUNTS")?;
+        Some(Self {
+            map: PerCpuArray::try_from(map).ok()?,
+            seen: [0; lifecycle_counter::COUNT as usize],
+        })
+    }
+
+    /// Forks and execs since the previous call.
+    fn delta(&mut self) -> (u64, u64) {
+        let mut delta = [0; lifecycle_counter::COUNT as usize];
+        for (slot, seen) in self.seen.iter_mut().enumerate() {
+            let Ok(per_cpu) = self.map.get(&(slot as u32), 0) else {
+                continue;
+            };
+            let total: u64 = per_cpu.iter().sum();
+            delta[slot] = total.saturating_sub(*seen);
+            *seen = total;
+        }
+        (
+            delta[lifecycle_counter::FORK as usize],
+            delta[lifecycle_counter::EXEC as usize],
+        )
+    }
+
+    /// Record the counts every second until `stopped` is set. Counts from
+    /// before `window` opens are left to the running generation.
+    pub fn spawn_poller(
+        mut self,
+        counts: Arc<KernelCounts>,
+        window: Arc<EventWindow>,
+        mut stopped: watch::Receiver<bool>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = tick.tick() => {
+                        let (forks, execs) = self.delta();
+                        if !window.is_pending() {
+                            counts.record(Instant::now(), forks, execs);
+                        }
+                    }
+                    Ok(()) = stopped.changed() => return,
+                }
+            }
+        })
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::{HashMap, VecDeque};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
 
 const DEFAULT_TTL: Duration = Duration::from_secs(60);
 const DEFAULT_CAPACITY: usize = 8_192;
@@ -28,11 +29,21 @@ impl LineageCache {
         }
     }
 
-    pub async fn record_fork(&self, child: u32, parent: u32) {
+    /// Record `child` under `parent`, and each of `ancestors` (the parent's
+    /// parent first, as Fork events carry them) under the next one, up to
+    /// the first 0.
+    pub async fn record_fork(&self, child: u32, parent: u32, ancestors: &[u32]) {
         let now = Instant::now();
         let mut guard = self.inner.lock().await;
-        guard.entries.insert(child, (parent, now));
-        guard.order.push_back((child, now));
+        let mut pid = child;
+        for &next in std::iter::once(&parent).chain(ancestors) {
+            if next == 0 {
+                break;
+            }
+            guard.entries.insert(pid, (next, now));
+            guard.order.push_back((pid, now));
+            pid = next;
+        }
         guard.purge(now, self.ttl, self.capacity);
     }
 
@@ -93,3 +104,22 @@ impl LineageInner {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fork_records_the_whole_ancestor_chain() {
+        let cache = LineageCache::default();
+        // sshd (1200) -> bash (1300) -> make (1400) forks cc (1500).
+        cache.record_fork(1500, 1400, &[1300, 1200, 1, 0]).await;
+
+        assert_eq!(cache.lookup(1500).await, Some(1400));
+        // The parents' own forks were never seen.
+        assert_eq!(cache.lookup(1400).await, Some(1300));
+        assert_eq!(cache.lookup(1300).await, Some(1200));
+        assert_eq!(cache.lookup(1200).await, Some(1));
+        assert_eq!(cache.lookup(1).await, None);
+    }
+}
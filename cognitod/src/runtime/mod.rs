@@ -1,10 +1,18 @@
 #![allow(unused_imports)]
+pub mod argv;
+pub mod file_paths;
+pub mod filter;
+pub mod lifecycle_counts;
 pub mod lineage;
 pub mod probes;
+pub mod sched_latency;
 pub mod sequencer;
 pub mod stream_listener;
+pub mod throttle;
 
 pub use sequencer::{
     OrderingValidator, SequencerConsumer, SequencerStats, disable_sequencer, enable_sequencer,
 };
-pub use stream_listener::start_perf_listener;
+pub use stream_listener::{
+    EventBuffers, EventListener, EventPipeline, EventWindow, start_event_listener,
+};
@@ -26,9 +26,30 @@ impl RssProbeMode {
     }
 }
 
+/// Where the block I/O probes read their fields from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockProbeMode {
+    /// BTF tracepoints reading `struct bio`/`struct request`.
+    Btf,
+    /// Classic tracepoints, for kernels without the BTF layout.
+    Tracepoint,
+    Disabled,
+}
+
+impl BlockProbeMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BlockProbeMode::Btf => "btf_tracepoint",
+            BlockProbeMode::Tracepoint => "tracepoint",
+            BlockProbeMode::Disabled => "disabled",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct ProbeState {
     pub rss_probe: RssProbeMode,
+    pub block_probe: BlockProbeMode,
     pub btf_available: bool,
 }
 
@@ -36,6 +57,7 @@ impl ProbeState {
     pub const fn disabled() -> Self {
         Self {
             rss_probe: RssProbeMode::Disabled,
+            block_probe: BlockProbeMode::Disabled,
             btf_available: false,
         }
     }
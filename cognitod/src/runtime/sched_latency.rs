@@ -0,0 +1,109 @@
+//! Runqueue latency histogram filled by the `sched_switch` probe.
+//!
+//! `SCHED_LATENCY_HIST` counts every delay between a thread becoming
+//! runnable and running, per CPU in log2 microsecond buckets. It is read
+//! periodically and the p95 of the delays since the previous read goes to
+//! the metrics; single delays above `probes.sched_latency_threshold_us`
+//! arrive as SchedLatency events instead.
+
+use crate::metrics::Metrics;
+use aya::Ebpf;
+use aya::maps::{MapData, PerCpuArray};
+use linnix_ai_ebpf_common::SCHED_LATENCY_BUCKETS;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// How often the histogram is read; the p95 covers this interval.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct SchedLatencyHistogram {
+    map: PerCpuArray<MapData, u64>,
+    /// Bucket totals at the previous read.
+    seen: [u64; SCHED_LATENCY_BUCKETS as usize],
+}
+
+impl SchedLatencyHistogram {
+    /// Take the histogram map out of a loaded BPF object. `None` for
+    /// objects built without the scheduler probes.
+    pub fn take(bpf: &mut Ebpf) -> Option<Self> {
+        let map = bpf.take_map("SCHED_LATENCY_HIST")?;
+        Some(Self {
+            map: PerCpuArray::try_from(map).ok()?,
+            seen: [0; SCHED_LATENCY_BUCKETS as usize],
+        })
+    }
+
+    /// Delays per bucket since the previous call.
+    fn delta(&mut self) -> [u64; SCHED_LATENCY_BUCKETS as usize] {
+        let mut delta = [0; SCHED_LATENCY_BUCKETS as usize];
+        for (bucket, seen) in self.seen.iter_mut().enumerate() {
+            let Ok(per_cpu) = self.map.get(&(bucket as u32), 0) else {
+                continue;
+            };
+            let total: u64 = per_cpu.iter().sum();
+            delta[bucket] = total.saturating_sub(*seen);
+            *seen = total;
+        }
+        delta
+    }
+
+    /// Publish the p95 to `metrics` every interval until `stopped` is set.
+    pub fn spawn_poller(
+        mut self,
+        metrics: Arc<Metrics>,
+        mut stopped: watch::Receiver<bool>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = tick.tick() => {
+                        let delta = self.delta();
+                        let samples = delta.iter().sum();
+                        let p95 = quantile_us(&delta, 0.95).unwrap_or(0);
+                        metrics.set_sched_latency(p95, samples);
+                    }
+                    Ok(()) = stopped.changed() => return,
+                }
+            }
+        })
+    }
+}
+
+/// Upper bound in microseconds of the bucket holding quantile `q` of the
+/// delays counted in `buckets`; `None` without any.
+pub fn quantile_us(buckets: &[u64], q: f64) -> Option<u64> {
+    let total: u64 = buckets.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let rank = ((total as f64 * q).ceil() as u64).max(1);
+    let mut seen = 0;
+    for (bucket, count) in buckets.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return Some(1u64 << (bucket + 1).min(63));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantile_is_the_upper_bound_of_its_bucket() {
+        let mut buckets = [0u64; SCHED_LATENCY_BUCKETS as usize];
+        assert_eq!(quantile_us(&buckets, 0.95), None);
+        // 90 delays of 4-7 µs, 8 of 1-2 ms, 2 of 16-32 ms.
+        buckets[2] = 90;
+        buckets[10] = 8;
+        buckets[14] = 2;
+        assert_eq!(quantile_us(&buckets, 0.5), Some(8));
+        assert_eq!(quantile_us(&buckets, 0.95), Some(2048));
+        assert_eq!(quantile_us(&buckets, 0.99), Some(32_768));
+    }
+}
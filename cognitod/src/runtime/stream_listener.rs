@@ -2,18 +2,26 @@
 use crate::config::OfflineGuard;
 use crate::context::ContextStore;
 use crate::handler::HandlerList;
+use crate::kernel_counts::KernelCounts;
 use crate::metrics::Metrics;
+use crate::quota::EventQuota;
+use crate::runtime::argv::ArgvCache;
+use crate::runtime::file_paths::FilePaths;
+use crate::runtime::lifecycle_counts::LifecycleCounters;
 use crate::runtime::lineage::LineageCache;
+use crate::runtime::sched_latency::SchedLatencyHistogram;
 use crate::{ProcessEvent, ProcessEventWire};
 use aya::maps::perf::PerfEventArrayBuffer;
-use aya::maps::{MapData, ring_buf::RingBuf};
+use aya::maps::{MapData, PerCpuArray, ring_buf::RingBuf};
 use bytes::BytesMut;
-use linnix_ai_ebpf_common::EventType;
-use std::{io, mem, ptr, sync::Arc, thread, time::Duration};
+use linnix_ai_ebpf_common::{EventType, fork_ancestors};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{io, ptr, sync::Arc, time::Duration};
 use tokio::io::unix::AsyncFd;
-use tokio::runtime::Handle;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 
-fn event_label(kind: u32) -> &'static str {
+pub(crate) fn event_label(kind: u32) -> &'static str {
     match kind {
         x if x == EventType::Exec as u32 => "Exec",
         x if x == EventType::Fork as u32 => "Fork",
@@ -25,83 +33,286 @@ fn event_label(kind: u32) -> &'static str {
         x if x == EventType::PageFault as u32 => "PageFault",
         x if x == EventType::MandateAllow as u32 => "MandateAllow",
         x if x == EventType::MandateDeny as u32 => "MandateDeny",
+        x if x == EventType::OomKill as u32 => "OomKill",
+        x if x == EventType::SchedLatency as u32 => "SchedLatency",
+        x if x == EventType::OffCpu as u32 => "OffCpu",
+        x if x == EventType::Connection as u32 => "Connection",
+        x if x == EventType::DirectReclaim as u32 => "DirectReclaim",
+        x if x == EventType::Signal as u32 => "Signal",
+        x if x == EventType::Creds as u32 => "Creds",
+        x if x == EventType::MemGrowth as u32 => "MemGrowth",
+        x if x == EventType::FileAccess as u32 => "FileAccess",
+        x if x == EventType::KernelLoad as u32 => "KernelLoad",
         _ => "Unknown",
     }
 }
 
-#[allow(dead_code)]
-pub fn start_listener(
-    mut ringbuf: RingBuf<MapData>,
-    context: Arc<ContextStore>,
-    metrics: Arc<Metrics>,
-    handlers: Arc<HandlerList>,
-    _offline: Arc<OfflineGuard>,
-    rate_cap: u64,
-) {
-    println!("[cognitod] Starting listener for BPF ring buffer...");
-    tokio::task::spawn_blocking(move || {
-        let rt_handle = Handle::current();
-        let handlers = handlers.clone();
+/// Everything an event reader feeds events into. Shared by successive
+/// generations of readers when the BPF programs are reloaded, so lineage
+/// and argv state carry over.
+#[derive(Clone)]
+pub struct EventPipeline {
+    pub context: Arc<ContextStore>,
+    pub metrics: Arc<Metrics>,
+    pub handlers: Arc<HandlerList>,
+    pub rate_cap: u64,
+    pub argv: Option<Arc<ArgvCache>>,
+    pub lineage: Arc<LineageCache>,
+    /// Per-pod and per-namespace quotas (`[quotas]`), checked after the
+    /// global rate cap.
+    pub quota: Option<Arc<EventQuota>>,
+    /// Fork and exec counts polled from the probes, shared with the rules.
+    pub kernel_counts: Arc<KernelCounts>,
+    /// Paths captured by the file access probes. Unlike the rest this
+    /// belongs to one loaded BPF object, so each generation gets its own.
+    pub file_paths: Option<Arc<FilePaths>>,
+}
+
+/// The buffers the probes of one loaded BPF object write events to.
+pub enum EventBuffers {
+    /// The shared `EVENTS_RB` ring buffer, with the per-CPU count of events
+    /// that found it full (absent in older BPF objects).
+    RingBuf {
+        ring: RingBuf<MapData>,
+        drops: Option<PerCpuArray<MapData, u64>>,
+    },
+    /// One `EVENTS` perf buffer per online CPU.
+    Perf(Vec<PerfEventArrayBuffer<MapData>>),
+}
+
+impl EventBuffers {
+    /// Transport name reported in `/status`.
+    pub fn transport(&self) -> &'static str {
+        match self {
+            Self::RingBuf { .. } => "ringbuf",
+            Self::Perf(_) => "perf",
+        }
+    }
+}
+
+/// The events, by `ts_ns`, one generation of readers dispatches.
+///
+/// During a reload the old and new programs are attached together and both
+/// write the events they see. Splitting at a cutover time, the old readers
+/// dispatch what happened before it and the new ones what happened from it
+/// on, so each event is dispatched once.
+pub struct EventWindow {
+    from_ns: AtomicU64,
+    until_ns: AtomicU64,
+}
+
+impl EventWindow {
+    /// Every event: the only generation attached.
+    pub fn all() -> Self {
+        Self {
+            from_ns: AtomicU64::new(0),
+            until_ns: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// No event until [`EventListener::hand_over`] opens it: a generation
+    /// started next to running readers.
+    pub fn pending() -> Self {
+        Self {
+            from_ns: AtomicU64::new(u64::MAX),
+            until_ns: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// Not opened yet by a handover.
+    pub(crate) fn is_pending(&self) -> bool {
+        self.from_ns.load(Ordering::Acquire) == u64::MAX
+    }
+
+    fn contains(&self, ts_ns: u64) -> bool {
+        self.from_ns.load(Ordering::Acquire) <= ts_ns
+            && ts_ns < self.until_ns.load(Ordering::Acquire)
+    }
+
+    /// Close `self` and open `next` at `cutover_ns`.
+    fn hand_over(&self, next: &EventWindow, cutover_ns: u64) {
+        next.from_ns.store(cutover_ns, Ordering::Release);
+        self.until_ns.store(cutover_ns, Ordering::Release);
+    }
+}
+
+/// Now on the clock the probes stamp events with (`bpf_ktime_get_ns`).
+fn monotonic_now_ns() -> u64 {
+    use nix::time::{ClockId, clock_gettime};
+    match clock_gettime(ClockId::CLOCK_MONOTONIC) {
+        Ok(ts) => (ts.tv_sec() as u64) * 1_000_000_000 + (ts.tv_nsec() as u64),
+        Err(_) => 0,
+    }
+}
+
+/// Readers of one set of event buffers.
+pub struct EventListener {
+    stop: watch::Sender<bool>,
+    tasks: Vec<JoinHandle<()>>,
+    window: Arc<EventWindow>,
+}
+
+impl EventListener {
+    /// Let `next`, started with [`EventWindow::pending`] while these
+    /// readers still run, dispatch events from now on; these readers keep
+    /// only the earlier ones. Call before detaching the old programs.
+    pub fn hand_over(&self, next: &EventListener) {
+        self.window.hand_over(&next.window, monotonic_now_ns());
+    }
+
+    /// Stop reading once everything already in the buffers has been
+    /// dispatched. Used after newer programs took over, so events written
+    /// before the old programs were detached aren't lost.
+    pub async fn drain_and_stop(self) {
+        let _ = self.stop.send(true);
+        for task in self.tasks {
+            let _ = task.await;
+        }
+    }
+
+    /// Also publish the runqueue latency p95 from `histogram`, until the
+    /// listener stops.
+    pub fn watch_sched_latency(&mut self, histogram: SchedLatencyHistogram, metrics: Arc<Metrics>) {
+        let stopped = self.stop.subscribe();
+        self.tasks.push(histogram.spawn_poller(metrics, stopped));
+    }
+
+    /// Also record the probes' fork and exec counts into `counts`, until
+    /// the listener stops.
+    pub fn watch_lifecycle_counts(
+        &mut self,
+        counters: LifecycleCounters,
+        counts: Arc<KernelCounts>,
+    ) {
+        let stopped = self.stop.subscribe();
+        let window = Arc::clone(&self.window);
+        self.tasks
+            .push(counters.spawn_poller(counts, window, stopped));
+    }
+}
+
+/// Start reading `buffers`, dispatching the events inside `window`.
+pub fn start_event_listener(
+    buffers: EventBuffers,
+    pipeline: &EventPipeline,
+    window: EventWindow,
+) -> EventListener {
+    let (stop, stopped) = watch::channel(false);
+    let window = Arc::new(window);
+    let tasks = match buffers {
+        EventBuffers::RingBuf { ring, drops } => {
+            println!("[cognitod] Starting listener for BPF ring buffer...");
+            vec![spawn_ring_buf_reader(
+                ring,
+                drops,
+                pipeline,
+                Arc::clone(&window),
+                stopped,
+            )]
+        }
+        EventBuffers::Perf(buffers) => {
+            println!("[cognitod] Starting listener for BPF perf buffers...");
+            spawn_perf_readers(buffers, pipeline, Arc::clone(&window), stopped)
+        }
+    };
+    EventListener {
+        stop,
+        tasks,
+        window,
+    }
+}
+
+/// How often the ring buffer's drop counters are read.
+const RING_BUF_DROP_POLL: Duration = Duration::from_secs(1);
+
+fn spawn_ring_buf_reader(
+    ring: RingBuf<MapData>,
+    drops: Option<PerCpuArray<MapData, u64>>,
+    pipeline: &EventPipeline,
+    window: Arc<EventWindow>,
+    mut stopped: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    let pipeline = pipeline.clone();
+    tokio::spawn(async move {
+        let mut async_ring = match AsyncFd::new(ring) {
+            Ok(fd) => fd,
+            Err(e) => {
+                log::error!("failed to create AsyncFd for ring buffer: {e}");
+                return;
+            }
+        };
+        let mut drop_poll = tokio::time::interval(RING_BUF_DROP_POLL);
+        let mut drops_seen = 0;
+
         loop {
-            if let Some(data) = ringbuf.next() {
-                if let Some(event) = parse_event(data.as_ref()) {
-                    let metrics_clone = metrics.clone();
-                    if !metrics_clone.record_event(rate_cap, event.event_type) {
-                        continue;
+            tokio::select! {
+                ready = async_ring.readable_mut() => match ready {
+                    Ok(mut guard) => {
+                        // Clear first: a record written while draining
+                        // marks the fd ready again instead of being missed.
+                        guard.clear_ready();
+                        drain_ring_buf(guard.get_inner_mut(), &pipeline, &window);
+                    }
+                    Err(e) => {
+                        log::warn!("ring buffer readable wait failed: {e}");
+                        pipeline.metrics.inc_perf_poll_error();
+                        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
                     }
-                    let comm = std::str::from_utf8(&event.comm)
-                        .unwrap_or("invalid")
-                        .trim_end_matches('\0')
-                        .to_string();
-
-                    // Process event asynchronously
-                    let context_clone = context.clone();
-                    let event_for_llm = event.clone();
-                    let handlers_clone = handlers.clone();
-                    rt_handle.spawn(async move {
-                        println!(
-                            "[event] type={:?} pid={} ppid={} uid={} gid={} comm={}",
-                            event_label(event_for_llm.event_type),
-                            event_for_llm.pid,
-                            event_for_llm.ppid,
-                            event_for_llm.uid,
-                            event_for_llm.gid,
-                            comm
-                        );
-                        handlers_clone.on_event(&event_for_llm).await;
-                        context_clone.add(event_for_llm);
-                    });
-                } else {
-                    metrics.inc_rb_overflow();
-                    println!("[cognitod] Failed to parse event");
+                },
+                _ = drop_poll.tick() => {
+                    drops_seen = record_ring_buf_drops(drops.as_ref(), drops_seen, &pipeline);
+                }
+                // Only ever set to true; a dropped sender disables this.
+                Ok(()) = stopped.changed() => {
+                    // The programs writing here are detached; read what is
+                    // left and exit.
+                    drain_ring_buf(async_ring.get_mut(), &pipeline, &window);
+                    record_ring_buf_drops(drops.as_ref(), drops_seen, &pipeline);
+                    return;
                 }
-            } else {
-                metrics.inc_rb_overflow();
-                thread::sleep(Duration::from_millis(1));
             }
         }
-    });
+    })
 }
 
-pub fn start_perf_listener(
-    buffers: Vec<PerfEventArrayBuffer<MapData>>,
-    context: Arc<ContextStore>,
-    metrics: Arc<Metrics>,
-    handlers: Arc<HandlerList>,
-    _offline: Arc<OfflineGuard>,
-    rate_cap: u64,
-) {
-    println!("[cognitod] Starting listener for BPF perf buffers...");
+fn drain_ring_buf(ring: &mut RingBuf<MapData>, pipeline: &EventPipeline, window: &EventWindow) {
+    while let Some(record) = ring.next() {
+        dispatch_record(&record, pipeline, window);
+    }
+}
 
-    let lineage_cache: Arc<LineageCache> = Arc::new(LineageCache::default());
+/// Add drops counted by the probes since `seen` to the metrics and return
+/// the new total.
+fn record_ring_buf_drops(
+    drops: Option<&PerCpuArray<MapData, u64>>,
+    seen: u64,
+    pipeline: &EventPipeline,
+) -> u64 {
+    let Some(total) = drops
+        .and_then(|drops| drops.get(&0, 0).ok())
+        .map(|per_cpu| per_cpu.iter().sum::<u64>())
+    else {
+        return seen;
+    };
+    pipeline
+        .metrics
+        .record_kernel_drops(total.saturating_sub(seen));
+    total.max(seen)
+}
 
+fn spawn_perf_readers(
+    buffers: Vec<PerfEventArrayBuffer<MapData>>,
+    pipeline: &EventPipeline,
+    window: Arc<EventWindow>,
+    stopped: watch::Receiver<bool>,
+) -> Vec<JoinHandle<()>> {
+    let mut tasks = Vec::with_capacity(buffers.len());
     for buffer in buffers {
-        let context = Arc::clone(&context);
-        let metrics = Arc::clone(&metrics);
-        let handlers = Arc::clone(&handlers);
-        let lineage = Arc::clone(&lineage_cache);
+        let pipeline = pipeline.clone();
+        let window = Arc::clone(&window);
+        let mut stopped = stopped.clone();
 
-        tokio::spawn(async move {
+        tasks.push(tokio::spawn(async move {
             let mut async_buffer = match AsyncFd::new(buffer) {
                 Ok(fd) => fd,
                 Err(e) => {
@@ -116,13 +327,28 @@ pub fn start_perf_listener(
                 .collect();
 
             loop {
-                let mut ready = match async_buffer.readable_mut().await {
-                    Ok(guard) => guard,
-                    Err(e) => {
-                        log::warn!("perf buffer readable wait failed: {e}");
-                        metrics.inc_perf_poll_error();
-                        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-                        continue;
+                let mut ready = tokio::select! {
+                    ready = async_buffer.readable_mut() => match ready {
+                        Ok(guard) => guard,
+                        Err(e) => {
+                            log::warn!("perf buffer readable wait failed: {e}");
+                            pipeline.metrics.inc_perf_poll_error();
+                            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+                            continue;
+                        }
+                    },
+                    // Only ever set to true; a dropped sender disables this.
+                    Ok(()) = stopped.changed() => {
+                        // The programs writing here are detached; read what
+                        // is left and exit.
+                        loop {
+                            match async_buffer.get_mut().read_events(scratch.as_mut_slice()) {
+                                Ok(events) if events.read > 0 => {
+                                    dispatch_batch(&mut scratch, events.read, &pipeline, &window)
+                                }
+                                _ => return,
+                            }
+                        }
                     }
                 };
 
@@ -136,7 +362,7 @@ pub fn start_perf_listener(
                     Ok(Err(e)) => {
                         ready.clear_ready();
                         log::warn!("perf.read_events error: {e}");
-                        metrics.inc_perf_poll_error();
+                        pipeline.metrics.inc_perf_poll_error();
                         tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
                         continue;
                     }
@@ -147,84 +373,173 @@ pub fn start_perf_listener(
                 };
                 ready.clear_ready();
 
-                if events.lost > 0 {
-                    metrics.inc_rb_overflow();
-                }
-
-                for buf in scratch.iter_mut().take(events.read) {
-                    if buf.len() < mem::size_of::<ProcessEventWire>() {
-                        buf.clear();
-                        continue;
-                    }
+                pipeline.metrics.record_kernel_drops(events.lost as u64);
 
-                    let event_wire: ProcessEventWire =
-                        unsafe { ptr::read_unaligned(buf.as_ptr() as *const ProcessEventWire) };
-                    buf.clear();
+                dispatch_batch(&mut scratch, events.read, &pipeline, &window);
+            }
+        }));
+    }
+    tasks
+}
 
-                    if !metrics.record_event(rate_cap, event_wire.event_type) {
-                        continue;
-                    }
+/// Decode the first `read` records of `scratch` and dispatch each on its own
+/// task.
+fn dispatch_batch(
+    scratch: &mut [BytesMut],
+    read: usize,
+    pipeline: &EventPipeline,
+    window: &EventWindow,
+) {
+    for buf in scratch.iter_mut().take(read) {
+        dispatch_record(buf, pipeline, window);
+        buf.clear();
+    }
+}
 
-                    let mut event_for_llm = ProcessEvent::new(event_wire);
-                    let comm = std::str::from_utf8(&event_for_llm.comm)
-                        .unwrap_or("invalid")
-                        .trim_end_matches('\0')
-                        .to_string();
-
-                    log::debug!(
-                        "[perf] received event type={:?} pid={} ppid={} comm={}",
-                        event_label(event_for_llm.event_type),
-                        event_for_llm.pid,
-                        event_for_llm.ppid,
-                        comm
-                    );
-
-                    let metrics_for_llm = Arc::clone(&metrics);
-                    let handlers_clone = Arc::clone(&handlers);
-                    let context_clone = Arc::clone(&context);
-                    let lineage_clone = Arc::clone(&lineage);
-
-                    tokio::spawn(async move {
-                        if event_for_llm.event_type == EventType::Fork as u32 {
-                            lineage_clone
-                                .record_fork(event_for_llm.pid, event_for_llm.ppid)
-                                .await;
-                        } else if event_for_llm.ppid == 0 {
-                            match lineage_clone.lookup(event_for_llm.pid).await {
-                                Some(ppid) => {
-                                    event_for_llm.ppid = ppid;
-                                    metrics_for_llm.inc_lineage_hit();
-                                }
-                                None => {
-                                    metrics_for_llm.inc_lineage_miss();
-                                }
-                            }
-                        }
+/// Decode one record and, if inside `window`, dispatch it on its own task.
+fn dispatch_record(bytes: &[u8], pipeline: &EventPipeline, window: &EventWindow) {
+    let Some(mut event) = parse_event(bytes) else {
+        return;
+    };
+    // The other generation dispatches it; don't count it twice either.
+    if !window.contains(event.ts_ns) {
+        return;
+    }
+    if !pipeline
+        .metrics
+        .record_event(pipeline.rate_cap, event.event_type)
+    {
+        return;
+    }
+    if let Some(quota) = &pipeline.quota
+        && !quota.admit(&event)
+    {
+        pipeline.metrics.inc_quota_suppressed();
+        return;
+    }
 
-                        println!(
-                            "[event] type={:?} pid={} ppid={} uid={} gid={} comm={}",
-                            event_label(event_for_llm.event_type),
-                            event_for_llm.pid,
-                            event_for_llm.ppid,
-                            event_for_llm.uid,
-                            event_for_llm.gid,
-                            comm
-                        );
-                        handlers_clone.on_event(&event_for_llm).await;
-                        context_clone.add(event_for_llm);
-                    });
-                }
-            }
-        });
+    // Take the path out of FILE_PATHS before the LRU can evict it.
+    if let Some(file_paths) = &pipeline.file_paths {
+        event.path = file_paths.resolve(&event);
     }
+
+    log::debug!(
+        "[bpf] received event type={:?} pid={} ppid={} comm={}",
+        event_label(event.event_type),
+        event.pid,
+        event.ppid,
+        comm_str(&event)
+    );
+
+    let pipeline = pipeline.clone();
+    tokio::spawn(async move {
+        dispatch_event(
+            event,
+            &pipeline.handlers,
+            &pipeline.context,
+            Some(&pipeline.lineage),
+            pipeline.argv.as_deref(),
+            Some(&pipeline.metrics),
+        )
+        .await;
+    });
 }
 
-#[allow(dead_code)]
-fn parse_event(bytes: &[u8]) -> Option<ProcessEvent> {
+fn comm_str(event: &ProcessEvent) -> &str {
+    std::str::from_utf8(&event.comm)
+        .unwrap_or("invalid")
+        .trim_end_matches('\0')
+}
+
+/// Decode one record as the probes write it.
+pub fn parse_event(bytes: &[u8]) -> Option<ProcessEvent> {
     if bytes.len() < std::mem::size_of::<ProcessEventWire>() {
         return None;
     }
-    let ptr = bytes.as_ptr() as *const ProcessEventWire;
-    let raw = unsafe { *ptr };
+    let raw = unsafe { ptr::read_unaligned(bytes.as_ptr() as *const ProcessEventWire) };
     Some(ProcessEvent::new(raw))
 }
+
+/// Enrich a decoded event and run it through the handlers and the context
+/// store. With a lineage cache, forks are recorded and events that arrive
+/// without a parent get it filled in; with an argv cache, exec events (and
+/// later events of the same process) get their command line.
+pub async fn dispatch_event(
+    mut event: ProcessEvent,
+    handlers: &HandlerList,
+    context: &ContextStore,
+    lineage: Option<&LineageCache>,
+    argv: Option<&ArgvCache>,
+    metrics: Option<&Metrics>,
+) {
+    if let Some(lineage) = lineage {
+        if event.event_type == EventType::Fork as u32 {
+            let ancestors = fork_ancestors(event.data, event.data2);
+            lineage.record_fork(event.pid, event.ppid, &ancestors).await;
+        } else if event.ppid == 0 {
+            match lineage.lookup(event.pid).await {
+                Some(ppid) => {
+                    event.ppid = ppid;
+                    if let Some(m) = metrics {
+                        m.inc_lineage_hit();
+                    }
+                }
+                None => {
+                    if let Some(m) = metrics {
+                        m.inc_lineage_miss();
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(argv) = argv {
+        event.argv = if event.event_type == EventType::Exec as u32 {
+            argv.on_exec(event.pid, comm_str(&event))
+        } else {
+            argv.lookup(event.pid)
+        };
+    }
+
+    println!(
+        "[event] type={:?} pid={} ppid={} uid={} gid={} comm={}",
+        event_label(event.event_type),
+        event.pid,
+        event.ppid,
+        event.uid,
+        event.gid,
+        comm_str(&event)
+    );
+    handlers.on_event(&event).await;
+    context.add(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hand_over_dispatches_overlapping_events_once() {
+        let old = EventWindow::all();
+        let new = EventWindow::pending();
+        assert!(new.is_pending() && !old.is_pending());
+        // Both generations attached: the same kernel events reach both,
+        // stamped a few nanoseconds apart.
+        let seen = [(900, 902), (1_000, 1_001), (1_100, 1_104)];
+        assert!(
+            seen.iter()
+                .all(|&(a, b)| old.contains(a) && !new.contains(b))
+        );
+
+        old.hand_over(&new, 1_000);
+        let dispatched: Vec<_> = seen
+            .iter()
+            .map(|&(a, b)| (old.contains(a), new.contains(b)))
+            .collect();
+        assert_eq!(
+            dispatched,
+            [(true, false), (false, true), (false, true)],
+            "each event dispatched by exactly one generation"
+        );
+    }
+}
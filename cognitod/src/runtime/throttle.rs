@@ -0,0 +1,180 @@
+//! Per-process emission intervals of the probes (`THROTTLE_CONFIG`).
+//!
+//! Page faults, block I/O and socket traffic are rate limited per process
+//! in the kernel. The intervals start out from `[probes]`, can be changed
+//! while running through `PATCH /config/throttle`, and are written to the
+//! programs again on every reload.
+
+use crate::config::ProbesConfig;
+use anyhow::Context;
+use aya::Ebpf;
+use aya::maps::{Array, MapData};
+use linnix_ai_ebpf_common::throttle_slot;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ThrottleIntervals {
+    pub page_fault_ms: u64,
+    pub block_io_ms: u64,
+    pub net_ms: u64,
+}
+
+impl ThrottleIntervals {
+    pub fn from_probes(probes: &ProbesConfig) -> Self {
+        Self {
+            page_fault_ms: probes.page_fault_interval_ms,
+            block_io_ms: probes.block_io_interval_ms,
+            net_ms: probes.net_interval_ms,
+        }
+    }
+
+    /// `THROTTLE_CONFIG` entries: slot and interval in nanoseconds.
+    fn slots(&self) -> [(u32, u64); throttle_slot::COUNT as usize] {
+        let ns = |ms: u64| ms.saturating_mul(1_000_000);
+        [
+            (throttle_slot::PAGE_FAULT, ns(self.page_fault_ms)),
+            (throttle_slot::BLOCK_IO, ns(self.block_io_ms)),
+            (throttle_slot::NET, ns(self.net_ms)),
+        ]
+    }
+}
+
+/// Body of `PATCH /config/throttle`; absent fields stay as they are.
+#[derive(Debug, Default, Deserialize)]
+pub struct ThrottlePatch {
+    pub page_fault_ms: Option<u64>,
+    pub block_io_ms: Option<u64>,
+    pub net_ms: Option<u64>,
+}
+
+impl ThrottlePatch {
+    pub fn is_empty(&self) -> bool {
+        self.page_fault_ms.is_none() && self.block_io_ms.is_none() && self.net_ms.is_none()
+    }
+
+    fn apply(&self, intervals: &mut ThrottleIntervals) {
+        if let Some(ms) = self.page_fault_ms {
+            intervals.page_fault_ms = ms;
+        }
+        if let Some(ms) = self.block_io_ms {
+            intervals.block_io_ms = ms;
+        }
+        if let Some(ms) = self.net_ms {
+            intervals.net_ms = ms;
+        }
+    }
+}
+
+struct Inner {
+    intervals: ThrottleIntervals,
+    /// Map of the running programs; `None` without kernel instrumentation.
+    map: Option<Array<MapData, u64>>,
+}
+
+pub struct ThrottleControl {
+    inner: Mutex<Inner>,
+}
+
+impl ThrottleControl {
+    pub fn new(intervals: ThrottleIntervals) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                intervals,
+                map: None,
+            }),
+        }
+    }
+
+    pub fn intervals(&self) -> ThrottleIntervals {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .intervals
+    }
+
+    /// Fill the map of a freshly loaded object, before its programs are
+    /// attached.
+    pub fn prepare(&self, bpf: &mut Ebpf) -> anyhow::Result<()> {
+        let intervals = self.intervals();
+        let mut map: Array<&mut MapData, u64> = Array::try_from(
+            bpf.map_mut("THROTTLE_CONFIG")
+                .context("THROTTLE_CONFIG not found")?,
+        )?;
+        write(&mut map, &intervals)
+    }
+
+    /// Take over the map of an object whose programs now run, so later
+    /// changes reach them. Changes made since [`Self::prepare`] are written
+    /// again.
+    pub fn adopt(&self, bpf: &mut Ebpf) -> anyhow::Result<()> {
+        let map = bpf
+            .take_map("THROTTLE_CONFIG")
+            .context("THROTTLE_CONFIG not found")?;
+        let mut map: Array<MapData, u64> = Array::try_from(map)?;
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        write(&mut map, &inner.intervals)?;
+        inner.map = Some(map);
+        Ok(())
+    }
+
+    /// Change intervals; the running programs (if any) see them at once.
+    pub fn update(&self, patch: &ThrottlePatch) -> anyhow::Result<ThrottleIntervals> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let mut intervals = inner.intervals;
+        patch.apply(&mut intervals);
+        if let Some(map) = inner.map.as_mut() {
+            write(map, &intervals)?;
+        }
+        inner.intervals = intervals;
+        Ok(intervals)
+    }
+}
+
+fn write<T>(map: &mut Array<T, u64>, intervals: &ThrottleIntervals) -> anyhow::Result<()>
+where
+    T: std::borrow::BorrowMut<MapData>,
+{
+    for (slot, ns) in intervals.slots() {
+        map.set(slot, ns, 0)
+            .with_context(|| format!("failed to set THROTTLE_CONFIG[{slot}]"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patches_change_only_given_intervals() {
+        let control =
+            ThrottleControl::new(ThrottleIntervals::from_probes(&ProbesConfig::default()));
+        assert_eq!(
+            control.intervals(),
+            ThrottleIntervals {
+                page_fault_ms: 50,
+                block_io_ms: 100,
+                net_ms: 100,
+            }
+        );
+
+        let patch = ThrottlePatch {
+            block_io_ms: Some(0),
+            net_ms: Some(1_000),
+            ..ThrottlePatch::default()
+        };
+        let updated = control.update(&patch).unwrap();
+        assert_eq!(updated.page_fault_ms, 50);
+        assert_eq!(control.intervals(), updated);
+        assert_eq!(
+            updated.slots(),
+            [
+                (throttle_slot::PAGE_FAULT, 50_000_000),
+                (throttle_slot::BLOCK_IO, 0),
+                (throttle_slot::NET, 1_000_000_000),
+            ]
+        );
+        assert!(ThrottlePatch::default().is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+//! Scripted detectors (`detector: script` in the rules file).
+//!
+//! A lighter alternative to WASM plugins: a [Rhai](https://rhai.rs) snippet
+//! evaluated per event, with the event as `event` and counters over the
+//! rule's window as `window`. Returning a non-empty string raises an alert
+//! with that message; anything else (`()`, `false`, `""`) doesn't.
+//!
+//! ```yaml
+//! - name: root_shell_burst
+//!   detector: script
+//!   window_seconds: 30
+//!   script: |
+//!     if event.uid == 0 && event.comm == "bash" && window.execs > 20 {
+//!       `${window.execs} execs in 30s, latest root bash pid ${event.pid}`
+//!     }
+//! ```
+//!
+//! Scripts are compiled when the rules file is loaded, so syntax errors fail
+//! the load like any other rule error, and run under an operation cap so a
+//! runaway loop is cut off instead of stalling the pipeline.
+
+use crate::ProcessEvent;
+use once_cell::sync::Lazy;
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Rhai operations one evaluation may perform.
+pub const MAX_OPERATIONS: u64 = 100_000;
+
+/// Event types scripts run on unless `event_types` says otherwise.
+pub const DEFAULT_EVENT_TYPES: [&str; 3] = ["exec", "fork", "exit"];
+
+const EVENT_TYPES: [(&str, u32); 18] = [
+    ("exec", 0),
+    ("fork", 1),
+    ("exit", 2),
+    ("net", 3),
+    ("file_io", 4),
+    ("syscall", 5),
+    ("block_io", 6),
+    ("page_fault", 7),
+    ("oom_kill", 10),
+    ("sched_latency", 11),
+    ("off_cpu", 12),
+    ("connection", 13),
+    ("direct_reclaim", 14),
+    ("signal", 15),
+    ("creds", 16),
+    ("mem_growth", 17),
+    ("file_access", 18),
+    ("kernel_load", 19),
+];
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(16)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4096)
+        .set_max_array_size(1024)
+        .set_max_map_size(256)
+        .disable_symbol("eval");
+    engine.on_print(|s| log::debug!("[rules:script] {s}"));
+    engine.on_debug(|s, _, _| log::debug!("[rules:script] {s}"));
+    engine
+});
+
+pub fn event_type_from_name(name: &str) -> Option<u32> {
+    EVENT_TYPES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, t)| *t)
+}
+
+pub fn event_type_name(event_type: u32) -> &'static str {
+    EVENT_TYPES
+        .iter()
+        .find(|(_, t)| *t == event_type)
+        .map(|(n, _)| *n)
+        .unwrap_or("other")
+}
+
+/// Counters over a script rule's `window_seconds`, ending at this event.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Window {
+    pub seconds: u64,
+    pub forks: u64,
+    pub execs: u64,
+    /// Exits of processes whose exec was seen.
+    pub exits: u64,
+    /// Of those, processes that lived at most 1s.
+    pub short_jobs: u64,
+    /// Forks by this event's parent.
+    pub parent_forks: u64,
+}
+
+pub struct Script {
+    source: String,
+    ast: AST,
+    errors: AtomicU64,
+}
+
+/// Shows the source only, so two compilations of the same script compare
+/// equal (rules reloads tell changed rules apart by their Debug form).
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script")
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Script {
+    pub fn compile(source: &str) -> anyhow::Result<Self> {
+        let ast = ENGINE
+            .compile(source)
+            .map_err(|e| anyhow::anyhow!("script does not compile: {e}"))?;
+        Ok(Self {
+            source: source.to_string(),
+            ast,
+            errors: AtomicU64::new(0),
+        })
+    }
+
+    /// Evaluate against one event; `Some(message)` when the script fires.
+    /// Runtime errors (including hitting the operation cap) count as "no
+    /// alert" and are logged on the first occurrence and every 1000th after.
+    pub fn eval(&self, rule: &str, event: &ProcessEvent, window: &Window) -> Option<String> {
+        let mut scope = Scope::new();
+        scope.push_constant("event", event_map(event));
+        scope.push_constant("window", window_map(window));
+        match ENGINE.eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast) {
+            Ok(value) => value
+                .into_immutable_string()
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+            Err(e) => {
+                let n = self.errors.fetch_add(1, Ordering::Relaxed) + 1;
+                #[allow(clippy::manual_is_multiple_of)]
+                // is_multiple_of not stable in nightly-2024-12-10
+                if n == 1 || n % 1000 == 0 {
+                    log::warn!("[rules] script rule '{rule}' failed ({n} errors so far): {e}");
+                }
+                None
+            }
+        }
+    }
+
+    /// Runtime errors since the rule was loaded.
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+pub(crate) fn event_map(event: &ProcessEvent) -> Map {
+    let comm = String::from_utf8_lossy(&event.comm)
+        .trim_end_matches('\0')
+        .to_string();
+    let mut map = Map::new();
+    map.insert("type".into(), event_type_name(event.event_type).into());
+    map.insert("event_type".into(), (event.event_type as i64).into());
+    map.insert("pid".into(), (event.pid as i64).into());
+    map.insert("ppid".into(), (event.ppid as i64).into());
+    map.insert("uid".into(), (event.uid as i64).into());
+    map.insert("gid".into(), (event.gid as i64).into());
+    map.insert("comm".into(), comm.into());
+    map.insert("ts_ns".into(), (event.ts_ns as i64).into());
+    map.insert("data".into(), (event.data as i64).into());
+    map.insert("data2".into(), (event.data2 as i64).into());
+    map.insert("aux".into(), (event.aux as i64).into());
+    map.insert("aux2".into(), (event.aux2 as i64).into());
+    map.insert("cgroup_id".into(), (event.cgroup_id as i64).into());
+    // -1.0 when the sample isn't available.
+    let pct = |v: Option<f32>| v.map(f64::from).unwrap_or(-1.0);
+    map.insert("cpu_pct".into(), pct(event.cpu_percent()).into());
+    map.insert("mem_pct".into(), pct(event.mem_percent()).into());
+    if let Some(conn) = crate::connection::Connection::from_event(event) {
+        map.insert("direction".into(), conn.direction.as_str().into());
+        map.insert("remote_ip".into(), conn.remote.ip().to_string().into());
+        map.insert("remote_port".into(), (conn.remote.port() as i64).into());
+    }
+    if let Some(sent) = crate::signals::SignalSent::from_event(event) {
+        map.insert("signal".into(), sent.name().into());
+        map.insert("target_pid".into(), (sent.target as i64).into());
+    }
+    if let Some(change) = crate::creds::CredChange::from_event(event) {
+        map.insert("new_uid".into(), (change.new_uid as i64).into());
+        map.insert("new_gid".into(), (change.new_gid as i64).into());
+        map.insert("caps_gained".into(), (change.caps_gained as i64).into());
+        map.insert("escalation".into(), change.is_escalation().into());
+    }
+    if let Some(access) = crate::file_access::FileAccess::from_event(event) {
+        map.insert(
+            "file_op".into(),
+            crate::file_access::op_name(access.op).into(),
+        );
+        if let Some(path) = access.path {
+            map.insert("path".into(), path.into());
+        }
+    }
+    if let Some(load) = crate::kernel_load::KernelLoad::from_event(event) {
+        map.insert(
+            "load_kind".into(),
+            crate::kernel_load::kind_name(load.kind).into(),
+        );
+        map.insert("load_name".into(), load.name.into());
+    }
+    map
+}
+
+fn window_map(window: &Window) -> Map {
+    let mut map = Map::new();
+    for (key, value) in [
+        ("seconds", window.seconds),
+        ("forks", window.forks),
+        ("execs", window.execs),
+        ("exits", window.exits),
+        ("short_jobs", window.short_jobs),
+        ("parent_forks", window.parent_forks),
+    ] {
+        map.insert(key.into(), (value as i64).into());
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn exec(uid: u32, comm: &str) -> ProcessEvent {
+        let mut c = [0u8; 16];
+        c[..comm.len()].copy_from_slice(comm.as_bytes());
+        ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid,
+            gid: 0,
+            event_type: 0,
+            ts_ns: 0,
+            seq: 0,
+            comm: c,
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn script_sees_event_and_window() {
+        let script = Script::compile(
+            r#"if event.type == "exec" && event.uid == 0 && window.execs > 2 {
+                   `${event.comm} x${window.execs}`
+               }"#,
+        )
+        .unwrap();
+        let window = Window {
+            execs: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            script.eval("t", &exec(0, "bash"), &window).as_deref(),
+            Some("bash x3")
+        );
+        assert_eq!(script.eval("t", &exec(1000, "bash"), &window), None);
+        assert_eq!(script.eval("t", &exec(0, "bash"), &Window::default()), None);
+        assert_eq!(script.errors(), 0);
+    }
+
+    #[test]
+    fn runaway_scripts_are_cut_off_and_counted() {
+        let script = Script::compile("loop { }").unwrap();
+        assert_eq!(script.eval("t", &exec(0, "sh"), &Window::default()), None);
+        assert_eq!(script.errors(), 1);
+    }
+
+    #[test]
+    fn syntax_errors_fail_compilation() {
+        assert!(Script::compile("if {").is_err());
+        assert!(Script::compile(r#"eval("1")"#).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+//! Login session attribution.
+//!
+//! On shared jump hosts a fork storm rooted at `sshd` says little; the useful
+//! answer is whose session it came from. The kernel records the audit login
+//! UID and session of every process (`/proc/<pid>/loginuid` and `sessionid`,
+//! inherited across fork and setuid), and systemd-logind keeps the user,
+//! service, TTY and remote host of each session under `/run/systemd/sessions`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// `loginuid` / `sessionid` of processes that never logged in.
+const UNSET: u32 = u32::MAX;
+
+/// Sessions cached before the cache is reset; sessions are few and long
+/// lived, so this is only a bound against churn.
+const MAX_CACHED_SESSIONS: usize = 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoginSession {
+    /// Audit session ID, which logind also uses as its session ID.
+    pub session_id: u32,
+    pub login_uid: u32,
+    pub user: String,
+    /// PAM service, e.g. `sshd`, `login`, `gdm-password`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tty: Option<String>,
+}
+
+impl LoginSession {
+    /// e.g. "alice's SSH session from 10.0.0.5".
+    pub fn describe(&self) -> String {
+        let mut text = match self.service.as_deref() {
+            Some("sshd") => format!("{}'s SSH session", self.user),
+            _ => match &self.tty {
+                Some(tty) => format!("{}'s session on {tty}", self.user),
+                None => format!("{}'s login session {}", self.user, self.session_id),
+            },
+        };
+        if let Some(host) = &self.remote_host {
+            text.push_str(&format!(" from {host}"));
+        }
+        text
+    }
+}
+
+pub struct SessionResolver {
+    proc_root: PathBuf,
+    sessions_dir: PathBuf,
+    passwd: PathBuf,
+    cache: RwLock<HashMap<u32, Arc<LoginSession>>>,
+}
+
+impl Default for SessionResolver {
+    fn default() -> Self {
+        Self::with_roots("/proc", "/run/systemd/sessions", "/etc/passwd")
+    }
+}
+
+impl SessionResolver {
+    pub(crate) fn with_roots(
+        proc_root: impl Into<PathBuf>,
+        sessions_dir: impl Into<PathBuf>,
+        passwd: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            proc_root: proc_root.into(),
+            sessions_dir: sessions_dir.into(),
+            passwd: passwd.into(),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Login session of `pid`, or `None` for daemons, kernel threads and
+    /// processes that have already exited.
+    pub fn session_for_pid(&self, pid: u32) -> Option<Arc<LoginSession>> {
+        let read = |file: &str| -> Option<u32> {
+            let path = self.proc_root.join(pid.to_string()).join(file);
+            let value = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+            (value != UNSET).then_some(value)
+        };
+        let login_uid = read("loginuid")?;
+        let session_id = read("sessionid")?;
+
+        if let Some(session) = self.read_cache().get(&session_id)
+            && session.login_uid == login_uid
+        {
+            return Some(Arc::clone(session));
+        }
+        let session = Arc::new(self.load(session_id, login_uid));
+        let mut cache = self.cache.write().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= MAX_CACHED_SESSIONS {
+            cache.clear();
+        }
+        cache.insert(session_id, Arc::clone(&session));
+        Some(session)
+    }
+
+    /// Session of `pid`, falling back to its parent when `pid` is gone (e.g.
+    /// the alert was triggered by an exit).
+    pub fn session_for(&self, pid: u32, ppid: u32) -> Option<Arc<LoginSession>> {
+        self.session_for_pid(pid)
+            .or_else(|| self.session_for_pid(ppid))
+    }
+
+    fn load(&self, session_id: u32, login_uid: u32) -> LoginSession {
+        let mut session = LoginSession {
+            session_id,
+            login_uid,
+            ..Default::default()
+        };
+        // logind session files are `KEY=value` lines.
+        let file = std::fs::read_to_string(self.sessions_dir.join(session_id.to_string()))
+            .unwrap_or_default();
+        for (key, value) in file.lines().filter_map(|l| l.split_once('=')) {
+            let value = Some(value.to_string()).filter(|v| !v.is_empty());
+            match key {
+                "USER" => session.user = value.unwrap_or_default(),
+                "SERVICE" => session.service = value,
+                "REMOTE_HOST" => session.remote_host = value,
+                "TTY" => session.tty = value,
+                _ => {}
+            }
+        }
+        if session.user.is_empty() {
+            session.user = self
+                .user_name(login_uid)
+                .unwrap_or_else(|| format!("uid {login_uid}"));
+        }
+        session
+    }
+
+    fn user_name(&self, uid: u32) -> Option<String> {
+        let passwd = std::fs::read_to_string(&self.passwd).ok()?;
+        passwd.lines().find_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            (fields.nth(1)?.parse::<u32>().ok()? == uid).then(|| name.to_string())
+        })
+    }
+
+    fn read_cache(&self) -> std::sync::RwLockReadGuard<'_, HashMap<u32, Arc<LoginSession>>> {
+        self.cache.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn fixture() -> (tempfile::TempDir, SessionResolver) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let proc_pid = |pid: u32, loginuid: u32, sessionid: u32| {
+            let p = root.join("proc").join(pid.to_string());
+            fs::create_dir_all(&p).unwrap();
+            fs::write(p.join("loginuid"), loginuid.to_string()).unwrap();
+            fs::write(p.join("sessionid"), sessionid.to_string()).unwrap();
+        };
+        proc_pid(100, 1000, 7);
+        proc_pid(101, 1001, 8);
+        proc_pid(1, UNSET, UNSET);
+        fs::create_dir_all(root.join("sessions")).unwrap();
+        fs::write(
+            root.join("sessions/7"),
+            "# This is private data. Do not parse.\nUID=1000\nUSER=alice\nSERVICE=sshd\nREMOTE_HOST=10.0.0.5\nTTY=\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("passwd"),
+            "root:x:0:0:root:/root:/bin/bash\nbob:x:1001:1001::/home/bob:/bin/sh\n",
+        )
+        .unwrap();
+        let resolver = SessionResolver::with_roots(
+            root.join("proc"),
+            root.join("sessions"),
+            root.join("passwd"),
+        );
+        (dir, resolver)
+    }
+
+    #[test]
+    fn reads_logind_session() {
+        let (_dir, resolver) = fixture();
+        let session = resolver.session_for_pid(100).unwrap();
+        assert_eq!(session.user, "alice");
+        assert_eq!(session.tty, None);
+        assert_eq!(session.describe(), "alice's SSH session from 10.0.0.5");
+        assert!(Arc::ptr_eq(
+            &session,
+            &resolver.session_for_pid(100).unwrap()
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_passwd_and_parent() {
+        let (_dir, resolver) = fixture();
+        let bob = resolver.session_for(4242, 101).unwrap();
+        assert_eq!(bob.describe(), "bob's login session 8");
+        assert_eq!(resolver.session_for_pid(1), None, "daemons have no session");
+        assert_eq!(resolver.session_for(4242, 4243), None);
+    }
+}
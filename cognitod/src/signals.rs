@@ -0,0 +1,98 @@
+//! Signals sent by processes (Signal events).
+
+use crate::ProcessEvent;
+use crate::crashloop::signal_name;
+use linnix_ai_ebpf_common::{EventType, signal_result};
+use std::fmt;
+
+/// `si_code` of signals raised by the kernel itself.
+const SI_KERNEL: i32 = 0x80;
+
+/// A decoded Signal event; the sender is the event's process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalSent {
+    pub signal: i32,
+    /// Task ID of the target; its PID for signals sent to a process.
+    pub target: u32,
+    /// Raised by the kernel (e.g. a fault) rather than sent with `kill`.
+    pub from_kernel: bool,
+    /// The target already ignored or had the signal pending.
+    pub dropped: bool,
+}
+
+impl SignalSent {
+    pub fn from_event(event: &ProcessEvent) -> Option<Self> {
+        if event.event_type != EventType::Signal as u32 {
+            return None;
+        }
+        Some(Self {
+            signal: event.aux as i32,
+            target: event.data as u32,
+            from_kernel: event.data2 as u32 as i32 == SI_KERNEL,
+            dropped: matches!(
+                event.aux2,
+                signal_result::IGNORED | signal_result::ALREADY_PENDING
+            ),
+        })
+    }
+
+    pub fn name(&self) -> String {
+        signal_name(self.signal).map_or_else(|| format!("signal {}", self.signal), str::to_string)
+    }
+}
+
+impl fmt::Display for SignalSent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} to pid {}", self.name(), self.target)?;
+        if self.from_kernel {
+            f.write_str(" from the kernel")?;
+        }
+        if self.dropped {
+            f.write_str(" (not delivered)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+
+    fn signal(target: u32, code: i32, sig: u32, result: u32) -> ProcessEvent {
+        ProcessEvent::new(ProcessEventWire {
+            pid: 42,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::Signal as u32,
+            ts_ns: 0,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: target as u64,
+            data2: code as u32 as u64,
+            aux: sig,
+            aux2: result,
+            cgroup_id: 0,
+        })
+    }
+
+    #[test]
+    fn decodes_signal_events() {
+        let kill = SignalSent::from_event(&signal(4242, 0, 9, signal_result::DELIVERED)).unwrap();
+        assert_eq!(kill.to_string(), "SIGKILL to pid 4242");
+
+        let fault = signal(7, SI_KERNEL, 11, signal_result::ALREADY_PENDING);
+        assert_eq!(
+            SignalSent::from_event(&fault).unwrap().to_string(),
+            "SIGSEGV to pid 7 from the kernel (not delivered)"
+        );
+        // si_code is negative for sigqueue/tkill senders.
+        let rt = SignalSent::from_event(&signal(7, -6, 40, 0)).unwrap();
+        assert!(!rt.from_kernel);
+        assert_eq!(rt.name(), "signal 40");
+    }
+}
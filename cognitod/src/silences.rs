@@ -0,0 +1,495 @@
+//! Silences: maintenance windows and ad-hoc mutes during which matching
+//! rules don't alert.
+//!
+//! Windows come from `[[silences]]` in the config, either a fixed `start`
+//! and `end` or a cron `schedule` (host local time) opening a window of
+//! `duration_minutes`. Ad-hoc silences are added through `POST /silences`
+//! and live in memory until they end or are deleted.
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::RwLock;
+
+/// Longest window a schedule can open, in minutes (one week).
+const MAX_SCHEDULE_MINUTES: u64 = 7 * 24 * 60;
+
+/// One `[[silences]]` entry.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SilenceWindow {
+    /// Shown in the API and logs; defaults to `config-<index>`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Rules silenced; a trailing `*` matches by prefix. Empty silences
+    /// every rule.
+    #[serde(default)]
+    pub rules: Vec<String>,
+    /// RFC 3339 timestamps of a one-off window.
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Cron expression (`minute hour day-of-month month day-of-week`) of
+    /// the times a recurring window opens.
+    #[serde(default)]
+    pub schedule: Option<String>,
+    /// How long each scheduled window stays open.
+    #[serde(default)]
+    pub duration_minutes: u64,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Body of `POST /silences`. The silence starts now unless `start` is
+/// given and ends at `end` or after `duration_minutes`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewSilence {
+    #[serde(default)]
+    pub rules: Vec<String>,
+    /// Epoch seconds.
+    #[serde(default)]
+    pub start: Option<i64>,
+    #[serde(default)]
+    pub end: Option<i64>,
+    #[serde(default)]
+    pub duration_minutes: Option<u64>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+/// A loaded silence.
+#[derive(Debug, Clone, Serialize)]
+pub struct Silence {
+    pub id: String,
+    /// `config` or `api`.
+    pub source: &'static str,
+    pub rules: Vec<String>,
+    #[serde(flatten)]
+    pub when: When,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+}
+
+/// When a silence applies.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum When {
+    /// Epoch seconds, end exclusive.
+    Between { start: i64, end: i64 },
+    Schedule {
+        schedule: Cron,
+        duration_minutes: u64,
+    },
+}
+
+impl Silence {
+    fn from_window(index: usize, window: &SilenceWindow) -> Result<Self, String> {
+        let when = match (&window.schedule, &window.start, &window.end) {
+            (Some(schedule), None, None) => {
+                if window.duration_minutes == 0 || window.duration_minutes > MAX_SCHEDULE_MINUTES {
+                    return Err(format!(
+                        "duration_minutes must be between 1 and {MAX_SCHEDULE_MINUTES}"
+                    ));
+                }
+                When::Schedule {
+                    schedule: schedule.parse()?,
+                    duration_minutes: window.duration_minutes,
+                }
+            }
+            (None, Some(start), Some(end)) => {
+                let parse = |s: &str| {
+                    DateTime::parse_from_rfc3339(s)
+                        .map(|t| t.timestamp())
+                        .map_err(|e| format!("invalid timestamp '{s}': {e}"))
+                };
+                When::Between {
+                    start: parse(start)?,
+                    end: parse(end)?,
+                }
+            }
+            _ => return Err("needs either a schedule or a start and an end".to_string()),
+        };
+        if let When::Between { start, end } = when
+            && end <= start
+        {
+            return Err("end is not after start".to_string());
+        }
+        Ok(Self {
+            id: window
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("config-{index}")),
+            source: "config",
+            rules: window.rules.clone(),
+            when,
+            comment: window.comment.clone(),
+            created_by: None,
+        })
+    }
+
+    fn matches_rule(&self, rule: &str) -> bool {
+        self.rules.is_empty()
+            || self
+                .rules
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => rule.starts_with(prefix),
+                    None => rule == pattern,
+                })
+    }
+
+    /// Whether the silence applies at `now`.
+    pub fn is_active(&self, now: DateTime<Local>) -> bool {
+        match &self.when {
+            When::Between { start, end } => (*start..*end).contains(&now.timestamp()),
+            When::Schedule {
+                schedule,
+                duration_minutes,
+            } => schedule.opened_within(now.naive_local(), *duration_minutes),
+        }
+    }
+
+    /// A one-off silence past its end.
+    fn is_over(&self, now: DateTime<Local>) -> bool {
+        matches!(self.when, When::Between { end, .. } if now.timestamp() >= end)
+    }
+}
+
+/// Silences from the config plus those added at runtime.
+#[derive(Default)]
+pub struct Silences {
+    silences: RwLock<Vec<Silence>>,
+}
+
+impl Silences {
+    /// Load `[[silences]]`; invalid entries are logged and skipped.
+    pub fn from_config(windows: &[SilenceWindow]) -> Self {
+        let mut silences = Vec::new();
+        for (index, window) in windows.iter().enumerate() {
+            match Silence::from_window(index, window) {
+                Ok(silence) => silences.push(silence),
+                Err(e) => log::warn!("[silences] ignoring silences[{index}]: {e}"),
+            }
+        }
+        Self {
+            silences: RwLock::new(silences),
+        }
+    }
+
+    /// Add an ad-hoc silence.
+    pub fn add(&self, new: NewSilence, now: DateTime<Local>) -> Result<Silence, String> {
+        let start = new.start.unwrap_or_else(|| now.timestamp());
+        let end = match (new.end, new.duration_minutes) {
+            (Some(end), None) => end,
+            (None, Some(minutes)) if minutes > 0 => {
+                start.saturating_add(minutes.saturating_mul(60).min(i64::MAX as u64) as i64)
+            }
+            _ => return Err("set either end or a positive duration_minutes".to_string()),
+        };
+        if end <= start.max(now.timestamp()) {
+            return Err("the silence would already be over".to_string());
+        }
+        let silence = Silence {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: "api",
+            rules: new.rules,
+            when: When::Between { start, end },
+            comment: new.comment,
+            created_by: new.created_by,
+        };
+        let mut silences = self.silences.write().unwrap_or_else(|e| e.into_inner());
+        silences.retain(|s| !s.is_over(now));
+        silences.push(silence.clone());
+        Ok(silence)
+    }
+
+    /// Delete an ad-hoc silence; config silences can't be.
+    pub fn remove(&self, id: &str) -> Result<Silence, RemoveError> {
+        let mut silences = self.silences.write().unwrap_or_else(|e| e.into_inner());
+        let index = silences
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or(RemoveError::NotFound)?;
+        if silences[index].source == "config" {
+            return Err(RemoveError::FromConfig);
+        }
+        Ok(silences.remove(index))
+    }
+
+    /// Every silence that hasn't ended, with whether it applies at `now`.
+    pub fn list(&self, now: DateTime<Local>) -> Vec<(Silence, bool)> {
+        let silences = self.silences.read().unwrap_or_else(|e| e.into_inner());
+        silences
+            .iter()
+            .filter(|s| !s.is_over(now))
+            .map(|s| (s.clone(), s.is_active(now)))
+            .collect()
+    }
+
+    /// ID of a silence muting `rule` at `now`.
+    pub fn silencing(&self, rule: &str, now: DateTime<Local>) -> Option<String> {
+        let silences = self.silences.read().unwrap_or_else(|e| e.into_inner());
+        silences
+            .iter()
+            .find(|s| s.matches_rule(rule) && s.is_active(now))
+            .map(|s| s.id.clone())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RemoveError {
+    NotFound,
+    FromConfig,
+}
+
+impl fmt::Display for RemoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoveError::NotFound => write!(f, "no such silence"),
+            RemoveError::FromConfig => write!(f, "silences from the config can't be deleted"),
+        }
+    }
+}
+
+/// A five-field cron expression: minute, hour, day of month, month and day
+/// of week, each `*`, a number or name, a range `a-b`, a step `*/n` or
+/// `a-b/n`, or a comma-separated list of those.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day of month / day of week were `*`. As in cron, when both
+    /// are restricted a day matching either one matches.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl std::str::FromStr for Cron {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const MONTHS: &[&str] = &[
+            "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+        ];
+        const WEEKDAYS: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("schedule '{s}' needs 5 fields"));
+        };
+        let field = |text: &str, min: u64, max: u64, names: &[&str], base: u64| {
+            parse_field(text, min, max, names, base)
+                .map_err(|e| format!("schedule '{s}': {e} in '{text}'"))
+        };
+        let mut weekdays = field(weekday, 0, 7, WEEKDAYS, 0)?;
+        // 7 is Sunday too.
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Self {
+            source: s.to_string(),
+            minutes: field(minute, 0, 59, &[], 0)?,
+            hours: field(hour, 0, 23, &[], 0)?,
+            days: field(day, 1, 31, &[], 0)?,
+            months: field(month, 1, 12, MONTHS, 1)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl Serialize for Cron {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+/// Bit set of the values a cron field allows.
+fn parse_field(text: &str, min: u64, max: u64, names: &[&str], base: u64) -> Result<u64, String> {
+    let value = |v: &str| -> Result<u64, String> {
+        let lower = v.to_ascii_lowercase();
+        let n = match names.iter().position(|name| *name == lower) {
+            Some(index) => index as u64 + base,
+            None => v.parse().map_err(|_| format!("bad value '{v}'"))?,
+        };
+        if !(min..=max).contains(&n) {
+            return Err(format!("{n} out of range {min}-{max}"));
+        }
+        Ok(n)
+    };
+    let mut bits = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u64>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("bad step '{step}'"))?,
+            ),
+            None => (part, 1),
+        };
+        let (from, to) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((from, to)) => (value(from)?, value(to)?),
+                // `5/15` means from 5 to the end, every 15.
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let n = value(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if from > to {
+            return Err(format!("empty range {from}-{to}"));
+        }
+        for n in (from..=to).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+impl Cron {
+    fn matches(&self, at: NaiveDateTime) -> bool {
+        let has = |bits: u64, n: u32| bits & (1 << n) != 0;
+        let day = has(self.days, at.day());
+        let weekday = has(self.weekdays, at.weekday().num_days_from_sunday());
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        };
+        has(self.minutes, at.minute())
+            && has(self.hours, at.hour())
+            && has(self.months, at.month())
+            && day_matches
+    }
+
+    /// Whether the schedule fired during the `minutes` up to and
+    /// including the minute of `now`.
+    fn opened_within(&self, now: NaiveDateTime, minutes: u64) -> bool {
+        let Some(minute) = now.with_second(0).and_then(|t| t.with_nanosecond(0)) else {
+            return false;
+        };
+        (0..minutes as i64).any(|back| self.matches(minute - Duration::minutes(back)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Local> {
+        let naive = NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    #[test]
+    fn schedules_open_windows_of_their_duration() {
+        let nightly = Silence::from_window(
+            0,
+            &SilenceWindow {
+                rules: vec!["fork_*".into()],
+                schedule: Some("30 2 * * sat,SUN".into()),
+                duration_minutes: 90,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        // 2026-10-17 is a Saturday.
+        assert!(nightly.is_active(local(2026, 10, 17, 2, 30)));
+        assert!(nightly.is_active(local(2026, 10, 17, 3, 59)));
+        assert!(!nightly.is_active(local(2026, 10, 17, 4, 0)));
+        assert!(!nightly.is_active(local(2026, 10, 17, 2, 29)));
+        assert!(!nightly.is_active(local(2026, 10, 16, 2, 45)), "a Friday");
+        assert!(nightly.matches_rule("fork_burst"));
+        assert!(!nightly.matches_rule("exec_rate"));
+
+        let cron: Cron = "*/15 9-17 1,15 * 1-5".parse().unwrap();
+        // The 15th, a Thursday, or any weekday: either day field matches.
+        assert!(cron.matches(local(2026, 10, 15, 9, 45).naive_local()));
+        assert!(cron.matches(local(2026, 10, 12, 17, 0).naive_local()));
+        assert!(!cron.matches(local(2026, 10, 18, 10, 0).naive_local()));
+        assert!(!cron.matches(local(2026, 10, 12, 10, 5).naive_local()));
+
+        assert!("* * *".parse::<Cron>().is_err());
+        assert!("61 * * * *".parse::<Cron>().is_err());
+        assert!("* * * foo *".parse::<Cron>().is_err());
+        let no_duration = SilenceWindow {
+            schedule: Some("0 2 * * *".into()),
+            ..Default::default()
+        };
+        assert!(Silence::from_window(0, &no_duration).is_err());
+    }
+
+    #[test]
+    fn api_silences_expire_and_config_ones_stay() {
+        let silences = Silences::from_config(&[
+            SilenceWindow {
+                name: Some("migration".into()),
+                start: Some("2026-10-20T22:00:00Z".into()),
+                end: Some("2026-10-21T01:00:00Z".into()),
+                ..Default::default()
+            },
+            SilenceWindow {
+                start: Some("yesterday".into()),
+                end: Some("tomorrow".into()),
+                ..Default::default()
+            },
+        ]);
+        let now = local(2026, 10, 16, 12, 0);
+        assert_eq!(silences.list(now).len(), 1, "invalid entries are skipped");
+        assert_eq!(silences.silencing("fork_burst", now), None);
+
+        let deploy = silences
+            .add(
+                NewSilence {
+                    rules: vec!["fork_burst".into()],
+                    duration_minutes: Some(30),
+                    comment: Some("deploy".into()),
+                    ..Default::default()
+                },
+                now,
+            )
+            .unwrap();
+        assert_eq!(
+            silences.silencing("fork_burst", now),
+            Some(deploy.id.clone())
+        );
+        assert_eq!(silences.silencing("exec_rate", now), None);
+        let later = now + Duration::minutes(30);
+        assert_eq!(silences.silencing("fork_burst", later), None);
+        assert!(
+            silences
+                .add(NewSilence::default(), now)
+                .unwrap_err()
+                .contains("duration_minutes")
+        );
+
+        assert_eq!(
+            silences.remove("migration").unwrap_err(),
+            RemoveError::FromConfig
+        );
+        assert_eq!(silences.remove(&deploy.id).unwrap().id, deploy.id);
+        assert_eq!(
+            silences.remove(&deploy.id).unwrap_err(),
+            RemoveError::NotFound
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! statsd / DogStatsD export of agent health.
+//!
+//! For Datadog and Telegraf setups that don't scrape `/metrics`: every
+//! `interval_secs` the core counters and gauges are sent to a statsd
+//! listener over UDP. Counters go out as deltas since the previous flush,
+//! which is what statsd aggregates.
+
+use crate::config::StatsdConfig;
+use crate::metrics::Metrics;
+use log::{debug, info, warn};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// Datagrams are kept under a typical MTU so they are not fragmented.
+const MAX_PACKET_BYTES: usize = 1432;
+
+/// Counter totals at a flush.
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    events: u64,
+    dropped: u64,
+    rb_overflows: u64,
+    kernel_dropped: u64,
+    rate_limited: u64,
+    alerts: u64,
+    perf_poll_errors: u64,
+    ilm_requests: u64,
+    ilm_latency_ms: u64,
+}
+
+impl Totals {
+    fn read(metrics: &Metrics) -> Self {
+        Self {
+            events: metrics.events_total.load(Ordering::Relaxed),
+            dropped: metrics.dropped_events_total.load(Ordering::Relaxed),
+            rb_overflows: metrics.rb_overflows(),
+            kernel_dropped: metrics.kernel_dropped_events(),
+            rate_limited: metrics.rate_limited_events(),
+            alerts: metrics.alerts_emitted(),
+            perf_poll_errors: metrics.perf_poll_errors(),
+            ilm_requests: metrics.ilm_requests(),
+            ilm_latency_ms: metrics.ilm_latency_ms_total(),
+        }
+    }
+}
+
+/// Metric lines for one flush, without tags.
+fn lines(metrics: &Metrics, prev: &Totals, cur: &Totals) -> Vec<(&'static str, String)> {
+    let delta = |f: fn(&Totals) -> u64| f(cur).saturating_sub(f(prev));
+    let mut lines = vec![
+        ("events", format!("{}|c", delta(|t| t.events))),
+        ("events_per_sec", format!("{}|g", metrics.events_per_sec())),
+        ("events.dropped", format!("{}|c", delta(|t| t.dropped))),
+        (
+            "events.kernel_dropped",
+            format!("{}|c", delta(|t| t.kernel_dropped)),
+        ),
+        (
+            "events.rate_limited",
+            format!("{}|c", delta(|t| t.rate_limited)),
+        ),
+        (
+            "ringbuf.overflows",
+            format!("{}|c", delta(|t| t.rb_overflows)),
+        ),
+        (
+            "perf.poll_errors",
+            format!("{}|c", delta(|t| t.perf_poll_errors)),
+        ),
+        ("alerts.emitted", format!("{}|c", delta(|t| t.alerts))),
+        (
+            "alerts.active",
+            format!("{}|g", metrics.alerts_active.load(Ordering::Relaxed)),
+        ),
+        (
+            "detector.state_keys",
+            format!("{}|g", metrics.detector_state_keys()),
+        ),
+        ("uptime_seconds", format!("{}|g", metrics.uptime_seconds())),
+    ];
+    // One timing per flush: the mean of the requests finished since the last.
+    if let Some(mean) = delta(|t| t.ilm_latency_ms).checked_div(delta(|t| t.ilm_requests)) {
+        lines.push(("ilm.latency_ms", format!("{mean}|ms")));
+    }
+    lines
+}
+
+/// Render `name:value|type[|#tags]` lines and pack them into datagrams.
+fn packets(lines: &[(&str, String)], cfg: &StatsdConfig) -> Vec<String> {
+    let prefix = match cfg.prefix.trim_end_matches('.') {
+        "" => String::new(),
+        p => format!("{p}."),
+    };
+    let tags = if cfg.dogstatsd && !cfg.tags.is_empty() {
+        format!("|#{}", cfg.tags.join(","))
+    } else {
+        String::new()
+    };
+
+    let mut packets = Vec::new();
+    let mut packet = String::new();
+    for (name, value) in lines {
+        let line = format!("{prefix}{name}:{value}{tags}");
+        if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_BYTES {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(&line);
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
+
+/// Send agent health to the configured statsd listener every
+/// `interval_secs`. Returns if the address can't be resolved.
+pub async fn run(metrics: Arc<Metrics>, cfg: StatsdConfig) {
+    let target = match tokio::net::lookup_host(&cfg.address).await {
+        Ok(mut addrs) => addrs.next(),
+        Err(e) => {
+            warn!("[statsd] cannot resolve {}: {e}", cfg.address);
+            return;
+        }
+    };
+    let Some(target) = target else {
+        warn!("[statsd] {} resolved to no addresses", cfg.address);
+        return;
+    };
+    let bind = if target.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    };
+    let socket = match UdpSocket::bind(bind).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("[statsd] failed to open UDP socket: {e}");
+            return;
+        }
+    };
+    if let Err(e) = socket.connect(target).await {
+        warn!("[statsd] failed to connect to {target}: {e}");
+        return;
+    }
+    info!(
+        "[statsd] sending to {target} every {}s{}",
+        cfg.interval_secs,
+        if cfg.dogstatsd { " (DogStatsD)" } else { "" }
+    );
+
+    let mut tick = tokio::time::interval(Duration::from_secs(cfg.interval_secs.max(1)));
+    tick.tick().await;
+    let mut prev = Totals::default();
+    loop {
+        tick.tick().await;
+        let cur = Totals::read(&metrics);
+        for packet in packets(&lines(&metrics, &prev, &cur), &cfg) {
+            // Nobody listening is normal (agent restarting); don't spam.
+            if let Err(e) = socket.send(packet.as_bytes()).await {
+                debug!("[statsd] send to {target} failed: {e}");
+            }
+        }
+        prev = cur;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_counter_deltas_with_dogstatsd_tags() {
+        let metrics = Metrics::new();
+        for _ in 0..5 {
+            metrics.record_event(0, 0);
+        }
+        metrics.inc_alerts_emitted();
+        let first = Totals::read(&metrics);
+        metrics.record_event(0, 0);
+        metrics.observe_ilm_latency(Duration::from_millis(300));
+        metrics.observe_ilm_latency(Duration::from_millis(500));
+        let second = Totals::read(&metrics);
+
+        let cfg = StatsdConfig {
+            prefix: "linnix.".into(),
+            dogstatsd: true,
+            tags: vec!["env:prod".into(), "team:infra".into()],
+            ..StatsdConfig::default()
+        };
+        let sent = packets(&lines(&metrics, &Totals::default(), &first), &cfg);
+        assert_eq!(sent.len(), 1);
+        let sent: Vec<&str> = sent[0].lines().collect();
+        assert_eq!(sent[0], "linnix.events:5|c|#env:prod,team:infra");
+        assert!(sent.contains(&"linnix.alerts.emitted:1|c|#env:prod,team:infra"));
+        assert!(!sent.iter().any(|l| l.contains("ilm")));
+
+        let plain = StatsdConfig::default();
+        let sent = packets(&lines(&metrics, &first, &second), &plain).join("\n");
+        assert!(sent.contains("linnix.events:1|c\n"));
+        assert!(sent.contains("linnix.alerts.emitted:0|c\n"));
+        assert!(sent.ends_with("linnix.ilm.latency_ms:400|ms"));
+    }
+
+    #[test]
+    fn splits_packets_at_the_size_limit() {
+        let lines: Vec<(&str, String)> = (0..200).map(|_| ("events", "1|c".into())).collect();
+        let sent = packets(&lines, &StatsdConfig::default());
+        assert!(sent.len() > 1);
+        assert!(sent.iter().all(|p| p.len() <= MAX_PACKET_BYTES));
+        assert_eq!(sent.iter().map(|p| p.lines().count()).sum::<usize>(), 200);
+    }
+}
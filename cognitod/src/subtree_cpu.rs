@@ -0,0 +1,150 @@
+//! CPU use by process subtree, for the `subtree_cpu_pct` detector.
+//!
+//! Events carry the CPU use of a single process, but a fan-out workload
+//! (make running a compiler per core, a pre-forked worker pool) can burn
+//! several cores without any one process standing out. Each sample is
+//! therefore also added to the process's ancestors, so the total of a PID is
+//! its own CPU plus that of every descendant sampled recently. Ancestors come
+//! from the event's parent and the fork lineage cache.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::time::Instant;
+
+struct Sample {
+    cpu: f32,
+    at: Instant,
+    /// Parent first.
+    ancestors: Vec<u32>,
+}
+
+pub struct SubtreeCpu {
+    samples: HashMap<u32, Sample>,
+    /// Sum of the samples of each PID's subtree, and how many there are.
+    totals: HashMap<u32, (f32, usize)>,
+    order: VecDeque<(u32, Instant)>,
+    keep: Duration,
+    max_pids: usize,
+}
+
+impl SubtreeCpu {
+    /// Count samples for `keep`, from at most `max_pids` processes.
+    pub fn new(keep: Duration, max_pids: usize) -> Self {
+        Self {
+            samples: HashMap::new(),
+            totals: HashMap::new(),
+            order: VecDeque::new(),
+            keep,
+            max_pids,
+        }
+    }
+
+    /// Replace the CPU sample of `pid`, whose `ancestors` are listed parent
+    /// first. Returns how many samples were evicted to stay under the cap.
+    pub fn observe(&mut self, pid: u32, ancestors: &[u32], cpu: f32, now: Instant) -> u64 {
+        self.remove(pid);
+        let sample = Sample {
+            cpu,
+            at: now,
+            ancestors: ancestors.to_vec(),
+        };
+        self.add(pid, &sample, 1.0);
+        self.samples.insert(pid, sample);
+        self.order.push_back((pid, now));
+        self.expire(now)
+    }
+
+    /// Forget the sample of a process that exited.
+    pub fn remove(&mut self, pid: u32) {
+        if let Some(sample) = self.samples.remove(&pid) {
+            self.add(pid, &sample, -1.0);
+        }
+    }
+
+    /// CPU of `pid` and its recently sampled descendants.
+    pub fn total(&self, pid: u32) -> f32 {
+        self.totals.get(&pid).map_or(0.0, |(cpu, _)| cpu.max(0.0))
+    }
+
+    /// Number of processes with a sample.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    fn add(&mut self, pid: u32, sample: &Sample, sign: f32) {
+        for owner in std::iter::once(pid).chain(sample.ancestors.iter().copied()) {
+            let total = self.totals.entry(owner).or_insert((0.0, 0));
+            total.0 += sign * sample.cpu;
+            if sign > 0.0 {
+                total.1 += 1;
+            } else {
+                total.1 = total.1.saturating_sub(1);
+                if total.1 == 0 {
+                    self.totals.remove(&owner);
+                }
+            }
+        }
+    }
+
+    fn expire(&mut self, now: Instant) -> u64 {
+        let mut evicted = 0;
+        while let Some(&(pid, at)) = self.order.front() {
+            let over_cap = self.samples.len() > self.max_pids;
+            if !over_cap && now.duration_since(at) <= self.keep {
+                break;
+            }
+            self.order.pop_front();
+            // Older entries of a PID sampled again since are stale.
+            if self.samples.get(&pid).is_some_and(|s| s.at == at) {
+                self.remove(pid);
+                if over_cap {
+                    evicted += 1;
+                }
+            }
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn children_add_up_in_their_ancestors() {
+        let now = Instant::now();
+        let mut cpu = SubtreeCpu::new(Duration::from_secs(10), 100);
+        // make (100) runs four compilers at 30% each under sh (50).
+        for pid in 201..205 {
+            cpu.observe(pid, &[100, 50], 30.0, now);
+        }
+        cpu.observe(100, &[50], 5.0, now);
+        assert_eq!(cpu.total(201), 30.0);
+        assert_eq!(cpu.total(100), 125.0);
+        assert_eq!(cpu.total(50), 125.0);
+
+        // A new sample replaces the old one; exits and old samples drop out.
+        cpu.observe(201, &[100, 50], 10.0, now);
+        cpu.remove(202);
+        assert_eq!(cpu.total(100), 75.0);
+        cpu.observe(300, &[50], 1.0, now + Duration::from_secs(11));
+        assert_eq!(cpu.total(100), 0.0);
+        assert_eq!(cpu.total(50), 1.0);
+        assert_eq!(cpu.len(), 1);
+    }
+
+    #[test]
+    fn evicts_the_oldest_samples_at_the_cap() {
+        let now = Instant::now();
+        let mut cpu = SubtreeCpu::new(Duration::from_secs(10), 2);
+        cpu.observe(1, &[9], 10.0, now);
+        cpu.observe(2, &[9], 10.0, now);
+        assert_eq!(cpu.observe(3, &[9], 10.0, now), 1);
+        assert_eq!(cpu.total(9), 20.0);
+        assert_eq!(cpu.total(1), 0.0);
+    }
+}
@@ -0,0 +1,307 @@
+//! CPU and NUMA topology, and where a process's threads are running.
+//!
+//! A CPU-bound process that is slow despite idle cores is often confined to
+//! one NUMA node or one CPU by an affinity mask or cpuset. Topology comes from
+//! `/sys/devices/system`; placement from each thread's last CPU
+//! (`/proc/<pid>/task/<tid>/stat`) and `Cpus_allowed_list`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpuInfo {
+    pub cpu: u32,
+    pub node: u32,
+    pub package: u32,
+    pub core: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumaNode {
+    pub node: u32,
+    pub cpus: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CpuTopology {
+    pub cpus: Vec<CpuInfo>,
+    pub nodes: Vec<NumaNode>,
+    pub packages: usize,
+}
+
+impl CpuTopology {
+    /// Topology of this host; empty if sysfs isn't available.
+    pub fn read() -> Self {
+        Self::read_from(Path::new("/sys/devices/system"))
+    }
+
+    fn read_from(sys: &Path) -> Self {
+        let read = |p: &Path| fs::read_to_string(p).ok();
+        let online = read(&sys.join("cpu/online"))
+            .map(|s| parse_cpu_list(&s))
+            .unwrap_or_default();
+
+        // Machines without NUMA (or with it compiled out) have no node dirs;
+        // treat them as a single node 0.
+        let mut nodes: Vec<NumaNode> = fs::read_dir(sys.join("node"))
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let node = e.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+                let cpus = parse_cpu_list(&read(&e.path().join("cpulist"))?);
+                Some(NumaNode { node, cpus })
+            })
+            .collect();
+        nodes.sort_by_key(|n| n.node);
+        if nodes.is_empty() && !online.is_empty() {
+            nodes.push(NumaNode {
+                node: 0,
+                cpus: online.clone(),
+            });
+        }
+
+        let id = |cpu: u32, file: &str| {
+            read(&sys.join(format!("cpu/cpu{cpu}/topology/{file}")))
+                .and_then(|s| s.trim().parse().ok())
+                .unwrap_or(0)
+        };
+        let cpus: Vec<CpuInfo> = online
+            .iter()
+            .map(|&cpu| CpuInfo {
+                cpu,
+                node: nodes
+                    .iter()
+                    .find(|n| n.cpus.contains(&cpu))
+                    .map_or(0, |n| n.node),
+                package: id(cpu, "physical_package_id"),
+                core: id(cpu, "core_id"),
+            })
+            .collect();
+        let packages = cpus
+            .iter()
+            .map(|c| c.package)
+            .collect::<BTreeSet<_>>()
+            .len();
+        Self {
+            cpus,
+            nodes,
+            packages,
+        }
+    }
+
+    pub fn node_of(&self, cpu: u32) -> Option<u32> {
+        self.cpus.iter().find(|c| c.cpu == cpu).map(|c| c.node)
+    }
+}
+
+/// Where a process's threads were last scheduled.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+    pub threads: usize,
+    /// CPUs the threads last ran on.
+    pub cpus: Vec<u32>,
+    /// NUMA nodes of those CPUs.
+    pub nodes: Vec<u32>,
+    /// CPUs the affinity mask / cpuset allows.
+    pub allowed_cpus: Vec<u32>,
+    /// Short note when placement looks constrained, e.g.
+    /// "all 8 threads on NUMA node 0".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+/// Placement of `pid`, or `None` if the process is gone.
+pub fn placement(pid: u32, topology: &CpuTopology) -> Option<Placement> {
+    let status = fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let allowed = status
+        .lines()
+        .find_map(|l| l.strip_prefix("Cpus_allowed_list:"))
+        .map(parse_cpu_list)
+        .unwrap_or_default();
+    let last_cpus: Vec<u32> = fs::read_dir(format!("/proc/{pid}/task"))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| fs::read_to_string(e.path().join("stat")).ok())
+        .filter_map(|stat| last_cpu(&stat))
+        .collect();
+    Some(place(&last_cpus, allowed, topology))
+}
+
+fn place(last_cpus: &[u32], allowed_cpus: Vec<u32>, topology: &CpuTopology) -> Placement {
+    let cpus: BTreeSet<u32> = last_cpus.iter().copied().collect();
+    let nodes: BTreeSet<u32> = cpus.iter().filter_map(|&c| topology.node_of(c)).collect();
+    let allowed_nodes: BTreeSet<u32> = allowed_cpus
+        .iter()
+        .filter_map(|&c| topology.node_of(c))
+        .collect();
+    let threads = last_cpus.len();
+    let numa = topology.nodes.len() > 1;
+
+    let hint = if threads > 1 && cpus.len() == 1 {
+        let cpu = cpus.first().copied().unwrap_or_default();
+        Some(if allowed_cpus.len() == 1 {
+            format!("all {threads} threads pinned to CPU {cpu}")
+        } else {
+            format!("all {threads} threads on CPU {cpu}")
+        })
+    } else if numa && allowed_nodes.len() == 1 {
+        let node = allowed_nodes.first().copied().unwrap_or_default();
+        Some(format!(
+            "pinned to NUMA node {node} (CPUs {})",
+            format_cpu_list(&allowed_cpus)
+        ))
+    } else if numa && threads > 1 && nodes.len() == 1 {
+        let node = nodes.first().copied().unwrap_or_default();
+        Some(format!("all {threads} threads on NUMA node {node}"))
+    } else {
+        None
+    };
+
+    Placement {
+        threads,
+        cpus: cpus.into_iter().collect(),
+        nodes: nodes.into_iter().collect(),
+        allowed_cpus,
+        hint,
+    }
+}
+
+/// `processor` (field 39) of a `/proc/<pid>/stat` line. The comm field can
+/// contain spaces and parentheses, so fields are counted from the last `)`.
+fn last_cpu(stat: &str) -> Option<u32> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(36)?.parse().ok()
+}
+
+/// Parse a kernel CPU list such as `0-3,8,10-11`.
+pub fn parse_cpu_list(list: &str) -> Vec<u32> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((lo, hi)) => {
+                if let (Ok(lo), Ok(hi)) = (lo.trim().parse::<u32>(), hi.trim().parse::<u32>()) {
+                    cpus.extend(lo..=hi);
+                }
+            }
+            None => cpus.extend(part.trim().parse::<u32>().ok()),
+        }
+    }
+    cpus
+}
+
+/// Inverse of [`parse_cpu_list`] for sorted input.
+pub fn format_cpu_list(cpus: &[u32]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut iter = cpus.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end += 1;
+            iter.next();
+        }
+        ranges.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{start}-{end}")
+        });
+    }
+    ranges.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_nodes() -> CpuTopology {
+        let cpu = |cpu, node| CpuInfo {
+            cpu,
+            node,
+            package: node,
+            core: cpu % 2,
+        };
+        CpuTopology {
+            cpus: vec![cpu(0, 0), cpu(1, 0), cpu(2, 1), cpu(3, 1)],
+            nodes: vec![
+                NumaNode {
+                    node: 0,
+                    cpus: vec![0, 1],
+                },
+                NumaNode {
+                    node: 1,
+                    cpus: vec![2, 3],
+                },
+            ],
+            packages: 2,
+        }
+    }
+
+    #[test]
+    fn cpu_lists_round_trip() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(format_cpu_list(&[0, 1, 2, 3, 8, 10, 11]), "0-3,8,10-11");
+        assert!(parse_cpu_list("").is_empty());
+    }
+
+    #[test]
+    fn reads_processor_after_odd_comm() {
+        let fields: Vec<String> = (3..=52).map(|i| i.to_string()).collect();
+        let stat = format!("42 (a) b (c)) {}", fields.join(" "));
+        assert_eq!(last_cpu(&stat), Some(39));
+    }
+
+    #[test]
+    fn hints_describe_constrained_placement() {
+        let topo = two_nodes();
+        let all = vec![0, 1, 2, 3];
+        assert_eq!(
+            place(&[1, 1, 1], all.clone(), &topo).hint.as_deref(),
+            Some("all 3 threads on CPU 1")
+        );
+        assert_eq!(
+            place(&[0, 1], vec![0, 1], &topo).hint.as_deref(),
+            Some("pinned to NUMA node 0 (CPUs 0-1)")
+        );
+        let spread_on_one_node = place(&[2, 3, 3], all.clone(), &topo);
+        assert_eq!(spread_on_one_node.nodes, vec![1]);
+        assert_eq!(
+            spread_on_one_node.hint.as_deref(),
+            Some("all 3 threads on NUMA node 1")
+        );
+        assert_eq!(place(&[0, 3], all, &topo).hint, None);
+    }
+
+    #[test]
+    fn reads_sysfs_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let sys = dir.path();
+        let write = |rel: &str, text: &str| {
+            let path = sys.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("cpu/online", "0-3\n");
+        write("node/node0/cpulist", "0-1\n");
+        write("node/node1/cpulist", "2-3\n");
+        for cpu in 0..4 {
+            write(
+                &format!("cpu/cpu{cpu}/topology/physical_package_id"),
+                &format!("{}\n", cpu / 2),
+            );
+            write(
+                &format!("cpu/cpu{cpu}/topology/core_id"),
+                &format!("{}\n", cpu % 2),
+            );
+        }
+        assert_eq!(CpuTopology::read_from(sys), two_nodes());
+    }
+
+    #[test]
+    fn own_process_has_a_placement() {
+        let placement = placement(std::process::id(), &CpuTopology::read()).unwrap();
+        assert!(placement.threads >= 1);
+        assert!(!placement.allowed_cpus.is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+//! Recorded event traces, for replaying through rules with
+//! `RuleEngine::evaluate_trace` (feature `trace-replay`).
+//!
+//! A trace is JSONL as the `jsonl` handler writes it. Lines that aren't
+//! process events (system snapshots, exec aggregates) and blank lines are
+//! skipped.
+
+use crate::ProcessEvent;
+use anyhow::Context;
+use std::path::Path;
+
+pub fn load(path: &Path) -> anyhow::Result<Vec<ProcessEvent>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read trace {}", path.display()))?;
+    parse(&text).with_context(|| format!("invalid trace {}", path.display()))
+}
+
+pub fn parse(text: &str) -> anyhow::Result<Vec<ProcessEvent>> {
+    let mut events = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let value: serde_json::Value =
+            serde_json::from_str(line).with_context(|| format!("line {}", index + 1))?;
+        if value.get("base").is_none() {
+            continue;
+        }
+        let event = serde_json::from_value(value)
+            .with_context(|| format!("line {}: not a process event", index + 1))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PERCENT_MILLI_UNKNOWN, ProcessEventWire};
+    use linnix_ai_ebpf_common::EventType;
+
+    fn event_line(pid: u32) -> String {
+        let event = ProcessEvent::new(ProcessEventWire {
+            pid,
+            ppid: 1,
+            uid: 0,
+            gid: 0,
+            event_type: EventType::Fork as u32,
+            ts_ns: 5,
+            seq: 0,
+            comm: [0; 16],
+            exit_time_ns: 0,
+            cpu_pct_milli: PERCENT_MILLI_UNKNOWN,
+            mem_pct_milli: PERCENT_MILLI_UNKNOWN,
+            data: 0,
+            data2: 0,
+            aux: 0,
+            aux2: 0,
+            cgroup_id: 0,
+        });
+        serde_json::to_string(&event).unwrap()
+    }
+
+    #[test]
+    fn reads_the_events_of_handler_output() {
+        let text = format!(
+            "{}\n\n{{\"timestamp\":1,\"cpu_percent\":3.0}}\n{}\n",
+            event_line(10),
+            event_line(11)
+        );
+        let events = parse(&text).unwrap();
+        assert_eq!(
+            events.iter().map(|e| (e.pid, e.ts_ns)).collect::<Vec<_>>(),
+            [(10, 5), (11, 5)]
+        );
+
+        let err = parse(&format!("{}\n{{\"base\":", event_line(10))).unwrap_err();
+        assert!(format!("{err:#}").starts_with("line 2"), "{err:#}");
+    }
+}
@@ -0,0 +1,239 @@
+//! Rule noise scoring and tuning suggestions.
+//!
+//! Combines how often each rule fired with the useful/noise labels given to
+//! its alerts (Slack buttons, `POST /api/feedback`) into a noise score, and
+//! for noisy rules proposes a higher threshold and/or longer cooldown in the
+//! shape `PATCH /rules/{name}` accepts. Labels outweigh raw volume once a
+//! handful exist; without any, a rule that fires many times a day scores as
+//! noisy on volume alone.
+
+use crate::alerts::RuleView;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Alerts per day at which volume alone scores 0.5.
+const QUIET_FIRES_PER_DAY: f64 = 5.0;
+/// Labels at which feedback and volume weigh the same.
+const LABEL_PRIOR: f64 = 3.0;
+/// Rules scoring at least this get a suggestion.
+const SUGGEST_ABOVE: f64 = 0.6;
+/// Longest cooldown suggested, in seconds.
+const MAX_SUGGESTED_COOLDOWN: u64 = 3600;
+
+/// Feedback labels for one rule's alerts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Labels {
+    pub useful: u64,
+    pub noise: u64,
+}
+
+impl Labels {
+    /// Count a stored label; `wrong` counts as noise, unknown labels are
+    /// ignored.
+    pub fn add(&mut self, label: &str, count: u64) {
+        match label {
+            "useful" => self.useful += count,
+            "noise" | "wrong" => self.noise += count,
+            _ => {}
+        }
+    }
+
+    fn total(&self) -> u64 {
+        self.useful + self.noise
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleQuality {
+    pub rule: String,
+    pub detector: &'static str,
+    /// 0 (every alert useful) to 1 (all noise).
+    pub noise_score: f64,
+    pub fires: u64,
+    pub fires_per_day: f64,
+    pub useful: u64,
+    pub noise: u64,
+    pub threshold_field: &'static str,
+    pub threshold: Option<serde_json::Number>,
+    pub cooldown: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<Suggestion>,
+}
+
+/// Proposed change, usable as a `PATCH /rules/{name}` body.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cooldown: Option<u64>,
+    pub reason: String,
+}
+
+/// Noise score from alert volume and feedback labels.
+pub fn noise_score(fires_per_day: f64, labels: Labels) -> f64 {
+    let volume = fires_per_day / (fires_per_day + QUIET_FIRES_PER_DAY);
+    let labelled = labels.total() as f64;
+    if labelled == 0.0 {
+        return volume;
+    }
+    let weight = labelled / (labelled + LABEL_PRIOR);
+    weight * labels.noise as f64 / labelled + (1.0 - weight) * volume
+}
+
+/// Score every rule over `observed` (the span `fires` and `labels` cover),
+/// noisiest first.
+pub fn assess(
+    rules: &[RuleView],
+    fires: &HashMap<String, u64>,
+    labels: &HashMap<String, Labels>,
+    observed: Duration,
+) -> Vec<RuleQuality> {
+    let days = (observed.as_secs_f64() / 86_400.0).max(1.0 / 24.0);
+    let mut scored: Vec<_> = rules
+        .iter()
+        .map(|view| {
+            let fires = fires.get(&view.name).copied().unwrap_or(0);
+            let labels = labels.get(&view.name).copied().unwrap_or_default();
+            let fires_per_day = fires as f64 / days;
+            let score = noise_score(fires_per_day, labels);
+            RuleQuality {
+                rule: view.name.clone(),
+                detector: view.detector,
+                noise_score: (score * 1000.0).round() / 1000.0,
+                fires,
+                fires_per_day: (fires_per_day * 10.0).round() / 10.0,
+                useful: labels.useful,
+                noise: labels.noise,
+                threshold_field: view.threshold_field,
+                threshold: view.threshold.clone(),
+                cooldown: view.cooldown,
+                suggestion: suggest(view, fires_per_day, labels, score),
+            }
+        })
+        .collect();
+    scored.sort_by(|a, b| b.noise_score.total_cmp(&a.noise_score));
+    scored
+}
+
+fn suggest(view: &RuleView, fires_per_day: f64, labels: Labels, score: f64) -> Option<Suggestion> {
+    if score < SUGGEST_ABOVE {
+        return None;
+    }
+    let mut reasons = Vec::new();
+    if labels.noise > 0 {
+        reasons.push(format!(
+            "{} of {} labelled alerts marked noise",
+            labels.noise,
+            labels.total()
+        ));
+    }
+    let frequent = fires_per_day >= QUIET_FIRES_PER_DAY;
+    if frequent {
+        reasons.push(format!("{fires_per_day:.1} alerts/day"));
+    }
+    let threshold = view
+        .threshold
+        .as_ref()
+        .and_then(|t| raise(t, view.threshold_field, score));
+    // Frequent re-fires fold into fewer alerts with a longer cooldown.
+    let cooldown = (frequent && view.cooldown < MAX_SUGGESTED_COOLDOWN)
+        .then(|| (view.cooldown.max(60) * 2).min(MAX_SUGGESTED_COOLDOWN));
+    if threshold.is_none() && cooldown.is_none() {
+        return None;
+    }
+    Some(Suggestion {
+        threshold,
+        cooldown,
+        reason: reasons.join("; "),
+    })
+}
+
+/// `current` scaled by `1 + score`: whole numbers (at least one more) for
+/// count detectors, one decimal and at most 100 for percentages.
+fn raise(current: &serde_json::Number, field: &str, score: f64) -> Option<f64> {
+    let factor = 1.0 + score;
+    if let Some(n) = current.as_u64() {
+        let n = n as f64;
+        return Some((n * factor).round().max(n + 1.0));
+    }
+    let value = current.as_f64()?;
+    let mut raised = (value * factor * 10.0).round() / 10.0;
+    if field != "threshold" {
+        raised = raised.min(100.0);
+    }
+    (raised > value).then_some(raised)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(name: &str, field: &'static str, threshold: serde_json::Number) -> RuleView {
+        RuleView {
+            name: name.into(),
+            detector: "fork_burst",
+            severity: "medium",
+            threshold_field: field,
+            threshold: Some(threshold),
+            cooldown: 60,
+            mode: Default::default(),
+        }
+    }
+
+    #[test]
+    fn labels_outweigh_volume_once_there_are_enough() {
+        assert_eq!(noise_score(0.0, Labels::default()), 0.0);
+        assert_eq!(noise_score(5.0, Labels::default()), 0.5);
+        let useful = Labels {
+            useful: 12,
+            noise: 0,
+        };
+        assert!(noise_score(100.0, useful) < 0.2);
+        let noisy = Labels {
+            useful: 1,
+            noise: 9,
+        };
+        assert!(noise_score(0.5, noisy) > 0.7);
+    }
+
+    #[test]
+    fn suggests_raising_noisy_rules_only() {
+        let rules = [
+            view("burst", "threshold", 30.into()),
+            view(
+                "psi",
+                "threshold_pct",
+                serde_json::Number::from_f64(80.0).unwrap(),
+            ),
+            view("quiet", "threshold", 10.into()),
+        ];
+        let fires = HashMap::from([("burst".to_string(), 140), ("psi".to_string(), 2)]);
+        let labels = HashMap::from([(
+            "psi".to_string(),
+            Labels {
+                useful: 0,
+                noise: 6,
+            },
+        )]);
+        let scored = assess(&rules, &fires, &labels, Duration::from_secs(7 * 86_400));
+        let names: Vec<_> = scored.iter().map(|q| q.rule.as_str()).collect();
+        assert_eq!(names, ["burst", "psi", "quiet"]);
+
+        let psi = scored[1].suggestion.as_ref().unwrap();
+        assert_eq!(psi.threshold, Some(100.0), "percentages cap at 100");
+        assert_eq!(psi.cooldown, None, "fires rarely");
+        assert_eq!(psi.reason, "6 of 6 labelled alerts marked noise");
+
+        let burst = &scored[0];
+        assert_eq!(burst.fires_per_day, 20.0);
+        let suggestion = burst.suggestion.as_ref().unwrap();
+        assert_eq!(suggestion.threshold, Some(54.0));
+        assert_eq!(suggestion.cooldown, Some(120));
+        assert_eq!(suggestion.reason, "20.0 alerts/day");
+
+        assert!(scored[2].suggestion.is_none());
+        assert_eq!(scored[2].noise_score, 0.0);
+    }
+}
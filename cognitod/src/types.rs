@@ -17,6 +17,18 @@ pub struct SystemSnapshot {
     pub psi_memory_full_avg10: f32, // % time ALL tasks stalled (complete thrashing)
     pub psi_io_some_avg10: f32,  // % time tasks stalled on I/O
     pub psi_io_full_avg10: f32,  // % time ALL tasks stalled on I/O
+    // Cgroups that hit their CFS quota since the previous snapshot, most
+    // throttled first. Busy-looking pods can be slow while the node is idle.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cgroup_throttling: Vec<crate::collectors::throttle::CgroupThrottle>,
+    // Thermal/frequency throttling of the CPUs themselves (cooling or power
+    // capping); None where the host exposes neither (most VMs).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_thermal: Option<crate::collectors::thermal::CpuThermal>,
+    // Processes whose GPU memory has been growing, fastest first; empty
+    // without an NVIDIA GPU.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gpu_memory: Vec<crate::collectors::gpu::GpuMemoryTrend>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -0,0 +1,349 @@
+//! Optional check for newer releases.
+//!
+//! A privileged node agent shouldn't send operators to a download that
+//! could have been tampered with, so a newer release is only reported once
+//! its `SHA256SUMS` verifies against a pinned minisign public key and, if
+//! `artifact` is set, the package itself matches its signed checksum.
+//! Nothing is downloaded for installation and nothing is installed.
+
+use crate::config::UpdateCheckConfig;
+use anyhow::{Context, Result, bail, ensure};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use log::{info, warn};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+const SIGNATURE_ASSET: &str = "SHA256SUMS.minisig";
+
+/// A minisign public key (`Ed` algorithm, key id, Ed25519 key).
+pub struct MinisignKey {
+    id: [u8; 8],
+    key: VerifyingKey,
+}
+
+impl MinisignKey {
+    /// Parse the base64 key line, or a whole `.pub` file with its comment.
+    pub fn parse(text: &str) -> Result<Self> {
+        let line = text
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with("untrusted comment:"))
+            .context("empty minisign public key")?;
+        let raw = BASE64
+            .decode(line)
+            .context("minisign public key is not base64")?;
+        ensure!(
+            raw.len() == 42 && &raw[..2] == b"Ed",
+            "not a minisign Ed25519 public key"
+        );
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&raw[2..10]);
+        let key = VerifyingKey::from_bytes(raw[10..].try_into()?)
+            .context("invalid Ed25519 public key")?;
+        Ok(Self { id, key })
+    }
+
+    /// Verify a `.minisig` file over `data` and return its trusted comment.
+    /// Only legacy (`Ed`) signatures are accepted: prehashed (`ED`) ones need
+    /// BLAKE2b, so sign the checksum file with `minisign -S -l`.
+    pub fn verify(&self, data: &[u8], minisig: &str) -> Result<String> {
+        let mut lines = minisig.lines().map(str::trim).filter(|l| !l.is_empty());
+        let _untrusted = lines.next().context("empty signature file")?;
+        let sig_line = lines.next().context("signature file has no signature")?;
+        let trusted = lines
+            .next()
+            .and_then(|l| l.strip_prefix("trusted comment: "))
+            .context("signature file has no trusted comment")?;
+        let global_line = lines
+            .next()
+            .context("signature file has no global signature")?;
+
+        let raw = BASE64.decode(sig_line).context("signature is not base64")?;
+        ensure!(raw.len() == 74, "malformed minisign signature");
+        match &raw[..2] {
+            b"Ed" => {}
+            b"ED" => bail!("prehashed minisign signatures are not supported; sign with -l"),
+            _ => bail!("unknown minisign signature algorithm"),
+        }
+        ensure!(
+            raw[2..10] == self.id,
+            "signed with key {}, expected {}",
+            hex::encode_upper(&raw[2..10]),
+            hex::encode_upper(self.id)
+        );
+        let signature = Signature::from_slice(&raw[10..])?;
+        self.key
+            .verify(data, &signature)
+            .context("signature does not match the checksum file")?;
+
+        // The global signature covers the signature and the trusted
+        // comment, so the comment can't be swapped either.
+        let global = BASE64
+            .decode(global_line)
+            .context("global signature is not base64")?;
+        let mut signed = raw[10..].to_vec();
+        signed.extend_from_slice(trusted.as_bytes());
+        self.key
+            .verify(&signed, &Signature::from_slice(&global)?)
+            .context("trusted comment signature does not match")?;
+        Ok(trusted.to_string())
+    }
+}
+
+/// `(major, minor, patch)` of `v1.2.3` / `1.2.3-rc1`; pre-release suffixes
+/// are ignored.
+pub fn parse_version(raw: &str) -> Option<(u64, u64, u64)> {
+    let core = raw
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()?;
+    let mut parts = core.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+    let patch = parts.next().unwrap_or("0").parse().ok()?;
+    Some((major, minor, patch))
+}
+
+/// Expected SHA-256 of `name` in a `sha256sum` listing.
+fn checksum_for<'a>(sums: &'a str, name: &str) -> Option<&'a str> {
+    sums.lines().find_map(|line| {
+        let (digest, file) = line.split_once(char::is_whitespace)?;
+        (file.trim().trim_start_matches('*') == name).then_some(digest)
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    #[serde(default)]
+    html_url: Option<String>,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Outcome of the latest check, served in `/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateStatus {
+    pub current: String,
+    pub latest: Option<String>,
+    /// True only when a newer release passed verification.
+    pub update_available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_url: Option<String>,
+    /// Why the last check failed, or why a newer release was not trusted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Unix seconds.
+    pub checked_at: i64,
+}
+
+pub struct UpdateChecker {
+    cfg: UpdateCheckConfig,
+    key: MinisignKey,
+    http: Client,
+    status: RwLock<Option<UpdateStatus>>,
+}
+
+impl UpdateChecker {
+    pub fn new(cfg: UpdateCheckConfig, http: Client) -> Result<Self> {
+        let key = MinisignKey::parse(&cfg.public_key).context("[update_check] public_key")?;
+        Ok(Self {
+            cfg,
+            key,
+            http,
+            status: RwLock::new(None),
+        })
+    }
+
+    pub fn status(&self) -> Option<UpdateStatus> {
+        self.status.read().ok()?.clone()
+    }
+
+    /// Check now and then every `interval_secs`.
+    pub async fn run(self: Arc<Self>) {
+        let mut tick = tokio::time::interval(Duration::from_secs(self.cfg.interval_secs.max(60)));
+        loop {
+            tick.tick().await;
+            let status = self.check().await;
+            if let Ok(mut slot) = self.status.write() {
+                *slot = Some(status);
+            }
+        }
+    }
+
+    async fn check(&self) -> UpdateStatus {
+        let mut status = UpdateStatus {
+            current: env!("CARGO_PKG_VERSION").to_string(),
+            latest: None,
+            update_available: false,
+            release_url: None,
+            error: None,
+            checked_at: chrono::Utc::now().timestamp(),
+        };
+        let release = match self.latest_release().await {
+            Ok(release) => release,
+            Err(e) => {
+                warn!("[update] checking {} failed: {e:#}", self.cfg.feed_url);
+                status.error = Some(format!("{e:#}"));
+                return status;
+            }
+        };
+        let latest = release.tag_name.trim_start_matches('v').to_string();
+        status.latest = Some(latest.clone());
+        status.release_url = release.html_url.clone();
+        match (parse_version(&latest), parse_version(&status.current)) {
+            (Some(latest), Some(current)) if latest > current => {}
+            _ => return status,
+        }
+
+        match self.verify_release(&release, &latest).await {
+            Ok(trusted) => {
+                warn!(
+                    "[update] cognitod {latest} is available (running {}); checksums verified ({trusted}): {}",
+                    status.current,
+                    status.release_url.as_deref().unwrap_or(&self.cfg.feed_url)
+                );
+                status.update_available = true;
+            }
+            Err(e) => {
+                warn!("[update] release {latest} failed verification, not offering it: {e:#}");
+                status.error = Some(format!("verification failed: {e:#}"));
+            }
+        }
+        status
+    }
+
+    async fn latest_release(&self) -> Result<Release> {
+        self.get(&self.cfg.feed_url)
+            .await?
+            .json()
+            .await
+            .context("unexpected release feed response")
+    }
+
+    /// Verify the release's signed checksums and, if configured, the
+    /// artifact. Returns the signature's trusted comment.
+    async fn verify_release(&self, release: &Release, version: &str) -> Result<String> {
+        let asset_url = |name: &str| {
+            release
+                .assets
+                .iter()
+                .find(|a| a.name == name)
+                .map(|a| a.browser_download_url.as_str())
+                .with_context(|| format!("release has no {name}"))
+        };
+        let sums = self.get(asset_url(CHECKSUMS_ASSET)?).await?.text().await?;
+        let minisig = self.get(asset_url(SIGNATURE_ASSET)?).await?.text().await?;
+        let trusted = self.key.verify(sums.as_bytes(), &minisig)?;
+
+        let artifact = self.cfg.artifact.replace("{version}", version);
+        if !artifact.is_empty() {
+            let expected = checksum_for(&sums, &artifact)
+                .with_context(|| format!("{artifact} is not in {CHECKSUMS_ASSET}"))?;
+            let bytes = self.get(asset_url(&artifact)?).await?.bytes().await?;
+            let actual = hex::encode(Sha256::digest(&bytes));
+            ensure!(
+                actual.eq_ignore_ascii_case(expected),
+                "{artifact} has SHA-256 {actual}, signed checksum is {expected}"
+            );
+            info!("[update] {artifact} matches its signed checksum");
+        }
+        Ok(trusted)
+    }
+
+    async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .http
+            .get(url)
+            .header(
+                "User-Agent",
+                concat!("cognitod/", env!("CARGO_PKG_VERSION")),
+            )
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await?
+            .error_for_status()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const KEY_ID: [u8; 8] = *b"linnix01";
+
+    fn public_key(signing: &SigningKey) -> String {
+        let mut raw = b"Ed".to_vec();
+        raw.extend_from_slice(&KEY_ID);
+        raw.extend_from_slice(signing.verifying_key().as_bytes());
+        format!(
+            "untrusted comment: minisign public key\n{}\n",
+            BASE64.encode(raw)
+        )
+    }
+
+    fn minisig(signing: &SigningKey, data: &[u8], trusted: &str) -> String {
+        let signature = signing.sign(data).to_bytes();
+        let mut raw = b"Ed".to_vec();
+        raw.extend_from_slice(&KEY_ID);
+        raw.extend_from_slice(&signature);
+        let mut global = signature.to_vec();
+        global.extend_from_slice(trusted.as_bytes());
+        format!(
+            "untrusted comment: signature\n{}\ntrusted comment: {trusted}\n{}\n",
+            BASE64.encode(raw),
+            BASE64.encode(signing.sign(&global).to_bytes())
+        )
+    }
+
+    #[test]
+    fn verifies_signed_checksums() {
+        let signing = SigningKey::from_bytes(&[7u8; 32]);
+        let key = MinisignKey::parse(&public_key(&signing)).unwrap();
+        let sums = "abc123  cognitod_0.3.0_amd64.deb\n";
+        let sig = minisig(
+            &signing,
+            sums.as_bytes(),
+            "timestamp:1700000000\tfile:SHA256SUMS",
+        );
+
+        assert_eq!(
+            key.verify(sums.as_bytes(), &sig).unwrap(),
+            "timestamp:1700000000\tfile:SHA256SUMS"
+        );
+        assert!(key.verify(b"tampered", &sig).is_err());
+        let swapped = sig.replace("file:SHA256SUMS", "file:other");
+        assert!(key.verify(sums.as_bytes(), &swapped).is_err());
+
+        let other = MinisignKey::parse(&public_key(&SigningKey::from_bytes(&[8u8; 32]))).unwrap();
+        assert!(other.verify(sums.as_bytes(), &sig).is_err());
+
+        assert_eq!(
+            checksum_for(sums, "cognitod_0.3.0_amd64.deb"),
+            Some("abc123")
+        );
+        assert_eq!(checksum_for(sums, "cognitod.deb"), None);
+    }
+
+    #[test]
+    fn compares_release_versions() {
+        assert_eq!(parse_version("v0.3.1"), Some((0, 3, 1)));
+        assert_eq!(parse_version("1.2.0-rc1"), Some((1, 2, 0)));
+        assert_eq!(parse_version("2"), Some((2, 0, 0)));
+        assert_eq!(parse_version("latest"), None);
+        assert!(parse_version("0.10.0") > parse_version("0.9.9"));
+    }
+}
@@ -0,0 +1,330 @@
+//! Shared file watching for reloadable files.
+//!
+//! Features that reload a file on change (rules, config) register it here
+//! rather than each running its own polling loop. The parent directory is
+//! watched with inotify so atomic replaces (write to a temp file, rename over
+//! the original, as editors and `persist_rule` do) are seen; when inotify is
+//! unavailable (e.g. the directory doesn't exist yet, or watches are
+//! exhausted) the file is polled instead. Bursts of changes are debounced
+//! into one reload, and reloads and failures are counted per watch in
+//! `/metrics`.
+
+use crate::config::WatchConfig;
+use crate::metrics::{Metrics, WatchCounters};
+use log::{debug, info, warn};
+use std::ffi::CString;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::unix::AsyncFd;
+use tokio::task::JoinHandle;
+
+/// Size of `struct inotify_event` without the trailing name.
+const INOTIFY_EVENT_HEADER: usize = 16;
+
+/// Watch `path` and call `on_change` after it changes (debounced). Errors from
+/// `on_change` are logged and counted; the watch keeps running.
+pub fn watch<F>(
+    name: &str,
+    path: impl Into<PathBuf>,
+    cfg: &WatchConfig,
+    metrics: &Metrics,
+    on_change: F,
+) -> JoinHandle<()>
+where
+    F: FnMut() -> anyhow::Result<()> + Send + 'static,
+{
+    let path = path.into();
+    let counters = metrics.register_watch(name, &path);
+    let name = name.to_string();
+    let cfg = cfg.clone();
+    tokio::spawn(run(name, path, cfg, counters, on_change))
+}
+
+async fn run<F>(
+    name: String,
+    path: PathBuf,
+    cfg: WatchConfig,
+    counters: Arc<WatchCounters>,
+    mut on_change: F,
+) where
+    F: FnMut() -> anyhow::Result<()> + Send + 'static,
+{
+    let debounce = Duration::from_millis(cfg.debounce_ms);
+    let mut source = Source::open(&path, &cfg);
+    counters.set_polling(matches!(source, Source::Poll { .. }));
+    info!(
+        "[watch] {name}: watching {} ({})",
+        path.display(),
+        source.kind()
+    );
+
+    loop {
+        if let Err(e) = source.changed(&path).await {
+            warn!("[watch] {name}: inotify failed ({e}); polling instead");
+            source = Source::poll(&path, &cfg);
+            counters.set_polling(true);
+            continue;
+        }
+        // Wait until the file has been quiet for `debounce`.
+        loop {
+            tokio::select! {
+                res = source.changed(&path) => {
+                    if res.is_err() {
+                        break;
+                    }
+                }
+                _ = tokio::time::sleep(debounce) => break,
+            }
+        }
+        counters.inc_change();
+        match on_change() {
+            Ok(()) => {
+                debug!("[watch] {name}: reloaded {}", path.display());
+                counters.inc_reload();
+            }
+            Err(e) => {
+                warn!("[watch] {name}: reload of {} failed: {e:#}", path.display());
+                counters.inc_failure();
+            }
+        }
+    }
+}
+
+/// What identifies a version of the file for polling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    ino: u64,
+}
+
+fn stamp(path: &Path) -> Option<FileStamp> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = std::fs::metadata(path).ok()?;
+    Some(FileStamp {
+        modified: meta.modified().ok(),
+        len: meta.len(),
+        ino: meta.ino(),
+    })
+}
+
+enum Source {
+    Inotify {
+        fd: AsyncFd<OwnedFd>,
+    },
+    Poll {
+        interval: tokio::time::Interval,
+        last: Option<FileStamp>,
+    },
+}
+
+impl Source {
+    fn open(path: &Path, cfg: &WatchConfig) -> Self {
+        if cfg.force_polling {
+            return Self::poll(path, cfg);
+        }
+        match inotify_dir(path) {
+            Ok(fd) => Source::Inotify { fd },
+            Err(e) => {
+                debug!("[watch] inotify unavailable for {}: {e}", path.display());
+                Self::poll(path, cfg)
+            }
+        }
+    }
+
+    fn poll(path: &Path, cfg: &WatchConfig) -> Self {
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(cfg.poll_interval_ms.max(10)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Source::Poll {
+            interval,
+            last: stamp(path),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Source::Inotify { .. } => "inotify",
+            Source::Poll { .. } => "polling",
+        }
+    }
+
+    /// Resolves when the file may have changed. Cancel-safe: nothing is
+    /// consumed across an await point.
+    async fn changed(&mut self, path: &Path) -> std::io::Result<()> {
+        match self {
+            Source::Inotify { fd } => {
+                let name = path.file_name().map(|n| n.as_bytes().to_vec());
+                loop {
+                    let mut guard = fd.readable().await?;
+                    let mut buf = [0u8; 4096];
+                    let read = guard.try_io(|fd| {
+                        let n = unsafe {
+                            libc::read(fd.get_ref().as_raw_fd(), buf.as_mut_ptr().cast(), buf.len())
+                        };
+                        if n < 0 {
+                            Err(std::io::Error::last_os_error())
+                        } else {
+                            Ok(n as usize)
+                        }
+                    });
+                    match read {
+                        Ok(Ok(n)) => {
+                            if inotify_names(&buf[..n]).any(|n| Some(n) == name.as_deref()) {
+                                return Ok(());
+                            }
+                        }
+                        Ok(Err(e)) => return Err(e),
+                        Err(_would_block) => continue,
+                    }
+                }
+            }
+            Source::Poll { interval, last } => loop {
+                interval.tick().await;
+                let current = stamp(path);
+                if current != *last {
+                    *last = current;
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
+
+/// inotify instance watching the directory containing `path`.
+fn inotify_dir(path: &Path) -> std::io::Result<AsyncFd<OwnedFd>> {
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let dir = CString::new(dir.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let raw = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+    if raw < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+    let mask = libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_TO
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_ATTRIB;
+    if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), mask) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    AsyncFd::new(fd)
+}
+
+/// File names in a buffer of `struct inotify_event` records.
+fn inotify_names(mut buf: &[u8]) -> impl Iterator<Item = &[u8]> {
+    std::iter::from_fn(move || {
+        if buf.len() < INOTIFY_EVENT_HEADER {
+            return None;
+        }
+        let len = u32::from_ne_bytes(buf[12..16].try_into().ok()?) as usize;
+        let end = (INOTIFY_EVENT_HEADER + len).min(buf.len());
+        let name = &buf[INOTIFY_EVENT_HEADER..end];
+        buf = &buf[end..];
+        // The name is NUL-padded to an alignment boundary.
+        let trimmed = name.split(|&b| b == 0).next().unwrap_or_default();
+        Some(trimmed)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn config(force_polling: bool) -> WatchConfig {
+        WatchConfig {
+            debounce_ms: 100,
+            poll_interval_ms: 20,
+            force_polling,
+        }
+    }
+
+    async fn wait_for(what: &str, cond: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if cond() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("timed out waiting for {what}");
+    }
+
+    async fn debounces_and_counts_failures(force_polling: bool) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.yaml");
+        std::fs::write(&path, "a").unwrap();
+        let metrics = Metrics::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let seen = Arc::clone(&calls);
+        let watched = path.clone();
+        let handle = watch(
+            "rules",
+            &path,
+            &config(force_polling),
+            &metrics,
+            move || {
+                seen.fetch_add(1, Ordering::SeqCst);
+                match std::fs::read_to_string(&watched)?.as_str() {
+                    "bad" => anyhow::bail!("parse error"),
+                    _ => Ok(()),
+                }
+            },
+        );
+        // Let the watch start before writing.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // A burst of writes plus an atomic replace: one reload.
+        std::fs::write(&path, "ab").unwrap();
+        std::fs::write(&path, "abc").unwrap();
+        let tmp = dir.path().join("rules.yaml.tmp");
+        std::fs::write(&tmp, "abcd").unwrap();
+        std::fs::rename(&tmp, &path).unwrap();
+        wait_for("reload", || calls.load(Ordering::SeqCst) == 1).await;
+
+        // Changes to other files in the directory are ignored.
+        std::fs::write(dir.path().join("other"), "x").unwrap();
+        std::fs::write(&path, "bad").unwrap();
+        wait_for("failed reload", || calls.load(Ordering::SeqCst) == 2).await;
+        let stats = metrics.watch_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].watch, "rules");
+        assert_eq!(stats[0].polling, force_polling);
+        assert_eq!((stats[0].reloads, stats[0].failures), (1, 1));
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn inotify_watch_debounces_and_counts_failures() {
+        debounces_and_counts_failures(false).await;
+    }
+
+    #[tokio::test]
+    async fn polling_watch_debounces_and_counts_failures() {
+        debounces_and_counts_failures(true).await;
+    }
+
+    #[test]
+    fn parses_inotify_records() {
+        let mut buf = Vec::new();
+        for name in [&b"a.yaml"[..], b"rules.yaml"] {
+            let padded = name.len().div_ceil(16) * 16;
+            buf.extend_from_slice(&1i32.to_ne_bytes());
+            buf.extend_from_slice(&libc::IN_CLOSE_WRITE.to_ne_bytes());
+            buf.extend_from_slice(&0u32.to_ne_bytes());
+            buf.extend_from_slice(&(padded as u32).to_ne_bytes());
+            buf.extend_from_slice(name);
+            buf.resize(buf.len() + padded - name.len(), 0);
+        }
+        let names: Vec<_> = inotify_names(&buf).collect();
+        assert_eq!(names, vec![&b"a.yaml"[..], b"rules.yaml"]);
+    }
+}
@@ -22,8 +22,8 @@ use bytemuck::{Pod, Zeroable};
 //   [0..8]   flags: u64        - Slot state (EMPTY/WRITING/READY/ABANDONED)
 //   [8..16]  reserved_at_ns    - Timestamp when slot was reserved
 //   [16..24] ticket_id: u64    - Sequence number for ordering validation
-//   [24..120] event: ProcessEvent (96 bytes)
-//   [120..256] _padding        - Cache line alignment padding
+//   [24..128] event: ProcessEvent (104 bytes)
+//   [128..256] _padding        - Cache line alignment padding
 // =============================================================================
 
 /// Ring buffer size: 1 million slots (256MB total RAM)
@@ -59,8 +59,7 @@ pub mod slot_flags {
 ///   [1..8]   _pad1: [u8; 7]    - Alignment padding
 ///   [8..16]  ticket_id: u64    - Sequence number
 ///   [16..24] reserved_at_ns: u64 - Timestamp for reaper
-///   [24..120] event: ProcessEvent (96 bytes)
-///   [120..128] _pad2: [u8; 8]  - Final padding to 128
+///   [24..128] event: ProcessEvent (104 bytes)
 ///
 /// The slot uses a simple state machine:
 ///   EMPTY -> WRITING (atomic ticket reservation)
@@ -85,14 +84,9 @@ pub struct SequencedSlot {
     /// Used by the "Reaper" to detect stalled producers.
     pub reserved_at_ns: u64,
 
-    /// The actual event payload (96 bytes)
+    /// The actual event payload (104 bytes), filling the slot to exactly
+    /// 128 bytes after the 24-byte header.
     pub event: ProcessEvent,
-
-    /// Final padding to reach exactly 128 bytes.
-    /// Header: 1 + 7 + 8 + 8 = 24 bytes
-    /// ProcessEvent: 96 bytes
-    /// Total: 24 + 96 = 120 bytes, need 8 more
-    pub _pad2: [u8; 8],
 }
 
 // Ensure SequencedSlot is exactly 128 bytes (2 cache lines)
@@ -125,8 +119,8 @@ impl SequencedSlot {
                 data2: 0,
                 aux: 0,
                 aux2: 0,
+                cgroup_id: 0,
             },
-            _pad2: [0; 8],
         }
     }
 }
@@ -166,6 +160,10 @@ pub struct ProcessEvent {
     pub aux: u32,
     /// Extended auxiliary field for additional flags or identifiers.
     pub aux2: u32,
+    /// cgroup v2 ID (the cgroup directory's inode number) of the process;
+    /// 0 when the event describes a task other than the one it was recorded
+    /// in (OOM victims, scheduler events).
+    pub cgroup_id: u64,
 }
 
 pub const PERCENT_MILLI_UNKNOWN: u16 = u16::MAX;
@@ -268,6 +266,67 @@ pub struct TelemetryConfig {
     /// Used by LSM hooks to build the MandateKey uniquely per process
     /// across PID recycling.  Discovered via BTF at daemon start.
     pub task_start_boottime_offset: u32,
+
+    // File I/O attribution: `file->f_inode`, then the inode's mode, number
+    // and super block, whose `s_dev` is the device. Zero disables FileIo events.
+    pub file_inode_offset: u32,
+    pub inode_mode_offset: u32,
+    pub inode_ino_offset: u32,
+    pub inode_sb_offset: u32,
+    pub sb_dev_offset: u32,
+
+    // OOM kill attribution: `oom_control.chosen` (victim task) and
+    // `oom_control.memcg` (set for cgroup OOMs). Zero disables OomKill events.
+    pub oom_chosen_offset: u32,
+    pub oom_memcg_offset: u32,
+    /// Offset of `exit_code` in task_struct (the `wait(2)` status word).
+    /// Zero leaves the exit status of Exit events unknown.
+    pub task_exit_code_offset: u32,
+    /// Offset of `__state` (`state` before 5.14) in task_struct. Zero
+    /// disables OffCpu events.
+    pub task_state_offset: u32,
+    pub _reserved2: u32,
+
+    // Connection attribution: `sock_common` fields, which start every
+    // `struct sock`. Offsets can be 0, so `sock_offsets_known` is set when
+    // they were found; without it accepts produce no Connection events.
+    pub skc_family_offset: u32,
+    pub skc_dport_offset: u32,
+    pub skc_num_offset: u32,
+    pub skc_daddr_offset: u32,
+    pub skc_v6_daddr_offset: u32,
+    pub sock_offsets_known: u32,
+
+    // Credential changes: `task_struct.real_cred` (the credentials being
+    // replaced) and the effective IDs and capabilities of a `struct cred`.
+    // Zero disables Creds events.
+    pub task_real_cred_offset: u32,
+    pub cred_euid_offset: u32,
+    pub cred_egid_offset: u32,
+    pub cred_cap_effective_offset: u32,
+
+    // Block I/O through BTF tracepoints (`block_source::BTF`): sector and
+    // size from `bio.bi_iter` and `request.__sector`/`__data_len`, and the
+    // device from `bd_dev` of `bio.bi_bdev` or `request.part`.
+    pub block_source: u32,
+    pub bio_bdev_offset: u32,
+    pub bio_sector_offset: u32,
+    pub bio_size_offset: u32,
+    pub bdev_dev_offset: u32,
+    pub rq_part_offset: u32,
+    pub rq_sector_offset: u32,
+    pub rq_data_len_offset: u32,
+
+    // File access auditing: `file.f_path.dentry`, `file.f_mode`, the
+    // dentry's parent, name string (`d_name.name`) and inode, and which
+    // `vfs_unlink` argument is the dentry (1 before 5.12, 2 since the
+    // mount idmap argument was added). Zero disables FileAccess events.
+    pub file_dentry_offset: u32,
+    pub file_mode_offset: u32,
+    pub dentry_parent_offset: u32,
+    pub dentry_name_offset: u32,
+    pub dentry_inode_offset: u32,
+    pub unlink_dentry_arg: u32,
 }
 
 impl TelemetryConfig {
@@ -292,6 +351,40 @@ impl TelemetryConfig {
             total_memory_bytes: 0,
             rss_source: 0,
             task_start_boottime_offset: 0,
+            file_inode_offset: 0,
+            inode_mode_offset: 0,
+            inode_ino_offset: 0,
+            inode_sb_offset: 0,
+            sb_dev_offset: 0,
+            oom_chosen_offset: 0,
+            oom_memcg_offset: 0,
+            task_exit_code_offset: 0,
+            task_state_offset: 0,
+            _reserved2: 0,
+            skc_family_offset: 0,
+            skc_dport_offset: 0,
+            skc_num_offset: 0,
+            skc_daddr_offset: 0,
+            skc_v6_daddr_offset: 0,
+            sock_offsets_known: 0,
+            task_real_cred_offset: 0,
+            cred_euid_offset: 0,
+            cred_egid_offset: 0,
+            cred_cap_effective_offset: 0,
+            block_source: 0,
+            bio_bdev_offset: 0,
+            bio_sector_offset: 0,
+            bio_size_offset: 0,
+            bdev_dev_offset: 0,
+            rq_part_offset: 0,
+            rq_sector_offset: 0,
+            rq_data_len_offset: 0,
+            file_dentry_offset: 0,
+            file_mode_offset: 0,
+            dentry_parent_offset: 0,
+            dentry_name_offset: 0,
+            dentry_inode_offset: 0,
+            unlink_dentry_arg: 0,
         }
     }
 }
@@ -302,6 +395,50 @@ pub mod rss_source {
     pub const DISABLED: u32 = 2;
 }
 
+/// How the block probes read their fields: from the classic tracepoint
+/// records, or from the kernel structs behind the BTF tracepoints.
+pub mod block_source {
+    pub const TRACEPOINT: u32 = 0;
+    pub const BTF: u32 = 1;
+}
+
+/// Values of the `EVENT_TRANSPORT` global: where the probes send events when
+/// the sequencer is off.
+pub mod event_transport {
+    /// Per-CPU `EVENTS` perf buffers (kernels without BPF ring buffer).
+    pub const PERF: u32 = 0;
+    /// The shared `EVENTS_RB` ring buffer.
+    pub const RING_BUF: u32 = 1;
+}
+
+/// Values of the `FILTER_PIDS` and `FILTER_CGROUPS` maps, keyed by TGID and
+/// cgroup v2 ID.
+pub mod filter_action {
+    /// Drop events of the process or cgroup subtree.
+    pub const EXCLUDE: u8 = 1;
+    /// With `filter_mode::INCLUDE_ONLY`, emit events of the process or
+    /// cgroup subtree.
+    pub const INCLUDE: u8 = 2;
+}
+
+/// Flags in element 0 of the `FILTER_MODE` map.
+pub mod filter_mode {
+    /// Filter disabled; the maps aren't consulted.
+    pub const OFF: u32 = 0;
+    /// Drop events of `filter_action::EXCLUDE` entries.
+    pub const EXCLUDE: u32 = 1;
+    /// Drop events of everything without a `filter_action::INCLUDE` entry.
+    pub const INCLUDE_ONLY: u32 = 2;
+}
+
+/// Cgroup ancestors checked against `FILTER_CGROUPS`, from the root down.
+pub const FILTER_MAX_CGROUP_DEPTH: u32 = 12;
+
+/// Entries of the per-CPU `SCHED_LATENCY_HIST` map. Entry `i` counts
+/// runqueue delays of `2^i` to `2^(i+1)` microseconds; entry 0 also holds
+/// everything under a microsecond and the last one everything longer.
+pub const SCHED_LATENCY_BUCKETS: u32 = 32;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 #[cfg_attr(feature = "user", derive(serde::Serialize, serde::Deserialize))]
@@ -311,6 +448,19 @@ pub struct RssTraceEvent {
     pub delta_pages: i64,
 }
 
+/// A sampled allocation not freed yet, in the `ALLOCS` map of the
+/// `malloc_trace` object (keyed by address).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct MallocAlloc {
+    /// Bytes requested.
+    pub size: u64,
+    /// `bpf_ktime_get_ns` when malloc returned.
+    pub ts_ns: u64,
+    /// Id of the user stack in the `STACKS` map, negative if none was taken.
+    pub stack_id: i64,
+}
+
 #[cfg(feature = "user")]
 #[allow(dead_code)]
 fn assert_telemetry_config_traits() {
@@ -322,17 +472,276 @@ fn assert_telemetry_config_traits() {
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum EventType {
     Exec = 0,
+    /// `pid` is the child, `ppid` the forking process and `data`/`data2`
+    /// the TGIDs of its older ancestors (see [`fork_ancestors`]).
     Fork = 1,
+    /// `exit_time_ns` (or `data` on the sequencer path) is the exit time;
+    /// `aux` is the `wait(2)` status word when `aux2` has
+    /// [`EXIT_STATUS_KNOWN`] set.
     Exit = 2,
     Net = 3,
     FileIo = 4,
+    /// One process's calls of one syscall on one CPU over a counting window:
+    /// `data` is the number of calls, `data2` the window length in
+    /// nanoseconds and `aux` the syscall number.
     Syscall = 5,
     BlockIo = 6,
     PageFault = 7,
     MandateAllow = 8,
     MandateDeny = 9,
+    /// The OOM killer chose a victim: `pid`/`ppid`/`comm` are the victim's,
+    /// `data` its RSS in bytes, `data2` the PID whose allocation triggered
+    /// the kill and `aux` 1 for a memory cgroup limit, 0 for system-wide.
+    OomKill = 10,
+    /// A task waited on a runqueue for longer than the kernel-side threshold
+    /// before it ran: `pid`/`ppid`/`comm` are the delayed task's, `data` the
+    /// delay in nanoseconds, `data2` its thread ID and `aux` the CPU it ran on.
+    SchedLatency = 11,
+    /// Periodic off-CPU summary of a process, sent when one of its threads
+    /// is switched in after the summary interval: `data` is the time its
+    /// threads spent blocked in uninterruptible sleep (D, mostly I/O) and
+    /// `data2` in interruptible sleep (S), both in nanoseconds, and `aux`
+    /// the length of the interval in milliseconds.
+    OffCpu = 12,
+    /// A TCP connection of the current process: outbound when it calls
+    /// `connect` (the handshake may still fail), inbound when `accept`
+    /// returns one. `data`/`data2` hold the remote address (see
+    /// [`connection_addr_fields`]), `aux` the remote port and `aux2` a
+    /// `conn_direction` in the low 16 bits and, for inbound connections, the
+    /// local port in the high 16.
+    Connection = 13,
+    /// A task stalled in direct memory reclaim for at least the kernel-side
+    /// threshold: `data` is how long in nanoseconds, `data2` the number of
+    /// pages it reclaimed, `aux` the order of the allocation that triggered
+    /// it and `aux2` the thread ID.
+    DirectReclaim = 14,
+    /// A signal from the configured set was generated for a task:
+    /// `pid`/`ppid`/`comm` are the sender's (the task running when it was
+    /// generated), `data` the target's task ID (its PID for signals sent
+    /// to a whole process), `data2` the `si_code`, `aux` the signal number
+    /// and `aux2` the `signal_result` of the attempt.
+    Signal = 15,
+    /// The current process changed its effective UID, GID or capabilities
+    /// (`commit_creds`, e.g. `setuid` or exec of a setuid binary): `uid`
+    /// and `gid` are the effective IDs before the change, `aux` and `aux2`
+    /// the effective UID and GID after it, `data` the new effective
+    /// capability set and `data2` the old one.
+    Creds = 16,
+    /// The current process grew its anonymous mappings or heap (`mmap`,
+    /// `brk`) by at least the kernel-side threshold since its previous
+    /// MemGrowth event: `data` is the growth in bytes, `data2` the RSS in
+    /// bytes when it was sent (0 if unknown) and `aux` the milliseconds the
+    /// growth took.
+    MemGrowth = 17,
+    /// A watched file, or a file below a watched directory, was opened or
+    /// unlinked: `data` is its inode number, `data2` the key of its path in
+    /// the `FILE_PATHS` map, `aux` the [`FileAccessOp`] and `aux2` the ID
+    /// the watched path was given in `WATCHED_PATHS`.
+    FileAccess = 18,
+    /// A kernel module was loaded, or the current process asked the kernel
+    /// to load a BPF program (`bpf(BPF_PROG_LOAD)`, seen before the
+    /// verifier, so the load may still fail): `aux` is the
+    /// [`KernelLoadKind`], `data`/`data2` the start of the module or
+    /// program name (see [`kernel_load_name`]) and `aux2` the module's
+    /// taint flags or the BPF program type.
+    KernelLoad = 19,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "user", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileAccessOp {
+    Open = 0,
+    /// Opened with write access.
+    OpenWrite = 1,
+    Unlink = 2,
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "user", derive(serde::Serialize, serde::Deserialize))]
+pub enum KernelLoadKind {
+    Module = 0,
+    BpfProgram = 1,
+}
+
+/// Bytes of the name kept in a KernelLoad event. BPF program names are
+/// at most 15 bytes; longer module names are truncated.
+pub const KERNEL_LOAD_NAME_LEN: usize = 16;
+
+/// `(data, data2)` of a KernelLoad event: the NUL-padded name, eight bytes
+/// per field in little-endian order.
+pub fn kernel_load_name_fields(name: [u8; KERNEL_LOAD_NAME_LEN]) -> (u64, u64) {
+    let mut fields = [0u64; 2];
+    let mut i = 0;
+    while i < KERNEL_LOAD_NAME_LEN {
+        fields[i / 8] |= (name[i] as u64) << ((i % 8) * 8);
+        i += 1;
+    }
+    (fields[0], fields[1])
+}
+
+/// Inverse of [`kernel_load_name_fields`].
+pub fn kernel_load_name(data: u64, data2: u64) -> [u8; KERNEL_LOAD_NAME_LEN] {
+    let mut name = [0u8; KERNEL_LOAD_NAME_LEN];
+    name[..8].copy_from_slice(&data.to_le_bytes());
+    name[8..].copy_from_slice(&data2.to_le_bytes());
+    name
+}
+
+/// Path components recorded for a FileAccess event, from the file up to
+/// (not including) the watched path.
+pub const FILE_PATH_DEPTH: usize = 8;
+
+/// Bytes kept of each path component, including the terminating NUL.
+pub const FILE_PATH_NAME_LEN: usize = 64;
+
+/// Path of a FileAccess event below its watched path, in the `FILE_PATHS`
+/// map. `names` holds `depth` NUL-terminated components, the file's own
+/// name first; longer names are truncated. `depth` 0 means the event is
+/// for the watched path itself.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct FilePath {
+    pub depth: u32,
+    pub _pad: u32,
+    pub names: [[u8; FILE_PATH_NAME_LEN]; FILE_PATH_DEPTH],
+}
+
+/// Key of the `WATCHED_PATHS` map: the kernel device number (`s_dev`) and
+/// inode of a watched file or directory.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Pod, Zeroable)]
+pub struct WatchedPathKey {
+    pub dev: u32,
+    pub _pad: u32,
+    pub ino: u64,
+}
+
+/// `signal_generate` outcomes, as in the kernel's `enum trace_signal_result`.
+pub mod signal_result {
+    pub const DELIVERED: u32 = 0;
+    pub const IGNORED: u32 = 1;
+    pub const ALREADY_PENDING: u32 = 2;
+    pub const OVERFLOW_FAIL: u32 = 3;
+    pub const LOSE_INFO: u32 = 4;
 }
 
+/// Ancestors beyond the parent recorded in a Fork event.
+pub const FORK_ANCESTORS: usize = 4;
+
+/// `(data, data2)` of a Fork event: the TGIDs of the parent's parent and
+/// older ancestors, nearest first, two per field with the nearer one in the
+/// low 32 bits. The chain ends at the first 0.
+pub fn fork_ancestor_fields(ancestors: [u32; FORK_ANCESTORS]) -> (u64, u64) {
+    (
+        ancestors[0] as u64 | (ancestors[1] as u64) << 32,
+        ancestors[2] as u64 | (ancestors[3] as u64) << 32,
+    )
+}
+
+/// Inverse of [`fork_ancestor_fields`].
+pub fn fork_ancestors(data: u64, data2: u64) -> [u32; FORK_ANCESTORS] {
+    [
+        data as u32,
+        (data >> 32) as u32,
+        data2 as u32,
+        (data2 >> 32) as u32,
+    ]
+}
+
+/// Slots of the `THROTTLE_CONFIG` map, written by userspace: the minimum
+/// interval in nanoseconds between two events of a kind from one process.
+/// 0 sends every event.
+pub mod throttle_slot {
+    /// Faults in between are dropped.
+    pub const PAGE_FAULT: u32 = 0;
+    /// Bytes in between are summed per process, device and operation.
+    pub const BLOCK_IO: u32 = 1;
+    /// Bytes and calls in between are summed per process and operation.
+    pub const NET: u32 = 2;
+    pub const COUNT: u32 = 3;
+}
+
+/// Bytes and calls summed since they were last reported, for the socket,
+/// file and block I/O accumulators that report at most once per interval.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, Pod, Zeroable)]
+pub struct ByteAccum {
+    pub bytes: u64,
+    pub calls: u64,
+    pub last_emit_ns: u64,
+}
+
+impl ByteAccum {
+    /// Accumulator for a key whose first transfer was just reported.
+    pub const fn started(now_ns: u64) -> Self {
+        Self {
+            bytes: 0,
+            calls: 0,
+            last_emit_ns: now_ns,
+        }
+    }
+
+    /// Add one transfer. Returns the bytes and calls to report once
+    /// `interval_ns` has passed since the last report, `None` while they
+    /// are held back.
+    #[inline(always)]
+    pub fn add(&mut self, bytes: u64, now_ns: u64, interval_ns: u64) -> Option<(u64, u64)> {
+        self.bytes += bytes;
+        self.calls += 1;
+        if now_ns.saturating_sub(self.last_emit_ns) < interval_ns {
+            return None;
+        }
+        let flushed = (self.bytes, self.calls);
+        *self = Self::started(now_ns);
+        Some(flushed)
+    }
+
+    /// Bytes and calls held back since the last report, which the
+    /// process's exit has to report instead of a later transfer.
+    #[inline(always)]
+    pub fn held_back(&self) -> Option<(u64, u64)> {
+        (self.bytes > 0).then_some((self.bytes, self.calls))
+    }
+}
+
+/// Slots of the `LIFECYCLE_COUNTS` per-CPU array: processes forked and
+/// exec'd since the programs were loaded, counted whether or not their
+/// event makes it into the buffer.
+pub mod lifecycle_counter {
+    pub const FORK: u32 = 0;
+    pub const EXEC: u32 = 1;
+    pub const COUNT: u32 = 2;
+}
+
+pub mod conn_direction {
+    pub const OUTBOUND: u32 = 0;
+    pub const INBOUND: u32 = 1;
+}
+
+/// `(data, data2)` of a Connection event for a remote IPv6 address (IPv4
+/// addresses are mapped to `::ffff:a.b.c.d`): its first and last eight
+/// bytes, big-endian.
+pub fn connection_addr_fields(octets: [u8; 16]) -> (u64, u64) {
+    let mut hi = [0u8; 8];
+    let mut lo = [0u8; 8];
+    hi.copy_from_slice(&octets[..8]);
+    lo.copy_from_slice(&octets[8..]);
+    (u64::from_be_bytes(hi), u64::from_be_bytes(lo))
+}
+
+/// Inverse of [`connection_addr_fields`].
+pub fn connection_addr_octets(data: u64, data2: u64) -> [u8; 16] {
+    let mut octets = [0u8; 16];
+    octets[..8].copy_from_slice(&data.to_be_bytes());
+    octets[8..].copy_from_slice(&data2.to_be_bytes());
+    octets
+}
+
+/// `aux2` flag on Exit events: `aux` holds the exit status read from the task.
+pub const EXIT_STATUS_KNOWN: u32 = 1;
+
 // =============================================================================
 // LINNIX-CLAW: MANDATE DATA STRUCTURES
 // =============================================================================
@@ -436,12 +845,24 @@ pub const MANDATE_RECONCILE_INTERVAL_SECS: u64 = 5;
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct ProcessEventExt {
     pub base: ProcessEvent,
+    /// Command line, filled in userspace on exec (and carried to later
+    /// events of the same process) since the probes don't capture it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argv: Option<Vec<String>>,
+    /// Path of a FileAccess event, joined in userspace from the watched
+    /// path and the components the probe recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
 }
 
 #[cfg(all(feature = "user", not(target_os = "none")))]
 impl ProcessEventExt {
     pub fn new(base: ProcessEvent) -> Self {
-        Self { base }
+        Self {
+            base,
+            argv: None,
+            path: None,
+        }
     }
 
     pub fn exit_time(&self) -> Option<u64> {
@@ -661,6 +1082,58 @@ mod tests {
         assert!(!flags.contains(PageFaultFlags::INSTRUCTION));
     }
 
+    /// Bytes and calls reported for `transfers` of `(now_ns, bytes)` by
+    /// one accumulator, the way the probes drive it, then at exit.
+    fn replay(transfers: &[(u64, u64)], interval_ns: u64) -> Vec<(u64, u64)> {
+        let mut reported = Vec::new();
+        let mut accum: Option<ByteAccum> = None;
+        for &(now, bytes) in transfers {
+            match &mut accum {
+                Some(accum) => reported.extend(accum.add(bytes, now, interval_ns)),
+                None => {
+                    accum = Some(ByteAccum::started(now));
+                    reported.push((bytes, 1));
+                }
+            }
+        }
+        reported.extend(accum.and_then(|accum| accum.held_back()));
+        reported
+    }
+
+    #[test]
+    fn byte_accum_reports_a_burst_by_exit() {
+        const SEC: u64 = 1_000_000_000;
+        // A short-lived process: everything after the first send falls
+        // inside one interval and only the exit reports it.
+        assert_eq!(
+            replay(&[(0, 100), (SEC / 10, 200), (SEC / 5, 300)], SEC),
+            [(100, 1), (500, 2)]
+        );
+        // A burst, a send after the interval, then idle until exit.
+        assert_eq!(
+            replay(
+                &[(0, 100), (SEC / 2, 200), (2 * SEC, 300), (2 * SEC + 1, 400)],
+                SEC
+            ),
+            [(100, 1), (500, 2), (400, 1)]
+        );
+        // Nothing held back, nothing more to report.
+        assert_eq!(
+            replay(&[(0, 100), (2 * SEC, 200)], SEC),
+            [(100, 1), (200, 1)]
+        );
+    }
+
+    #[test]
+    fn kernel_load_name_roundtrip() {
+        let mut name = [0u8; KERNEL_LOAD_NAME_LEN];
+        name[..9].copy_from_slice(b"nf_tables");
+        let (data, data2) = kernel_load_name_fields(name);
+        assert_eq!(data.to_le_bytes(), *b"nf_table");
+        assert_eq!(data2, b's' as u64);
+        assert_eq!(kernel_load_name(data, data2), name);
+    }
+
     #[cfg(feature = "user")]
     #[test]
     fn block_io_event_roundtrip() {
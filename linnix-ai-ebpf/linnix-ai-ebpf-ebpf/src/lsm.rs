@@ -22,7 +22,9 @@
 //   - BTF support (/sys/kernel/btf/vmlinux)
 
 use aya_ebpf::{
-    helpers::{bpf_get_current_task_btf, bpf_ktime_get_ns, bpf_probe_read},
+    helpers::{
+        bpf_get_current_cgroup_id, bpf_get_current_task_btf, bpf_ktime_get_ns, bpf_probe_read,
+    },
     macros::{lsm, map},
     maps::{Array, HashMap as BpfHashMap},
     programs::LsmContext,
@@ -164,6 +166,7 @@ fn push_mandate_receipt(
         data2: mandate_seq,
         aux: mode,
         aux2: 0,
+        cgroup_id: unsafe { bpf_get_current_cgroup_id() },
     };
     let _ = crate::program::submit_to_sequencer(&event);
 }
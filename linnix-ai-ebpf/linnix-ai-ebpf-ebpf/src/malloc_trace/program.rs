@@ -0,0 +1,98 @@
+//! Uprobes on glibc `malloc` and `free`, attached on demand to one process
+//! by cognitod's malloc sampler. Allocations of at least `MALLOC_MIN_BYTES`
+//! are recorded with their user stack until they are freed; whatever is left
+//! in `ALLOCS` when the session ends was allocated and not freed during it.
+
+use aya_ebpf::{
+    helpers::{bpf_get_current_pid_tgid, bpf_ktime_get_ns},
+    macros::{map, uprobe, uretprobe},
+    maps::{HashMap, StackTrace},
+    programs::{ProbeContext, RetProbeContext},
+};
+use linnix_ai_ebpf_common::MallocAlloc;
+
+/// `BPF_F_USER_STACK`: walk the user stack instead of the kernel one.
+const BPF_F_USER_STACK: u64 = 1 << 8;
+
+/// Smallest request recorded, set by userspace from
+/// `malloc_sampler.min_bytes`.
+#[no_mangle]
+static mut MALLOC_MIN_BYTES: u64 = 65_536;
+
+/// Requested size per thread between malloc entry and return.
+#[map(name = "MALLOC_SIZES")]
+static mut MALLOC_SIZES: HashMap<u64, u64> = HashMap::with_max_entries(16_384, 0);
+
+/// Recorded allocations not freed yet, by address.
+#[map(name = "ALLOCS")]
+static mut ALLOCS: HashMap<u64, MallocAlloc> = HashMap::with_max_entries(65_536, 0);
+
+#[map(name = "STACKS")]
+static mut STACKS: StackTrace = StackTrace::with_max_entries(16_384, 0);
+
+#[uprobe]
+pub fn malloc_enter(ctx: ProbeContext) -> u32 {
+    let size: u64 = match ctx.arg(0) {
+        Some(size) => size,
+        None => return 0,
+    };
+    if size < unsafe { core::ptr::read_volatile(&MALLOC_MIN_BYTES) } {
+        return 0;
+    }
+    let key = bpf_get_current_pid_tgid();
+    let sizes = unsafe { &raw const MALLOC_SIZES };
+    let _ = unsafe { (*sizes).insert(&key, &size, 0) };
+    0
+}
+
+#[uretprobe]
+pub fn malloc_exit(ctx: RetProbeContext) -> u32 {
+    let key = bpf_get_current_pid_tgid();
+    let sizes = unsafe { &raw const MALLOC_SIZES };
+    let size = match unsafe { (*sizes).get(&key) } {
+        Some(size) => *size,
+        None => return 0,
+    };
+    let _ = unsafe { (*sizes).remove(&key) };
+
+    let addr: u64 = match ctx.ret() {
+        Some(addr) => addr,
+        None => return 0,
+    };
+    if addr == 0 {
+        return 0;
+    }
+    let stacks = unsafe { &raw mut STACKS };
+    let stack_id = unsafe { (*stacks).get_stackid(&ctx, BPF_F_USER_STACK) }.unwrap_or(-1);
+    let alloc = MallocAlloc {
+        size,
+        ts_ns: unsafe { bpf_ktime_get_ns() },
+        stack_id,
+    };
+    let allocs = unsafe { &raw const ALLOCS };
+    let _ = unsafe { (*allocs).insert(&addr, &alloc, 0) };
+    0
+}
+
+#[uprobe]
+pub fn free_enter(ctx: ProbeContext) -> u32 {
+    let addr: u64 = match ctx.arg(0) {
+        Some(addr) => addr,
+        None => return 0,
+    };
+    if addr != 0 {
+        let allocs = unsafe { &raw const ALLOCS };
+        let _ = unsafe { (*allocs).remove(&addr) };
+    }
+    0
+}
+
+#[cfg(all(not(test), target_arch = "bpf"))]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+#[link_section = "license"]
+#[no_mangle]
+static LICENSE: [u8; 4] = *b"GPL\0";
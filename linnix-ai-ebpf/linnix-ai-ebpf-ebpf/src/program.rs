@@ -2,31 +2,173 @@ use core::cmp;
 
 use aya_ebpf::{
     helpers::{
-        bpf_get_current_task_btf, bpf_get_current_uid_gid, bpf_ktime_get_ns, bpf_probe_read,
+        bpf_get_current_ancestor_cgroup_id, bpf_get_current_cgroup_id, bpf_get_current_pid_tgid,
+        bpf_get_current_task_btf, bpf_get_current_uid_gid, bpf_get_smp_processor_id,
+        bpf_ktime_get_ns, bpf_probe_read, bpf_probe_read_kernel_str_bytes, bpf_probe_read_user,
     },
-    macros::{btf_tracepoint, kprobe, map, tracepoint},
-    maps::{perf::PerfEventArray, Array, HashMap, PerCpuArray},
-    programs::{BtfTracePointContext, ProbeContext, TracePointContext},
+    macros::{btf_tracepoint, kprobe, kretprobe, map, tracepoint},
+    maps::{
+        perf::PerfEventArray, ring_buf::RingBufEntry, Array, HashMap, LruHashMap, LruPerCpuHashMap,
+        PerCpuArray, RingBuf,
+    },
+    programs::{BtfTracePointContext, ProbeContext, RetProbeContext, TracePointContext},
     EbpfContext,
 };
 use aya_log_ebpf::info;
 use linnix_ai_ebpf_common::{
-    rss_source, slot_flags, BlockOp, EventType, PageFaultOrigin, ProcessEvent, SequencedSlot,
-    TelemetryConfig, PERCENT_MILLI_UNKNOWN, SEQUENCER_RING_MASK, SEQUENCER_RING_SIZE,
+    block_source, conn_direction, connection_addr_fields, event_transport, filter_action,
+    filter_mode, fork_ancestor_fields, kernel_load_name_fields, lifecycle_counter, rss_source,
+    slot_flags, throttle_slot, BlockOp, ByteAccum, EventType, FileAccessOp, FileOp, FilePath,
+    KernelLoadKind, NetOp, PageFaultOrigin, ProcessEvent, SequencedSlot, TelemetryConfig,
+    WatchedPathKey, EXIT_STATUS_KNOWN, FILE_PATH_DEPTH, FILTER_MAX_CGROUP_DEPTH, FORK_ANCESTORS,
+    KERNEL_LOAD_NAME_LEN, PERCENT_MILLI_UNKNOWN, SCHED_LATENCY_BUCKETS, SEQUENCER_RING_MASK,
+    SEQUENCER_RING_SIZE,
 };
 
 #[map(name = "EVENTS")]
 static mut EVENTS: PerfEventArray<ProcessEvent> = PerfEventArray::new(0);
 
+/// Shared event ring buffer (kernel 5.8+). One buffer for all CPUs instead of
+/// a perf buffer per CPU; userspace sizes it with `set_max_entries` and picks
+/// it over `EVENTS` through `EVENT_TRANSPORT`.
+#[map(name = "EVENTS_RB")]
+static mut EVENTS_RB: RingBuf = RingBuf::with_byte_size(4 * 1024 * 1024, 0);
+
+/// Events that found `EVENTS_RB` full. Perf buffers report their losses to
+/// the reader; the ring buffer doesn't, so they are counted here.
+#[map(name = "EVENTS_RB_DROPS")]
+static mut EVENTS_RB_DROPS: PerCpuArray<u64> = PerCpuArray::with_max_entries(1, 0);
+
+/// Forks and execs per `lifecycle_counter` slot, polled by userspace so
+/// rates stay exact when events are lost in a fork storm.
+#[map(name = "LIFECYCLE_COUNTS")]
+static mut LIFECYCLE_COUNTS: PerCpuArray<u64> =
+    PerCpuArray::with_max_entries(lifecycle_counter::COUNT, 0);
+
 #[map(name = "TASK_STATS")]
 static mut TASK_STATS: HashMap<u32, TaskStats> = HashMap::with_max_entries(65_536, 0);
 
+/// Scratch space for building events bound for the perf buffer or the
+/// sequencer; ring buffer events are built in their reserved slot instead.
 #[map(name = "EVENT_BUFFER")]
 static mut EVENT_BUFFER: PerCpuArray<ProcessEvent> = PerCpuArray::with_max_entries(1, 0);
 
 #[map(name = "PAGE_FAULT_THROTTLE")]
 static mut PAGE_FAULT_THROTTLE: HashMap<u32, u64> = HashMap::with_max_entries(65_536, 0);
 
+/// Per-process emission intervals indexed by `throttle_slot`, written by
+/// userspace before the probes are attached and changed at runtime.
+#[map(name = "THROTTLE_CONFIG")]
+static mut THROTTLE_CONFIG: Array<u64> = Array::with_max_entries(throttle_slot::COUNT, 0);
+
+/// Bytes transferred per (PID, NetOp) since the last Net event, keyed by
+/// `pid << 32 | op`. LRU like the file and block accumulators, so all three
+/// go through [`accumulate`].
+#[map(name = "NET_ACCUM")]
+static mut NET_ACCUM: LruHashMap<u64, ByteAccum> = LruHashMap::with_max_entries(65_536, 0);
+
+/// `struct file *` passed to `vfs_read`/`vfs_write`, keyed by pid_tgid, for
+/// the return probe.
+#[map(name = "VFS_ARGS")]
+static mut VFS_ARGS: HashMap<u64, u64> = HashMap::with_max_entries(16_384, 0);
+
+/// Bytes read or written per (PID, file, direction) since the last FileIo
+/// event. LRU because files, unlike directions, can't be enumerated at exit;
+/// PENDING_FILES finds the ones holding bytes back.
+#[map(name = "FILE_ACCUM")]
+static mut FILE_ACCUM: LruHashMap<FileKey, ByteAccum> = LruHashMap::with_max_entries(65_536, 0);
+
+/// FILE_ACCUM keys holding bytes back, keyed by `pid << 32 | slot` (see
+/// [`pending_key`]), for the exit flush.
+#[map(name = "PENDING_FILES")]
+static mut PENDING_FILES: LruHashMap<u64, FileKey> = LruHashMap::with_max_entries(65_536, 0);
+
+/// Bytes per (PID, device, BlockOp) since the last BlockIo event.
+#[map(name = "BLOCK_ACCUM")]
+static mut BLOCK_ACCUM: LruHashMap<BlockKey, ByteAccum> = LruHashMap::with_max_entries(16_384, 0);
+
+/// BLOCK_ACCUM keys holding bytes back, like PENDING_FILES.
+#[map(name = "PENDING_BLOCKS")]
+static mut PENDING_BLOCKS: LruHashMap<u64, BlockKey> = LruHashMap::with_max_entries(16_384, 0);
+
+/// Kernel-side event filter, filled by userspace from `[runtime]`: TGIDs
+/// and cgroup v2 IDs mapped to a `filter_action`. Only consulted when
+/// `FILTER_MODE` is not `filter_mode::OFF`.
+#[map(name = "FILTER_PIDS")]
+static mut FILTER_PIDS: HashMap<u32, u8> = HashMap::with_max_entries(1024, 0);
+
+#[map(name = "FILTER_CGROUPS")]
+static mut FILTER_CGROUPS: HashMap<u64, u8> = HashMap::with_max_entries(1024, 0);
+
+/// `filter_mode` flags, written after both filter maps.
+#[map(name = "FILTER_MODE")]
+static mut FILTER_MODE: Array<u32> = Array::with_max_entries(1, 0);
+
+/// When each thread became runnable, keyed by TID, until it is switched
+/// in. LRU because a thread can exit while still queued.
+#[map(name = "SCHED_QUEUED_AT")]
+static mut SCHED_QUEUED_AT: LruHashMap<u32, u64> = LruHashMap::with_max_entries(65_536, 0);
+
+/// Log2 histogram of every runqueue delay in microseconds; see
+/// `SCHED_LATENCY_BUCKETS`. Read by userspace for the p95.
+#[map(name = "SCHED_LATENCY_HIST")]
+static mut SCHED_LATENCY_HIST: PerCpuArray<u64> =
+    PerCpuArray::with_max_entries(SCHED_LATENCY_BUCKETS, 0);
+
+/// Time of the last SchedLatency event per TGID.
+#[map(name = "SCHED_LATENCY_THROTTLE")]
+static mut SCHED_LATENCY_THROTTLE: LruHashMap<u32, u64> = LruHashMap::with_max_entries(16_384, 0);
+
+/// Syscalls per TGID and syscall number (see `syscall_key`) in the current
+/// window. Per CPU so the hot path needs no atomics; LRU so exited
+/// processes age out.
+#[map(name = "SYSCALL_ACCUM")]
+static mut SYSCALL_ACCUM: LruPerCpuHashMap<u64, SyscallAccum> =
+    LruPerCpuHashMap::with_max_entries(16_384, 0);
+
+/// Last Connection event per process, direction, port and remote address.
+#[map(name = "CONN_SEEN")]
+static mut CONN_SEEN: LruHashMap<ConnKey, u64> = LruHashMap::with_max_entries(16_384, 0);
+
+/// When each thread went to sleep, keyed by TID, until it is woken.
+#[map(name = "OFFCPU_SINCE")]
+static mut OFFCPU_SINCE: LruHashMap<u32, OffCpuStart> = LruHashMap::with_max_entries(65_536, 0);
+
+/// Off-CPU time per TGID since its last OffCpu event.
+#[map(name = "OFFCPU_ACCUM")]
+static mut OFFCPU_ACCUM: LruHashMap<u32, OffCpuAccum> = LruHashMap::with_max_entries(16_384, 0);
+
+/// When each thread entered direct reclaim, keyed by TID, until it leaves.
+#[map(name = "RECLAIM_START")]
+static mut RECLAIM_START: LruHashMap<u32, ReclaimStart> = LruHashMap::with_max_entries(16_384, 0);
+
+/// Anonymous mmap and brk growth per process not yet reported.
+#[map(name = "MEM_GROWTH")]
+static mut MEM_GROWTH: LruHashMap<u32, MemGrowthAccum> = LruHashMap::with_max_entries(16_384, 0);
+
+/// Last program break returned by `brk` per process.
+#[map(name = "BRK_LAST")]
+static mut BRK_LAST: LruHashMap<u32, u64> = LruHashMap::with_max_entries(16_384, 0);
+
+/// Watched files and directories, filled by userspace from
+/// `probes.watched_paths`; the value is the ID reported in `aux2`.
+#[map(name = "WATCHED_PATHS")]
+static mut WATCHED_PATHS: HashMap<WatchedPathKey, u32> = HashMap::with_max_entries(256, 0);
+
+/// Paths of FileAccess events, keyed by the event's `data2`, until
+/// userspace reads them.
+#[map(name = "FILE_PATHS")]
+static mut FILE_PATHS: LruHashMap<u64, FilePath> = LruHashMap::with_max_entries(4_096, 0);
+
+/// A path is built here before it's copied to `FILE_PATHS` (it doesn't fit
+/// on the stack).
+#[map(name = "FILE_PATH_SCRATCH")]
+static mut FILE_PATH_SCRATCH: PerCpuArray<FilePath> = PerCpuArray::with_max_entries(1, 0);
+
+/// Per-CPU sequence numbers for `FILE_PATHS` keys.
+#[map(name = "FILE_PATH_SEQ")]
+static mut FILE_PATH_SEQ: PerCpuArray<u64> = PerCpuArray::with_max_entries(1, 0);
+
 // =============================================================================
 // SEQUENCED MPSC RING BUFFER - Kernel Producer Maps
 // =============================================================================
@@ -85,17 +227,100 @@ static mut SEQUENCER_ENABLED: Array<u32> = Array::with_max_entries(1, 0);
 #[no_mangle]
 static mut TELEMETRY_CONFIG: TelemetryConfig = TelemetryConfig::zeroed();
 
-const BYTES_PER_SECTOR: u64 = 512;
-const PAGE_FAULT_MIN_INTERVAL_NS: u64 = 50_000_000; // 50 ms window per PID
+/// One of `event_transport::*`, set by userspace at load time.
+#[no_mangle]
+static mut EVENT_TRANSPORT: u32 = event_transport::PERF;
+
+/// Runqueue delay from which SchedLatency events are emitted, set by
+/// userspace from `probes.sched_latency_threshold_us`.
+#[no_mangle]
+static mut SCHED_LATENCY_THRESHOLD_NS: u64 = u64::MAX;
+
+/// Syscall counting window, set by userspace from
+/// `probes.syscall_interval_ms`. Zero disables Syscall events.
+#[no_mangle]
+static mut SYSCALL_INTERVAL_NS: u64 = 0;
+
+/// Calls of one syscall a process must make on a CPU within a window for
+/// the window to be reported, from `probes.syscall_min_calls`.
+#[no_mangle]
+static mut SYSCALL_MIN_CALLS: u64 = 0;
+
+/// Interval of the OffCpu summaries, set by userspace from
+/// `probes.offcpu_interval_ms`. Zero disables off-CPU accounting.
+#[no_mangle]
+static mut OFFCPU_INTERVAL_NS: u64 = 0;
+
+/// Direct reclaim stall from which DirectReclaim events are emitted, set by
+/// userspace from `probes.direct_reclaim_min_us`.
+#[no_mangle]
+static mut DIRECT_RECLAIM_MIN_NS: u64 = u64::MAX;
+
+/// Signals reported as Signal events, bit N for signal N, set by userspace
+/// from `probes.signals`.
+#[no_mangle]
+static mut SIGNAL_MASK: u64 = 0;
 
-const BLOCK_BIO_DEV_OFFSET: usize = 0;
-const BLOCK_BIO_SECTOR_OFFSET: usize = 8;
-const BLOCK_BIO_NR_SECTOR_OFFSET: usize = 16;
+/// Growth from which a MemGrowth event is emitted, set by userspace from
+/// `probes.mem_growth_mb`.
+#[no_mangle]
+static mut MEM_GROWTH_MIN_BYTES: u64 = u64::MAX;
 
-const BLOCK_RQ_DEV_OFFSET: usize = 0;
-const BLOCK_RQ_SECTOR_OFFSET: usize = 8;
-const BLOCK_RQ_NR_SECTOR_OFFSET: usize = 16;
-const BLOCK_RQ_ISSUE_BYTES_OFFSET: usize = 20;
+const BYTES_PER_SECTOR: u64 = 512;
+/// `f_mode` bit of files opened for writing.
+const FMODE_WRITE: u32 = 0x2;
+const FILE_FLUSH_INTERVAL_NS: u64 = 100_000_000; // 100 ms per PID, file and direction
+const SCHED_LATENCY_MIN_INTERVAL_NS: u64 = 100_000_000; // 100 ms per PID
+const SYSCALL_FLUSH_CALLS: u64 = 1_000_000; // ends a window early during storms
+const CONN_REPEAT_INTERVAL_NS: u64 = 1_000_000_000; // 1 s per connection key
+const AF_INET: u16 = 2;
+const AF_INET6: u16 = 10;
+const S_IFMT: u16 = 0o170000;
+// task_struct state bits (include/linux/sched.h).
+const TASK_INTERRUPTIBLE: u32 = 0x1;
+const TASK_UNINTERRUPTIBLE: u32 = 0x2;
+const TASK_NOLOAD: u32 = 0x400;
+const S_IFREG: u16 = 0o100000;
+
+// block/block_bio_queue, block_rq_issue and block_rq_complete: `dev_t dev`,
+// `sector_t sector` and `unsigned int nr_sector` after the common fields;
+// block_rq_issue follows with `unsigned int bytes`. Only used when the
+// kernel has no BTF for the struct reads of the btf_tracepoint programs.
+const BLOCK_TP_DEV_OFFSET: usize = 8;
+const BLOCK_TP_SECTOR_OFFSET: usize = 16;
+const BLOCK_TP_NR_SECTOR_OFFSET: usize = 24;
+const BLOCK_TP_ISSUE_BYTES_OFFSET: usize = 28;
+
+// raw_syscalls/sys_enter: `long id` after the common fields.
+const SYS_ENTER_ID_OFFSET: usize = 8;
+// vmscan/mm_vmscan_direct_reclaim_begin: `int order`; _end: `unsigned long
+// nr_reclaimed`, both right after the common fields.
+const RECLAIM_BEGIN_ORDER_OFFSET: usize = 8;
+const RECLAIM_END_NR_RECLAIMED_OFFSET: usize = 8;
+// signal/signal_generate: int sig, int errno, int code, char comm[16],
+// pid_t pid, int group, int result.
+const SIGNAL_SIG_OFFSET: usize = 8;
+// syscalls/sys_enter_mmap: `int __syscall_nr`, then addr, len, prot, flags,
+// fd and off as 8-byte fields; syscalls/sys_exit_brk: `long ret`.
+const MMAP_LEN_OFFSET: usize = 24;
+const MMAP_FLAGS_OFFSET: usize = 40;
+const SYS_EXIT_RET_OFFSET: usize = 16;
+const MAP_ANONYMOUS: u64 = 0x20;
+// module/module_load: `unsigned int taints`, then the `__data_loc char[]
+// name` word (offset from the start of the record in the low 16 bits).
+const MODULE_LOAD_TAINTS_OFFSET: usize = 8;
+const MODULE_LOAD_NAME_LOC_OFFSET: usize = 12;
+// syscalls/sys_enter_bpf: `int __syscall_nr`, then cmd, uattr and size as
+// 8-byte fields. For BPF_PROG_LOAD, `union bpf_attr` starts with the
+// `__u32 prog_type` and has `char prog_name[16]` at 48 (kernel 4.15+).
+const BPF_CMD_OFFSET: usize = 16;
+const BPF_UATTR_OFFSET: usize = 24;
+const BPF_SIZE_OFFSET: usize = 32;
+const BPF_PROG_LOAD: u64 = 5;
+const BPF_ATTR_PROG_NAME_OFFSET: usize = 48;
+const SIGNAL_CODE_OFFSET: usize = 16;
+const SIGNAL_PID_OFFSET: usize = 36;
+const SIGNAL_RESULT_OFFSET: usize = 44;
 const DEVICE_MAJOR_BITS: u32 = 12;
 const DEVICE_MINOR_BITS: u32 = 20;
 const DEVICE_MAJOR_MASK: u64 = (1u64 << DEVICE_MAJOR_BITS) - 1;
@@ -131,6 +356,14 @@ unsafe fn read_task_pid(task: *const TaskStruct) -> u32 {
     bpf_probe_read(tgid_ptr).unwrap_or(0) as u32
 }
 
+/// Read pid (thread ID) from task_struct using dynamic offset from config
+#[inline(always)]
+unsafe fn read_task_tid(task: *const TaskStruct) -> u32 {
+    let cfg = load_config();
+    let pid_ptr = (task as *const u8).add(cfg.task_pid_offset as usize) as *const i32;
+    bpf_probe_read(pid_ptr).unwrap_or(0) as u32
+}
+
 /// Read comm field from task_struct using dynamic offset from config
 #[inline(always)]
 unsafe fn read_task_comm(task: *const TaskStruct) -> [u8; 16] {
@@ -139,6 +372,54 @@ unsafe fn read_task_comm(task: *const TaskStruct) -> [u8; 16] {
     bpf_probe_read(comm_ptr).unwrap_or([0u8; 16])
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ConnKey {
+    pid: u32,
+    /// Remote port in the high 16 bits, `conn_direction` in the low 16.
+    port_direction: u32,
+    addr_hi: u64,
+    addr_lo: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct SyscallAccum {
+    calls: u64,
+    since_ns: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct OffCpuStart {
+    since_ns: u64,
+    /// 1 for uninterruptible sleep.
+    blocked: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct OffCpuAccum {
+    blocked_ns: u64,
+    sleep_ns: u64,
+    since_ns: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct MemGrowthAccum {
+    bytes: u64,
+    since_ns: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct ReclaimStart {
+    since_ns: u64,
+    order: u32,
+    _pad: u32,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 struct TaskStats {
@@ -146,8 +427,28 @@ struct TaskStats {
     last_timestamp_ns: u64,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct BlockKey {
+    pid: u32,
+    dev: u32,
+    op: u32,
+    _pad: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct FileKey {
+    ino: u64,
+    dev: u32,
+    pid: u32,
+    op: u32,
+    _pad: u32,
+}
+
 #[inline(always)]
-fn encode_block_dev(dev: u64) -> u32 {
+fn encode_block_dev(dev: u32) -> u32 {
+    let dev = dev as u64;
     let major = (dev >> DEVICE_MINOR_BITS) & DEVICE_MAJOR_MASK;
     let minor = dev & DEVICE_MINOR_MASK;
     ((major as u32) << DEVICE_MINOR_BITS) | (minor as u32)
@@ -158,12 +459,103 @@ fn block_bytes_from_sectors(sectors: u32) -> u64 {
     (sectors as u64) * BYTES_PER_SECTOR
 }
 
+/// `THROTTLE_CONFIG` interval for a `throttle_slot`.
+#[inline(always)]
+fn throttle_interval(slot: u32) -> u64 {
+    unsafe { THROTTLE_CONFIG.get(slot).copied().unwrap_or(0) }
+}
+
+/// Add `bytes` to `key`'s running total in `map` and return the bytes and
+/// calls to report, at most once per `interval`, so a busy socket, file or
+/// device costs one event per interval rather than one per call. The first
+/// transfer is reported immediately.
+#[inline(always)]
+fn accumulate<K>(
+    map: &LruHashMap<K, ByteAccum>,
+    key: &K,
+    bytes: u64,
+    interval: u64,
+    now: u64,
+) -> Option<(u64, u64)> {
+    match map.get_ptr_mut(key) {
+        Some(ptr) => unsafe { &mut *ptr }.add(bytes, now, interval),
+        None => {
+            let _ = map.insert(key, &ByteAccum::started(now), 0);
+            Some((bytes, 1))
+        }
+    }
+}
+
+/// [`accumulate`] for a map that can't be enumerated by PID: a key that
+/// starts holding bytes back is also recorded at `index` in `pending`, for
+/// [`take_pending`] at exit.
+#[inline(always)]
+fn accumulate_pending<K: Copy>(
+    map: &LruHashMap<K, ByteAccum>,
+    key: &K,
+    bytes: u64,
+    interval: u64,
+    now: u64,
+    pending: &LruHashMap<u64, K>,
+    index: u64,
+) -> Option<(u64, u64)> {
+    let report = accumulate(map, key, bytes, interval, now);
+    if report.is_none() && unsafe { map.get(key) }.is_some_and(|accum| accum.calls == 1) {
+        let _ = pending.insert(&index, key, 0);
+    }
+    report
+}
+
+/// Slots per process in the pending-key maps. Keys landing in the same slot
+/// replace each other, so a process exiting with more keys holding bytes
+/// back than this can still lose some.
+const PENDING_SLOTS: u32 = 16;
+
+/// `pending` index of a key of `pid` that hashes to `hash`.
+#[inline(always)]
+fn pending_key(pid: u32, hash: u32) -> u64 {
+    ((pid as u64) << 32) | (hash & (PENDING_SLOTS - 1)) as u64
+}
+
+/// Take the bytes and calls held back by every key recorded for `pid` in
+/// `pending`, handing each to `emit`.
+#[inline(always)]
+fn take_pending<K: Copy>(
+    map: &LruHashMap<K, ByteAccum>,
+    pending: &LruHashMap<u64, K>,
+    pid: u32,
+    mut emit: impl FnMut(&K, u64, u64),
+) {
+    for slot in 0..PENDING_SLOTS {
+        let index = pending_key(pid, slot);
+        let Some(key) = unsafe { pending.get(&index) }.copied() else {
+            continue;
+        };
+        let _ = pending.remove(&index);
+        if let Some((total, calls)) = take_held_back(map, &key) {
+            emit(&key, total, calls);
+        }
+    }
+}
+
+/// Remove `key` from `map`, returning the bytes and calls it held back.
+#[inline(always)]
+fn take_held_back<K>(map: &LruHashMap<K, ByteAccum>, key: &K) -> Option<(u64, u64)> {
+    let held = unsafe { map.get(key) }.and_then(ByteAccum::held_back);
+    let _ = map.remove(key);
+    held
+}
+
 #[inline(always)]
 fn throttle_page_fault(pid: u32, now: u64) -> bool {
+    let interval = throttle_interval(throttle_slot::PAGE_FAULT);
+    if interval == 0 {
+        return true;
+    }
     let state = unsafe { &PAGE_FAULT_THROTTLE };
     if let Some(ptr) = state.get_ptr_mut(&pid) {
         let last = unsafe { &mut *ptr };
-        if now.saturating_sub(*last) < PAGE_FAULT_MIN_INTERVAL_NS {
+        if now.saturating_sub(*last) < interval {
             return false;
         }
         *last = now;
@@ -182,33 +574,43 @@ fn tp_read_u32(ctx: &TracePointContext, offset: usize) -> Option<u32> {
     unsafe { ctx.read_at::<u32>(offset).ok() }
 }
 
-fn emit_block_event_common(
-    ctx: &TracePointContext,
+fn emit_block_event_common<C: EbpfContext>(
+    ctx: &C,
     now: u64,
     op: BlockOp,
-    dev: u64,
+    dev: u32,
     sector: u64,
-    sectors: u32,
-    bytes_override: Option<u32>,
+    bytes: u64,
 ) -> u32 {
-    if sectors == 0 {
+    if bytes == 0 {
         return 0;
     }
 
-    let bytes = match bytes_override {
-        Some(value) if value > 0 => value as u64,
-        _ => block_bytes_from_sectors(sectors),
+    let pid = ctx.pid();
+    if pid == 0 {
+        return 0;
+    }
+
+    let dev = encode_block_dev(dev);
+
+    // Sum the bytes per process, device and op, reporting at most once per
+    // BLOCK_IO throttle interval. The first request is reported immediately.
+    let interval = throttle_interval(throttle_slot::BLOCK_IO);
+    let key = BlockKey {
+        pid,
+        dev,
+        op: op as u32,
+        _pad: 0,
+    };
+    let accum = unsafe { &*(&raw const BLOCK_ACCUM) };
+    let pending = unsafe { &*(&raw const PENDING_BLOCKS) };
+    let index = pending_key(pid, dev ^ op as u32);
+    let Some((total, _)) = accumulate_pending(accum, &key, bytes, interval, now, pending, index)
+    else {
+        return 0;
     };
 
-    emit_activity_event(
-        ctx,
-        EventType::BlockIo,
-        now,
-        bytes,
-        sector,
-        op as u32,
-        encode_block_dev(dev),
-    )
+    emit_activity_event(ctx, EventType::BlockIo, now, total, sector, op as u32, dev)
 }
 
 pub(crate) fn load_config() -> TelemetryConfig {
@@ -232,6 +634,18 @@ fn read_ptr(base: *const u8, offset: u32) -> Option<*const u8> {
     }
 }
 
+/// `(aux, aux2)` of an Exit event: the task's `wait(2)` status word and
+/// [`EXIT_STATUS_KNOWN`], or zeros when the offset is unknown.
+fn exit_status(task: *const u8, config: &TelemetryConfig) -> (u32, u32) {
+    if task.is_null() || config.task_exit_code_offset == 0 {
+        return (0, 0);
+    }
+    match read_field::<i32>(task, config.task_exit_code_offset) {
+        Some(code) => (code as u32, EXIT_STATUS_KNOWN),
+        None => (0, 0),
+    }
+}
+
 fn parent_tgid(task: *const u8, config: &TelemetryConfig) -> Option<u32> {
     if config.task_real_parent_offset == 0 || config.task_tgid_offset == 0 {
         return None;
@@ -245,6 +659,31 @@ fn parent_tgid(task: *const u8, config: &TelemetryConfig) -> Option<u32> {
     }
 }
 
+/// `(data, data2)` of a Fork event: the TGIDs of `parent`'s ancestors,
+/// walked through `real_parent` while the fork is still in progress, so
+/// userspace knows the chain even if the child exits before the event is
+/// read.
+#[inline(always)]
+fn fork_ancestry(parent: *const u8, config: &TelemetryConfig) -> (u64, u64) {
+    if config.task_real_parent_offset == 0 || config.task_tgid_offset == 0 {
+        return (0, 0);
+    }
+    let mut ancestors = [0u32; FORK_ANCESTORS];
+    let mut task = parent;
+    for slot in ancestors.iter_mut() {
+        let Some(next) = read_ptr(task, config.task_real_parent_offset) else {
+            break;
+        };
+        // init's real_parent is the idle task, TGID 0.
+        match read_field::<i32>(next, config.task_tgid_offset) {
+            Some(tgid) if tgid > 0 => *slot = tgid as u32,
+            _ => break,
+        }
+        task = next;
+    }
+    fork_ancestor_fields(ancestors)
+}
+
 #[cfg(target_arch = "bpf")]
 fn read_sum_exec_runtime(task: *const u8, config: &TelemetryConfig) -> Option<u64> {
     if config.task_se_offset == 0 || config.se_sum_exec_runtime_offset == 0 {
@@ -390,6 +829,92 @@ fn event_buffer_mut() -> Option<&'static mut ProcessEvent> {
     unsafe { EVENT_BUFFER.get_ptr_mut(0).map(|ptr| &mut *ptr) }
 }
 
+fn sequencer_enabled() -> bool {
+    unsafe { SEQUENCER_ENABLED.get(0).is_some_and(|val| *val != 0) }
+}
+
+/// Whether events of the current task, whose TGID is `pid`, are dropped by
+/// the filter. A cgroup entry covers its whole subtree.
+#[inline(always)]
+fn filtered(pid: u32) -> bool {
+    let mode = unsafe { FILTER_MODE.get(0).copied().unwrap_or(filter_mode::OFF) };
+    if mode == filter_mode::OFF {
+        return false;
+    }
+    let mut included = false;
+    match unsafe { FILTER_PIDS.get(&pid) } {
+        Some(&filter_action::EXCLUDE) => return true,
+        Some(&filter_action::INCLUDE) => included = true,
+        _ => {}
+    }
+    for level in 0..FILTER_MAX_CGROUP_DEPTH {
+        let cgroup = unsafe { bpf_get_current_ancestor_cgroup_id(level as i32) };
+        if cgroup == 0 {
+            break;
+        }
+        match unsafe { FILTER_CGROUPS.get(&cgroup) } {
+            Some(&filter_action::EXCLUDE) => return true,
+            Some(&filter_action::INCLUDE) => included = true,
+            _ => {}
+        }
+    }
+    mode & filter_mode::INCLUDE_ONLY != 0 && !included
+}
+
+fn ring_buf_enabled() -> bool {
+    unsafe { core::ptr::read_volatile(&EVENT_TRANSPORT) == event_transport::RING_BUF }
+}
+
+fn count_ring_buf_drop() {
+    if let Some(drops) = unsafe { EVENTS_RB_DROPS.get_ptr_mut(0) } {
+        unsafe { *drops += 1 };
+    }
+}
+
+fn count_lifecycle(slot: u32) {
+    if let Some(count) = unsafe { LIFECYCLE_COUNTS.get_ptr_mut(slot) } {
+        unsafe { *count += 1 };
+    }
+}
+
+/// An event being filled in before it is sent.
+enum PendingEvent {
+    /// Reserved in `EVENTS_RB`; submitting it makes it visible to userspace.
+    Ring(RingBufEntry<ProcessEvent>),
+    /// In the per-CPU scratch buffer, copied out on submit.
+    Scratch(&'static mut ProcessEvent),
+}
+
+impl PendingEvent {
+    /// Reserve room for an event on the active transport. `None` when the
+    /// ring buffer is full (counted as a drop) or the scratch lookup fails.
+    fn reserve() -> Option<Self> {
+        if ring_buf_enabled() && !sequencer_enabled() {
+            let entry = unsafe { EVENTS_RB.reserve::<ProcessEvent>(0) };
+            if entry.is_none() {
+                count_ring_buf_drop();
+            }
+            return entry.map(Self::Ring);
+        }
+        event_buffer_mut().map(Self::Scratch)
+    }
+
+    fn event(&mut self) -> &mut ProcessEvent {
+        match self {
+            // init_event writes every field before the entry is submitted.
+            Self::Ring(entry) => unsafe { &mut *entry.as_mut_ptr() },
+            Self::Scratch(event) => event,
+        }
+    }
+
+    fn submit<C: EbpfContext>(self, ctx: &C) {
+        match self {
+            Self::Ring(entry) => entry.submit(0),
+            Self::Scratch(event) => submit_event(ctx, event),
+        }
+    }
+}
+
 fn init_event<C: EbpfContext>(
     ctx: &C,
     event_type: EventType,
@@ -412,6 +937,7 @@ fn init_event<C: EbpfContext>(
     event.data2 = 0;
     event.aux = 0;
     event.aux2 = 0;
+    event.cgroup_id = unsafe { bpf_get_current_cgroup_id() };
 
     let mut comm = [0u8; 16];
     if let Ok(name) = ctx.command() {
@@ -435,19 +961,22 @@ fn init_event<C: EbpfContext>(
 }
 
 fn submit_event<C: EbpfContext>(ctx: &C, event: &ProcessEvent) {
-    // Check if sequencer is enabled (read from map)
-    let sequencer_enabled = unsafe {
-        match SEQUENCER_ENABLED.get(0) {
-            Some(val) => *val,
-            None => 0,
-        }
-    };
-
-    if sequencer_enabled != 0 {
+    if sequencer_enabled() {
         // Use the new lock-free sequencer
         let _ = submit_to_sequencer(event);
     } else {
-        // Fall back to legacy perf buffer
+        output_event(ctx, event);
+    }
+}
+
+/// Copy a built event to the ring buffer or, without one, the perf buffer.
+#[inline(always)]
+fn output_event<C: EbpfContext>(ctx: &C, event: &ProcessEvent) {
+    if ring_buf_enabled() {
+        if unsafe { EVENTS_RB.output(event, 0) }.is_err() {
+            count_ring_buf_drop();
+        }
+    } else {
         let events = unsafe { &mut EVENTS };
         events.output(ctx, event, 0);
     }
@@ -456,7 +985,8 @@ fn submit_event<C: EbpfContext>(ctx: &C, event: &ProcessEvent) {
 /// Zero-stack event submission for hot paths (fork, exec, exit).
 ///
 /// This bypasses stack allocation entirely by writing directly to the ring buffer.
-/// Only used when sequencer is enabled. Falls back to perf buffer otherwise.
+/// Only used when sequencer is enabled. Falls back to the BPF ring buffer or
+/// perf buffer otherwise.
 #[inline(always)]
 fn submit_event_direct<C: EbpfContext>(
     ctx: &C,
@@ -474,15 +1004,7 @@ fn submit_event_direct<C: EbpfContext>(
     aux: u32,
     aux2: u32,
 ) {
-    // Check if sequencer is enabled
-    let sequencer_enabled = unsafe {
-        match SEQUENCER_ENABLED.get(0) {
-            Some(val) => *val,
-            None => 0,
-        }
-    };
-
-    if sequencer_enabled != 0 {
+    if sequencer_enabled() {
         // ZERO-STACK PATH: Direct write to ring buffer
         let _ = submit_to_sequencer_direct(
             pid,
@@ -500,8 +1022,8 @@ fn submit_event_direct<C: EbpfContext>(
             aux2,
         );
     } else {
-        // LEGACY PATH: Build event on stack for perf buffer
-        // (perf buffer requires a contiguous struct)
+        // Build the event on the stack for the ring or perf buffer
+        // (both copy from a contiguous struct)
         let event = ProcessEvent {
             pid,
             ppid,
@@ -518,9 +1040,9 @@ fn submit_event_direct<C: EbpfContext>(
             data2,
             aux,
             aux2,
+            cgroup_id: unsafe { bpf_get_current_cgroup_id() },
         };
-        let events = unsafe { &mut EVENTS };
-        events.output(ctx, &event, 0);
+        output_event(ctx, &event);
     }
 }
 
@@ -605,7 +1127,7 @@ pub(crate) fn submit_to_sequencer(event: &ProcessEvent) -> Result<(), i64> {
     // 4. COPY DATA (Direct write to ring buffer)
     // --------------------------------------------------------
     // The event is passed by reference - we write it directly.
-    // This is a single memcpy of 96 bytes.
+    // This is a single memcpy of 104 bytes.
     unsafe {
         core::ptr::write_volatile(&mut (*slot_ptr).event, *event);
     }
@@ -698,6 +1220,7 @@ fn submit_to_sequencer_direct(
         core::ptr::write_volatile(&mut e.data2, data2);
         core::ptr::write_volatile(&mut e.aux, aux);
         core::ptr::write_volatile(&mut e.aux2, aux2);
+        core::ptr::write_volatile(&mut e.cgroup_id, bpf_get_current_cgroup_id());
     }
 
     // 5. COMMIT
@@ -729,15 +1252,15 @@ fn try_handle_exec(ctx: TracePointContext) -> u32 {
     info!(&ctx, "process exec");
     let now = unsafe { bpf_ktime_get_ns() };
     let pid = ctx.pid();
-    if pid == 0 {
+    if pid == 0 || filtered(pid) {
         return 0;
     }
-    let event = match event_buffer_mut() {
-        Some(event) => event,
-        None => return 1,
+    count_lifecycle(lifecycle_counter::EXEC);
+    let Some(mut pending) = PendingEvent::reserve() else {
+        return 1;
     };
-    init_event(&ctx, EventType::Exec, now, pid, event);
-    submit_event(&ctx, event);
+    init_event(&ctx, EventType::Exec, now, pid, pending.event());
+    pending.submit(&ctx);
     0
 }
 
@@ -766,9 +1289,10 @@ pub fn handle_exec_raw(ctx: BtfTracePointContext) -> u32 {
 fn try_handle_exec_raw(ctx: &BtfTracePointContext) -> u32 {
     let now = unsafe { bpf_ktime_get_ns() };
     let pid = ctx.pid();
-    if pid == 0 {
+    if pid == 0 || filtered(pid) {
         return 0;
     }
+    count_lifecycle(lifecycle_counter::EXEC);
 
     // Use the direct ring buffer write path (zero-stack optimization)
     // We don't extract task_struct fields directly in this hot path;
@@ -826,6 +1350,11 @@ pub fn handle_fork(ctx: TracePointContext) -> u32 {
 
 #[cfg(target_arch = "bpf")]
 fn try_handle_fork(ctx: TracePointContext) -> Result<u32, u32> {
+    // The parent is the current task; the child shares its cgroup.
+    if filtered(ctx.pid()) {
+        return Ok(0);
+    }
+    count_lifecycle(lifecycle_counter::FORK);
     let ids = bpf_get_current_uid_gid();
     let uid = ids as u32;
     let gid = (ids >> 32) as u32;
@@ -838,6 +1367,8 @@ fn try_handle_fork(ctx: TracePointContext) -> Result<u32, u32> {
     comm.copy_from_slice(&child_comm_raw);
 
     let now = unsafe { bpf_ktime_get_ns() };
+    let parent = unsafe { bpf_get_current_task_btf() } as *const u8;
+    let (ancestors, ancestors2) = fork_ancestry(parent, &load_config());
 
     // ZERO-STACK PATH: Use direct write to avoid stack allocation
     submit_event_direct(
@@ -851,8 +1382,8 @@ fn try_handle_fork(ctx: TracePointContext) -> Result<u32, u32> {
         &comm,
         PERCENT_MILLI_UNKNOWN, // cpu_pct_milli
         PERCENT_MILLI_UNKNOWN, // mem_pct_milli
-        0,                     // data
-        0,                     // data2
+        ancestors,             // data = grandparent, great-grandparent
+        ancestors2,            // data2 = the next two ancestors
         0,                     // aux
         0,                     // aux2
     );
@@ -881,12 +1412,15 @@ fn try_handle_fork_raw(ctx: &BtfTracePointContext) -> i32 {
     let child_pid = unsafe { read_task_pid(child) };
     let parent_pid = unsafe { read_task_pid(parent) };
 
-    if child_pid == 0 {
+    // The parent is the current task; the child shares its cgroup.
+    if child_pid == 0 || filtered(parent_pid) {
         return 0;
     }
+    count_lifecycle(lifecycle_counter::FORK);
 
     // Read comm from child task_struct
     let comm = unsafe { read_task_comm(child) };
+    let (ancestors, ancestors2) = fork_ancestry(parent as *const u8, &load_config());
 
     // Get UID/GID from current context
     let ids = bpf_get_current_uid_gid();
@@ -904,8 +1438,8 @@ fn try_handle_fork_raw(ctx: &BtfTracePointContext) -> i32 {
         &comm,
         PERCENT_MILLI_UNKNOWN, // cpu_pct_milli
         PERCENT_MILLI_UNKNOWN, // mem_pct_milli
-        0,                     // data
-        0,                     // data2
+        ancestors,             // data = grandparent, great-grandparent
+        ancestors2,            // data2 = the next two ancestors
         0,                     // aux
         0,                     // aux2
     );
@@ -932,14 +1466,17 @@ pub fn handle_exit(ctx: TracePointContext) -> u32 {
 fn try_handle_exit(ctx: TracePointContext) -> u32 {
     let now = unsafe { bpf_ktime_get_ns() };
     let pid = ctx.pid();
-    if pid != 0 {
-        let event = match event_buffer_mut() {
-            Some(event) => event,
-            None => return 1,
+    if pid != 0 && !filtered(pid) {
+        flush_held_back(&ctx, pid, now);
+        let Some(mut pending) = PendingEvent::reserve() else {
+            return 1;
         };
+        let event = pending.event();
         init_event(&ctx, EventType::Exit, now, pid, event);
         event.exit_time_ns = now;
-        submit_event(&ctx, event);
+        let task = unsafe { bpf_get_current_task_btf() } as *const u8;
+        (event.aux, event.aux2) = exit_status(task, &load_config());
+        pending.submit(&ctx);
     }
 
     cleanup_process_state(pid);
@@ -963,9 +1500,16 @@ fn try_handle_exit_raw(ctx: &BtfTracePointContext) -> i32 {
     if pid == 0 {
         return 0;
     }
+    if filtered(pid) {
+        cleanup_process_state(pid);
+        return 0;
+    }
+
+    flush_held_back(ctx, pid, now);
 
     // Read comm from task_struct
     let comm = unsafe { read_task_comm(task) };
+    let (status, status_flags) = exit_status(task as *const u8, &load_config());
 
     // Get UID/GID from current context
     let ids = bpf_get_current_uid_gid();
@@ -985,8 +1529,8 @@ fn try_handle_exit_raw(ctx: &BtfTracePointContext) -> i32 {
         PERCENT_MILLI_UNKNOWN, // mem_pct_milli
         now,                   // data = exit_time_ns
         0,                     // data2
-        0,                     // aux
-        0,                     // aux2
+        status,                // aux = wait status
+        status_flags,          // aux2
     );
 
     // Clean up per-process state
@@ -995,6 +1539,32 @@ fn try_handle_exit_raw(ctx: &BtfTracePointContext) -> i32 {
     0
 }
 
+/// Report what an exiting process's accumulators held back since their last
+/// event, which would otherwise wait for a transfer that never comes.
+/// Called before its Exit event so userspace still knows the process.
+#[inline(always)]
+fn flush_held_back<C: EbpfContext>(ctx: &C, pid: u32, now: u64) {
+    let net = unsafe { &*(&raw const NET_ACCUM) };
+    for op in NET_OPS {
+        if let Some((total, calls)) = take_held_back(net, &net_key(pid, op)) {
+            emit_activity_event(ctx, EventType::Net, now, total, calls, op as u32, 0);
+        }
+    }
+
+    let files = unsafe { &*(&raw const FILE_ACCUM) };
+    let pending = unsafe { &*(&raw const PENDING_FILES) };
+    take_pending(files, pending, pid, |key, total, _| {
+        emit_activity_event(ctx, EventType::FileIo, now, total, key.ino, key.op, key.dev);
+    });
+
+    // The sector of the last request isn't kept, so these carry none.
+    let blocks = unsafe { &*(&raw const BLOCK_ACCUM) };
+    let pending = unsafe { &*(&raw const PENDING_BLOCKS) };
+    take_pending(blocks, pending, pid, |key, total, _| {
+        emit_activity_event(ctx, EventType::BlockIo, now, total, 0, key.op, key.dev);
+    });
+}
+
 /// Clean up per-process state maps when a process exits
 #[inline(always)]
 fn cleanup_process_state(pid: u32) {
@@ -1004,7 +1574,61 @@ fn cleanup_process_state(pid: u32) {
 
         let faults = unsafe { &raw const PAGE_FAULT_THROTTLE };
         let _ = unsafe { (*faults).remove(&pid) };
+
+        let sched = unsafe { &raw const SCHED_LATENCY_THROTTLE };
+        let _ = unsafe { (*sched).remove(&pid) };
+
+        let offcpu = unsafe { &raw const OFFCPU_ACCUM };
+        let _ = unsafe { (*offcpu).remove(&pid) };
+
+        let growth = unsafe { &raw const MEM_GROWTH };
+        let _ = unsafe { (*growth).remove(&pid) };
+        let brk = unsafe { &raw const BRK_LAST };
+        let _ = unsafe { (*brk).remove(&pid) };
+
+        let net = unsafe { &raw const NET_ACCUM };
+        for op in NET_OPS {
+            let _ = unsafe { (*net).remove(&net_key(pid, op)) };
+        }
+    }
+}
+
+/// Every op with a NET_ACCUM entry per process.
+const NET_OPS: [NetOp; 8] = [
+    NetOp::TcpSend,
+    NetOp::TcpRecv,
+    NetOp::UdpSend,
+    NetOp::UdpRecv,
+    NetOp::UnixStreamSend,
+    NetOp::UnixStreamRecv,
+    NetOp::UnixDgramSend,
+    NetOp::UnixDgramRecv,
+];
+
+#[inline(always)]
+fn net_key(pid: u32, op: NetOp) -> u64 {
+    ((pid as u64) << 32) | op as u64
+}
+
+/// Add `bytes` to the PID's running total for `op` and emit a Net event
+/// carrying the total (`data`) and number of calls (`data2`) at most once
+/// per NET throttle interval, so a busy socket costs one event per interval
+/// rather than one per call. The first transfer is reported immediately.
+fn account_net<C: EbpfContext>(ctx: &C, op: NetOp, bytes: u64) -> u32 {
+    if bytes == 0 {
+        return 0;
+    }
+    let pid = ctx.pid();
+    if pid == 0 {
+        return 0;
     }
+    let now = unsafe { bpf_ktime_get_ns() };
+    let interval = throttle_interval(throttle_slot::NET);
+    let accum = unsafe { &*(&raw const NET_ACCUM) };
+    let Some((total, calls)) = accumulate(accum, &net_key(pid, op), bytes, interval, now) else {
+        return 0;
+    };
+    emit_activity_event(ctx, EventType::Net, now, total, calls, op as u32, 0)
 }
 
 fn emit_activity_event<C: EbpfContext>(
@@ -1016,13 +1640,6 @@ fn emit_activity_event<C: EbpfContext>(
     aux: u32,
     aux2: u32,
 ) -> u32 {
-    if matches!(
-        event_type,
-        EventType::Net | EventType::FileIo | EventType::Syscall | EventType::BlockIo
-    ) {
-        return 0;
-    }
-
     if matches!(
         event_type,
         EventType::Net | EventType::FileIo | EventType::BlockIo
@@ -1032,189 +1649,706 @@ fn emit_activity_event<C: EbpfContext>(
     }
 
     let pid = ctx.pid();
-    if pid == 0 {
+    if pid == 0 || filtered(pid) {
         return 0;
     }
 
-    let event = match event_buffer_mut() {
-        Some(event) => event,
-        None => return 1,
+    let Some(mut pending) = PendingEvent::reserve() else {
+        return 1;
     };
 
+    let event = pending.event();
     init_event(ctx, event_type, now, pid, event);
     event.data = data;
     event.data2 = data2;
     event.aux = aux;
     event.aux2 = aux2;
-    submit_event(ctx, event);
+    pending.submit(ctx);
     0
 }
 
-#[kprobe(function = "tcp_sendmsg")]
-pub fn trace_tcp_send(ctx: ProbeContext) -> u32 {
-    try_trace_tcp_send(ctx)
+// The send paths take the requested length as their third argument:
+//   int tcp_sendmsg(struct sock *sk, struct msghdr *msg, size_t size)
+//   int udp_sendmsg(struct sock *sk, struct msghdr *msg, size_t len)
+//   int unix_{stream,dgram}_sendmsg(struct socket *sock, struct msghdr *msg, size_t len)
+#[inline(always)]
+fn account_net_send(ctx: &ProbeContext, op: NetOp) -> u32 {
+    let size: u64 = match ctx.arg(2) {
+        Some(size) => size,
+        None => return 0,
+    };
+    account_net(ctx, op, size)
 }
 
-fn try_trace_tcp_send(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
+// Receive paths are return probes: the length argument is the buffer size,
+// the return value is what was actually copied (or -errno).
+#[inline(always)]
+fn account_net_recv(ctx: &RetProbeContext, op: NetOp) -> u32 {
+    let copied: i64 = match ctx.ret() {
+        Some(copied) => copied,
+        None => return 0,
+    };
+    if copied <= 0 {
+        return 0;
+    }
+    account_net(ctx, op, copied as u64)
 }
 
-#[kprobe(function = "tcp_recvmsg")]
-pub fn trace_tcp_recv(ctx: ProbeContext) -> u32 {
-    try_trace_tcp_recv(ctx)
+#[kprobe(function = "tcp_sendmsg")]
+pub fn trace_tcp_send(ctx: ProbeContext) -> u32 {
+    account_net_send(&ctx, NetOp::TcpSend)
 }
 
-fn try_trace_tcp_recv(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
+#[kretprobe(function = "tcp_recvmsg")]
+pub fn trace_tcp_recv(ctx: RetProbeContext) -> u32 {
+    account_net_recv(&ctx, NetOp::TcpRecv)
 }
 
 #[kprobe(function = "udp_sendmsg")]
 pub fn trace_udp_send(ctx: ProbeContext) -> u32 {
-    try_trace_udp_send(ctx)
-}
-
-fn try_trace_udp_send(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
-}
-
-#[kprobe(function = "udp_recvmsg")]
-pub fn trace_udp_recv(ctx: ProbeContext) -> u32 {
-    try_trace_udp_recv(ctx)
+    account_net_send(&ctx, NetOp::UdpSend)
 }
 
-fn try_trace_udp_recv(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
+#[kretprobe(function = "udp_recvmsg")]
+pub fn trace_udp_recv(ctx: RetProbeContext) -> u32 {
+    account_net_recv(&ctx, NetOp::UdpRecv)
 }
 
 #[kprobe(function = "unix_stream_sendmsg")]
 pub fn trace_unix_stream_send(ctx: ProbeContext) -> u32 {
-    try_trace_unix_stream_send(ctx)
-}
-
-fn try_trace_unix_stream_send(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
-}
-
-#[kprobe(function = "unix_stream_recvmsg")]
-pub fn trace_unix_stream_recv(ctx: ProbeContext) -> u32 {
-    try_trace_unix_stream_recv(ctx)
+    account_net_send(&ctx, NetOp::UnixStreamSend)
 }
 
-fn try_trace_unix_stream_recv(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
+#[kretprobe(function = "unix_stream_recvmsg")]
+pub fn trace_unix_stream_recv(ctx: RetProbeContext) -> u32 {
+    account_net_recv(&ctx, NetOp::UnixStreamRecv)
 }
 
 #[kprobe(function = "unix_dgram_sendmsg")]
 pub fn trace_unix_dgram_send(ctx: ProbeContext) -> u32 {
-    try_trace_unix_dgram_send(ctx)
-}
-
-fn try_trace_unix_dgram_send(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
-}
-
-#[kprobe(function = "unix_dgram_recvmsg")]
-pub fn trace_unix_dgram_recv(ctx: ProbeContext) -> u32 {
-    try_trace_unix_dgram_recv(ctx)
-}
-
-fn try_trace_unix_dgram_recv(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
+    account_net_send(&ctx, NetOp::UnixDgramSend)
 }
 
-#[kprobe(function = "vfs_read")]
-pub fn trace_vfs_read(ctx: ProbeContext) -> u32 {
-    try_trace_vfs_read(ctx)
+#[kretprobe(function = "unix_dgram_recvmsg")]
+pub fn trace_unix_dgram_recv(ctx: RetProbeContext) -> u32 {
+    account_net_recv(&ctx, NetOp::UnixDgramRecv)
 }
 
-fn try_trace_vfs_read(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
+// TCP connections. Outbound ones are read from the address passed to
+// connect, inbound ones from the socket accept returns:
+//   int tcp_v4_connect(struct sock *sk, struct sockaddr *uaddr, int addr_len)
+//   int tcp_v6_connect(struct sock *sk, struct sockaddr *uaddr, int addr_len)
+//   struct sock *inet_csk_accept(struct sock *sk, ...)
+#[kprobe(function = "tcp_v4_connect")]
+pub fn trace_tcp_v4_connect(ctx: ProbeContext) -> u32 {
+    let Some(uaddr) = ctx.arg::<*const u8>(1) else {
+        return 0;
+    };
+    // struct sockaddr_in: sin_family, sin_port (big-endian), sin_addr.
+    let (Some(port), Some(addr)) = (read_field::<u16>(uaddr, 2), read_field::<[u8; 4]>(uaddr, 4))
+    else {
+        return 0;
+    };
+    emit_connection(
+        &ctx,
+        conn_direction::OUTBOUND,
+        ipv4_mapped(addr),
+        u16::from_be(port),
+        0,
+    )
 }
 
-#[kprobe(function = "vfs_write")]
-pub fn trace_vfs_write(ctx: ProbeContext) -> u32 {
-    try_trace_vfs_write(ctx)
+#[kprobe(function = "tcp_v6_connect")]
+pub fn trace_tcp_v6_connect(ctx: ProbeContext) -> u32 {
+    let Some(uaddr) = ctx.arg::<*const u8>(1) else {
+        return 0;
+    };
+    // struct sockaddr_in6: sin6_family, sin6_port, sin6_flowinfo, sin6_addr.
+    // IPv4-mapped destinations also reach tcp_v4_connect; CONN_SEEN drops
+    // the second event.
+    let (Some(port), Some(addr)) = (
+        read_field::<u16>(uaddr, 2),
+        read_field::<[u8; 16]>(uaddr, 8),
+    ) else {
+        return 0;
+    };
+    emit_connection(&ctx, conn_direction::OUTBOUND, addr, u16::from_be(port), 0)
 }
 
-fn try_trace_vfs_write(ctx: ProbeContext) -> u32 {
-    let _ = ctx;
-    0
+#[kretprobe(function = "inet_csk_accept")]
+pub fn trace_inet_csk_accept(ctx: RetProbeContext) -> u32 {
+    let config = load_config();
+    if config.sock_offsets_known == 0 {
+        return 0;
+    }
+    let sk: *const u8 = match ctx.ret() {
+        Some(sk) => sk,
+        None => return 0,
+    };
+    let Some(family) = read_field::<u16>(sk, config.skc_family_offset) else {
+        return 0;
+    };
+    let addr = match family {
+        AF_INET => match read_field::<[u8; 4]>(sk, config.skc_daddr_offset) {
+            Some(addr) => ipv4_mapped(addr),
+            None => return 0,
+        },
+        AF_INET6 => match read_field::<[u8; 16]>(sk, config.skc_v6_daddr_offset) {
+            Some(addr) => addr,
+            None => return 0,
+        },
+        _ => return 0,
+    };
+    let (Some(remote_port), Some(local_port)) = (
+        read_field::<u16>(sk, config.skc_dport_offset),
+        read_field::<u16>(sk, config.skc_num_offset),
+    ) else {
+        return 0;
+    };
+    emit_connection(
+        &ctx,
+        conn_direction::INBOUND,
+        addr,
+        u16::from_be(remote_port),
+        local_port,
+    )
 }
 
-#[tracepoint(category = "block", name = "block_bio_queue")]
-pub fn trace_block_queue(ctx: TracePointContext) -> u32 {
-    try_trace_block_queue(ctx)
+#[inline(always)]
+fn ipv4_mapped(addr: [u8; 4]) -> [u8; 16] {
+    let mut mapped = [0u8; 16];
+    mapped[10] = 0xff;
+    mapped[11] = 0xff;
+    mapped[12..].copy_from_slice(&addr);
+    mapped
 }
 
-fn try_trace_block_queue(ctx: TracePointContext) -> u32 {
-    let dev = match tp_read_u64(&ctx, BLOCK_BIO_DEV_OFFSET) {
-        Some(value) => value,
+/// Emit a Connection event unless the same process reported the same
+/// connection within CONN_REPEAT_INTERVAL_NS, so reconnect loops don't
+/// flood the buffer.
+#[inline(always)]
+fn emit_connection<C: EbpfContext>(
+    ctx: &C,
+    direction: u32,
+    addr: [u8; 16],
+    remote_port: u16,
+    local_port: u16,
+) -> u32 {
+    let pid = ctx.pid();
+    if pid == 0 {
+        return 0;
+    }
+    let now = unsafe { bpf_ktime_get_ns() };
+    let (addr_hi, addr_lo) = connection_addr_fields(addr);
+    let key = ConnKey {
+        pid,
+        port_direction: ((remote_port as u32) << 16) | direction,
+        addr_hi,
+        addr_lo,
+    };
+    let seen = unsafe { &raw const CONN_SEEN };
+    if let Some(last) = unsafe { (*seen).get(&key) } {
+        if now.saturating_sub(*last) < CONN_REPEAT_INTERVAL_NS {
+            return 0;
+        }
+    }
+    let _ = unsafe { (*seen).insert(&key, &now, 0) };
+    emit_activity_event(
+        ctx,
+        EventType::Connection,
+        now,
+        addr_hi,
+        addr_lo,
+        remote_port as u32,
+        ((local_port as u32) << 16) | direction,
+    )
+}
+
+// File I/O: the entry probes stash the `struct file *`, the return probes
+// read how much was actually transferred (or -errno):
+//   ssize_t vfs_read(struct file *file, char __user *buf, size_t count, loff_t *pos)
+//   ssize_t vfs_write(struct file *file, const char __user *buf, size_t count, loff_t *pos)
+#[inline(always)]
+fn stash_vfs_file(ctx: &ProbeContext) -> u32 {
+    if load_config().file_inode_offset == 0 || ctx.pid() == 0 {
+        return 0;
+    }
+    let file: u64 = match ctx.arg(0) {
+        Some(file) => file,
         None => return 0,
     };
-    let sector = match tp_read_u64(&ctx, BLOCK_BIO_SECTOR_OFFSET) {
-        Some(value) => value,
+    let key = bpf_get_current_pid_tgid();
+    let args = unsafe { &raw const VFS_ARGS };
+    let _ = unsafe { (*args).insert(&key, &file, 0) };
+    0
+}
+
+/// Device (`s_dev`) and inode number of the file, if it is a regular file.
+/// Pipes, sockets and ttys also go through `vfs_read`/`vfs_write` and are
+/// skipped.
+#[inline(always)]
+fn regular_file_id(file: *const u8, config: &TelemetryConfig) -> Option<(u32, u64)> {
+    let inode = read_ptr(file, config.file_inode_offset)?;
+    let mode: u16 = read_field(inode, config.inode_mode_offset)?;
+    if mode & S_IFMT != S_IFREG {
+        return None;
+    }
+    let ino: u64 = read_field(inode, config.inode_ino_offset)?;
+    let sb = read_ptr(inode, config.inode_sb_offset)?;
+    let dev: u32 = read_field(sb, config.sb_dev_offset)?;
+    Some((dev, ino))
+}
+
+/// Add the bytes transferred to the running total for this PID, file and
+/// direction and emit a FileIo event carrying the total (`data`), inode
+/// (`data2`), `FileOp` (`aux`) and device (`aux2`) at most every
+/// FILE_FLUSH_INTERVAL_NS. The first transfer is reported immediately, and
+/// bytes still held back when the process exits by [`flush_held_back`].
+fn account_file_io(ctx: &RetProbeContext, op: FileOp) -> u32 {
+    let key = bpf_get_current_pid_tgid();
+    let args = unsafe { &raw const VFS_ARGS };
+    let file = match unsafe { (*args).get(&key) } {
+        Some(file) => *file,
         None => return 0,
     };
-    let sectors = match tp_read_u32(&ctx, BLOCK_BIO_NR_SECTOR_OFFSET) {
-        Some(value) => value,
+    let _ = unsafe { (*args).remove(&key) };
+
+    let copied: i64 = match ctx.ret() {
+        Some(copied) => copied,
         None => return 0,
     };
+    if copied <= 0 {
+        return 0;
+    }
+    let pid = ctx.pid();
+    let config = load_config();
+    let Some((dev, ino)) = regular_file_id(file as *const u8, &config) else {
+        return 0;
+    };
+
     let now = unsafe { bpf_ktime_get_ns() };
-    emit_block_event_common(&ctx, now, BlockOp::Queue, dev, sector, sectors, None)
+    let key = FileKey {
+        ino,
+        dev,
+        pid,
+        op: op as u32,
+        _pad: 0,
+    };
+    let accum = unsafe { &*(&raw const FILE_ACCUM) };
+    let pending = unsafe { &*(&raw const PENDING_FILES) };
+    let index = pending_key(pid, ino as u32 ^ (ino >> 32) as u32 ^ dev ^ op as u32);
+    let Some((total, _)) = accumulate_pending(
+        accum,
+        &key,
+        copied as u64,
+        FILE_FLUSH_INTERVAL_NS,
+        now,
+        pending,
+        index,
+    ) else {
+        return 0;
+    };
+    emit_activity_event(ctx, EventType::FileIo, now, total, ino, op as u32, dev)
 }
 
-#[tracepoint(category = "block", name = "block_rq_issue")]
-pub fn trace_block_issue(ctx: TracePointContext) -> u32 {
-    try_trace_block_issue(ctx)
+#[kprobe(function = "vfs_read")]
+pub fn trace_vfs_read_entry(ctx: ProbeContext) -> u32 {
+    stash_vfs_file(&ctx)
+}
+
+#[kretprobe(function = "vfs_read")]
+pub fn trace_vfs_read(ctx: RetProbeContext) -> u32 {
+    account_file_io(&ctx, FileOp::Read)
+}
+
+#[kprobe(function = "vfs_write")]
+pub fn trace_vfs_write_entry(ctx: ProbeContext) -> u32 {
+    stash_vfs_file(&ctx)
+}
+
+#[kretprobe(function = "vfs_write")]
+pub fn trace_vfs_write(ctx: RetProbeContext) -> u32 {
+    account_file_io(&ctx, FileOp::Write)
 }
 
-fn try_trace_block_issue(ctx: TracePointContext) -> u32 {
-    let dev = match tp_read_u64(&ctx, BLOCK_RQ_DEV_OFFSET) {
-        Some(value) => value,
+// The OOM killer has picked its victim in `oc->chosen` by the time it calls
+//   static void oom_kill_process(struct oom_control *oc, const char *message)
+#[kprobe(function = "oom_kill_process")]
+pub fn trace_oom_kill(ctx: ProbeContext) -> u32 {
+    try_trace_oom_kill(&ctx)
+}
+
+fn try_trace_oom_kill(ctx: &ProbeContext) -> u32 {
+    let config = load_config();
+    if config.oom_chosen_offset == 0 || config.task_tgid_offset == 0 {
+        return 0;
+    }
+    let oc: u64 = match ctx.arg(0) {
+        Some(oc) => oc,
         None => return 0,
     };
-    let sector = match tp_read_u64(&ctx, BLOCK_RQ_SECTOR_OFFSET) {
-        Some(value) => value,
-        None => return 0,
+    let oc = oc as *const u8;
+    let Some(victim) = read_ptr(oc, config.oom_chosen_offset) else {
+        return 0;
     };
-    let sectors = match tp_read_u32(&ctx, BLOCK_RQ_NR_SECTOR_OFFSET) {
-        Some(value) => value,
-        None => return 0,
+    let victim_pid: i32 = match read_field(victim, config.task_tgid_offset) {
+        Some(pid) if pid > 0 => pid,
+        _ => return 0,
+    };
+    let memcg_oom = config.oom_memcg_offset != 0 && read_ptr(oc, config.oom_memcg_offset).is_some();
+    let now = unsafe { bpf_ktime_get_ns() };
+
+    let Some(mut pending) = PendingEvent::reserve() else {
+        return 1;
     };
-    let bytes = tp_read_u32(&ctx, BLOCK_RQ_ISSUE_BYTES_OFFSET);
+    let event = pending.event();
+    // Built for the allocating task, then rewritten to describe the victim.
+    let trigger_pid = ctx.pid();
+    init_event(ctx, EventType::OomKill, now, trigger_pid, event);
+    event.pid = victim_pid as u32;
+    event.ppid = parent_tgid(victim, &config).unwrap_or(0);
+    event.cgroup_id = 0;
+    event.comm = unsafe { read_task_comm(victim as *const TaskStruct) };
+    event.cpu_pct_milli = PERCENT_MILLI_UNKNOWN;
+    event.mem_pct_milli = sample_mem(victim, &config);
+    event.data = rss_bytes(victim, &config).unwrap_or(0);
+    event.data2 = trigger_pid as u64;
+    event.aux = memcg_oom as u32;
+    pending.submit(ctx);
+    0
+}
+
+// Runqueue latency: a thread is stamped when it becomes runnable (woken,
+// newly forked, or preempted while still runnable) and measured when
+// sched_switch picks it. Off-CPU time: a thread switched out asleep is
+// stamped with its state and measured when it is woken. The raw
+// tracepoints pass task_struct pointers:
+//   sched_wakeup(struct task_struct *p)
+//   sched_wakeup_new(struct task_struct *p)
+//   sched_switch(bool preempt, struct task_struct *prev, struct task_struct *next, ...)
+#[btf_tracepoint(function = "sched_wakeup")]
+pub fn trace_sched_wakeup(ctx: BtfTracePointContext) -> u32 {
+    let task = unsafe { ctx.arg::<*const TaskStruct>(0) };
+    let now = unsafe { bpf_ktime_get_ns() };
+    mark_queued(task, now);
+    end_off_cpu(&ctx, task, now)
+}
+
+#[btf_tracepoint(function = "sched_wakeup_new")]
+pub fn trace_sched_wakeup_new(ctx: BtfTracePointContext) -> u32 {
+    let task = unsafe { ctx.arg::<*const TaskStruct>(0) };
+    mark_queued(task, unsafe { bpf_ktime_get_ns() })
+}
+
+#[inline(always)]
+fn mark_queued(task: *const TaskStruct, now: u64) -> u32 {
+    if load_config().task_pid_offset == 0 {
+        return 0;
+    }
+    let tid = unsafe { read_task_tid(task) };
+    if tid != 0 {
+        let _ = unsafe { SCHED_QUEUED_AT.insert(&tid, &now, 0) };
+    }
+    0
+}
+
+#[btf_tracepoint(function = "sched_switch")]
+pub fn trace_sched_switch(ctx: BtfTracePointContext) -> u32 {
+    try_trace_sched_switch(&ctx)
+}
+
+fn try_trace_sched_switch(ctx: &BtfTracePointContext) -> u32 {
+    let config = load_config();
+    if config.task_pid_offset == 0 || config.task_tgid_offset == 0 {
+        return 0;
+    }
+    let preempt = unsafe { ctx.arg::<u64>(0) } & 0xff != 0;
+    let prev = unsafe { ctx.arg::<*const TaskStruct>(1) };
+    let next = unsafe { ctx.arg::<*const TaskStruct>(2) };
     let now = unsafe { bpf_ktime_get_ns() };
-    emit_block_event_common(&ctx, now, BlockOp::Issue, dev, sector, sectors, bytes)
+
+    // A preempted task goes straight back on the runqueue.
+    if preempt {
+        mark_queued(prev, now);
+    } else {
+        start_off_cpu(prev, now, &config);
+    }
+    let tid = unsafe { read_task_tid(next) };
+    if tid == 0 {
+        return 0;
+    }
+    let Some(queued) = (unsafe { SCHED_QUEUED_AT.get(&tid) }).copied() else {
+        return 0;
+    };
+    let _ = unsafe { SCHED_QUEUED_AT.remove(&tid) };
+    let delay = now.saturating_sub(queued);
+    record_sched_latency(delay);
+
+    let threshold = unsafe { core::ptr::read_volatile(&SCHED_LATENCY_THRESHOLD_NS) };
+    if delay < threshold {
+        return 0;
+    }
+    let pid = unsafe { read_task_pid(next) };
+    if pid == 0 || !throttle_sched_latency(pid, now) {
+        return 0;
+    }
+
+    let Some(mut pending) = PendingEvent::reserve() else {
+        return 1;
+    };
+    let event = pending.event();
+    // Built for the task being switched out, then rewritten to describe the
+    // one that waited.
+    init_event(ctx, EventType::SchedLatency, now, ctx.pid(), event);
+    describe_task(event, next, pid, &config);
+    event.data = delay;
+    event.data2 = tid as u64;
+    event.aux = unsafe { bpf_get_smp_processor_id() };
+    pending.submit(ctx);
+    0
+}
+
+/// Rewrite an event built for the current task to describe `task` instead.
+#[inline(always)]
+fn describe_task(
+    event: &mut ProcessEvent,
+    task: *const TaskStruct,
+    pid: u32,
+    config: &TelemetryConfig,
+) {
+    let task = task as *const u8;
+    event.pid = pid;
+    event.ppid = parent_tgid(task, config).unwrap_or(0);
+    // The helper only reads the current task's cgroup.
+    event.cgroup_id = 0;
+    event.comm = unsafe { read_task_comm(task as *const TaskStruct) };
+    event.cpu_pct_milli = PERCENT_MILLI_UNKNOWN;
+    event.mem_pct_milli = sample_mem(task, config);
+}
+
+#[inline(always)]
+fn offcpu_interval_ns() -> u64 {
+    unsafe { core::ptr::read_volatile(&OFFCPU_INTERVAL_NS) }
+}
+
+/// Stamp `task` if it is being switched out to sleep. Idle kernel threads
+/// (uninterruptible with TASK_NOLOAD) count as sleeping, not blocked.
+#[inline(always)]
+fn start_off_cpu(task: *const TaskStruct, now: u64, config: &TelemetryConfig) {
+    if config.task_state_offset == 0 || offcpu_interval_ns() == 0 {
+        return;
+    }
+    let Some(state) = read_field::<u32>(task as *const u8, config.task_state_offset) else {
+        return;
+    };
+    let blocked = if state & TASK_UNINTERRUPTIBLE != 0 {
+        state & TASK_NOLOAD == 0
+    } else if state & TASK_INTERRUPTIBLE != 0 {
+        false
+    } else {
+        return;
+    };
+    let tid = unsafe { read_task_tid(task) };
+    if tid != 0 {
+        let start = OffCpuStart {
+            since_ns: now,
+            blocked: blocked as u64,
+        };
+        let _ = unsafe { OFFCPU_SINCE.insert(&tid, &start, 0) };
+    }
+}
+
+/// Add the time the woken `task` slept to its process, and send the
+/// process's OffCpu summary once the interval is over.
+#[inline(always)]
+fn end_off_cpu(ctx: &BtfTracePointContext, task: *const TaskStruct, now: u64) -> u32 {
+    let config = load_config();
+    let interval = offcpu_interval_ns();
+    if config.task_state_offset == 0 || interval == 0 {
+        return 0;
+    }
+    let tid = unsafe { read_task_tid(task) };
+    if tid == 0 {
+        return 0;
+    }
+    let Some(start) = (unsafe { OFFCPU_SINCE.get(&tid) }).copied() else {
+        return 0;
+    };
+    let _ = unsafe { OFFCPU_SINCE.remove(&tid) };
+    let pid = unsafe { read_task_pid(task) };
+    if pid == 0 {
+        return 0;
+    }
+    let slept = now.saturating_sub(start.since_ns);
+
+    let accum = unsafe { &OFFCPU_ACCUM };
+    let Some(ptr) = accum.get_ptr_mut(&pid) else {
+        let first = OffCpuAccum {
+            blocked_ns: if start.blocked != 0 { slept } else { 0 },
+            sleep_ns: if start.blocked != 0 { 0 } else { slept },
+            since_ns: start.since_ns,
+        };
+        let _ = accum.insert(&pid, &first, 0);
+        return 0;
+    };
+    let totals = unsafe { &mut *ptr };
+    if start.blocked != 0 {
+        totals.blocked_ns += slept;
+    } else {
+        totals.sleep_ns += slept;
+    }
+    let elapsed = now.saturating_sub(totals.since_ns);
+    if elapsed < interval {
+        return 0;
+    }
+    let (blocked_ns, sleep_ns) = (totals.blocked_ns, totals.sleep_ns);
+    totals.blocked_ns = 0;
+    totals.sleep_ns = 0;
+    totals.since_ns = now;
+
+    let Some(mut pending) = PendingEvent::reserve() else {
+        return 1;
+    };
+    let event = pending.event();
+    // Built for the waker, then rewritten to describe the woken process.
+    init_event(ctx, EventType::OffCpu, now, ctx.pid(), event);
+    describe_task(event, task, pid, &config);
+    event.data = blocked_ns;
+    event.data2 = sleep_ns;
+    event.aux = (elapsed / 1_000_000) as u32;
+    pending.submit(ctx);
+    0
+}
+
+#[inline(always)]
+fn record_sched_latency(delay_ns: u64) {
+    let us = (delay_ns / 1_000) | 1;
+    let bucket = (63 - us.leading_zeros()).min(SCHED_LATENCY_BUCKETS - 1);
+    if let Some(count) = unsafe { SCHED_LATENCY_HIST.get_ptr_mut(bucket) } {
+        unsafe { *count += 1 };
+    }
+}
+
+/// At most one SchedLatency event per process and interval, so a saturated
+/// CPU doesn't turn every context switch into an event.
+#[inline(always)]
+fn throttle_sched_latency(pid: u32, now: u64) -> bool {
+    let state = unsafe { &SCHED_LATENCY_THROTTLE };
+    if let Some(ptr) = state.get_ptr_mut(&pid) {
+        let last = unsafe { &mut *ptr };
+        if now.saturating_sub(*last) < SCHED_LATENCY_MIN_INTERVAL_NS {
+            return false;
+        }
+        *last = now;
+        true
+    } else {
+        let _ = state.insert(&pid, &now, 0);
+        true
+    }
+}
+
+#[tracepoint(category = "block", name = "block_bio_queue")]
+pub fn trace_block_queue(ctx: TracePointContext) -> u32 {
+    try_trace_block_tp(ctx, BlockOp::Queue)
+}
+
+#[tracepoint(category = "block", name = "block_rq_issue")]
+pub fn trace_block_issue(ctx: TracePointContext) -> u32 {
+    try_trace_block_tp(ctx, BlockOp::Issue)
 }
 
 #[tracepoint(category = "block", name = "block_rq_complete")]
 pub fn trace_block_complete(ctx: TracePointContext) -> u32 {
-    try_trace_block_complete(ctx)
+    try_trace_block_tp(ctx, BlockOp::Complete)
 }
 
-fn try_trace_block_complete(ctx: TracePointContext) -> u32 {
-    let dev = match tp_read_u64(&ctx, BLOCK_RQ_DEV_OFFSET) {
-        Some(value) => value,
-        None => return 0,
+/// Fallback for kernels without usable BTF: the fields the tracepoints
+/// export, at the offsets of their `format` files.
+fn try_trace_block_tp(ctx: TracePointContext, op: BlockOp) -> u32 {
+    let Some(dev) = tp_read_u32(&ctx, BLOCK_TP_DEV_OFFSET) else {
+        return 0;
     };
-    let sector = match tp_read_u64(&ctx, BLOCK_RQ_SECTOR_OFFSET) {
-        Some(value) => value,
-        None => return 0,
+    let Some(sector) = tp_read_u64(&ctx, BLOCK_TP_SECTOR_OFFSET) else {
+        return 0;
     };
-    let sectors = match tp_read_u32(&ctx, BLOCK_RQ_NR_SECTOR_OFFSET) {
-        Some(value) => value,
-        None => return 0,
+    let Some(sectors) = tp_read_u32(&ctx, BLOCK_TP_NR_SECTOR_OFFSET) else {
+        return 0;
     };
+    let bytes = match op {
+        BlockOp::Issue => tp_read_u32(&ctx, BLOCK_TP_ISSUE_BYTES_OFFSET)
+            .filter(|bytes| *bytes > 0)
+            .map_or_else(|| block_bytes_from_sectors(sectors), |bytes| bytes as u64),
+        _ => block_bytes_from_sectors(sectors),
+    };
+    let now = unsafe { bpf_ktime_get_ns() };
+    emit_block_event_common(&ctx, now, op, dev, sector, bytes)
+}
+
+// The same hooks as BTF tracepoints (5.12+: one `struct bio *` or `struct
+// request *` argument, and `bio->bi_bdev`). Fields are read from the structs
+// at the offsets userspace found in the kernel's BTF.
+
+#[btf_tracepoint(function = "block_bio_queue")]
+pub fn trace_block_queue_btf(ctx: BtfTracePointContext) -> u32 {
+    let config = load_config();
+    if config.block_source != block_source::BTF {
+        return 0;
+    }
+    let bio: *const u8 = unsafe { ctx.arg(0) };
+    let Some(sector) = read_field::<u64>(bio, config.bio_sector_offset) else {
+        return 0;
+    };
+    let Some(bytes) = read_field::<u32>(bio, config.bio_size_offset) else {
+        return 0;
+    };
+    let dev = read_ptr(bio, config.bio_bdev_offset)
+        .and_then(|bdev| read_field::<u32>(bdev, config.bdev_dev_offset))
+        .unwrap_or(0);
     let now = unsafe { bpf_ktime_get_ns() };
-    emit_block_event_common(&ctx, now, BlockOp::Complete, dev, sector, sectors, None)
+    emit_block_event_common(&ctx, now, BlockOp::Queue, dev, sector, bytes as u64)
+}
+
+#[btf_tracepoint(function = "block_rq_issue")]
+pub fn trace_block_issue_btf(ctx: BtfTracePointContext) -> u32 {
+    let config = load_config();
+    if config.block_source != block_source::BTF {
+        return 0;
+    }
+    let rq: *const u8 = unsafe { ctx.arg(0) };
+    let Some(bytes) = read_field::<u32>(rq, config.rq_data_len_offset) else {
+        return 0;
+    };
+    try_trace_block_rq_btf(&ctx, &config, rq, BlockOp::Issue, bytes as u64)
+}
+
+#[btf_tracepoint(function = "block_rq_complete")]
+pub fn trace_block_complete_btf(ctx: BtfTracePointContext) -> u32 {
+    let config = load_config();
+    if config.block_source != block_source::BTF {
+        return 0;
+    }
+    // (struct request *rq, blk_status_t error, unsigned int nr_bytes)
+    let rq: *const u8 = unsafe { ctx.arg(0) };
+    let bytes: u32 = unsafe { ctx.arg(2) };
+    try_trace_block_rq_btf(&ctx, &config, rq, BlockOp::Complete, bytes as u64)
+}
+
+fn try_trace_block_rq_btf(
+    ctx: &BtfTracePointContext,
+    config: &TelemetryConfig,
+    rq: *const u8,
+    op: BlockOp,
+    bytes: u64,
+) -> u32 {
+    let Some(sector) = read_field::<u64>(rq, config.rq_sector_offset) else {
+        return 0;
+    };
+    // `rq->part` is unset for passthrough requests; they count as device 0.
+    let dev = read_ptr(rq, config.rq_part_offset)
+        .and_then(|bdev| read_field::<u32>(bdev, config.bdev_dev_offset))
+        .unwrap_or(0);
+    let now = unsafe { bpf_ktime_get_ns() };
+    emit_block_event_common(ctx, now, op, dev, sector, bytes)
 }
 
 #[btf_tracepoint(function = "page_fault_user")]
@@ -1255,11 +2389,421 @@ pub fn trace_sys_enter(ctx: TracePointContext) -> u32 {
     try_trace_sys_enter(ctx)
 }
 
+fn syscall_key(pid: u32, nr: u32) -> u64 {
+    ((pid as u64) << 32) | nr as u64
+}
+
+/// Count the call for the current process and syscall on this CPU. When
+/// the window is over (at the next call of the same syscall) or the count
+/// reaches SYSCALL_FLUSH_CALLS, one Syscall event carries the count if it
+/// reached SYSCALL_MIN_CALLS, and a new window starts.
 fn try_trace_sys_enter(ctx: TracePointContext) -> u32 {
-    let _ = ctx;
+    let interval = unsafe { core::ptr::read_volatile(&SYSCALL_INTERVAL_NS) };
+    if interval == 0 {
+        return 0;
+    }
+    let pid = ctx.pid();
+    if pid == 0 {
+        return 0;
+    }
+    let Ok(nr) = (unsafe { ctx.read_at::<i64>(SYS_ENTER_ID_OFFSET) }) else {
+        return 0;
+    };
+    if nr < 0 {
+        return 0;
+    }
+    let nr = nr as u32;
+    let now = unsafe { bpf_ktime_get_ns() };
+    let key = syscall_key(pid, nr);
+    let accum = unsafe { &raw const SYSCALL_ACCUM };
+    let Some(ptr) = (unsafe { (*accum).get_ptr_mut(&key) }) else {
+        let entry = SyscallAccum {
+            calls: 1,
+            since_ns: now,
+        };
+        let _ = unsafe { (*accum).insert(&key, &entry, 0) };
+        return 0;
+    };
+    let entry = unsafe { &mut *ptr };
+    entry.calls += 1;
+    let window = now.saturating_sub(entry.since_ns);
+    if window < interval && entry.calls < SYSCALL_FLUSH_CALLS {
+        return 0;
+    }
+    let calls = entry.calls;
+    entry.calls = 0;
+    entry.since_ns = now;
+
+    let min_calls = unsafe { core::ptr::read_volatile(&SYSCALL_MIN_CALLS) };
+    if calls < min_calls {
+        return 0;
+    }
+    emit_activity_event(&ctx, EventType::Syscall, now, calls, window, nr, 0)
+}
+
+#[tracepoint(category = "vmscan", name = "mm_vmscan_direct_reclaim_begin")]
+pub fn trace_direct_reclaim_begin(ctx: TracePointContext) -> u32 {
+    let tid = bpf_get_current_pid_tgid() as u32;
+    if tid == 0 {
+        return 0;
+    }
+    let order = unsafe { ctx.read_at::<i32>(RECLAIM_BEGIN_ORDER_OFFSET) }.unwrap_or(0);
+    let start = ReclaimStart {
+        since_ns: unsafe { bpf_ktime_get_ns() },
+        order: order.max(0) as u32,
+        _pad: 0,
+    };
+    let _ = unsafe { RECLAIM_START.insert(&tid, &start, 0) };
+    0
+}
+
+/// The allocating thread leaves direct reclaim: report the stall if it
+/// lasted at least DIRECT_RECLAIM_MIN_NS.
+#[tracepoint(category = "vmscan", name = "mm_vmscan_direct_reclaim_end")]
+pub fn trace_direct_reclaim_end(ctx: TracePointContext) -> u32 {
+    let tid = bpf_get_current_pid_tgid() as u32;
+    let Some(start) = (unsafe { RECLAIM_START.get(&tid) }).copied() else {
+        return 0;
+    };
+    let _ = unsafe { RECLAIM_START.remove(&tid) };
+    let now = unsafe { bpf_ktime_get_ns() };
+    let stalled = now.saturating_sub(start.since_ns);
+    let min = unsafe { core::ptr::read_volatile(&DIRECT_RECLAIM_MIN_NS) };
+    if stalled < min {
+        return 0;
+    }
+    let reclaimed = tp_read_u64(&ctx, RECLAIM_END_NR_RECLAIMED_OFFSET).unwrap_or(0);
+    emit_activity_event(
+        &ctx,
+        EventType::DirectReclaim,
+        now,
+        stalled,
+        reclaimed,
+        start.order,
+        tid,
+    )
+}
+
+/// Anonymous mappings count as growth; file mappings (libraries, mapped
+/// data files) are mostly page cache.
+#[tracepoint(category = "syscalls", name = "sys_enter_mmap")]
+pub fn trace_mmap(ctx: TracePointContext) -> u32 {
+    let flags = tp_read_u64(&ctx, MMAP_FLAGS_OFFSET).unwrap_or(0);
+    if flags & MAP_ANONYMOUS == 0 {
+        return 0;
+    }
+    let len = tp_read_u64(&ctx, MMAP_LEN_OFFSET).unwrap_or(0);
+    account_mem_growth(&ctx, len)
+}
+
+/// `brk` returns the new break; growth is the distance from the last one
+/// seen, so the first call of a process only sets the baseline.
+#[tracepoint(category = "syscalls", name = "sys_exit_brk")]
+pub fn trace_brk(ctx: TracePointContext) -> u32 {
+    let pid = ctx.pid();
+    let Some(brk) = tp_read_u64(&ctx, SYS_EXIT_RET_OFFSET) else {
+        return 0;
+    };
+    let last = unsafe { BRK_LAST.get(&pid) }.copied();
+    let _ = unsafe { BRK_LAST.insert(&pid, &brk, 0) };
+    match last {
+        Some(last) if brk > last => account_mem_growth(&ctx, brk - last),
+        _ => 0,
+    }
+}
+
+fn account_mem_growth<C: EbpfContext>(ctx: &C, bytes: u64) -> u32 {
+    let min = unsafe { core::ptr::read_volatile(&MEM_GROWTH_MIN_BYTES) };
+    let pid = ctx.pid();
+    if bytes == 0 || min == u64::MAX || pid == 0 {
+        return 0;
+    }
+    let now = unsafe { bpf_ktime_get_ns() };
+    let mut accum = unsafe { MEM_GROWTH.get(&pid) }
+        .copied()
+        .unwrap_or(MemGrowthAccum {
+            bytes: 0,
+            since_ns: now,
+        });
+    accum.bytes = accum.bytes.saturating_add(bytes);
+    if accum.bytes < min {
+        let _ = unsafe { MEM_GROWTH.insert(&pid, &accum, 0) };
+        return 0;
+    }
+    let _ = unsafe { MEM_GROWTH.remove(&pid) };
+    let task = unsafe { bpf_get_current_task_btf() } as *const u8;
+    let rss = rss_bytes(task, &load_config()).unwrap_or(0);
+    let took_ms = (now.saturating_sub(accum.since_ns) / 1_000_000).min(u32::MAX as u64) as u32;
+    emit_activity_event(ctx, EventType::MemGrowth, now, accum.bytes, rss, took_ms, 0)
+}
+
+// Credentials are replaced with
+//   int commit_creds(struct cred *new)
+// while `current->real_cred` still points at the old ones.
+#[kprobe(function = "commit_creds")]
+pub fn trace_commit_creds(ctx: ProbeContext) -> u32 {
+    try_trace_commit_creds(&ctx)
+}
+
+fn try_trace_commit_creds(ctx: &ProbeContext) -> u32 {
+    let config = load_config();
+    if config.task_real_cred_offset == 0 {
+        return 0;
+    }
+    let pid = ctx.pid();
+    if pid == 0 || filtered(pid) {
+        return 0;
+    }
+    let new: u64 = match ctx.arg(0) {
+        Some(new) => new,
+        None => return 0,
+    };
+    let new = new as *const u8;
+    let task = unsafe { bpf_get_current_task_btf() } as *const u8;
+    let Some(old) = read_ptr(task, config.task_real_cred_offset) else {
+        return 0;
+    };
+    let ids = |cred: *const u8| -> Option<(u32, u32, u64)> {
+        Some((
+            read_field(cred, config.cred_euid_offset)?,
+            read_field(cred, config.cred_egid_offset)?,
+            read_field(cred, config.cred_cap_effective_offset)?,
+        ))
+    };
+    let (Some(before), Some(after)) = (ids(old), ids(new)) else {
+        return 0;
+    };
+    if before == after {
+        return 0;
+    }
+
+    let Some(mut pending) = PendingEvent::reserve() else {
+        return 1;
+    };
+    let event = pending.event();
+    init_event(
+        ctx,
+        EventType::Creds,
+        unsafe { bpf_ktime_get_ns() },
+        pid,
+        event,
+    );
+    (event.uid, event.gid, event.data2) = before;
+    (event.aux, event.aux2, event.data) = after;
+    pending.submit(ctx);
     0
 }
 
+// Opens and unlinks of watched paths:
+//   int security_file_open(struct file *file)
+//   int vfs_unlink(struct inode *dir, struct dentry *dentry, ...)
+// with a `struct user_namespace *` / `struct mnt_idmap *` first argument
+// since 5.12, hence `unlink_dentry_arg`.
+#[kprobe(function = "security_file_open")]
+pub fn trace_file_open(ctx: ProbeContext) -> u32 {
+    let config = load_config();
+    let file: u64 = match ctx.arg(0) {
+        Some(file) => file,
+        None => return 0,
+    };
+    let file = file as *const u8;
+    let Some(dentry) = read_ptr(file, config.file_dentry_offset) else {
+        return 0;
+    };
+    let mode: u32 = read_field(file, config.file_mode_offset).unwrap_or(0);
+    let op = if mode & FMODE_WRITE != 0 {
+        FileAccessOp::OpenWrite
+    } else {
+        FileAccessOp::Open
+    };
+    try_file_access(&ctx, dentry, op, &config)
+}
+
+#[kprobe(function = "vfs_unlink")]
+pub fn trace_vfs_unlink(ctx: ProbeContext) -> u32 {
+    let config = load_config();
+    let dentry: u64 = match ctx.arg(config.unlink_dentry_arg as usize) {
+        Some(dentry) => dentry,
+        None => return 0,
+    };
+    try_file_access(&ctx, dentry as *const u8, FileAccessOp::Unlink, &config)
+}
+
+/// `s_dev` and inode number of the inode behind `dentry`.
+#[inline(always)]
+fn dentry_id(dentry: *const u8, config: &TelemetryConfig) -> Option<WatchedPathKey> {
+    let inode = read_ptr(dentry, config.dentry_inode_offset)?;
+    let sb = read_ptr(inode, config.inode_sb_offset)?;
+    Some(WatchedPathKey {
+        dev: read_field(sb, config.sb_dev_offset)?,
+        _pad: 0,
+        ino: read_field(inode, config.inode_ino_offset)?,
+    })
+}
+
+/// Walk from `dentry` up its parents looking for a watched path, recording
+/// the names passed on the way, and emit a FileAccess event if one is found
+/// within FILE_PATH_DEPTH levels.
+fn try_file_access(
+    ctx: &ProbeContext,
+    dentry: *const u8,
+    op: FileAccessOp,
+    config: &TelemetryConfig,
+) -> u32 {
+    if config.dentry_parent_offset == 0 {
+        return 0;
+    }
+    let pid = ctx.pid();
+    if pid == 0 || filtered(pid) {
+        return 0;
+    }
+    let Some(file_id) = dentry_id(dentry, config) else {
+        return 0;
+    };
+    let Some(path) = (unsafe { FILE_PATH_SCRATCH.get_ptr_mut(0) }) else {
+        return 0;
+    };
+    let path = unsafe { &mut *path };
+    let watched = unsafe { &raw const WATCHED_PATHS };
+
+    let mut cur = dentry;
+    let mut watched_id = None;
+    path.depth = 0;
+    for level in 0..=FILE_PATH_DEPTH {
+        if let Some(id) = dentry_id(cur, config).and_then(|key| unsafe { (*watched).get(&key) }) {
+            watched_id = Some(*id);
+            break;
+        }
+        if level == FILE_PATH_DEPTH {
+            break;
+        }
+        let Some(name) = read_ptr(cur, config.dentry_name_offset) else {
+            return 0;
+        };
+        let dest = &mut path.names[level];
+        if unsafe { bpf_probe_read_kernel_str_bytes(name, dest) }.is_err() {
+            dest[0] = 0;
+        }
+        path.depth = level as u32 + 1;
+        let Some(parent) = read_ptr(cur, config.dentry_parent_offset) else {
+            return 0;
+        };
+        // The root of a filesystem is its own parent.
+        if parent == cur {
+            break;
+        }
+        cur = parent;
+    }
+    let Some(watched_id) = watched_id else {
+        return 0;
+    };
+
+    let Some(seq) = (unsafe { FILE_PATH_SEQ.get_ptr_mut(0) }) else {
+        return 0;
+    };
+    let key = unsafe {
+        *seq += 1;
+        (bpf_get_smp_processor_id() as u64) << 48 | (*seq & ((1 << 48) - 1))
+    };
+    let paths = unsafe { &raw const FILE_PATHS };
+    let _ = unsafe { (*paths).insert(&key, path, 0) };
+
+    emit_activity_event(
+        ctx,
+        EventType::FileAccess,
+        unsafe { bpf_ktime_get_ns() },
+        file_id.ino,
+        key,
+        op as u32,
+        watched_id,
+    )
+}
+
+/// A module was loaded and is about to run its init function; the current
+/// task is the loader (`modprobe`, `insmod`, `systemd-udevd`).
+#[tracepoint(category = "module", name = "module_load")]
+pub fn trace_module_load(ctx: TracePointContext) -> u32 {
+    let taints = tp_read_u32(&ctx, MODULE_LOAD_TAINTS_OFFSET).unwrap_or(0);
+    let loc = tp_read_u32(&ctx, MODULE_LOAD_NAME_LOC_OFFSET).unwrap_or(0);
+    let mut name = [0u8; KERNEL_LOAD_NAME_LEN];
+    let src = unsafe { (ctx.as_ptr() as *const u8).add((loc & 0xffff) as usize) };
+    if loc == 0 || unsafe { bpf_probe_read_kernel_str_bytes(src, &mut name) }.is_err() {
+        name[0] = 0;
+    }
+    let (data, data2) = kernel_load_name_fields(name);
+    emit_activity_event(
+        &ctx,
+        EventType::KernelLoad,
+        unsafe { bpf_ktime_get_ns() },
+        data,
+        data2,
+        KernelLoadKind::Module as u32,
+        taints,
+    )
+}
+
+/// `bpf(BPF_PROG_LOAD)` on entry, before the verifier runs: the attributes
+/// are still in the caller's memory.
+#[tracepoint(category = "syscalls", name = "sys_enter_bpf")]
+pub fn trace_bpf_prog_load(ctx: TracePointContext) -> u32 {
+    if tp_read_u64(&ctx, BPF_CMD_OFFSET) != Some(BPF_PROG_LOAD) {
+        return 0;
+    }
+    let Some(attr) = tp_read_u64(&ctx, BPF_UATTR_OFFSET) else {
+        return 0;
+    };
+    let attr = attr as *const u8;
+    let prog_type = unsafe { bpf_probe_read_user(attr as *const u32) }.unwrap_or(0);
+    // Loaders built before prog_name existed pass a shorter attr.
+    let size = tp_read_u64(&ctx, BPF_SIZE_OFFSET).unwrap_or(0);
+    let name = if size >= (BPF_ATTR_PROG_NAME_OFFSET + KERNEL_LOAD_NAME_LEN) as u64 {
+        unsafe {
+            bpf_probe_read_user(
+                attr.add(BPF_ATTR_PROG_NAME_OFFSET) as *const [u8; KERNEL_LOAD_NAME_LEN]
+            )
+        }
+        .unwrap_or([0; KERNEL_LOAD_NAME_LEN])
+    } else {
+        [0; KERNEL_LOAD_NAME_LEN]
+    };
+    let (data, data2) = kernel_load_name_fields(name);
+    emit_activity_event(
+        &ctx,
+        EventType::KernelLoad,
+        unsafe { bpf_ktime_get_ns() },
+        data,
+        data2,
+        KernelLoadKind::BpfProgram as u32,
+        prog_type,
+    )
+}
+
+/// A signal is generated for a task; the current task is the sender (or
+/// the task the kernel raised it in, e.g. on a fault).
+#[tracepoint(category = "signal", name = "signal_generate")]
+pub fn trace_signal_generate(ctx: TracePointContext) -> u32 {
+    let sig = unsafe { ctx.read_at::<i32>(SIGNAL_SIG_OFFSET) }.unwrap_or(0);
+    if !(1..64).contains(&sig) {
+        return 0;
+    }
+    let mask = unsafe { core::ptr::read_volatile(&SIGNAL_MASK) };
+    if mask & (1u64 << sig) == 0 {
+        return 0;
+    }
+    let target = unsafe { ctx.read_at::<i32>(SIGNAL_PID_OFFSET) }.unwrap_or(0);
+    let code = unsafe { ctx.read_at::<i32>(SIGNAL_CODE_OFFSET) }.unwrap_or(0);
+    let result = tp_read_u32(&ctx, SIGNAL_RESULT_OFFSET).unwrap_or(0);
+    emit_activity_event(
+        &ctx,
+        EventType::Signal,
+        unsafe { bpf_ktime_get_ns() },
+        target.max(0) as u64,
+        code as u32 as u64,
+        sig as u32,
+        result,
+    )
+}
+
 #[cfg(all(not(test), target_arch = "bpf"))]
 #[panic_handler]
 fn panic(_: &core::panic::PanicInfo) -> ! {
@@ -0,0 +1,412 @@
+//! `linnix-cli admin`: install, upgrade and uninstall cognitod as a systemd
+//! service, replacing `scripts/install.sh`.
+//!
+//! Every command first builds a plan of file and systemctl steps, so
+//! `--dry-run` prints exactly what a real run would do. Configuration in
+//! the config directory and state in `/var/lib/linnix` are never
+//! overwritten: when a shipped default differs from the file on disk it is
+//! written next to it as `<name>.new`.
+
+use clap::{Args, Subcommand};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Copies of the files in `configs/`, kept inside the crate so it packages
+/// on its own; `tests/admin.rs` checks they haven't drifted.
+const UNIT: &str = include_str!("../assets/linnix-cognitod.service");
+const DEFAULT_CONFIGS: [(&str, &str); 2] = [
+    ("linnix.toml", include_str!("../assets/linnix.toml")),
+    ("rules.yaml", include_str!("../assets/rules.yaml")),
+];
+const SERVICE: &str = "cognitod";
+/// Directories the unit's `ReadWritePaths` expects to exist.
+const STATE_DIR: &str = "/var/lib/linnix";
+const LOG_DIR: &str = "/var/log/linnix";
+/// Where the unit's `LINNIX_BPF_PATH` points.
+const BPF_OBJECT: &str = "/usr/local/share/linnix/linnix-ai-ebpf-ebpf";
+/// Pinned maps and programs left behind by a crashed or older cognitod.
+const BPF_PIN_DIR: &str = "/sys/fs/bpf/linnix";
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum AdminCommand {
+    /// Install cognitod, its default config and the systemd unit
+    Install {
+        #[clap(flatten)]
+        sources: Sources,
+        /// Enable and start the service afterwards
+        #[clap(long)]
+        enable: bool,
+        #[clap(flatten)]
+        layout: Layout,
+    },
+    /// Replace the binary and unit of an existing install, keeping config
+    /// and state; a running service is restarted
+    Upgrade {
+        #[clap(flatten)]
+        sources: Sources,
+        #[clap(flatten)]
+        layout: Layout,
+    },
+    /// Stop and remove the service, binary and BPF pins; config and state
+    /// are kept unless --purge is given
+    Uninstall {
+        /// Also remove the config directory, /var/lib/linnix and /var/log/linnix
+        #[clap(long)]
+        purge: bool,
+        #[clap(flatten)]
+        layout: Layout,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct Sources {
+    /// cognitod binary to install (default: next to linnix-cli, then
+    /// ./target/release/cognitod)
+    #[clap(long)]
+    binary: Option<PathBuf>,
+    /// Compiled eBPF object to install as the unit's LINNIX_BPF_PATH
+    #[clap(long)]
+    bpf_object: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct Layout {
+    /// Stage everything under this directory instead of / (for building
+    /// packages); systemctl is not run
+    #[clap(long)]
+    root: Option<PathBuf>,
+    /// Directory for the cognitod binary
+    #[clap(long, default_value = "/usr/local/bin")]
+    bin_dir: PathBuf,
+    /// Directory for linnix.toml and rules.yaml
+    #[clap(long, default_value = "/etc/linnix")]
+    config_dir: PathBuf,
+    /// Directory for cognitod.service
+    #[clap(long, default_value = "/etc/systemd/system")]
+    unit_dir: PathBuf,
+    /// Print the steps without changing anything
+    #[clap(long)]
+    dry_run: bool,
+}
+
+impl Layout {
+    /// Where `path` (as the installed system sees it) lives on disk now.
+    fn staged(&self, path: &Path) -> PathBuf {
+        match &self.root {
+            Some(root) => root.join(path.strip_prefix("/").unwrap_or(path)),
+            None => path.to_path_buf(),
+        }
+    }
+
+    fn binary(&self) -> PathBuf {
+        self.bin_dir.join(SERVICE)
+    }
+
+    fn unit(&self) -> PathBuf {
+        self.unit_dir.join(format!("{SERVICE}.service"))
+    }
+
+    /// The shipped unit with its binary and config paths pointed at this
+    /// layout.
+    fn render_unit(&self) -> String {
+        UNIT.replace("/usr/local/bin/cognitod", &self.binary().to_string_lossy())
+            .replace(
+                "/etc/linnix/",
+                &format!("{}/", self.config_dir.to_string_lossy()),
+            )
+    }
+
+    /// systemctl only makes sense against the running system.
+    fn systemd(&self) -> bool {
+        self.root.is_none()
+    }
+}
+
+#[derive(Debug)]
+enum Step {
+    CreateDir(PathBuf),
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+        mode: u32,
+    },
+    Write {
+        to: PathBuf,
+        contents: String,
+        mode: u32,
+    },
+    /// An existing config file that differs from the shipped default.
+    KeepConfig {
+        path: PathBuf,
+        contents: String,
+    },
+    Remove(PathBuf),
+    RemoveTree(PathBuf),
+    Systemctl(Vec<&'static str>),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::CreateDir(dir) => write!(f, "create {}", dir.display()),
+            Step::Copy { from, to, mode } => {
+                write!(
+                    f,
+                    "install {} -> {} ({mode:o})",
+                    from.display(),
+                    to.display()
+                )
+            }
+            Step::Write { to, .. } => write!(f, "write {}", to.display()),
+            Step::KeepConfig { path, .. } => write!(
+                f,
+                "keep {}; new default written to {}",
+                path.display(),
+                new_path(path).display()
+            ),
+            Step::Remove(path) => write!(f, "remove {}", path.display()),
+            Step::RemoveTree(path) => write!(f, "remove {} recursively", path.display()),
+            Step::Systemctl(args) => write!(f, "systemctl {}", args.join(" ")),
+        }
+    }
+}
+
+impl Step {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Step::CreateDir(dir) => {
+                fs::create_dir_all(dir).map_err(|e| format!("creating {}: {e}", dir.display()))?
+            }
+            Step::Copy { from, to, mode } => {
+                let bytes =
+                    fs::read(from).map_err(|e| format!("reading {}: {e}", from.display()))?;
+                write_atomic(to, &bytes, *mode)?;
+            }
+            Step::Write { to, contents, mode } => write_atomic(to, contents.as_bytes(), *mode)?,
+            Step::KeepConfig { path, contents } => {
+                write_atomic(&new_path(path), contents.as_bytes(), 0o644)?
+            }
+            Step::Remove(path) => match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(format!("removing {}: {e}", path.display()).into())
+                }
+                _ => {}
+            },
+            Step::RemoveTree(path) => match fs::remove_dir_all(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(format!("removing {}: {e}", path.display()).into())
+                }
+                _ => {}
+            },
+            Step::Systemctl(args) => {
+                let status = Command::new("systemctl")
+                    .args(args)
+                    .status()
+                    .map_err(|e| format!("running systemctl: {e}"))?;
+                if !status.success() {
+                    return Err(format!("systemctl {} failed: {status}", args.join(" ")).into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn new_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".new");
+    path.with_file_name(name)
+}
+
+/// Write through a temporary file and rename, so a running binary or a
+/// reader of the file never sees a partial write.
+fn write_atomic(to: &Path, bytes: &[u8], mode: u32) -> Result<(), Box<dyn Error>> {
+    let name = to.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = to.with_file_name(format!(".{name}.tmp"));
+    fs::write(&tmp, bytes).map_err(|e| format!("writing {}: {e}", tmp.display()))?;
+    fs::set_permissions(&tmp, fs::Permissions::from_mode(mode))?;
+    fs::rename(&tmp, to).map_err(|e| format!("replacing {}: {e}", to.display()))?;
+    Ok(())
+}
+
+fn service_active() -> bool {
+    Command::new("systemctl")
+        .args(["is-active", "--quiet", SERVICE])
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// The cognitod binary to install: `--binary`, else one next to this
+/// executable, else a local release build.
+fn source_binary(sources: &Sources) -> Result<PathBuf, Box<dyn Error>> {
+    if let Some(path) = &sources.binary {
+        return Ok(path.clone());
+    }
+    let sibling = std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(SERVICE));
+    sibling
+        .into_iter()
+        .chain([PathBuf::from("target/release/cognitod")])
+        .find(|p| p.is_file())
+        .ok_or_else(|| "cognitod binary not found; pass --binary <path>".into())
+}
+
+/// Directories, binary, eBPF object, config and unit: shared by install and
+/// upgrade.
+fn install_files(layout: &Layout, sources: &Sources) -> Result<Vec<Step>, Box<dyn Error>> {
+    let binary = source_binary(sources)?;
+    if !binary.is_file() {
+        return Err(format!("{} is not a file", binary.display()).into());
+    }
+    let mut steps = Vec::new();
+    for dir in [
+        layout.bin_dir.as_path(),
+        layout.config_dir.as_path(),
+        layout.unit_dir.as_path(),
+        Path::new(STATE_DIR),
+        Path::new(LOG_DIR),
+    ] {
+        let dir = layout.staged(dir);
+        if !dir.is_dir() {
+            steps.push(Step::CreateDir(dir));
+        }
+    }
+    steps.push(Step::Copy {
+        from: binary,
+        to: layout.staged(&layout.binary()),
+        mode: 0o755,
+    });
+    if let Some(object) = &sources.bpf_object {
+        let to = layout.staged(Path::new(BPF_OBJECT));
+        if let Some(dir) = to.parent().filter(|d| !d.is_dir()) {
+            steps.push(Step::CreateDir(dir.to_path_buf()));
+        }
+        steps.push(Step::Copy {
+            from: object.clone(),
+            to,
+            mode: 0o644,
+        });
+    }
+    for (name, contents) in DEFAULT_CONFIGS {
+        let path = layout.staged(&layout.config_dir.join(name));
+        match fs::read_to_string(&path) {
+            Ok(existing) if existing == contents => {}
+            Ok(_) => steps.push(Step::KeepConfig {
+                path,
+                contents: contents.to_string(),
+            }),
+            Err(_) => steps.push(Step::Write {
+                to: path,
+                contents: contents.to_string(),
+                mode: 0o644,
+            }),
+        }
+    }
+    steps.push(Step::Write {
+        to: layout.staged(&layout.unit()),
+        contents: layout.render_unit(),
+        mode: 0o644,
+    });
+    Ok(steps)
+}
+
+fn plan(command: &AdminCommand) -> Result<(&Layout, Vec<Step>), Box<dyn Error>> {
+    match command {
+        AdminCommand::Install {
+            sources,
+            enable,
+            layout,
+        } => {
+            let mut steps = install_files(layout, sources)?;
+            if layout.systemd() {
+                steps.push(Step::Systemctl(vec!["daemon-reload"]));
+                if *enable {
+                    steps.push(Step::Systemctl(vec!["enable", "--now", SERVICE]));
+                }
+            }
+            Ok((layout, steps))
+        }
+        AdminCommand::Upgrade { sources, layout } => {
+            let installed = layout.staged(&layout.binary());
+            if !installed.exists() {
+                return Err(format!(
+                    "{} not found; use `admin install` first",
+                    installed.display()
+                )
+                .into());
+            }
+            let restart = layout.systemd() && service_active();
+            let mut steps = Vec::new();
+            if restart {
+                steps.push(Step::Systemctl(vec!["stop", SERVICE]));
+            }
+            steps.extend(install_files(layout, sources)?);
+            if layout.systemd() {
+                steps.push(Step::Systemctl(vec!["daemon-reload"]));
+            }
+            if restart {
+                steps.push(Step::Systemctl(vec!["start", SERVICE]));
+            }
+            Ok((layout, steps))
+        }
+        AdminCommand::Uninstall { purge, layout } => {
+            let mut steps = Vec::new();
+            let unit = layout.staged(&layout.unit());
+            if layout.systemd() && unit.exists() {
+                steps.push(Step::Systemctl(vec!["disable", "--now", SERVICE]));
+            }
+            steps.push(Step::Remove(unit));
+            if layout.systemd() {
+                steps.push(Step::Systemctl(vec!["daemon-reload"]));
+            }
+            steps.push(Step::Remove(layout.staged(&layout.binary())));
+            steps.push(Step::Remove(layout.staged(Path::new(BPF_OBJECT))));
+            let pins = layout.staged(Path::new(BPF_PIN_DIR));
+            if pins.exists() {
+                steps.push(Step::RemoveTree(pins));
+            }
+            if *purge {
+                for dir in [
+                    layout.config_dir.as_path(),
+                    Path::new(STATE_DIR),
+                    Path::new(LOG_DIR),
+                ] {
+                    steps.push(Step::RemoveTree(layout.staged(dir)));
+                }
+            }
+            Ok((layout, steps))
+        }
+    }
+}
+
+pub fn run_admin(command: AdminCommand) -> Result<(), Box<dyn Error>> {
+    let (layout, steps) = plan(&command)?;
+    if let AdminCommand::Install { .. } = command {
+        if layout.systemd() && !Path::new("/sys/kernel/btf/vmlinux").exists() {
+            eprintln!(
+                "warning: /sys/kernel/btf/vmlinux not found; cognitod needs a kernel with CONFIG_DEBUG_INFO_BTF for most probes"
+            );
+        }
+    }
+    for step in &steps {
+        if layout.dry_run {
+            println!("would {step}");
+            continue;
+        }
+        println!("{step}");
+        step.run()?;
+    }
+    if let AdminCommand::Uninstall { purge: false, .. } = command {
+        println!(
+            "kept {} and {STATE_DIR}; pass --purge to remove them",
+            layout.staged(&layout.config_dir).display()
+        );
+    }
+    Ok(())
+}
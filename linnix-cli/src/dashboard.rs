@@ -0,0 +1,468 @@
+use crate::sse::{self, SseEvent};
+use colored::*;
+use futures_util::StreamExt;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Number of live events kept for the events pane.
+const EVENT_LINES: usize = 12;
+/// Number of alert / insight / process rows shown per pane.
+const PANE_ROWS: usize = 8;
+const GAUGE_WIDTH: usize = 30;
+
+#[derive(Deserialize, Debug, Default)]
+struct DashStatus {
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    uptime_s: u64,
+    #[serde(default)]
+    events_per_sec: u64,
+    #[serde(default)]
+    dropped_events_total: u64,
+    #[serde(default)]
+    active_rules: usize,
+    #[serde(default)]
+    offline: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct DashSystem {
+    #[serde(default)]
+    cpu_percent: f32,
+    #[serde(default)]
+    mem_percent: f32,
+    #[serde(default)]
+    load_avg: [f32; 3],
+    #[serde(default)]
+    psi_cpu_some_avg10: f32,
+    #[serde(default)]
+    psi_memory_some_avg10: f32,
+    #[serde(default)]
+    psi_memory_full_avg10: f32,
+    #[serde(default)]
+    psi_io_some_avg10: f32,
+    #[serde(default)]
+    psi_io_full_avg10: f32,
+    /// Processes whose GPU memory keeps growing; empty without a GPU.
+    #[serde(default)]
+    gpu_memory: Vec<DashGpuMemory>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DashGpuMemory {
+    pid: u32,
+    #[serde(default)]
+    comm: String,
+    #[serde(default)]
+    pod: Option<String>,
+    used_mib: u64,
+    growth_mib: u64,
+    growing_secs: u64,
+    rate_mib_per_min: f32,
+}
+
+#[derive(Deserialize, Debug)]
+struct DashAlert {
+    timestamp: u64,
+    severity: String,
+    rule: String,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct DashInsightRecord {
+    timestamp: u64,
+    insight: DashInsight,
+}
+
+#[derive(Deserialize, Debug)]
+struct DashInsight {
+    id: String,
+    reason_code: String,
+    summary: String,
+    confidence: f32,
+}
+
+#[derive(Deserialize, Debug)]
+struct DashProcess {
+    pid: u32,
+    comm: String,
+    cpu_pct: Option<f32>,
+    mem_pct: Option<f32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DashEvent {
+    pid: u32,
+    ppid: u32,
+    comm: String,
+    #[serde(default)]
+    event_type_name: String,
+}
+
+/// Everything needed to draw one frame.
+#[derive(Default)]
+struct Frame {
+    status: Option<DashStatus>,
+    system: Option<DashSystem>,
+    alerts: Option<Vec<DashAlert>>,
+    insights: Option<Vec<DashInsightRecord>>,
+    processes: Option<Vec<DashProcess>>,
+    events: Vec<String>,
+}
+
+async fn fetch<T: for<'de> Deserialize<'de>>(client: &Client, url: &str, path: &str) -> Option<T> {
+    let resp = client.get(format!("{url}{path}")).send().await.ok()?;
+    let resp = resp.error_for_status().ok()?;
+    resp.json().await.ok()
+}
+
+async fn collect_frame(client: &Client, url: &str, events: &Mutex<VecDeque<String>>) -> Frame {
+    let (status, system, alerts, insights, processes) = tokio::join!(
+        fetch::<DashStatus>(client, url, "/status"),
+        fetch::<DashSystem>(client, url, "/system"),
+        fetch::<Vec<DashAlert>>(client, url, "/timeline"),
+        fetch::<Vec<DashInsightRecord>>(client, url, "/insights/recent"),
+        fetch::<Vec<DashProcess>>(client, url, "/processes"),
+    );
+    let events = events.lock().unwrap().iter().cloned().collect();
+    Frame {
+        status,
+        system,
+        alerts,
+        insights,
+        processes,
+        events,
+    }
+}
+
+fn gauge(label: &str, pct: f32, color: bool) -> String {
+    let clamped = pct.clamp(0.0, 100.0);
+    let filled = ((clamped / 100.0) * GAUGE_WIDTH as f32).round() as usize;
+    let bar = format!("{}{}", "#".repeat(filled), ".".repeat(GAUGE_WIDTH - filled));
+    let bar = if !color {
+        bar
+    } else if clamped >= 60.0 {
+        bar.red().to_string()
+    } else if clamped >= 20.0 {
+        bar.yellow().to_string()
+    } else {
+        bar.green().to_string()
+    };
+    format!("  {label:<12} [{bar}] {pct:>5.1}%")
+}
+
+fn heading(title: &str, color: bool) -> String {
+    if color {
+        format!("── {} ", title).bold().to_string()
+    } else {
+        format!("── {} ", title)
+    }
+}
+
+fn severity_label(sev: &str, color: bool) -> String {
+    let label = format!("{:<6}", sev.to_uppercase());
+    if !color {
+        return label;
+    }
+    match sev.to_lowercase().as_str() {
+        "high" => label.red().bold().to_string(),
+        "medium" => label.yellow().to_string(),
+        "low" => label.blue().to_string(),
+        _ => label.normal().to_string(),
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let mut out: String = s.chars().take(max.saturating_sub(1)).collect();
+        out.push('…');
+        out
+    }
+}
+
+fn render(frame: &Frame, url: &str, color: bool) -> String {
+    let mut out = String::new();
+
+    let title = format!("linnix dashboard — {url}");
+    let _ = writeln!(
+        out,
+        "{}",
+        if color {
+            title.cyan().bold().to_string()
+        } else {
+            title
+        }
+    );
+    match &frame.status {
+        Some(s) => {
+            let _ = writeln!(
+                out,
+                "  cognitod {} | up {}s | {} ev/s | dropped {} | rules {}{}",
+                s.version,
+                s.uptime_s,
+                s.events_per_sec,
+                s.dropped_events_total,
+                s.active_rules,
+                if s.offline { " | OFFLINE" } else { "" }
+            );
+        }
+        None => {
+            let _ = writeln!(out, "  status unavailable");
+        }
+    }
+    out.push('\n');
+
+    let _ = writeln!(out, "{}", heading("System / PSI", color));
+    match &frame.system {
+        Some(sys) => {
+            let _ = writeln!(out, "{}", gauge("cpu", sys.cpu_percent, color));
+            let _ = writeln!(out, "{}", gauge("memory", sys.mem_percent, color));
+            let _ = writeln!(out, "{}", gauge("psi cpu", sys.psi_cpu_some_avg10, color));
+            let _ = writeln!(
+                out,
+                "{}",
+                gauge("psi mem", sys.psi_memory_some_avg10, color)
+            );
+            let _ = writeln!(
+                out,
+                "{}",
+                gauge("psi mem full", sys.psi_memory_full_avg10, color)
+            );
+            let _ = writeln!(out, "{}", gauge("psi io", sys.psi_io_some_avg10, color));
+            let _ = writeln!(
+                out,
+                "{}",
+                gauge("psi io full", sys.psi_io_full_avg10, color)
+            );
+            let _ = writeln!(
+                out,
+                "  load avg     {:.2} {:.2} {:.2}",
+                sys.load_avg[0], sys.load_avg[1], sys.load_avg[2]
+            );
+        }
+        None => {
+            let _ = writeln!(out, "  system snapshot unavailable");
+        }
+    }
+    out.push('\n');
+
+    let gpu_memory = frame.system.as_ref().map_or(&[][..], |sys| &sys.gpu_memory);
+    if !gpu_memory.is_empty() {
+        let _ = writeln!(out, "{}", heading("GPU memory", color));
+        let _ = writeln!(out, "  {:<8} {:<9} {:<26} CMD", "PID", "USED MiB", "GROWTH");
+        for g in gpu_memory.iter().take(PANE_ROWS) {
+            let growth = format!(
+                "+{} in {}s ({:.1}/min)",
+                g.growth_mib, g.growing_secs, g.rate_mib_per_min
+            );
+            let _ = writeln!(
+                out,
+                "  {:<8} {:<9} {:<26} {}{}",
+                g.pid,
+                g.used_mib,
+                growth,
+                g.comm,
+                g.pod
+                    .as_deref()
+                    .map(|pod| format!(" ({pod})"))
+                    .unwrap_or_default()
+            );
+        }
+        out.push('\n');
+    }
+
+    let _ = writeln!(out, "{}", heading("Active alerts", color));
+    match &frame.alerts {
+        Some(alerts) if !alerts.is_empty() => {
+            for a in alerts.iter().take(PANE_ROWS) {
+                let _ = writeln!(
+                    out,
+                    "  {} {} {} - {}",
+                    a.timestamp,
+                    severity_label(&a.severity, color),
+                    a.rule,
+                    truncate(&a.message, 80)
+                );
+            }
+        }
+        Some(_) => {
+            let _ = writeln!(out, "  no alerts");
+        }
+        None => {
+            let _ = writeln!(out, "  alerts unavailable");
+        }
+    }
+    out.push('\n');
+
+    let _ = writeln!(out, "{}", heading("Recent insights", color));
+    match &frame.insights {
+        Some(records) if !records.is_empty() => {
+            for r in records.iter().take(PANE_ROWS) {
+                let _ = writeln!(
+                    out,
+                    "  {} {} ({:.0}%) {} - {}",
+                    r.timestamp,
+                    r.insight.reason_code,
+                    r.insight.confidence * 100.0,
+                    r.insight.id,
+                    truncate(&r.insight.summary, 70)
+                );
+            }
+        }
+        Some(_) => {
+            let _ = writeln!(out, "  no insights");
+        }
+        None => {
+            let _ = writeln!(out, "  insights unavailable");
+        }
+    }
+    out.push('\n');
+
+    let _ = writeln!(out, "{}", heading("Top CPU", color));
+    match &frame.processes {
+        Some(procs) if !procs.is_empty() => {
+            let mut sorted: Vec<&DashProcess> = procs.iter().collect();
+            sorted.sort_by(|a, b| {
+                b.cpu_pct
+                    .unwrap_or(0.0)
+                    .partial_cmp(&a.cpu_pct.unwrap_or(0.0))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let _ = writeln!(out, "  {:<8} {:<6} {:<6} CMD", "PID", "CPU%", "MEM%");
+            for p in sorted.into_iter().take(PANE_ROWS) {
+                let _ = writeln!(
+                    out,
+                    "  {:<8} {:<6} {:<6} {}",
+                    p.pid,
+                    format_pct(p.cpu_pct),
+                    format_pct(p.mem_pct),
+                    p.comm
+                );
+            }
+        }
+        Some(_) => {
+            let _ = writeln!(out, "  no processes");
+        }
+        None => {
+            let _ = writeln!(out, "  processes unavailable");
+        }
+    }
+    out.push('\n');
+
+    let _ = writeln!(out, "{}", heading("Live events", color));
+    if frame.events.is_empty() {
+        let _ = writeln!(out, "  waiting for events…");
+    } else {
+        for line in &frame.events {
+            let _ = writeln!(out, "  {line}");
+        }
+    }
+    out.push('\n');
+    let _ = writeln!(out, "Press Ctrl-C to exit.");
+    out
+}
+
+fn format_pct(opt: Option<f32>) -> String {
+    match opt {
+        Some(value) => format!("{:.1}", value),
+        None => "-".to_string(),
+    }
+}
+
+/// Background task feeding the live events pane from `/stream`.
+fn spawn_event_feed(client: Client, url: String, events: Arc<Mutex<VecDeque<String>>>) {
+    tokio::spawn(async move {
+        let mut stream = match sse::connect_sse(&client, &format!("{url}/stream")).await {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        while let Some(Ok(event)) = stream.next().await {
+            let SseEvent::Message(msg) = event else {
+                continue;
+            };
+            let json = msg.strip_prefix("data: ").unwrap_or(&msg);
+            let Ok(ev) = serde_json::from_str::<DashEvent>(json) else {
+                continue;
+            };
+            let line = format!(
+                "{:<9} pid={} ppid={} {}",
+                ev.event_type_name, ev.pid, ev.ppid, ev.comm
+            );
+            let mut buf = events.lock().unwrap();
+            if buf.len() >= EVENT_LINES {
+                buf.pop_front();
+            }
+            buf.push_back(line);
+        }
+    });
+}
+
+/// Full-screen terminal dashboard for on-box triage over SSH.
+///
+/// Redraws every `interval_secs` until Ctrl-C. With `once`, a single frame is
+/// printed without clearing the screen (useful for scripts and tests).
+pub async fn run_dashboard(
+    client: &Client,
+    url: &str,
+    interval_secs: u64,
+    once: bool,
+    color: bool,
+) -> Result<(), Box<dyn Error>> {
+    let events = Arc::new(Mutex::new(VecDeque::with_capacity(EVENT_LINES)));
+
+    if once {
+        let frame = collect_frame(client, url, &events).await;
+        print!("{}", render(&frame, url, color));
+        return Ok(());
+    }
+
+    spawn_event_feed(client.clone(), url.to_string(), Arc::clone(&events));
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+    // Hide cursor while drawing; restored on exit.
+    print!("\x1b[?25l");
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let frame = collect_frame(client, url, &events).await;
+                print!("\x1b[2J\x1b[H{}", render(&frame, url, color));
+                use std::io::Write;
+                let _ = std::io::stdout().flush();
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    println!("\x1b[?25h");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gauge_is_clamped() {
+        let g = gauge("cpu", 150.0, false);
+        assert!(g.contains(&"#".repeat(GAUGE_WIDTH)));
+        let g = gauge("cpu", -5.0, false);
+        assert!(g.contains(&".".repeat(GAUGE_WIDTH)));
+    }
+
+    #[test]
+    fn render_handles_missing_sections() {
+        let frame = Frame::default();
+        let out = render(&frame, "http://x", false);
+        assert!(out.contains("status unavailable"));
+        assert!(out.contains("alerts unavailable"));
+        assert!(out.contains("waiting for events"));
+    }
+}
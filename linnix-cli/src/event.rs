@@ -30,6 +30,19 @@ pub struct ProcessEvent {
     pub aux: u32,
     #[serde(default)]
     pub aux2: u32,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub exit_signal: Option<i32>,
+    /// FileAccess events: the file's path.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// KernelLoad events: the module or program name, and the program's
+    /// type.
+    #[serde(default)]
+    pub load_name: Option<String>,
+    #[serde(default)]
+    pub prog_type: Option<String>,
     pub tags: Vec<String>,
 }
 
@@ -0,0 +1,162 @@
+use reqwest::Client;
+use serde::Deserialize;
+use std::error::Error;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum EventsFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+/// Filters passed through to `GET /events/history`.
+#[derive(Debug, Clone)]
+pub struct EventsQuery {
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub event_type: Option<String>,
+    pub comm: Option<String>,
+    pub pid: Option<u32>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryResponse {
+    oldest: Option<String>,
+    #[serde(default)]
+    partial: bool,
+    #[serde(default)]
+    truncated: bool,
+    events: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryEvent {
+    time: String,
+    pid: u32,
+    ppid: u32,
+    uid: u32,
+    event_type_name: String,
+    comm: String,
+    #[serde(default)]
+    argv: Option<Vec<String>>,
+}
+
+pub async fn run_events(
+    client: &Client,
+    url: &str,
+    query: &EventsQuery,
+    format: EventsFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut params: Vec<(&str, String)> = Vec::new();
+    for (key, value) in [
+        ("since", &query.since),
+        ("until", &query.until),
+        ("type", &query.event_type),
+        ("comm", &query.comm),
+    ] {
+        if let Some(value) = value {
+            params.push((key, value.clone()));
+        }
+    }
+    if let Some(pid) = query.pid {
+        params.push(("pid", pid.to_string()));
+    }
+    if let Some(limit) = query.limit {
+        params.push(("limit", limit.to_string()));
+    }
+
+    let resp = client
+        .get(format!("{}/events/history", url))
+        .query(&params)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!("failed to query event history: {status} {body}").into());
+    }
+    let history: HistoryResponse = resp.json().await?;
+
+    if history.partial {
+        eprintln!(
+            "note: event history only reaches back to {}; earlier events are not available",
+            history.oldest.as_deref().unwrap_or("-")
+        );
+    }
+    if history.truncated {
+        eprintln!(
+            "note: showing the first {} matching events; narrow the window or raise --limit",
+            history.events.len()
+        );
+    }
+
+    match format {
+        EventsFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&history.events)?);
+        }
+        EventsFormat::Csv => {
+            println!("time,pid,ppid,uid,type,comm,argv");
+            for event in parse_events(history.events)? {
+                let fields = [
+                    event.time,
+                    event.pid.to_string(),
+                    event.ppid.to_string(),
+                    event.uid.to_string(),
+                    event.event_type_name,
+                    event.comm,
+                    event.argv.unwrap_or_default().join(" "),
+                ];
+                let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+                println!("{}", row.join(","));
+            }
+        }
+        EventsFormat::Table => {
+            println!(
+                "{:<32} {:<8} {:<8} {:<6} {:<9} {:<16} ARGS",
+                "TIME", "PID", "PPID", "UID", "TYPE", "COMM"
+            );
+            for event in parse_events(history.events)? {
+                println!(
+                    "{:<32} {:<8} {:<8} {:<6} {:<9} {:<16} {}",
+                    event.time,
+                    event.pid,
+                    event.ppid,
+                    event.uid,
+                    event.event_type_name,
+                    event.comm,
+                    event.argv.unwrap_or_default().join(" ")
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_events(events: Vec<serde_json::Value>) -> Result<Vec<HistoryEvent>, Box<dyn Error>> {
+    events
+        .into_iter()
+        .map(|e| serde_json::from_value(e).map_err(Into::into))
+        .collect()
+}
+
+/// Quote a CSV field when it contains a separator, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("cron"), "cron");
+        assert_eq!(csv_field("sh -c a,b"), "\"sh -c a,b\"");
+        assert_eq!(csv_field("echo \"hi\""), "\"echo \"\"hi\"\"\"");
+    }
+}
@@ -25,6 +25,47 @@ struct StatusResp {
     rss_mb: u64,
 }
 
+#[derive(Deserialize)]
+struct HostResp {
+    kernel: String,
+    distro: Option<String>,
+    cpus: usize,
+    memory_bytes: u64,
+    cloud: Option<CloudResp>,
+    #[serde(default)]
+    gpus: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CloudResp {
+    provider: String,
+    instance_type: Option<String>,
+}
+
+/// "aws m5.xlarge, 4 CPUs, 15.6 GiB, Ubuntu 22.04.4 LTS, kernel 6.5.0".
+fn describe_host(host: &HostResp) -> String {
+    let mut parts = Vec::new();
+    if let Some(cloud) = &host.cloud {
+        parts.push(match &cloud.instance_type {
+            Some(t) => format!("{} {t}", cloud.provider),
+            None => cloud.provider.clone(),
+        });
+    }
+    parts.push(format!("{} CPUs", host.cpus));
+    parts.push(format!(
+        "{:.1} GiB",
+        host.memory_bytes as f64 / (1u64 << 30) as f64
+    ));
+    if let Some(distro) = &host.distro {
+        parts.push(distro.clone());
+    }
+    parts.push(format!("kernel {}", host.kernel));
+    if !host.gpus.is_empty() {
+        parts.push(format!("GPUs: {}", host.gpus.join(", ")));
+    }
+    parts.join(", ")
+}
+
 pub async fn export_incident(
     client: &Client,
     base: &str,
@@ -47,6 +88,12 @@ pub async fn export_incident(
         .json()
         .await?;
 
+    // Older daemons, or inventory disabled: export without it.
+    let host: Option<HostResp> = match client.get(format!("{}/inventory", base)).send().await {
+        Ok(resp) if resp.status().is_success() => resp.json().await.ok(),
+        _ => None,
+    };
+
     let mut out = String::new();
     match format {
         Format::Md => {
@@ -59,6 +106,12 @@ pub async fn export_incident(
             writeln!(out, "Timeframe: since {since}")?;
         }
     }
+    if let Some(host) = &host {
+        match format {
+            Format::Md => writeln!(out, "**Host:** {}", describe_host(host))?,
+            Format::Txt => writeln!(out, "Host: {}", describe_host(host))?,
+        };
+    }
 
     if let Some(ev) = events.first() {
         let chain = format!("{} -> {}", ev.ppid, ev.pid);
@@ -138,4 +191,19 @@ mod tests {
         assert_eq!(redacted1, redacted2);
         assert_eq!(hash1, hash2);
     }
+
+    #[test]
+    fn host_line_leads_with_instance_type() {
+        let host: HostResp = serde_json::from_str(
+            r#"{"kernel":"6.1.0","distro":"Debian GNU/Linux 12 (bookworm)","arch":"x86_64",
+                "cpu_model":null,"cpus":4,"memory_bytes":17179869184,
+                "cloud":{"provider":"aws","instance_type":"m5.xlarge","region":"us-east-1"},
+                "gpus":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            describe_host(&host),
+            "aws m5.xlarge, 4 CPUs, 16.0 GiB, Debian GNU/Linux 12 (bookworm), kernel 6.1.0"
+        );
+    }
 }
@@ -0,0 +1,137 @@
+use clap::Subcommand;
+use reqwest::Client;
+use std::error::Error;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum IncidentsCommand {
+    /// List recent incidents
+    List {
+        /// Maximum number of incidents to show
+        #[clap(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Show what happened around an incident, in order (for postmortems)
+    Timeline {
+        /// Incident id
+        id: i64,
+        /// Seconds before the incident to include (default 600)
+        #[clap(long)]
+        before: Option<i64>,
+        /// Seconds after the incident to include (default 600)
+        #[clap(long)]
+        after: Option<i64>,
+        /// Print the raw JSON response
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+pub async fn run_incidents(
+    client: &Client,
+    url: &str,
+    command: IncidentsCommand,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        IncidentsCommand::List { limit } => {
+            let resp = client
+                .get(format!("{}/incidents?limit={}", url, limit))
+                .send()
+                .await?;
+            let status = resp.status();
+            if !status.is_success() {
+                let msg = resp.text().await.unwrap_or_default();
+                return Err(format!("failed to fetch incidents: {} {}", status, msg).into());
+            }
+            let incidents: Vec<serde_json::Value> = resp.json().await?;
+            print_incidents(&incidents);
+        }
+        IncidentsCommand::Timeline {
+            id,
+            before,
+            after,
+            json,
+        } => {
+            let mut query = Vec::new();
+            if let Some(before) = before {
+                query.push(("before", before));
+            }
+            if let Some(after) = after {
+                query.push(("after", after));
+            }
+            let resp = client
+                .get(format!("{}/incidents/{}/timeline", url, id))
+                .query(&query)
+                .send()
+                .await?;
+            let status = resp.status();
+            if !status.is_success() {
+                let msg = resp.text().await.unwrap_or_default();
+                return Err(format!(
+                    "failed to fetch timeline for incident {}: {} {}",
+                    id, status, msg
+                )
+                .into());
+            }
+            let timeline: serde_json::Value = resp.json().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&timeline)?);
+            } else {
+                print_timeline(&timeline);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_incidents(incidents: &[serde_json::Value]) {
+    if incidents.is_empty() {
+        println!("no incidents");
+        return;
+    }
+    println!(
+        "{:>6} {:>11} {:<22} {:<12} {:>6}  target",
+        "id", "timestamp", "type", "action", "cpu%"
+    );
+    for incident in incidents {
+        let target = match (
+            incident["target_name"].as_str(),
+            incident["target_pid"].as_i64(),
+        ) {
+            (Some(name), Some(pid)) => format!("{} ({})", name, pid),
+            (None, Some(pid)) => pid.to_string(),
+            _ => "-".to_string(),
+        };
+        println!(
+            "{:>6} {:>11} {:<22} {:<12} {:>6.1}  {}",
+            incident["id"],
+            incident["timestamp"],
+            incident["event_type"].as_str().unwrap_or("?"),
+            incident["action"].as_str().unwrap_or("?"),
+            incident["cpu_percent"].as_f64().unwrap_or(0.0),
+            target
+        );
+    }
+}
+
+fn print_timeline(timeline: &serde_json::Value) {
+    println!(
+        "Incident {}: {} .. {}",
+        timeline["incident_id"],
+        timeline["from"].as_str().unwrap_or("?"),
+        timeline["to"].as_str().unwrap_or("?")
+    );
+    if timeline["complete"] == false {
+        eprintln!(
+            "note: event history no longer covers the whole window; older alerts, insights and events may be missing"
+        );
+    }
+    println!("{:<32} {:<17} summary", "time", "kind");
+    for entry in timeline["entries"].as_array().into_iter().flatten() {
+        println!(
+            "{:<32} {:<17} {}",
+            entry["time"].as_str().unwrap_or("?"),
+            entry["kind"].as_str().unwrap_or("?"),
+            entry["summary"].as_str().unwrap_or("")
+        );
+    }
+}
@@ -5,13 +5,18 @@ use serde::Deserialize;
 use std::collections::HashSet;
 use std::error::Error;
 
+mod admin;
 mod alert;
 mod blame;
+mod dashboard;
 mod doctor;
 mod event;
+mod events;
 mod export;
+mod incidents;
 mod pretty;
 mod processes;
+mod rules;
 mod sse;
 use alert::Alert;
 use event::ProcessEvent;
@@ -55,6 +60,30 @@ enum Command {
         #[clap(long, value_enum, default_value = "txt")]
         format: Format,
     },
+    /// Query recent event history (e.g. what execed between 02:00 and 02:05)
+    Events {
+        /// Start of the window: age (15m, 2h), local time (02:00), RFC 3339 or epoch seconds
+        #[clap(long)]
+        since: Option<String>,
+        /// End of the window, in the same forms as --since (default: now)
+        #[clap(long)]
+        until: Option<String>,
+        /// Event types, comma-separated (exec, fork, exit, net, fileio, ...)
+        #[clap(long = "type")]
+        event_type: Option<String>,
+        /// Only processes whose name contains this
+        #[clap(long)]
+        comm: Option<String>,
+        /// Only this PID
+        #[clap(long)]
+        pid: Option<u32>,
+        /// Maximum number of events to return
+        #[clap(long)]
+        limit: Option<usize>,
+        /// Output format
+        #[clap(long, value_enum, default_value = "table")]
+        format: events::EventsFormat,
+    },
     /// Blame a node for performance issues (requires kubectl)
     Blame {
         /// Node name to analyze
@@ -73,6 +102,30 @@ enum Command {
     Doctor,
     /// List running processes with priority
     Processes,
+    /// Full-screen live dashboard (events, alerts, insights, PSI gauges)
+    Dashboard {
+        /// Refresh interval in seconds
+        #[clap(long, default_value_t = 2)]
+        interval: u64,
+        /// Print a single frame and exit
+        #[clap(long)]
+        once: bool,
+    },
+    /// Inspect detection rules
+    Rules {
+        #[clap(subcommand)]
+        command: rules::RulesCommand,
+    },
+    /// Inspect circuit breaker incidents
+    Incidents {
+        #[clap(subcommand)]
+        command: incidents::IncidentsCommand,
+    },
+    /// Install, upgrade or uninstall cognitod as a systemd service
+    Admin {
+        #[clap(subcommand)]
+        command: admin::AdminCommand,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, serde::Serialize)]
@@ -110,6 +163,28 @@ async fn main() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
+    if let Some(Command::Events {
+        since,
+        until,
+        event_type,
+        comm,
+        pid,
+        limit,
+        format,
+    }) = args.command.clone()
+    {
+        let query = events::EventsQuery {
+            since,
+            until,
+            event_type,
+            comm,
+            pid,
+            limit,
+        };
+        events::run_events(&client, &args.url, &query, format).await?;
+        return Ok(());
+    }
+
     if let Some(Command::Blame { node_name }) = args.command {
         blame::run_blame(&node_name).await?;
         return Ok(());
@@ -141,6 +216,26 @@ async fn main() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
+    if let Some(Command::Dashboard { interval, once }) = args.command {
+        dashboard::run_dashboard(&client, &args.url, interval, once, color).await?;
+        return Ok(());
+    }
+
+    if let Some(Command::Rules { command }) = args.command {
+        rules::run_rules(&client, &args.url, command).await?;
+        return Ok(());
+    }
+
+    if let Some(Command::Incidents { command }) = args.command {
+        incidents::run_incidents(&client, &args.url, command).await?;
+        return Ok(());
+    }
+
+    if let Some(Command::Admin { command }) = args.command {
+        admin::run_admin(command)?;
+        return Ok(());
+    }
+
     if args.stats {
         let status: Status = client
             .get(format!("{}/status", args.url))
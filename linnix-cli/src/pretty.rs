@@ -1,6 +1,10 @@
 use crate::event::ProcessEvent;
 use colored::*;
-use linnix_ai_ebpf_common::{BlockOp, EventType, FileOp, NetOp, PageFaultFlags, PageFaultOrigin};
+use linnix_ai_ebpf_common::{
+    conn_direction, connection_addr_octets, BlockOp, EventType, FileOp, NetOp, PageFaultFlags,
+    PageFaultOrigin,
+};
+use std::net::{Ipv6Addr, SocketAddr};
 
 const DEVICE_MINOR_BITS: u32 = 20;
 const DEVICE_MINOR_MASK: u32 = (1 << DEVICE_MINOR_BITS) - 1;
@@ -26,6 +30,18 @@ fn decode_net_op(op: u32) -> Option<NetOp> {
     }
 }
 
+fn signal_label(sig: u32) -> String {
+    match sig {
+        1 => "SIGHUP".to_string(),
+        2 => "SIGINT".to_string(),
+        3 => "SIGQUIT".to_string(),
+        6 => "SIGABRT".to_string(),
+        9 => "SIGKILL".to_string(),
+        15 => "SIGTERM".to_string(),
+        other => format!("signal {other}"),
+    }
+}
+
 fn decode_file_op(op: u32) -> Option<FileOp> {
     match op {
         x if x == FileOp::Read as u32 => Some(FileOp::Read),
@@ -120,8 +136,13 @@ impl PrettyEvent for ProcessEvent {
                 } else {
                     "[EXIT]".to_string()
                 };
+                let status = match (self.exit_code, self.exit_signal) {
+                    (_, Some(sig)) => format!(" killed by signal {sig}"),
+                    (Some(code), _) => format!(" code {code}"),
+                    _ => String::new(),
+                };
                 format!(
-                    "{etype}    PID {styled_pid:<8} CMD {styled_comm}  at {} ns{tags}",
+                    "{etype}    PID {styled_pid:<8} CMD {styled_comm}  at {} ns{status}{tags}",
                     self.exit_time().unwrap_or(0)
                 )
             }
@@ -158,11 +179,165 @@ impl PrettyEvent for ProcessEvent {
                     Some(FileOp::Read) => "read",
                     None => "touched",
                 };
+                let file = if self.data2 != 0 {
+                    let (major, minor) = decode_block_dev(self.aux2);
+                    format!(" inode {} on {major}:{minor}", self.data2)
+                } else {
+                    String::new()
+                };
                 format!(
-                    "{etype} PID {styled_pid:<8} {bytes} bytes {op} CMD {styled_comm}{tags}",
+                    "{etype} PID {styled_pid:<8} {bytes} bytes {op}{file} CMD {styled_comm}{tags}",
                     bytes = self.data
                 )
             }
+            x if x == EventType::OomKill as u32 => {
+                let etype = if color {
+                    "[OOM]".bright_red().bold().to_string()
+                } else {
+                    "[OOM]".to_string()
+                };
+                let scope = if self.aux == 1 { "cgroup" } else { "system" };
+                format!(
+                    "{etype}     PID {styled_pid:<8} killed holding {rss} MiB RSS ({scope} OOM, triggered by PID {trigger}) CMD {styled_comm}{tags}",
+                    rss = self.data / (1024 * 1024),
+                    trigger = self.data2
+                )
+            }
+            x if x == EventType::SchedLatency as u32 => {
+                let etype = if color {
+                    "[SCHED]".yellow().bold().to_string()
+                } else {
+                    "[SCHED]".to_string()
+                };
+                format!(
+                    "{etype}   PID {styled_pid:<8} waited {delay:.1} ms on the runqueue (TID {tid}, CPU {cpu}) CMD {styled_comm}{tags}",
+                    delay = self.data as f64 / 1_000_000.0,
+                    tid = self.data2,
+                    cpu = self.aux
+                )
+            }
+            x if x == EventType::OffCpu as u32 => {
+                let etype = if color {
+                    "[OFFCPU]".blue().bold().to_string()
+                } else {
+                    "[OFFCPU]".to_string()
+                };
+                format!(
+                    "{etype}  PID {styled_pid:<8} {blocked:.1} ms blocked on I/O, {sleep:.1} ms sleeping in {interval} ms CMD {styled_comm}{tags}",
+                    blocked = self.data as f64 / 1_000_000.0,
+                    sleep = self.data2 as f64 / 1_000_000.0,
+                    interval = self.aux
+                )
+            }
+            x if x == EventType::Connection as u32 => {
+                let etype = if color {
+                    "[CONN]".bright_cyan().bold().to_string()
+                } else {
+                    "[CONN]".to_string()
+                };
+                let ip = Ipv6Addr::from(connection_addr_octets(self.data, self.data2));
+                let remote = match ip.to_ipv4_mapped() {
+                    Some(v4) => SocketAddr::new(v4.into(), self.aux as u16),
+                    None => SocketAddr::new(ip.into(), self.aux as u16),
+                };
+                let flow = if self.aux2 & 0xffff == conn_direction::INBOUND {
+                    format!("accepted from {remote} on port {}", self.aux2 >> 16)
+                } else {
+                    format!("connecting to {remote}")
+                };
+                format!("{etype}    PID {styled_pid:<8} {flow} CMD {styled_comm}{tags}")
+            }
+            x if x == EventType::DirectReclaim as u32 => {
+                let etype = if color {
+                    "[RECLAIM]".bright_magenta().bold().to_string()
+                } else {
+                    "[RECLAIM]".to_string()
+                };
+                format!(
+                    "{etype} PID {styled_pid:<8} stalled {stall:.1} ms in direct reclaim, {pages} pages reclaimed (order {order}, TID {tid}) CMD {styled_comm}{tags}",
+                    stall = self.data as f64 / 1_000_000.0,
+                    pages = self.data2,
+                    order = self.aux,
+                    tid = self.aux2
+                )
+            }
+            x if x == EventType::Signal as u32 => {
+                let etype = if color {
+                    "[SIGNAL]".bright_red().bold().to_string()
+                } else {
+                    "[SIGNAL]".to_string()
+                };
+                format!(
+                    "{etype}  PID {styled_pid:<8} sent {sig} to PID {target} CMD {styled_comm}{tags}",
+                    sig = signal_label(self.aux),
+                    target = self.data
+                )
+            }
+            x if x == EventType::Creds as u32 => {
+                let etype = if color {
+                    "[CREDS]".bright_red().bold().to_string()
+                } else {
+                    "[CREDS]".to_string()
+                };
+                format!(
+                    "{etype}   PID {styled_pid:<8} euid {old_uid} -> {new_uid}, egid {old_gid} -> {new_gid}, caps {old_caps:#x} -> {new_caps:#x} CMD {styled_comm}{tags}",
+                    old_uid = self.uid,
+                    new_uid = self.aux,
+                    old_gid = self.gid,
+                    new_gid = self.aux2,
+                    old_caps = self.data2,
+                    new_caps = self.data
+                )
+            }
+            x if x == EventType::MemGrowth as u32 => {
+                let etype = if color {
+                    "[MEMGROW]".yellow().bold().to_string()
+                } else {
+                    "[MEMGROW]".to_string()
+                };
+                format!(
+                    "{etype} PID {styled_pid:<8} mapped {grown:.1} MiB more in {ms} ms, RSS {rss:.1} MiB CMD {styled_comm}{tags}",
+                    grown = self.data as f64 / (1024.0 * 1024.0),
+                    ms = self.aux,
+                    rss = self.data2 as f64 / (1024.0 * 1024.0)
+                )
+            }
+            x if x == EventType::FileAccess as u32 => {
+                let etype = if color {
+                    "[FILE]".bright_red().bold().to_string()
+                } else {
+                    "[FILE]".to_string()
+                };
+                let op = match self.aux {
+                    0 => "opened",
+                    1 => "opened for writing",
+                    2 => "unlinked",
+                    _ => "accessed",
+                };
+                let path = match &self.path {
+                    Some(path) => path.clone(),
+                    None => format!("inode {}", self.data),
+                };
+                format!("{etype}    PID {styled_pid:<8} {op} {path} CMD {styled_comm}{tags}")
+            }
+            x if x == EventType::KernelLoad as u32 => {
+                let etype = if color {
+                    "[KLOAD]".bright_red().bold().to_string()
+                } else {
+                    "[KLOAD]".to_string()
+                };
+                let name = self.load_name.as_deref().unwrap_or("");
+                let what = if self.aux == 0 {
+                    format!("loaded module {name}")
+                } else {
+                    let prog_type = self.prog_type.as_deref().unwrap_or("unknown");
+                    format!("loading {prog_type} BPF program {name}")
+                };
+                format!(
+                    "{etype}   PID {styled_pid:<8} {what} CMD {styled_comm}{tags}",
+                    what = what.trim_end()
+                )
+            }
             x if x == EventType::Syscall as u32 => {
                 let etype = if color {
                     "[SYSCALL]".white().bold().to_string()
@@ -170,8 +345,10 @@ impl PrettyEvent for ProcessEvent {
                     "[SYSCALL]".to_string()
                 };
                 format!(
-                    "{etype} PID {styled_pid:<8} call #{call} CMD {styled_comm}{tags}",
-                    call = self.data
+                    "{etype} PID {styled_pid:<8} {calls} calls of syscall {nr} in {ms} ms CMD {styled_comm}{tags}",
+                    calls = self.data,
+                    nr = self.aux,
+                    ms = self.data2 / 1_000_000
                 )
             }
             x if x == EventType::BlockIo as u32 => {
@@ -0,0 +1,154 @@
+use clap::Subcommand;
+use reqwest::Client;
+use std::error::Error;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum RulesCommand {
+    /// Print the JSON Schema for rule files (for editor validation/completion)
+    Schema,
+    /// Adjust a loaded rule's threshold, cooldown and/or mode at runtime
+    Set {
+        /// Rule name
+        name: String,
+        /// New value for the detector's threshold (threshold, threshold_pct or rate_per_min)
+        #[clap(long)]
+        threshold: Option<f64>,
+        /// New cooldown in seconds
+        #[clap(long)]
+        cooldown: Option<u64>,
+        /// `shadow` to count matches without alerting, `enforce` to alert
+        #[clap(long, value_parser = ["enforce", "shadow"])]
+        mode: Option<String>,
+        /// Also write the change back to the rules file on the node (TOML only)
+        #[clap(long)]
+        persist: bool,
+    },
+    /// Show each rule's noise score and suggested threshold/cooldown changes
+    Suggestions {
+        /// Days of alert history and feedback to score
+        #[clap(long, default_value_t = 7)]
+        days: u64,
+    },
+}
+
+pub async fn run_rules(
+    client: &Client,
+    url: &str,
+    command: RulesCommand,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        RulesCommand::Schema => {
+            let resp = client.get(format!("{}/rules/schema", url)).send().await?;
+            if !resp.status().is_success() {
+                return Err(format!("failed to fetch rules schema: {}", resp.status()).into());
+            }
+            let schema: serde_json::Value = resp.json().await?;
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+        RulesCommand::Set {
+            name,
+            threshold,
+            cooldown,
+            mode,
+            persist,
+        } => {
+            if threshold.is_none() && cooldown.is_none() && mode.is_none() {
+                return Err("nothing to change; pass --threshold, --cooldown and/or --mode".into());
+            }
+            let mut body = serde_json::json!({ "persist": persist });
+            if let Some(threshold) = threshold {
+                body["threshold"] = threshold.into();
+            }
+            if let Some(cooldown) = cooldown {
+                body["cooldown"] = cooldown.into();
+            }
+            if let Some(mode) = mode {
+                body["mode"] = mode.into();
+            }
+            let resp = client
+                .patch(format!("{}/rules/{}", url, name))
+                .json(&body)
+                .send()
+                .await?;
+            let status = resp.status();
+            if !status.is_success() {
+                let msg = resp.text().await.unwrap_or_default();
+                return Err(format!("failed to update rule '{}': {} {}", name, status, msg).into());
+            }
+            let rule: serde_json::Value = resp.json().await?;
+            println!(
+                "{}: {}={} cooldown={}s{}{}",
+                rule["name"].as_str().unwrap_or(&name),
+                rule["threshold_field"].as_str().unwrap_or("threshold"),
+                rule["threshold"],
+                rule["cooldown"],
+                if rule["mode"] == "shadow" {
+                    " mode=shadow"
+                } else {
+                    ""
+                },
+                if persist {
+                    " (saved to rules file)"
+                } else {
+                    ""
+                }
+            );
+        }
+        RulesCommand::Suggestions { days } => {
+            let resp = client
+                .get(format!("{}/rules/suggestions?days={}", url, days))
+                .send()
+                .await?;
+            let status = resp.status();
+            if !status.is_success() {
+                let msg = resp.text().await.unwrap_or_default();
+                return Err(format!("failed to fetch rule suggestions: {} {}", status, msg).into());
+            }
+            let rules: Vec<serde_json::Value> = resp.json().await?;
+            print_suggestions(&rules);
+        }
+    }
+    Ok(())
+}
+
+fn print_suggestions(rules: &[serde_json::Value]) {
+    println!(
+        "{:<28} {:>6} {:>8} {:>7} {:>6}  suggestion",
+        "rule", "noise", "fires/d", "useful", "noisy"
+    );
+    for rule in rules {
+        let name = rule["rule"].as_str().unwrap_or("?");
+        let suggestion = &rule["suggestion"];
+        let mut change = String::new();
+        if let Some(threshold) = suggestion["threshold"].as_f64() {
+            change.push_str(&format!(
+                "{} {} -> {} ",
+                rule["threshold_field"].as_str().unwrap_or("threshold"),
+                rule["threshold"],
+                threshold
+            ));
+        }
+        if let Some(cooldown) = suggestion["cooldown"].as_u64() {
+            change.push_str(&format!("cooldown {}s -> {}s ", rule["cooldown"], cooldown));
+        }
+        if let Some(reason) = suggestion["reason"].as_str() {
+            change.push_str(&format!("({reason})"));
+        }
+        println!(
+            "{:<28} {:>6.2} {:>8.1} {:>7} {:>6}  {}",
+            name,
+            rule["noise_score"].as_f64().unwrap_or(0.0),
+            rule["fires_per_day"].as_f64().unwrap_or(0.0),
+            rule["useful"].as_u64().unwrap_or(0),
+            rule["noise"].as_u64().unwrap_or(0),
+            if change.is_empty() {
+                "-"
+            } else {
+                change.trim_end()
+            }
+        );
+    }
+    if rules.iter().any(|r| r["suggestion"].is_object()) {
+        println!("\nApply with: linnix-cli rules set <rule> --threshold <n> --cooldown <secs>");
+    }
+}
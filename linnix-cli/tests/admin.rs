@@ -0,0 +1,117 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+
+fn admin(root: &Path, args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .arg("admin")
+        .args(args)
+        .arg("--root")
+        .arg(root)
+        .assert()
+}
+
+#[test]
+fn install_upgrade_uninstall_preserves_config() {
+    let root = tempfile::tempdir().unwrap();
+    let build = tempfile::tempdir().unwrap();
+    let binary = build.path().join("cognitod");
+    fs::write(&binary, "#!/bin/sh\necho v1\n").unwrap();
+    let binary = binary.to_str().unwrap();
+
+    admin(root.path(), &["install", "--binary", binary]).success();
+    let installed = root.path().join("usr/local/bin/cognitod");
+    let config = root.path().join("etc/linnix/linnix.toml");
+    let unit = root.path().join("etc/systemd/system/cognitod.service");
+    assert_eq!(
+        fs::read_to_string(&installed).unwrap(),
+        "#!/bin/sh\necho v1\n"
+    );
+    assert!(config.exists());
+    assert!(root.path().join("etc/linnix/rules.yaml").exists());
+    assert!(root.path().join("var/lib/linnix").is_dir());
+    assert!(fs::read_to_string(&unit)
+        .unwrap()
+        .contains("ExecStart=/usr/local/bin/cognitod --config /etc/linnix/linnix.toml"));
+
+    // A local edit survives the upgrade; the new default goes next to it.
+    fs::write(&config, "[api]\nlisten_addr = \"0.0.0.0:3000\"\n").unwrap();
+    fs::write(root.path().join("var/lib/linnix/state.db"), "state").unwrap();
+    fs::write(build.path().join("cognitod"), "#!/bin/sh\necho v2\n").unwrap();
+    admin(root.path(), &["upgrade", "--binary", binary])
+        .success()
+        .stdout(predicates::str::contains("keep"));
+    assert_eq!(
+        fs::read_to_string(&installed).unwrap(),
+        "#!/bin/sh\necho v2\n"
+    );
+    assert_eq!(
+        fs::read_to_string(&config).unwrap(),
+        "[api]\nlisten_addr = \"0.0.0.0:3000\"\n"
+    );
+    assert!(root.path().join("etc/linnix/linnix.toml.new").exists());
+    assert!(!root.path().join("etc/linnix/rules.yaml.new").exists());
+
+    admin(root.path(), &["uninstall"]).success();
+    assert!(!installed.exists());
+    assert!(!unit.exists());
+    assert!(config.exists());
+    assert!(root.path().join("var/lib/linnix/state.db").exists());
+
+    admin(root.path(), &["uninstall", "--purge"]).success();
+    assert!(!root.path().join("etc/linnix").exists());
+    assert!(!root.path().join("var/lib/linnix").exists());
+}
+
+#[test]
+fn dry_run_changes_nothing() {
+    let root = tempfile::tempdir().unwrap();
+    let binary = root.path().join("cognitod-build");
+    fs::write(&binary, "bin").unwrap();
+
+    admin(
+        root.path(),
+        &[
+            "install",
+            "--binary",
+            binary.to_str().unwrap(),
+            "--bin-dir",
+            "/opt/linnix/bin",
+            "--dry-run",
+        ],
+    )
+    .success()
+    .stdout(predicates::str::contains("would install"))
+    .stdout(predicates::str::contains("/opt/linnix/bin/cognitod"));
+    assert!(!root.path().join("opt").exists());
+    assert!(!root.path().join("etc").exists());
+}
+
+#[test]
+fn upgrade_requires_an_install() {
+    let root = tempfile::tempdir().unwrap();
+    admin(root.path(), &["upgrade", "--binary", "/bin/true"])
+        .failure()
+        .stderr(predicates::str::contains("admin install"));
+}
+
+#[test]
+fn bundled_defaults_match_configs() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let configs = crate_dir.join("../configs");
+    // A packaged crate has no workspace next to it.
+    if !configs.exists() {
+        return;
+    }
+    for (asset, original) in [
+        ("linnix-cognitod.service", "systemd/linnix-cognitod.service"),
+        ("linnix.toml", "linnix.toml"),
+        ("rules.yaml", "rules.yaml"),
+    ] {
+        assert_eq!(
+            fs::read_to_string(crate_dir.join("assets").join(asset)).unwrap(),
+            fs::read_to_string(configs.join(original)).unwrap(),
+            "linnix-cli/assets/{asset} differs from configs/{original}"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use assert_cmd::Command;
+use httpmock::prelude::*;
+use predicates::prelude::*;
+
+#[tokio::test]
+async fn dashboard_once_renders_all_panes() {
+    let server = MockServer::start_async().await;
+    let _status = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/status");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"{"version":"0.2.0","uptime_s":42,"events_per_sec":7,"dropped_events_total":0,"active_rules":3,"offline":false}"#);
+        })
+        .await;
+    let _system = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/system");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"{"cpu_percent":12.5,"mem_percent":40.0,"load_avg":[0.5,0.4,0.3],"psi_cpu_some_avg10":3.0,"psi_memory_some_avg10":0.0,"psi_memory_full_avg10":0.0,"psi_io_some_avg10":1.0,"psi_io_full_avg10":0.0}"#);
+        })
+        .await;
+    let _timeline = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/timeline");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"[{"id":"alert-1","timestamp":1700000000,"severity":"high","rule":"fork_storm","message":"forks/s exceeded","host":"h"}]"#);
+        })
+        .await;
+    let _insights = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/insights/recent");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body("[]");
+        })
+        .await;
+    let _processes = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/processes");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"[{"pid":1234,"ppid":1,"comm":"busy","cpu_pct":55.0,"mem_pct":1.0}]"#);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "--no-color",
+            "dashboard",
+            "--once",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("fork_storm"))
+        .stdout(predicates::str::contains("psi cpu"))
+        .stdout(predicates::str::contains("busy"))
+        .stdout(predicates::str::contains("no insights"))
+        .stdout(predicates::str::contains("GPU memory").not());
+}
+
+#[tokio::test]
+async fn dashboard_shows_growing_gpu_memory() {
+    let server = MockServer::start_async().await;
+    let _system = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/system");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"{"cpu_percent":12.5,"mem_percent":40.0,"load_avg":[0.5,0.4,0.3],"gpu_memory":[{"pid":4242,"comm":"trainer","pod":"ml/train-0","used_mib":8120,"growth_mib":2048,"growing_secs":600,"rate_mib_per_min":204.8}]}"#);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "--no-color",
+            "dashboard",
+            "--once",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("GPU memory"))
+        .stdout(predicates::str::contains("+2048 in 600s (204.8/min)"))
+        .stdout(predicates::str::contains("trainer (ml/train-0)"));
+}
@@ -0,0 +1,100 @@
+use assert_cmd::Command;
+use httpmock::prelude::*;
+
+const HISTORY: &str = r#"{
+    "oldest": "2025-06-01T01:58:00+00:00",
+    "partial": false,
+    "truncated": false,
+    "events": [
+        {"time": "2025-06-01T02:01:07+00:00", "pid": 4242, "ppid": 1, "uid": 0,
+         "event_type_name": "exec", "comm": "backup.sh",
+         "argv": ["/bin/sh", "-c", "tar czf /tmp/a,b.tgz /etc"]}
+    ]
+}"#;
+
+#[tokio::test]
+async fn events_passes_filters_and_prints_table() {
+    let server = MockServer::start_async().await;
+    let _m = server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/events/history")
+                .query_param("since", "02:00")
+                .query_param("until", "02:05")
+                .query_param("type", "exec")
+                .query_param("pid", "4242");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(HISTORY);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "events",
+            "--since",
+            "02:00",
+            "--until",
+            "02:05",
+            "--type",
+            "exec",
+            "--pid",
+            "4242",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("TIME"))
+        .stdout(predicates::str::contains("backup.sh"))
+        .stdout(predicates::str::contains("tar czf"));
+}
+
+#[tokio::test]
+async fn events_prints_csv_and_warns_about_partial_history() {
+    let server = MockServer::start_async().await;
+    let body = HISTORY.replace(r#""partial": false"#, r#""partial": true"#);
+    let _m = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/events/history");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(body);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "events",
+            "--since",
+            "1h",
+            "--format",
+            "csv",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("time,pid,ppid,uid,type,comm,argv"))
+        .stdout(predicates::str::contains(
+            "2025-06-01T02:01:07+00:00,4242,1,0,exec,backup.sh,\"/bin/sh -c tar czf /tmp/a,b.tgz /etc\"",
+        ))
+        .stderr(predicates::str::contains("only reaches back to"));
+}
+
+#[tokio::test]
+async fn events_reports_bad_time_from_server() {
+    let server = MockServer::start_async().await;
+    let _m = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/events/history");
+            then.status(400).body("invalid time \"soon\"");
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args(["--url", &server.base_url(), "events", "--since", "soon"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("invalid time"));
+}
@@ -0,0 +1,54 @@
+use assert_cmd::Command;
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn incidents_timeline_prints_entries() {
+    let server = MockServer::start_async().await;
+    let _m = server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/incidents/7/timeline")
+                .query_param("before", "60");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(
+                    r#"{"incident_id":7,"from":"2025-01-01T00:00:00+00:00","to":"2025-01-01T00:20:00+00:00","complete":false,"entries":[
+                        {"timestamp_ms":1000,"time":"2025-01-01T00:01:00+00:00","kind":"threshold_crossed","summary":"circuit_breaker_cpu conditions first met"},
+                        {"timestamp_ms":2000,"time":"2025-01-01T00:01:30+00:00","kind":"action","summary":"auto_kill on stress (pid 4242)"}
+                    ]}"#,
+                );
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "incidents",
+            "timeline",
+            "7",
+            "--before",
+            "60",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("threshold_crossed"))
+        .stdout(predicates::str::contains("auto_kill on stress (pid 4242)"))
+        .stderr(predicates::str::contains("no longer covers"));
+}
+
+#[tokio::test]
+async fn incidents_timeline_fails_for_unknown_incident() {
+    let server = MockServer::start_async().await;
+    let _m = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/incidents/99/timeline");
+            then.status(404).body("Incident not found");
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args(["--url", &server.base_url(), "incidents", "timeline", "99"])
+        .assert()
+        .failure();
+}
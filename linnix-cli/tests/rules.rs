@@ -0,0 +1,129 @@
+use assert_cmd::Command;
+use httpmock::prelude::*;
+
+#[tokio::test]
+async fn rules_schema_prints_schema() {
+    let server = MockServer::start_async().await;
+    let _m = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/rules/schema");
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"{"title":"Linnix rules file","anyOf":[]}"#);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args(["--url", &server.base_url(), "rules", "schema"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "\"title\": \"Linnix rules file\"",
+        ));
+}
+
+#[tokio::test]
+async fn rules_schema_fails_on_http_error() {
+    let server = MockServer::start_async().await;
+    let _m = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/rules/schema");
+            then.status(404);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args(["--url", &server.base_url(), "rules", "schema"])
+        .assert()
+        .failure();
+}
+
+#[tokio::test]
+async fn rules_set_patches_rule() {
+    let server = MockServer::start_async().await;
+    let m = server
+        .mock_async(|when, then| {
+            when.method("PATCH")
+                .path("/rules/fork_burst")
+                .json_body(serde_json::json!({"threshold": 300.0, "persist": true}));
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"{"name":"fork_burst","detector":"fork_burst","severity":"medium","threshold_field":"threshold","threshold":300,"cooldown":60}"#);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "rules",
+            "set",
+            "fork_burst",
+            "--threshold",
+            "300",
+            "--persist",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "fork_burst: threshold=300 cooldown=60s (saved to rules file)",
+        ));
+    m.assert_async().await;
+}
+
+#[tokio::test]
+async fn rules_set_switches_mode() {
+    let server = MockServer::start_async().await;
+    let m = server
+        .mock_async(|when, then| {
+            when.method("PATCH")
+                .path("/rules/fork_trial")
+                .json_body(serde_json::json!({"mode": "shadow", "persist": false}));
+            then.status(200)
+                .header("content-type", "application/json")
+                .body(r#"{"name":"fork_trial","detector":"fork_burst","severity":"medium","threshold_field":"threshold","threshold":120,"cooldown":60,"mode":"shadow"}"#);
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "rules",
+            "set",
+            "fork_trial",
+            "--mode",
+            "shadow",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "fork_trial: threshold=120 cooldown=60s mode=shadow",
+        ));
+    m.assert_async().await;
+}
+
+#[tokio::test]
+async fn rules_set_reports_unknown_rule() {
+    let server = MockServer::start_async().await;
+    let _m = server
+        .mock_async(|when, then| {
+            when.method("PATCH").path("/rules/nope");
+            then.status(404).body("no such rule");
+        })
+        .await;
+
+    Command::new(assert_cmd::cargo::cargo_bin!("linnix-cli"))
+        .args([
+            "--url",
+            &server.base_url(),
+            "rules",
+            "set",
+            "nope",
+            "--cooldown",
+            "600",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("no such rule"));
+}